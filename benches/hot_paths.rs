@@ -0,0 +1,195 @@
+//! Benchmarks for the paths every checkpoint and blame goes through, on a large synthetic
+//! repository. Alongside criterion's timings each benchmark prints how many heap allocations
+//! one run makes, since most of these paths are dominated by copying strings around.
+//!
+//! Run with `cargo bench --bench hot_paths`.
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use git_ai::commands::blame::GitAiBlameOptions;
+use git_ai::git::diff_parser::{DiffParser, added_lines_by_file};
+use git_ai::git::status::porcelain_v2_entries;
+use git_ai::git::test_utils::TmpRepo;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const FILES: usize = 300;
+const LINES_PER_FILE: usize = 200;
+const BLAME_LINES: usize = 2000;
+const BLAME_COMMITS: usize = 20;
+
+/// Print the allocations one run of `f` makes (subprocesses excluded)
+fn report_allocations<T>(name: &str, f: impl FnOnce() -> T) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(f());
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    eprintln!("{}: {} allocations per run", name, allocations);
+}
+
+fn file_contents(file: usize, revision: usize) -> String {
+    (0..LINES_PER_FILE)
+        .map(|line| format!("fn item_{}_{}() -> u32 {{ {} }}\n", file, line, revision))
+        .collect()
+}
+
+/// A repository with many committed files, a third of them modified in the working tree,
+/// plus one long file whose lines were rewritten by alternating human and AI commits
+fn synthetic_repo() -> TmpRepo {
+    let repo = TmpRepo::new().unwrap();
+    for file in 0..FILES {
+        repo.write_file(
+            &format!("src/mod_{}.rs", file),
+            &file_contents(file, 0),
+            true,
+        )
+        .unwrap();
+    }
+    let mut blame_lines: Vec<String> = (0..BLAME_LINES).map(|i| format!("line {}", i)).collect();
+    repo.write_file("history.txt", &(blame_lines.join("\n") + "\n"), true)
+        .unwrap();
+    repo.trigger_checkpoint_with_author("bench").unwrap();
+    repo.commit_with_message("initial").unwrap();
+
+    for commit in 0..BLAME_COMMITS {
+        for line in (commit..BLAME_LINES).step_by(BLAME_COMMITS / 2 + 1) {
+            blame_lines[line] = format!("line {} revision {}", line, commit);
+        }
+        repo.write_file("history.txt", &(blame_lines.join("\n") + "\n"), true)
+            .unwrap();
+        if commit % 2 == 0 {
+            repo.trigger_checkpoint_with_ai("bench_agent", None, None)
+                .unwrap();
+        } else {
+            repo.trigger_checkpoint_with_author("bench").unwrap();
+        }
+        repo.commit_with_message(&format!("revision {}", commit))
+            .unwrap();
+    }
+
+    for file in (0..FILES).step_by(3) {
+        repo.write_file(
+            &format!("src/mod_{}.rs", file),
+            &file_contents(file, 1),
+            false,
+        )
+        .unwrap();
+    }
+    repo
+}
+
+fn synthetic_status_output() -> Vec<u8> {
+    let mut raw = Vec::new();
+    for i in 0..10_000 {
+        raw.extend_from_slice(
+            format!(
+                "1 .M N... 100644 100644 100644 {:040x} {:040x} src/module_{}/file_{}.rs\0",
+                i,
+                i,
+                i / 100,
+                i
+            )
+            .as_bytes(),
+        );
+    }
+    for i in 0..1_000 {
+        raw.extend_from_slice(format!("? generated/output_{}.rs\0", i).as_bytes());
+    }
+    raw
+}
+
+fn bench_status_parsing(c: &mut Criterion) {
+    let raw = synthetic_status_output();
+    let parse = || {
+        porcelain_v2_entries(&raw)
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .is_ok_and(|entry| entry.path.ends_with(".rs"))
+            })
+            .count()
+    };
+    report_allocations("status/parse_porcelain_v2", parse);
+    c.bench_function("status/parse_porcelain_v2", |b| b.iter(parse));
+}
+
+fn synthetic_diff_output() -> String {
+    let mut diff = String::new();
+    for file in 0..200 {
+        let path = format!("src/module_{}/file_{}.rs", file / 20, file);
+        diff.push_str(&format!(
+            "diff --git a/{0} b/{0}\nindex 1111111..2222222 100644\n--- a/{0}\n+++ b/{0}\n",
+            path
+        ));
+        for hunk in 0..10 {
+            let start = hunk * 20 + 1;
+            diff.push_str(&format!("@@ -{},5 +{},10 @@\n", start, start));
+            for line in 0..5 {
+                diff.push_str(&format!("-    let old_{} = {};\n", line, hunk));
+            }
+            for line in 0..10 {
+                diff.push_str(&format!("+    let new_{} = {};\n", line, hunk));
+            }
+        }
+    }
+    diff
+}
+
+fn bench_diff_parsing(c: &mut Criterion) {
+    let diff = synthetic_diff_output();
+    let parse = || added_lines_by_file(DiffParser::new(diff.lines()));
+    report_allocations("diff/added_lines_by_file", parse);
+    c.bench_function("diff/added_lines_by_file", |b| b.iter(parse));
+}
+
+fn bench_checkpoint_and_blame(c: &mut Criterion) {
+    let repo = synthetic_repo();
+
+    let checkpoint = || repo.trigger_checkpoint_with_author("bench").unwrap();
+    report_allocations("checkpoint/human", checkpoint);
+    let mut group = c.benchmark_group("checkpoint");
+    group.sample_size(10);
+    group.bench_function("human", |b| b.iter(checkpoint));
+    group.finish();
+
+    let options = GitAiBlameOptions {
+        no_output: true,
+        ..Default::default()
+    };
+    let blame = || repo.gitai_repo().blame("history.txt", &options).unwrap();
+    report_allocations("blame/file", blame);
+    let mut group = c.benchmark_group("blame");
+    group.sample_size(10);
+    group.bench_function("file", |b| b.iter(blame));
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_status_parsing,
+    bench_diff_parsing,
+    bench_checkpoint_and_blame
+);
+criterion_main!(benches);
@@ -0,0 +1,266 @@
+//! Authorship tracking for tools that embed git-ai instead of running the `git-ai` binary,
+//! such as IDE plugins and CI bots.
+//!
+//! ```no_run
+//! use git_ai::api::{CheckpointRequest, GitAi};
+//! use git_ai::authorship::working_log::AgentId;
+//!
+//! let git_ai = GitAi::open("/path/to/repo")?;
+//! git_ai.checkpoint(CheckpointRequest::human())?;
+//! // ... the agent edits files ...
+//! let agent = AgentId {
+//!     tool: "my-plugin".to_string(),
+//!     id: "session-1".to_string(),
+//!     model: "unknown".to_string(),
+//! };
+//! git_ai.checkpoint(CheckpointRequest::agent(agent).with_files(vec!["src/lib.rs".to_string()]))?;
+//!
+//! for line in git_ai.blame("src/lib.rs")? {
+//!     println!("{} {}", line.line, line.author);
+//! }
+//! let stats = git_ai.stats("HEAD")?;
+//! # Ok::<(), git_ai::error::GitAiError>(())
+//! ```
+//!
+//! Nothing here prints or exits the process; failures come back as [`GitAiError`]. Commits
+//! still get their authorship notes from git-ai's hooks, so the repository's git has to go
+//! through git-ai for the checkpoints to end up in notes.
+
+use crate::authorship::authorship_log::PromptRecord;
+use crate::authorship::authorship_log_serialization::AuthorshipLog;
+use crate::authorship::stats::{CommitStats, stats_for_commit_stats};
+use crate::authorship::transcript::AiTranscript;
+use crate::authorship::working_log::{AgentId, CheckpointKind};
+use crate::commands::blame::GitAiBlameOptions;
+use crate::commands::checkpoint::{self, CheckpointOptions};
+use crate::commands::checkpoint_agent::agent_presets::AgentRunResult;
+use crate::commands::process_queue::finish_pending_work;
+use crate::error::GitAiError;
+use crate::git::refs::get_authorship;
+use crate::git::repo_registry::{register_repository_in, try_register_repository};
+use crate::git::repository::{Repository, find_repository_in_path};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// A repository with git-ai's authorship tracking
+pub struct GitAi {
+    repo: Repository,
+    /// Where checkpoints register the repository instead of ~/.git-ai/repos.json
+    repo_registry: Option<PathBuf>,
+}
+
+/// Who made the changes a checkpoint records
+#[derive(Debug, Clone)]
+pub struct CheckpointRequest {
+    pub kind: CheckpointKind,
+    /// The agent behind an AI checkpoint; ignored for human ones
+    pub agent_id: Option<AgentId>,
+    pub transcript: Option<AiTranscript>,
+    /// Files the agent edited, relative to the repository root. `None` checks every changed
+    /// file, which is slower on large repositories.
+    pub files: Option<Vec<String>>,
+    /// Author of human checkpoints; defaults to the repository's user.name
+    pub author: Option<String>,
+    /// Contents of files by path, such as editor buffers not saved yet, checkpointed instead
+    /// of what's on disk
+    pub file_contents: Option<HashMap<String, String>>,
+}
+
+impl CheckpointRequest {
+    /// Changes made by a person since the last checkpoint
+    pub fn human() -> Self {
+        Self {
+            kind: CheckpointKind::Human,
+            agent_id: None,
+            transcript: None,
+            files: None,
+            author: None,
+            file_contents: None,
+        }
+    }
+
+    /// Changes made by an agent since the last checkpoint
+    pub fn agent(agent_id: AgentId) -> Self {
+        Self {
+            kind: CheckpointKind::AiAgent,
+            agent_id: Some(agent_id),
+            ..Self::human()
+        }
+    }
+
+    pub fn with_transcript(mut self, transcript: AiTranscript) -> Self {
+        self.transcript = Some(transcript);
+        self
+    }
+
+    pub fn with_files(mut self, files: Vec<String>) -> Self {
+        self.files = Some(files);
+        self
+    }
+
+    pub fn with_author(mut self, author: impl Into<String>) -> Self {
+        self.author = Some(author.into());
+        self
+    }
+
+    pub fn with_file_contents(mut self, file_contents: HashMap<String, String>) -> Self {
+        self.file_contents = Some(file_contents);
+        self
+    }
+}
+
+/// What a checkpoint recorded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointSummary {
+    /// Files with changes attributed by this checkpoint
+    pub files_changed: usize,
+    /// Files with uncommitted changes, including ones earlier checkpoints already covered
+    pub files_uncommitted: usize,
+    /// Checkpoints in the working log since the last commit
+    pub checkpoints: usize,
+}
+
+/// Who wrote one line of a file
+#[derive(Debug, Clone)]
+pub struct BlameLine {
+    /// 1-based line number in the working copy
+    pub line: u32,
+    /// The agent's tool for AI lines, the git author otherwise
+    pub author: String,
+    /// Key of the prompt record that wrote the line, for AI lines
+    pub prompt_id: Option<String>,
+    pub prompt: Option<PromptRecord>,
+}
+
+impl GitAi {
+    /// Open the repository containing `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, GitAiError> {
+        let path = path.as_ref().to_string_lossy().to_string();
+        Ok(Self {
+            repo: find_repository_in_path(&path)?,
+            repo_registry: None,
+        })
+    }
+
+    /// Register the repository in the registry at `path` instead of ~/.git-ai/repos.json,
+    /// for hosts that keep git-ai's state out of the user's home directory
+    pub fn with_repo_registry(mut self, path: impl Into<PathBuf>) -> Self {
+        self.repo_registry = Some(path.into());
+        self
+    }
+
+    pub fn repository(&self) -> &Repository {
+        &self.repo
+    }
+
+    /// Attribute the changes since the last checkpoint, like `git-ai checkpoint`
+    pub fn checkpoint(&self, request: CheckpointRequest) -> Result<CheckpointSummary, GitAiError> {
+        let agent_run_result = match (request.kind, request.agent_id) {
+            (CheckpointKind::Human, _) if request.file_contents.is_none() => None,
+            (CheckpointKind::Human, _) => Some(AgentRunResult {
+                agent_id: AgentId {
+                    tool: "human".to_string(),
+                    id: "human".to_string(),
+                    model: "human".to_string(),
+                },
+                checkpoint_kind: CheckpointKind::Human,
+                transcript: None,
+                repo_working_dir: None,
+                edited_filepaths: None,
+                will_edit_filepaths: None,
+                commit_message_source: None,
+                agent_version: None,
+                file_contents: request.file_contents,
+            }),
+            (_, None) => {
+                return Err(GitAiError::Generic(
+                    "AI checkpoints need an agent id".to_string(),
+                ));
+            }
+            (kind, Some(agent_id)) => Some(AgentRunResult {
+                agent_id,
+                checkpoint_kind: kind,
+                transcript: request.transcript,
+                repo_working_dir: None,
+                edited_filepaths: request.files.clone(),
+                will_edit_filepaths: None,
+                commit_message_source: None,
+                agent_version: None,
+                file_contents: request.file_contents,
+            }),
+        };
+        let author = match request.author {
+            Some(author) => author,
+            None => match self.repo.config_get_str("user.name") {
+                Ok(Some(name)) if !name.trim().is_empty() => name,
+                _ => "unknown".to_string(),
+            },
+        };
+
+        finish_pending_work(&self.repo);
+        let (files_changed, files_uncommitted, checkpoints) = checkpoint::run(
+            &self.repo,
+            &author,
+            request.kind,
+            agent_run_result,
+            CheckpointOptions {
+                quiet: true,
+                ..Default::default()
+            },
+        )?;
+        if let Ok(workdir) = self.repo.workdir() {
+            match &self.repo_registry {
+                Some(registry) => register_repository_in(registry, &workdir)?,
+                None => try_register_repository(&workdir),
+            }
+        }
+
+        Ok(CheckpointSummary {
+            files_changed,
+            files_uncommitted,
+            checkpoints,
+        })
+    }
+
+    /// Every line of the working copy of `file_path` with who wrote it, like `git-ai blame`
+    pub fn blame(&self, file_path: &str) -> Result<Vec<BlameLine>, GitAiError> {
+        let options = GitAiBlameOptions {
+            use_prompt_hashes_as_names: true,
+            no_output: true,
+            ..Default::default()
+        };
+        let (line_authors, prompt_records) = self.repo.blame(file_path, &options)?;
+
+        let mut lines: Vec<BlameLine> = line_authors
+            .into_iter()
+            .map(|(line, author)| match prompt_records.get(&author) {
+                Some(prompt) => BlameLine {
+                    line,
+                    author: prompt.agent_id.tool.clone(),
+                    prompt_id: Some(author),
+                    prompt: Some(prompt.clone()),
+                },
+                None => BlameLine {
+                    line,
+                    author,
+                    prompt_id: None,
+                    prompt: None,
+                },
+            })
+            .collect();
+        lines.sort_by_key(|line| line.line);
+        Ok(lines)
+    }
+
+    /// AI and human line counts for `commit`, like `git-ai stats --json`
+    pub fn stats(&self, commit: &str) -> Result<CommitStats, GitAiError> {
+        let sha = self.repo.revparse_single(commit)?.id();
+        stats_for_commit_stats(&self.repo, &sha, commit)
+    }
+
+    /// The authorship note of `commit`, if it has one
+    pub fn authorship(&self, commit: &str) -> Result<Option<AuthorshipLog>, GitAiError> {
+        let sha = self.repo.revparse_single(commit)?.id();
+        Ok(get_authorship(&self.repo, &sha))
+    }
+}
@@ -0,0 +1,548 @@
+//! Attribution by anchored token matching
+//!
+//! An alternative to the character diff of [`AttributionTracker`] for code that gets reformatted
+//! a lot. Both versions of a file are split into tokens (identifiers, numbers and single
+//! punctuation chars) with whitespace left out, and the tokens are matched the way patience diff
+//! matches lines: tokens that occur exactly once in both versions anchor the match, the longest
+//! run of anchors in the same order is kept, and the stretches between anchors are matched the
+//! same way, starting with their common beginnings and ends. A matched token keeps its old
+//! attributions however the whitespace around it changed.
+//!
+//! [`AttributionTracker`]: crate::authorship::attribution_tracker::AttributionTracker
+
+use crate::authorship::attribution_tracker::{
+    Attribution, AttributionAlgorithm, AttributionConfig, MOVED_BLOCK_MIN_ALNUM,
+};
+use crate::error::GitAiError;
+use std::collections::HashMap;
+
+/// Stretches of fewer tokens than this aren't treated as moved
+const MOVED_RUN_MIN_TOKENS: usize = 8;
+
+/// Places in the old version a moved stretch starting with a given token is looked for, so
+/// a rewrite full of common tokens like `(` stays fast
+const MOVE_CANDIDATES_MAX: usize = 64;
+
+pub struct AnchoredTokenTracker {
+    config: AttributionConfig,
+}
+
+impl AnchoredTokenTracker {
+    pub fn new() -> Self {
+        Self::with_config(AttributionConfig::default())
+    }
+
+    pub fn with_config(config: AttributionConfig) -> Self {
+        AnchoredTokenTracker { config }
+    }
+}
+
+impl Default for AnchoredTokenTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AttributionAlgorithm for AnchoredTokenTracker {
+    fn update_attributions(
+        &self,
+        old_content: &str,
+        new_content: &str,
+        old_attributions: &[Attribution],
+        current_author: &str,
+        ts: u128,
+    ) -> Result<Vec<Attribution>, GitAiError> {
+        let old_tokens = tokenize(old_content);
+        let new_tokens = tokenize(new_content);
+        let old_texts: Vec<&str> = old_tokens.iter().map(|t| &old_content[t.0..t.1]).collect();
+        let new_texts: Vec<&str> = new_tokens.iter().map(|t| &new_content[t.0..t.1]).collect();
+
+        let mut matches = match_tokens(&old_texts, &new_texts);
+        if self.config.detects_moves() {
+            match_moved_runs(&old_texts, &new_texts, &mut matches);
+        }
+
+        let mut attributions = Vec::new();
+        for (new_index, &(start, end)) in new_tokens.iter().enumerate() {
+            match matches[new_index] {
+                Some(old_index) => carry_attributions(
+                    old_attributions,
+                    old_tokens[old_index].0,
+                    old_tokens[old_index].1,
+                    start,
+                    &mut attributions,
+                ),
+                None => {
+                    attributions.push(Attribution::new(start, end, current_author.to_string(), ts))
+                }
+            }
+        }
+
+        // Whitespace between two tokens that were next to each other keeps its attributions
+        // when it didn't change; any other whitespace is the current author's
+        for gap in 0..=new_tokens.len() {
+            let new_start = if gap == 0 { 0 } else { new_tokens[gap - 1].1 };
+            let new_end = new_tokens.get(gap).map_or(new_content.len(), |t| t.0);
+            if new_start == new_end {
+                continue;
+            }
+            let old_gap = old_gap_between(&old_tokens, old_content.len(), &matches, gap);
+            match old_gap {
+                Some((old_start, old_end))
+                    if old_content[old_start..old_end] == new_content[new_start..new_end] =>
+                {
+                    carry_attributions(
+                        old_attributions,
+                        old_start,
+                        old_end,
+                        new_start,
+                        &mut attributions,
+                    );
+                }
+                _ => attributions.push(Attribution::new(
+                    new_start,
+                    new_end,
+                    current_author.to_string(),
+                    ts,
+                )),
+            }
+        }
+
+        Ok(coalesce(attributions))
+    }
+}
+
+/// Byte ranges of the tokens of `content`: runs of alphanumeric chars and underscores, and
+/// every other non-whitespace char on its own
+fn tokenize(content: &str) -> Vec<(usize, usize)> {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut tokens = Vec::new();
+    let mut chars = content.char_indices().peekable();
+    while let Some((start, c)) = chars.next() {
+        if c.is_whitespace() {
+            continue;
+        }
+        let mut end = start + c.len_utf8();
+        if is_word(c) {
+            while let Some(&(next_start, next)) = chars.peek() {
+                if !is_word(next) {
+                    break;
+                }
+                end = next_start + next.len_utf8();
+                chars.next();
+            }
+        }
+        tokens.push((start, end));
+    }
+    tokens
+}
+
+/// For each new token, the old token it matched, if any
+fn match_tokens(old: &[&str], new: &[&str]) -> Vec<Option<usize>> {
+    let mut matches = vec![None; new.len()];
+    let mut ranges = vec![(0, old.len(), 0, new.len())];
+    while let Some((mut old_start, mut old_end, mut new_start, mut new_end)) = ranges.pop() {
+        while old_start < old_end && new_start < new_end && old[old_start] == new[new_start] {
+            matches[new_start] = Some(old_start);
+            old_start += 1;
+            new_start += 1;
+        }
+        while old_start < old_end && new_start < new_end && old[old_end - 1] == new[new_end - 1] {
+            old_end -= 1;
+            new_end -= 1;
+            matches[new_end] = Some(old_end);
+        }
+
+        let anchors = unique_anchors(&old[old_start..old_end], &new[new_start..new_end]);
+        if anchors.is_empty() {
+            continue;
+        }
+        let (mut old_next, mut new_next) = (old_start, new_start);
+        for (old_anchor, new_anchor) in anchors {
+            let (old_anchor, new_anchor) = (old_start + old_anchor, new_start + new_anchor);
+            matches[new_anchor] = Some(old_anchor);
+            if old_next < old_anchor && new_next < new_anchor {
+                ranges.push((old_next, old_anchor, new_next, new_anchor));
+            }
+            old_next = old_anchor + 1;
+            new_next = new_anchor + 1;
+        }
+        if old_next < old_end && new_next < new_end {
+            ranges.push((old_next, old_end, new_next, new_end));
+        }
+    }
+    matches
+}
+
+/// Positions of the tokens occurring once in both `old` and `new`, keeping the longest run of
+/// them that is in the same order in both
+fn unique_anchors(old: &[&str], new: &[&str]) -> Vec<(usize, usize)> {
+    // Occurrences and last position in old, then in new
+    let mut counts: HashMap<&str, (usize, usize, usize, usize)> = HashMap::new();
+    for (index, token) in old.iter().enumerate() {
+        let entry = counts.entry(token).or_default();
+        entry.0 += 1;
+        entry.1 = index;
+    }
+    for (index, token) in new.iter().enumerate() {
+        if let Some(entry) = counts.get_mut(token) {
+            entry.2 += 1;
+            entry.3 = index;
+        }
+    }
+    let mut pairs: Vec<(usize, usize)> = counts
+        .values()
+        .filter(|(old_count, _, new_count, _)| *old_count == 1 && *new_count == 1)
+        .map(|(_, old_index, _, new_index)| (*old_index, *new_index))
+        .collect();
+    pairs.sort_by_key(|(_, new_index)| *new_index);
+
+    // Longest increasing run of old positions, patience sorting style
+    let mut tails: Vec<usize> = Vec::new();
+    let mut previous: Vec<Option<usize>> = vec![None; pairs.len()];
+    for (i, (old_index, _)) in pairs.iter().enumerate() {
+        let pile = tails.partition_point(|&tail| pairs[tail].0 < *old_index);
+        if pile > 0 {
+            previous[i] = Some(tails[pile - 1]);
+        }
+        if pile == tails.len() {
+            tails.push(i);
+        } else {
+            tails[pile] = i;
+        }
+    }
+    let mut run = Vec::with_capacity(tails.len());
+    let mut next = tails.last().copied();
+    while let Some(i) = next {
+        run.push(pairs[i]);
+        next = previous[i];
+    }
+    run.reverse();
+    run
+}
+
+/// Match stretches of new tokens left unmatched to identical stretches of unmatched old tokens
+/// elsewhere, so code moved past other code keeps its authors. Like the diff algorithm's move
+/// detection, short stretches and those with few alphanumeric chars are left alone.
+fn match_moved_runs(old: &[&str], new: &[&str], matches: &mut [Option<usize>]) {
+    // Runs of matches too short to be a moved stretch themselves are as likely coincidences
+    // (a `fn` or `) ;` matched across blocks) as real matches, so a moved stretch may take them
+    let mut released = Vec::new();
+    let mut run_start = 0;
+    for new_index in 0..=new.len() {
+        let continues = new_index < new.len()
+            && new_index > run_start
+            && matches[new_index].is_some()
+            && matches[new_index] == matches[new_index - 1].map(|old_index| old_index + 1);
+        if continues {
+            continue;
+        }
+        if new_index - run_start < MOVED_RUN_MIN_TOKENS {
+            released.extend(
+                (run_start..new_index)
+                    .filter_map(|index| matches[index].map(|old_index| (index, old_index))),
+            );
+        }
+        run_start = new_index;
+    }
+    for (new_index, _) in &released {
+        matches[*new_index] = None;
+    }
+
+    let mut old_used = vec![false; old.len()];
+    for old_index in matches.iter().flatten() {
+        old_used[*old_index] = true;
+    }
+    let mut old_positions: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (index, token) in old.iter().enumerate() {
+        if !old_used[index] {
+            old_positions.entry(token).or_default().push(index);
+        }
+    }
+
+    let mut new_index = 0;
+    while new_index < new.len() {
+        if matches[new_index].is_some() {
+            new_index += 1;
+            continue;
+        }
+        // Longest stretch of unused old tokens equal to the new ones from here
+        let mut longest = (0, 0);
+        for &old_start in old_positions
+            .get(new[new_index])
+            .into_iter()
+            .flatten()
+            .take(MOVE_CANDIDATES_MAX)
+        {
+            let mut len = 0;
+            while new_index + len < new.len()
+                && matches[new_index + len].is_none()
+                && old_start + len < old.len()
+                && !old_used[old_start + len]
+                && old[old_start + len] == new[new_index + len]
+            {
+                len += 1;
+            }
+            if len > longest.0 {
+                longest = (len, old_start);
+            }
+        }
+
+        let (len, old_start) = longest;
+        let alnum: usize = new[new_index..new_index + len]
+            .iter()
+            .map(|token| token.chars().filter(|c| c.is_alphanumeric()).count())
+            .sum();
+        if len < MOVED_RUN_MIN_TOKENS || alnum < MOVED_BLOCK_MIN_ALNUM {
+            new_index += 1;
+            continue;
+        }
+        for i in 0..len {
+            matches[new_index + i] = Some(old_start + i);
+            old_used[old_start + i] = true;
+        }
+        new_index += len;
+    }
+
+    // Grow matches over equal unused neighbours, so a moved stretch gets back its first and
+    // last tokens from the coincidental matches released above
+    for new_index in 1..new.len() {
+        if matches[new_index].is_none()
+            && let Some(old_index) = matches[new_index - 1].map(|old_index| old_index + 1)
+            && old_index < old.len()
+            && !old_used[old_index]
+            && old[old_index] == new[new_index]
+        {
+            matches[new_index] = Some(old_index);
+            old_used[old_index] = true;
+        }
+    }
+    for new_index in (0..new.len().saturating_sub(1)).rev() {
+        if matches[new_index].is_none()
+            && let Some(old_index) =
+                matches[new_index + 1].and_then(|old_index| old_index.checked_sub(1))
+            && !old_used[old_index]
+            && old[old_index] == new[new_index]
+        {
+            matches[new_index] = Some(old_index);
+            old_used[old_index] = true;
+        }
+    }
+
+    // Released tokens nothing else took keep their match
+    for (new_index, old_index) in released {
+        if matches[new_index].is_none() && !old_used[old_index] {
+            matches[new_index] = Some(old_index);
+            old_used[old_index] = true;
+        }
+    }
+}
+
+/// The old whitespace in the place of the whitespace before new token `gap` (or at the end),
+/// when the tokens on both sides of it matched tokens that were next to each other
+fn old_gap_between(
+    old_tokens: &[(usize, usize)],
+    old_len: usize,
+    matches: &[Option<usize>],
+    gap: usize,
+) -> Option<(usize, usize)> {
+    // The start and end of the file stand in for the tokens before the first and after the last
+    let left = if gap == 0 {
+        None
+    } else {
+        Some(matches[gap - 1]?)
+    };
+    let right = if gap == matches.len() {
+        None
+    } else {
+        Some(matches[gap]?)
+    };
+    let adjacent = match (left, right) {
+        (None, None) => old_tokens.is_empty(),
+        (None, Some(right)) => right == 0,
+        (Some(left), None) => left + 1 == old_tokens.len(),
+        (Some(left), Some(right)) => left + 1 == right,
+    };
+    if !adjacent {
+        return None;
+    }
+    let start = left.map_or(0, |left| old_tokens[left].1);
+    let end = right.map_or(old_len, |right| old_tokens[right].0);
+    Some((start, end))
+}
+
+/// Copy the attributions of the old bytes `old_start..old_end` to the same bytes at `new_start`
+fn carry_attributions(
+    old_attributions: &[Attribution],
+    old_start: usize,
+    old_end: usize,
+    new_start: usize,
+    attributions: &mut Vec<Attribution>,
+) {
+    for old_attr in old_attributions {
+        if let Some((start, end)) = old_attr.intersection(old_start, old_end) {
+            attributions.push(Attribution::new(
+                new_start + (start - old_start),
+                new_start + (end - old_start),
+                old_attr.author_id.clone(),
+                old_attr.ts,
+            ));
+        }
+    }
+}
+
+/// Join touching ranges of the same author and timestamp, which matching token by token
+/// leaves in pieces
+fn coalesce(mut attributions: Vec<Attribution>) -> Vec<Attribution> {
+    attributions.sort_by(|a, b| {
+        (a.author_id.as_str(), a.ts, a.start).cmp(&(b.author_id.as_str(), b.ts, b.start))
+    });
+    let mut merged: Vec<Attribution> = Vec::with_capacity(attributions.len());
+    for attr in attributions {
+        if let Some(last) = merged.last_mut()
+            && last.author_id == attr.author_id
+            && last.ts == attr.ts
+            && attr.start <= last.end
+        {
+            last.end = last.end.max(attr.end);
+            continue;
+        }
+        merged.push(attr);
+    }
+    merged.sort_by_key(|a| (a.start, a.end, a.author_id.clone()));
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authorship::attribution_tracker::attributions_to_line_attributions;
+
+    const TEST_TS: u128 = 1234567890000;
+
+    fn line_authors(attributions: &[Attribution], content: &str) -> Vec<String> {
+        let line_attributions = attributions_to_line_attributions(&attributions.to_vec(), content);
+        (1..=content.lines().count() as u32)
+            .map(|line| {
+                line_attributions
+                    .iter()
+                    .find(|attr| attr.start_line <= line && line <= attr.end_line)
+                    .map_or("human".to_string(), |attr| attr.author_id.clone())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_reformat_keeps_authors() {
+        let tracker = AnchoredTokenTracker::new();
+        let old = "fn main() {\n    if ready { run(1, 2); }\n}\n";
+        let new = "fn main() {\n  if ready {\n    run(1,2);\n  }\n}\n";
+        let old_attributions = vec![Attribution::new(0, old.len(), "ai".to_string(), 1)];
+
+        let attributions = tracker
+            .update_attributions(old, new, &old_attributions, "human", TEST_TS)
+            .unwrap();
+
+        assert_eq!(line_authors(&attributions, new), vec!["ai"; 5]);
+    }
+
+    #[test]
+    fn test_new_tokens_go_to_current_author() {
+        let tracker = AnchoredTokenTracker::new();
+        let old = "let total = a + b;\n";
+        let new = "let total = a + b;\nlet doubled = total * 2;\n";
+        let old_attributions = vec![Attribution::new(0, old.len(), "ai".to_string(), 1)];
+
+        let attributions = tracker
+            .update_attributions(old, new, &old_attributions, "human", TEST_TS)
+            .unwrap();
+
+        assert_eq!(line_authors(&attributions, new), vec!["ai", "human"]);
+        // Every byte is accounted for
+        let filled = tracker.attribute_unattributed_ranges(new, &attributions, "none", TEST_TS);
+        assert!(!filled.iter().any(|attr| attr.author_id == "none"));
+    }
+
+    #[test]
+    fn test_repeated_tokens_match_between_anchors() {
+        let tracker = AnchoredTokenTracker::new();
+        let old = "a(x);\nfirst();\na(x);\n";
+        let new = "a(x);\nfirst();\nsecond();\na(x);\n";
+        let old_attributions = vec![
+            Attribution::new(0, 14, "ai".to_string(), 1),
+            Attribution::new(14, old.len(), "human".to_string(), 2),
+        ];
+
+        let attributions = tracker
+            .update_attributions(old, new, &old_attributions, "claude", TEST_TS)
+            .unwrap();
+
+        assert_eq!(
+            line_authors(&attributions, new),
+            vec!["ai", "ai", "claude", "human"]
+        );
+    }
+
+    #[test]
+    fn test_moved_block_keeps_authors() {
+        let helper = "fn helper(value: u32) -> u32 {\n    value * 2 + offset\n}\n";
+        let main = "fn main() {\n    let config = load_config();\n    run(config);\n    report(&config);\n    cleanup(config)\n}\n";
+        // The helper stays put as far as matching goes, with the AI's main moved above it
+        let old = format!("{}{}", helper, main);
+        let new = format!("{}{}", main, helper);
+        let old_attributions = vec![
+            Attribution::new(0, helper.len(), "human".to_string(), 1),
+            Attribution::new(helper.len(), old.len(), "ai".to_string(), 2),
+        ];
+
+        let attributions = AnchoredTokenTracker::new()
+            .update_attributions(&old, &new, &old_attributions, "human", TEST_TS)
+            .unwrap();
+        let mut expected = vec!["ai"; 6];
+        expected.extend(["human"; 3]);
+        assert_eq!(line_authors(&attributions, &new), expected);
+
+        let no_moves = AnchoredTokenTracker::with_config(
+            AttributionConfig::default().with_detect_moves(false),
+        );
+        let attributions = no_moves
+            .update_attributions(&old, &new, &old_attributions, "human", TEST_TS)
+            .unwrap();
+        assert_eq!(line_authors(&attributions, &new)[..6], ["human"; 6]);
+    }
+
+    #[test]
+    fn test_swapped_lines_keep_authors() {
+        let old = "let first_value = compute_first(alpha, beta);\nlet second_value = compute_second(gamma, delta);\n";
+        let new = "let second_value = compute_second(gamma, delta);\nlet first_value = compute_first(alpha, beta);\n";
+        let old_attributions = vec![Attribution::new(0, old.len(), "ai".to_string(), 1)];
+
+        let attributions = AnchoredTokenTracker::new()
+            .update_attributions(old, new, &old_attributions, "human", TEST_TS)
+            .unwrap();
+
+        assert_eq!(line_authors(&attributions, new), vec!["ai", "ai"]);
+    }
+
+    #[test]
+    fn test_utf8_tokens() {
+        let tracker = AnchoredTokenTracker::new();
+        let old = "let café = \"naïve\";\n";
+        let new = "let  café = \"naïve\"; // 👋\n";
+        let old_attributions = vec![Attribution::new(0, old.len(), "ai".to_string(), 1)];
+
+        let attributions = tracker
+            .update_attributions(old, new, &old_attributions, "human", TEST_TS)
+            .unwrap();
+
+        for attr in &attributions {
+            assert!(new.is_char_boundary(attr.start) && new.is_char_boundary(attr.end));
+        }
+        let comment = new.find("//").unwrap();
+        assert!(
+            attributions.iter().any(|attr| attr.author_id == "human"
+                && attr.start <= comment
+                && attr.end > comment)
+        );
+    }
+}
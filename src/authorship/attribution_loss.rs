@@ -0,0 +1,258 @@
+//! Accounting of the AI attributions an operation drops. Resets, rewrites git-ai didn't see and
+//! reconstructions that fail can leave AI lines without a working log entry or note to trace
+//! them back to their prompts. Hooks record each such loss on the repository, and once the git
+//! command is done the proxy warns about it, with a command that recovers the lines when there
+//! is one, and keeps it in the command's outcome.
+
+use crate::authorship::authorship_log::LineRange;
+use crate::authorship::working_log::CheckpointKind;
+use crate::error::GitAiError;
+use crate::git::refs::get_authorship;
+use crate::git::repository::{Repository, exec_git};
+use crate::git::rewrite_log::RewriteLogEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AttributionLoss {
+    /// What dropped the attributions, e.g. `reset --hard`
+    pub operation: String,
+    /// Why they couldn't be kept
+    pub reason: String,
+    pub ai_lines: u32,
+    pub files: usize,
+    /// Command that brings the attributions back, when there is one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recovery: Option<String>,
+}
+
+impl AttributionLoss {
+    /// A loss of the counted lines, or `None` when none of them were AI's
+    pub fn new(
+        operation: impl Into<String>,
+        reason: impl Into<String>,
+        lost: AiLineCount,
+        recovery: Option<String>,
+    ) -> Option<Self> {
+        (lost.lines > 0).then(|| Self {
+            operation: operation.into(),
+            reason: reason.into(),
+            ai_lines: lost.lines,
+            files: lost.files.len(),
+            recovery,
+        })
+    }
+
+    /// e.g. `git-ai: lost the attribution of 12 AI lines in 2 files (reset --hard: ...)`,
+    /// followed by the recovery command on a line of its own
+    pub fn warning(&self) -> String {
+        let mut warning = format!(
+            "git-ai: lost the attribution of {} AI line{} in {} file{} ({}: {})",
+            self.ai_lines,
+            if self.ai_lines == 1 { "" } else { "s" },
+            self.files,
+            if self.files == 1 { "" } else { "s" },
+            self.operation,
+            self.reason
+        );
+        if let Some(recovery) = &self.recovery {
+            warning.push_str(&format!("\ngit-ai: recover them with: {}", recovery));
+        }
+        warning
+    }
+}
+
+/// AI-attributed lines and the files they're in
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AiLineCount {
+    pub lines: u32,
+    pub files: BTreeSet<String>,
+}
+
+impl AiLineCount {
+    pub fn add(&mut self, other: AiLineCount) {
+        self.lines += other.lines;
+        self.files.extend(other.files);
+    }
+}
+
+/// The AI lines the working log of `base_commit` holds for uncommitted changes
+pub fn uncommitted_ai_lines(
+    repo: &Repository,
+    base_commit: &str,
+) -> Result<AiLineCount, GitAiError> {
+    // The latest entry of each file holds the attributions of the file as it is now
+    let checkpoints = repo
+        .storage
+        .working_log_for_base_commit(base_commit)
+        .read_all_checkpoints()?;
+    let mut latest_by_file = HashMap::new();
+    for checkpoint in &checkpoints {
+        for entry in &checkpoint.entries {
+            latest_by_file.insert(entry.file.as_str(), entry);
+        }
+    }
+
+    let mut count = AiLineCount::default();
+    for (file, entry) in latest_by_file {
+        let lines: u32 = entry
+            .line_attributions
+            .iter()
+            .filter(|attribution| attribution.author_id != CheckpointKind::Human.to_str())
+            .map(|attribution| attribution.end_line - attribution.start_line + 1)
+            .sum();
+        if lines > 0 {
+            count.lines += lines;
+            count.files.insert(file.to_string());
+        }
+    }
+    Ok(count)
+}
+
+/// The AI lines the notes of `commits` attribute
+pub fn committed_ai_lines(repo: &Repository, commits: &[String]) -> AiLineCount {
+    let mut count = AiLineCount::default();
+    for commit in commits {
+        let Some(log) = get_authorship(repo, commit) else {
+            continue;
+        };
+        for file in &log.attestations {
+            let lines: u32 = file
+                .entries
+                .iter()
+                .filter(|entry| log.metadata.prompts.contains_key(&entry.hash))
+                .flat_map(|entry| &entry.line_ranges)
+                .map(|range| match range {
+                    LineRange::Single(_) => 1,
+                    LineRange::Range(start, end) => end - start + 1,
+                })
+                .sum();
+            if lines > 0 {
+                count.lines += lines;
+                count.files.insert(file.file_path.clone());
+            }
+        }
+    }
+    count
+}
+
+/// What a rewrite whose authorship couldn't be carried over left behind: the rewritten commits
+/// keep their notes, the commits replacing them get none
+pub fn rewrite_loss(
+    repo: &Repository,
+    event: &RewriteLogEvent,
+    error: &GitAiError,
+) -> Option<AttributionLoss> {
+    let reason = format!("their authorship couldn't be rewritten: {}", error);
+    match event {
+        RewriteLogEvent::CommitAmend { commit_amend } => {
+            let mut lost =
+                committed_ai_lines(repo, std::slice::from_ref(&commit_amend.original_commit));
+            // What the amend folded in was still in the original commit's working log
+            if let Ok(uncommitted) = uncommitted_ai_lines(repo, &commit_amend.original_commit) {
+                lost.add(uncommitted);
+            }
+            AttributionLoss::new(
+                "commit --amend",
+                reason,
+                lost,
+                Some(backfill_squash_command(
+                    &commit_amend.amended_commit_sha,
+                    &commit_amend.original_commit,
+                )),
+            )
+        }
+        RewriteLogEvent::RebaseComplete { rebase_complete } => {
+            let lost = committed_ai_lines(repo, &rebase_complete.original_commits);
+            // A rebase that squashed everything into one commit is a squash of the old branch
+            let recovery = match rebase_complete.new_commits.as_slice() {
+                [new_commit] => Some(backfill_squash_command(
+                    new_commit,
+                    &rebase_complete.original_head,
+                )),
+                _ => None,
+            };
+            AttributionLoss::new("rebase", reason, lost, recovery)
+        }
+        _ => None,
+    }
+}
+
+/// What a reset from `old_head` back to `target` left behind when the working log couldn't be
+/// rebuilt for it: the lines of the unwound commits and the uncommitted ones. Moving HEAD back
+/// puts them under their notes again.
+pub fn reset_loss(
+    repo: &Repository,
+    operation: &str,
+    target: &str,
+    old_head: &str,
+    error: &GitAiError,
+) -> Option<AttributionLoss> {
+    let exclude = format!("^{}", target);
+    let unwound = rev_list(repo, &[old_head, &exclude]).unwrap_or_default();
+    let mut lost = committed_ai_lines(repo, &unwound);
+    if let Ok(uncommitted) = uncommitted_ai_lines(repo, old_head) {
+        lost.add(uncommitted);
+    }
+    AttributionLoss::new(
+        operation,
+        format!("the working log couldn't be rebuilt: {}", error),
+        lost,
+        Some(format!("git reset --soft {}", old_head)),
+    )
+}
+
+/// The command that writes the note of `commit`, which replaced `original`'s history with a
+/// single commit, from the notes of `original`'s commits
+pub fn backfill_squash_command(commit: &str, original: &str) -> String {
+    format!("git-ai backfill-squash {} --from {}", commit, original)
+}
+
+/// `git rev-list` of the given revisions, newest first
+pub fn rev_list(repo: &Repository, revisions: &[&str]) -> Result<Vec<String>, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("rev-list".to_string());
+    args.extend(revisions.iter().map(|revision| revision.to_string()));
+    let output = exec_git(&args)?;
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .map(str::to_string)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loss_warning() {
+        let lost = AiLineCount {
+            lines: 12,
+            files: BTreeSet::from(["a.rs".to_string(), "b.rs".to_string()]),
+        };
+        let loss = AttributionLoss::new(
+            "reset",
+            "the working log couldn't be rebuilt",
+            lost,
+            Some("git reset --soft abc1234".to_string()),
+        )
+        .unwrap();
+        assert_eq!(
+            loss.warning(),
+            "git-ai: lost the attribution of 12 AI lines in 2 files (reset: the working log couldn't be rebuilt)\ngit-ai: recover them with: git reset --soft abc1234"
+        );
+
+        let lost = AiLineCount {
+            lines: 1,
+            files: BTreeSet::from(["a.rs".to_string()]),
+        };
+        let loss = AttributionLoss::new("reset --hard", "discarded", lost, None).unwrap();
+        assert_eq!(
+            loss.warning(),
+            "git-ai: lost the attribution of 1 AI line in 1 file (reset --hard: discarded)"
+        );
+
+        // Nothing of AI's, nothing to warn about
+        assert!(AttributionLoss::new("reset", "r", AiLineCount::default(), None).is_none());
+    }
+}
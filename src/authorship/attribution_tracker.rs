@@ -3,12 +3,16 @@
 //! This library maintains attribution ranges as files are edited, preserving
 //! authorship information even through moves, edits, and whitespace changes.
 
+use crate::authorship::anchored_attribution::AnchoredTokenTracker;
 use crate::authorship::move_detection::{DeletedLine, InsertedLine, detect_moves};
 use crate::authorship::working_log::CheckpointKind;
+use crate::config::Settings;
 use crate::error::GitAiError;
 use diff_match_patch_rs::dmp::Diff;
 use diff_match_patch_rs::traits::{Compat, Efficient};
 use diff_match_patch_rs::{DiffMatchPatch, Ops};
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
 use std::collections::HashMap;
 
 /// Represents a single attribution range in the file.
@@ -21,7 +25,8 @@ pub struct Attribution {
     pub end: usize,
     /// Identifier for the author of this range
     pub author_id: String,
-    /// Timestamp of the attribution (in milliseconds since epoch)
+    /// Ordering timestamp of the attribution. For checkpoints this is
+    /// `CheckpointClock::attribution_ts` (sequence, then milliseconds since epoch).
     pub ts: u128,
 }
 
@@ -203,19 +208,128 @@ fn collect_line_metadata(content: &str) -> Vec<LineMetadata> {
     metadata
 }
 
+/// How attributions follow a file from one version to the next
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttributionMethod {
+    /// Character diff of the two versions, with move detection ([`AttributionTracker`])
+    #[default]
+    Diff,
+    /// Code tokens matched around tokens that occur once in both versions, ignoring
+    /// whitespace ([`AnchoredTokenTracker`]). Holds up better through reformatting.
+    Anchored,
+}
+
 /// Configuration for the attribution tracker
+#[derive(Debug, Clone, Copy)]
 pub struct AttributionConfig {
     move_lines_threshold: usize,
+    /// When a changed region differs from the old content only in whitespace (reindenting,
+    /// rewrapping), keep the original authors instead of attributing it to the current author
+    ignore_whitespace: bool,
+    method: AttributionMethod,
 }
 
 impl Default for AttributionConfig {
     fn default() -> Self {
         AttributionConfig {
             move_lines_threshold: 3,
+            ignore_whitespace: false,
+            method: AttributionMethod::default(),
         }
     }
 }
 
+impl AttributionConfig {
+    /// The repository's `ignore_whitespace`, `detect_moves` and `attribution_algorithm`
+    /// settings
+    pub fn for_settings(settings: &Settings) -> Self {
+        AttributionConfig::default()
+            .with_ignore_whitespace(settings.ignore_whitespace)
+            .with_detect_moves(settings.detect_moves)
+            .with_method(settings.attribution_algorithm)
+    }
+
+    pub fn with_method(mut self, method: AttributionMethod) -> Self {
+        self.method = method;
+        self
+    }
+
+    /// Whether moved blocks of code keep their authors
+    pub fn detects_moves(&self) -> bool {
+        self.move_lines_threshold > 0
+    }
+
+    pub fn with_ignore_whitespace(mut self, ignore_whitespace: bool) -> Self {
+        self.ignore_whitespace = ignore_whitespace;
+        self
+    }
+
+    /// Turn detection of moved blocks on or off. When off, moved code is attributed to
+    /// whoever moved it.
+    pub fn with_detect_moves(mut self, detect_moves: bool) -> Self {
+        self.move_lines_threshold = if detect_moves {
+            AttributionConfig::default().move_lines_threshold
+        } else {
+            0
+        };
+        self
+    }
+}
+
+/// One file's content before and after a change, used to follow code moved between files
+pub struct FileChange<'a> {
+    pub previous_content: &'a str,
+    pub previous_attributions: &'a [Attribution],
+    pub content: &'a str,
+}
+
+/// Like git's `--color-moved`, blocks with fewer alphanumeric chars than this aren't treated
+/// as moved, so a run of closing braces doesn't carry attributions across files
+pub(crate) const MOVED_BLOCK_MIN_ALNUM: usize = 20;
+
+/// A strategy for carrying attributions through file changes. Every implementation produces
+/// the same [`Attribution`] ranges, so working logs and notes don't depend on the one used.
+pub trait AttributionAlgorithm {
+    /// Update attributions from old content to new content
+    ///
+    /// # Arguments
+    /// * `old_content` - The previous version of the file
+    /// * `new_content` - The new version of the file
+    /// * `old_attributions` - Attributions from the previous version
+    /// * `current_author` - Author ID to use for new changes
+    ///
+    /// # Returns
+    /// A vector of updated attributions for the new content
+    fn update_attributions(
+        &self,
+        old_content: &str,
+        new_content: &str,
+        old_attributions: &[Attribution],
+        current_author: &str,
+        ts: u128,
+    ) -> Result<Vec<Attribution>, GitAiError>;
+
+    /// Attribute all unattributed ranges to the given author
+    fn attribute_unattributed_ranges(
+        &self,
+        content: &str,
+        prev_attributions: &[Attribution],
+        author: &str,
+        ts: u128,
+    ) -> Vec<Attribution> {
+        fill_unattributed_ranges(content, prev_attributions, author, ts)
+    }
+}
+
+/// The algorithm `config` selects
+pub fn attribution_algorithm(config: AttributionConfig) -> Box<dyn AttributionAlgorithm> {
+    match config.method {
+        AttributionMethod::Diff => Box::new(AttributionTracker::with_config(config)),
+        AttributionMethod::Anchored => Box::new(AnchoredTokenTracker::with_config(config)),
+    }
+}
+
 /// Main attribution tracker
 pub struct AttributionTracker {
     config: AttributionConfig,
@@ -321,96 +435,152 @@ impl AttributionTracker {
         s.is_char_boundary(start) && s.is_char_boundary(end)
     }
 
-    /// Attribute all unattributed ranges to the given author
-    pub fn attribute_unattributed_ranges(
+    /// Follow blocks of lines deleted from one file and inserted into another as part of the
+    /// same change. `attributions[i]` holds the updated attributions of `changes[i]`; the lines
+    /// of a moved block get back the attributions they had in the file they came from. Moves
+    /// within a single file are already handled by `update_attributions`.
+    pub fn carry_moves_between_files(
         &self,
-        content: &str,
-        prev_attributions: &[Attribution],
-        author: &str,
-        ts: u128,
-    ) -> Vec<Attribution> {
-        let mut attributions = prev_attributions.to_vec();
-        let mut unattributed_char_idxs = Vec::new();
+        changes: &[FileChange],
+        attributions: &mut [Vec<Attribution>],
+    ) {
+        let threshold = self.config.move_lines_threshold;
+        if threshold == 0 || changes.len() < 2 {
+            return;
+        }
 
-        // Find all unattributed character positions
-        for i in 0..content.len() {
-            if !attributions.iter().any(|a| a.overlaps(i, i + 1)) {
-                unattributed_char_idxs.push(i);
+        let mut old_lines = Vec::with_capacity(changes.len());
+        let mut new_lines = Vec::with_capacity(changes.len());
+        let mut line_offsets = Vec::with_capacity(changes.len());
+        let mut deleted_lines = Vec::new();
+        let mut inserted_lines = Vec::new();
+        // Line numbers are offset per file, with a gap between files, so that no block of
+        // contiguous lines spans two files
+        let mut line_offset = 0;
+        for (file_idx, change) in changes.iter().enumerate() {
+            let old = collect_line_metadata(change.previous_content);
+            let new = collect_line_metadata(change.content);
+
+            let diff = TextDiff::from_lines(change.previous_content, change.content);
+            for line in diff.iter_all_changes() {
+                match (line.tag(), line.old_index(), line.new_index()) {
+                    (ChangeTag::Delete, Some(index), _) if index < old.len() => {
+                        deleted_lines.push(DeletedLine::new(
+                            old[index].text.clone(),
+                            line_offset + old[index].number,
+                            file_idx,
+                        ));
+                    }
+                    (ChangeTag::Insert, _, Some(index)) if index < new.len() => {
+                        inserted_lines.push(InsertedLine::new(
+                            new[index].text.clone(),
+                            line_offset + new[index].number,
+                            file_idx,
+                        ));
+                    }
+                    _ => {}
+                }
             }
+
+            line_offsets.push(line_offset);
+            line_offset += old.len().max(new.len()) + 2;
+            old_lines.push(old);
+            new_lines.push(new);
         }
 
-        // Sort the unattributed character indices by position
-        unattributed_char_idxs.sort();
+        if deleted_lines.is_empty() || inserted_lines.is_empty() {
+            return;
+        }
 
-        // Group contiguous unattributed ranges
-        let mut contiguous_ranges = Vec::new();
-        if !unattributed_char_idxs.is_empty() {
-            let mut start = unattributed_char_idxs[0];
-            let mut end = start + 1;
+        let mappings = detect_moves(&mut inserted_lines, &mut deleted_lines, threshold);
+        let mut touched = vec![false; changes.len()];
+        for mapping in mappings {
+            let (Some(first_deleted), Some(first_inserted)) =
+                (mapping.deleted.first(), mapping.inserted.first())
+            else {
+                continue;
+            };
+            let (source, target) = (first_deleted.deletion_idx, first_inserted.insertion_idx);
+            if source == target {
+                continue;
+            }
+            let alnum: usize = mapping
+                .deleted
+                .iter()
+                .map(|line| {
+                    line.normalized_content
+                        .chars()
+                        .filter(|ch| ch.is_alphanumeric())
+                        .count()
+                })
+                .sum();
+            if alnum < MOVED_BLOCK_MIN_ALNUM {
+                continue;
+            }
 
-            for i in 1..unattributed_char_idxs.len() {
-                let current = unattributed_char_idxs[i];
-                if current == end {
-                    // Contiguous with previous range
-                    end = current + 1;
-                } else {
-                    // Gap found, save current range and start new one
-                    contiguous_ranges.push((start, end));
-                    start = current;
-                    end = current + 1;
-                }
+            for (deleted, inserted) in mapping.deleted.iter().zip(&mapping.inserted) {
+                let old_line = &old_lines[source][deleted.line_number - line_offsets[source] - 1];
+                let new_line = &new_lines[target][inserted.line_number - line_offsets[target] - 1];
+                // Only the trimmed text is known to match; indentation keeps its attribution
+                let old_indent = old_line.text.len() - old_line.text.trim_start().len();
+                let new_indent = new_line.text.len() - new_line.text.trim_start().len();
+                splice_attributions(
+                    &mut attributions[target],
+                    new_line.start + new_indent,
+                    changes[source].previous_attributions,
+                    old_line.start + old_indent,
+                    deleted.normalized_content.len(),
+                );
             }
-            // Don't forget the last range
-            contiguous_ranges.push((start, end));
+            touched[target] = true;
         }
 
-        // Create attributions for each contiguous unattributed range
-        for (start, end) in contiguous_ranges {
-            attributions.push(Attribution::new(start, end, author.to_string(), ts));
+        for (file_attributions, touched) in attributions.iter_mut().zip(touched) {
+            if touched {
+                *file_attributions = self.merge_attributions(std::mem::take(file_attributions));
+            }
         }
-
-        attributions
     }
 
-    /// Update attributions from old content to new content
-    ///
-    /// # Arguments
-    /// * `old_content` - The previous version of the file
-    /// * `new_content` - The new version of the file
-    /// * `old_attributions` - Attributions from the previous version
-    /// * `current_author` - Author ID to use for new changes
-    ///
-    /// # Returns
-    /// A vector of updated attributions for the new content
-    pub fn update_attributions(
+    /// Diff the non-whitespace chars of both versions and return the ones that are unchanged
+    fn whitespace_insensitive_matches(
         &self,
         old_content: &str,
         new_content: &str,
-        old_attributions: &[Attribution],
-        current_author: &str,
-        ts: u128,
-    ) -> Result<Vec<Attribution>, GitAiError> {
-        // Phase 1: Compute diff
-        let diffs = self.compute_diffs(old_content, new_content)?;
+    ) -> Result<Vec<WhitespaceInsensitiveMatch>, GitAiError> {
+        let (old_chars, old_stripped) = non_whitespace_chars(old_content);
+        let (new_chars, new_stripped) = non_whitespace_chars(new_content);
 
-        // Phase 2: Build deletion and insertion catalogs
-        let (deletions, insertions) = self.build_diff_catalog(&diffs);
-
-        // Phase 3: Detect move operations
-        let move_mappings = self.detect_moves(old_content, new_content, &deletions, &insertions);
+        let diffs = self
+            .dmp
+            .diff_main::<Compat>(&old_stripped, &new_stripped)
+            .map_err(|e| GitAiError::Generic(format!("Diff computation failed: {:?}", e)))?;
 
-        // Phase 4: Transform attributions through the diff
-        let new_attributions = self.transform_attributions(
-            &diffs,
-            old_attributions,
-            current_author,
-            &insertions,
-            &move_mappings,
-            ts,
-        );
+        let mut matches = Vec::new();
+        let mut old_index = 0;
+        let mut new_index = 0;
+        for diff in &diffs {
+            let len = diff.data().len();
+            match diff.op() {
+                Ops::Equal => {
+                    for offset in 0..len {
+                        let (old_pos, ch) = old_chars[old_index + offset];
+                        matches.push(WhitespaceInsensitiveMatch {
+                            old_pos,
+                            new_pos: new_chars[new_index + offset].0,
+                            len: ch.len_utf8(),
+                            new_index: new_index + offset,
+                        });
+                    }
+                    old_index += len;
+                    new_index += len;
+                }
+                Ops::Delete => old_index += len,
+                Ops::Insert => new_index += len,
+            }
+        }
 
-        // Phase 5: Merge and clean up
-        Ok(self.merge_attributions(new_attributions))
+        Ok(matches)
     }
 
     /// Build catalogs of deletions and insertions from the diff
@@ -812,12 +982,249 @@ impl AttributionTracker {
     }
 }
 
+impl AttributionAlgorithm for AttributionTracker {
+    fn update_attributions(
+        &self,
+        old_content: &str,
+        new_content: &str,
+        old_attributions: &[Attribution],
+        current_author: &str,
+        ts: u128,
+    ) -> Result<Vec<Attribution>, GitAiError> {
+        // Phase 1: Compute diff
+        let diffs = self.compute_diffs(old_content, new_content)?;
+
+        // Phase 2: Build deletion and insertion catalogs
+        let (deletions, insertions) = self.build_diff_catalog(&diffs);
+
+        // Phase 3: Detect move operations
+        let move_mappings = self.detect_moves(old_content, new_content, &deletions, &insertions);
+
+        // Phase 4: Transform attributions through the diff
+        let mut new_attributions = self.transform_attributions(
+            &diffs,
+            old_attributions,
+            current_author,
+            &insertions,
+            &move_mappings,
+            ts,
+        );
+
+        if self.config.ignore_whitespace {
+            let matches = self.whitespace_insensitive_matches(old_content, new_content)?;
+            new_attributions = restore_whitespace_insensitive_matches(
+                new_attributions,
+                old_attributions,
+                &matches,
+            );
+        }
+
+        // Phase 5: Merge and clean up
+        Ok(self.merge_attributions(new_attributions))
+    }
+}
+
 impl Default for AttributionTracker {
     fn default() -> Self {
         Self::new()
     }
 }
 
+/// Attribute all unattributed ranges to the given author
+fn fill_unattributed_ranges(
+    content: &str,
+    prev_attributions: &[Attribution],
+    author: &str,
+    ts: u128,
+) -> Vec<Attribution> {
+    let mut attributions = prev_attributions.to_vec();
+    let mut unattributed_char_idxs = Vec::new();
+
+    // Find all unattributed character positions
+    for i in 0..content.len() {
+        if !attributions.iter().any(|a| a.overlaps(i, i + 1)) {
+            unattributed_char_idxs.push(i);
+        }
+    }
+
+    // Sort the unattributed character indices by position
+    unattributed_char_idxs.sort();
+
+    // Group contiguous unattributed ranges
+    let mut contiguous_ranges = Vec::new();
+    if !unattributed_char_idxs.is_empty() {
+        let mut start = unattributed_char_idxs[0];
+        let mut end = start + 1;
+
+        for i in 1..unattributed_char_idxs.len() {
+            let current = unattributed_char_idxs[i];
+            if current == end {
+                // Contiguous with previous range
+                end = current + 1;
+            } else {
+                // Gap found, save current range and start new one
+                contiguous_ranges.push((start, end));
+                start = current;
+                end = current + 1;
+            }
+        }
+        // Don't forget the last range
+        contiguous_ranges.push((start, end));
+    }
+
+    // Create attributions for each contiguous unattributed range
+    for (start, end) in contiguous_ranges {
+        attributions.push(Attribution::new(start, end, author.to_string(), ts));
+    }
+
+    attributions
+}
+
+/// Replace whatever covers the `len` bytes at `new_start` with the old attributions of the
+/// same bytes at `old_start`
+fn splice_attributions(
+    attributions: &mut Vec<Attribution>,
+    new_start: usize,
+    old_attributions: &[Attribution],
+    old_start: usize,
+    len: usize,
+) {
+    let new_end = new_start + len;
+    let old_end = old_start + len;
+
+    let mut result = Vec::with_capacity(attributions.len());
+    for attr in attributions.drain(..) {
+        if !attr.overlaps(new_start, new_end) {
+            result.push(attr);
+            continue;
+        }
+        if attr.start < new_start {
+            result.push(Attribution::new(
+                attr.start,
+                new_start,
+                attr.author_id.clone(),
+                attr.ts,
+            ));
+        }
+        if attr.end > new_end {
+            result.push(Attribution::new(
+                new_end,
+                attr.end,
+                attr.author_id.clone(),
+                attr.ts,
+            ));
+        }
+    }
+
+    for old_attr in old_attributions {
+        if let Some((start, end)) = old_attr.intersection(old_start, old_end) {
+            result.push(Attribution::new(
+                new_start + (start - old_start),
+                new_start + (end - old_start),
+                old_attr.author_id.clone(),
+                old_attr.ts,
+            ));
+        }
+    }
+
+    *attributions = result;
+}
+
+/// A non-whitespace char that a whitespace-insensitive diff considers unchanged
+struct WhitespaceInsensitiveMatch {
+    old_pos: usize,
+    new_pos: usize,
+    len: usize,
+    /// Index of the char among the new content's non-whitespace chars
+    new_index: usize,
+}
+
+fn non_whitespace_chars(content: &str) -> (Vec<(usize, char)>, String) {
+    let chars: Vec<(usize, char)> = content
+        .char_indices()
+        .filter(|(_, ch)| !ch.is_whitespace())
+        .collect();
+    let stripped = chars.iter().map(|(_, ch)| *ch).collect();
+    (chars, stripped)
+}
+
+/// Give chars that only moved around whitespace back to the authors that wrote them. Whatever
+/// currently covers a matched char is replaced by the old attributions of its old position.
+fn restore_whitespace_insensitive_matches(
+    attributions: Vec<Attribution>,
+    old_attributions: &[Attribution],
+    matches: &[WhitespaceInsensitiveMatch],
+) -> Vec<Attribution> {
+    // Contiguous runs of matched chars: (start, end, first match, last match)
+    let mut runs: Vec<(usize, usize, usize, usize)> = Vec::new();
+    for (idx, m) in matches.iter().enumerate() {
+        match runs.last_mut() {
+            Some(run) if matches[run.3].new_index + 1 == m.new_index => {
+                run.1 = m.new_pos + m.len;
+                run.3 = idx;
+            }
+            _ => runs.push((m.new_pos, m.new_pos + m.len, idx, idx)),
+        }
+    }
+
+    let mut result = Vec::with_capacity(attributions.len());
+    for attr in attributions {
+        // Cut the matched runs out of every existing attribution
+        let mut start = attr.start;
+        for &(run_start, run_end, _, _) in &runs {
+            if run_end <= start || run_start >= attr.end {
+                continue;
+            }
+            if run_start > start {
+                result.push(Attribution::new(
+                    start,
+                    run_start,
+                    attr.author_id.clone(),
+                    attr.ts,
+                ));
+            }
+            start = start.max(run_end);
+        }
+        if start < attr.end {
+            result.push(Attribution::new(
+                start,
+                attr.end,
+                attr.author_id.clone(),
+                attr.ts,
+            ));
+        }
+    }
+
+    for &(_, _, first, last) in &runs {
+        for old_attr in old_attributions {
+            let mut current: Option<(usize, usize)> = None;
+            for m in &matches[first..=last] {
+                if old_attr.start <= m.old_pos && m.old_pos + m.len <= old_attr.end {
+                    let range = current.get_or_insert((m.new_pos, m.new_pos));
+                    range.1 = m.new_pos + m.len;
+                } else if let Some((start, end)) = current.take() {
+                    result.push(Attribution::new(
+                        start,
+                        end,
+                        old_attr.author_id.clone(),
+                        old_attr.ts,
+                    ));
+                }
+            }
+            if let Some((start, end)) = current {
+                result.push(Attribution::new(
+                    start,
+                    end,
+                    old_attr.author_id.clone(),
+                    old_attr.ts,
+                ));
+            }
+        }
+    }
+
+    result
+}
+
 /// Helper struct to track line boundaries in content
 struct LineBoundaries {
     /// Maps line number (1-indexed) to (start_char, end_char) exclusive end
@@ -3511,4 +3918,122 @@ export function MobileNav({
             human_block_2.len()
         );
     }
+
+    #[test]
+    fn test_ignore_whitespace_keeps_authors_of_reformatted_code() {
+        let old = "fn main() {\n    let items = vec![1, 2, 3];\n    for item in items {\n        if item > 1 { println!(\"{}\", item); }\n    }\n}\n";
+        let new = "fn main() {\n  let items = vec![1,2,3];\n  for item in items {\n    if item > 1 {\n      println!(\"{}\", item);\n    }\n  }\n}\n";
+        let old_attributions = vec![Attribution::new(0, old.len(), "ai".to_string(), TEST_TS)];
+
+        let tracker = AttributionTracker::with_config(
+            AttributionConfig::default().with_ignore_whitespace(true),
+        );
+        let attributions = tracker
+            .update_attributions(old, new, &old_attributions, "human", TEST_TS + 1)
+            .unwrap();
+
+        let line_attributions = attributions_to_line_attributions(&attributions, new);
+        assert_eq!(
+            line_attributions,
+            vec![LineAttribution::new(1, 8, "ai".to_string(), false)]
+        );
+        // Only whitespace is left to the reformatter
+        for attr in attributions.iter().filter(|a| a.author_id == "human") {
+            assert!(new[attr.start..attr.end].trim().is_empty(), "{:?}", attr);
+        }
+    }
+
+    #[test]
+    fn test_ignore_whitespace_still_attributes_real_changes() {
+        let old = "let total = a + b;\n";
+        let new = "let total = a  +  b + c;\n";
+        let old_attributions = vec![Attribution::new(0, old.len(), "ai".to_string(), TEST_TS)];
+
+        let tracker = AttributionTracker::with_config(
+            AttributionConfig::default().with_ignore_whitespace(true),
+        );
+        let attributions = tracker
+            .update_attributions(old, new, &old_attributions, "human", TEST_TS + 1)
+            .unwrap();
+
+        let c_pos = new.find('c').unwrap();
+        assert_range_owned_by(&attributions, c_pos, c_pos + 1, "human");
+        assert_range_owned_by(&attributions, 0, 5, "ai");
+    }
+
+    fn move_between_files(config: AttributionConfig, block: &str) -> (String, Vec<Attribution>) {
+        let old_a = format!("fn keep() {{}}\n{}", block);
+        let new_a = "fn keep() {}\n".to_string();
+        let old_b = "fn other() {}\n".to_string();
+        let new_b = format!("fn other() {{}}\n    {}", block.replace('\n', "\n    "));
+        let new_b = new_b.trim_end_matches(' ').to_string();
+        let old_a_attributions = vec![
+            Attribution::new(0, new_a.len(), "human".to_string(), TEST_TS),
+            Attribution::new(new_a.len(), old_a.len(), "ai".to_string(), TEST_TS),
+        ];
+        let old_b_attributions = vec![Attribution::new(
+            0,
+            old_b.len(),
+            "human".to_string(),
+            TEST_TS,
+        )];
+
+        let tracker = AttributionTracker::with_config(config);
+        let mut attributions = vec![
+            tracker
+                .update_attributions(&old_a, &new_a, &old_a_attributions, "human", TEST_TS + 1)
+                .unwrap(),
+            tracker
+                .update_attributions(&old_b, &new_b, &old_b_attributions, "human", TEST_TS + 1)
+                .unwrap(),
+        ];
+        let changes = [
+            FileChange {
+                previous_content: &old_a,
+                previous_attributions: &old_a_attributions,
+                content: &new_a,
+            },
+            FileChange {
+                previous_content: &old_b,
+                previous_attributions: &old_b_attributions,
+                content: &new_b,
+            },
+        ];
+        tracker.carry_moves_between_files(&changes, &mut attributions);
+        (new_b, attributions.remove(1))
+    }
+
+    const MOVED_BLOCK: &str =
+        "fn helper(value: u32) -> u32 {\n    let doubled = value * 2;\n    doubled + 1\n}\n";
+
+    #[test]
+    fn test_block_moved_between_files_keeps_its_author() {
+        let (new_b, attributions) = move_between_files(AttributionConfig::default(), MOVED_BLOCK);
+
+        let line_attributions = attributions_to_line_attributions(&attributions, &new_b);
+        for line in 2..=5 {
+            assert!(
+                line_attributions
+                    .iter()
+                    .any(|la| la.author_id == "ai" && la.start_line <= line && line <= la.end_line),
+                "line {} should stay AI: {:?}",
+                line,
+                line_attributions
+            );
+        }
+        let first_line_end = new_b.find('\n').unwrap();
+        assert_range_owned_by(&attributions, 0, first_line_end, "human");
+    }
+
+    #[test]
+    fn test_move_detection_can_be_disabled_or_skips_trivial_blocks() {
+        let (_, attributions) = move_between_files(
+            AttributionConfig::default().with_detect_moves(false),
+            MOVED_BLOCK,
+        );
+        assert!(attributions.iter().all(|attr| attr.author_id == "human"));
+
+        let (_, attributions) = move_between_files(AttributionConfig::default(), "}\n}\n}\n");
+        assert!(attributions.iter().all(|attr| attr.author_id == "human"));
+    }
 }
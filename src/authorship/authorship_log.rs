@@ -1,5 +1,5 @@
-use crate::authorship::transcript::Message;
-use crate::authorship::working_log::AgentId;
+use crate::authorship::transcript::{Message, TranscriptSummary};
+use crate::authorship::working_log::{AgentId, EnvironmentFingerprint};
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
@@ -198,4 +198,116 @@ pub struct PromptRecord {
     pub accepted_lines: u32,
     #[serde(default)]
     pub overriden_lines: u32,
+    /// Set when `messages` was cut down to a summary before being written to the note
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<TranscriptSummary>,
+    /// Distinct machines and tool versions this session's checkpoints were made with
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub environments: Vec<EnvironmentFingerprint>,
+    /// When the session's last checkpoint before the commit was made (seconds since the epoch),
+    /// for measuring how long its code waited to be committed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generated_at: Option<u64>,
+}
+
+impl PromptRecord {
+    /// Add the environment a checkpoint of this session was made in, once per distinct one
+    pub fn add_environment(&mut self, environment: Option<&EnvironmentFingerprint>) {
+        if let Some(environment) = environment
+            && !self.environments.contains(environment)
+        {
+            self.environments.push(environment.clone());
+        }
+    }
+
+    /// Add the time a checkpoint of this session was made, keeping the latest
+    pub fn add_generation_time(&mut self, timestamp: u64) {
+        self.generated_at = self.generated_at.max(Some(timestamp));
+    }
+}
+
+/// Who wrote the commit message itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitMessageSource {
+    Human,
+    Ai,
+}
+
+/// Commit message authorship recorded in the note metadata. `agent_id` is set when the
+/// message was written by an AI agent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitMessageAuthorship {
+    pub source: CommitMessageSource,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_id: Option<AgentId>,
+}
+
+/// Where a commit imported by `git am` came from: its patch mail and the series it was part of
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchProvenance {
+    pub subject: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message_id: Option<String>,
+    /// The message the patch replied to, usually the series' cover letter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_reply_to: Option<String>,
+    /// Position in the series, e.g. "2/5"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub position: Option<String>,
+    /// The tool the patch's `X-AI-Generated` header named
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ai_generated: Option<String>,
+}
+
+/// How much an AI attribution can be trusted. Notes written at commit time from checkpoints
+/// are exact; notes carried through rebases and cherry-picks are reconstructed by replaying
+/// diffs; notes built from merged or leftover state (squash merges, backfill) are heuristic.
+/// Ordered from most to least certain.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttributionConfidence {
+    #[default]
+    Exact,
+    Reconstructed,
+    Heuristic,
+}
+
+impl AttributionConfidence {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttributionConfidence::Exact => "exact",
+            AttributionConfidence::Reconstructed => "reconstructed",
+            AttributionConfidence::Heuristic => "heuristic",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "exact" => Some(AttributionConfidence::Exact),
+            "reconstructed" => Some(AttributionConfidence::Reconstructed),
+            "heuristic" => Some(AttributionConfidence::Heuristic),
+            _ => None,
+        }
+    }
+
+    /// Suffix appended to the agent name in blame output ("" for exact)
+    pub fn blame_marker(&self) -> &'static str {
+        match self {
+            AttributionConfidence::Exact => "",
+            AttributionConfidence::Reconstructed => "~",
+            AttributionConfidence::Heuristic => "?",
+        }
+    }
+}
+
+impl CommitMessageAuthorship {
+    /// Short label for display, e.g. "AI (cursor)" or "human"
+    pub fn label(&self) -> String {
+        match (&self.source, &self.agent_id) {
+            (CommitMessageSource::Ai, Some(agent_id)) => format!("AI ({})", agent_id.tool),
+            (CommitMessageSource::Ai, None) => "AI".to_string(),
+            (CommitMessageSource::Human, _) => "human".to_string(),
+        }
+    }
 }
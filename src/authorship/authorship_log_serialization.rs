@@ -1,16 +1,66 @@
-use crate::authorship::authorship_log::{Author, LineRange, PromptRecord};
+use crate::authorship::authorship_log::{
+    AttributionConfidence, Author, CommitMessageAuthorship, LineRange, PatchProvenance,
+    PromptRecord,
+};
+use crate::authorship::prompt_lineage::PromptLineage;
+use crate::authorship::transcript::{redact_transcript, summarize_transcript};
 use crate::authorship::working_log::CheckpointKind;
-use crate::config;
+use crate::config::TranscriptStore;
 use crate::git::repository::Repository;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fmt;
 use std::io::{BufRead, Write};
 use std::time::{SystemTime, UNIX_EPOCH};
 
-/// Authorship log format version identifier
-pub const AUTHORSHIP_LOG_VERSION: &str = "authorship/3.0.0";
+/// Authorship log format version identifier. 3.1.0 added the summary, environments,
+/// generated_at, confidence, lineage, empty_commit, patch provenance and commit_message fields.
+pub const AUTHORSHIP_LOG_VERSION: &str = "authorship/3.1.0";
+
+/// How a note's schema version relates to `AUTHORSHIP_LOG_VERSION`. Versions within a major
+/// only ever add fields, which older readers ignore; a new major may change the layout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SchemaCompatibility {
+    /// Same major, same or older minor: everything in the note is understood
+    Supported,
+    /// Same major, newer minor: parses, but fields this version doesn't know are dropped
+    NewerMinor,
+    /// Newer major: read on a best-effort basis, upgrading git-ai is the fix
+    NewerMajor,
+    /// Older major or not a version git-ai ever wrote
+    Unsupported,
+}
+
+/// Split `authorship/<major>.<minor>.<patch>` into its numbers
+pub fn parse_schema_version(version: &str) -> Option<(u32, u32, u32)> {
+    let numbers = version.strip_prefix("authorship/")?;
+    let mut parts = numbers.split('.').map(|part| part.parse::<u32>().ok());
+    let version = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() {
+        return None;
+    }
+    Some(version)
+}
+
+pub fn schema_compatibility(version: &str) -> SchemaCompatibility {
+    let (Some((major, minor, patch)), Some((our_major, our_minor, our_patch))) = (
+        parse_schema_version(version),
+        parse_schema_version(AUTHORSHIP_LOG_VERSION),
+    ) else {
+        return SchemaCompatibility::Unsupported;
+    };
+    if major > our_major {
+        SchemaCompatibility::NewerMajor
+    } else if major < our_major {
+        SchemaCompatibility::Unsupported
+    } else if (minor, patch) > (our_minor, our_patch) {
+        SchemaCompatibility::NewerMinor
+    } else {
+        SchemaCompatibility::Supported
+    }
+}
 
 /// Metadata section that goes below the divider as JSON
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -18,6 +68,27 @@ pub struct AuthorshipMetadata {
     pub schema_version: String,
     pub base_commit_sha: String,
     pub prompts: BTreeMap<String, PromptRecord>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_message: Option<CommitMessageAuthorship>,
+    /// Attributions that aren't exact, keyed by file then prompt hash. Entries missing here are
+    /// exact, so notes written at commit time never carry this section.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub confidence: BTreeMap<String, BTreeMap<String, AttributionConfidence>>,
+    /// Earlier prompts whose lines each prompt rewrote, keyed by file then prompt hash, most
+    /// recent first. Lets a block be traced back through the prompts that shaped it.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lineage: BTreeMap<String, PromptLineage>,
+    /// The commit changed no files, so there is nothing to attest. Tells an empty commit
+    /// apart from one whose note was lost.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub empty_commit: bool,
+    /// The patch mail the commit was imported from with `git am`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub patch_series: Option<PatchProvenance>,
+}
+
+pub(crate) fn is_false(value: &bool) -> bool {
+    !*value
 }
 
 impl AuthorshipMetadata {
@@ -26,10 +97,21 @@ impl AuthorshipMetadata {
             schema_version: AUTHORSHIP_LOG_VERSION.to_string(),
             base_commit_sha: String::new(),
             prompts: BTreeMap::new(),
+            commit_message: None,
+            confidence: BTreeMap::new(),
+            lineage: BTreeMap::new(),
+            empty_commit: false,
+            patch_series: None,
         }
     }
 }
 
+impl AuthorshipMetadata {
+    pub fn schema_compatibility(&self) -> SchemaCompatibility {
+        schema_compatibility(&self.schema_version)
+    }
+}
+
 impl Default for AuthorshipMetadata {
     fn default() -> Self {
         Self::new()
@@ -125,6 +207,15 @@ impl AuthorshipLog {
         }
     }
 
+    /// The note of a commit that changed no files: no attestations or prompts, only the
+    /// empty-commit marker
+    pub fn for_empty_commit(base_commit_sha: &str) -> Self {
+        let mut log = Self::new();
+        log.metadata.base_commit_sha = base_commit_sha.to_string();
+        log.metadata.empty_commit = true;
+        log
+    }
+
     /// Filter authorship log to keep only committed line ranges
     ///
     /// This keeps only attributions for lines that were actually committed, removing everything else.
@@ -134,44 +225,62 @@ impl AuthorshipLog {
     /// * `committed_hunks` - Map of file paths to their committed line ranges
     pub fn filter_to_committed_lines(&mut self, committed_hunks: &HashMap<String, Vec<LineRange>>) {
         for file_attestation in &mut self.attestations {
-            if let Some(committed_ranges) = committed_hunks.get(&file_attestation.file_path) {
-                // For each attestation entry, keep only the lines that were committed
-                for entry in &mut file_attestation.entries {
-                    // Expand entry's line ranges to individual lines
-                    let mut entry_lines: Vec<u32> = Vec::new();
-                    for range in &entry.line_ranges {
-                        entry_lines.extend(range.expand());
-                    }
+            keep_committed_lines(
+                file_attestation,
+                committed_hunks.get(&file_attestation.file_path),
+            );
+        }
 
-                    // Keep only lines that are in committed ranges
-                    let mut committed_lines: Vec<u32> = Vec::new();
-                    for line in entry_lines {
-                        if committed_ranges.iter().any(|range| range.contains(line)) {
-                            committed_lines.push(line);
-                        }
-                    }
+        // Remove file attestations that have no entries left
+        self.attestations.retain(|file| !file.entries.is_empty());
+        self.retain_attested_lineage();
+    }
 
-                    if !committed_lines.is_empty() {
-                        committed_lines.sort_unstable();
-                        committed_lines.dedup();
-                        entry.line_ranges = LineRange::compress_lines(&committed_lines);
-                    } else {
-                        entry.line_ranges.clear();
-                    }
-                }
+    /// [`Self::filter_to_committed_lines`] for the attestations of `files` alone, leaving the
+    /// rest of the log as it is. Lineage is only pruned by [`Self::retain_attested_lineage`],
+    /// once every file has been filtered.
+    pub fn filter_files_to_committed_lines(
+        &mut self,
+        files: &HashSet<String>,
+        committed_hunks: &HashMap<String, Vec<LineRange>>,
+    ) {
+        for file_attestation in &mut self.attestations {
+            if files.contains(&file_attestation.file_path) {
+                keep_committed_lines(
+                    file_attestation,
+                    committed_hunks.get(&file_attestation.file_path),
+                );
+            }
+        }
+        self.attestations.retain(|file| !file.entries.is_empty());
+    }
 
-                // Remove entries that have no line ranges left
-                file_attestation
-                    .entries
-                    .retain(|entry| !entry.line_ranges.is_empty());
-            } else {
-                // No committed lines for this file, remove all entries
-                file_attestation.entries.clear();
+    /// Replace each prompt's transcript with its summary: the opening request, the final
+    /// reply, message counts and the files the prompt's lines landed in. Prompts that are
+    /// already summarized are left alone.
+    pub fn summarize_transcripts(&mut self) {
+        let mut edited_files: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+        for file_attestation in &self.attestations {
+            for entry in &file_attestation.entries {
+                edited_files
+                    .entry(entry.hash.as_str())
+                    .or_default()
+                    .insert(file_attestation.file_path.as_str());
             }
         }
 
-        // Remove file attestations that have no entries left
-        self.attestations.retain(|file| !file.entries.is_empty());
+        for (hash, prompt) in self.metadata.prompts.iter_mut() {
+            if prompt.summary.is_some() {
+                continue;
+            }
+            let files = edited_files
+                .get(hash.as_str())
+                .map(|files| files.iter().map(|file| file.to_string()).collect())
+                .unwrap_or_default();
+            let (messages, summary) = summarize_transcript(&prompt.messages, files);
+            prompt.messages = messages;
+            prompt.summary = Some(summary);
+        }
     }
 
     /// Merge overlapping and adjacent line ranges
@@ -279,8 +388,14 @@ impl AuthorshipLog {
                             total_deletions: 0,
                             accepted_lines: 0,
                             overriden_lines: 0,
+                            summary: None,
+                            environments: Vec::new(),
+                            generated_at: None,
                         });
 
+                entry.add_environment(checkpoint.environment.as_ref());
+                entry.add_generation_time(checkpoint.timestamp);
+
                 // Update transcript if provided and longer than existing
                 if let Some(transcript) = &checkpoint.transcript {
                     if entry.messages.len() < transcript.messages().len() {
@@ -303,6 +418,14 @@ impl AuthorshipLog {
 
         // Process each file entry in checkpoint
         for entry in &checkpoint.entries {
+            if entry.lineage.is_empty() {
+                self.metadata.lineage.remove(&entry.file);
+            } else {
+                self.metadata
+                    .lineage
+                    .insert(entry.file.clone(), entry.lineage.clone());
+            }
+
             // REPLACE all attestation entries for this file (since checkpoint has complete state)
             let file_attestation = self.get_or_create_file(&entry.file);
             file_attestation.entries.clear();
@@ -411,6 +534,8 @@ impl AuthorshipLog {
             prompt_record.accepted_lines = *session_accepted_lines.get(session_id).unwrap_or(&0);
             // overriden_lines is calculated and accumulated in apply_checkpoint, don't reset it here
         }
+
+        self.retain_attested_lineage();
     }
 
     /// Convert from working log checkpoints to authorship log
@@ -451,16 +576,125 @@ impl AuthorshipLog {
         // Finalize the log (cleanup, consolidate, metrics)
         authorship_log.finalize(&session_additions, &session_deletions);
 
-        // If prompts should be ignored, clear the transcripts but keep the prompt records
-        let ignore_prompts: bool = config::Config::get().get_ignore_prompts();
-        if ignore_prompts {
-            // Clear transcripts but keep the prompt records
-            for prompt_record in authorship_log.metadata.prompts.values_mut() {
-                prompt_record.messages.clear();
+        authorship_log
+    }
+
+    /// Drop every transcript but keep the prompt records, for repos that ignore prompts
+    pub fn clear_transcripts(&mut self) {
+        for prompt_record in self.metadata.prompts.values_mut() {
+            prompt_record.messages.clear();
+        }
+    }
+
+    /// Cut every prompt's transcript down to what `store` allows. Prompt hashes, agents and
+    /// attestations are left alone, so attribution is unchanged.
+    pub fn redact_transcripts(&mut self, store: TranscriptStore) {
+        for prompt_record in self.metadata.prompts.values_mut() {
+            let messages = std::mem::take(&mut prompt_record.messages);
+            prompt_record.messages = redact_transcript(messages, store);
+        }
+    }
+
+    /// Confidence of the attribution for `hash` in `file`
+    pub fn confidence_for(&self, file: &str, hash: &str) -> AttributionConfidence {
+        self.metadata
+            .confidence
+            .get(file)
+            .and_then(|hashes| hashes.get(hash))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Lower the confidence of every attestation entry to at most `confidence`.
+    /// Entries that are already less certain keep their level.
+    pub fn mark_confidence(&mut self, confidence: AttributionConfidence) {
+        let entries: Vec<(String, String)> = self
+            .attestations
+            .iter()
+            .flat_map(|attestation| {
+                attestation
+                    .entries
+                    .iter()
+                    .map(|entry| (attestation.file_path.clone(), entry.hash.clone()))
+            })
+            .collect();
+        for (file, hash) in entries {
+            self.lower_confidence(&file, &hash, confidence);
+        }
+    }
+
+    /// Carry over non-exact confidence levels from the notes this log was rebuilt from, for
+    /// entries that still exist in this log
+    pub fn inherit_confidence(&mut self, source: &AuthorshipLog) {
+        for (file, hashes) in &source.metadata.confidence {
+            for (hash, confidence) in hashes {
+                let present = self
+                    .attestations
+                    .iter()
+                    .filter(|attestation| &attestation.file_path == file)
+                    .any(|attestation| attestation.entries.iter().any(|e| &e.hash == hash));
+                if present {
+                    self.lower_confidence(file, hash, *confidence);
+                }
             }
         }
+    }
 
-        authorship_log
+    /// Earlier prompts whose lines the prompt `hash` rewrote in `file`, most recent first
+    pub fn lineage_for(&self, file: &str, hash: &str) -> &[String] {
+        self.metadata
+            .lineage
+            .get(file)
+            .and_then(|lineage| lineage.get(hash))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Carry over lineage from the notes or working log this log was rebuilt from, for entries
+    /// that still exist in this log and don't have one of their own
+    pub fn inherit_lineage(&mut self, source: &BTreeMap<String, PromptLineage>) {
+        for (file, lineage) in source {
+            for (hash, chain) in lineage {
+                let present = self
+                    .attestations
+                    .iter()
+                    .filter(|attestation| &attestation.file_path == file)
+                    .any(|attestation| attestation.entries.iter().any(|e| &e.hash == hash));
+                if present {
+                    self.metadata
+                        .lineage
+                        .entry(file.clone())
+                        .or_default()
+                        .entry(hash.clone())
+                        .or_insert_with(|| chain.clone());
+                }
+            }
+        }
+    }
+
+    /// Drop lineage of prompts that no longer own any line of the file
+    pub fn retain_attested_lineage(&mut self) {
+        let attestations = &self.attestations;
+        self.metadata.lineage.retain(|file, lineage| {
+            let Some(attestation) = attestations.iter().find(|a| &a.file_path == file) else {
+                return false;
+            };
+            lineage.retain(|hash, _| attestation.entries.iter().any(|e| &e.hash == hash));
+            !lineage.is_empty()
+        });
+    }
+
+    fn lower_confidence(&mut self, file: &str, hash: &str, confidence: AttributionConfidence) {
+        if confidence == AttributionConfidence::Exact {
+            return;
+        }
+        let current = self
+            .metadata
+            .confidence
+            .entry(file.to_string())
+            .or_default()
+            .entry(hash.to_string())
+            .or_default();
+        *current = (*current).max(confidence);
     }
 
     pub fn get_or_create_file(&mut self, file: &str) -> &mut FileAttestation {
@@ -547,6 +781,22 @@ impl AuthorshipLog {
         })
     }
 
+    /// The schema version a note declares, read without parsing the rest of it, so notes
+    /// this version can't deserialize still report what wrote them
+    pub fn schema_version_of(content: &str) -> Option<String> {
+        let json_start = content.lines().position(|line| line == "---")? + 1;
+        let json_content = content
+            .lines()
+            .skip(json_start)
+            .collect::<Vec<_>>()
+            .join("\n");
+        let metadata: serde_json::Value = serde_json::from_str(&json_content).ok()?;
+        metadata
+            .get("schema_version")?
+            .as_str()
+            .map(|version| version.to_string())
+    }
+
     /// Read from a reader in the new format
     pub fn _deserialize_from_reader<R: BufRead>(
         reader: R,
@@ -780,6 +1030,47 @@ impl AuthorshipLog {
     }
 }
 
+/// Keep only the lines of `file_attestation` in `committed_ranges`; with none, the file
+/// wasn't committed and loses every entry
+fn keep_committed_lines(
+    file_attestation: &mut FileAttestation,
+    committed_ranges: Option<&Vec<LineRange>>,
+) {
+    let Some(committed_ranges) = committed_ranges else {
+        file_attestation.entries.clear();
+        return;
+    };
+    // For each attestation entry, keep only the lines that were committed
+    for entry in &mut file_attestation.entries {
+        // Expand entry's line ranges to individual lines
+        let mut entry_lines: Vec<u32> = Vec::new();
+        for range in &entry.line_ranges {
+            entry_lines.extend(range.expand());
+        }
+
+        // Keep only lines that are in committed ranges
+        let mut committed_lines: Vec<u32> = Vec::new();
+        for line in entry_lines {
+            if committed_ranges.iter().any(|range| range.contains(line)) {
+                committed_lines.push(line);
+            }
+        }
+
+        if !committed_lines.is_empty() {
+            committed_lines.sort_unstable();
+            committed_lines.dedup();
+            entry.line_ranges = LineRange::compress_lines(&committed_lines);
+        } else {
+            entry.line_ranges.clear();
+        }
+    }
+
+    // Remove entries that have no line ranges left
+    file_attestation
+        .entries
+        .retain(|entry| !entry.line_ranges.is_empty());
+}
+
 /// Convert line numbers to working log Line format (Single/Range)
 fn compress_lines_to_working_log_format(
     lines: &[u32],
@@ -1110,6 +1401,9 @@ mod tests {
                 total_deletions: 0,
                 accepted_lines: 0,
                 overriden_lines: 0,
+                summary: None,
+                environments: Vec::new(),
+                generated_at: None,
             },
         );
 
@@ -1176,6 +1470,9 @@ mod tests {
                 total_deletions: 0,
                 accepted_lines: 0,
                 overriden_lines: 0,
+                summary: None,
+                environments: Vec::new(),
+                generated_at: None,
             },
         );
 
@@ -1224,6 +1521,9 @@ mod tests {
                 total_deletions: 0,
                 accepted_lines: 0,
                 overriden_lines: 0,
+                summary: None,
+                environments: Vec::new(),
+                generated_at: None,
             },
         );
 
@@ -1241,6 +1541,126 @@ mod tests {
         assert_eq!(deserialized.attestations.len(), 0);
     }
 
+    #[test]
+    fn test_summarize_transcripts() {
+        use crate::authorship::transcript::Message;
+
+        let mut log = AuthorshipLog::new();
+        let agent_id = crate::authorship::working_log::AgentId {
+            tool: "cursor".to_string(),
+            id: "session_123".to_string(),
+            model: "claude-3-sonnet".to_string(),
+        };
+        let prompt_hash = generate_short_hash(&agent_id.id, &agent_id.tool);
+        log.metadata.prompts.insert(
+            prompt_hash.clone(),
+            crate::authorship::authorship_log::PromptRecord {
+                agent_id,
+                human_author: None,
+                messages: vec![
+                    Message::user("add a parser".to_string(), None),
+                    Message::assistant("Reading the module first".to_string(), None),
+                    Message::tool_use("read_file".to_string(), serde_json::json!({})),
+                    Message::user("also handle errors".to_string(), None),
+                    Message::assistant("x".repeat(5000), None),
+                ],
+                total_additions: 0,
+                total_deletions: 0,
+                accepted_lines: 0,
+                overriden_lines: 0,
+                summary: None,
+                environments: Vec::new(),
+                generated_at: None,
+            },
+        );
+        for path in ["src/parser.rs", "src/error.rs"] {
+            let mut file = FileAttestation::new(path.to_string());
+            file.add_entry(AttestationEntry::new(
+                prompt_hash.clone(),
+                vec![LineRange::Range(1, 3)],
+            ));
+            log.attestations.push(file);
+        }
+
+        log.summarize_transcripts();
+
+        let prompt = &log.metadata.prompts[&prompt_hash];
+        assert_eq!(prompt.messages.len(), 2);
+        assert_eq!(
+            prompt.messages[0],
+            Message::user("add a parser".to_string(), None)
+        );
+        match &prompt.messages[1] {
+            Message::Assistant { text, .. } => assert!(text.chars().count() < 5000),
+            other => panic!("expected the last assistant message, got {:?}", other),
+        }
+        let summary = prompt.summary.as_ref().unwrap();
+        assert_eq!(summary.message_count, 5);
+        assert_eq!(summary.user_messages, 2);
+        assert_eq!(summary.assistant_messages, 2);
+        assert_eq!(summary.tool_uses, 1);
+        assert_eq!(summary.edited_files, vec!["src/error.rs", "src/parser.rs"]);
+
+        // Summaries survive the note roundtrip and aren't summarized again
+        let serialized = log.serialize_to_string().unwrap();
+        let mut deserialized = AuthorshipLog::deserialize_from_string(&serialized).unwrap();
+        deserialized.summarize_transcripts();
+        assert_eq!(deserialized.metadata.prompts[&prompt_hash], *prompt);
+    }
+
+    #[test]
+    fn test_redact_transcripts() {
+        use crate::authorship::transcript::Message;
+
+        let mut log = AuthorshipLog::new();
+        let agent_id = crate::authorship::working_log::AgentId {
+            tool: "cursor".to_string(),
+            id: "session_123".to_string(),
+            model: "claude-3-sonnet".to_string(),
+        };
+        let prompt_hash = generate_short_hash(&agent_id.id, &agent_id.tool);
+        log.metadata.prompts.insert(
+            prompt_hash.clone(),
+            crate::authorship::authorship_log::PromptRecord {
+                agent_id,
+                human_author: None,
+                messages: vec![
+                    Message::user("use the key sk-123".to_string(), None),
+                    Message::tool_use("read_file".to_string(), serde_json::json!({"p": 1})),
+                    Message::assistant("done".to_string(), None),
+                ],
+                total_additions: 0,
+                total_deletions: 0,
+                accepted_lines: 0,
+                overriden_lines: 0,
+                summary: None,
+                environments: Vec::new(),
+                generated_at: None,
+            },
+        );
+
+        log.redact_transcripts(TranscriptStore::MessagesOnly);
+        assert_eq!(log.metadata.prompts[&prompt_hash].messages.len(), 2);
+
+        log.redact_transcripts(TranscriptStore::HashesOnly);
+        let hashed = log.metadata.prompts[&prompt_hash].messages.clone();
+        match &hashed[0] {
+            Message::User { text, .. } => {
+                assert!(text.starts_with("sha256:"));
+                assert!(!text.contains("sk-123"));
+            }
+            other => panic!("expected a user message, got {:?}", other),
+        }
+
+        // Hashing again leaves the digests as they are
+        log.redact_transcripts(TranscriptStore::HashesOnly);
+        assert_eq!(log.metadata.prompts[&prompt_hash].messages, hashed);
+
+        log.redact_transcripts(TranscriptStore::None);
+        assert!(log.metadata.prompts[&prompt_hash].messages.is_empty());
+        assert!(log.metadata.prompts.contains_key(&prompt_hash));
+    }
+
     #[test]
     fn test_remove_line_ranges_complete_removal() {
         let mut entry =
@@ -1400,6 +1820,9 @@ mod tests {
                 total_deletions: 3,
                 accepted_lines: 11,
                 overriden_lines: 0,
+                summary: None,
+                environments: Vec::new(),
+                generated_at: None,
             },
         );
 
@@ -1570,6 +1993,9 @@ mod tests {
                 total_deletions: 0,
                 accepted_lines: 10,
                 overriden_lines: 0,
+                summary: None,
+                environments: Vec::new(),
+                generated_at: None,
             },
         );
 
@@ -1593,6 +2019,9 @@ mod tests {
                 total_deletions: 0,
                 accepted_lines: 20,
                 overriden_lines: 0,
+                summary: None,
+                environments: Vec::new(),
+                generated_at: None,
             },
         );
 
@@ -1672,4 +2101,146 @@ mod tests {
             .sum();
         assert_eq!(lines_session2, 20);
     }
+
+    #[test]
+    fn test_attribution_confidence_marking_and_round_trip() {
+        let mut log = AuthorshipLog::new();
+        log.get_or_create_file("src/a.rs")
+            .add_entry(AttestationEntry::new(
+                "abcd123".to_string(),
+                vec![LineRange::Range(1, 3)],
+            ));
+        log.get_or_create_file("src/b.rs")
+            .add_entry(AttestationEntry::new(
+                "efab456".to_string(),
+                vec![LineRange::Single(7)],
+            ));
+
+        // Exact logs don't write a confidence section, so existing notes are unchanged
+        let exact = log.serialize_to_string().unwrap();
+        assert!(!exact.contains("confidence"));
+        assert_eq!(
+            log.confidence_for("src/a.rs", "abcd123"),
+            AttributionConfidence::Exact
+        );
+
+        let mut source = AuthorshipLog::new();
+        source
+            .metadata
+            .confidence
+            .entry("src/b.rs".to_string())
+            .or_default()
+            .insert("efab456".to_string(), AttributionConfidence::Heuristic);
+        source
+            .metadata
+            .confidence
+            .entry("src/gone.rs".to_string())
+            .or_default()
+            .insert("abcd123".to_string(), AttributionConfidence::Heuristic);
+
+        log.inherit_confidence(&source);
+        log.mark_confidence(AttributionConfidence::Reconstructed);
+
+        // Marking never makes an entry look more certain than it was
+        assert_eq!(
+            log.confidence_for("src/a.rs", "abcd123"),
+            AttributionConfidence::Reconstructed
+        );
+        assert_eq!(
+            log.confidence_for("src/b.rs", "efab456"),
+            AttributionConfidence::Heuristic
+        );
+        assert!(!log.metadata.confidence.contains_key("src/gone.rs"));
+
+        let round_tripped =
+            AuthorshipLog::deserialize_from_string(&log.serialize_to_string().unwrap()).unwrap();
+        assert_eq!(round_tripped.metadata.confidence, log.metadata.confidence);
+    }
+
+    #[test]
+    fn test_schema_compatibility() {
+        assert_eq!(parse_schema_version("authorship/3.1.2"), Some((3, 1, 2)));
+        assert_eq!(parse_schema_version("authorship/3.1"), None);
+        assert_eq!(parse_schema_version("3.0.0"), None);
+
+        assert_eq!(
+            schema_compatibility(AUTHORSHIP_LOG_VERSION),
+            SchemaCompatibility::Supported
+        );
+        assert_eq!(
+            schema_compatibility("authorship/3.4.0"),
+            SchemaCompatibility::NewerMinor
+        );
+        assert_eq!(
+            schema_compatibility("authorship/4.0.0"),
+            SchemaCompatibility::NewerMajor
+        );
+        assert_eq!(
+            schema_compatibility("authorship/2.0.0"),
+            SchemaCompatibility::Unsupported
+        );
+        assert_eq!(
+            schema_compatibility("garbage"),
+            SchemaCompatibility::Unsupported
+        );
+    }
+
+    #[test]
+    fn test_current_version_note_round_trips() {
+        let content = r#"src/a.rs
+  abcd123 1-2
+---
+{
+  "schema_version": "authorship/3.1.0",
+  "base_commit_sha": "",
+  "prompts": {},
+  "empty_commit": true
+}"#;
+        let log = AuthorshipLog::deserialize_from_string(content).unwrap();
+        assert_eq!(log.metadata.schema_version, AUTHORSHIP_LOG_VERSION);
+        assert_eq!(
+            log.metadata.schema_compatibility(),
+            SchemaCompatibility::Supported
+        );
+        assert!(log.metadata.empty_commit);
+
+        let round_tripped =
+            AuthorshipLog::deserialize_from_string(&log.serialize_to_string().unwrap()).unwrap();
+        assert_eq!(
+            round_tripped.metadata.schema_version,
+            AUTHORSHIP_LOG_VERSION
+        );
+        assert_eq!(round_tripped.attestations.len(), 1);
+        assert!(round_tripped.metadata.empty_commit);
+
+        // Notes written before the bump are still fully understood
+        assert_eq!(
+            schema_compatibility("authorship/3.0.0"),
+            SchemaCompatibility::Supported
+        );
+    }
+
+    #[test]
+    fn test_newer_minor_note_parses_ignoring_unknown_fields() {
+        let content = r#"src/a.rs
+  abcd123 1-2
+---
+{
+  "schema_version": "authorship/3.7.0",
+  "base_commit_sha": "",
+  "prompts": {},
+  "field_from_the_future": {"nested": [1, 2, 3]}
+}"#;
+        let log = AuthorshipLog::deserialize_from_string(content).unwrap();
+        assert_eq!(log.attestations.len(), 1);
+        assert_eq!(
+            log.metadata.schema_compatibility(),
+            SchemaCompatibility::NewerMinor
+        );
+        assert_eq!(
+            AuthorshipLog::schema_version_of(content).as_deref(),
+            Some("authorship/3.7.0")
+        );
+        assert_eq!(AuthorshipLog::schema_version_of("no divider"), None);
+    }
 }
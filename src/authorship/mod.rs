@@ -1,11 +1,15 @@
+pub mod anchored_attribution;
+pub mod attribution_loss;
 pub mod attribution_tracker;
 pub mod authorship_log;
 pub mod authorship_log_serialization;
 pub mod move_detection;
 pub mod post_commit;
 pub mod pre_commit;
+pub mod prompt_lineage;
 pub mod range_authorship;
 pub mod rebase_authorship;
+pub mod sampling;
 pub mod stats;
 pub mod transcript;
 pub mod virtual_attribution;
@@ -1,12 +1,24 @@
-use crate::authorship::authorship_log::LineRange;
-use crate::authorship::authorship_log_serialization::AuthorshipLog;
+use crate::authorship::authorship_log::{LineRange, PromptRecord};
+use crate::authorship::authorship_log_serialization::{
+    AttestationEntry, AuthorshipLog, generate_short_hash,
+};
 use crate::authorship::stats::{stats_for_commit_stats, write_stats_to_terminal};
 use crate::authorship::working_log::Checkpoint;
+use crate::cancellation::{CleanupScope, Compensation};
+use crate::commands::checkpoint::directory_tool_agent_id;
 use crate::commands::checkpoint_agent::agent_presets::CursorPreset;
+use crate::config::{Config, DirectoryPolicy, TranscriptMode};
 use crate::error::GitAiError;
+use crate::git::diff_parser::{DiffHunk, DiffSide, map_line};
 use crate::git::refs::notes_add;
 use crate::git::repository::Repository;
-use std::collections::{HashMap, HashSet};
+use crate::utils::debug_log;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// How many files each post-commit `git diff` covers. A batch's diffs are attributed and
+/// dropped before the next batch is diffed, so a commit touching thousands of files doesn't
+/// hold all of them at once.
+const POST_COMMIT_BATCH_FILES: usize = 256;
 
 pub fn post_commit(
     repo: &Repository,
@@ -23,18 +35,13 @@ pub fn post_commit(
     let repo_storage = &repo.storage;
     let working_log = repo_storage.working_log_for_base_commit(&parent_sha);
 
-    // Pull all working log entries from the parent commit
-
-    let parent_working_log = working_log.read_all_checkpoints()?;
-
-    // debug_log(&format!(
-    //     "edited files: {:?}",
-    //     parent_working_log.edited_files
-    // ));
+    // Pull all working log entries from the parent commit. They are read once here and
+    // reused below when deciding what carries over to the next working log.
+    let parent_checkpoints = working_log.read_all_checkpoints()?;
 
     // Filter out untracked files from the working log
     let mut filtered_working_log =
-        filter_untracked_files(repo, &parent_working_log, &commit_sha, None)?;
+        filter_untracked_files(repo, &parent_checkpoints, &commit_sha, None)?;
 
     // mutates inline
     CursorPreset::update_cursor_conversations_to_latest(&mut filtered_working_log)?;
@@ -46,54 +53,103 @@ pub fn post_commit(
         Some(&human_author),
         Some(&working_log),
     );
+    drop(filtered_working_log);
+
+    // Only files the working log knows about can carry attributions, so the rest of a
+    // giant commit (a vendored dependency, say) is never diffed
+    let working_log_files: BTreeSet<String> = parent_checkpoints
+        .iter()
+        .flat_map(|checkpoint| checkpoint.entries.iter().map(|entry| entry.file.clone()))
+        .collect();
+    let attested_files: BTreeSet<String> = authorship_log
+        .attestations
+        .iter()
+        .map(|file| file.file_path.clone())
+        .collect();
+
+    // Working directory lines left out of the commit, which may carry over to the next one
+    let unstaged_hunks = attribute_committed_lines(
+        repo,
+        &mut authorship_log,
+        &working_log_files,
+        &attested_files,
+        &parent_sha,
+        &commit_sha,
+        POST_COMMIT_BATCH_FILES,
+    )?;
 
-    // Filter the authorship log to only include committed lines
-    // We need to keep ONLY lines that are in the commit, not filter out unstaged lines
-    let committed_hunks = collect_committed_hunks(repo, &parent_sha, &commit_sha, None)?;
-
-    // Convert authorship log line numbers from working directory coordinates to commit coordinates
-    // The working log uses working directory coordinates (which includes unstaged changes),
-    // but the authorship log should store commit coordinates (line numbers as they appear in the commit tree)
-    let unstaged_hunks = collect_unstaged_hunks(repo, &commit_sha, None)?;
-
-    // Convert working directory line numbers to commit line numbers
-    convert_authorship_log_to_commit_coordinates(&mut authorship_log, &unstaged_hunks);
+    // Files excluded after they were checkpointed (a new `.gitaiignore` line, say) carry nothing
+    drop_excluded_files(repo, &mut authorship_log);
 
-    // Now filter to only include committed lines
-    authorship_log.filter_to_committed_lines(&committed_hunks);
+    // Directory policies have the last word on their subtrees, whatever the checkpoints saw
+    apply_directory_policies(
+        repo,
+        &mut authorship_log,
+        &parent_sha,
+        &commit_sha,
+        &human_author,
+    )?;
+
+    // Nothing was committed (`--allow-empty`): whatever the working log holds is still
+    // uncommitted, so the note only marks the commit empty
+    if repo
+        .find_commit(commit_sha.clone())
+        .and_then(|commit| commit.is_empty())
+        .unwrap_or(false)
+    {
+        authorship_log = AuthorshipLog::for_empty_commit(&parent_sha);
+    }
 
     // Check if there are unstaged AI-authored lines to preserve in working log
-    let has_unstaged_ai_lines = if !unstaged_hunks.is_empty() {
-        // Check if any unstaged lines match the working log
-        let parent_working_log = repo_storage.working_log_for_base_commit(&parent_sha);
-        let parent_checkpoints = parent_working_log
-            .read_all_checkpoints()
-            .map(|wl| wl)
-            .unwrap_or_default();
-
-        !parent_checkpoints.is_empty() && parent_checkpoints.iter().any(|cp| cp.agent_id.is_some())
-    } else {
-        false
-    };
+    let has_unstaged_ai_lines =
+        !unstaged_hunks.is_empty() && parent_checkpoints.iter().any(|cp| cp.agent_id.is_some());
+
+    authorship_log.metadata.commit_message = working_log.take_commit_message_authorship();
+
+    let settings = repo.settings();
+    // Checkpoints made before the setting changed may still hold more than it allows
+    authorship_log.redact_transcripts(settings.transcript_store);
+    if settings.ignore_prompts {
+        authorship_log.clear_transcripts();
+    } else if settings.transcript_mode == TranscriptMode::Summary {
+        if Config::get().keep_local_transcripts() {
+            for (hash, prompt) in &authorship_log.metadata.prompts {
+                if let Err(e) = repo_storage.write_local_transcript(hash, &prompt.messages) {
+                    debug_log(&format!("Failed to keep local transcript {}: {}", hash, e));
+                }
+            }
+        }
+        authorship_log.summarize_transcripts();
+    }
 
     // Serialize the authorship log
     let authorship_json = authorship_log
         .serialize_to_string()
         .map_err(|_| GitAiError::Generic("Failed to serialize authorship log".to_string()))?;
 
+    // Writing the note and moving the working log have to happen together: if git-ai is
+    // interrupted in between, the note is taken back and the working log left where it was,
+    // for `git-ai stats-delta` to pick up
+    let mut cleanup = CleanupScope::new(repo, "post-commit");
+    cleanup.check()?;
+    cleanup.register(Compensation::restore_notes(repo));
     notes_add(repo, &commit_sha, &authorship_json)?;
+    cleanup.notes_written();
+    cleanup.check()?;
 
     // Only delete the working log if there are no unstaged AI-authored lines
     // If there are unstaged AI lines, filter and transfer the working log to the new commit
     if !has_unstaged_ai_lines {
+        cleanup.finish();
         if !cfg!(debug_assertions) {
             repo_storage.delete_working_log_for_base_commit(&parent_sha)?;
         }
     } else {
         // Transfer working log entries for files with unstaged changes
-        let parent_working_log = repo_storage.working_log_for_base_commit(&parent_sha);
-        let parent_checkpoints = parent_working_log.read_all_checkpoints()?;
-
+        let new_working_log_dir = repo_storage.working_logs.join(&commit_sha);
+        if !new_working_log_dir.exists() {
+            cleanup.register(Compensation::RemovePath(new_working_log_dir));
+        }
         let new_working_log = repo_storage.working_log_for_base_commit(&commit_sha);
 
         // Build a map of file -> (most recent entry, checkpoint)
@@ -224,10 +280,18 @@ pub fn post_commit(
         // Append all checkpoint groups to the new working log
         for (_, (mut checkpoint, entries)) in author_checkpoint_groups {
             if !entries.is_empty() {
+                // The entries still refer to blobs of the parent's working log
+                for entry in &entries {
+                    if let Ok(content) = working_log.get_file_version(&entry.blob_sha) {
+                        new_working_log.persist_file_version(&content)?;
+                    }
+                }
                 checkpoint.entries = entries;
                 new_working_log.append_checkpoint(&checkpoint)?;
             }
         }
+        cleanup.check()?;
+        cleanup.finish();
 
         // Delete the old working log, but keep it in debug mode
         if !cfg!(debug_assertions) {
@@ -243,6 +307,119 @@ pub fn post_commit(
     Ok((commit_sha.to_string(), authorship_log))
 }
 
+/// Drop the attributions of `ignore` subtrees and give every committed line of a `tool:<name>`
+/// subtree to that tool. Prompts left without lines are dropped.
+fn apply_directory_policies(
+    repo: &Repository,
+    authorship_log: &mut AuthorshipLog,
+    parent_sha: &str,
+    commit_sha: &str,
+    human_author: &str,
+) -> Result<(), GitAiError> {
+    let settings = repo.settings();
+    if settings.directory_policies.is_empty() {
+        return Ok(());
+    }
+    let attributed_prompts = referenced_prompts(authorship_log);
+
+    authorship_log.attestations.retain(|file| {
+        !matches!(
+            settings.directory_policy(&file.file_path),
+            Some((_, DirectoryPolicy::Ignore))
+        )
+    });
+
+    let tool_files: HashSet<String> = repo
+        .list_commit_files(commit_sha, None)?
+        .into_iter()
+        .filter(|file| settings.directory_tool(file).is_some())
+        .collect();
+    if !tool_files.is_empty() {
+        let committed_hunks =
+            collect_committed_hunks(repo, parent_sha, commit_sha, Some(&tool_files))?;
+        let mut tool_lines: HashMap<String, u32> = HashMap::new();
+        for (file, ranges) in committed_hunks {
+            let Some(tool) = settings.directory_tool(&file) else {
+                continue;
+            };
+            let agent_id = directory_tool_agent_id(tool);
+            let hash = generate_short_hash(&agent_id.id, &agent_id.tool);
+
+            let attestation = authorship_log.get_or_create_file(&file);
+            for entry in &mut attestation.entries {
+                entry.remove_line_ranges(&ranges);
+            }
+            attestation
+                .entries
+                .retain(|entry| !entry.line_ranges.is_empty());
+            *tool_lines.entry(hash.clone()).or_default() += ranges
+                .iter()
+                .map(|range| range.expand().len() as u32)
+                .sum::<u32>();
+            attestation.add_entry(AttestationEntry::new(hash.clone(), ranges));
+
+            authorship_log
+                .metadata
+                .prompts
+                .entry(hash)
+                .or_insert_with(|| PromptRecord {
+                    agent_id,
+                    human_author: Some(human_author.to_string()),
+                    messages: Vec::new(),
+                    total_additions: 0,
+                    total_deletions: 0,
+                    accepted_lines: 0,
+                    overriden_lines: 0,
+                    summary: None,
+                    environments: Vec::new(),
+                    generated_at: None,
+                });
+        }
+        for (hash, lines) in tool_lines {
+            if let Some(prompt) = authorship_log.metadata.prompts.get_mut(&hash) {
+                prompt.accepted_lines = lines;
+                prompt.total_additions = prompt.total_additions.max(lines);
+            }
+        }
+    }
+
+    let remaining_prompts = referenced_prompts(authorship_log);
+    authorship_log
+        .metadata
+        .prompts
+        .retain(|hash, _| !attributed_prompts.contains(hash) || remaining_prompts.contains(hash));
+    Ok(())
+}
+
+/// Drop the attributions of files `exclude_paths` or `.gitaiignore` leave out, and the prompts
+/// left without lines
+fn drop_excluded_files(repo: &Repository, authorship_log: &mut AuthorshipLog) {
+    let settings = repo.settings();
+    if settings.exclude_paths.is_empty() && settings.ignore_file.is_empty() {
+        return;
+    }
+    let attributed_prompts = referenced_prompts(authorship_log);
+    authorship_log
+        .attestations
+        .retain(|file| !settings.is_excluded_path(&file.file_path));
+    let remaining_prompts = referenced_prompts(authorship_log);
+    authorship_log
+        .metadata
+        .prompts
+        .retain(|hash, _| !attributed_prompts.contains(hash) || remaining_prompts.contains(hash));
+}
+
+/// Prompts the log attributes at least one line to
+fn referenced_prompts(authorship_log: &AuthorshipLog) -> HashSet<String> {
+    authorship_log
+        .attestations
+        .iter()
+        .flat_map(|file| &file.entries)
+        .filter(|entry| !entry.line_ranges.is_empty())
+        .map(|entry| entry.hash.clone())
+        .collect()
+}
+
 /// Filter out working log entries for untracked files
 fn filter_untracked_files(
     repo: &Repository,
@@ -317,69 +494,154 @@ fn collect_committed_hunks(
     Ok(committed_hunks)
 }
 
-/// Collect all unstaged line ranges from the working directory
-///
-/// This function diffs the HEAD commit (what was just committed) against the working directory
-/// to find all lines that exist in the working directory but weren't part of the commit.
-/// These lines should be excluded from the authorship log.
-fn collect_unstaged_hunks(
+/// Move the authorship log from working directory to commit coordinates and keep only the
+/// committed lines, diffing `batch_size` files at a time: each batch is attributed before the
+/// next is diffed. Returns the working directory lines left out of the commit, by file.
+fn attribute_committed_lines(
     repo: &Repository,
+    authorship_log: &mut AuthorshipLog,
+    working_log_files: &BTreeSet<String>,
+    attested_files: &BTreeSet<String>,
+    parent_sha: &str,
     commit_sha: &str,
-    pathspecs: Option<&HashSet<String>>,
+    batch_size: usize,
 ) -> Result<HashMap<String, Vec<LineRange>>, GitAiError> {
-    let mut unstaged_hunks: HashMap<String, Vec<LineRange>> = HashMap::new();
+    let files: BTreeSet<String> = working_log_files.union(attested_files).cloned().collect();
+    let mut unstaged_hunks = HashMap::new();
+    for_each_batch(&files, batch_size, |paths| {
+        // The working log uses working directory coordinates (which include unstaged
+        // changes), but the authorship log stores lines as they are in the commit tree
+        let line_mappings = collect_commit_line_mappings(repo, commit_sha, paths)?;
+        convert_authorship_log_to_commit_coordinates(authorship_log, &line_mappings);
+        unstaged_hunks.extend(
+            line_mappings
+                .iter()
+                .map(|(file, mapping)| (file.clone(), mapping.unstaged_ranges()))
+                .filter(|(_, ranges)| !ranges.is_empty()),
+        );
 
-    // Use git diff to get added lines in working directory vs commit
-    let added_lines = repo.diff_workdir_added_lines(commit_sha, pathspecs)?;
+        // Keep ONLY lines that are in the commit, not filter out unstaged lines
+        let attested: HashSet<String> = paths
+            .iter()
+            .filter(|path| attested_files.contains(*path))
+            .cloned()
+            .collect();
+        if !attested.is_empty() {
+            let committed_hunks =
+                collect_committed_hunks(repo, parent_sha, commit_sha, Some(&attested))?;
+            authorship_log.filter_files_to_committed_lines(&attested, &committed_hunks);
+        }
+        Ok(())
+    })?;
+    authorship_log.retain_attested_lineage();
+    Ok(unstaged_hunks)
+}
 
-    for (file_path, lines) in added_lines {
-        if !lines.is_empty() {
-            unstaged_hunks.insert(file_path, LineRange::compress_lines(&lines));
+/// Run `f` over `files` in batches of at most `batch_size` pathspecs. An empty file set
+/// skips `f` entirely; without pathspecs git would diff everything.
+fn for_each_batch<F>(
+    files: &BTreeSet<String>,
+    batch_size: usize,
+    mut f: F,
+) -> Result<(), GitAiError>
+where
+    F: FnMut(&HashSet<String>) -> Result<(), GitAiError>,
+{
+    let files: Vec<&String> = files.iter().collect();
+    for batch in files.chunks(batch_size.max(1)) {
+        let pathspecs: HashSet<String> = batch.iter().map(|file| (*file).clone()).collect();
+        f(&pathspecs)?;
+    }
+    Ok(())
+}
+
+/// How a file's working directory lines reach the commit: first into the index, then from
+/// the index into the committed tree. The second step is empty unless the index no longer
+/// matches the commit (a pre-commit hook restaged the file, or only some paths were committed).
+#[derive(Debug, Clone, Default, PartialEq)]
+struct CommitLineMapping {
+    workdir_to_index: Vec<DiffHunk>,
+    index_to_commit: Vec<DiffHunk>,
+}
+
+impl CommitLineMapping {
+    /// The commit line a working directory line ended up on, or `None` if it wasn't committed
+    fn commit_line(&self, workdir_line: u32) -> Option<u32> {
+        let index_line = map_line(&self.workdir_to_index, DiffSide::New, workdir_line)?;
+        map_line(&self.index_to_commit, DiffSide::New, index_line)
+    }
+
+    /// Working directory lines that aren't in the commit
+    fn unstaged_ranges(&self) -> Vec<LineRange> {
+        let mut lines: Vec<u32> = Vec::new();
+        for hunk in &self.workdir_to_index {
+            lines.extend(hunk.new_start..hunk.new_start + hunk.new_count);
         }
+        for hunk in &self.index_to_commit {
+            for index_line in hunk.new_start..hunk.new_start + hunk.new_count {
+                lines.extend(map_line(&self.workdir_to_index, DiffSide::Old, index_line));
+            }
+        }
+        lines.sort_unstable();
+        lines.dedup();
+        LineRange::compress_lines(&lines)
     }
+}
 
-    Ok(unstaged_hunks)
+/// Line mappings from the working directory to `commit_sha` for the files in `pathspecs`
+///
+/// Staging, editing and partially staging the same region leaves the working directory,
+/// the index and the commit with different lines in different places, so each step is a
+/// full diff (deletions included) rather than a count of unstaged additions. The index is
+/// snapshotted against the committed tree first, and only files where the two differ get
+/// the index-to-commit diff.
+fn collect_commit_line_mappings(
+    repo: &Repository,
+    commit_sha: &str,
+    pathspecs: &HashSet<String>,
+) -> Result<HashMap<String, CommitLineMapping>, GitAiError> {
+    let mut mappings: HashMap<String, CommitLineMapping> = HashMap::new();
+    for (file, hunks) in repo.diff_workdir_hunks_from_index(Some(pathspecs))? {
+        mappings.entry(file).or_default().workdir_to_index = hunks;
+    }
+
+    let index = repo.index_snapshot(Some(pathspecs))?;
+    let tree = repo.tree_snapshot(commit_sha, Some(pathspecs))?;
+    let restaged: HashSet<String> = index
+        .iter()
+        .filter(|(file, blob)| tree.get(*file) != Some(*blob))
+        .map(|(file, _)| file.clone())
+        .collect();
+    if !restaged.is_empty() {
+        for (file, hunks) in repo.diff_index_hunks(commit_sha, Some(&restaged))? {
+            mappings.entry(file).or_default().index_to_commit = hunks;
+        }
+    }
+    Ok(mappings)
 }
 
 /// Convert authorship log line numbers from working directory coordinates to commit coordinates
 ///
 /// The working log records line numbers in working directory coordinates (which includes unstaged changes),
 /// but the authorship log should store commit coordinates (line numbers as they appear in the commit tree).
-/// This function adjusts all line numbers in the authorship log by subtracting the number of unstaged lines
-/// above each line.
+/// Lines that weren't committed are dropped; the rest move by the lines added and removed above them.
 ///
 /// For example, if there's an unstaged line at position 1, then working directory line 22 becomes commit line 21,
-/// and working directory line 31 becomes commit line 30.
+/// and if a committed line 5 was deleted in the working directory, working directory line 30 becomes commit line 31.
 fn convert_authorship_log_to_commit_coordinates(
     authorship_log: &mut AuthorshipLog,
-    unstaged_hunks: &HashMap<String, Vec<LineRange>>,
+    line_mappings: &HashMap<String, CommitLineMapping>,
 ) {
     for file_attestation in &mut authorship_log.attestations {
-        if let Some(unstaged_ranges) = unstaged_hunks.get(&file_attestation.file_path) {
-            // Expand unstaged ranges to individual line numbers for easier comparison
-            let mut unstaged_lines: Vec<u32> = Vec::new();
-            for range in unstaged_ranges {
-                unstaged_lines.extend(range.expand());
-            }
-            unstaged_lines.sort_unstable();
-
+        if let Some(mapping) = line_mappings.get(&file_attestation.file_path) {
             // For each attestation entry, convert working directory line numbers to commit line numbers
             for entry in &mut file_attestation.entries {
-                // Expand entry's line ranges to individual lines
-                let mut entry_lines: Vec<u32> = Vec::new();
-                for range in &entry.line_ranges {
-                    entry_lines.extend(range.expand());
-                }
-
-                // Convert each line from working directory coordinates to commit coordinates
-                let mut converted_lines: Vec<u32> = Vec::new();
-                for workdir_line in entry_lines {
-                    // Count how many unstaged lines are strictly before this line
-                    let adjustment =
-                        unstaged_lines.iter().filter(|&&l| l < workdir_line).count() as u32;
-                    let commit_line = workdir_line - adjustment;
-                    converted_lines.push(commit_line);
-                }
+                let mut converted_lines: Vec<u32> = entry
+                    .line_ranges
+                    .iter()
+                    .flat_map(|range| range.expand())
+                    .filter_map(|workdir_line| mapping.commit_line(workdir_line))
+                    .collect();
 
                 if !converted_lines.is_empty() {
                     converted_lines.sort_unstable();
@@ -405,6 +667,7 @@ fn convert_authorship_log_to_commit_coordinates(
 
 #[cfg(test)]
 mod tests {
+    use super::*;
     use crate::git::test_utils::TmpRepo;
 
     #[test]
@@ -468,4 +731,71 @@ mod tests {
             "Should have empty attestations when no checkpoints exist"
         );
     }
+
+    #[test]
+    fn test_post_commit_batches_match_single_diff() {
+        let tmp_repo = TmpRepo::new().unwrap();
+        tmp_repo.write_file("base.txt", "base\n", true).unwrap();
+        tmp_repo.commit_with_message("base").unwrap();
+        let parent_sha = tmp_repo.head_commit_sha().unwrap();
+
+        let mut files = BTreeSet::new();
+        for i in 0..5 {
+            let name = format!("dir/file{}.txt", i);
+            tmp_repo
+                .write_file(&name, &"line\n".repeat(i + 1), true)
+                .unwrap();
+            files.insert(name);
+        }
+        tmp_repo
+            .trigger_checkpoint_with_ai("Claude", None, None)
+            .unwrap();
+        let authorship_log = tmp_repo.commit_with_message("many files").unwrap();
+        assert_eq!(authorship_log.attestations.len(), 5);
+        let commit_sha = tmp_repo.head_commit_sha().unwrap();
+
+        let repo = tmp_repo.gitai_repo();
+        let working_log = repo.storage.working_log_for_base_commit(&parent_sha);
+        let checkpoints = working_log.read_all_checkpoints().unwrap();
+        let attribute = |batch_size: usize| {
+            let mut log = AuthorshipLog::from_working_log_with_base_commit_and_human_author(
+                &checkpoints,
+                &parent_sha,
+                None,
+                Some(&working_log),
+            );
+            let unstaged = attribute_committed_lines(
+                repo,
+                &mut log,
+                &files,
+                &files,
+                &parent_sha,
+                &commit_sha,
+                batch_size,
+            )
+            .unwrap();
+            (log, unstaged)
+        };
+        let (whole, whole_unstaged) = attribute(usize::MAX);
+        let (batched, batched_unstaged) = attribute(2);
+        assert_eq!(batched.attestations, whole.attestations);
+        assert_eq!(batched_unstaged, whole_unstaged);
+        assert_eq!(batched.attestations.len(), 5);
+
+        // Batches never exceed the size, and nothing runs without files
+        let mut batches = Vec::new();
+        for_each_batch(&files, 2, |paths| {
+            batches.push(paths.len());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(batches, vec![2, 2, 1]);
+        let mut calls = 0;
+        for_each_batch(&BTreeSet::new(), 2, |_| {
+            calls += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(calls, 0);
+    }
 }
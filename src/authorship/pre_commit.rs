@@ -1,17 +1,114 @@
-use crate::authorship::working_log::CheckpointKind;
+use crate::authorship::working_log::{AgentId, CheckpointKind};
+use crate::commands::checkpoint::CheckpointOptions;
+use crate::commands::checkpoint_agent::agent_presets::AgentRunResult;
 use crate::error::GitAiError;
 use crate::git::repository::Repository;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Marks a scripted commit as written by an agent, e.g. `GIT_AI_COMMIT_AUTHOR=agent:codex`
+pub const COMMIT_AUTHOR_ENV: &str = "GIT_AI_COMMIT_AUTHOR";
+/// Git config equivalent of [`COMMIT_AUTHOR_ENV`], usually passed as `git -c gitai.author=ai`
+pub const COMMIT_AUTHOR_CONFIG: &str = "gitai.author";
 
 pub fn pre_commit(repo: &Repository, default_author: String) -> Result<(), GitAiError> {
+    // A commit the agent makes itself has no checkpoint of its own: everything since the
+    // last checkpoint goes to that agent, with a prompt record that has no transcript
+    if let Some(tool) = commit_agent_tool(repo) {
+        let agent_run_result = AgentRunResult {
+            agent_id: AgentId {
+                tool,
+                id: format!(
+                    "commit-{}",
+                    SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|d| d.as_nanos())
+                        .unwrap_or(0)
+                ),
+                model: "unknown".to_string(),
+            },
+            checkpoint_kind: CheckpointKind::AiAgent,
+            transcript: None,
+            repo_working_dir: None,
+            edited_filepaths: None,
+            will_edit_filepaths: None,
+            commit_message_source: None,
+            agent_version: None,
+            file_contents: None,
+        };
+        return crate::commands::checkpoint::run(
+            repo,
+            &default_author,
+            CheckpointKind::AiAgent,
+            Some(agent_run_result),
+            CheckpointOptions {
+                quiet: true,
+                ..Default::default()
+            },
+        )
+        .map(|_| ());
+    }
+
     // Run checkpoint as human editor.
     let result: Result<(usize, usize, usize), GitAiError> = crate::commands::checkpoint::run(
         repo,
         &default_author,
         CheckpointKind::Human,
-        false,
-        false,
-        true,
         None,
+        CheckpointOptions {
+            quiet: true,
+            ..Default::default()
+        },
     );
     result.map(|_| ())
 }
+
+/// The agent a commit is attributed to by [`COMMIT_AUTHOR_ENV`] or, failing that,
+/// [`COMMIT_AUTHOR_CONFIG`]
+fn commit_agent_tool(repo: &Repository) -> Option<String> {
+    if let Ok(value) = std::env::var(COMMIT_AUTHOR_ENV) {
+        return parse_commit_author(&value);
+    }
+    repo.config_get_str(COMMIT_AUTHOR_CONFIG)
+        .ok()
+        .flatten()
+        .and_then(|value| parse_commit_author(&value))
+}
+
+/// `ai` and `agent` name an unknown agent, `agent:<tool>` (or `ai:<tool>`) a specific one.
+/// Anything else, `human` included, leaves the commit to the usual checkpoints.
+fn parse_commit_author(value: &str) -> Option<String> {
+    let value = value.trim();
+    let (kind, tool) = match value.split_once(':') {
+        Some((kind, tool)) => (kind, Some(tool.trim())),
+        None => (value, None),
+    };
+    if !kind.eq_ignore_ascii_case("ai") && !kind.eq_ignore_ascii_case("agent") {
+        return None;
+    }
+    match tool {
+        Some(tool) if !tool.is_empty() => Some(tool.to_string()),
+        _ => Some("ai".to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_commit_author() {
+        assert_eq!(parse_commit_author("ai"), Some("ai".to_string()));
+        assert_eq!(parse_commit_author("Agent"), Some("ai".to_string()));
+        assert_eq!(
+            parse_commit_author("agent:codex"),
+            Some("codex".to_string())
+        );
+        assert_eq!(
+            parse_commit_author("ai: claude "),
+            Some("claude".to_string())
+        );
+        assert_eq!(parse_commit_author("agent:"), Some("ai".to_string()));
+        assert_eq!(parse_commit_author("human"), None);
+        assert_eq!(parse_commit_author(""), None);
+    }
+}
@@ -0,0 +1,203 @@
+use crate::authorship::attribution_tracker::LineAttribution;
+use crate::authorship::working_log::CheckpointKind;
+use similar::{DiffTag, TextDiff};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+
+/// Most earlier prompts remembered per attribution. Older ones fall off the end.
+pub const MAX_PROMPT_LINEAGE: usize = 8;
+
+/// Earlier prompt hashes whose lines each prompt rewrote in one file, most recent first
+pub type PromptLineage = BTreeMap<String, Vec<String>>;
+
+/// Lineage of a file after a checkpoint. A prompt whose lines replace lines another AI prompt
+/// wrote gets that prompt, followed by that prompt's own lineage, in front of its chain.
+/// Chains of prompts that no longer own any line are dropped.
+pub fn update_lineage(
+    previous_lineage: &PromptLineage,
+    previous_content: &str,
+    previous_line_attributions: &[LineAttribution],
+    content: &str,
+    line_attributions: &[LineAttribution],
+) -> PromptLineage {
+    let old_authors = ai_authors_by_line(previous_line_attributions);
+    let new_authors = ai_authors_by_line(line_attributions);
+
+    // Prompt -> prompts whose lines it replaced, in the order they were met
+    let mut replaced: HashMap<&str, Vec<&str>> = HashMap::new();
+    if !old_authors.is_empty() && !new_authors.is_empty() {
+        // A missing final newline would make appending after the last line look like
+        // rewriting it
+        let previous_content = with_final_newline(previous_content);
+        let content = with_final_newline(content);
+        let diff = TextDiff::from_lines(previous_content.as_ref(), content.as_ref());
+        for op in diff.ops() {
+            let (tag, old_range, new_range) = op.as_tag_tuple();
+            if tag != DiffTag::Replace {
+                continue;
+            }
+            let old_lines = count_by_author(&old_authors, old_range);
+            let new_lines = count_by_author(&new_authors, new_range);
+            // Only prompts that lost lines in the hunk were rewritten
+            let predecessors: Vec<&str> = old_lines
+                .iter()
+                .filter(|(author, count)| {
+                    !new_lines
+                        .iter()
+                        .any(|(new_author, new_count)| new_author == author && new_count >= count)
+                })
+                .map(|(author, _)| *author)
+                .collect();
+            for (author, _) in &new_lines {
+                let chain = replaced.entry(author).or_default();
+                for predecessor in &predecessors {
+                    if predecessor != author && !chain.contains(predecessor) {
+                        chain.push(predecessor);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut lineage = PromptLineage::new();
+    for author in new_authors.values() {
+        if lineage.contains_key(*author) {
+            continue;
+        }
+        let mut chain: Vec<String> = Vec::new();
+        let mut push = |hash: &str| {
+            if hash != *author && !chain.iter().any(|existing| existing == hash) {
+                chain.push(hash.to_string());
+            }
+        };
+        for predecessor in replaced.get(author).into_iter().flatten() {
+            push(predecessor);
+            for ancestor in previous_lineage.get(*predecessor).into_iter().flatten() {
+                push(ancestor);
+            }
+        }
+        for ancestor in previous_lineage.get(*author).into_iter().flatten() {
+            push(ancestor);
+        }
+        chain.truncate(MAX_PROMPT_LINEAGE);
+        if !chain.is_empty() {
+            lineage.insert(author.to_string(), chain);
+        }
+    }
+    lineage
+}
+
+fn with_final_newline(content: &str) -> Cow<'_, str> {
+    if content.is_empty() || content.ends_with('\n') {
+        Cow::Borrowed(content)
+    } else {
+        Cow::Owned(format!("{}\n", content))
+    }
+}
+
+/// AI lines per prompt among the 0-indexed `lines`, in the order the prompts first appear
+fn count_by_author<'a>(
+    authors: &HashMap<u32, &'a str>,
+    lines: std::ops::Range<usize>,
+) -> Vec<(&'a str, usize)> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    for line in lines {
+        let Some(author) = authors.get(&(line as u32 + 1)).copied() else {
+            continue;
+        };
+        match counts.iter_mut().find(|(existing, _)| *existing == author) {
+            Some((_, count)) => *count += 1,
+            None => counts.push((author, 1)),
+        }
+    }
+    counts
+}
+
+/// Lines (1-indexed) attributed to an AI prompt, with the prompt's hash
+fn ai_authors_by_line(line_attributions: &[LineAttribution]) -> HashMap<u32, &str> {
+    let human = CheckpointKind::Human.to_str();
+    line_attributions
+        .iter()
+        .filter(|attr| attr.author_id != human)
+        .flat_map(|attr| {
+            (attr.start_line..=attr.end_line).map(move |line| (line, attr.author_id.as_str()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attr(start_line: u32, end_line: u32, author: &str) -> LineAttribution {
+        LineAttribution::new(start_line, end_line, author.to_string(), false)
+    }
+
+    #[test]
+    fn test_rewritten_lines_record_the_previous_prompt() {
+        let lineage = update_lineage(
+            &PromptLineage::new(),
+            "fn a() {}\nfn b() {}\nfn c() {}\n",
+            &[attr(1, 1, "human"), attr(2, 3, "aaaaaaa")],
+            "fn a() {}\nfn b2() {}\nfn c() {}\nfn d() {}\n",
+            &[
+                attr(2, 2, "bbbbbbb"),
+                attr(3, 3, "aaaaaaa"),
+                attr(4, 4, "bbbbbbb"),
+            ],
+        );
+        assert_eq!(
+            lineage,
+            PromptLineage::from([("bbbbbbb".to_string(), vec!["aaaaaaa".to_string()])])
+        );
+    }
+
+    #[test]
+    fn test_lineage_chains_and_is_capped() {
+        let mut previous = PromptLineage::new();
+        previous.insert(
+            "ccccccc".to_string(),
+            (0..MAX_PROMPT_LINEAGE)
+                .map(|i| format!("old{}", i))
+                .collect(),
+        );
+        previous.insert("gone000".to_string(), vec!["old0".to_string()]);
+
+        let lineage = update_lineage(
+            &previous,
+            "one\ntwo\n",
+            &[attr(1, 2, "ccccccc")],
+            "one\nthree\n",
+            &[attr(1, 1, "ccccccc"), attr(2, 2, "ddddddd")],
+        );
+
+        let chain = &lineage["ddddddd"];
+        assert_eq!(chain.len(), MAX_PROMPT_LINEAGE);
+        assert_eq!(chain[0], "ccccccc");
+        assert_eq!(chain[1], "old0");
+        // The rewritten prompt keeps its own chain; prompts without lines drop theirs
+        assert_eq!(lineage["ccccccc"], previous["ccccccc"]);
+        assert!(!lineage.contains_key("gone000"));
+    }
+
+    #[test]
+    fn test_human_edits_and_self_rewrites_add_nothing() {
+        let lineage = update_lineage(
+            &PromptLineage::new(),
+            "one\ntwo\n",
+            &[attr(1, 2, "aaaaaaa")],
+            "one\nTWO\nthree\n",
+            &[attr(1, 3, "aaaaaaa")],
+        );
+        assert!(lineage.is_empty());
+
+        let lineage = update_lineage(
+            &PromptLineage::new(),
+            "one\ntwo\n",
+            &[attr(1, 2, "aaaaaaa")],
+            "one\nhuman\n",
+            &[attr(1, 1, "aaaaaaa")],
+        );
+        assert!(lineage.is_empty());
+    }
+}
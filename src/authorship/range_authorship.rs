@@ -175,18 +175,37 @@ fn calculate_range_stats_direct(
     let output = crate::git::repository::exec_git(&args)?;
     let diff_output = String::from_utf8(output.stdout)?;
 
-    // Parse diff to extract added lines and their line numbers
-    let added_lines_by_file = parse_git_diff_for_added_lines(&diff_output)?;
+    // Parse diff to extract added lines and their line numbers. Generated files are
+    // counted but not blamed.
+    let settings = repo.settings();
+    let mut added_lines_by_file = parse_git_diff_for_added_lines(&diff_output)?;
+    added_lines_by_file.retain(|file_path, _| !settings.is_generated_path(file_path));
 
     let mut git_diff_added_lines = 0u32;
     let mut git_diff_deleted_lines = 0u32;
+    let mut generated_added_lines = 0u32;
+    let mut generated_deleted_lines = 0u32;
+    let mut in_generated_file = false;
 
     // First pass: count total additions/deletions from the diff
     for line in diff_output.lines() {
-        if line.starts_with('+') && !line.starts_with("+++") {
+        if line.starts_with("diff --git") {
+            in_generated_file = false;
+        } else if let Some(path) = line
+            .strip_prefix("--- a/")
+            .or_else(|| line.strip_prefix("+++ b/"))
+        {
+            in_generated_file |= settings.is_generated_path(path);
+        } else if line.starts_with('+') && !line.starts_with("+++") {
             git_diff_added_lines += 1;
+            if in_generated_file {
+                generated_added_lines += 1;
+            }
         } else if line.starts_with('-') && !line.starts_with("---") {
             git_diff_deleted_lines += 1;
+            if in_generated_file {
+                generated_deleted_lines += 1;
+            }
         }
     }
 
@@ -202,7 +221,7 @@ fn calculate_range_stats_direct(
                 authorship_log,
                 ..
             } => {
-                auth_logs.insert(sha.clone(), Some(authorship_log.clone()));
+                auth_logs.insert(sha.clone(), Some(authorship_log.as_ref().clone()));
             }
             CommitAuthorship::NoLog { sha, .. } => {
                 auth_logs.insert(sha.clone(), None);
@@ -254,9 +273,13 @@ fn calculate_range_stats_direct(
         time_waiting_for_ai: 0,
         git_diff_deleted_lines,
         git_diff_added_lines,
-        human_deletions: git_diff_deleted_lines,
+        human_deletions: git_diff_deleted_lines - generated_deleted_lines,
         ai_deletions: 0,
         tool_model_breakdown: std::collections::BTreeMap::new(),
+        commit_message: None,
+        empty_commit: false,
+        generated_added_lines,
+        generated_deleted_lines,
     })
 }
 
@@ -436,3 +459,97 @@ pub fn print_range_authorship_stats(stats: &RangeAuthorshipStats) {
         }
     }
 }
+
+/// One commit of a range with its own stats, for trending AI contribution commit by commit
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitStatsRow {
+    pub commit: String,
+    /// Author date, YYYY-MM-DD
+    pub date: String,
+    pub subject: String,
+    #[serde(flatten)]
+    pub stats: CommitStats,
+}
+
+/// Stats of every commit in the range, oldest first. Merges get a row only when their notes
+/// count (see `RangeWalk::counts_merge_notes`), so no line is counted twice.
+pub fn per_commit_stats(commit_range: CommitRange) -> Result<Vec<CommitStatsRow>, GitAiError> {
+    commit_range.is_valid()?;
+    let repository = commit_range.repo();
+
+    let mut args = repository.global_args_for_exec();
+    args.push("log".to_string());
+    args.push("--reverse".to_string());
+    args.push("--format=%H%x00%as%x00%s".to_string());
+    args.extend(commit_range.walk.rev_list_args());
+    if !commit_range.walk.counts_merge_notes() && !commit_range.walk.no_merges {
+        args.push("--no-merges".to_string());
+    }
+    args.push(format!(
+        "{}..{}",
+        commit_range.start_oid, commit_range.end_oid
+    ));
+    let output = crate::git::repository::exec_git(&args)?;
+
+    let mut rows = Vec::new();
+    for line in String::from_utf8(output.stdout)?.lines() {
+        let mut fields = line.splitn(3, '\0');
+        let (Some(commit), Some(date), Some(subject)) =
+            (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        rows.push(CommitStatsRow {
+            commit: commit.to_string(),
+            date: date.to_string(),
+            subject: subject.to_string(),
+            stats: stats_for_commit_stats(repository, commit, &commit_range.refname)?,
+        });
+    }
+    Ok(rows)
+}
+
+pub fn print_per_commit_stats(rows: &[CommitStatsRow]) {
+    if rows.is_empty() {
+        println!("No commits in range");
+        return;
+    }
+    println!(
+        "{:<8} {:<10} {:>6} {:>7} {:>4}  Subject",
+        "Commit", "Date", "Added", "AI", "AI%"
+    );
+    let (mut total_added, mut total_ai) = (0u32, 0u32);
+    for row in rows {
+        let added = row.stats.counted_added_lines();
+        let ai = row.stats.ai_additions.min(added);
+        total_added += added;
+        total_ai += ai;
+        println!(
+            "{:<8} {:<10} {:>6} {:>7} {:>3}%  {}",
+            &row.commit[..row.commit.len().min(7)],
+            row.date,
+            added,
+            ai,
+            ai_percent(ai, added),
+            row.subject
+        );
+    }
+    println!(
+        "{:<8} {:<10} {:>6} {:>7} {:>3}%  {} commit{}",
+        "Total",
+        "",
+        total_added,
+        total_ai,
+        ai_percent(total_ai, total_added),
+        rows.len(),
+        if rows.len() == 1 { "" } else { "s" }
+    );
+}
+
+fn ai_percent(ai: u32, added: u32) -> u32 {
+    if added > 0 {
+        (ai as f64 / added as f64 * 100.0).round() as u32
+    } else {
+        0
+    }
+}
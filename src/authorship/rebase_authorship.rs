@@ -1,11 +1,14 @@
-use crate::authorship::authorship_log_serialization::AuthorshipLog;
+use crate::authorship::authorship_log::{AttributionConfidence, LineRange};
+use crate::authorship::authorship_log_serialization::{AttestationEntry, AuthorshipLog};
 use crate::authorship::post_commit;
+use crate::cancellation::{CleanupScope, Compensation};
 use crate::error::GitAiError;
+use crate::git::partial_clone;
 use crate::git::refs::get_reference_as_authorship_log_v3;
 use crate::git::repository::{Commit, Repository};
 use crate::git::rewrite_log::RewriteLogEvent;
 use crate::utils::debug_log;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 // Process events in the rewrite log and call the correct rewrite functions in this file
 pub fn rewrite_authorship_if_needed(
@@ -15,6 +18,13 @@ pub fn rewrite_authorship_if_needed(
     _full_log: &Vec<RewriteLogEvent>,
     supress_output: bool,
 ) -> Result<(), GitAiError> {
+    if let Err(e) = prefetch_rewrite_objects(repo, last_event) {
+        debug_log(&format!(
+            "Failed to prefetch objects for the rewrite: {}",
+            e
+        ));
+    }
+
     match last_event {
         RewriteLogEvent::Commit { commit } => {
             // This is going to become the regualar post-commit
@@ -39,6 +49,23 @@ pub fn rewrite_authorship_if_needed(
                 &commit_amend.original_commit, &commit_amend.amended_commit_sha
             ));
         }
+        RewriteLogEvent::MergeSquash { merge_squash }
+            if merge_squash.squash_commit.is_some() =>
+        {
+            // Squashed elsewhere, so there's no working log: the note goes straight on the commit
+            let squash_commit = merge_squash.squash_commit.as_deref().unwrap_or_default();
+            write_squash_commit_note(
+                repo,
+                &merge_squash.source_head,
+                &merge_squash.base_head,
+                squash_commit,
+            )?;
+
+            debug_log(&format!(
+                "✓ Recovered authorship of {} squashed into {}",
+                merge_squash.source_branch, squash_commit
+            ));
+        }
         RewriteLogEvent::MergeSquash { merge_squash } => {
             // --squash always fails if repo is not clean
             // this clears old working logs in the event you reset, make manual changes, reset, try again
@@ -87,12 +114,63 @@ pub fn rewrite_authorship_if_needed(
                 cherry_pick_complete.new_commits.len()
             ));
         }
+        RewriteLogEvent::RevertMixed { revert_mixed } => {
+            if revert_mixed.success
+                && let Some(revert_commit) = &revert_mixed.revert_commit
+            {
+                rewrite_authorship_after_revert(
+                    repo,
+                    &revert_mixed.reverted_commit,
+                    revert_commit,
+                    &revert_mixed.affected_files,
+                )?;
+
+                debug_log(&format!(
+                    "✓ Rewrote authorship for revert of {}",
+                    revert_mixed.reverted_commit
+                ));
+            }
+        }
         _ => {}
     }
 
     Ok(())
 }
 
+/// In a partial clone, fetch the files and notes of the commits a rewrite carries authorship
+/// over from in one batch, instead of one lazy fetch per object as the rewrite reads them
+fn prefetch_rewrite_objects(repo: &Repository, event: &RewriteLogEvent) -> Result<(), GitAiError> {
+    if partial_clone::promisor_remote(repo).is_none() {
+        return Ok(());
+    }
+    let (sources, rewritten): (Vec<String>, Vec<String>) = match event {
+        RewriteLogEvent::Commit { commit } => (Vec::new(), vec![commit.commit_sha.clone()]),
+        RewriteLogEvent::CommitAmend { commit_amend } => (
+            vec![commit_amend.original_commit.clone()],
+            vec![commit_amend.amended_commit_sha.clone()],
+        ),
+        RewriteLogEvent::RebaseComplete { rebase_complete } => (
+            rebase_complete.original_commits.clone(),
+            rebase_complete.new_commits.clone(),
+        ),
+        RewriteLogEvent::CherryPickComplete {
+            cherry_pick_complete,
+        } => (
+            cherry_pick_complete.source_commits.clone(),
+            cherry_pick_complete.new_commits.clone(),
+        ),
+        RewriteLogEvent::RevertMixed { revert_mixed } => (
+            vec![revert_mixed.reverted_commit.clone()],
+            revert_mixed.revert_commit.iter().cloned().collect(),
+        ),
+        _ => return Ok(()),
+    };
+    let commits: Vec<String> = sources.iter().chain(&rewritten).cloned().collect();
+    partial_clone::prefetch_objects(repo, &partial_clone::commit_blobs(repo, &commits)?)?;
+    partial_clone::prefetch_notes(repo, &sources)?;
+    Ok(())
+}
+
 /// Prepare working log after a merge --squash (before commit)
 ///
 /// This handles the case where `git merge --squash` has staged changes but hasn't committed yet.
@@ -189,10 +267,6 @@ pub fn rewrite_authorship_after_squash_or_rebase(
     merge_commit_sha: &str,
     _suppress_output: bool,
 ) -> Result<(), GitAiError> {
-    use crate::authorship::virtual_attribution::{
-        VirtualAttributions, merge_attributions_favoring_first,
-    };
-
     // Step 1: Get target branch head (first parent on merge_ref)
     // This is more correct than just parent(0) in cases with complex back-and-forth merge history
     let merge_commit = repo.find_commit(merge_commit_sha.to_string())?;
@@ -204,8 +278,29 @@ pub fn rewrite_authorship_after_squash_or_rebase(
         source_head_sha, merge_commit_sha
     ));
 
+    write_squash_commit_note(
+        repo,
+        source_head_sha,
+        &target_branch_head_sha,
+        merge_commit_sha,
+    )
+}
+
+/// Write the note of a squash commit that is already committed on top of
+/// `target_branch_head_sha`, merging the authorship of the squashed branch (ending at
+/// `source_head_sha`) with the target branch's
+pub fn write_squash_commit_note(
+    repo: &Repository,
+    source_head_sha: &str,
+    target_branch_head_sha: &str,
+    merge_commit_sha: &str,
+) -> Result<(), GitAiError> {
+    use crate::authorship::virtual_attribution::{
+        VirtualAttributions, merge_attributions_favoring_first,
+    };
+
     // Step 2: Get list of changed files between the two branches
-    let changed_files = repo.diff_changed_files(source_head_sha, &target_branch_head_sha)?;
+    let changed_files = repo.diff_changed_files(source_head_sha, target_branch_head_sha)?;
 
     if changed_files.is_empty() {
         // No files changed, nothing to do
@@ -233,7 +328,7 @@ pub fn rewrite_authorship_after_squash_or_rebase(
     let target_va = smol::block_on(async {
         VirtualAttributions::new_for_base_commit(
             repo_clone,
-            target_branch_head_sha.clone(),
+            target_branch_head_sha.to_string(),
             &changed_files,
         )
         .await
@@ -253,6 +348,9 @@ pub fn rewrite_authorship_after_squash_or_rebase(
     // Step 6: Convert to AuthorshipLog (everything is committed in CI merge)
     let mut authorship_log = merged_va.to_authorship_log()?;
     authorship_log.metadata.base_commit_sha = merge_commit_sha.to_string();
+    // Both sides were merged and conflicts resolved without checkpoints, so attribution of
+    // the result is a best guess
+    authorship_log.mark_confidence(AttributionConfidence::Heuristic);
 
     debug_log(&format!(
         "Created authorship log with {} attestations, {} prompts",
@@ -275,6 +373,85 @@ pub fn rewrite_authorship_after_squash_or_rebase(
     Ok(())
 }
 
+/// Write a note for a merge commit that doesn't have one yet. It attests the lines the merge
+/// adds to its first parent, attributed the way the merged branches had them, so walks along
+/// first parents count the merged work once, at the merge. Lines none of the parents had
+/// (conflict resolutions) are left unattested. Returns false for non-merge commits and merges
+/// that already have a note.
+pub fn synthesize_merge_commit_note(
+    repo: &Repository,
+    merge_commit_sha: &str,
+) -> Result<bool, GitAiError> {
+    use crate::authorship::virtual_attribution::{
+        VirtualAttributions, merge_attributions_favoring_first,
+    };
+
+    let merge_commit = repo.find_commit(merge_commit_sha.to_string())?;
+    let parents: Vec<String> = merge_commit.parents().map(|parent| parent.id()).collect();
+    if parents.len() < 2 || get_reference_as_authorship_log_v3(repo, merge_commit_sha).is_ok() {
+        return Ok(false);
+    }
+
+    let added_lines = repo.diff_added_lines(&parents[0], merge_commit_sha, None)?;
+    let mut changed_files: Vec<String> = added_lines
+        .iter()
+        .filter(|(_, lines)| !lines.is_empty())
+        .map(|(file, _)| file.clone())
+        .collect();
+    changed_files.sort();
+
+    let mut authorship_log = AuthorshipLog::new();
+    if !changed_files.is_empty() {
+        let committed_files = get_committed_files_content(repo, merge_commit_sha, &changed_files)?;
+        let mut merged_va = None;
+        for parent in &parents {
+            let repo_clone = repo.clone();
+            let parent_va = smol::block_on(async {
+                VirtualAttributions::new_for_base_commit(repo_clone, parent.clone(), &changed_files)
+                    .await
+            })?;
+            merged_va = Some(match merged_va {
+                None => parent_va,
+                Some(merged) => {
+                    merge_attributions_favoring_first(merged, parent_va, committed_files.clone())?
+                }
+            });
+        }
+        if let Some(merged_va) = merged_va {
+            authorship_log = merged_va.to_authorship_log()?;
+        }
+
+        let committed_hunks: HashMap<String, Vec<LineRange>> = added_lines
+            .iter()
+            .map(|(file, lines)| (file.clone(), LineRange::compress_lines(lines)))
+            .collect();
+        authorship_log.filter_to_committed_lines(&committed_hunks);
+        let attested: HashSet<&String> = authorship_log
+            .attestations
+            .iter()
+            .flat_map(|file| file.entries.iter().map(|entry| &entry.hash))
+            .collect();
+        authorship_log
+            .metadata
+            .prompts
+            .retain(|hash, _| attested.contains(hash));
+    }
+    authorship_log.metadata.base_commit_sha = merge_commit_sha.to_string();
+    authorship_log.mark_confidence(AttributionConfidence::Reconstructed);
+
+    let authorship_json = authorship_log
+        .serialize_to_string()
+        .map_err(|_| GitAiError::Generic("Failed to serialize authorship log".to_string()))?;
+    crate::git::refs::notes_add(repo, merge_commit_sha, &authorship_json)?;
+
+    debug_log(&format!(
+        "Synthesized authorship log for merge commit {} with {} attestations",
+        merge_commit_sha,
+        authorship_log.attestations.len()
+    ));
+    Ok(true)
+}
+
 pub fn rewrite_authorship_after_rebase_v2(
     repo: &Repository,
     original_head: &str,
@@ -291,8 +468,8 @@ pub fn rewrite_authorship_after_rebase_v2(
     let pathspecs = get_pathspecs_from_commits(repo, original_commits)?;
 
     if pathspecs.is_empty() {
-        // No files were modified, nothing to do
-        return Ok(());
+        // No files were modified, only empty commits to mark
+        return write_empty_commit_notes(repo, new_commits);
     }
 
     debug_log(&format!(
@@ -340,8 +517,15 @@ pub fn rewrite_authorship_after_rebase_v2(
         )
     };
 
+    let original_logs = authorship_logs_for_commits(repo, original_commits);
+
+    // An interrupted rebase rewrite leaves no notes half-written: all or none
+    let mut cleanup = CleanupScope::new(repo, "rebase authorship rewrite");
+    cleanup.register(Compensation::restore_notes(repo));
+
     // Step 3: Process each new commit in order (oldest to newest)
     for (idx, new_commit) in new_commits.iter().enumerate() {
+        cleanup.check()?;
         debug_log(&format!(
             "Processing commit {}/{}: {}",
             idx + 1,
@@ -356,6 +540,12 @@ pub fn rewrite_authorship_after_rebase_v2(
         let commit_tree = commit_obj.tree()?;
         let parent_tree = parent_obj.tree()?;
 
+        // Kept empty (`--empty=keep`, `--allow-empty`), or its changes were already upstream
+        if commit_tree.id() == parent_tree.id() {
+            write_empty_commit_note(repo, new_commit)?;
+            continue;
+        }
+
         let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit_tree), None, None)?;
 
         // Build new content by applying the diff to current content
@@ -418,6 +608,11 @@ pub fn rewrite_authorship_after_rebase_v2(
         });
 
         authorship_log.metadata.base_commit_sha = new_commit.clone();
+        for original_log in &original_logs {
+            authorship_log.inherit_confidence(original_log);
+            authorship_log.inherit_lineage(&original_log.metadata.lineage);
+        }
+        authorship_log.mark_confidence(AttributionConfidence::Reconstructed);
 
         // Save authorship log
         let authorship_json = authorship_log
@@ -425,6 +620,7 @@ pub fn rewrite_authorship_after_rebase_v2(
             .map_err(|_| GitAiError::Generic("Failed to serialize authorship log".to_string()))?;
 
         crate::git::refs::notes_add(repo, new_commit, &authorship_json)?;
+        cleanup.notes_written();
 
         debug_log(&format!(
             "Saved authorship log for commit {} ({} files)",
@@ -433,6 +629,8 @@ pub fn rewrite_authorship_after_rebase_v2(
         ));
     }
 
+    cleanup.check()?;
+    cleanup.finish();
     Ok(())
 }
 
@@ -474,9 +672,9 @@ pub fn rewrite_authorship_after_cherry_pick(
     let pathspecs = get_pathspecs_from_commits(repo, source_commits)?;
 
     if pathspecs.is_empty() {
-        // No files were modified, nothing to do
+        // No files were modified, only empty commits to mark
         debug_log("No files modified in source commits");
-        return Ok(());
+        return write_empty_commit_notes(repo, new_commits);
     }
 
     debug_log(&format!(
@@ -526,8 +724,14 @@ pub fn rewrite_authorship_after_cherry_pick(
         )
     };
 
+    let source_logs = authorship_logs_for_commits(repo, source_commits);
+
+    let mut cleanup = CleanupScope::new(repo, "cherry-pick authorship rewrite");
+    cleanup.register(Compensation::restore_notes(repo));
+
     // Step 3: Process each new commit in order (oldest to newest)
     for (idx, new_commit) in new_commits.iter().enumerate() {
+        cleanup.check()?;
         debug_log(&format!(
             "Processing cherry-picked commit {}/{}: {}",
             idx + 1,
@@ -542,6 +746,12 @@ pub fn rewrite_authorship_after_cherry_pick(
         let commit_tree = commit_obj.tree()?;
         let parent_tree = parent_obj.tree()?;
 
+        // Kept empty (`--empty=keep`, `--allow-empty`), or its changes were already upstream
+        if commit_tree.id() == parent_tree.id() {
+            write_empty_commit_note(repo, new_commit)?;
+            continue;
+        }
+
         let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit_tree), None, None)?;
 
         // Build new content by applying the diff to current content
@@ -604,6 +814,11 @@ pub fn rewrite_authorship_after_cherry_pick(
         });
 
         authorship_log.metadata.base_commit_sha = new_commit.clone();
+        for source_log in &source_logs {
+            authorship_log.inherit_confidence(source_log);
+            authorship_log.inherit_lineage(&source_log.metadata.lineage);
+        }
+        authorship_log.mark_confidence(AttributionConfidence::Reconstructed);
 
         // Save authorship log
         let authorship_json = authorship_log
@@ -611,6 +826,7 @@ pub fn rewrite_authorship_after_cherry_pick(
             .map_err(|_| GitAiError::Generic("Failed to serialize authorship log".to_string()))?;
 
         crate::git::refs::notes_add(repo, new_commit, &authorship_json)?;
+        cleanup.notes_written();
 
         debug_log(&format!(
             "Saved authorship log for cherry-picked commit {} ({} files)",
@@ -619,6 +835,199 @@ pub fn rewrite_authorship_after_cherry_pick(
         ));
     }
 
+    cleanup.check()?;
+    cleanup.finish();
+    Ok(())
+}
+
+/// Write the note of `revert_commit`, which reverts `reverted_commit`. The lines a revert adds
+/// back are the ones the reverted commit removed, so they're blamed in the state the reverted
+/// commit was made on: its mainline parent. That recovers the original AI attribution when a
+/// revert is itself reverted. Lines the revert only removes need no attestation.
+///
+/// An existing note on the revert commit (a conflicted revert concluded with `git commit`) keeps
+/// its attestations for every line that isn't restored.
+pub fn rewrite_authorship_after_revert(
+    repo: &Repository,
+    reverted_commit: &str,
+    revert_commit: &str,
+    affected_files: &[String],
+) -> Result<(), GitAiError> {
+    use crate::commands::blame::GitAiBlameOptions;
+    use similar::{ChangeTag, TextDiff};
+
+    let source_commit = revert_source_commit(repo, reverted_commit, revert_commit)?;
+    let revert_parent = repo.find_commit(revert_commit.to_string())?.parent(0)?.id();
+    debug_log(&format!(
+        "Processing revert {} of {}, restoring lines from {}",
+        revert_commit, reverted_commit, source_commit
+    ));
+
+    let mut authorship_log = get_reference_as_authorship_log_v3(repo, revert_commit)
+        .unwrap_or_else(|_| AuthorshipLog::new());
+    authorship_log.metadata.base_commit_sha = revert_commit.to_string();
+
+    // Every line ends in a newline, so a missing one at the end of the file doesn't hide a match
+    let read = |file: &str, commit: &str| {
+        let mut content = repo
+            .get_file_content(file, commit)
+            .map(|content| String::from_utf8_lossy(&content).to_string())
+            .unwrap_or_default();
+        if !content.is_empty() && !content.ends_with('\n') {
+            content.push('\n');
+        }
+        content
+    };
+
+    for file in affected_files {
+        let new_content = read(file, revert_commit);
+        if new_content.is_empty() {
+            continue;
+        }
+
+        // Lines the revert added, numbered in the revert commit
+        let old_content = read(file, &revert_parent);
+        let added: HashSet<usize> = TextDiff::from_lines(&old_content, &new_content)
+            .iter_all_changes()
+            .filter(|change| change.tag() == ChangeTag::Insert)
+            .filter_map(|change| change.new_index())
+            .collect();
+        if added.is_empty() {
+            continue;
+        }
+
+        let source_content = read(file, &source_commit);
+        if source_content.is_empty() {
+            continue;
+        }
+        let options = GitAiBlameOptions {
+            newest_commit: Some(source_commit.clone()),
+            use_prompt_hashes_as_names: true,
+            return_human_authors_as_human: true,
+            no_output: true,
+            ..Default::default()
+        };
+        let Ok((line_authors, prompt_records)) = repo.blame(file, &options) else {
+            debug_log(&format!("Failed to blame {} at {}", file, source_commit));
+            continue;
+        };
+
+        // Added lines that are unchanged from the source state, by the prompt that wrote them
+        let mut restored: BTreeMap<String, Vec<u32>> = BTreeMap::new();
+        for change in TextDiff::from_lines(&source_content, &new_content).iter_all_changes() {
+            let (Some(source_index), Some(new_index)) = (change.old_index(), change.new_index())
+            else {
+                continue;
+            };
+            if !added.contains(&new_index) {
+                continue;
+            }
+            let Some(author) = line_authors.get(&(source_index as u32 + 1)) else {
+                continue;
+            };
+            if let Some(prompt) = prompt_records.get(author) {
+                authorship_log
+                    .metadata
+                    .prompts
+                    .entry(author.clone())
+                    .or_insert_with(|| prompt.clone());
+                restored
+                    .entry(author.clone())
+                    .or_default()
+                    .push(new_index as u32 + 1);
+            }
+        }
+        if restored.is_empty() {
+            continue;
+        }
+
+        let restored_lines: Vec<u32> = restored.values().flatten().copied().collect();
+        let restored_ranges = LineRange::compress_lines(&restored_lines);
+        let attestation = authorship_log.get_or_create_file(file);
+        for entry in &mut attestation.entries {
+            entry.remove_line_ranges(&restored_ranges);
+        }
+        attestation
+            .entries
+            .retain(|entry| !entry.line_ranges.is_empty());
+        for (hash, lines) in restored {
+            attestation.entries.push(AttestationEntry::new(
+                hash,
+                LineRange::compress_lines(&lines),
+            ));
+        }
+    }
+
+    if let Ok(source_log) = get_reference_as_authorship_log_v3(repo, reverted_commit) {
+        authorship_log.inherit_lineage(&source_log.metadata.lineage);
+    }
+    authorship_log.mark_confidence(AttributionConfidence::Reconstructed);
+
+    let authorship_json = authorship_log
+        .serialize_to_string()
+        .map_err(|_| GitAiError::Generic("Failed to serialize authorship log".to_string()))?;
+    crate::git::refs::notes_add(repo, revert_commit, &authorship_json)?;
+
+    debug_log(&format!(
+        "Saved authorship log for revert commit {} ({} files)",
+        revert_commit,
+        authorship_log.attestations.len()
+    ));
+    Ok(())
+}
+
+/// The state a reverted commit was made on: the parent named by `reversing changes made to`
+/// in the revert's message when a merge was reverted, and the first parent otherwise
+fn revert_source_commit(
+    repo: &Repository,
+    reverted_commit: &str,
+    revert_commit: &str,
+) -> Result<String, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("show".to_string());
+    args.push("-s".to_string());
+    args.push("--no-notes".to_string());
+    args.push("--format=%B".to_string());
+    args.push(revert_commit.to_string());
+    let message = String::from_utf8(crate::git::repository::exec_git(&args)?.stdout)?;
+    let message = message.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if let Some((_, rest)) = message.split_once("reversing changes made to ")
+        && let Some(mainline) = rest.split(['.', ' ']).next()
+        && let Ok(commit) = repo.revparse_single(mainline)
+    {
+        return Ok(commit.id());
+    }
+    Ok(repo
+        .find_commit(reverted_commit.to_string())?
+        .parent(0)?
+        .id())
+}
+
+/// Notes of the commits being rewritten, skipping commits without one
+fn authorship_logs_for_commits(repo: &Repository, commits: &[String]) -> Vec<AuthorshipLog> {
+    commits
+        .iter()
+        .filter_map(|sha| get_reference_as_authorship_log_v3(repo, sha).ok())
+        .collect()
+}
+
+/// Write the empty-commit note for each of `commits` that changed no files
+fn write_empty_commit_notes(repo: &Repository, commits: &[String]) -> Result<(), GitAiError> {
+    for commit in commits {
+        if repo.find_commit(commit.clone())?.is_empty()? {
+            write_empty_commit_note(repo, commit)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_empty_commit_note(repo: &Repository, commit: &str) -> Result<(), GitAiError> {
+    let authorship_json = AuthorshipLog::for_empty_commit(commit)
+        .serialize_to_string()
+        .map_err(|_| GitAiError::Generic("Failed to serialize authorship log".to_string()))?;
+    crate::git::refs::notes_add(repo, commit, &authorship_json)?;
+    debug_log(&format!("Marked commit {} as empty", commit));
     Ok(())
 }
 
@@ -697,9 +1106,20 @@ pub fn rewrite_authorship_after_commit_amend(
     let pathspecs: Vec<String> = changed_files.into_iter().collect();
 
     if pathspecs.is_empty() {
-        // No files changed, just update the base commit SHA
+        // No files changed, just update the base commit SHA. An amend that leaves the commit
+        // empty keeps only the commit message authorship.
+        let empty = repo
+            .find_commit(amended_commit.to_string())
+            .and_then(|commit| commit.is_empty())
+            .unwrap_or(false);
         let mut authorship_log = match get_reference_as_authorship_log_v3(repo, original_commit) {
+            Ok(log) if empty => {
+                let mut empty_log = AuthorshipLog::for_empty_commit(amended_commit);
+                empty_log.metadata.commit_message = log.metadata.commit_message;
+                empty_log
+            }
             Ok(log) => log,
+            Err(_) if empty => AuthorshipLog::for_empty_commit(amended_commit),
             Err(_) => {
                 let mut log = AuthorshipLog::new();
                 log.metadata.base_commit_sha = amended_commit.to_string();
@@ -753,6 +1173,23 @@ pub fn rewrite_authorship_after_commit_amend(
     let (mut authorship_log, initial_attributions) =
         working_va.to_authorship_log_and_initial_working_log(committed_files)?;
 
+    // Lineage recorded since the original commit is newer than the one in its note
+    let mut working_lineage = BTreeMap::new();
+    for checkpoint in repo
+        .storage
+        .working_log_for_base_commit(original_commit)
+        .read_all_checkpoints()
+        .unwrap_or_default()
+    {
+        for entry in checkpoint.entries {
+            working_lineage.insert(entry.file, entry.lineage);
+        }
+    }
+    authorship_log.inherit_lineage(&working_lineage);
+    if let Ok(original_log) = get_reference_as_authorship_log_v3(repo, original_commit) {
+        authorship_log.inherit_lineage(&original_log.metadata.lineage);
+    }
+
     // Update base commit SHA
     authorship_log.metadata.base_commit_sha = amended_commit.to_string();
 
@@ -987,7 +1424,7 @@ fn transform_attributions_to_final_state(
     final_state: HashMap<String, String>,
     original_head_state: Option<&crate::authorship::virtual_attribution::VirtualAttributions>,
 ) -> Result<crate::authorship::virtual_attribution::VirtualAttributions, GitAiError> {
-    use crate::authorship::attribution_tracker::AttributionTracker;
+    use crate::authorship::attribution_tracker::{AttributionAlgorithm, AttributionTracker};
     use crate::authorship::virtual_attribution::VirtualAttributions;
 
     let tracker = AttributionTracker::new();
@@ -0,0 +1,242 @@
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Time strata a sample is spread over, fewer when the sample is small
+pub const MAX_STRATA: usize = 10;
+
+/// Normal quantile of a two-sided 95% confidence interval
+const Z_95: f64 = 1.96;
+
+/// How many commits of a long history to read: `--sample 10%` or `--max-commits N`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SampleSize {
+    Fraction(f64),
+    MaxCommits(usize),
+}
+
+impl SampleSize {
+    /// `10%` or `10` for ten percent; anything outside (0, 100] is rejected
+    pub fn parse_percent(value: &str) -> Option<Self> {
+        let percent = value.trim_end_matches('%').parse::<f64>().ok()?;
+        (percent > 0.0 && percent <= 100.0).then_some(SampleSize::Fraction(percent / 100.0))
+    }
+
+    /// Commits to read out of `population`: at least one, at most all of them
+    pub fn commits(&self, population: usize) -> usize {
+        let commits = match self {
+            SampleSize::Fraction(fraction) => (population as f64 * fraction).ceil() as usize,
+            SampleSize::MaxCommits(max) => *max,
+        };
+        commits.clamp(1.min(population), population)
+    }
+}
+
+/// A run of consecutive commits in time, and the ones picked from it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stratum {
+    pub population: usize,
+    pub sampled: Vec<String>,
+}
+
+/// Split `commits` (sha and commit time) into runs of equal size by time and pick from each in
+/// proportion to its size, so every period of the history is represented. Commits are picked by
+/// a hash of their sha, so the same history always gives the same sample.
+pub fn stratified_sample(commits: &[(String, i64)], size: SampleSize) -> Vec<Stratum> {
+    let population = commits.len();
+    let sample_size = size.commits(population);
+    if sample_size == 0 {
+        return Vec::new();
+    }
+
+    let mut by_time: Vec<&(String, i64)> = commits.iter().collect();
+    by_time.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+    // Two or more picks per stratum, so each has a spread to measure
+    let strata = (sample_size / 2).clamp(1, MAX_STRATA);
+    let mut result = Vec::with_capacity(strata);
+    let mut start = 0;
+    for index in 0..strata {
+        let end = population * (index + 1) / strata;
+        let members = &by_time[start..end];
+        let picks = ((sample_size as f64 * members.len() as f64 / population as f64).round()
+            as usize)
+            .clamp(1.min(members.len()), members.len());
+
+        let mut ranked: Vec<(Vec<u8>, &String)> = members
+            .iter()
+            .map(|(sha, _)| (Sha256::digest(sha.as_bytes()).to_vec(), sha))
+            .collect();
+        ranked.sort();
+        result.push(Stratum {
+            population: members.len(),
+            sampled: ranked
+                .into_iter()
+                .take(picks)
+                .map(|(_, sha)| sha.clone())
+                .collect(),
+        });
+        start = end;
+    }
+    result
+}
+
+/// An estimated count or share with its 95% confidence interval
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Estimate {
+    pub value: f64,
+    pub low: f64,
+    pub high: f64,
+}
+
+/// Estimate a total over the whole history from per-commit values. `strata` holds each
+/// stratum's population and the values of its sampled commits.
+pub fn estimate_total(strata: &[(usize, Vec<f64>)]) -> Estimate {
+    let value: f64 = strata
+        .iter()
+        .filter(|(_, values)| !values.is_empty())
+        .map(|(population, values)| *population as f64 * mean(values))
+        .sum();
+    let margin = Z_95 * total_variance(strata).sqrt();
+    Estimate {
+        value,
+        low: (value - margin).max(0.0),
+        high: value + margin,
+    }
+}
+
+/// Estimate the ratio of two totals, like the AI share of added lines, from per-commit
+/// (numerator, denominator) pairs. The interval comes from the spread of each commit's
+/// numerator around the ratio applied to its denominator.
+pub fn estimate_ratio(strata: &[(usize, Vec<(f64, f64)>)]) -> Estimate {
+    let split = |pick: fn(&(f64, f64)) -> f64| -> Vec<(usize, Vec<f64>)> {
+        strata
+            .iter()
+            .map(|(population, pairs)| (*population, pairs.iter().map(pick).collect()))
+            .collect()
+    };
+    let numerator = estimate_total(&split(|pair| pair.0)).value;
+    let denominator = estimate_total(&split(|pair| pair.1)).value;
+    if denominator == 0.0 {
+        return Estimate {
+            value: 0.0,
+            low: 0.0,
+            high: 0.0,
+        };
+    }
+
+    let ratio = numerator / denominator;
+    let residuals: Vec<(usize, Vec<f64>)> = strata
+        .iter()
+        .map(|(population, pairs)| {
+            (
+                *population,
+                pairs.iter().map(|(y, x)| y - ratio * x).collect(),
+            )
+        })
+        .collect();
+    let margin = Z_95 * total_variance(&residuals).sqrt() / denominator;
+    Estimate {
+        value: ratio,
+        low: (ratio - margin).max(0.0),
+        high: (ratio + margin).min(1.0),
+    }
+}
+
+/// Variance of the stratified estimate of a total, with the finite population correction
+fn total_variance(strata: &[(usize, Vec<f64>)]) -> f64 {
+    strata
+        .iter()
+        .filter(|(_, values)| values.len() > 1)
+        .map(|(population, values)| {
+            let population = *population as f64;
+            let sampled = values.len() as f64;
+            let mean = mean(values);
+            let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (sampled - 1.0);
+            population * population * (1.0 - sampled / population) * variance / sampled
+        })
+        .sum()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn history(commits: usize) -> Vec<(String, i64)> {
+        (0..commits)
+            .map(|i| (format!("{:040x}", i), i as i64 * 60))
+            .collect()
+    }
+
+    #[test]
+    fn test_sample_size() {
+        assert_eq!(
+            SampleSize::parse_percent("10%"),
+            Some(SampleSize::Fraction(0.1))
+        );
+        assert_eq!(SampleSize::parse_percent("0%"), None);
+        assert_eq!(SampleSize::parse_percent("150"), None);
+        assert_eq!(SampleSize::Fraction(0.1).commits(1001), 101);
+        assert_eq!(SampleSize::MaxCommits(50).commits(10), 10);
+        assert_eq!(SampleSize::Fraction(0.01).commits(3), 1);
+        assert_eq!(SampleSize::MaxCommits(5).commits(0), 0);
+    }
+
+    #[test]
+    fn test_stratified_sample_covers_history_and_is_deterministic() {
+        let commits = history(1000);
+        let strata = stratified_sample(&commits, SampleSize::MaxCommits(100));
+        assert_eq!(strata.len(), MAX_STRATA);
+        assert_eq!(strata.iter().map(|s| s.population).sum::<usize>(), 1000);
+        assert_eq!(strata.iter().map(|s| s.sampled.len()).sum::<usize>(), 100);
+        // Each stratum picks from its own period
+        let first_period: Vec<&String> = commits[..100].iter().map(|(sha, _)| sha).collect();
+        assert!(
+            strata[0]
+                .sampled
+                .iter()
+                .all(|sha| first_period.contains(&sha))
+        );
+
+        assert_eq!(
+            strata,
+            stratified_sample(&commits, SampleSize::MaxCommits(100))
+        );
+    }
+
+    #[test]
+    fn test_estimates_are_exact_for_a_full_sample() {
+        let strata = vec![(3, vec![1.0, 2.0, 3.0]), (2, vec![10.0, 0.0])];
+        let total = estimate_total(&strata);
+        assert_eq!(total.value, 16.0);
+        assert_eq!((total.low, total.high), (16.0, 16.0));
+
+        let ratio = estimate_ratio(&[(2, vec![(1.0, 2.0), (3.0, 6.0)])]);
+        assert_eq!(ratio.value, 0.5);
+    }
+
+    #[test]
+    fn test_estimate_interval_covers_the_true_total() {
+        // Every fourth commit adds 8 lines, the rest 0: 250 commits, 2000 lines in all
+        let commits = history(1000);
+        let value = |sha: &String| {
+            let index = usize::from_str_radix(sha, 16).unwrap();
+            if index.is_multiple_of(4) { 8.0 } else { 0.0 }
+        };
+        let strata: Vec<(usize, Vec<f64>)> = stratified_sample(&commits, SampleSize::Fraction(0.2))
+            .into_iter()
+            .map(|stratum| {
+                (
+                    stratum.population,
+                    stratum.sampled.iter().map(value).collect(),
+                )
+            })
+            .collect();
+        let total = estimate_total(&strata);
+        assert!(total.low < 2000.0 && 2000.0 < total.high, "{:?}", total);
+        assert!(total.high - total.low < 2000.0);
+    }
+}
@@ -1,11 +1,14 @@
-use crate::authorship::authorship_log::LineRange;
-use crate::authorship::authorship_log_serialization::AuthorshipLog;
+use crate::authorship::authorship_log::{CommitMessageAuthorship, LineRange};
+use crate::authorship::authorship_log_serialization::{AuthorshipLog, is_false};
 use crate::authorship::transcript::Message;
+use crate::config::Settings;
 use crate::error::GitAiError;
 use crate::git::refs::get_authorship;
 use crate::git::repository::Repository;
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
+
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ToolModelHeadlineStats {
@@ -32,7 +35,7 @@ pub struct AuthorshipAnalysis {
     pub tool_model_breakdown: BTreeMap<String, ToolModelHeadlineStats>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct CommitStats {
     #[serde(default)]
     pub human_additions: u32, // Lines written only by humans
@@ -54,6 +57,25 @@ pub struct CommitStats {
     pub ai_deletions: u32,
     #[serde(default)]
     pub tool_model_breakdown: BTreeMap<String, ToolModelHeadlineStats>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit_message: Option<CommitMessageAuthorship>,
+    /// The commit changed no files
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub empty_commit: bool,
+    /// Lines added and deleted in generated files (lockfiles, vendored code, ...). They are
+    /// part of the git_diff counts but left out of the AI/human split.
+    #[serde(default)]
+    pub generated_added_lines: u32,
+    #[serde(default)]
+    pub generated_deleted_lines: u32,
+}
+
+impl CommitStats {
+    /// Added lines outside generated files, the ones split between AI and humans
+    pub fn counted_added_lines(&self) -> u32 {
+        self.git_diff_added_lines
+            .saturating_sub(self.generated_added_lines)
+    }
 }
 
 pub fn stats_command(
@@ -105,9 +127,9 @@ pub fn write_stats_to_terminal(stats: &CommitStats, print: bool) -> String {
     // Set maximum bar width to 40 characters
     let bar_width: usize = 40;
 
-    // Handle deletion-only commits (no additions)
-    if stats.git_diff_added_lines == 0 && stats.git_diff_deleted_lines > 0 {
-        // Show gray bar for deletion-only commit
+    // Handle empty and deletion-only commits (no additions)
+    if let Some(label) = no_additions_label(stats) {
+        // Show gray bar for commits without additions
         let mut progress_bar = String::new();
         progress_bar.push_str("you  ");
         progress_bar.push_str("\x1b[90m"); // Gray color
@@ -121,14 +143,16 @@ pub fn write_stats_to_terminal(stats: &CommitStats, print: bool) -> String {
             println!("{}", progress_bar);
         }
 
-        // Show "(no additions)" message below the bar
-        let no_additions_msg = format!("     \x1b[90m{:^40}\x1b[0m", "(no additions)");
+        // Show why there is nothing to split below the bar
+        let no_additions_msg = format!("     \x1b[90m{:^40}\x1b[0m", label);
         output.push_str(&no_additions_msg);
         output.push('\n');
         if print {
             println!("{}", no_additions_msg);
         }
         // No percentage line or AI stats for deletion-only commits
+        write_generated_line(stats, &mut output, print);
+        write_commit_message_line(stats, &mut output, print);
         return output;
     }
 
@@ -299,9 +323,49 @@ pub fn write_stats_to_terminal(stats: &CommitStats, print: bool) -> String {
             println!("{}", ai_acceptance_str);
         }
     }
+    write_generated_line(stats, &mut output, print);
+    write_commit_message_line(stats, &mut output, print);
     return output;
 }
 
+/// What to show instead of the you/ai split when the commit adds no lines
+fn no_additions_label(stats: &CommitStats) -> Option<&'static str> {
+    if stats.empty_commit {
+        Some("(empty commit)")
+    } else if stats.counted_added_lines() == 0 && stats.generated_added_lines > 0 {
+        Some("(generated files only)")
+    } else if stats.git_diff_added_lines == 0 && stats.git_diff_deleted_lines > 0 {
+        Some("(no additions)")
+    } else {
+        None
+    }
+}
+
+fn write_generated_line(stats: &CommitStats, output: &mut String, print: bool) {
+    if stats.generated_added_lines > 0 {
+        let generated_line = format!(
+            "     \x1b[90m+{} lines in generated files not counted\x1b[0m",
+            stats.generated_added_lines
+        );
+        output.push_str(&generated_line);
+        output.push('\n');
+        if print {
+            println!("{}", generated_line);
+        }
+    }
+}
+
+fn write_commit_message_line(stats: &CommitStats, output: &mut String, print: bool) {
+    if let Some(commit_message) = &stats.commit_message {
+        let message_line = format!("     \x1b[90mmessage: {}\x1b[0m", commit_message.label());
+        output.push_str(&message_line);
+        output.push('\n');
+        if print {
+            println!("{}", message_line);
+        }
+    }
+}
+
 /// Format stats into a Markdown string for display
 #[allow(dead_code)]
 pub fn write_stats_to_markdown(stats: &CommitStats) -> String {
@@ -310,9 +374,9 @@ pub fn write_stats_to_markdown(stats: &CommitStats) -> String {
     // Set maximum bar width to 40 characters
     let bar_width: usize = 40;
 
-    // Handle deletion-only commits (no additions)
-    if stats.git_diff_added_lines == 0 && stats.git_diff_deleted_lines > 0 {
-        // Show gray bar for deletion-only commit
+    // Handle empty and deletion-only commits (no additions)
+    if let Some(label) = no_additions_label(stats) {
+        // Show gray bar for commits without additions
         let mut progress_bar = String::new();
         progress_bar.push_str("you&nbsp;&nbsp;");
         progress_bar.push_str(&"&nbsp;".repeat(bar_width)); // Gray bar
@@ -321,8 +385,8 @@ pub fn write_stats_to_markdown(stats: &CommitStats) -> String {
         output.push_str(&progress_bar);
         output.push('\n');
 
-        // Show "(no additions)" message below the bar
-        let no_additions_msg = format!("{}{:^40}", "&nbsp;".repeat(6), "(no additions)");
+        // Show why there is nothing to split below the bar
+        let no_additions_msg = format!("{}{:^40}", "&nbsp;".repeat(6), label);
         output.push_str(&no_additions_msg);
         output.push('\n');
         // No percentage line or AI stats for deletion-only commits
@@ -475,10 +539,202 @@ pub fn stats_for_commit_stats(
     // Step 1: get the diff between this commit and its parent ON refname (if more than one parent)
     // If initial than everything is additions
     // We want the count here git shows +111 -55
-    let (git_diff_added_lines, git_diff_deleted_lines) = get_git_diff_stats(repo, commit_sha)?;
+    let settings = repo.settings();
+    let diff_stats = get_git_diff_stats(repo, commit_sha, settings)?;
 
     // Step 2: get the authorship log for this commit
-    let authorship_log = get_authorship(repo, &commit_sha);
+    let mut stats = stats_from_diff(settings, diff_stats, get_authorship(repo, commit_sha))?;
+
+    // Notes written before empty commits were marked don't say so
+    stats.empty_commit = stats.empty_commit
+        || (stats.git_diff_added_lines == 0
+            && stats.git_diff_deleted_lines == 0
+            && repo
+                .find_commit(commit_sha.to_string())
+                .and_then(|commit| commit.is_empty())
+                .unwrap_or(false));
+    Ok(stats)
+}
+
+/// Stats of everything not committed yet, as if it were all committed now: the working
+/// directory against HEAD, untracked files included, attributed by the working log
+pub fn pending_stats(repo: &Repository) -> Result<CommitStats, GitAiError> {
+    let settings = repo.settings();
+    let head = repo.revparse_single("HEAD").map(|head| head.id()).ok();
+
+    let mut args = repo.global_args_for_exec();
+    args.push("diff".to_string());
+    args.push("--numstat".to_string());
+    args.push(head.clone().unwrap_or_else(|| EMPTY_TREE.to_string()));
+    let output = crate::git::repository::exec_git(&args)?;
+    let mut diff_stats = parse_numstat(&String::from_utf8(output.stdout)?, settings);
+
+    // Untracked files are all additions; binary ones aren't counted, like in numstat
+    let workdir = repo.workdir()?;
+    for file in repo.untracked_files()? {
+        let Ok(content) = std::fs::read(workdir.join(&file)) else {
+            continue;
+        };
+        let Ok(content) = String::from_utf8(content) else {
+            continue;
+        };
+        let lines = content.lines().count() as u32;
+        if settings.is_generated_path(&file) {
+            diff_stats.generated_added_lines += lines;
+        } else {
+            diff_stats.added_lines += lines;
+        }
+    }
+
+    let authorship_log = working_log_authorship(repo, head)?;
+    stats_from_diff(settings, diff_stats, authorship_log)
+}
+
+/// What `git commit` records on top of HEAD
+#[derive(Debug, Clone, Copy)]
+pub enum CommitScope<'a> {
+    /// The index
+    Index,
+    /// The tracked working tree (`git commit -a`)
+    All,
+    /// The working tree of these pathspecs, leaving the rest of the index out
+    /// (`git commit <paths>`, `--only`)
+    Only(&'a [String]),
+    /// The index plus the working tree of these pathspecs (`git commit --include <paths>`)
+    Include(&'a [String]),
+}
+
+/// Stats of what `git commit` is about to record in `scope`, attributed by the working log.
+/// Only files in the diff are attributed; lines of a file that stay unstaged still count.
+pub fn staged_stats(repo: &Repository, scope: CommitScope) -> Result<CommitStats, GitAiError> {
+    let settings = repo.settings();
+    let head = repo.revparse_single("HEAD").map(|head| head.id()).ok();
+    let base = head.clone().unwrap_or_else(|| EMPTY_TREE.to_string());
+    let numstat = |cached: bool, pathspecs: &[String]| -> Result<String, GitAiError> {
+        let mut args = repo.global_args_for_exec();
+        args.push("diff".to_string());
+        args.push("--numstat".to_string());
+        if cached {
+            args.push("--cached".to_string());
+        }
+        args.push(base.clone());
+        if !pathspecs.is_empty() {
+            args.push("--".to_string());
+            args.extend(pathspecs.iter().cloned());
+        }
+        Ok(String::from_utf8(
+            crate::git::repository::exec_git(&args)?.stdout,
+        )?)
+    };
+    let numstat_paths = |output: &str| -> HashSet<String> {
+        output
+            .lines()
+            .filter_map(|line| line.splitn(3, '\t').nth(2))
+            .map(numstat_new_path)
+            .collect()
+    };
+
+    let output = match scope {
+        CommitScope::Index => numstat(true, &[])?,
+        CommitScope::All => numstat(false, &[])?,
+        CommitScope::Only(pathspecs) => numstat(false, pathspecs)?,
+        CommitScope::Include(pathspecs) => {
+            // The pathspecs' files as in the working tree, every other file as staged
+            let mut output = numstat(false, pathspecs)?;
+            let included = numstat_paths(&output);
+            for line in numstat(true, &[])?.lines() {
+                let path = line.splitn(3, '\t').nth(2).map(numstat_new_path);
+                if path.is_some_and(|path| !included.contains(&path)) {
+                    output.push_str(line);
+                    output.push('\n');
+                }
+            }
+            output
+        }
+    };
+    let diff_stats = parse_numstat(&output, settings);
+    let files = numstat_paths(&output);
+
+    let authorship_log = working_log_authorship(repo, head)?.map(|mut log| {
+        log.attestations
+            .retain(|file| files.contains(&file.file_path));
+        log
+    });
+    stats_from_diff(settings, diff_stats, authorship_log)
+}
+
+/// The authorship log the working log on `head` would become if it were committed now
+fn working_log_authorship(
+    repo: &Repository,
+    head: Option<String>,
+) -> Result<Option<AuthorshipLog>, GitAiError> {
+    let base_commit = head.unwrap_or_else(|| "initial".to_string());
+    let working_log = repo.storage.working_log_for_base_commit(&base_commit);
+    let checkpoints = working_log.read_all_checkpoints()?;
+    Ok((!checkpoints.is_empty()).then(|| {
+        AuthorshipLog::from_working_log_with_base_commit_and_human_author(
+            &checkpoints,
+            &base_commit,
+            None,
+            Some(&working_log),
+        )
+    }))
+}
+
+/// One line for commit message footers and shell prompts, e.g.
+/// `AI 62% (145/234 lines), 2 agents, waited 3m12s`. Scripts parse this, so the format only
+/// ever gains fields at the end.
+pub fn write_stats_oneline(stats: &CommitStats) -> String {
+    let total = stats.counted_added_lines();
+    let ai = stats.ai_additions.min(total);
+    let percent = if total > 0 {
+        (ai as f64 / total as f64 * 100.0).round() as u32
+    } else {
+        0
+    };
+    let agents = stats
+        .tool_model_breakdown
+        .values()
+        .filter(|tool| tool.ai_additions + tool.mixed_additions + tool.ai_deletions > 0)
+        .count();
+    format!(
+        "AI {}% ({}/{} lines), {} agent{}, waited {}",
+        percent,
+        ai,
+        total,
+        agents,
+        if agents == 1 { "" } else { "s" },
+        format_duration_compact(stats.time_waiting_for_ai)
+    )
+}
+
+/// `45s`, `3m12s`, `1h5m0s`
+fn format_duration_compact(seconds: u64) -> String {
+    let (hours, minutes, seconds) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    if hours > 0 {
+        format!("{}h{}m{}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m{}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// Stats from a diff's line counts and the authorship log of its changes
+fn stats_from_diff(
+    settings: &Settings,
+    diff_stats: GitDiffStats,
+    authorship_log: Option<AuthorshipLog>,
+) -> Result<CommitStats, GitAiError> {
+    let git_diff_added_lines = diff_stats.added_lines + diff_stats.generated_added_lines;
+    let git_diff_deleted_lines = diff_stats.deleted_lines + diff_stats.generated_deleted_lines;
+
+    // Generated files are left out of the split
+    let authorship_log = authorship_log.map(|mut log| {
+        log.attestations
+            .retain(|file| !settings.is_generated_path(&file.file_path));
+        log
+    });
 
     // Step 3: For prompts with > 1 messages, sum all the time between user messages and AI messages.
     // if the last message is a human message, don't count anything
@@ -490,13 +746,19 @@ pub fn stats_for_commit_stats(
 
     // Calculate human additions as the difference between total git diff and AI additions
     // This handles cases where there are no AI-authored lines (authorship log is empty)
-    let human_additions = if git_diff_added_lines >= analysis.ai_additions {
-        git_diff_added_lines - analysis.ai_additions
+    let human_additions = if diff_stats.added_lines >= analysis.ai_additions {
+        diff_stats.added_lines - analysis.ai_additions
     } else {
         analysis.human_additions
     };
 
-    let human_deletions = git_diff_deleted_lines.saturating_sub(analysis.ai_deletions);
+    let human_deletions = diff_stats
+        .deleted_lines
+        .saturating_sub(analysis.ai_deletions);
+
+    let empty_commit = authorship_log
+        .as_ref()
+        .is_some_and(|log| log.metadata.empty_commit);
 
     Ok(CommitStats {
         human_additions,
@@ -509,23 +771,46 @@ pub fn stats_for_commit_stats(
         human_deletions,
         ai_deletions: analysis.ai_deletions,
         tool_model_breakdown: analysis.tool_model_breakdown,
+        commit_message: authorship_log.and_then(|log| log.metadata.commit_message),
+        empty_commit,
+        generated_added_lines: diff_stats.generated_added_lines,
+        generated_deleted_lines: diff_stats.generated_deleted_lines,
     })
 }
 
+/// Line counts of a commit's diff, with generated files counted apart
+#[derive(Debug, Default, PartialEq)]
+struct GitDiffStats {
+    added_lines: u32,
+    deleted_lines: u32,
+    generated_added_lines: u32,
+    generated_deleted_lines: u32,
+}
+
 /// Get git diff statistics between commit and its parent
-fn get_git_diff_stats(repo: &Repository, commit_sha: &str) -> Result<(u32, u32), GitAiError> {
+fn get_git_diff_stats(
+    repo: &Repository,
+    commit_sha: &str,
+    settings: &Settings,
+) -> Result<GitDiffStats, GitAiError> {
     // Use git show --numstat to get diff statistics
     let mut args = repo.global_args_for_exec();
     args.push("show".to_string());
     args.push("--numstat".to_string());
     args.push("--format=".to_string()); // No format, just the numstat
+    // Merge commits are diffed against their first parent, matching what their notes attest
+    args.push("-m".to_string());
+    args.push("--first-parent".to_string());
     args.push(commit_sha.to_string());
 
     let output = crate::git::repository::exec_git(&args)?;
     let stdout = String::from_utf8(output.stdout)?;
 
-    let mut added_lines = 0u32;
-    let mut deleted_lines = 0u32;
+    Ok(parse_numstat(&stdout, settings))
+}
+
+fn parse_numstat(stdout: &str, settings: &Settings) -> GitDiffStats {
+    let mut stats = GitDiffStats::default();
 
     // Parse numstat output
     for line in stdout.lines() {
@@ -541,21 +826,49 @@ fn get_git_diff_stats(repo: &Repository, commit_sha: &str) -> Result<(u32, u32),
         // Parse numstat format: "added\tdeleted\tfilename"
         let parts: Vec<&str> = line.split('\t').collect();
         if parts.len() >= 2 {
+            let generated = parts
+                .get(2)
+                .is_some_and(|path| settings.is_generated_path(&numstat_new_path(path)));
+            let (added_lines, deleted_lines) = if generated {
+                (
+                    &mut stats.generated_added_lines,
+                    &mut stats.generated_deleted_lines,
+                )
+            } else {
+                (&mut stats.added_lines, &mut stats.deleted_lines)
+            };
+
             // Parse added lines
             if let Ok(added) = parts[0].parse::<u32>() {
-                added_lines += added;
+                *added_lines += added;
             }
 
             // Parse deleted lines (handle "-" for binary files)
             if parts[1] != "-" {
                 if let Ok(deleted) = parts[1].parse::<u32>() {
-                    deleted_lines += deleted;
+                    *deleted_lines += deleted;
                 }
             }
         }
     }
 
-    Ok((added_lines, deleted_lines))
+    stats
+}
+
+/// The destination of a numstat path, which shows renames as `old => new` or
+/// `dir/{old => new}/file`
+pub fn numstat_new_path(path: &str) -> String {
+    match (path.find('{'), path.find(" => "), path.find('}')) {
+        (Some(open), Some(arrow), Some(close)) if open < arrow && arrow < close => format!(
+            "{}{}{}",
+            &path[..open],
+            &path[arrow + 4..close],
+            &path[close + 1..]
+        )
+        .replace("//", "/"),
+        (_, Some(arrow), _) => path[arrow + 4..].to_string(),
+        _ => path.to_string(),
+    }
 }
 
 /// Analyze authorship log to extract statistics
@@ -594,7 +907,7 @@ pub fn analyze_authorship_log(
                 }
 
                 // Count accepted lines (this is a simplified approach)
-                // In a real implementation, you might want to track acceptance more precisely
+                // `git-ai retention` measures how many of these survive later commits
                 analysis.ai_accepted += lines_in_entry; // For now, assume all AI lines are accepted
 
                 let key = format!(
@@ -711,6 +1024,10 @@ mod tests {
             human_deletions: 0,
             ai_deletions: 0,
             tool_model_breakdown: BTreeMap::new(),
+            commit_message: None,
+            empty_commit: false,
+            generated_added_lines: 0,
+            generated_deleted_lines: 0,
         };
 
         let mixed_output = write_stats_to_terminal(&stats, true);
@@ -728,6 +1045,10 @@ mod tests {
             human_deletions: 0,
             ai_deletions: 0,
             tool_model_breakdown: BTreeMap::new(),
+            commit_message: None,
+            empty_commit: false,
+            generated_added_lines: 0,
+            generated_deleted_lines: 0,
         };
 
         let ai_only_output = write_stats_to_terminal(&ai_stats, true);
@@ -745,6 +1066,10 @@ mod tests {
             human_deletions: 0,
             ai_deletions: 0,
             tool_model_breakdown: BTreeMap::new(),
+            commit_message: None,
+            empty_commit: false,
+            generated_added_lines: 0,
+            generated_deleted_lines: 0,
         };
 
         let human_only_output = write_stats_to_terminal(&human_stats, true);
@@ -762,6 +1087,10 @@ mod tests {
             human_deletions: 0,
             ai_deletions: 0,
             tool_model_breakdown: BTreeMap::new(),
+            commit_message: None,
+            empty_commit: false,
+            generated_added_lines: 0,
+            generated_deleted_lines: 0,
         };
 
         let minimal_human_output = write_stats_to_terminal(&minimal_human_stats, true);
@@ -779,12 +1108,136 @@ mod tests {
             human_deletions: 0,
             ai_deletions: 0,
             tool_model_breakdown: BTreeMap::new(),
+            commit_message: None,
+            empty_commit: false,
+            generated_added_lines: 0,
+            generated_deleted_lines: 0,
         };
 
         let deletion_only_output = write_stats_to_terminal(&deletion_only_stats, true);
         assert_debug_snapshot!(deletion_only_output);
     }
 
+    #[test]
+    fn test_terminal_stats_shows_commit_message_source() {
+        use crate::authorship::authorship_log::CommitMessageSource;
+        use crate::authorship::working_log::AgentId;
+
+        let mut stats = CommitStats {
+            human_additions: 2,
+            mixed_additions: 0,
+            ai_additions: 3,
+            ai_accepted: 3,
+            time_waiting_for_ai: 0,
+            git_diff_deleted_lines: 0,
+            git_diff_added_lines: 5,
+            human_deletions: 0,
+            ai_deletions: 0,
+            tool_model_breakdown: BTreeMap::new(),
+            commit_message: None,
+            empty_commit: false,
+            generated_added_lines: 0,
+            generated_deleted_lines: 0,
+        };
+        assert!(!write_stats_to_terminal(&stats, false).contains("message:"));
+
+        stats.commit_message = Some(CommitMessageAuthorship {
+            source: CommitMessageSource::Ai,
+            agent_id: Some(AgentId {
+                tool: "cursor".to_string(),
+                id: "session".to_string(),
+                model: "gpt-4".to_string(),
+            }),
+        });
+        let output = write_stats_to_terminal(&stats, false);
+        assert!(output.contains("message: AI (cursor)"));
+    }
+
+    #[test]
+    fn test_stats_display_for_empty_commit() {
+        let stats = CommitStats {
+            human_additions: 0,
+            mixed_additions: 0,
+            ai_additions: 0,
+            ai_accepted: 0,
+            time_waiting_for_ai: 0,
+            git_diff_deleted_lines: 0,
+            git_diff_added_lines: 0,
+            human_deletions: 0,
+            ai_deletions: 0,
+            tool_model_breakdown: BTreeMap::new(),
+            commit_message: None,
+            empty_commit: true,
+            generated_added_lines: 0,
+            generated_deleted_lines: 0,
+        };
+
+        let terminal = write_stats_to_terminal(&stats, false);
+        assert!(terminal.contains("(empty commit)"));
+        assert!(!terminal.contains('%'));
+        let markdown = write_stats_to_markdown(&stats);
+        assert!(markdown.contains("(empty commit)"));
+        assert!(!markdown.contains('%'));
+    }
+
+    #[test]
+    fn test_numstat_counts_generated_files_apart() {
+        let settings = Settings {
+            generated_paths: vec!["gen/*".to_string()],
+            ..Settings::default()
+        };
+        let numstat = "10\t2\tsrc/main.rs\n\
+                       300\t120\tCargo.lock\n\
+                       5\t0\tgen/{old => new}/api.rs\n\
+                       -\t-\tlogo.png\n\
+                       4\t1\tweb/{ => app}/package-lock.json\n";
+
+        assert_eq!(
+            parse_numstat(numstat, &settings),
+            GitDiffStats {
+                added_lines: 10,
+                deleted_lines: 2,
+                generated_added_lines: 309,
+                generated_deleted_lines: 121,
+            }
+        );
+        assert_eq!(numstat_new_path("a.rs => b.rs"), "b.rs");
+        assert_eq!(
+            numstat_new_path("web/{ => app}/package-lock.json"),
+            "web/app/package-lock.json"
+        );
+        assert_eq!(
+            numstat_new_path("src/{lib => core}/mod.rs"),
+            "src/core/mod.rs"
+        );
+        assert_eq!(numstat_new_path("src/{old => }/mod.rs"), "src/mod.rs");
+    }
+
+    #[test]
+    fn test_stats_display_for_generated_only_commit() {
+        let stats = CommitStats {
+            human_additions: 0,
+            mixed_additions: 0,
+            ai_additions: 0,
+            ai_accepted: 0,
+            time_waiting_for_ai: 0,
+            git_diff_deleted_lines: 40,
+            git_diff_added_lines: 120,
+            human_deletions: 0,
+            ai_deletions: 0,
+            tool_model_breakdown: BTreeMap::new(),
+            commit_message: None,
+            empty_commit: false,
+            generated_added_lines: 120,
+            generated_deleted_lines: 40,
+        };
+
+        let terminal = write_stats_to_terminal(&stats, false);
+        assert!(terminal.contains("(generated files only)"));
+        assert!(terminal.contains("+120 lines in generated files not counted"));
+        assert!(!terminal.contains('%'));
+    }
+
     #[test]
     fn test_markdown_stats_display() {
         // Test with mixed human/AI stats
@@ -799,6 +1252,10 @@ mod tests {
             human_deletions: 0,
             ai_deletions: 0,
             tool_model_breakdown: BTreeMap::new(),
+            commit_message: None,
+            empty_commit: false,
+            generated_added_lines: 0,
+            generated_deleted_lines: 0,
         };
 
         let mixed_output = write_stats_to_markdown(&stats);
@@ -816,6 +1273,10 @@ mod tests {
             human_deletions: 0,
             ai_deletions: 0,
             tool_model_breakdown: BTreeMap::new(),
+            commit_message: None,
+            empty_commit: false,
+            generated_added_lines: 0,
+            generated_deleted_lines: 0,
         };
 
         let ai_only_output = write_stats_to_markdown(&ai_stats);
@@ -833,6 +1294,10 @@ mod tests {
             human_deletions: 0,
             ai_deletions: 0,
             tool_model_breakdown: BTreeMap::new(),
+            commit_message: None,
+            empty_commit: false,
+            generated_added_lines: 0,
+            generated_deleted_lines: 0,
         };
 
         let human_only_output = write_stats_to_markdown(&human_stats);
@@ -850,6 +1315,10 @@ mod tests {
             human_deletions: 0,
             ai_deletions: 0,
             tool_model_breakdown: BTreeMap::new(),
+            commit_message: None,
+            empty_commit: false,
+            generated_added_lines: 0,
+            generated_deleted_lines: 0,
         };
 
         let minimal_human_output = write_stats_to_markdown(&minimal_human_stats);
@@ -867,6 +1336,10 @@ mod tests {
             human_deletions: 0,
             ai_deletions: 0,
             tool_model_breakdown: BTreeMap::new(),
+            commit_message: None,
+            empty_commit: false,
+            generated_added_lines: 0,
+            generated_deleted_lines: 0,
         };
 
         let deletion_only_output = write_stats_to_markdown(&deletion_only_stats);
@@ -921,6 +1394,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_oneline_format() {
+        let mut stats = CommitStats {
+            human_additions: 89,
+            ai_additions: 145,
+            git_diff_added_lines: 244,
+            generated_added_lines: 10,
+            time_waiting_for_ai: 192,
+            ..Default::default()
+        };
+        for (key, lines) in [("claude::opus", 100), ("cursor::gpt", 45)] {
+            stats.tool_model_breakdown.insert(
+                key.to_string(),
+                ToolModelHeadlineStats {
+                    ai_additions: lines,
+                    ..Default::default()
+                },
+            );
+        }
+        assert_eq!(
+            write_stats_oneline(&stats),
+            "AI 62% (145/234 lines), 2 agents, waited 3m12s"
+        );
+
+        stats.tool_model_breakdown.remove("cursor::gpt");
+        stats.time_waiting_for_ai = 3900;
+        assert_eq!(
+            write_stats_oneline(&stats),
+            "AI 62% (145/234 lines), 1 agent, waited 1h5m0s"
+        );
+        assert_eq!(
+            write_stats_oneline(&CommitStats::default()),
+            "AI 0% (0/0 lines), 0 agents, waited 0s"
+        );
+    }
+
     #[test]
     fn test_stats_for_mixed_commit() {
         let tmp_repo = TmpRepo::new().unwrap();
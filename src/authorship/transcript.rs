@@ -1,4 +1,6 @@
+use crate::config::TranscriptStore;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 /// Represents a single message in an AI transcript
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -189,3 +191,123 @@ impl Default for AiTranscript {
         Self::new()
     }
 }
+
+/// Characters kept of each message that survives summarization
+const SUMMARY_MESSAGE_CHARS: usize = 1000;
+
+/// What's left of a transcript once it has been summarized for the note: message counts and
+/// the files the session touched. The first user and last assistant message stay in the
+/// prompt record's messages.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TranscriptSummary {
+    pub message_count: u32,
+    pub user_messages: u32,
+    pub assistant_messages: u32,
+    pub tool_uses: u32,
+    #[serde(default)]
+    pub edited_files: Vec<String>,
+}
+
+/// Cut a transcript down to its first user message and last assistant message (both
+/// truncated) and summarize the rest
+pub fn summarize_transcript(
+    messages: &[Message],
+    edited_files: Vec<String>,
+) -> (Vec<Message>, TranscriptSummary) {
+    let mut summary = TranscriptSummary {
+        message_count: messages.len() as u32,
+        edited_files,
+        ..Default::default()
+    };
+    for message in messages {
+        match message {
+            Message::User { .. } => summary.user_messages += 1,
+            Message::Assistant { .. } => summary.assistant_messages += 1,
+            Message::ToolUse { .. } => summary.tool_uses += 1,
+        }
+    }
+
+    let first_user = messages
+        .iter()
+        .position(|m| matches!(m, Message::User { .. }));
+    let last_assistant = messages
+        .iter()
+        .rposition(|m| matches!(m, Message::Assistant { .. }));
+    let mut kept: Vec<usize> = first_user.into_iter().chain(last_assistant).collect();
+    kept.sort();
+
+    let kept_messages = kept
+        .into_iter()
+        .map(|idx| match &messages[idx] {
+            Message::User { text, timestamp } => Message::User {
+                text: truncate_message(text),
+                timestamp: timestamp.clone(),
+            },
+            Message::Assistant { text, timestamp } => Message::Assistant {
+                text: truncate_message(text),
+                timestamp: timestamp.clone(),
+            },
+            other => other.clone(),
+        })
+        .collect();
+
+    (kept_messages, summary)
+}
+
+fn truncate_message(text: &str) -> String {
+    match text.char_indices().nth(SUMMARY_MESSAGE_CHARS) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
+/// Marks message text that `hashes_only` redaction already replaced with its digest
+const REDACTED_PREFIX: &str = "sha256:";
+
+/// Cut a transcript down to what `store` allows. Redacting an already redacted transcript
+/// again, at the same or a weaker level, leaves it as it is.
+pub fn redact_transcript(messages: Vec<Message>, store: TranscriptStore) -> Vec<Message> {
+    match store {
+        TranscriptStore::Full => messages,
+        TranscriptStore::MessagesOnly => messages
+            .into_iter()
+            .filter(|message| !message.is_tool_use())
+            .collect(),
+        TranscriptStore::HashesOnly => messages
+            .into_iter()
+            .map(|message| match message {
+                Message::User { text, timestamp } => Message::User {
+                    text: digest(&text),
+                    timestamp,
+                },
+                Message::Assistant { text, timestamp } => Message::Assistant {
+                    text: digest(&text),
+                    timestamp,
+                },
+                Message::ToolUse {
+                    name,
+                    input,
+                    timestamp,
+                } => {
+                    let input = match input {
+                        serde_json::Value::String(text) => digest(&text),
+                        other => digest(&other.to_string()),
+                    };
+                    Message::ToolUse {
+                        name,
+                        input: serde_json::Value::String(input),
+                        timestamp,
+                    }
+                }
+            })
+            .collect(),
+        TranscriptStore::None => Vec::new(),
+    }
+}
+
+fn digest(text: &str) -> String {
+    if text.len() == REDACTED_PREFIX.len() + 64 && text.starts_with(REDACTED_PREFIX) {
+        return text.to_string();
+    }
+    format!("{}{:x}", REDACTED_PREFIX, Sha256::digest(text.as_bytes()))
+}
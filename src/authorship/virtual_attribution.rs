@@ -1,5 +1,5 @@
 use crate::authorship::attribution_tracker::{
-    Attribution, LineAttribution, line_attributions_to_attributions,
+    Attribution, AttributionAlgorithm, LineAttribution, line_attributions_to_attributions,
 };
 use crate::authorship::authorship_log::PromptRecord;
 use crate::authorship::working_log::CheckpointKind;
@@ -350,7 +350,7 @@ impl VirtualAttributions {
                         &agent_id.id,
                         &agent_id.tool,
                     );
-                checkpoint_prompts
+                let prompt = checkpoint_prompts
                     .entry(author_id.clone())
                     .or_insert_with(|| crate::authorship::authorship_log::PromptRecord {
                         agent_id: agent_id.clone(),
@@ -364,7 +364,12 @@ impl VirtualAttributions {
                         total_deletions: 0,
                         accepted_lines: 0,
                         overriden_lines: 0,
+                        summary: None,
+                        environments: Vec::new(),
+                        generated_at: None,
                     });
+                prompt.add_environment(checkpoint.environment.as_ref());
+                prompt.add_generation_time(checkpoint.timestamp);
             }
 
             // Collect attributions from checkpoint entries
@@ -1,6 +1,9 @@
 use crate::authorship::attribution_tracker::{Attribution, LineAttribution};
+use crate::authorship::prompt_lineage::PromptLineage;
 use crate::authorship::transcript::AiTranscript;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
 use std::fmt;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -18,6 +21,9 @@ pub struct WorkingLogEntry {
     pub attributions: Vec<Attribution>,
     #[serde(default)]
     pub line_attributions: Vec<LineAttribution>,
+    /// Earlier prompts whose lines each prompt in this file rewrote
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub lineage: PromptLineage,
 }
 
 impl WorkingLogEntry {
@@ -33,6 +39,7 @@ impl WorkingLogEntry {
             blob_sha,
             attributions,
             line_attributions,
+            lineage: PromptLineage::new(),
         }
     }
 }
@@ -139,6 +146,95 @@ pub struct Checkpoint {
     pub line_stats: CheckpointLineStats,
     #[serde(default)]
     pub api_version: String,
+    /// Position of this checkpoint in its working log, starting at 1. Working logs written
+    /// before sequences existed read as 0.
+    #[serde(default)]
+    pub sequence: u64,
+    /// Machine and tool versions that made the checkpoint, when `record_environment` is on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub environment: Option<EnvironmentFingerprint>,
+    /// Reconstructed after the fact (e.g. from Copilot telemetry) rather than recorded live
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub recovered: bool,
+    /// Changed files this checkpoint left out, and why
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped_files: Vec<SkippedFile>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// A changed file a checkpoint didn't attribute
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub file: String,
+    pub reason: SkipReason,
+    /// Size on disk, in bytes
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SkipReason {
+    /// Bigger than `large_file_threshold_kb`, so never read
+    Large,
+    /// Holds a null byte
+    Binary,
+}
+
+impl SkipReason {
+    pub fn to_str(self) -> &'static str {
+        match self {
+            SkipReason::Large => "large",
+            SkipReason::Binary => "binary",
+        }
+    }
+}
+
+/// Which machine and tool versions produced a checkpoint, for audit trails. The hostname is
+/// hashed so notes don't reveal machine names but checkpoints from one machine still match.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct EnvironmentFingerprint {
+    pub host: String,
+    /// e.g. `linux-x86_64`
+    pub os: String,
+    pub git_ai_version: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub agent_version: Option<String>,
+}
+
+impl EnvironmentFingerprint {
+    pub fn current(agent_version: Option<String>) -> Self {
+        Self {
+            host: hostname_hash(hostname().as_deref().unwrap_or("unknown")),
+            os: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+            git_ai_version: env!("CARGO_PKG_VERSION").to_string(),
+            agent_version,
+        }
+    }
+}
+
+fn hostname_hash(hostname: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(hostname.trim().to_lowercase().as_bytes());
+    format!("{:x}", hasher.finalize())[..16].to_string()
+}
+
+#[cfg(unix)]
+fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    let result = unsafe { libc::gethostname(buf.as_mut_ptr() as *mut libc::c_char, buf.len()) };
+    if result != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+    String::from_utf8(buf[..len].to_vec()).ok()
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
 }
 
 impl Checkpoint {
@@ -163,10 +259,50 @@ impl Checkpoint {
             agent_id: None,
             line_stats: CheckpointLineStats::default(),
             api_version: CHECKPOINT_API_VERSION.to_string(),
+            sequence: 0,
+            environment: None,
+            recovered: false,
+            skipped_files: Vec::new(),
         }
     }
 }
 
+/// Logical time of a checkpoint. Attributions are ordered by `(sequence, timestamp_ms)` rather
+/// than wall-clock time alone, so edits keep their order when the clock jumps backwards (VM
+/// resume, NTP correction).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct CheckpointClock {
+    pub sequence: u64,
+    pub timestamp_ms: u64,
+}
+
+impl CheckpointClock {
+    /// Clock for the checkpoint that follows `previous`
+    pub fn next(previous: &[Checkpoint]) -> Self {
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64;
+        Self::next_at(previous, timestamp_ms)
+    }
+
+    pub fn next_at(previous: &[Checkpoint], timestamp_ms: u64) -> Self {
+        let last_sequence = previous.iter().map(|c| c.sequence).max().unwrap_or(0);
+        Self {
+            sequence: last_sequence + 1,
+            timestamp_ms,
+        }
+    }
+
+    /// Value stored in `Attribution::ts`: the sequence in the high 64 bits and the wall-clock
+    /// milliseconds in the low 64 bits, so comparing attribution timestamps compares
+    /// `(sequence, timestamp_ms)`. Attributions from older working logs hold plain
+    /// milliseconds and therefore sort before any sequenced attribution.
+    pub fn attribution_ts(&self) -> u128 {
+        ((self.sequence as u128) << 64) | self.timestamp_ms as u128
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -324,4 +460,73 @@ mod tests {
         assert_eq!(deserialized_agent.tool, "cursor");
         assert_eq!(deserialized_agent.id, "session-abc123");
     }
+
+    #[test]
+    fn test_checkpoint_clock_survives_clock_going_backwards() {
+        use crate::authorship::attribution_tracker::{
+            AttributionAlgorithm, AttributionTracker, attributions_to_line_attributions,
+        };
+
+        let mut first = Checkpoint::new(
+            CheckpointKind::AiAgent,
+            String::new(),
+            "ai".to_string(),
+            vec![],
+        );
+        let first_clock = CheckpointClock::next_at(&[], 5_000);
+        first.sequence = first_clock.sequence;
+
+        // The wall clock jumped back between the two checkpoints
+        let second_clock = CheckpointClock::next_at(&[first], 1_000);
+        assert_eq!(second_clock.sequence, 2);
+        assert!(second_clock > first_clock);
+        assert!(second_clock.attribution_ts() > first_clock.attribution_ts());
+        // Attributions from working logs without sequences sort first
+        assert!(first_clock.attribution_ts() > 5_000);
+
+        let old_content = "let x = 1;\n";
+        let new_content = "let x = 2;\n";
+        let tracker = AttributionTracker::new();
+        let attributions = tracker
+            .update_attributions(
+                old_content,
+                new_content,
+                &[Attribution::new(
+                    0,
+                    old_content.len(),
+                    "ai".to_string(),
+                    first_clock.attribution_ts(),
+                )],
+                "human",
+                second_clock.attribution_ts(),
+            )
+            .unwrap();
+        let lines = attributions_to_line_attributions(&attributions, new_content);
+        assert_eq!(lines.len(), 1);
+        assert_eq!(lines[0].author_id, "human");
+    }
+
+    #[test]
+    fn test_environment_fingerprint() {
+        // Stable across runs and case, without revealing the name
+        assert_eq!(hostname_hash("Build-01"), hostname_hash("build-01\n"));
+        assert_ne!(hostname_hash("build-01"), hostname_hash("build-02"));
+        assert_eq!(hostname_hash("build-01").len(), 16);
+
+        let environment = EnvironmentFingerprint::current(Some("1.0.3".to_string()));
+        assert_eq!(environment.git_ai_version, env!("CARGO_PKG_VERSION"));
+        assert!(environment.os.starts_with(std::env::consts::OS));
+
+        // Checkpoints without one still read, and don't write the field
+        let checkpoint = Checkpoint::new(
+            CheckpointKind::Human,
+            String::new(),
+            "human".to_string(),
+            vec![],
+        );
+        let json = serde_json::to_string(&checkpoint).unwrap();
+        assert!(!json.contains("environment"));
+        let deserialized: Checkpoint = serde_json::from_str(&json).unwrap();
+        assert!(deserialized.environment.is_none());
+    }
 }
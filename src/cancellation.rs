@@ -0,0 +1,317 @@
+//! Cancellation of multi-step hook work on SIGINT, SIGTERM and SIGHUP.
+//!
+//! A hook that writes an authorship note and then moves a working log, or rewrites the notes of
+//! every commit of a rebase, used to die wherever the signal caught it. While hooks run, these
+//! signals now only raise a flag. Multi-step operations check it between steps through the
+//! repository's [`CancellationToken`], registering a [`Compensation`] for each step that
+//! changed something; when a check finds the flag raised, the compensations run newest first
+//! and the operation returns [`GitAiError::Cancelled`]. git-ai then exits the way the signal
+//! would have terminated it.
+
+use crate::error::GitAiError;
+use crate::git::refs::{notes_ref_tip, set_notes_ref_tip};
+use crate::git::repository::Repository;
+use crate::utils::debug_log;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+/// The signal received while the handlers were installed, 0 for none
+static RECEIVED_SIGNAL: AtomicI32 = AtomicI32::new(0);
+
+#[cfg(unix)]
+const CANCEL_SIGNALS: &[libc::c_int] = &[libc::SIGINT, libc::SIGTERM, libc::SIGHUP];
+
+#[cfg(unix)]
+extern "C" fn record_signal(sig: libc::c_int) {
+    // Storing to an atomic is all a signal handler can safely do
+    RECEIVED_SIGNAL.store(sig, Ordering::SeqCst);
+}
+
+/// Turn the cancel signals into a flag instead of terminating the process
+pub fn install_handlers() {
+    #[cfg(unix)]
+    unsafe {
+        let handler = record_signal as *const () as usize;
+        for &sig in CANCEL_SIGNALS {
+            let _ = libc::signal(sig, handler);
+        }
+    }
+}
+
+/// Exit the way the received signal would have terminated git-ai, if one was received
+pub fn exit_if_cancelled() {
+    let sig = RECEIVED_SIGNAL.load(Ordering::SeqCst);
+    if sig == 0 {
+        return;
+    }
+    debug_log(&format!("Exiting after signal {}", sig));
+    #[cfg(unix)]
+    unsafe {
+        libc::signal(sig, libc::SIG_DFL);
+        libc::raise(sig);
+    }
+    std::process::exit(128 + sig);
+}
+
+/// Whether the operation holding it should stop. The token of a repository follows the signal
+/// flag; [`CancellationToken::detached`] makes one that is only cancelled through `cancel`.
+#[derive(Debug, Clone)]
+pub struct CancellationToken {
+    local: Option<Arc<AtomicI32>>,
+}
+
+impl CancellationToken {
+    /// Cancelled by a signal received while the handlers are installed
+    pub fn signals() -> Self {
+        Self { local: None }
+    }
+
+    #[cfg(test)]
+    pub fn detached() -> Self {
+        Self {
+            local: Some(Arc::new(AtomicI32::new(0))),
+        }
+    }
+
+    fn flag(&self) -> &AtomicI32 {
+        self.local.as_deref().unwrap_or(&RECEIVED_SIGNAL)
+    }
+
+    #[cfg(test)]
+    pub fn cancel(&self, sig: i32) {
+        self.flag().store(sig, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.flag().load(Ordering::SeqCst) != 0
+    }
+
+    pub fn check(&self) -> Result<(), GitAiError> {
+        match self.flag().load(Ordering::SeqCst) {
+            0 => Ok(()),
+            sig => Err(GitAiError::Cancelled(sig)),
+        }
+    }
+}
+
+/// Undoes one step of an operation that was cancelled before it finished
+#[derive(Debug, Clone, PartialEq)]
+pub enum Compensation {
+    /// Point the notes ref back at `tip` (delete it for `None`), undoing every note written
+    /// since in one go. Skipped when the ref no longer points at `produced`, the tip the
+    /// operation's own last note left, so notes another git-ai process wrote since survive.
+    RestoreNotesRef {
+        tip: Option<String>,
+        produced: Option<String>,
+    },
+    /// Delete a file or directory the operation created
+    RemovePath(PathBuf),
+}
+
+impl Compensation {
+    /// Restoring the notes ref to where it is now
+    pub fn restore_notes(repo: &Repository) -> Self {
+        let tip = notes_ref_tip(repo);
+        Compensation::RestoreNotesRef {
+            produced: tip.clone(),
+            tip,
+        }
+    }
+
+    fn run(&self, repo: &Repository) -> Result<(), GitAiError> {
+        match self {
+            Compensation::RestoreNotesRef { tip, produced } => {
+                if tip == produced {
+                    return Ok(());
+                }
+                if notes_ref_tip(repo) != *produced {
+                    debug_log("Notes ref moved since this operation wrote to it, keeping it");
+                    return Ok(());
+                }
+                set_notes_ref_tip(repo, tip.as_deref(), produced.as_deref())
+            }
+            Compensation::RemovePath(path) if path.is_dir() => Ok(fs::remove_dir_all(path)?),
+            Compensation::RemovePath(path) if path.exists() => Ok(fs::remove_file(path)?),
+            Compensation::RemovePath(_) => Ok(()),
+        }
+    }
+}
+
+/// The compensations of one multi-step operation. Call [`CleanupScope::check`] between steps
+/// and [`CleanupScope::finish`] once the last one is done.
+pub struct CleanupScope<'a> {
+    repo: &'a Repository,
+    operation: &'static str,
+    compensations: Vec<Compensation>,
+}
+
+impl<'a> CleanupScope<'a> {
+    pub fn new(repo: &'a Repository, operation: &'static str) -> Self {
+        Self {
+            repo,
+            operation,
+            compensations: Vec::new(),
+        }
+    }
+
+    /// Register how to undo the step about to run
+    pub fn register(&mut self, compensation: Compensation) {
+        self.compensations.push(compensation);
+    }
+
+    /// Record that the operation just wrote a note, so rolling back restores the notes ref only
+    /// while it still points at this write
+    pub fn notes_written(&mut self) {
+        let current = notes_ref_tip(self.repo);
+        if let Some(Compensation::RestoreNotesRef { produced, .. }) = self
+            .compensations
+            .iter_mut()
+            .rev()
+            .find(|c| matches!(c, Compensation::RestoreNotesRef { .. }))
+        {
+            *produced = current;
+        }
+    }
+
+    /// Roll back the steps done so far if the operation was cancelled
+    pub fn check(&mut self) -> Result<(), GitAiError> {
+        if let Err(e) = self.repo.cancellation.check() {
+            self.roll_back();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Every step is done; nothing will be rolled back anymore
+    pub fn finish(mut self) {
+        self.compensations.clear();
+    }
+
+    fn roll_back(&mut self) {
+        debug_log(&format!(
+            "{} cancelled, undoing {} step(s)",
+            self.operation,
+            self.compensations.len()
+        ));
+        while let Some(compensation) = self.compensations.pop() {
+            if let Err(e) = compensation.run(self.repo) {
+                debug_log(&format!("Failed to undo {:?}: {}", compensation, e));
+            }
+        }
+    }
+}
+
+impl Drop for CleanupScope<'_> {
+    fn drop(&mut self) {
+        // An operation that bailed out with an error after the signal arrived is rolled back too
+        if !self.compensations.is_empty() && self.repo.cancellation.is_cancelled() {
+            self.roll_back();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::refs::{notes_add, show_authorship_note};
+    use crate::git::test_utils::TmpRepo;
+
+    fn cancellable_repo(tmp_repo: &TmpRepo) -> Repository {
+        let mut repo = tmp_repo.gitai_repo().clone();
+        repo.cancellation = CancellationToken::detached();
+        repo
+    }
+
+    #[test]
+    fn test_cancelled_scope_rolls_back_newest_first() {
+        let (tmp_repo, _, _) = TmpRepo::new_with_base_commit().unwrap();
+        let repo = cancellable_repo(&tmp_repo);
+        let head = tmp_repo.head_commit_sha().unwrap();
+        let original_note = show_authorship_note(&repo, &head);
+        assert!(original_note.is_some());
+
+        let mut cleanup = CleanupScope::new(&repo, "test");
+        cleanup.register(Compensation::restore_notes(&repo));
+        notes_add(&repo, &head, "rewritten").unwrap();
+        cleanup.notes_written();
+        let partial = repo.storage.working_logs.join("partial");
+        cleanup.register(Compensation::RemovePath(partial.clone()));
+        fs::create_dir_all(&partial).unwrap();
+        cleanup.check().unwrap();
+
+        repo.cancellation.cancel(libc::SIGINT);
+        assert!(matches!(
+            cleanup.check(),
+            Err(GitAiError::Cancelled(sig)) if sig == libc::SIGINT
+        ));
+        assert!(!partial.exists());
+        assert_eq!(show_authorship_note(&repo, &head), original_note);
+    }
+
+    #[test]
+    fn test_rollback_keeps_notes_written_by_another_process() {
+        let (tmp_repo, _, _) = TmpRepo::new_with_base_commit().unwrap();
+        let repo = cancellable_repo(&tmp_repo);
+        let head = tmp_repo.head_commit_sha().unwrap();
+
+        let mut cleanup = CleanupScope::new(&repo, "test");
+        cleanup.register(Compensation::restore_notes(&repo));
+        notes_add(&repo, &head, "rewritten").unwrap();
+        cleanup.notes_written();
+        // Another git-ai process writes a note after this operation's last one
+        notes_add(tmp_repo.gitai_repo(), &head, "concurrent").unwrap();
+
+        repo.cancellation.cancel(libc::SIGINT);
+        assert!(cleanup.check().is_err());
+        assert_eq!(
+            show_authorship_note(&repo, &head).as_deref().map(str::trim),
+            Some("concurrent")
+        );
+    }
+
+    #[test]
+    fn test_finished_scope_keeps_its_work() {
+        let (tmp_repo, _, _) = TmpRepo::new_with_base_commit().unwrap();
+        let repo = cancellable_repo(&tmp_repo);
+        let head = tmp_repo.head_commit_sha().unwrap();
+
+        let mut cleanup = CleanupScope::new(&repo, "test");
+        cleanup.register(Compensation::restore_notes(&repo));
+        notes_add(&repo, &head, "rewritten").unwrap();
+        cleanup.finish();
+
+        repo.cancellation.cancel(libc::SIGTERM);
+        assert_eq!(
+            show_authorship_note(&repo, &head).as_deref().map(str::trim),
+            Some("rewritten")
+        );
+    }
+
+    #[test]
+    fn test_post_commit_leaves_no_note_when_cancelled() {
+        let (tmp_repo, mut lines, _) = TmpRepo::new_with_base_commit().unwrap();
+        let base = tmp_repo.head_commit_sha().unwrap();
+        lines.append("AI line\n").unwrap();
+        tmp_repo
+            .trigger_checkpoint_with_ai("Claude", None, None)
+            .unwrap();
+        tmp_repo.commit_with_message("AI commit").unwrap();
+        let head = tmp_repo.head_commit_sha().unwrap();
+        crate::git::refs::notes_remove(tmp_repo.gitai_repo(), std::slice::from_ref(&head)).unwrap();
+
+        let repo = cancellable_repo(&tmp_repo);
+        repo.cancellation.cancel(libc::SIGINT);
+        let result = crate::authorship::post_commit::post_commit(
+            &repo,
+            Some(base),
+            head.clone(),
+            "Test User".to_string(),
+            true,
+        );
+
+        assert!(matches!(result, Err(GitAiError::Cancelled(_))));
+        assert_eq!(show_authorship_note(&repo, &head), None);
+    }
+}
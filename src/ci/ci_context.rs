@@ -1,4 +1,6 @@
-use crate::authorship::rebase_authorship::rewrite_authorship_after_squash_or_rebase;
+use crate::authorship::rebase_authorship::{
+    rewrite_authorship_after_squash_or_rebase, synthesize_merge_commit_note,
+};
 use crate::error::GitAiError;
 use crate::git::repository::Repository;
 use crate::git::sync_authorship::fetch_authorship_notes;
@@ -33,15 +35,23 @@ impl CiContext {
                 base_ref,
                 base_sha: _,
             } => {
-                // Only handle squash or rebase-like merges.
-                // Skip simple merge commits (2+ parents) and fast-forward merges (merge commit == head).
+                // Merge commits (2+ parents) get a note summarizing the merged branch.
+                // Skip fast-forward merges (merge commit == head), whose commits already have notes.
                 let merge_commit = self.repo.find_commit(merge_commit_sha.clone())?;
                 let parent_count = merge_commit.parents().count();
                 if parent_count > 1 {
                     println!(
-                        "{} has {} parents (simple merge)",
+                        "{} has {} parents (merge commit). Fetching authorship history",
                         merge_commit_sha, parent_count
                     );
+                    fetch_authorship_notes(&self.repo, "origin")?;
+                    if synthesize_merge_commit_note(&self.repo, merge_commit_sha)? {
+                        println!("Wrote authorship for merge commit. Pushing authorship...");
+                        self.repo.push_authorship("origin")?;
+                        println!("Pushed authorship. Done.");
+                    } else {
+                        println!("Merge commit already has authorship. Done.");
+                    }
                     return Ok(());
                 }
 
@@ -0,0 +1,374 @@
+use crate::authorship::authorship_log::AttributionConfidence;
+use crate::authorship::authorship_log_serialization::AuthorshipLog;
+use crate::authorship::rebase_authorship::synthesize_merge_commit_note;
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::refs::{commits_with_ai_notes, notes_add};
+use crate::git::repo_storage::{BackfillRefProgress, BackfillState};
+use crate::git::repository::{Repository, exec_git};
+use std::collections::HashSet;
+use std::time::Instant;
+
+const DEFAULT_CHUNK_SIZE: usize = 500;
+
+#[derive(Debug, Clone)]
+pub struct BackfillOptions {
+    /// Refs to backfill. Empty means every local branch.
+    pub refs: Vec<String>,
+    pub chunk_size: usize,
+    pub resume: bool,
+}
+
+impl Default for BackfillOptions {
+    fn default() -> Self {
+        Self {
+            refs: Vec::new(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            resume: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BackfillSummary {
+    pub refs: usize,
+    pub commits_checked: usize,
+    pub notes_written: usize,
+}
+
+pub fn handle_backfill(args: &[String]) {
+    let mut options = BackfillOptions::default();
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--resume" => {
+                options.resume = true;
+                i += 1;
+            }
+            "--chunk-size" => {
+                match args
+                    .get(i + 1)
+                    .and_then(|value| value.parse::<usize>().ok())
+                {
+                    Some(chunk_size) if chunk_size > 0 => options.chunk_size = chunk_size,
+                    _ => {
+                        eprintln!("--chunk-size requires a positive number");
+                        std::process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            arg if arg.starts_with('-') => {
+                eprintln!("Unknown backfill argument: {}", arg);
+                std::process::exit(1);
+            }
+            refname => {
+                options.refs.push(refname.to_string());
+                i += 1;
+            }
+        }
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match run(&repo, &options) {
+        Ok(summary) => {
+            println!(
+                "Backfilled {} note(s) across {} ref(s) ({} commits checked)",
+                summary.notes_written, summary.refs, summary.commits_checked
+            );
+        }
+        Err(e) => {
+            eprintln!("Backfill failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Write an authorship note for every commit reachable from the given refs that doesn't have one.
+/// Commits are processed oldest-first in chunks; progress is persisted after every chunk to
+/// .git/ai/backfill_state so an interrupted run can pick up where it stopped with `--resume`.
+pub fn run(repo: &Repository, options: &BackfillOptions) -> Result<BackfillSummary, GitAiError> {
+    let storage = &repo.storage;
+
+    let mut state = if options.resume {
+        storage.read_backfill_state()
+    } else {
+        storage.clear_backfill_state()?;
+        BackfillState::default()
+    };
+
+    let refs = if options.refs.is_empty() {
+        local_branches(repo)?
+    } else {
+        options
+            .refs
+            .iter()
+            .map(|refname| resolve_refname(repo, refname))
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let mut noted = commits_with_ai_notes(repo)?;
+    let working_log_bases = storage.working_log_base_commits()?;
+    let mut summary = BackfillSummary {
+        refs: refs.len(),
+        ..Default::default()
+    };
+
+    for refname in &refs {
+        let mut progress = state.refs.get(refname).cloned().unwrap_or_default();
+
+        // Resume after the last processed commit if it is still part of the ref's history.
+        // Otherwise (e.g. the branch was rewritten) start over; commits that already have
+        // notes are cheap to skip.
+        let resume_from = progress
+            .last_processed
+            .clone()
+            .filter(|last| is_ancestor(repo, last, refname));
+        if resume_from.is_none() {
+            progress = BackfillRefProgress::default();
+        }
+
+        let commits = commits_oldest_first(repo, refname, resume_from.as_deref())?;
+        let total = progress.processed + commits.len();
+        if commits.is_empty() {
+            eprintln!("{}: up to date ({} commits)", refname, total);
+            progress.total = total;
+            progress.completed = true;
+            state.refs.insert(refname.clone(), progress);
+            storage.write_backfill_state(&state)?;
+            continue;
+        }
+
+        let started_at = Instant::now();
+        let mut done_this_run = 0;
+        for chunk in commits.chunks(options.chunk_size) {
+            for (sha, parents) in chunk {
+                summary.commits_checked += 1;
+                if noted.contains(sha) {
+                    continue;
+                }
+                // Oldest first, so the merged branches already have their notes
+                if parents.len() > 1 && synthesize_merge_commit_note(repo, sha)? {
+                    noted.insert(sha.clone());
+                    summary.notes_written += 1;
+                    continue;
+                }
+                let first_parent = parents.first().map(String::as_str);
+                let note = build_note(repo, first_parent, &working_log_bases)?;
+                let note_json = note.serialize_to_string().map_err(|_| {
+                    GitAiError::Generic("Failed to serialize authorship log".to_string())
+                })?;
+                notes_add(repo, sha, &note_json)?;
+                noted.insert(sha.clone());
+                summary.notes_written += 1;
+            }
+
+            done_this_run += chunk.len();
+            progress.last_processed = chunk.last().map(|(sha, _)| sha.clone());
+            progress.processed += chunk.len();
+            progress.total = total;
+            progress.completed = progress.processed >= total;
+            state.refs.insert(refname.clone(), progress.clone());
+            storage.write_backfill_state(&state)?;
+
+            eprintln!(
+                "{}: {}/{} ({:.1}%) ETA {}",
+                refname,
+                progress.processed,
+                total,
+                progress.processed as f64 / total as f64 * 100.0,
+                format_eta(
+                    started_at.elapsed().as_secs_f64(),
+                    done_this_run,
+                    total - progress.processed
+                )
+            );
+        }
+    }
+
+    Ok(summary)
+}
+
+/// Commits that git-ai didn't see get the note post_commit would have written: attributions
+/// from a leftover working log on the parent if there is one, otherwise an empty (all human) log.
+fn build_note(
+    repo: &Repository,
+    first_parent: Option<&str>,
+    working_log_bases: &HashSet<String>,
+) -> Result<AuthorshipLog, GitAiError> {
+    let base_commit = first_parent.unwrap_or("initial");
+
+    if working_log_bases.contains(base_commit) {
+        let working_log = repo.storage.working_log_for_base_commit(base_commit);
+        let checkpoints = working_log.read_all_checkpoints()?;
+        if !checkpoints.is_empty() {
+            // The working log may include edits that never made it into this commit
+            let mut log = AuthorshipLog::from_working_log_with_base_commit_and_human_author(
+                &checkpoints,
+                base_commit,
+                None,
+                None,
+            );
+            log.mark_confidence(AttributionConfidence::Heuristic);
+            if repo.settings().ignore_prompts {
+                log.clear_transcripts();
+            }
+            return Ok(log);
+        }
+    }
+
+    let mut log = AuthorshipLog::new();
+    log.metadata.base_commit_sha = base_commit.to_string();
+    Ok(log)
+}
+
+/// (sha, parents) for every commit reachable from `refname` but not from `exclude`,
+/// oldest first
+fn commits_oldest_first(
+    repo: &Repository,
+    refname: &str,
+    exclude: Option<&str>,
+) -> Result<Vec<(String, Vec<String>)>, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("rev-list".to_string());
+    args.push("--reverse".to_string());
+    args.push("--topo-order".to_string());
+    args.push("--parents".to_string());
+    args.push(refname.to_string());
+    if let Some(exclude) = exclude {
+        args.push(format!("^{}", exclude));
+    }
+
+    let output = exec_git(&args)?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let mut shas = line.split_whitespace();
+            let sha = shas.next()?.to_string();
+            Some((sha, shas.map(|parent| parent.to_string()).collect()))
+        })
+        .collect())
+}
+
+fn is_ancestor(repo: &Repository, ancestor: &str, refname: &str) -> bool {
+    let mut args = repo.global_args_for_exec();
+    args.push("merge-base".to_string());
+    args.push("--is-ancestor".to_string());
+    args.push(ancestor.to_string());
+    args.push(refname.to_string());
+    exec_git(&args).is_ok()
+}
+
+fn local_branches(repo: &Repository) -> Result<Vec<String>, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("for-each-ref".to_string());
+    args.push("--format=%(refname)".to_string());
+    args.push("refs/heads".to_string());
+
+    let output = exec_git(&args)?;
+    let stdout = String::from_utf8(output.stdout)?;
+    Ok(stdout.lines().map(|line| line.to_string()).collect())
+}
+
+/// Expand short names like `main` to `refs/heads/main` so state keys are stable
+fn resolve_refname(repo: &Repository, refname: &str) -> Result<String, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("rev-parse".to_string());
+    args.push("--symbolic-full-name".to_string());
+    args.push(refname.to_string());
+
+    let output = exec_git(&args)?;
+    let full_name = String::from_utf8(output.stdout)?.trim().to_string();
+    if full_name.is_empty() {
+        // Not a symbolic ref (e.g. a commit sha); use it as given
+        return Ok(refname.to_string());
+    }
+    Ok(full_name)
+}
+
+fn format_eta(elapsed_secs: f64, done: usize, remaining: usize) -> String {
+    if remaining == 0 {
+        return "done".to_string();
+    }
+    if done == 0 {
+        return "unknown".to_string();
+    }
+    let eta_secs = (elapsed_secs / done as f64 * remaining as f64).round() as u64;
+    let minutes = eta_secs / 60;
+    let seconds = eta_secs % 60;
+    if minutes > 0 {
+        format!("{}m {}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_utils::TmpRepo;
+
+    #[test]
+    fn test_format_eta() {
+        assert_eq!(format_eta(10.0, 100, 0), "done");
+        assert_eq!(format_eta(0.0, 0, 10), "unknown");
+        assert_eq!(format_eta(10.0, 100, 50), "5s");
+        assert_eq!(format_eta(60.0, 10, 25), "2m 30s");
+    }
+
+    #[test]
+    fn test_backfill_writes_missing_notes_and_resumes() {
+        let (tmp_repo, mut file, _) = TmpRepo::new_with_base_commit().unwrap();
+        let repo = tmp_repo.gitai_repo();
+
+        // Commits made with plain git never get notes
+        for i in 0..3 {
+            file.append(&format!("line {}\n", i)).unwrap();
+            tmp_repo
+                .git_command(&["commit", "-am", &format!("commit {}", i)])
+                .unwrap();
+        }
+        let head = tmp_repo.get_head_commit_sha().unwrap();
+        assert!(crate::git::refs::get_authorship(repo, &head).is_none());
+
+        let options = BackfillOptions {
+            chunk_size: 2,
+            ..Default::default()
+        };
+        let summary = run(repo, &options).unwrap();
+        assert_eq!(summary.commits_checked, 4);
+        assert_eq!(summary.notes_written, 3);
+        assert!(crate::git::refs::get_authorship(repo, &head).is_some());
+
+        let state = repo.storage.read_backfill_state();
+        let progress = state.refs.values().next().unwrap();
+        assert!(progress.completed);
+        assert_eq!(progress.last_processed.as_deref(), Some(head.as_str()));
+
+        // Resuming a finished backfill only picks up new commits
+        file.append("one more\n").unwrap();
+        tmp_repo
+            .git_command(&["commit", "-am", "after backfill"])
+            .unwrap();
+        let resumed = run(
+            repo,
+            &BackfillOptions {
+                resume: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(resumed.commits_checked, 1);
+        assert_eq!(resumed.notes_written, 1);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::commands::hooks::commit_hooks::get_commit_default_author;
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::refs::{commits_with_ai_notes, show_authorship_note};
+use crate::git::repository::{Repository, exec_git, exec_git_stdin};
+use crate::git::rewrite_log::{MergeSquashEvent, RewriteLogEvent};
+use crate::utils::debug_log;
+use std::collections::{HashMap, HashSet};
+
+/// Newly fetched commits checked against a deleted branch when looking for its squash commit
+const MAX_SQUASH_CANDIDATES: usize = 100;
+
+pub fn handle_backfill_squash(args: &[String]) {
+    let mut squash_commit = None;
+    let mut source = None;
+    let mut force = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--from" if i + 1 < args.len() => {
+                source = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--force" => {
+                force = true;
+                i += 1;
+            }
+            arg if squash_commit.is_none() && !arg.starts_with("--") => {
+                squash_commit = Some(arg.to_string());
+                i += 1;
+            }
+            _ => {
+                eprintln!("Unknown backfill-squash argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+    let (Some(squash_commit), Some(source)) = (squash_commit, source) else {
+        eprintln!("Usage: git-ai backfill-squash <merged-commit> --from <branch> [--force]");
+        std::process::exit(1);
+    };
+
+    let mut repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match backfill_squash(&mut repo, &squash_commit, &source, force) {
+        Ok(squash_sha) => println!(
+            "Recovered authorship of {} for squash commit {}",
+            source,
+            &squash_sha[..7.min(squash_sha.len())]
+        ),
+        Err(e) => {
+            eprintln!("Backfill squash failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Write the note of `squash_commit`, a squash of `source` made without git-ai (e.g. by the
+/// server), from the notes of `source`'s commits. Returns the squash commit's sha.
+pub fn backfill_squash(
+    repo: &mut Repository,
+    squash_commit: &str,
+    source: &str,
+    force: bool,
+) -> Result<String, GitAiError> {
+    let squash_sha = resolve_commit(repo, squash_commit)?;
+    let source_head = resolve_commit(repo, source)?;
+
+    if !force && show_authorship_note(repo, &squash_sha).is_some() {
+        return Err(GitAiError::Generic(format!(
+            "{} already has an authorship note; use --force to replace it",
+            squash_commit
+        )));
+    }
+    let commit = repo.find_commit(squash_sha.clone())?;
+    let parents: Vec<String> = commit.parents().map(|parent| parent.id()).collect();
+    let [base_head] = parents.as_slice() else {
+        return Err(GitAiError::Generic(format!(
+            "{} has {} parents; only a squash commit (one parent) can be backfilled",
+            squash_commit,
+            parents.len()
+        )));
+    };
+    if is_ancestor(repo, &source_head, &squash_sha) {
+        return Err(GitAiError::Generic(format!(
+            "{} is already in {}'s history, so it wasn't squashed",
+            source, squash_commit
+        )));
+    }
+
+    let base_branch = repo
+        .head()
+        .ok()
+        .and_then(|head| head.shorthand().ok())
+        .unwrap_or_else(|| "HEAD".to_string());
+    let commit_author = get_commit_default_author(repo, &[]);
+    repo.handle_rewrite_log_event(
+        RewriteLogEvent::merge_squash(MergeSquashEvent::new(
+            source.to_string(),
+            source_head,
+            base_branch,
+            base_head.clone(),
+            Some(squash_sha.clone()),
+        )),
+        commit_author,
+        false,
+        true,
+    );
+    if let Some(e) = repo.hook_errors.pop() {
+        return Err(GitAiError::Generic(e));
+    }
+    Ok(squash_sha)
+}
+
+/// Remote-tracking branches and the commits they point at
+pub fn remote_branch_tips(repo: &Repository) -> HashMap<String, String> {
+    let mut args = repo.global_args_for_exec();
+    args.push("for-each-ref".to_string());
+    args.push("--format=%(refname) %(objectname)".to_string());
+    args.push("refs/remotes".to_string());
+    let Ok(output) = exec_git(&args) else {
+        return HashMap::new();
+    };
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .filter(|(refname, _)| !refname.ends_with("/HEAD"))
+        .map(|(refname, sha)| (refname.to_string(), sha.to_string()))
+        .collect()
+}
+
+/// After a fetch removed remote-tracking branches (the branch was deleted on the server, usually
+/// right after it was merged), look for the squash commit each one became among the fetched
+/// commits and backfill its note. A squash commit is recognized by introducing the same change
+/// as the branch did, by `git patch-id`. Returns (branch, squash commit) pairs.
+pub fn recover_server_squashes(
+    repo: &mut Repository,
+    before: &HashMap<String, String>,
+) -> Vec<(String, String)> {
+    let after = remote_branch_tips(repo);
+    let removed: Vec<(&String, &String)> = before
+        .iter()
+        .filter(|(refname, _)| !after.contains_key(*refname))
+        .collect();
+    if removed.is_empty() {
+        return Vec::new();
+    }
+
+    let noted = commits_with_ai_notes(repo).unwrap_or_default();
+    let candidates: Vec<String> = fetched_commits(repo, before, &after)
+        .into_iter()
+        .filter(|commit| !noted.contains(commit))
+        .collect();
+    if candidates.is_empty() {
+        return Vec::new();
+    }
+
+    let mut candidate_patch_ids: HashMap<&str, Option<String>> = HashMap::new();
+    let mut recovered = Vec::new();
+    for (refname, tip) in removed {
+        // Nothing to recover from a branch none of whose own commits have notes
+        if !branch_commits(repo, tip, &after)
+            .iter()
+            .any(|commit| noted.contains(commit))
+        {
+            continue;
+        }
+        let branch = refname.trim_start_matches("refs/remotes/").to_string();
+
+        for candidate in &candidates {
+            let candidate_patch_id = candidate_patch_ids
+                .entry(candidate)
+                .or_insert_with(|| patch_id(repo, &format!("{}^", candidate), candidate));
+            let Some(candidate_patch_id) = candidate_patch_id.clone() else {
+                continue;
+            };
+            let branch_patch_id = repo
+                .merge_base(tip.clone(), format!("{}^", candidate))
+                .ok()
+                .and_then(|base| patch_id(repo, &base, tip));
+            if branch_patch_id.as_deref() != Some(candidate_patch_id.as_str()) {
+                continue;
+            }
+
+            debug_log(&format!(
+                "{} ({}) was squashed into {}",
+                branch, tip, candidate
+            ));
+            match backfill_squash(repo, candidate, tip, false) {
+                Ok(_) => recovered.push((branch.clone(), candidate.clone())),
+                Err(e) => debug_log(&format!("Failed to backfill squash {}: {}", candidate, e)),
+            }
+            break;
+        }
+    }
+    recovered
+}
+
+/// Single-parent commits the fetch brought in, newest first
+fn fetched_commits(
+    repo: &Repository,
+    before: &HashMap<String, String>,
+    after: &HashMap<String, String>,
+) -> Vec<String> {
+    let old_tips: HashSet<&String> = before.values().collect();
+    let new_tips: Vec<&String> = after
+        .values()
+        .filter(|sha| !old_tips.contains(sha))
+        .collect();
+    if new_tips.is_empty() {
+        return Vec::new();
+    }
+
+    let mut args = repo.global_args_for_exec();
+    args.push("rev-list".to_string());
+    args.push("--max-parents=1".to_string());
+    args.push("--min-parents=1".to_string());
+    args.push(format!("--max-count={}", MAX_SQUASH_CANDIDATES));
+    args.extend(new_tips.into_iter().cloned());
+    args.push("--not".to_string());
+    args.extend(old_tips.into_iter().cloned());
+    rev_list(&args)
+}
+
+/// The deleted branch's own commits: the ones no remaining remote branch has
+fn branch_commits(repo: &Repository, tip: &str, after: &HashMap<String, String>) -> Vec<String> {
+    let mut args = repo.global_args_for_exec();
+    args.push("rev-list".to_string());
+    args.push(tip.to_string());
+    args.push("--not".to_string());
+    args.extend(after.values().cloned());
+    rev_list(&args)
+}
+
+fn rev_list(args: &[String]) -> Vec<String> {
+    exec_git(args)
+        .map(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .lines()
+                .map(|line| line.trim().to_string())
+                .filter(|line| !line.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// `git patch-id` of the change from `from` to `to`; None when there is no change
+fn patch_id(repo: &Repository, from: &str, to: &str) -> Option<String> {
+    let mut args = repo.global_args_for_exec();
+    args.push("diff".to_string());
+    args.push("--no-color".to_string());
+    args.push("--no-ext-diff".to_string());
+    args.push(from.to_string());
+    args.push(to.to_string());
+    let diff = exec_git(&args).ok()?.stdout;
+    if diff.is_empty() {
+        return None;
+    }
+
+    let mut args = repo.global_args_for_exec();
+    args.push("patch-id".to_string());
+    args.push("--stable".to_string());
+    let output = exec_git_stdin(&args, &diff).ok()?;
+    let output = String::from_utf8(output.stdout).ok()?;
+    output.split_whitespace().next().map(str::to_string)
+}
+
+fn resolve_commit(repo: &Repository, rev: &str) -> Result<String, GitAiError> {
+    repo.revparse_single(rev)
+        .and_then(|object| object.peel_to_commit())
+        .map(|commit| commit.id())
+        .map_err(|_| GitAiError::Generic(format!("Unknown commit {}", rev)))
+}
+
+fn is_ancestor(repo: &Repository, ancestor: &str, descendant: &str) -> bool {
+    let mut args = repo.global_args_for_exec();
+    args.push("merge-base".to_string());
+    args.push("--is-ancestor".to_string());
+    args.push(ancestor.to_string());
+    args.push(descendant.to_string());
+    exec_git(&args).is_ok()
+}
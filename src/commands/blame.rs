@@ -1,12 +1,15 @@
-use crate::authorship::authorship_log::PromptRecord;
-use crate::authorship::authorship_log_serialization::AuthorshipLog;
+use crate::authorship::authorship_log::{AttributionConfidence, PromptRecord};
+use crate::authorship::authorship_log_serialization::{AuthorshipLog, generate_short_hash};
 use crate::authorship::working_log::CheckpointKind;
 use crate::error::GitAiError;
+use crate::git::partial_clone;
 use crate::git::refs::get_reference_as_authorship_log_v3;
+use crate::git::repo_storage::RepoStorage;
 use crate::git::repository::Repository;
 use crate::git::repository::exec_git;
+use crate::utils::{debug_log, normalize_line_endings};
 use chrono::{DateTime, FixedOffset, TimeZone, Utc};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::io::{self, IsTerminal, Write};
 
@@ -41,6 +44,16 @@ pub struct BlameHunk {
     pub is_boundary: bool,
 }
 
+/// When the default output colors AI authors, from `--color[=<when>]` and `--no-color`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum BlameColor {
+    /// On a terminal, unless color.ui is off or the pager is delta, which colors blame itself
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
 #[derive(Debug, Clone)]
 pub struct GitAiBlameOptions {
     // Line range options
@@ -67,6 +80,9 @@ pub struct GitAiBlameOptions {
     pub blank_boundary: bool,
     pub show_root: bool,
 
+    // Whitespace option; None ignores whitespace
+    pub ignore_whitespace: Option<bool>,
+
     // Movement detection options
     pub detect_moves: bool,
     pub detect_copies: u32, // Number of -C flags (0-3)
@@ -74,11 +90,13 @@ pub struct GitAiBlameOptions {
 
     // Ignore options
     pub ignore_revs: Vec<String>,
-    pub ignore_revs_file: Option<String>,
+    // Read after blame.ignoreRevsFile; an empty one clears the files before it
+    pub ignore_revs_files: Vec<String>,
 
     // Color options
     pub color_lines: bool,
     pub color_by_age: bool,
+    pub color: BlameColor,
 
     // Progress options
     pub progress: bool,
@@ -103,10 +121,62 @@ pub struct GitAiBlameOptions {
     // Return all human authors as CheckpointKind::Human
     pub return_human_authors_as_human: bool,
 
+    // AI attributions less certain than this are shown as the commit author
+    pub min_confidence: AttributionConfidence,
+
+    // Split lines into the character ranges each author wrote, where the working log knows
+    pub char_level: bool,
+
+    // One JSON object per line instead of git blame's output
+    pub json: bool,
+
     // No output
     pub no_output: bool,
 }
 
+/// Part of a line written by one author. Columns count characters from 0; `end` is exclusive.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct LineSegment {
+    pub start: usize,
+    pub end: usize,
+    pub author: String,
+    pub ai: bool,
+}
+
+/// What the default output highlights: AI lines, and the AI's characters in lines people
+/// and AI both wrote
+#[derive(Clone, Copy)]
+struct AiHighlights<'a> {
+    lines: &'a HashMap<u32, AttributionConfidence>,
+    segments: &'a HashMap<u32, Vec<LineSegment>>,
+}
+
+impl AiHighlights<'_> {
+    /// The author shown for a line, with the agent marked when its attribution isn't exact
+    fn author(&self, line_authors: &HashMap<u32, String>, line_num: u32, fallback: &str) -> String {
+        let author = line_authors.get(&line_num).map_or(fallback, String::as_str);
+        match self.lines.get(&line_num) {
+            Some(confidence) => format!("{}{}", author, confidence.blame_marker()),
+            None => author.to_string(),
+        }
+    }
+}
+
+/// A line of `git-ai blame --json`
+#[derive(Debug, serde::Serialize)]
+struct JsonBlameLine<'a> {
+    line: u32,
+    commit: &'a str,
+    author: &'a str,
+    ai: bool,
+    /// How certain an AI line's attribution is; absent for human lines
+    #[serde(skip_serializing_if = "Option::is_none")]
+    confidence: Option<AttributionConfidence>,
+    content: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    segments: Option<Vec<LineSegment>>,
+}
+
 impl Default for GitAiBlameOptions {
     fn default() -> Self {
         Self {
@@ -125,13 +195,15 @@ impl Default for GitAiBlameOptions {
             abbrev: None,
             blank_boundary: false,
             show_root: false,
+            ignore_whitespace: None,
             detect_moves: false,
             detect_copies: 0,
             move_threshold: None,
             ignore_revs: Vec::new(),
-            ignore_revs_file: None,
+            ignore_revs_files: Vec::new(),
             color_lines: false,
             color_by_age: false,
+            color: BlameColor::Auto,
             progress: false,
             date_format: None,
             contents_file: None,
@@ -140,29 +212,50 @@ impl Default for GitAiBlameOptions {
             encoding: None,
             use_prompt_hashes_as_names: false,
             return_human_authors_as_human: false,
+            min_confidence: AttributionConfidence::Heuristic,
+            char_level: false,
+            json: false,
             no_output: false,
         }
     }
 }
 
+/// Author of each blamed line, and the prompts behind the AI ones by prompt hash
+pub type LineAuthors = (HashMap<u32, String>, HashMap<String, PromptRecord>);
+
 impl Repository {
     pub fn blame(
         &self,
         file_path: &str,
         options: &GitAiBlameOptions,
-    ) -> Result<(HashMap<u32, String>, HashMap<String, PromptRecord>), GitAiError> {
+    ) -> Result<LineAuthors, GitAiError> {
+        // A bare repository has no working copy, and a sparse checkout leaves files out of it,
+        // so blame those as of HEAD
+        let head_options;
+        let options = if options.newest_commit.is_none()
+            && (self.is_bare() || outside_sparse_checkout(self, file_path))
+        {
+            head_options = GitAiBlameOptions {
+                newest_commit: Some(self.revparse_single("HEAD")?.id()),
+                ..options.clone()
+            };
+            &head_options
+        } else {
+            options
+        };
+
         // Use repo root for file system operations
-        let repo_root = self.workdir().or_else(|e| {
-            Err(GitAiError::Generic(format!(
-                "Repository has no working directory: {}",
-                e
-            )))
-        })?;
+        let repo_root = || {
+            self.workdir().map_err(|e| {
+                GitAiError::Generic(format!("Repository has no working directory: {}", e))
+            })
+        };
 
         // Normalize the file path to be relative to repo root
         // This is important for AI authorship lookup which stores paths relative to repo root
         let file_path_buf = std::path::Path::new(file_path);
         let relative_file_path = if file_path_buf.is_absolute() {
+            let repo_root = repo_root()?;
             // Convert absolute path to relative path
             // Canonicalize both paths to handle symlinks (e.g., /var -> /private/var on macOS)
             let canonical_file_path = file_path_buf.canonicalize().map_err(|e| {
@@ -194,18 +287,25 @@ impl Repository {
             file_path.to_string()
         };
 
-        let abs_file_path = repo_root.join(&relative_file_path);
+        // Blaming at a commit covers that commit's version of the file, which may have more
+        // lines than the working copy (or no longer exist in it)
+        let file_content = if let Some(commit) = &options.newest_commit {
+            let content = self.get_file_content(&relative_file_path, commit)?;
+            String::from_utf8_lossy(&content).to_string()
+        } else {
+            let abs_file_path = repo_root()?.join(&relative_file_path);
 
-        // Validate that the file exists
-        if !abs_file_path.exists() {
-            return Err(GitAiError::Generic(format!(
-                "File not found: {}",
-                abs_file_path.display()
-            )));
-        }
+            // Validate that the file exists
+            if !abs_file_path.exists() {
+                return Err(GitAiError::Generic(format!(
+                    "File not found: {}",
+                    abs_file_path.display()
+                )));
+            }
 
-        // Read the current file content
-        let file_content = fs::read_to_string(&abs_file_path)?;
+            // Read the current file content
+            fs::read_to_string(&abs_file_path)?
+        };
         let lines: Vec<&str> = file_content.lines().collect();
         let total_lines = lines.len() as u32;
 
@@ -216,14 +316,37 @@ impl Repository {
             options.line_ranges.clone()
         };
 
-        // Validate line ranges
-        for (start, end) in &line_ranges {
-            if *start == 0 || *end == 0 || start > end || *end > total_lines {
+        // Validate line ranges. Like git blame, a range may run past the end of the file but
+        // not start after it
+        let mut line_ranges = line_ranges;
+        for (start, end) in &mut line_ranges {
+            if *start == 0 || *end == 0 || start > end {
                 return Err(GitAiError::Generic(format!(
                     "Invalid line range: {}:{}. File has {} lines",
                     start, end, total_lines
                 )));
             }
+            if *start > total_lines {
+                return Err(GitAiError::Generic(format!(
+                    "file {} has only {} lines",
+                    relative_file_path, total_lines
+                )));
+            }
+            *end = (*end).min(total_lines);
+        }
+
+        // A partial clone would fetch each old version of the file as blame reaches it
+        let is_partial_clone = partial_clone::promisor_remote(self).is_some();
+        if is_partial_clone {
+            let rev = options.newest_commit.as_deref().unwrap_or("HEAD");
+            if let Err(e) = partial_clone::file_history_blobs(self, rev, &relative_file_path)
+                .and_then(|blobs| partial_clone::prefetch_objects(self, &blobs))
+            {
+                debug_log(&format!(
+                    "Failed to prefetch the history of {}: {}",
+                    relative_file_path, e
+                ));
+            }
         }
 
         // Step 1: Get Git's native blame for all ranges
@@ -234,15 +357,44 @@ impl Repository {
         }
 
         // Step 2: Overlay AI authorship information
-        let (line_authors, prompt_records) =
+        if is_partial_clone {
+            let commits: Vec<String> = all_blame_hunks
+                .iter()
+                .map(|hunk| hunk.commit_sha.clone())
+                .collect::<HashSet<_>>()
+                .into_iter()
+                .collect();
+            if let Err(e) = partial_clone::prefetch_notes(self, &commits) {
+                debug_log(&format!("Failed to prefetch authorship notes: {}", e));
+            }
+        }
+        let (line_authors, prompt_records, ai_lines) =
             overlay_ai_authorship(self, &all_blame_hunks, &relative_file_path, options)?;
 
         if options.no_output {
             return Ok((line_authors, prompt_records));
         }
 
+        // Character attributions only exist for changes the working log holds, so only the
+        // working copy can be split below whole lines
+        let segments = if options.char_level && options.newest_commit.is_none() {
+            working_log_segments(self, &relative_file_path, &file_content)
+        } else {
+            HashMap::new()
+        };
+
         // Output based on format
-        if options.porcelain || options.line_porcelain {
+        if options.json {
+            output_json_format(
+                &all_blame_hunks,
+                &line_authors,
+                &ai_lines,
+                &segments,
+                &lines,
+                &line_ranges,
+                options,
+            )?;
+        } else if options.porcelain || options.line_porcelain {
             output_porcelain_format(
                 self,
                 &line_authors,
@@ -264,6 +416,10 @@ impl Repository {
             output_default_format(
                 self,
                 &line_authors,
+                AiHighlights {
+                    lines: &ai_lines,
+                    segments: &segments,
+                },
                 &relative_file_path,
                 &lines,
                 &line_ranges,
@@ -286,19 +442,25 @@ impl Repository {
         args.push("blame".to_string());
         args.push("--line-porcelain".to_string());
 
-        // Match previous behavior: ignore whitespace
-        args.push("-w".to_string());
+        // Ignore whitespace unless turned off by flag
+        if options.ignore_whitespace.unwrap_or(true) {
+            args.push("-w".to_string());
+        }
 
         // Respect ignore options in use
         for rev in &options.ignore_revs {
             args.push("--ignore-rev".to_string());
             args.push(rev.clone());
         }
-        if let Some(file) = &options.ignore_revs_file {
+        for file in &options.ignore_revs_files {
             args.push("--ignore-revs-file".to_string());
             args.push(file.clone());
         }
 
+        if options.first_parent {
+            args.push("--first-parent".to_string());
+        }
+
         // Limit to specified range
         args.push("-L".to_string());
         args.push(format!("{},{}", start_line, end_line));
@@ -313,25 +475,73 @@ impl Repository {
         args.push("--".to_string());
         args.push(file_path.to_string());
 
-        let output = exec_git(&args)?;
+        let output = match exec_git(&args) {
+            Ok(output) => output,
+            // Versions of the file a partial clone can't fetch leave its lines unknown
+            Err(e) if partial_clone::promisor_remote(self).is_some() => {
+                eprintln!(
+                    "warning: history of {} is missing from this partial clone and couldn't be fetched; lines {}-{} are attributed to unknown",
+                    file_path, start_line, end_line
+                );
+                debug_log(&format!("git blame failed: {}", e));
+                return Ok(vec![unknown_hunk(start_line, end_line)]);
+            }
+            Err(e) => return Err(e),
+        };
         let stdout = String::from_utf8(output.stdout)?;
 
-        // Parser state for current hunk
+        let abbrev_len = if options.long_rev {
+            40
+        } else {
+            options.abbrev.unwrap_or(7) as usize
+        };
+
+        // Parser state for current hunk. --line-porcelain repeats the metadata on every line,
+        // so it borrows from the output and is only copied once per hunk.
         #[derive(Default)]
-        struct CurMeta {
-            author: String,
-            author_mail: String,
+        struct CurMeta<'a> {
+            author: &'a str,
+            author_mail: &'a str,
             author_time: i64,
-            author_tz: String,
-            committer: String,
-            committer_mail: String,
+            author_tz: &'a str,
+            committer: &'a str,
+            committer_mail: &'a str,
             committer_time: i64,
-            committer_tz: String,
+            committer_tz: &'a str,
             boundary: bool,
         }
 
+        let finish_hunk =
+            |sha: &str, final_start: u32, orig_start: u32, group_size: u32, meta: &CurMeta| {
+                let end = if group_size > 0 {
+                    final_start + group_size - 1
+                } else {
+                    final_start
+                };
+                let orig_end = if group_size > 0 {
+                    orig_start + group_size - 1
+                } else {
+                    orig_start
+                };
+                BlameHunk {
+                    range: (final_start, end),
+                    orig_range: (orig_start, orig_end),
+                    commit_sha: sha.to_string(),
+                    abbrev_sha: sha[..abbrev_len.min(sha.len())].to_string(),
+                    original_author: meta.author.to_string(),
+                    author_email: meta.author_mail.to_string(),
+                    author_time: meta.author_time,
+                    author_tz: meta.author_tz.to_string(),
+                    committer: meta.committer.to_string(),
+                    committer_email: meta.committer_mail.to_string(),
+                    committer_time: meta.committer_time,
+                    committer_tz: meta.committer_tz.to_string(),
+                    is_boundary: meta.boundary,
+                }
+            };
+
         let mut hunks: Vec<BlameHunk> = Vec::new();
-        let mut cur_commit: Option<String> = None;
+        let mut cur_commit: Option<&str> = None;
         let mut cur_final_start: u32 = 0;
         let mut cur_orig_start: u32 = 0;
         let mut cur_group_size: u32 = 0;
@@ -349,16 +559,12 @@ impl Repository {
 
             // Metadata lines
             if let Some(rest) = line.strip_prefix("author ") {
-                cur_meta.author = rest.to_string();
+                cur_meta.author = rest;
                 continue;
             }
             if let Some(rest) = line.strip_prefix("author-mail ") {
                 // Usually in form: <mail>
-                cur_meta.author_mail = rest
-                    .trim()
-                    .trim_start_matches('<')
-                    .trim_end_matches('>')
-                    .to_string();
+                cur_meta.author_mail = rest.trim().trim_start_matches('<').trim_end_matches('>');
                 continue;
             }
             if let Some(rest) = line.strip_prefix("author-time ") {
@@ -368,19 +574,15 @@ impl Repository {
                 continue;
             }
             if let Some(rest) = line.strip_prefix("author-tz ") {
-                cur_meta.author_tz = rest.trim().to_string();
+                cur_meta.author_tz = rest.trim();
                 continue;
             }
             if let Some(rest) = line.strip_prefix("committer ") {
-                cur_meta.committer = rest.to_string();
+                cur_meta.committer = rest;
                 continue;
             }
             if let Some(rest) = line.strip_prefix("committer-mail ") {
-                cur_meta.committer_mail = rest
-                    .trim()
-                    .trim_start_matches('<')
-                    .trim_end_matches('>')
-                    .to_string();
+                cur_meta.committer_mail = rest.trim().trim_start_matches('<').trim_end_matches('>');
                 continue;
             }
             if let Some(rest) = line.strip_prefix("committer-time ") {
@@ -390,7 +592,7 @@ impl Repository {
                 continue;
             }
             if let Some(rest) = line.strip_prefix("committer-tz ") {
-                cur_meta.committer_tz = rest.trim().to_string();
+                cur_meta.committer_tz = rest.trim();
                 continue;
             }
             if line == "boundary" {
@@ -416,50 +618,17 @@ impl Repository {
             // If we encounter a new hunk header (4 fields), flush previous hunk first
             if p4.is_some() {
                 if let Some(prev_sha) = cur_commit.take() {
-                    // Push the previous hunk
-                    let start = cur_final_start;
-                    let end = if cur_group_size > 0 {
-                        start + cur_group_size - 1
-                    } else {
-                        start
-                    };
-                    let orig_start = cur_orig_start;
-                    let orig_end = if cur_group_size > 0 {
-                        orig_start + cur_group_size - 1
-                    } else {
-                        orig_start
-                    };
-
-                    let abbrev_len = if options.long_rev {
-                        40
-                    } else {
-                        options.abbrev.unwrap_or(7) as usize
-                    };
-                    let abbrev = if abbrev_len < prev_sha.len() {
-                        prev_sha[..abbrev_len].to_string()
-                    } else {
-                        prev_sha.clone()
-                    };
-
-                    hunks.push(BlameHunk {
-                        range: (start, end),
-                        orig_range: (orig_start, orig_end),
-                        commit_sha: prev_sha,
-                        abbrev_sha: abbrev,
-                        original_author: cur_meta.author.clone(),
-                        author_email: cur_meta.author_mail.clone(),
-                        author_time: cur_meta.author_time,
-                        author_tz: cur_meta.author_tz.clone(),
-                        committer: cur_meta.committer.clone(),
-                        committer_email: cur_meta.committer_mail.clone(),
-                        committer_time: cur_meta.committer_time,
-                        committer_tz: cur_meta.committer_tz.clone(),
-                        is_boundary: cur_meta.boundary,
-                    });
+                    hunks.push(finish_hunk(
+                        prev_sha,
+                        cur_final_start,
+                        cur_orig_start,
+                        cur_group_size,
+                        &cur_meta,
+                    ));
                 }
 
                 // Start new hunk
-                cur_commit = Some(sha.to_string());
+                cur_commit = Some(sha);
                 // According to docs: fields are orig_lineno, final_lineno, group_size
                 let orig_start = p2.parse::<u32>().unwrap_or(0);
                 let final_start = p3.parse::<u32>().unwrap_or(0);
@@ -475,7 +644,7 @@ impl Repository {
                 // Metadata remains from the first line of the hunk
                 if cur_commit.is_none() {
                     // Defensive: if no current hunk, start one with size 1
-                    cur_commit = Some(sha.to_string());
+                    cur_commit = Some(sha);
                     cur_orig_start = p2.parse::<u32>().unwrap_or(0);
                     cur_final_start = p3.parse::<u32>().unwrap_or(0);
                     cur_group_size = 1;
@@ -485,59 +654,76 @@ impl Repository {
 
         // Flush the final hunk if present
         if let Some(prev_sha) = cur_commit.take() {
-            let start = cur_final_start;
-            let end = if cur_group_size > 0 {
-                start + cur_group_size - 1
-            } else {
-                start
-            };
-            let orig_start = cur_orig_start;
-            let orig_end = if cur_group_size > 0 {
-                orig_start + cur_group_size - 1
-            } else {
-                orig_start
-            };
-
-            let abbrev_len = if options.long_rev {
-                40
-            } else {
-                options.abbrev.unwrap_or(7) as usize
-            };
-            let abbrev = if abbrev_len < prev_sha.len() {
-                prev_sha[..abbrev_len].to_string()
-            } else {
-                prev_sha.clone()
-            };
-
-            hunks.push(BlameHunk {
-                range: (start, end),
-                orig_range: (orig_start, orig_end),
-                commit_sha: prev_sha,
-                abbrev_sha: abbrev,
-                original_author: cur_meta.author.clone(),
-                author_email: cur_meta.author_mail.clone(),
-                author_time: cur_meta.author_time,
-                author_tz: cur_meta.author_tz.clone(),
-                committer: cur_meta.committer.clone(),
-                committer_email: cur_meta.committer_mail.clone(),
-                committer_time: cur_meta.committer_time,
-                committer_tz: cur_meta.committer_tz.clone(),
-                is_boundary: cur_meta.boundary,
-            });
+            hunks.push(finish_hunk(
+                prev_sha,
+                cur_final_start,
+                cur_orig_start,
+                cur_group_size,
+                &cur_meta,
+            ));
         }
 
         Ok(hunks)
     }
 }
 
+/// Author of each line, the prompts behind the AI ones, and the AI lines with how certain
+/// their attribution is
+type AiOverlay = (
+    HashMap<u32, String>,
+    HashMap<String, PromptRecord>,
+    HashMap<u32, AttributionConfidence>,
+);
+
+/// Lines whose history couldn't be read, attributed to no commit and an `unknown` author
+fn unknown_hunk(start_line: u32, end_line: u32) -> BlameHunk {
+    BlameHunk {
+        range: (start_line, end_line),
+        orig_range: (start_line, end_line),
+        commit_sha: "0".repeat(40),
+        abbrev_sha: "0".repeat(7),
+        original_author: "unknown".to_string(),
+        author_email: String::new(),
+        author_time: 0,
+        author_tz: "+0000".to_string(),
+        committer: "unknown".to_string(),
+        committer_email: String::new(),
+        committer_time: 0,
+        committer_tz: "+0000".to_string(),
+        is_boundary: false,
+    }
+}
+
+/// Whether `file_path` is left out of the working copy by a sparse checkout (its index entry
+/// has the skip-worktree bit), so only its committed version can be blamed
+fn outside_sparse_checkout(repo: &Repository, file_path: &str) -> bool {
+    if std::path::Path::new(file_path).is_absolute()
+        || repo
+            .workdir()
+            .map_or(true, |workdir| workdir.join(file_path).exists())
+    {
+        return false;
+    }
+    let mut args = repo.global_args_for_exec();
+    args.push("ls-files".to_string());
+    args.push("-t".to_string());
+    args.push("--".to_string());
+    args.push(file_path.to_string());
+    exec_git(&args)
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .is_some_and(|out| out.starts_with("S "))
+}
+
 fn overlay_ai_authorship(
     repo: &Repository,
     blame_hunks: &[BlameHunk],
     file_path: &str,
     options: &GitAiBlameOptions,
-) -> Result<(HashMap<u32, String>, HashMap<String, PromptRecord>), GitAiError> {
+) -> Result<AiOverlay, GitAiError> {
     let mut line_authors: HashMap<u32, String> = HashMap::new();
     let mut prompt_records: HashMap<String, PromptRecord> = HashMap::new();
+    let mut ai_lines: HashMap<u32, AttributionConfidence> = HashMap::new();
 
     // Group hunks by commit SHA to avoid repeated lookups
     let mut commit_authorship_cache: HashMap<String, Option<AuthorshipLog>> = HashMap::new();
@@ -545,18 +731,16 @@ fn overlay_ai_authorship(
     let mut foreign_prompts_cache: HashMap<String, Option<PromptRecord>> = HashMap::new();
 
     for hunk in blame_hunks {
-        // Check if we've already looked up this commit's authorship
-        let authorship_log = if let Some(cached) = commit_authorship_cache.get(&hunk.commit_sha) {
-            cached.clone()
-        } else {
+        // Look up each commit's authorship once, and borrow it from the cache after that
+        if !commit_authorship_cache.contains_key(&hunk.commit_sha) {
             // Try to get authorship log for this commit
             let authorship = match get_reference_as_authorship_log_v3(repo, &hunk.commit_sha) {
                 Ok(v3_log) => Some(v3_log),
                 Err(_) => None, // No AI authorship data for this commit
             };
-            commit_authorship_cache.insert(hunk.commit_sha.clone(), authorship.clone());
-            authorship
-        };
+            commit_authorship_cache.insert(hunk.commit_sha.clone(), authorship);
+        }
+        let authorship_log = commit_authorship_cache[&hunk.commit_sha].as_ref();
 
         // If we have AI authorship data, look up the author for lines in this hunk
         if let Some(authorship_log) = authorship_log {
@@ -576,12 +760,26 @@ fn overlay_ai_authorship(
                     // If this line is AI-assisted, display the tool name; otherwise the human username
                     if let Some(prompt_record) = prompt {
                         let prompt_hash = prompt_hash.unwrap();
+                        let confidence = authorship_log.confidence_for(file_path, &prompt_hash);
+                        if confidence > options.min_confidence {
+                            // Too uncertain to claim as AI: show the commit author instead
+                            if options.return_human_authors_as_human {
+                                line_authors.insert(
+                                    current_line_num,
+                                    CheckpointKind::Human.to_str().to_string(),
+                                );
+                            } else {
+                                line_authors.insert(current_line_num, hunk.original_author.clone());
+                            }
+                            continue;
+                        }
                         if options.use_prompt_hashes_as_names {
                             line_authors.insert(current_line_num, prompt_hash.clone());
                         } else {
                             line_authors
                                 .insert(current_line_num, prompt_record.agent_id.tool.clone());
                         }
+                        ai_lines.insert(current_line_num, confidence);
                         prompt_records.insert(prompt_hash, prompt_record.clone());
                     } else {
                         if options.return_human_authors_as_human {
@@ -615,7 +813,7 @@ fn overlay_ai_authorship(
         }
     }
 
-    Ok((line_authors, prompt_records))
+    Ok((line_authors, prompt_records, ai_lines))
 }
 
 fn output_porcelain_format(
@@ -817,12 +1015,15 @@ fn output_incremental_format(
 fn output_default_format(
     repo: &Repository,
     line_authors: &HashMap<u32, String>,
+    highlights: AiHighlights,
     file_path: &str,
     lines: &[&str],
     line_ranges: &[(u32, u32)],
     options: &GitAiBlameOptions,
 ) -> Result<(), GitAiError> {
     let mut output = String::new();
+    let pager = blame_pager(repo);
+    let color = use_color(repo, options, pager.as_deref());
 
     // Build a map from line number to BlameHunk for fast lookup
     let mut line_to_hunk: HashMap<u32, BlameHunk> = HashMap::new();
@@ -844,9 +1045,7 @@ fn output_default_format(
     for (start_line, end_line) in line_ranges {
         let h = repo.blame_hunks(file_path, *start_line, *end_line, options)?;
         for hunk in h {
-            let author = line_authors
-                .get(&hunk.range.0)
-                .unwrap_or(&hunk.original_author);
+            let author = highlights.author(line_authors, hunk.range.0, &hunk.original_author);
             let author_display = if options.suppress_author {
                 "".to_string()
             } else if options.show_email {
@@ -867,6 +1066,7 @@ fn output_default_format(
                 ""
             };
 
+            let line_start = output.len();
             if let Some(hunk) = line_to_hunk.get(&line_num) {
                 // Determine hash length - match git blame default (7 chars)
                 let hash_len = if options.long_rev {
@@ -895,7 +1095,7 @@ fn output_default_format(
                 };
 
                 // Get the author for this line (AI authorship or original)
-                let author = line_authors.get(&line_num).unwrap_or(&hunk.original_author);
+                let author = highlights.author(line_authors, line_num, &hunk.original_author);
 
                 // Format date according to options
                 let date_str = format_blame_date(hunk.author_time, &hunk.author_tz, options);
@@ -909,11 +1109,16 @@ fn output_default_format(
                     author.to_string()
                 };
 
-                // Pad author name to consistent width
-                let padded_author = if max_author_width > 0 {
-                    format!("{:<width$}", author_display, width = max_author_width)
+                // Pad author name to consistent width. The padding stays outside the color so
+                // the columns line up the same with the escapes stripped.
+                let padding = " ".repeat(max_author_width.saturating_sub(author_display.len()));
+                let padded_author = if color && highlights.lines.contains_key(&line_num) {
+                    format!(
+                        "{}{}{}{}",
+                        AI_AUTHOR_COLOR, author_display, COLOR_RESET, padding
+                    )
                 } else {
-                    author_display
+                    format!("{}{}", author_display, padding)
                 };
 
                 let _filename_display = if options.show_name {
@@ -981,6 +1186,21 @@ fn output_default_format(
                     width = line_num_width
                 ));
             }
+
+            // Under a line both people and AI wrote, mark the AI's characters
+            if let Some(line_segments) = highlights.segments.get(&line_num)
+                && line_segments.iter().any(|segment| segment.ai)
+                && line_segments.iter().any(|segment| !segment.ai)
+            {
+                let printed = &output[line_start..];
+                let escapes = if color && highlights.lines.contains_key(&line_num) {
+                    AI_AUTHOR_COLOR.len() + COLOR_RESET.len()
+                } else {
+                    0
+                };
+                let indent = printed.chars().count() - line_content.chars().count() - 1 - escapes;
+                output.push_str(&segment_markers(indent, line_content, line_segments));
+            }
         }
     }
 
@@ -991,20 +1211,18 @@ fn output_default_format(
         output.push_str(stats);
     }
 
-    // Output handling - respect pager environment variables
-    let pager = std::env::var("GIT_PAGER")
-        .or_else(|_| std::env::var("PAGER"))
-        .unwrap_or_else(|_| "less".to_string());
-
-    // If pager is set to "cat" or empty, output directly
-    if pager == "cat" || pager.is_empty() {
-        print!("{}", output);
-    } else if io::stdout().is_terminal() {
-        // Try to use the specified pager
-        match std::process::Command::new(&pager)
-            .stdin(std::process::Stdio::piped())
-            .spawn()
-        {
+    // Output handling - page like git blame would
+    if let Some(pager) = pager.filter(|_| io::stdout().is_terminal()) {
+        // Run it through the shell like git does, since pagers are often pipelines with
+        // arguments ("delta --dark", "diff-so-fancy | less -RFX")
+        let mut command = std::process::Command::new("sh");
+        command.arg("-c").arg(&pager);
+        for (var, default) in [("LESS", "FRX"), ("LV", "-c")] {
+            if std::env::var_os(var).is_none() {
+                command.env(var, default);
+            }
+        }
+        match command.stdin(std::process::Stdio::piped()).spawn() {
             Ok(mut child) => {
                 if let Some(stdin) = child.stdin.as_mut() {
                     if stdin.write_all(output.as_bytes()).is_ok() {
@@ -1024,12 +1242,254 @@ fn output_default_format(
             }
         }
     } else {
-        // Not a terminal, output directly
+        // Not a terminal or not paged, output directly
         print!("{}", output);
     }
     Ok(())
 }
 
+/// A line of carets under the AI-written characters of `line_content`, printed `indent`
+/// columns in, followed by the AI authors. Tabs are repeated so the carets line up.
+fn segment_markers(indent: usize, line_content: &str, segments: &[LineSegment]) -> String {
+    let ai_columns = |column: usize| {
+        segments
+            .iter()
+            .any(|segment| segment.ai && (segment.start..segment.end).contains(&column))
+    };
+    let mut markers: String = line_content
+        .chars()
+        .enumerate()
+        .map(|(column, ch)| match ch {
+            _ if ai_columns(column) => '^',
+            '\t' => '\t',
+            _ => ' ',
+        })
+        .collect();
+    markers.truncate(markers.trim_end().len());
+
+    let mut authors: Vec<&str> = Vec::new();
+    for segment in segments.iter().filter(|segment| segment.ai) {
+        if !authors.contains(&segment.author.as_str()) {
+            authors.push(&segment.author);
+        }
+    }
+    format!("{}{} {}\n", " ".repeat(indent), markers, authors.join(", "))
+}
+
+fn output_json_format(
+    blame_hunks: &[BlameHunk],
+    line_authors: &HashMap<u32, String>,
+    ai_lines: &HashMap<u32, AttributionConfidence>,
+    segments: &HashMap<u32, Vec<LineSegment>>,
+    lines: &[&str],
+    line_ranges: &[(u32, u32)],
+    options: &GitAiBlameOptions,
+) -> Result<(), GitAiError> {
+    let mut line_to_hunk: HashMap<u32, &BlameHunk> = HashMap::new();
+    for hunk in blame_hunks {
+        for line_num in hunk.range.0..=hunk.range.1 {
+            line_to_hunk.insert(line_num, hunk);
+        }
+    }
+
+    let mut json_lines = Vec::new();
+    for (start_line, end_line) in line_ranges {
+        for line_num in *start_line..=*end_line {
+            let content = lines.get((line_num - 1) as usize).copied().unwrap_or("");
+            let hunk = line_to_hunk.get(&line_num);
+            let author = line_authors
+                .get(&line_num)
+                .map(String::as_str)
+                .or(hunk.map(|hunk| hunk.original_author.as_str()))
+                .unwrap_or("unknown");
+            let confidence = ai_lines.get(&line_num).copied();
+            let ai = confidence.is_some();
+            // Lines the working log can't split are one segment, as blame attributes them
+            let line_segments = options.char_level.then(|| {
+                segments.get(&line_num).cloned().unwrap_or_else(|| {
+                    let end = content.chars().count();
+                    (end > 0)
+                        .then(|| LineSegment {
+                            start: 0,
+                            end,
+                            author: author.to_string(),
+                            ai,
+                        })
+                        .into_iter()
+                        .collect()
+                })
+            });
+            json_lines.push(JsonBlameLine {
+                line: line_num,
+                commit: hunk.map(|hunk| hunk.commit_sha.as_str()).unwrap_or(""),
+                author,
+                ai,
+                confidence,
+                content,
+                segments: line_segments,
+            });
+        }
+    }
+
+    println!("{}", serde_json::to_string_pretty(&json_lines)?);
+    Ok(())
+}
+
+/// Character-level authors of each line of `content`, the working copy of `file_path`, from
+/// the latest checkpoint of it. Empty unless that checkpoint saw exactly this content.
+fn working_log_segments(
+    repo: &Repository,
+    file_path: &str,
+    content: &str,
+) -> HashMap<u32, Vec<LineSegment>> {
+    let base_commit = repo
+        .head()
+        .and_then(|head| head.target())
+        .unwrap_or_else(|_| "initial".to_string());
+    let working_log =
+        RepoStorage::for_repo_path(repo.path()).working_log_for_base_commit(&base_commit);
+    let Ok(checkpoints) = working_log.read_all_checkpoints() else {
+        return HashMap::new();
+    };
+    let Some(entry) = checkpoints.iter().rev().find_map(|checkpoint| {
+        checkpoint
+            .entries
+            .iter()
+            .find(|entry| entry.file == file_path)
+    }) else {
+        return HashMap::new();
+    };
+    // Versions are saved with LF line endings, whatever the working copy uses
+    if working_log
+        .get_file_version(&entry.blob_sha)
+        .ok()
+        .as_deref()
+        != Some(normalize_line_endings(content.to_string()).as_str())
+    {
+        return HashMap::new();
+    }
+
+    // Attributions name AI sessions by hash; blame shows their tool
+    let tools: HashMap<String, &str> = checkpoints
+        .iter()
+        .filter_map(|checkpoint| checkpoint.agent_id.as_ref())
+        .map(|agent| {
+            (
+                generate_short_hash(&agent.id, &agent.tool),
+                agent.tool.as_str(),
+            )
+        })
+        .collect();
+    let human = CheckpointKind::Human.to_str();
+
+    let mut segments = HashMap::new();
+    let mut line_start = 0;
+    for (index, line) in content.split_inclusive('\n').enumerate() {
+        let line_end = line_start + line.trim_end_matches(['\n', '\r']).len();
+
+        // Cut the line wherever an attribution starts or ends; the newest one covering a piece
+        // wrote it, and nobody's piece is human
+        let mut cuts = vec![line_start, line_end];
+        for attribution in &entry.attributions {
+            for offset in [attribution.start, attribution.end] {
+                if offset > line_start && offset < line_end && content.is_char_boundary(offset) {
+                    cuts.push(offset);
+                }
+            }
+        }
+        cuts.sort_unstable();
+        cuts.dedup();
+
+        let mut line_segments: Vec<LineSegment> = Vec::new();
+        let mut column = 0;
+        for piece in cuts.windows(2) {
+            let (start, end) = (piece[0], piece[1]);
+            let author_id = entry
+                .attributions
+                .iter()
+                .filter(|attribution| attribution.start <= start && attribution.end >= end)
+                .max_by_key(|attribution| attribution.ts)
+                .map(|attribution| attribution.author_id.as_str())
+                .unwrap_or(&human);
+            let (author, ai) = if author_id == human {
+                (human.clone(), false)
+            } else {
+                let tool = tools.get(author_id).copied().unwrap_or(author_id);
+                (tool.to_string(), true)
+            };
+            let width = content[start..end].chars().count();
+            match line_segments.last_mut() {
+                Some(last) if last.author == author && last.ai == ai => last.end += width,
+                _ => line_segments.push(LineSegment {
+                    start: column,
+                    end: column + width,
+                    author,
+                    ai,
+                }),
+            }
+            column += width;
+        }
+        if !line_segments.is_empty() {
+            segments.insert(index as u32 + 1, line_segments);
+        }
+        line_start += line.len();
+    }
+    segments
+}
+
+/// AI authors are the only colored part of the default output: the author name, then a reset
+/// before its padding. Stripping the escapes gives exactly the uncolored output, which is what
+/// pagers that parse `git blame` lines (delta) rely on.
+const AI_AUTHOR_COLOR: &str = "\x1b[35m";
+const COLOR_RESET: &str = "\x1b[0m";
+
+/// The pager git would use for `git blame` here, None when output isn't paged
+fn blame_pager(repo: &Repository) -> Option<String> {
+    let config = |key: &str| repo.config_get_str(key).ok().flatten();
+    let pager = match std::env::var("GIT_PAGER") {
+        Ok(pager) => pager,
+        Err(_) => match config("pager.blame").as_deref() {
+            Some("false" | "no" | "off" | "0") => return None,
+            Some(pager) if !matches!(pager, "true" | "yes" | "on" | "1") => pager.to_string(),
+            _ => config("core.pager")
+                .or_else(|| std::env::var("PAGER").ok())
+                .unwrap_or_else(|| "less".to_string()),
+        },
+    };
+    let pager = pager.trim();
+    if pager.is_empty() || pager == "cat" {
+        None
+    } else {
+        Some(pager.to_string())
+    }
+}
+
+/// Whether the pager is delta, which recognizes blame output and colors it itself
+fn pager_is_delta(pager: &str) -> bool {
+    // The first program of the pipeline, after any VAR=value assignments
+    pager
+        .split('|')
+        .next()
+        .and_then(|command| command.split_whitespace().find(|word| !word.contains('=')))
+        .and_then(|program| program.rsplit(['/', '\\']).next())
+        .is_some_and(|program| program == "delta" || program == "delta.exe")
+}
+
+fn use_color(repo: &Repository, options: &GitAiBlameOptions, pager: Option<&str>) -> bool {
+    match options.color {
+        BlameColor::Always => true,
+        BlameColor::Never => false,
+        BlameColor::Auto => {
+            io::stdout().is_terminal()
+                && !pager.is_some_and(pager_is_delta)
+                && !matches!(
+                    repo.config_get_str("color.ui").ok().flatten().as_deref(),
+                    Some("false" | "never" | "no" | "off")
+                )
+        }
+    }
+}
+
 fn format_blame_date(author_time: i64, author_tz: &str, options: &GitAiBlameOptions) -> String {
     let dt = DateTime::from_timestamp(author_time, 0)
         .unwrap_or_else(|| DateTime::from_timestamp(0, 0).unwrap());
@@ -1063,7 +1523,8 @@ fn format_blame_date(author_time: i64, author_tz: &str, options: &GitAiBlameOpti
 
 pub fn parse_blame_args(args: &[String]) -> Result<(String, GitAiBlameOptions), GitAiError> {
     let mut options = GitAiBlameOptions::default();
-    let mut file_path = None;
+    let mut positional = Vec::new();
+    let mut positional_end = None;
     let mut i = 0;
 
     while i < args.len() {
@@ -1073,17 +1534,13 @@ pub fn parse_blame_args(args: &[String]) -> Result<(String, GitAiBlameOptions),
                 if i + 1 >= args.len() {
                     return Err(GitAiError::Generic("Missing argument for -L".to_string()));
                 }
-                let range_str = &args[i + 1];
-                if let Some((start, end)) = parse_line_range(range_str) {
-                    options.line_ranges.push((start, end));
-                } else {
-                    return Err(GitAiError::Generic(format!(
-                        "Invalid line range: {}",
-                        range_str
-                    )));
-                }
+                options.line_ranges.push(parse_line_range(&args[i + 1])?);
                 i += 2;
             }
+            arg if arg.starts_with("-L") => {
+                options.line_ranges.push(parse_line_range(&arg[2..])?);
+                i += 1;
+            }
 
             // Output format options
             "--porcelain" => {
@@ -1119,6 +1576,30 @@ pub fn parse_blame_args(args: &[String]) -> Result<(String, GitAiBlameOptions),
                 options.show_stats = true;
                 i += 1;
             }
+            "--char-level" => {
+                options.char_level = true;
+                i += 1;
+            }
+            "--json" => {
+                options.json = true;
+                i += 1;
+            }
+            "--min-confidence" => {
+                if i + 1 >= args.len() {
+                    return Err(GitAiError::Generic(
+                        "Missing argument for --min-confidence".to_string(),
+                    ));
+                }
+                options.min_confidence = AttributionConfidence::parse(&args[i + 1]).ok_or_else(
+                    || {
+                        GitAiError::Generic(format!(
+                            "Invalid --min-confidence '{}'. Expected exact, reconstructed or heuristic",
+                            args[i + 1]
+                        ))
+                    },
+                )?;
+                i += 2;
+            }
 
             // Commit display options
             "-l" => {
@@ -1155,6 +1636,16 @@ pub fn parse_blame_args(args: &[String]) -> Result<(String, GitAiBlameOptions),
                 i += 1;
             }
 
+            // Whitespace options
+            "-w" | "--ignore-whitespace" => {
+                options.ignore_whitespace = Some(true);
+                i += 1;
+            }
+            "--no-ignore-whitespace" => {
+                options.ignore_whitespace = Some(false);
+                i += 1;
+            }
+
             // Movement detection options
             "-M" => {
                 options.detect_moves = true;
@@ -1193,15 +1684,27 @@ pub fn parse_blame_args(args: &[String]) -> Result<(String, GitAiBlameOptions),
                 options.ignore_revs.push(args[i + 1].clone());
                 i += 2;
             }
+            arg if arg.starts_with("--ignore-rev=") => {
+                options
+                    .ignore_revs
+                    .push(arg["--ignore-rev=".len()..].to_string());
+                i += 1;
+            }
             "--ignore-revs-file" => {
                 if i + 1 >= args.len() {
                     return Err(GitAiError::Generic(
                         "Missing argument for --ignore-revs-file".to_string(),
                     ));
                 }
-                options.ignore_revs_file = Some(args[i + 1].clone());
+                options.ignore_revs_files.push(args[i + 1].clone());
                 i += 2;
             }
+            arg if arg.starts_with("--ignore-revs-file=") => {
+                options
+                    .ignore_revs_files
+                    .push(arg["--ignore-revs-file=".len()..].to_string());
+                i += 1;
+            }
 
             // Color options
             "--color-lines" => {
@@ -1212,6 +1715,28 @@ pub fn parse_blame_args(args: &[String]) -> Result<(String, GitAiBlameOptions),
                 options.color_by_age = true;
                 i += 1;
             }
+            "--color" => {
+                options.color = BlameColor::Always;
+                i += 1;
+            }
+            "--no-color" => {
+                options.color = BlameColor::Never;
+                i += 1;
+            }
+            arg if arg.starts_with("--color=") => {
+                options.color = match &arg["--color=".len()..] {
+                    "always" => BlameColor::Always,
+                    "never" => BlameColor::Never,
+                    "auto" => BlameColor::Auto,
+                    when => {
+                        return Err(GitAiError::Generic(format!(
+                            "Invalid --color value: {}",
+                            when
+                        )));
+                    }
+                };
+                i += 1;
+            }
 
             // Progress options
             "--progress" => {
@@ -1267,15 +1792,16 @@ pub fn parse_blame_args(args: &[String]) -> Result<(String, GitAiBlameOptions),
                 i += 2;
             }
 
-            // File path (non-option argument)
+            // Everything after -- is a path
+            "--" => {
+                positional_end = Some(positional.len());
+                positional.extend(args[i + 1..].iter().cloned());
+                break;
+            }
+
+            // Revision and file path (non-option arguments)
             arg if !arg.starts_with('-') => {
-                if file_path.is_none() {
-                    file_path = Some(arg.to_string());
-                } else {
-                    return Err(GitAiError::Generic(
-                        "Multiple file paths specified".to_string(),
-                    ));
-                }
+                positional.push(arg.to_string());
                 i += 1;
             }
 
@@ -1286,23 +1812,156 @@ pub fn parse_blame_args(args: &[String]) -> Result<(String, GitAiBlameOptions),
         }
     }
 
+    // [<rev>] [--] <file>, like git blame
+    let (revision, paths) = match positional_end {
+        Some(end) if end > 1 => {
+            return Err(GitAiError::Generic(
+                "Multiple revisions specified".to_string(),
+            ));
+        }
+        Some(end) => positional.split_at(end),
+        None if positional.len() > 1 => positional.split_at(1),
+        None => positional.split_at(0),
+    };
+    if let Some(revision) = revision.first() {
+        options.newest_commit = Some(revision.clone());
+    }
+    if paths.len() > 1 {
+        return Err(GitAiError::Generic(
+            "Multiple file paths specified".to_string(),
+        ));
+    }
+    let file_path = paths.first().cloned();
+
     let file_path =
         file_path.ok_or_else(|| GitAiError::Generic("No file path specified".to_string()))?;
 
     Ok((file_path, options))
 }
 
-fn parse_line_range(range_str: &str) -> Option<(u32, u32)> {
-    if let Some(dash_pos) = range_str.find(',') {
-        let start_str = &range_str[..dash_pos];
-        let end_str = &range_str[dash_pos + 1..];
+/// A `-L` range in git's forms: `<start>,<end>`, `<start>,+<count>`, `<start>,-<count>`,
+/// `<start>` or `<start>,` (to the end of the file) and `,<end>`. An end past the last line is
+/// `u32::MAX`, clamped to the file once it's read.
+fn parse_line_range(range_str: &str) -> Result<(u32, u32), GitAiError> {
+    let invalid = || GitAiError::Generic(format!("Invalid line range: {}", range_str));
+    let number = |s: &str| s.parse::<u32>().map_err(|_| invalid());
+    let (start, end) = range_str.split_once(',').unwrap_or((range_str, ""));
+
+    let start = if start.is_empty() { 1 } else { number(start)? };
+    if start == 0 {
+        return Err(invalid());
+    }
+    let range = if end.is_empty() {
+        (start, u32::MAX)
+    } else if let Some(count) = end.strip_prefix('+') {
+        let count = number(count)?;
+        if count == 0 {
+            return Err(invalid());
+        }
+        (start, start.saturating_add(count - 1))
+    } else if let Some(count) = end.strip_prefix('-') {
+        let count = number(count)?;
+        if count == 0 {
+            return Err(invalid());
+        }
+        (start.saturating_sub(count - 1).max(1), start)
+    } else {
+        let end = number(end)?;
+        if end == 0 {
+            return Err(invalid());
+        }
+        // git blame takes the lines between the two, whichever comes first
+        (start.min(end), start.max(end))
+    };
+    Ok(range)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pager_is_delta() {
+        assert!(pager_is_delta("delta"));
+        assert!(pager_is_delta("delta --dark --line-numbers"));
+        assert!(pager_is_delta("/opt/homebrew/bin/delta"));
+        assert!(pager_is_delta("DELTA_FEATURES=+side-by-side delta"));
+        assert!(!pager_is_delta("less -R"));
+        assert!(!pager_is_delta("diff-so-fancy | less --tabs=4 -RFX"));
+        assert!(!pager_is_delta("less | delta"));
+    }
+
+    #[test]
+    fn test_parse_color_args() {
+        let color = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            parse_blame_args(&args).map(|(_, options)| options.color)
+        };
+        assert_eq!(color(&["file.rs"]).unwrap(), BlameColor::Auto);
+        assert_eq!(color(&["--color", "file.rs"]).unwrap(), BlameColor::Always);
+        assert_eq!(
+            color(&["--color=never", "file.rs"]).unwrap(),
+            BlameColor::Never
+        );
+        assert_eq!(
+            color(&["--no-color", "file.rs"]).unwrap(),
+            BlameColor::Never
+        );
+        assert!(color(&["--color=sometimes", "file.rs"]).is_err());
+    }
 
-        if let (Ok(start), Ok(end)) = (start_str.parse::<u32>(), end_str.parse::<u32>()) {
-            return Some((start, end));
+    #[test]
+    fn test_parse_line_ranges_and_revision() {
+        let parse = |args: &[&str]| {
+            let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+            parse_blame_args(&args)
+        };
+        let ranges = |range: &str| parse(&["-L", range, "file.rs"]).map(|(_, o)| o.line_ranges);
+        assert_eq!(ranges("2,4").unwrap(), vec![(2, 4)]);
+        assert_eq!(ranges("4,2").unwrap(), vec![(2, 4)]);
+        assert_eq!(ranges("5").unwrap(), vec![(5, u32::MAX)]);
+        assert_eq!(ranges("5,").unwrap(), vec![(5, u32::MAX)]);
+        assert_eq!(ranges(",3").unwrap(), vec![(1, 3)]);
+        assert_eq!(ranges("5,+2").unwrap(), vec![(5, 6)]);
+        assert_eq!(ranges("5,-2").unwrap(), vec![(4, 5)]);
+        assert_eq!(ranges("1,-3").unwrap(), vec![(1, 1)]);
+        for invalid in ["0,3", "a,b", "5,+0", "/fn main/"] {
+            assert!(ranges(invalid).is_err(), "{}", invalid);
         }
-    } else if let Ok(line) = range_str.parse::<u32>() {
-        return Some((line, line));
+        let (_, options) = parse(&["-L2,3", "-L", "7,+1", "file.rs"]).unwrap();
+        assert_eq!(options.line_ranges, vec![(2, 3), (7, 7)]);
+
+        let (file, options) = parse(&["v1.2.0", "file.rs"]).unwrap();
+        assert_eq!(file, "file.rs");
+        assert_eq!(options.newest_commit.as_deref(), Some("v1.2.0"));
+        let (file, options) = parse(&["HEAD~2", "--", "-odd-name.rs"]).unwrap();
+        assert_eq!(file, "-odd-name.rs");
+        assert_eq!(options.newest_commit.as_deref(), Some("HEAD~2"));
+        let (_, options) = parse(&["--", "file.rs"]).unwrap();
+        assert_eq!(options.newest_commit, None);
+        assert!(parse(&["a", "b", "file.rs"]).is_err());
+        assert!(parse(&["a", "b", "--", "file.rs"]).is_err());
     }
 
-    None
+    #[test]
+    fn test_parse_ignore_revs() {
+        let args: Vec<String> = [
+            "--ignore-rev",
+            "abc123",
+            "--ignore-rev=def456",
+            "--ignore-revs-file",
+            ".git-blame-ignore-revs",
+            "--ignore-revs-file=",
+            "file.rs",
+        ]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+        let (_, options) = parse_blame_args(&args).unwrap();
+        assert_eq!(options.ignore_revs, vec!["abc123", "def456"]);
+        assert_eq!(
+            options.ignore_revs_files,
+            vec![".git-blame-ignore-revs", ""]
+        );
+    }
 }
@@ -0,0 +1,263 @@
+use crate::authorship::authorship_log::{AttributionConfidence, LineRange};
+use crate::authorship::stats::stats_for_commit_stats;
+use crate::config::PushPolicy;
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::refs::get_authorship;
+use crate::git::repository::{Repository, exec_git};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// One commit breaking one rule of `push_policies`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PolicyViolation {
+    pub commit: String,
+    pub policy: String,
+    pub rule: String,
+    /// The file the rule is about, for `no_ai` rules
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub reason: String,
+    /// How certain the least certain AI attribution behind the violation is
+    pub confidence: AttributionConfidence,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CheckReport {
+    pub commits: usize,
+    pub policies: usize,
+    pub violations: Vec<PolicyViolation>,
+}
+
+pub fn handle_check(args: &[String]) {
+    let mut range = None;
+    let mut json_output = false;
+    let mut min_confidence = AttributionConfidence::Heuristic;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json_output = true,
+            "--min-confidence" => {
+                match args
+                    .next()
+                    .and_then(|value| AttributionConfidence::parse(value))
+                {
+                    Some(confidence) => min_confidence = confidence,
+                    None => {
+                        eprintln!("--min-confidence expects exact, reconstructed or heuristic");
+                        std::process::exit(1);
+                    }
+                }
+            }
+            _ if !arg.starts_with("--") && range.is_none() => range = Some(arg.clone()),
+            _ => {
+                eprintln!("Unknown check argument: {}", arg);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let report = commits_to_check(&repo, range.as_deref()).and_then(|commits| {
+        Ok(CheckReport {
+            commits: commits.len(),
+            policies: repo.settings().push_policies.len(),
+            violations: check_commits(&repo, &commits, min_confidence)?,
+        })
+    });
+    let report = match report {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Check failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if json_output {
+        match serde_json::to_string(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize report: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else if report.violations.is_empty() {
+        println!(
+            "{} commit(s) pass {} polic{}",
+            report.commits,
+            report.policies,
+            if report.policies == 1 { "y" } else { "ies" }
+        );
+    } else {
+        print_violations(&repo, &report.violations);
+    }
+
+    if !report.violations.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// The commits of `range` (a commit or `<start>..<end>`), or the ones on HEAD that no remote
+/// has yet, oldest first
+pub fn commits_to_check(repo: &Repository, range: Option<&str>) -> Result<Vec<String>, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("rev-list".to_string());
+    args.push("--reverse".to_string());
+    match range {
+        Some(range) if range.contains("..") => args.push(range.to_string()),
+        Some(commit) => {
+            args.push("--no-walk".to_string());
+            args.push(format!("{}^{{commit}}", commit));
+        }
+        None => {
+            args.push("HEAD".to_string());
+            args.push("--not".to_string());
+            args.push("--remotes".to_string());
+        }
+    }
+    let output = String::from_utf8(exec_git(&args)?.stdout)?;
+    Ok(output.lines().map(str::to_string).collect())
+}
+
+/// Hold each commit to every rule of `push_policies`. Commits without a note have no AI lines
+/// and pass. AI lines attributed less certainly than `min_confidence` are not counted.
+pub fn check_commits(
+    repo: &Repository,
+    commits: &[String],
+    min_confidence: AttributionConfidence,
+) -> Result<Vec<PolicyViolation>, GitAiError> {
+    let policies = &repo.settings().push_policies;
+    let mut violations = Vec::new();
+    if policies.is_empty() {
+        return Ok(violations);
+    }
+
+    for commit in commits {
+        let Some(log) = get_authorship(repo, commit) else {
+            continue;
+        };
+
+        // AI-authored lines per file, with the least certain attribution among them, and the
+        // AI lines left out for being less certain than `min_confidence`
+        let mut ai_lines: BTreeMap<&str, (u32, AttributionConfidence)> = BTreeMap::new();
+        let mut uncertain_lines = 0;
+        for file in &log.attestations {
+            for entry in &file.entries {
+                if !log.metadata.prompts.contains_key(&entry.hash) {
+                    continue;
+                }
+                let lines = entry
+                    .line_ranges
+                    .iter()
+                    .map(|range| match range {
+                        LineRange::Single(_) => 1,
+                        LineRange::Range(start, end) => end - start + 1,
+                    })
+                    .sum::<u32>();
+                let confidence = log.confidence_for(&file.file_path, &entry.hash);
+                if confidence > min_confidence {
+                    uncertain_lines += lines;
+                    continue;
+                }
+                let counted = ai_lines.entry(file.file_path.as_str()).or_default();
+                counted.0 += lines;
+                counted.1 = counted.1.max(confidence);
+            }
+        }
+        if ai_lines.is_empty() {
+            continue;
+        }
+        let commit_confidence = ai_lines
+            .values()
+            .map(|(_, confidence)| *confidence)
+            .max()
+            .unwrap_or_default();
+
+        for (name, policy) in policies {
+            let violation =
+                |file: Option<&str>, reason: String, confidence: AttributionConfidence| {
+                    PolicyViolation {
+                        commit: commit.clone(),
+                        policy: name.clone(),
+                        rule: policy.to_string(),
+                        file: file.map(str::to_string),
+                        reason,
+                        confidence,
+                    }
+                };
+            match policy {
+                PushPolicy::MaxAiPercent(max) => {
+                    let stats = stats_for_commit_stats(repo, commit, "")?;
+                    let total = stats.counted_added_lines();
+                    if total == 0 {
+                        continue;
+                    }
+                    let ai = stats
+                        .ai_additions
+                        .saturating_sub(uncertain_lines)
+                        .min(total);
+                    let percent = ai as f64 / total as f64 * 100.0;
+                    if percent > *max as f64 {
+                        violations.push(violation(
+                            None,
+                            format!(
+                                "{:.0}% of added lines ({}/{}) are AI-authored and unedited, over {}%",
+                                percent, ai, total, max
+                            ),
+                            commit_confidence,
+                        ));
+                    }
+                }
+                PushPolicy::NoAi(_) => {
+                    for (file, (lines, confidence)) in &ai_lines {
+                        if policy.forbids_ai_in(file) {
+                            violations.push(violation(
+                                Some(file),
+                                format!("{} AI-authored line(s)", lines),
+                                *confidence,
+                            ));
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(violations)
+}
+
+pub fn print_violations(repo: &Repository, violations: &[PolicyViolation]) {
+    let mut current = None;
+    for violation in violations {
+        if current != Some(&violation.commit) {
+            let subject = repo
+                .find_commit(violation.commit.clone())
+                .and_then(|commit| commit.summary())
+                .unwrap_or_default();
+            eprintln!(
+                "{} {}",
+                &violation.commit[..7.min(violation.commit.len())],
+                subject
+            );
+            current = Some(&violation.commit);
+        }
+        let reason = match violation.confidence {
+            AttributionConfidence::Exact => violation.reason.clone(),
+            confidence => format!("{} ({} attribution)", violation.reason, confidence.as_str()),
+        };
+        match &violation.file {
+            Some(file) => eprintln!(
+                "  {} ({}): {}: {}",
+                violation.policy, violation.rule, file, reason
+            ),
+            None => eprintln!("  {} ({}): {}", violation.policy, violation.rule, reason),
+        }
+    }
+}
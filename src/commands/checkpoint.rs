@@ -1,41 +1,108 @@
-use crate::authorship::attribution_tracker::{Attribution, AttributionTracker, LineAttribution};
+use crate::authorship::attribution_tracker::{
+    Attribution, AttributionConfig, AttributionTracker, FileChange, LineAttribution,
+    attribution_algorithm,
+};
+use crate::authorship::authorship_log::{CommitMessageAuthorship, CommitMessageSource};
+use crate::authorship::prompt_lineage::{PromptLineage, update_lineage};
+use crate::authorship::transcript::{AiTranscript, redact_transcript};
 use crate::authorship::working_log::CheckpointKind;
-use crate::authorship::working_log::{Checkpoint, WorkingLogEntry};
+use crate::authorship::working_log::{
+    AgentId, Checkpoint, CheckpointClock, EnvironmentFingerprint, SkipReason, SkippedFile,
+    WorkingLogEntry,
+};
 use crate::commands::blame::GitAiBlameOptions;
 use crate::commands::checkpoint_agent::agent_presets::AgentRunResult;
+use crate::commands::checkpoint_agent::batch::EditEvent;
+use crate::commands::checkpoint_agent::copilot_telemetry;
+use crate::config::SubmodulePolicy;
 use crate::error::GitAiError;
-use crate::git::repo_storage::{PersistedWorkingLog, RepoStorage};
-use crate::git::repository::Repository;
-use crate::git::status::{EntryKind, StatusCode};
-use crate::utils::{Timer, debug_log};
+use crate::git::jj;
+use crate::git::refs::get_reference_as_authorship_log_v3;
+use crate::git::repo_storage::{FileStateCache, PersistedWorkingLog, RepoStorage};
+use crate::git::repository::{Repository, find_repository_in_path};
+use crate::git::status::{EntryKind, StatusCode, porcelain_v2_entries};
+use crate::utils::{Timer, debug_log, join_repo_path, normalize_line_endings, to_repo_path};
 use sha2::{Digest, Sha256};
 use similar::{ChangeTag, TextDiff};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::Read;
+use std::path::Path;
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
+
+/// File contents held in memory by repository-relative path, read instead of the files on disk
+pub type FileContents = HashMap<String, String>;
+
+/// How [`run`] checkpoints, beyond who made the changes
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CheckpointOptions<'a> {
+    /// Print the working log after the checkpoint
+    pub show_working_log: bool,
+    /// Start a fresh working log, diffing against the base commit
+    pub reset: bool,
+    /// Don't print the summary line
+    pub quiet: bool,
+    /// Reread every changed file instead of trusting the file state cache
+    pub no_cache: bool,
+    /// Check files over `large_file_threshold_kb` too
+    pub include_large: bool,
+    /// Working log to checkpoint into instead of HEAD's, e.g. mid-rebase
+    pub base_commit: Option<&'a str>,
+}
 
 pub fn run(
     repo: &Repository,
     author: &str,
     kind: CheckpointKind,
-    show_working_log: bool,
-    reset: bool,
-    quiet: bool,
     agent_run_result: Option<AgentRunResult>,
+    options: CheckpointOptions<'_>,
 ) -> Result<(usize, usize, usize), GitAiError> {
     let total_timer = Timer::default();
-    // Robustly handle zero-commit repos
-    let base_commit = match repo.head() {
-        Ok(head) => match head.target() {
-            Ok(oid) => oid,
-            Err(_) => "initial".to_string(),
-        },
-        Err(_) => "initial".to_string(),
+    let CheckpointOptions {
+        show_working_log,
+        reset,
+        quiet,
+        no_cache,
+        include_large,
+        base_commit,
+    } = options;
+
+    // Edits inside a submodule belong to the submodule's repository, never to this one
+    let submodules = repo.submodule_paths();
+    if !submodules.is_empty() && repo.settings().submodules == SubmodulePolicy::Recurse {
+        checkpoint_submodules(
+            repo,
+            author,
+            kind,
+            &submodules,
+            CheckpointOptions {
+                quiet,
+                no_cache,
+                include_large,
+                ..Default::default()
+            },
+            agent_run_result.as_ref(),
+        );
+    }
+
+    if !repo.settings().is_tracked_branch(repo) {
+        debug_log("Skipping checkpoint because the current branch is not tracked");
+        return Ok((0, 0, 0));
+    }
+
+    // jj rewrites commits without going through git; catch up before reading the working log
+    if let Err(e) = jj::sync_rewrites(repo) {
+        debug_log(&format!("Failed to sync jj rewrites: {}", e));
+    }
+
+    // An explicit base commit pins the working log for callers that know better than HEAD,
+    // e.g. mid-rebase. Otherwise robustly handle zero-commit repos.
+    let base_commit = match base_commit {
+        Some(spec) => resolve_base_commit(repo, spec)?,
+        None => head_base_commit(repo),
     };
 
     // Cannot run checkpoint on bare repositories
     if repo.workdir().is_err() {
-        eprintln!("Cannot run checkpoint on bare repositories");
         return Err(GitAiError::Generic(
             "Cannot run checkpoint on bare repositories".to_string(),
         ));
@@ -45,11 +112,8 @@ pub fn run(
     let repo_storage = RepoStorage::for_repo_path(repo.path());
     let working_log = repo_storage.working_log_for_base_commit(&base_commit);
 
-    // Get the current timestamp in milliseconds since the Unix epoch
-    let ts = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap_or_default()
-        .as_millis();
+    // Unsaved editor buffers and the like stand in for the files on disk
+    let contents = in_memory_contents(repo, agent_run_result.as_ref());
 
     // Extract edited filepaths from agent_run_result if available
     // For human checkpoints, use will_edit_filepaths to narrow git status scope
@@ -65,21 +129,28 @@ pub fn run(
 
         paths.and_then(|p| {
             let repo_workdir = repo.workdir().ok()?;
+            // Canonical paths are verbatim (`\\?\C:\...`) on Windows and resolve symlinked
+            // temp dirs on macOS, so they're only compared with the canonical workdir
+            let canonical_workdir = repo_workdir
+                .canonicalize()
+                .unwrap_or_else(|_| repo_workdir.clone());
             let filtered: Vec<String> = p
                 .iter()
                 .filter_map(|path| {
+                    // Tools on Windows report paths with `\`; git pathspecs use `/`
+                    let path = to_repo_path(path);
                     // Check if path is absolute and outside repo
-                    if std::path::Path::new(path).is_absolute() {
+                    if std::path::Path::new(&path).is_absolute() {
                         // For absolute paths, check if they start with repo_workdir
-                        if !std::path::Path::new(path).starts_with(&repo_workdir) {
+                        if !std::path::Path::new(&path).starts_with(&repo_workdir) {
                             return None;
                         }
                     } else {
                         // For relative paths, join with workdir and canonicalize to check
-                        let joined = repo_workdir.join(path);
+                        let joined = join_repo_path(&repo_workdir, &path);
                         // Try to canonicalize to resolve .. and . components
                         if let Ok(canonical) = joined.canonicalize() {
-                            if !canonical.starts_with(&repo_workdir) {
+                            if !canonical.starts_with(&canonical_workdir) {
                                 return None;
                             }
                         } else {
@@ -103,10 +174,14 @@ pub fn run(
                             }
                         }
                     }
-                    Some(path.clone())
+                    Some(path)
                 })
                 .collect();
 
+            let filtered: Vec<String> = filtered
+                .into_iter()
+                .filter(|path| !in_submodule(path, &repo_workdir, &submodules))
+                .collect();
             if filtered.is_empty() {
                 None
             } else {
@@ -116,8 +191,60 @@ pub fn run(
         })
     });
 
+    // Files whose mtime/size match the last checkpoint are known text files with a known
+    // content hash, so we don't need to read them again. Without the cache every file is
+    // read, and the cache is rebuilt from what was read.
+    let mut file_state_cache = if reset || no_cache || !repo.settings().file_state_cache {
+        FileStateCache::default()
+    } else {
+        working_log.read_file_state_cache()
+    };
+
+    let large_file_threshold_kb = if include_large {
+        0
+    } else {
+        repo.settings().large_file_threshold_kb
+    };
+
     let end_get_files_clock = Timer::default().start_quiet("checkpoint: get tracked files");
-    let files = get_all_tracked_files(repo, &base_commit, &working_log, pathspec_filter)?;
+    let mut skipped_files = Vec::new();
+    let mut files = get_all_tracked_files(
+        repo,
+        &working_log,
+        pathspec_filter,
+        &file_state_cache,
+        large_file_threshold_kb,
+        &mut skipped_files,
+    )?;
+    // Files held in memory may differ from HEAD whatever git status says about the disk
+    for file_path in contents.keys() {
+        if !files.contains(file_path) && !repo.settings().is_excluded_path(file_path) {
+            files.push(file_path.clone());
+        }
+    }
+    skipped_files.retain(|skipped| !contents.contains_key(&skipped.file));
+    if !submodules.is_empty() {
+        let workdir = repo.workdir()?;
+        files.retain(|file| !in_submodule(file, &workdir, &submodules));
+        skipped_files.retain(|skipped| !in_submodule(&skipped.file, &workdir, &submodules));
+    }
+    // Files of subtrees handed to a tool are checkpointed as that tool's, whoever edited them,
+    // once this checkpoint is done
+    let own_tool = agent_run_result
+        .as_ref()
+        .filter(|_| kind != CheckpointKind::Human)
+        .map(|agent_run| agent_run.agent_id.tool.clone());
+    let mut tool_subtree_files: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    files.retain(|file| match repo.settings().directory_tool(file) {
+        Some(tool) if own_tool.as_deref() != Some(tool) => {
+            tool_subtree_files
+                .entry(tool.to_string())
+                .or_default()
+                .push(file.clone());
+            false
+        }
+        _ => true,
+    });
     let get_files_duration = end_get_files_clock();
     Timer::default().print_duration("checkpoint: get tracked files", get_files_duration);
     let mut checkpoints = if reset {
@@ -167,6 +294,15 @@ pub fn run(
                     }
                 }
 
+                for skipped in &checkpoint.skipped_files {
+                    debug_log(&format!(
+                        "  Skipped: {} ({}, {} bytes)",
+                        skipped.file,
+                        skipped.reason.to_str(),
+                        skipped.size
+                    ));
+                }
+
                 debug_log("  Entries:");
                 for entry in &checkpoint.entries {
                     debug_log(&format!("    File: {}", entry.file));
@@ -184,22 +320,26 @@ pub fn run(
         return Ok((0, files.len(), checkpoints.len()));
     }
 
+    // Order this checkpoint after every earlier one, whatever the wall clock says
+    let clock = CheckpointClock::next(&checkpoints);
+    let ts = clock.attribution_ts();
+    let entry_options = EntryOptions {
+        kind,
+        agent_run_result: agent_run_result.as_ref(),
+        ts,
+        attribution_config: AttributionConfig::for_settings(&repo.settings()),
+        concurrency: repo.settings().checkpoint_concurrency as usize,
+    };
+
     // Save current file states and get content hashes
     let end_save_states_clock = Timer::default().start_quiet("checkpoint: persist file versions");
-    let file_content_hashes = save_current_file_states(&working_log, &files)?;
+    let file_content_hashes =
+        save_current_file_states(&working_log, &files, &mut file_state_cache, &contents)?;
+    working_log.write_file_state_cache(&mut file_state_cache)?;
     let save_states_duration = end_save_states_clock();
     Timer::default().print_duration("checkpoint: persist file versions", save_states_duration);
 
-    // Order file hashes by key and create a hash of the ordered hashes
-    let mut ordered_hashes: Vec<_> = file_content_hashes.iter().collect();
-    ordered_hashes.sort_by_key(|(file_path, _)| *file_path);
-
-    let mut combined_hasher = Sha256::new();
-    for (file_path, hash) in ordered_hashes {
-        combined_hasher.update(file_path.as_bytes());
-        combined_hasher.update(hash.as_bytes());
-    }
-    let combined_hash = format!("{:x}", combined_hasher.finalize());
+    let combined_hash = combined_content_hash(&file_content_hashes);
 
     // Note: foreign prompts from INITIAL file are read in post_commit.rs
     // when converting working log -> authorship log
@@ -212,29 +352,27 @@ pub fn run(
 
         let end = timer.start("checkpoint: get initial checkpoint entries");
         let result = smol::block_on(get_initial_checkpoint_entries(
-            kind,
             repo,
             &working_log,
             &files,
             &base_commit,
             &file_content_hashes,
-            agent_run_result.as_ref(),
-            ts,
+            &contents,
+            entry_options,
         ))?;
 
         end();
         result
     } else {
         // Subsequent checkpoint - diff against last saved state
-        get_subsequent_checkpoint_entries(
-            kind,
+        smol::block_on(get_subsequent_checkpoint_entries(
             &working_log,
             &files,
             &file_content_hashes,
+            &contents,
             &checkpoints,
-            agent_run_result.as_ref(),
-            ts,
-        )?
+            entry_options,
+        ))?
     };
     let entries_duration = end_entries_clock();
     Timer::default().print_duration("checkpoint: compute entries", entries_duration);
@@ -247,11 +385,20 @@ pub fn run(
             author.to_string(),
             entries.clone(),
         );
+        checkpoint.sequence = clock.sequence;
+        checkpoint.skipped_files = skipped_files.clone();
 
         // Compute and set line stats
         let end_stats_clock = Timer::default().start_quiet("checkpoint: compute line stats");
-        checkpoint.line_stats =
-            compute_line_stats(repo, &working_log, &files, &entries, &checkpoints, kind)?;
+        checkpoint.line_stats = compute_line_stats(
+            repo,
+            &working_log,
+            &files,
+            &entries,
+            &checkpoints,
+            kind,
+            &contents,
+        )?;
         let stats_duration = end_stats_clock();
         Timer::default().print_duration("checkpoint: compute line stats", stats_duration);
 
@@ -259,16 +406,76 @@ pub fn run(
         if kind != CheckpointKind::Human
             && let Some(agent_run) = &agent_run_result
         {
-            checkpoint.transcript = Some(agent_run.transcript.clone().unwrap_or_default());
+            let transcript = agent_run.transcript.clone().unwrap_or_default();
+            checkpoint.transcript = Some(AiTranscript {
+                messages: redact_transcript(transcript.messages, repo.settings().transcript_store),
+            });
             checkpoint.agent_id = Some(agent_run.agent_id.clone());
         }
 
+        if repo.settings().record_environment {
+            let agent_version = agent_run_result
+                .as_ref()
+                .and_then(|agent_run| agent_run.agent_version.clone());
+            checkpoint.environment = Some(EnvironmentFingerprint::current(agent_version));
+        }
+
         // Append checkpoint to the working log
         let end_append_clock = Timer::default().start_quiet("checkpoint: append working log");
         working_log.append_checkpoint(&checkpoint)?;
         let append_duration = end_append_clock();
         Timer::default().print_duration("checkpoint: append working log", append_duration);
+
+        // Edits since the last checkpoint may include Copilot completions accepted while
+        // git-ai wasn't running
+        let recovered = if kind == CheckpointKind::Human
+            && repo.settings().recover_copilot_telemetry
+        {
+            let since_ms = match checkpoints.last() {
+                Some(previous) => previous.timestamp * 1000,
+                None => repo
+                    .find_commit(base_commit.clone())
+                    .and_then(|commit| commit.time())
+                    .map(|time| time.seconds().max(0) as u64 * 1000)
+                    .unwrap_or(0),
+            };
+            let acceptances = copilot_telemetry::accepted_since(&working_log.repo_root, since_ms);
+            if acceptances.is_empty() {
+                None
+            } else {
+                let recovered_clock = CheckpointClock {
+                    sequence: clock.sequence + 1,
+                    timestamp_ms: clock.timestamp_ms,
+                };
+                copilot_telemetry::recover_tab_completions(
+                    &working_log,
+                    &checkpoint,
+                    &acceptances,
+                    recovered_clock.attribution_ts(),
+                )
+                .map(|mut recovered| {
+                    recovered.sequence = recovered_clock.sequence;
+                    recovered
+                })
+            }
+        } else {
+            None
+        };
+
         checkpoints.push(checkpoint);
+        if let Some(recovered) = recovered {
+            working_log.append_checkpoint(&recovered)?;
+            checkpoints.push(recovered);
+        }
+    }
+
+    // Remember who is writing the commit message so post_commit can put it in the note
+    if let Some(agent_run) = &agent_run_result
+        && let Some(source) = agent_run.commit_message_source
+    {
+        let agent_id = (source == CommitMessageSource::Ai).then(|| agent_run.agent_id.clone());
+        working_log
+            .write_commit_message_authorship(&CommitMessageAuthorship { source, agent_id })?;
     }
 
     let agent_tool = if kind != CheckpointKind::Human
@@ -291,6 +498,17 @@ pub fn run(
     };
 
     if !quiet {
+        for skipped in skipped_files
+            .iter()
+            .filter(|skipped| skipped.reason == SkipReason::Large)
+        {
+            eprintln!(
+                "Skipped {} ({} KiB, over large_file_threshold_kb); rerun with --include-large to check it",
+                skipped.file,
+                skipped.size / 1024
+            );
+        }
+
         let log_author = agent_tool.unwrap_or(author);
         // Only count files that actually have checkpoint entries to avoid confusion.
         // Files that were previously checkpointed but have no new changes won't have entries.
@@ -320,27 +538,210 @@ pub fn run(
         }
     }
 
+    for (tool, tool_files) in tool_subtree_files {
+        let tool_contents: FileContents = tool_files
+            .iter()
+            .filter_map(|file| Some((file.clone(), contents.get(file)?.clone())))
+            .collect();
+        run(
+            repo,
+            author,
+            CheckpointKind::AiAgent,
+            Some(directory_tool_run(&tool, tool_files, tool_contents)),
+            CheckpointOptions {
+                quiet: true,
+                no_cache,
+                include_large,
+                base_commit: (base_commit != "initial").then_some(base_commit.as_str()),
+                ..Default::default()
+            },
+        )?;
+    }
+
     // Return the requested values: (entries_len, files_len, working_log_len)
     Timer::default().print_duration("checkpoint: total", total_timer.epoch.elapsed());
     Ok((entries.len(), files.len(), checkpoints.len()))
 }
 
+/// The agent every edit in a `tool:<name>` directory policy subtree is attributed to. One
+/// session per tool, so all of its lines share a prompt record.
+pub fn directory_tool_agent_id(tool: &str) -> AgentId {
+    AgentId {
+        tool: tool.to_string(),
+        id: "directory-policy".to_string(),
+        model: "unknown".to_string(),
+    }
+}
+
+/// A checkpoint of `files` as the edits of `tool`, for a `tool:<name>` directory policy
+fn directory_tool_run(tool: &str, files: Vec<String>, contents: FileContents) -> AgentRunResult {
+    AgentRunResult {
+        agent_id: directory_tool_agent_id(tool),
+        checkpoint_kind: CheckpointKind::AiAgent,
+        transcript: None,
+        repo_working_dir: None,
+        edited_filepaths: Some(files),
+        will_edit_filepaths: None,
+        commit_message_source: None,
+        agent_version: None,
+        file_contents: (!contents.is_empty()).then_some(contents),
+    }
+}
+
+/// Record a batch of agent edits, in order, as one checkpoint per edit. The edits carry the
+/// files' contents, so nothing is read from disk and `git status` isn't needed; the
+/// checkpoints are appended to the working log together at the end. Returns the checkpoints
+/// added, the files they touched, and the working log's checkpoints in total.
+pub fn run_batch(
+    repo: &Repository,
+    author: &str,
+    agent_run_result: &AgentRunResult,
+    events: &[EditEvent],
+) -> Result<(usize, usize, usize), GitAiError> {
+    if !repo.settings().is_tracked_branch(repo) {
+        debug_log("Skipping batch checkpoint because the current branch is not tracked");
+        return Ok((0, 0, 0));
+    }
+    if let Err(e) = jj::sync_rewrites(repo) {
+        debug_log(&format!("Failed to sync jj rewrites: {}", e));
+    }
+    if repo.workdir().is_err() {
+        return Err(GitAiError::Generic(
+            "Cannot run checkpoint on bare repositories".to_string(),
+        ));
+    }
+
+    let kind = agent_run_result.checkpoint_kind;
+    let base_commit = head_base_commit(repo);
+    let repo_storage = RepoStorage::for_repo_path(repo.path());
+    let working_log = repo_storage.working_log_for_base_commit(&base_commit);
+    let mut checkpoints = working_log.read_all_checkpoints()?;
+    let previous_count = checkpoints.len();
+    let mut file_state_cache = working_log.read_file_state_cache();
+    let settings = repo.settings();
+    let attribution_config = AttributionConfig::for_settings(&settings);
+    let mut contents = FileContents::new();
+    let mut touched_files = HashSet::new();
+    for event in events {
+        if settings.is_excluded_path(&event.file) {
+            continue;
+        }
+
+        // A patch applies to the file as the previous edit, checkpoint or HEAD left it
+        let before = match contents.get(&event.file) {
+            Some(content) => content.clone(),
+            None => last_checkpointed_content(&working_log, &checkpoints, &event.file)
+                .or_else(|| {
+                    repo.get_file_content(&event.file, &base_commit)
+                        .ok()
+                        .map(|bytes| String::from_utf8_lossy(&bytes).to_string())
+                })
+                .unwrap_or_default(),
+        };
+        contents.insert(event.file.clone(), event.content_after(&before)?);
+
+        let files = vec![event.file.clone()];
+        let clock = match event.timestamp_ms() {
+            Some(timestamp_ms) => CheckpointClock::next_at(&checkpoints, timestamp_ms),
+            None => CheckpointClock::next(&checkpoints),
+        };
+        let entry_options = EntryOptions {
+            kind,
+            agent_run_result: Some(agent_run_result),
+            ts: clock.attribution_ts(),
+            attribution_config,
+            concurrency: settings.checkpoint_concurrency as usize,
+        };
+        let file_content_hashes =
+            save_current_file_states(&working_log, &files, &mut file_state_cache, &contents)?;
+
+        let entries = if checkpoints.is_empty() {
+            smol::block_on(get_initial_checkpoint_entries(
+                repo,
+                &working_log,
+                &files,
+                &base_commit,
+                &file_content_hashes,
+                &contents,
+                entry_options,
+            ))?
+        } else {
+            smol::block_on(get_subsequent_checkpoint_entries(
+                &working_log,
+                &files,
+                &file_content_hashes,
+                &contents,
+                &checkpoints,
+                entry_options,
+            ))?
+        };
+        if entries.is_empty() {
+            continue;
+        }
+
+        let mut checkpoint = Checkpoint::new(
+            kind,
+            combined_content_hash(&file_content_hashes),
+            author.to_string(),
+            entries.clone(),
+        );
+        checkpoint.sequence = clock.sequence;
+        checkpoint.timestamp = clock.timestamp_ms / 1000;
+        checkpoint.line_stats = compute_line_stats(
+            repo,
+            &working_log,
+            &files,
+            &entries,
+            &checkpoints,
+            kind,
+            &contents,
+        )?;
+        let transcript = event.transcript(agent_run_result.transcript.as_ref());
+        checkpoint.transcript = Some(AiTranscript {
+            messages: redact_transcript(transcript.messages, settings.transcript_store),
+        });
+        checkpoint.agent_id = Some(agent_run_result.agent_id.clone());
+        if settings.record_environment {
+            checkpoint.environment = Some(EnvironmentFingerprint::current(
+                agent_run_result.agent_version.clone(),
+            ));
+        }
+
+        touched_files.insert(event.file.clone());
+        checkpoints.push(checkpoint);
+    }
+
+    working_log.append_checkpoints(&checkpoints[previous_count..])?;
+    working_log.write_file_state_cache(&mut file_state_cache)?;
+
+    Ok((
+        checkpoints.len() - previous_count,
+        touched_files.len(),
+        checkpoints.len(),
+    ))
+}
+
+/// Changed text files to checkpoint. Changed files left out for being large or binary are
+/// added to `skipped`; `threshold_kb` of 0 checks files of any size.
 fn get_all_files(
     repo: &Repository,
     edited_filepaths: Option<&Vec<String>>,
+    file_state_cache: &FileStateCache,
+    threshold_kb: u64,
+    skipped: &mut Vec<SkippedFile>,
 ) -> Result<Vec<String>, GitAiError> {
     let mut files = Vec::new();
 
     // Convert edited_filepaths to HashSet for git status if provided
-    let pathspec = edited_filepaths.map(|paths| {
-        use std::collections::HashSet;
-        paths.iter().cloned().collect::<HashSet<String>>()
-    });
+    let pathspec =
+        edited_filepaths.map(|paths| paths.iter().map(String::as_str).collect::<HashSet<&str>>());
 
-    // Use porcelain v2 format to get status
-    let statuses = repo.status(pathspec.as_ref())?;
+    // Use porcelain v2 format to get status, borrowing paths from git's output
+    let status_output = repo.status_porcelain_v2(pathspec.as_ref())?;
+
+    for entry in porcelain_v2_entries(&status_output) {
+        let entry = entry?;
 
-    for entry in statuses {
         // Skip ignored files
         if entry.kind == EntryKind::Ignored {
             continue;
@@ -361,38 +762,62 @@ fn get_all_files(
             let is_deleted =
                 entry.staged == StatusCode::Deleted || entry.unstaged == StatusCode::Deleted;
 
-            let is_text = if is_deleted {
-                is_text_file_in_head(repo, &entry.path)
+            let class = if is_deleted {
+                if is_text_file_in_head(repo, entry.path) {
+                    FileClass::Text
+                } else {
+                    FileClass::Other
+                }
             } else {
-                is_text_file(repo, &entry.path)
+                classify_file_cached(repo, entry.path, file_state_cache, threshold_kb)
             };
 
-            if is_text {
-                files.push(entry.path.clone());
+            match class {
+                FileClass::Text => files.push(entry.path.to_string()),
+                FileClass::Skipped(reason, size) => skipped.push(SkippedFile {
+                    file: entry.path.to_string(),
+                    reason,
+                    size,
+                }),
+                FileClass::Other => {}
             }
         }
     }
 
+    let settings = repo.settings();
+    files.retain(|file| !settings.is_excluded_path(file));
+    skipped.retain(|skipped| !settings.is_excluded_path(&skipped.file));
+
     Ok(files)
 }
 
 /// Get all files that should be tracked, including those from previous checkpoints
 fn get_all_tracked_files(
     repo: &Repository,
-    _base_commit: &str,
     working_log: &PersistedWorkingLog,
     edited_filepaths: Option<&Vec<String>>,
+    file_state_cache: &FileStateCache,
+    threshold_kb: u64,
+    skipped: &mut Vec<SkippedFile>,
 ) -> Result<Vec<String>, GitAiError> {
-    let mut files = get_all_files(repo, edited_filepaths)?;
+    let mut files = get_all_files(
+        repo,
+        edited_filepaths,
+        file_state_cache,
+        threshold_kb,
+        skipped,
+    )?;
+    let mut seen: HashSet<String> = files.iter().cloned().collect();
 
     // Also include files that were in previous checkpoints but might not show up in git status
     // This ensures we track deletions when files return to their original state
     if let Ok(working_log_data) = working_log.read_all_checkpoints() {
         for checkpoint in &working_log_data {
             for entry in &checkpoint.entries {
-                if !files.contains(&entry.file) {
+                if !seen.contains(&entry.file) {
                     // Check if it's a text file before adding
-                    if is_text_file(repo, &entry.file) {
+                    if is_text_file_cached(repo, &entry.file, file_state_cache, threshold_kb) {
+                        seen.insert(entry.file.clone());
                         files.push(entry.file.clone());
                     }
                 }
@@ -403,61 +828,299 @@ fn get_all_tracked_files(
     Ok(files)
 }
 
+/// The full sha of the commit `spec` names. Tags and other refs are peeled; anything that
+/// isn't a commit is an error rather than a working log nothing will ever read.
+fn resolve_base_commit(repo: &Repository, spec: &str) -> Result<String, GitAiError> {
+    let spec = spec.trim();
+    if spec.is_empty() || spec.starts_with('-') {
+        return Err(GitAiError::Generic(format!("Invalid base commit: {:?}", spec)));
+    }
+    repo.revparse_single(&format!("{}^{{commit}}", spec))
+        .map(|object| object.id())
+        .map_err(|_| GitAiError::Generic(format!("Base commit {} is not a commit", spec)))
+}
+
+/// HEAD's commit, or "initial" in a repository without commits
+fn head_base_commit(repo: &Repository) -> String {
+    match repo.head() {
+        Ok(head) => match head.target() {
+            Ok(oid) => oid,
+            Err(_) => "initial".to_string(),
+        },
+        Err(_) => "initial".to_string(),
+    }
+}
+
+/// Hash of the files' content hashes, ordered by path
+fn combined_content_hash(file_content_hashes: &HashMap<String, String>) -> String {
+    let mut ordered_hashes: Vec<_> = file_content_hashes.iter().collect();
+    ordered_hashes.sort_by_key(|(file_path, _)| *file_path);
+
+    let mut combined_hasher = Sha256::new();
+    for (file_path, hash) in ordered_hashes {
+        combined_hasher.update(file_path.as_bytes());
+        combined_hasher.update(hash.as_bytes());
+    }
+    format!("{:x}", combined_hasher.finalize())
+}
+
+/// The caller's in-memory file contents, keyed by path relative to the repository root.
+/// Absolute paths outside the repository are dropped.
+/// Whether `path`, absolute or relative to `workdir`, is one of `submodules` or inside one
+fn in_submodule(path: &str, workdir: &Path, submodules: &[String]) -> bool {
+    let path = Path::new(path);
+    let relative = path.strip_prefix(workdir).unwrap_or(path);
+    submodules
+        .iter()
+        .any(|submodule| relative.starts_with(submodule))
+}
+
+/// Checkpoint each checked out submodule the edits may have landed in, in the submodule's own
+/// repository and working logs. A failing submodule doesn't fail the parent's checkpoint.
+fn checkpoint_submodules(
+    repo: &Repository,
+    author: &str,
+    kind: CheckpointKind,
+    submodules: &[String],
+    options: CheckpointOptions<'_>,
+    agent_run_result: Option<&AgentRunResult>,
+) {
+    let Ok(workdir) = repo.workdir() else {
+        return;
+    };
+    for submodule in submodules {
+        let root = workdir.join(submodule);
+        // Not checked out (`git submodule update` never ran): nothing to attribute
+        if !root.join(".git").exists() {
+            continue;
+        }
+        let agent_run = match agent_run_result {
+            Some(result) => match agent_run_in_submodule(result, &workdir, submodule) {
+                Some(agent_run) => Some(agent_run),
+                None => continue,
+            },
+            None => None,
+        };
+        let submodule_repo = match find_repository_in_path(&root.to_string_lossy()) {
+            Ok(submodule_repo) => submodule_repo,
+            Err(e) => {
+                debug_log(&format!("Skipping submodule {}: {}", submodule, e));
+                continue;
+            }
+        };
+        let same_root = |dir: &Path| dir.canonicalize().ok() == root.canonicalize().ok();
+        if !submodule_repo.workdir().is_ok_and(|dir| same_root(&dir)) {
+            continue;
+        }
+        if let Err(e) = run(&submodule_repo, author, kind, agent_run, options) {
+            debug_log(&format!(
+                "Failed to checkpoint submodule {}: {}",
+                submodule, e
+            ));
+        }
+    }
+}
+
+/// The agent run as seen from inside `submodule`: its paths relative to the submodule's root.
+/// None when the run names the files it edits and none of them are in the submodule.
+fn agent_run_in_submodule(
+    result: &AgentRunResult,
+    workdir: &Path,
+    submodule: &str,
+) -> Option<AgentRunResult> {
+    let root = workdir.join(submodule);
+    let relative = |path: &str| {
+        let path = Path::new(path);
+        let inside = if path.is_absolute() {
+            path.strip_prefix(&root).ok()?
+        } else {
+            path.strip_prefix(submodule).ok()?
+        };
+        Some(to_repo_path(&inside.to_string_lossy())).filter(|inside| !inside.is_empty())
+    };
+    let relative_paths = |paths: &Vec<String>| {
+        paths
+            .iter()
+            .filter_map(|path| relative(path))
+            .collect::<Vec<_>>()
+    };
+
+    let mut agent_run = result.clone();
+    agent_run.repo_working_dir = Some(root.to_string_lossy().to_string());
+    agent_run.edited_filepaths = result.edited_filepaths.as_ref().map(relative_paths);
+    agent_run.will_edit_filepaths = result.will_edit_filepaths.as_ref().map(relative_paths);
+    agent_run.file_contents = result.file_contents.as_ref().map(|contents| {
+        contents
+            .iter()
+            .filter_map(|(path, content)| Some((relative(path)?, content.clone())))
+            .collect()
+    });
+
+    let named = if result.checkpoint_kind == CheckpointKind::Human {
+        &agent_run.will_edit_filepaths
+    } else {
+        &agent_run.edited_filepaths
+    };
+    match named {
+        Some(paths) if paths.is_empty() => None,
+        _ => Some(agent_run),
+    }
+}
+
+fn in_memory_contents(
+    repo: &Repository,
+    agent_run_result: Option<&AgentRunResult>,
+) -> FileContents {
+    let Some(file_contents) = agent_run_result.and_then(|result| result.file_contents.as_ref())
+    else {
+        return FileContents::new();
+    };
+    let workdir = repo.workdir().ok();
+    file_contents
+        .iter()
+        .filter_map(|(path, content)| {
+            let path = Path::new(path);
+            let relative = if path.is_absolute() {
+                path.strip_prefix(workdir.as_ref()?).ok()?
+            } else {
+                path
+            };
+            Some((
+                to_repo_path(&relative.to_string_lossy()),
+                normalize_line_endings(content.clone()),
+            ))
+        })
+        .collect()
+}
+
+/// The file's content as held in memory, or else as it is on disk
+fn current_content(repo_root: &Path, file_path: &str, contents: &FileContents) -> String {
+    match contents.get(file_path) {
+        Some(content) => content.clone(),
+        None => read_working_copy_file(repo_root, file_path),
+    }
+}
+
+/// A working copy file as checkpoints hash and diff it: lossily decoded and with LF line
+/// endings. Empty when it can't be read.
+fn read_working_copy_file(repo_root: &Path, file_path: &str) -> String {
+    std::fs::read(join_repo_path(repo_root, file_path))
+        .map(|bytes| normalize_line_endings(String::from_utf8_lossy(&bytes).to_string()))
+        .unwrap_or_default()
+}
+
+/// The file's content at the latest checkpoint that has an entry for it
+fn last_checkpointed_content(
+    working_log: &PersistedWorkingLog,
+    checkpoints: &[Checkpoint],
+    file_path: &str,
+) -> Option<String> {
+    let entry = checkpoints
+        .iter()
+        .rev()
+        .find_map(|checkpoint| checkpoint.entries.iter().find(|e| e.file == file_path))?;
+    working_log
+        .get_file_version(&entry.blob_sha)
+        .ok()
+        .map(normalize_line_endings)
+}
+
 fn save_current_file_states(
     working_log: &PersistedWorkingLog,
     files: &[String],
+    file_state_cache: &mut FileStateCache,
+    contents: &FileContents,
 ) -> Result<HashMap<String, String>, GitAiError> {
     let mut file_content_hashes = HashMap::new();
 
     for file_path in files {
-        let abs_path = working_log.repo_root.join(file_path);
-        let content = if abs_path.exists() {
-            // Read file as bytes first, then convert to string with UTF-8 lossy conversion
-            match std::fs::read(&abs_path) {
-                Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
-                Err(_) => String::new(), // If we can't read the file, treat as empty
-            }
-        } else {
-            String::new()
-        };
+        // In-memory contents say nothing about the file on disk, so they never go in the cache
+        if let Some(content) = contents.get(file_path) {
+            file_state_cache.files.remove(file_path);
+            let content_hash = working_log.persist_file_version(content)?;
+            file_content_hashes.insert(file_path.clone(), content_hash);
+            continue;
+        }
+
+        let abs_path = join_repo_path(&working_log.repo_root, file_path);
+        let metadata = std::fs::metadata(&abs_path).ok();
+
+        // Short-circuit unchanged files: reuse the hash (and blob) from the last checkpoint
+        if let Some(metadata) = &metadata
+            && let Some(blob_sha) = file_state_cache.lookup(file_path, metadata)
+            && working_log.has_file_version(blob_sha)
+        {
+            file_content_hashes.insert(file_path.clone(), blob_sha.to_string());
+            continue;
+        }
+
+        // Missing or unreadable files are treated as empty
+        let content = read_working_copy_file(&working_log.repo_root, file_path);
 
         // Persist the file content and get the content hash
         let content_hash = working_log.persist_file_version(&content)?;
+        match &metadata {
+            Some(metadata) => file_state_cache.record(file_path, metadata, &content_hash),
+            None => {
+                file_state_cache.files.remove(file_path);
+            }
+        }
         file_content_hashes.insert(file_path.clone(), content_hash);
     }
 
     Ok(file_content_hashes)
 }
 
-async fn get_initial_checkpoint_entries(
+/// What every entry of one checkpoint shares: who the changes are attributed to, their
+/// timestamp, how attribution follows them, and how many files are diffed at once
+#[derive(Clone, Copy)]
+struct EntryOptions<'a> {
     kind: CheckpointKind,
+    agent_run_result: Option<&'a AgentRunResult>,
+    ts: u128,
+    attribution_config: AttributionConfig,
+    concurrency: usize,
+}
+
+impl EntryOptions<'_> {
+    /// The author id of the changes: the session hash for AI checkpoints, the checkpoint kind
+    /// otherwise
+    fn author_id(&self) -> String {
+        if self.kind != CheckpointKind::Human {
+            self.agent_run_result
+                .map(|result| {
+                    crate::authorship::authorship_log_serialization::generate_short_hash(
+                        &result.agent_id.id,
+                        &result.agent_id.tool,
+                    )
+                })
+                .unwrap_or_else(|| self.kind.to_str())
+        } else {
+            self.kind.to_str()
+        }
+    }
+}
+
+async fn get_initial_checkpoint_entries(
     repo: &Repository,
     working_log: &PersistedWorkingLog,
     files: &[String],
     _base_commit: &str,
     file_content_hashes: &HashMap<String, String>,
-    agent_run_result: Option<&AgentRunResult>,
-    ts: u128,
+    contents: &FileContents,
+    options: EntryOptions<'_>,
 ) -> Result<Vec<WorkingLogEntry>, GitAiError> {
     // Read INITIAL attributions from working log (empty if file doesn't exist)
     let initial_data = working_log.read_initial_attributions();
     let initial_attributions = initial_data.files;
 
-    // Determine author_id based on checkpoint kind and agent_id
-    let author_id = if kind != CheckpointKind::Human {
-        // For AI checkpoints, use session hash
-        agent_run_result
-            .map(|result| {
-                crate::authorship::authorship_log_serialization::generate_short_hash(
-                    &result.agent_id.id,
-                    &result.agent_id.tool,
-                )
-            })
-            .unwrap_or_else(|| kind.to_str())
-    } else {
-        // For human checkpoints, use checkpoint kind string
-        kind.to_str()
-    };
+    let author_id = options.author_id();
+    let EntryOptions {
+        kind,
+        ts,
+        attribution_config,
+        ..
+    } = options;
 
     // Diff working directory against HEAD tree for each file
     let head_commit = repo
@@ -471,10 +1134,15 @@ async fn get_initial_checkpoint_entries(
         .and_then(|c| c.tree().ok())
         .map(|t| t.id());
 
-    const MAX_CONCURRENT: usize = 30;
+    // Lineage committed with HEAD, so chains keep growing from one commit to the next
+    let head_lineage = head_commit_sha
+        .as_deref()
+        .and_then(|sha| get_reference_as_authorship_log_v3(repo, sha).ok())
+        .map(|log| log.metadata.lineage)
+        .unwrap_or_default();
 
     // Create a semaphore to limit concurrent tasks
-    let semaphore = Arc::new(smol::lock::Semaphore::new(MAX_CONCURRENT));
+    let semaphore = Arc::new(smol::lock::Semaphore::new(options.concurrency.max(1)));
 
     // Spawn tasks for each file
     let mut tasks = Vec::new();
@@ -496,6 +1164,8 @@ async fn get_initial_checkpoint_entries(
             .get(&file_path)
             .cloned()
             .unwrap_or_default();
+        let lineage_for_file = head_lineage.get(&file_path).cloned().unwrap_or_default();
+        let content_in_memory = contents.get(&file_path).cloned();
 
         let task = smol::spawn(async move {
             // Acquire semaphore permit to limit concurrency
@@ -504,7 +1174,6 @@ async fn get_initial_checkpoint_entries(
             // Wrap all the blocking git operations in smol::unblock
             smol::unblock(move || {
                 let repo_workdir = repo.workdir().unwrap();
-                let abs_path = repo_workdir.join(&file_path);
 
                 // Previous content from HEAD tree if present, otherwise empty
                 let previous_content = if let Some(tree_id) = &head_tree_id {
@@ -514,7 +1183,9 @@ async fn get_initial_checkpoint_entries(
                             Ok(entry) => {
                                 if let Ok(blob) = repo.find_blob(entry.id()) {
                                     let blob_content = blob.content().unwrap_or_default();
-                                    String::from_utf8_lossy(&blob_content).to_string()
+                                    normalize_line_endings(
+                                        String::from_utf8_lossy(&blob_content).to_string(),
+                                    )
                                 } else {
                                     String::new()
                                 }
@@ -528,9 +1199,9 @@ async fn get_initial_checkpoint_entries(
                     String::new()
                 };
 
-                // Current content from filesystem
-                let current_content =
-                    std::fs::read_to_string(&abs_path).unwrap_or_else(|_| String::new());
+                // Current content from memory or the filesystem
+                let current_content = content_in_memory
+                    .unwrap_or_else(|| read_working_copy_file(&repo_workdir, &file_path));
 
                 // Skip if no changes, UNLESS we have INITIAL attributions for this file
                 // (in which case we need to create an entry to record those attributions)
@@ -631,17 +1302,22 @@ async fn get_initial_checkpoint_entries(
                         (&previous_content, &current_content)
                     };
 
+                let revision = FileRevision {
+                    previous_content: prev_content_for_entry.clone(),
+                    previous_attributions: prev_attributions,
+                    content: curr_content_for_entry.clone(),
+                };
                 let entry = make_entry_for_file(
                     &file_path,
                     &blob_sha,
                     &author_id,
-                    prev_content_for_entry,
-                    &prev_attributions,
-                    curr_content_for_entry,
+                    &revision,
+                    &lineage_for_file,
                     ts,
+                    attribution_config,
                 )?;
 
-                Ok(Some(entry))
+                Ok(Some((entry, revision)))
             })
             .await
         });
@@ -654,112 +1330,211 @@ async fn get_initial_checkpoint_entries(
 
     // Process results
     let mut entries = Vec::new();
+    let mut revisions = Vec::new();
     for result in results {
         match result {
-            Ok(Some(entry)) => entries.push(entry),
+            Ok(Some((entry, revision))) => {
+                entries.push(entry);
+                revisions.push(revision);
+            }
             Ok(None) => {} // File had no changes
             Err(e) => return Err(e),
         }
     }
 
+    carry_moves_between_files(&mut entries, &revisions, attribution_config);
     Ok(entries)
 }
 
-fn get_subsequent_checkpoint_entries(
-    kind: CheckpointKind,
+async fn get_subsequent_checkpoint_entries(
     working_log: &PersistedWorkingLog,
     files: &[String],
     file_content_hashes: &HashMap<String, String>,
+    contents: &FileContents,
     previous_checkpoints: &Vec<Checkpoint>,
-    agent_run_result: Option<&AgentRunResult>,
-    ts: u128,
+    options: EntryOptions<'_>,
 ) -> Result<Vec<WorkingLogEntry>, GitAiError> {
-    let mut entries = Vec::new();
-
-    // Determine author_id based on checkpoint kind and agent_id
-    let author_id = if kind != CheckpointKind::Human {
-        // For AI checkpoints, use session hash
-        agent_run_result
-            .map(|result| {
-                crate::authorship::authorship_log_serialization::generate_short_hash(
-                    &result.agent_id.id,
-                    &result.agent_id.tool,
-                )
-            })
-            .unwrap_or_else(|| kind.to_str())
-    } else {
-        // For human checkpoints, use checkpoint kind string
-        kind.to_str()
-    };
-
-    // Build a map of file path -> (blob_sha, attributions) by iterating through previous checkpoints to get the latest
-    let mut previous_file_hashes_with_attributions: HashMap<String, (String, Vec<Attribution>)> =
-        HashMap::new();
+    let author_id = options.author_id();
+    let EntryOptions {
+        ts,
+        attribution_config,
+        ..
+    } = options;
+
+    // Build a map of file path -> (blob_sha, attributions, lineage) by iterating through previous checkpoints to get the latest
+    let mut previous_file_hashes_with_attributions: HashMap<
+        &str,
+        (&str, &[Attribution], &PromptLineage),
+    > = HashMap::new();
     for checkpoint in previous_checkpoints {
         for entry in &checkpoint.entries {
             previous_file_hashes_with_attributions.insert(
-                entry.file.clone(),
-                (entry.blob_sha.clone(), entry.attributions.clone()),
+                entry.file.as_str(),
+                (
+                    entry.blob_sha.as_str(),
+                    entry.attributions.as_slice(),
+                    &entry.lineage,
+                ),
             );
         }
     }
 
-    for file_path in files {
-        let abs_path = working_log.repo_root.join(file_path);
+    // Limit how many files are diffed at once
+    let semaphore = Arc::new(smol::lock::Semaphore::new(options.concurrency.max(1)));
 
-        // Read current content directly from the file system
-        let current_content = std::fs::read_to_string(&abs_path).unwrap_or_else(|_| String::new());
+    // Spawn tasks for each file
+    let mut tasks = Vec::new();
 
-        // Read the previous content from the blob storage using the previous checkpoint's blob_sha
-        let (previous_content, prev_attributions) = if let Some((prev_content_hash, prev_attrs)) =
-            previous_file_hashes_with_attributions.get(file_path)
-        {
-            (
-                working_log
-                    .get_file_version(prev_content_hash)
-                    .unwrap_or_default(),
-                prev_attrs.clone(),
-            )
-        } else {
-            (String::new(), Vec::new()) // No previous version, treat as empty
-        };
+    for file_path in files {
+        let previous = previous_file_hashes_with_attributions.get(file_path.as_str());
 
-        if current_content == previous_content {
-            // No changes, no need to add entries
+        // A file whose hash matches the last checkpoint's is unchanged; with the file state
+        // cache that's known without reading either version
+        if let Some((prev_content_hash, _, _)) = previous
+            && file_content_hashes.get(file_path).map(String::as_str) == Some(*prev_content_hash)
+        {
             continue;
         }
 
-        // Get the blob SHA for this file from the pre-computed hashes
+        let file_path = file_path.clone();
+        let working_log = working_log.clone();
+        let author_id = author_id.clone();
         let blob_sha = file_content_hashes
-            .get(file_path)
+            .get(&file_path)
             .cloned()
             .unwrap_or_default();
+        let prev_content_hash = previous.map(|(hash, _, _)| hash.to_string());
+        let prev_attributions = previous
+            .map(|(_, attrs, _)| attrs.to_vec())
+            .unwrap_or_default();
+        let prev_lineage = previous
+            .map(|(_, _, lineage)| (*lineage).clone())
+            .unwrap_or_default();
+        let content_in_memory = contents.get(&file_path).cloned();
+        let semaphore = Arc::clone(&semaphore);
 
-        let entry = make_entry_for_file(
-            file_path,
-            &blob_sha,
-            &author_id,
-            &previous_content,
-            &prev_attributions,
-            &current_content,
-            ts,
-        )?;
-        entries.push(entry);
+        let task = smol::spawn(async move {
+            // Acquire semaphore permit to limit concurrency
+            let _permit = semaphore.acquire().await;
+
+            smol::unblock(move || {
+                let current_content = content_in_memory
+                    .unwrap_or_else(|| read_working_copy_file(&working_log.repo_root, &file_path));
+
+                // Read the previous content from the blob storage using the previous checkpoint's blob_sha
+                // No previous version, treat as empty. Versions saved before line endings were
+                // normalized may still hold CRLF.
+                let previous_content = prev_content_hash
+                    .and_then(|hash| working_log.get_file_version(&hash).ok())
+                    .map(normalize_line_endings)
+                    .unwrap_or_default();
+
+                if current_content == previous_content {
+                    // No changes, no need to add entries
+                    return Ok(None);
+                }
+
+                let revision = FileRevision {
+                    previous_content,
+                    previous_attributions: prev_attributions,
+                    content: current_content,
+                };
+                let entry = make_entry_for_file(
+                    &file_path,
+                    &blob_sha,
+                    &author_id,
+                    &revision,
+                    &prev_lineage,
+                    ts,
+                    attribution_config,
+                )?;
+
+                Ok(Some((entry, revision)))
+            })
+            .await
+        });
+
+        tasks.push(task);
     }
 
+    // Results come back in the order the files were given, whichever task finished first
+    let results = futures::future::join_all(tasks).await;
+
+    let mut entries = Vec::new();
+    let mut revisions = Vec::new();
+    for result in results {
+        match result {
+            Ok(Some((entry, revision))) => {
+                entries.push(entry);
+                revisions.push(revision);
+            }
+            Ok(None) => {} // File had no changes
+            Err(e) => return Err(e),
+        }
+    }
+
+    carry_moves_between_files(&mut entries, &revisions, attribution_config);
     Ok(entries)
 }
 
+/// A file's content before and after a checkpoint, kept around to follow code moved between
+/// files once every entry has been computed
+struct FileRevision {
+    previous_content: String,
+    previous_attributions: Vec<Attribution>,
+    content: String,
+}
+
+/// Give blocks moved from one file to another in this checkpoint the authors they had in the
+/// file they came from, then refresh the affected line attributions
+fn carry_moves_between_files(
+    entries: &mut [WorkingLogEntry],
+    revisions: &[FileRevision],
+    attribution_config: AttributionConfig,
+) {
+    if entries.len() < 2 {
+        return;
+    }
+
+    let changes: Vec<FileChange> = revisions
+        .iter()
+        .map(|revision| FileChange {
+            previous_content: &revision.previous_content,
+            previous_attributions: &revision.previous_attributions,
+            content: &revision.content,
+        })
+        .collect();
+    let mut attributions: Vec<Vec<Attribution>> = entries
+        .iter_mut()
+        .map(|entry| std::mem::take(&mut entry.attributions))
+        .collect();
+
+    AttributionTracker::with_config(attribution_config)
+        .carry_moves_between_files(&changes, &mut attributions);
+
+    for ((entry, revision), attributions) in entries.iter_mut().zip(revisions).zip(attributions) {
+        entry.line_attributions =
+            crate::authorship::attribution_tracker::attributions_to_line_attributions(
+                &attributions,
+                &revision.content,
+            );
+        entry.attributions = attributions;
+    }
+}
+
 fn make_entry_for_file(
     file_path: &str,
     blob_sha: &str,
     author_id: &str,
-    previous_content: &str,
-    previous_attributions: &Vec<Attribution>,
-    content: &str,
+    revision: &FileRevision,
+    previous_lineage: &PromptLineage,
     ts: u128,
+    attribution_config: AttributionConfig,
 ) -> Result<WorkingLogEntry, GitAiError> {
-    let tracker = AttributionTracker::new();
+    let previous_content = revision.previous_content.as_str();
+    let previous_attributions = revision.previous_attributions.as_slice();
+    let content = revision.content.as_str();
+    let tracker = attribution_algorithm(attribution_config);
     let filled_in_prev_attributions = tracker.attribute_unattributed_ranges(
         previous_content,
         previous_attributions,
@@ -780,12 +1555,38 @@ fn make_entry_for_file(
             &new_attributions,
             content,
         );
-    Ok(WorkingLogEntry::new(
+
+    // Only an AI checkpoint replacing lines another prompt wrote extends the lineage, so the
+    // previous line attributions are only worked out when that could have happened
+    let human = CheckpointKind::Human.to_str();
+    let previous_line_attributions = if author_id != human
+        && filled_in_prev_attributions
+            .iter()
+            .any(|attr| attr.author_id != human && attr.author_id != author_id)
+    {
+        crate::authorship::attribution_tracker::attributions_to_line_attributions(
+            &filled_in_prev_attributions,
+            previous_content,
+        )
+    } else {
+        Vec::new()
+    };
+    let lineage = update_lineage(
+        previous_lineage,
+        previous_content,
+        &previous_line_attributions,
+        content,
+        &line_attributions,
+    );
+
+    let mut entry = WorkingLogEntry::new(
         file_path.to_string(),
         blob_sha.to_string(),
         new_attributions,
         line_attributions,
-    ))
+    );
+    entry.lineage = lineage;
+    Ok(entry)
 }
 
 /// Compute line statistics by diffing files against their previous versions
@@ -796,6 +1597,7 @@ fn compute_line_stats(
     entries: &[WorkingLogEntry],
     previous_checkpoints: &[Checkpoint],
     kind: CheckpointKind,
+    contents: &FileContents,
 ) -> Result<crate::authorship::working_log::CheckpointLineStats, GitAiError> {
     // Start with previous checkpoint's stats (if exists)
     let mut stats = previous_checkpoints
@@ -804,53 +1606,62 @@ fn compute_line_stats(
         .unwrap_or_default();
 
     // Build a map of file path -> most recent (blob_sha, line_attributions)
-    let mut previous_file_state: HashMap<String, (String, Vec<LineAttribution>)> = HashMap::new();
+    let mut previous_file_state: HashMap<&str, (&str, &[LineAttribution])> = HashMap::new();
     for checkpoint in previous_checkpoints {
         for entry in &checkpoint.entries {
             previous_file_state.insert(
-                entry.file.clone(),
-                (entry.blob_sha.clone(), entry.line_attributions.clone()),
+                entry.file.as_str(),
+                (entry.blob_sha.as_str(), entry.line_attributions.as_slice()),
             );
         }
     }
 
+    // Files without a previous version are compared against HEAD, looked up on first use
+    let mut head_tree = None;
+
     // Count added/deleted lines for each file in this checkpoint
     let mut total_additions = 0u32;
     let mut total_deletions = 0u32;
 
     // good candidate for parallelization
     for file_path in files {
-        let abs_path = working_log.repo_root.join(file_path);
-        let current_content = std::fs::read_to_string(&abs_path).unwrap_or_else(|_| String::new());
+        let current_content = current_content(&working_log.repo_root, file_path, contents);
 
         // Get previous content
-        let previous_content = if let Some((prev_hash, _)) = previous_file_state.get(file_path) {
-            working_log.get_file_version(prev_hash).unwrap_or_default()
-        } else {
-            // No previous version, try to get from HEAD
-            let head_commit = repo
-                .head()
-                .ok()
-                .and_then(|h| h.target().ok())
-                .and_then(|oid| repo.find_commit(oid).ok());
-            let head_tree = head_commit.as_ref().and_then(|c| c.tree().ok());
-
-            if let Some(tree) = head_tree {
-                match tree.get_path(std::path::Path::new(file_path)) {
-                    Ok(entry) => {
-                        if let Ok(blob) = repo.find_blob(entry.id()) {
-                            let blob_content = blob.content().unwrap_or_default();
-                            String::from_utf8_lossy(&blob_content).to_string()
-                        } else {
-                            String::new()
+        let previous_content =
+            if let Some((prev_hash, _)) = previous_file_state.get(file_path.as_str()) {
+                working_log
+                    .get_file_version(prev_hash)
+                    .map(normalize_line_endings)
+                    .unwrap_or_default()
+            } else {
+                // No previous version, try to get from HEAD
+                let head_tree = head_tree.get_or_insert_with(|| {
+                    repo.head()
+                        .ok()
+                        .and_then(|h| h.target().ok())
+                        .and_then(|oid| repo.find_commit(oid).ok())
+                        .and_then(|c| c.tree().ok())
+                });
+
+                if let Some(tree) = head_tree {
+                    match tree.get_path(std::path::Path::new(file_path)) {
+                        Ok(entry) => {
+                            if let Ok(blob) = repo.find_blob(entry.id()) {
+                                let blob_content = blob.content().unwrap_or_default();
+                                normalize_line_endings(
+                                    String::from_utf8_lossy(&blob_content).to_string(),
+                                )
+                            } else {
+                                String::new()
+                            }
                         }
+                        Err(_) => String::new(),
                     }
-                    Err(_) => String::new(),
+                } else {
+                    String::new()
                 }
-            } else {
-                String::new()
-            }
-        };
+            };
 
         // Use TextDiff to count line changes
         let diff = TextDiff::from_lines(&previous_content, &current_content);
@@ -883,7 +1694,7 @@ fn compute_line_stats(
     for entry in entries {
         let current_overrides = collect_overridden_lines(&entry.line_attributions);
         let previous_overrides = previous_file_state
-            .get(&entry.file)
+            .get(entry.file.as_str())
             .map(|(_, attrs)| collect_overridden_lines(attrs))
             .unwrap_or_else(HashSet::new);
 
@@ -932,6 +1743,14 @@ mod tests {
     use super::*;
     use crate::git::test_utils::TmpRepo;
 
+    #[test]
+    fn test_contains_null_byte_reads_past_first_chunk() {
+        let mut content = vec![b'a'; 200 * 1024];
+        assert!(!contains_null_byte(content.as_slice()).unwrap());
+        content[150 * 1024] = 0;
+        assert!(contains_null_byte(content.as_slice()).unwrap());
+    }
+
     #[test]
     fn test_checkpoint_with_staged_changes() {
         // Create a repo with an initial commit
@@ -976,6 +1795,80 @@ mod tests {
         assert_eq!(entries_len, 1, "Should have 1 file entry in checkpoint");
     }
 
+    #[test]
+    fn test_checkpoint_file_state_cache_skips_unchanged_files() {
+        let (tmp_repo, mut file, _) = TmpRepo::new_with_base_commit().unwrap();
+
+        file.append("New line added by user\n").unwrap();
+        let (entries_len, _, _) = tmp_repo.trigger_checkpoint_with_author("Aidan").unwrap();
+        assert_eq!(entries_len, 1);
+
+        let working_log = RepoStorage::for_repo_path(tmp_repo.repo().path())
+            .working_log_for_base_commit(&tmp_repo.get_head_commit_sha().unwrap());
+        let cache = working_log.read_file_state_cache();
+        assert!(
+            cache.files.contains_key(file.filename()),
+            "Checkpointed file should be recorded in the file state cache"
+        );
+
+        // Nothing changed, so the second checkpoint is a no-op
+        let (entries_len, files_len, _) = tmp_repo.trigger_checkpoint_with_author("Aidan").unwrap();
+        assert_eq!(files_len, 1);
+        assert_eq!(entries_len, 0);
+
+        // A real edit still invalidates the cached state
+        file.append("Another line\n").unwrap();
+        let (entries_len, _, _) = tmp_repo.trigger_checkpoint_with_author("Aidan").unwrap();
+        assert_eq!(entries_len, 1);
+    }
+
+    #[test]
+    fn test_checkpoint_with_explicit_base_commit() {
+        let (tmp_repo, mut file, _) = TmpRepo::new_with_base_commit().unwrap();
+        let first_sha = tmp_repo.get_head_commit_sha().unwrap();
+        file.append("Second commit\n").unwrap();
+        tmp_repo.commit_with_message("Second commit").unwrap();
+        let head_sha = tmp_repo.get_head_commit_sha().unwrap();
+
+        file.append("New line added by user\n").unwrap();
+        let short_sha = &first_sha[..10];
+        let (entries_len, _, _) = run(
+            tmp_repo.gitai_repo(),
+            "Aidan",
+            CheckpointKind::Human,
+            None,
+            CheckpointOptions {
+                quiet: true,
+                base_commit: Some(short_sha),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(entries_len, 1);
+
+        // Written to the pinned commit's working log, under its full sha, and not HEAD's
+        let storage = RepoStorage::for_repo_path(tmp_repo.repo().path());
+        let pinned = storage.working_log_for_base_commit(&first_sha);
+        assert_eq!(pinned.read_all_checkpoints().unwrap().len(), 1);
+        let head = storage.working_log_for_base_commit(&head_sha);
+        assert!(head.read_all_checkpoints().unwrap().is_empty());
+
+        for invalid in ["not-a-commit", "", "--all", "HEAD^{tree}"] {
+            let result = run(
+                tmp_repo.gitai_repo(),
+                "Aidan",
+                CheckpointKind::Human,
+                None,
+                CheckpointOptions {
+                    quiet: true,
+                    base_commit: Some(invalid),
+                    ..Default::default()
+                },
+            );
+            assert!(result.is_err(), "{:?} should be rejected", invalid);
+        }
+    }
+
     #[test]
     fn test_checkpoint_with_staged_changes_after_previous_checkpoint() {
         // Create a repo with an initial commit
@@ -1175,6 +2068,9 @@ mod tests {
                 file.filename().to_string(), // This one is valid
             ]),
             will_edit_filepaths: None,
+            commit_message_source: None,
+            agent_version: None,
+            file_contents: None,
         };
 
         // Run checkpoint - should not crash even with paths outside repo
@@ -1194,6 +2090,121 @@ mod tests {
         assert_eq!(entries_len, 1, "Should create 1 entry");
     }
 
+    #[test]
+    fn test_checkpoint_ignores_crlf_line_endings_in_working_copy() {
+        let tmp_repo = TmpRepo::new().unwrap();
+        let file = tmp_repo
+            .write_file("crlf.txt", "first\nsecond\n", true)
+            .unwrap();
+        tmp_repo
+            .trigger_checkpoint_with_author("test_user")
+            .unwrap();
+        tmp_repo.commit_with_message("initial commit").unwrap();
+
+        // Checked out with CRLF (core.autocrlf on Windows) plus one line from the agent
+        std::fs::write(file.path(), "first\r\nsecond\r\nAI line\r\n").unwrap();
+        tmp_repo
+            .trigger_checkpoint_with_ai("mock_ai", None, None)
+            .unwrap();
+
+        let repo =
+            crate::git::repository::find_repository_in_path(tmp_repo.path().to_str().unwrap())
+                .unwrap();
+        let checkpoints = repo
+            .storage
+            .working_log_for_base_commit(&tmp_repo.head_commit_sha().unwrap())
+            .read_all_checkpoints()
+            .unwrap();
+        let stats = &checkpoints.last().unwrap().line_stats;
+        assert_eq!(stats.ai_agent_additions, 1);
+        assert_eq!(stats.ai_agent_deletions, 0);
+    }
+
+    #[test]
+    fn test_checkpoint_accepts_backslash_separated_paths() {
+        use crate::authorship::transcript::AiTranscript;
+        use crate::authorship::working_log::AgentId;
+        use crate::commands::checkpoint_agent::agent_presets::AgentRunResult;
+
+        let (tmp_repo, _, _) = TmpRepo::new_with_base_commit().unwrap();
+        tmp_repo
+            .write_file("src/nested.txt", "Nested line\n", false)
+            .unwrap();
+
+        // Agents on Windows report paths with backslashes
+        let agent_run_result = AgentRunResult {
+            agent_id: AgentId {
+                tool: "test_tool".to_string(),
+                id: "test_session".to_string(),
+                model: "test_model".to_string(),
+            },
+            transcript: Some(AiTranscript { messages: vec![] }),
+            checkpoint_kind: CheckpointKind::AiAgent,
+            repo_working_dir: None,
+            edited_filepaths: Some(vec!["src\\nested.txt".to_string()]),
+            will_edit_filepaths: None,
+            commit_message_source: None,
+            agent_version: None,
+            file_contents: None,
+        };
+
+        let (entries_len, files_len, _) = tmp_repo
+            .trigger_checkpoint_with_agent_result("test_user", Some(agent_run_result))
+            .unwrap();
+        assert_eq!(files_len, 1);
+        assert_eq!(entries_len, 1);
+    }
+
+    #[test]
+    fn test_checkpoint_records_ai_commit_message_in_note() {
+        use crate::authorship::authorship_log::{CommitMessageAuthorship, CommitMessageSource};
+        use crate::authorship::transcript::AiTranscript;
+        use crate::authorship::working_log::AgentId;
+        use crate::commands::checkpoint_agent::agent_presets::AgentRunResult;
+
+        let (tmp_repo, mut file, _) = TmpRepo::new_with_base_commit().unwrap();
+        file.append("AI line\n").unwrap();
+
+        let agent_id = AgentId {
+            tool: "cursor".to_string(),
+            id: "session".to_string(),
+            model: "test_model".to_string(),
+        };
+        let agent_run_result = AgentRunResult {
+            agent_id: agent_id.clone(),
+            transcript: Some(AiTranscript { messages: vec![] }),
+            checkpoint_kind: CheckpointKind::AiAgent,
+            repo_working_dir: None,
+            edited_filepaths: None,
+            will_edit_filepaths: None,
+            commit_message_source: Some(CommitMessageSource::Ai),
+            agent_version: None,
+            file_contents: None,
+        };
+        tmp_repo
+            .trigger_checkpoint_with_agent_result("test_user", Some(agent_run_result))
+            .unwrap();
+
+        let authorship_log = tmp_repo.commit_with_message("AI commit").unwrap();
+        let commit_message = authorship_log.metadata.commit_message.unwrap();
+        assert_eq!(
+            commit_message,
+            CommitMessageAuthorship {
+                source: CommitMessageSource::Ai,
+                agent_id: Some(agent_id),
+            }
+        );
+        assert_eq!(commit_message.label(), "AI (cursor)");
+
+        // The recorded source only applies to the commit it was made for
+        file.append("Human line\n").unwrap();
+        tmp_repo
+            .trigger_checkpoint_with_author("test_user")
+            .unwrap();
+        let authorship_log = tmp_repo.commit_with_message("Human commit").unwrap();
+        assert!(authorship_log.metadata.commit_message.is_none());
+    }
+
     #[test]
     fn test_checkpoint_works_after_conflict_resolution_maintains_authorship() {
         // Create a repo with an initial commit
@@ -1428,6 +2439,7 @@ mod tests {
             &entries,
             &[ai_checkpoint.clone()],
             CheckpointKind::Human,
+            &FileContents::new(),
         )
         .expect("compute_line_stats should succeed");
 
@@ -1452,6 +2464,7 @@ mod tests {
             &entries,
             &previous_checkpoints,
             CheckpointKind::Human,
+            &FileContents::new(),
         )
         .expect("compute_line_stats should succeed when overrides already recorded");
 
@@ -1529,6 +2542,7 @@ mod tests {
             &entries_first,
             &[ai_checkpoint.clone()],
             CheckpointKind::Human,
+            &FileContents::new(),
         )
         .expect("compute_line_stats should succeed for first human edit");
         assert_eq!(
@@ -1563,6 +2577,7 @@ mod tests {
             &entries_second,
             &previous_checkpoints,
             CheckpointKind::Human,
+            &FileContents::new(),
         )
         .expect("compute_line_stats should succeed for subsequent human edit");
 
@@ -1573,24 +2588,79 @@ mod tests {
     }
 }
 
-fn is_text_file(repo: &Repository, path: &str) -> bool {
+/// Whether a file on disk gets checkpointed
+enum FileClass {
+    Text,
+    /// Left out, with its size in bytes
+    Skipped(SkipReason, u64),
+    /// Missing, unreadable or not a regular file
+    Other,
+}
+
+fn classify_file(repo: &Repository, path: &str, threshold_kb: u64) -> FileClass {
     let repo_workdir = repo.workdir().unwrap();
-    let abs_path = repo_workdir.join(path);
+    let abs_path = join_repo_path(&repo_workdir, path);
+
+    // If metadata can't be read, treat as non-text
+    let size = match std::fs::metadata(&abs_path) {
+        Ok(metadata) if metadata.is_file() => metadata.len(),
+        _ => return FileClass::Other,
+    };
 
-    if let Ok(metadata) = std::fs::metadata(&abs_path) {
-        if !metadata.is_file() {
-            return false;
+    // Large files aren't read at all
+    if threshold_kb > 0 && size > threshold_kb.saturating_mul(1024) {
+        return FileClass::Skipped(SkipReason::Large, size);
+    }
+
+    // Consider a file text if it contains no null bytes
+    match std::fs::File::open(&abs_path).and_then(contains_null_byte) {
+        Ok(false) => FileClass::Text,
+        Ok(true) => FileClass::Skipped(SkipReason::Binary, size),
+        Err(_) => FileClass::Other,
+    }
+}
+
+/// Reads `reader` a chunk at a time, stopping at the first null byte
+fn contains_null_byte(mut reader: impl Read) -> std::io::Result<bool> {
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) => return Ok(false),
+            Ok(n) if buf[..n].contains(&0) => return Ok(true),
+            Ok(_) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
         }
-    } else {
-        return false; // If metadata can't be read, treat as non-text
     }
+}
 
-    if let Ok(content) = std::fs::read(&abs_path) {
-        // Consider a file text if it contains no null bytes
-        !content.contains(&0)
-    } else {
-        false
+/// Same as classify_file, but trusts the file state cache (which only ever holds text files)
+fn classify_file_cached(
+    repo: &Repository,
+    path: &str,
+    file_state_cache: &FileStateCache,
+    threshold_kb: u64,
+) -> FileClass {
+    let abs_path = join_repo_path(&repo.workdir().unwrap(), path);
+    if let Ok(metadata) = std::fs::metadata(&abs_path)
+        && metadata.is_file()
+        && file_state_cache.lookup(path, &metadata).is_some()
+    {
+        return FileClass::Text;
     }
+    classify_file(repo, path, threshold_kb)
+}
+
+fn is_text_file_cached(
+    repo: &Repository,
+    path: &str,
+    file_state_cache: &FileStateCache,
+    threshold_kb: u64,
+) -> bool {
+    matches!(
+        classify_file_cached(repo, path, file_state_cache, threshold_kb),
+        FileClass::Text
+    )
 }
 
 fn is_text_file_in_head(repo: &Repository, path: &str) -> bool {
@@ -1,5 +1,6 @@
 use crate::{
     authorship::{
+        authorship_log::CommitMessageSource,
         transcript::{AiTranscript, Message},
         working_log::{AgentId, CheckpointKind},
     },
@@ -7,6 +8,7 @@ use crate::{
 };
 use chrono::{TimeZone, Utc};
 use rusqlite::{Connection, OpenFlags};
+use std::collections::HashMap;
 use std::env;
 use std::path::{Path, PathBuf};
 
@@ -14,6 +16,7 @@ pub struct AgentCheckpointFlags {
     pub hook_input: Option<String>,
 }
 
+#[derive(Clone)]
 pub struct AgentRunResult {
     pub agent_id: AgentId,
     pub checkpoint_kind: CheckpointKind,
@@ -21,6 +24,13 @@ pub struct AgentRunResult {
     pub repo_working_dir: Option<String>,
     pub edited_filepaths: Option<Vec<String>>,
     pub will_edit_filepaths: Option<Vec<String>>,
+    /// Set when the agent reports who is writing the upcoming commit message
+    pub commit_message_source: Option<CommitMessageSource>,
+    /// Version of the agent or its git-ai integration, when it reports one
+    pub agent_version: Option<String>,
+    /// Contents of files by path, e.g. editor buffers not saved yet, checkpointed instead of
+    /// what's on disk
+    pub file_contents: Option<HashMap<String, String>>,
 }
 
 pub trait AgentCheckpointPreset {
@@ -99,6 +109,9 @@ impl AgentCheckpointPreset for ClaudePreset {
                 repo_working_dir: None,
                 edited_filepaths: None,
                 will_edit_filepaths: file_path_as_vec,
+                commit_message_source: None,
+                agent_version: None,
+                file_contents: None,
             });
         }
 
@@ -110,10 +123,185 @@ impl AgentCheckpointPreset for ClaudePreset {
             repo_working_dir: None,
             edited_filepaths: file_path_as_vec,
             will_edit_filepaths: None,
+            commit_message_source: None,
+            agent_version: claude_code_version(&jsonl_content),
+            file_contents: None,
         })
     }
 }
 
+/// Claude Code stamps its version on every transcript line; the latest one wins
+fn claude_code_version(jsonl_content: &str) -> Option<String> {
+    jsonl_content.lines().rev().find_map(|line| {
+        serde_json::from_str::<serde_json::Value>(line)
+            .ok()?
+            .get("version")?
+            .as_str()
+            .map(|version| version.to_string())
+    })
+}
+
+/// Claude Code tools that write files, with the input field naming the file
+const CLAUDE_CODE_EDIT_TOOLS: &[(&str, &str)] = &[
+    ("Edit", "file_path"),
+    ("MultiEdit", "file_path"),
+    ("Write", "file_path"),
+    ("NotebookEdit", "notebook_path"),
+];
+
+// Claude Code session to checkpoint preset, for running without Claude Code's hooks: it reads
+// the session's transcript file and checkpoints every file the session edited
+pub struct ClaudeCodeSessionPreset {
+    /// Directory Claude Code was started in
+    pub working_dir: PathBuf,
+    /// Session id or transcript path. Defaults to the session of `working_dir` written to last.
+    pub session: Option<String>,
+}
+
+impl AgentCheckpointPreset for ClaudeCodeSessionPreset {
+    fn run(&self, flags: AgentCheckpointFlags) -> Result<AgentRunResult, GitAiError> {
+        // Hook input, when there is some, already names the transcript
+        let hook_transcript = match &flags.hook_input {
+            Some(hook_input) => serde_json::from_str::<serde_json::Value>(hook_input)
+                .map_err(|e| GitAiError::PresetError(format!("Invalid JSON in hook_input: {}", e)))?
+                .get("transcript_path")
+                .and_then(|v| v.as_str())
+                .map(PathBuf::from),
+            None => None,
+        };
+        let transcript_path = match hook_transcript {
+            Some(path) => path,
+            None => self.transcript_path()?,
+        };
+        let session_id = transcript_path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| {
+                GitAiError::PresetError(format!(
+                    "Could not extract a session id from {}",
+                    transcript_path.display()
+                ))
+            })?
+            .to_string();
+
+        let jsonl_content = std::fs::read_to_string(&transcript_path)?;
+        let (transcript, model) = AiTranscript::from_claude_code_jsonl_with_model(&jsonl_content)
+            .map_err(GitAiError::JsonError)?;
+
+        let edited_filepaths = claude_code_edited_files(&transcript);
+        if edited_filepaths.is_empty() {
+            return Err(GitAiError::PresetError(format!(
+                "Claude Code session {} hasn't edited any files",
+                session_id
+            )));
+        }
+
+        Ok(AgentRunResult {
+            agent_id: AgentId {
+                tool: "claude".to_string(),
+                id: session_id,
+                model: model.unwrap_or_else(|| "unknown".to_string()),
+            },
+            checkpoint_kind: CheckpointKind::AiAgent,
+            transcript: Some(transcript),
+            repo_working_dir: None,
+            edited_filepaths: Some(edited_filepaths),
+            will_edit_filepaths: None,
+            commit_message_source: None,
+            agent_version: claude_code_version(&jsonl_content),
+            file_contents: None,
+        })
+    }
+}
+
+impl ClaudeCodeSessionPreset {
+    /// The transcript of the requested session, or of the latest one in the working directory
+    fn transcript_path(&self) -> Result<PathBuf, GitAiError> {
+        if let Some(session) = &self.session
+            && (session.ends_with(".jsonl") || session.contains(std::path::MAIN_SEPARATOR))
+        {
+            return Ok(PathBuf::from(session));
+        }
+
+        let project_dir =
+            claude_code_projects_dir()?.join(claude_code_project_name(&self.working_dir));
+        if let Some(session) = &self.session {
+            let path = project_dir.join(format!("{}.jsonl", session));
+            if !path.is_file() {
+                return Err(GitAiError::PresetError(format!(
+                    "No Claude Code session {} in {}",
+                    session,
+                    project_dir.display()
+                )));
+            }
+            return Ok(path);
+        }
+
+        std::fs::read_dir(&project_dir)
+            .ok()
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+            .filter_map(|path| {
+                let modified = path.metadata().ok()?.modified().ok()?;
+                Some((modified, path))
+            })
+            .max()
+            .map(|(_, path)| path)
+            .ok_or_else(|| {
+                GitAiError::PresetError(format!(
+                    "No Claude Code sessions for {} in {}",
+                    self.working_dir.display(),
+                    project_dir.display()
+                ))
+            })
+    }
+}
+
+/// Where Claude Code keeps its transcripts, one directory per project
+fn claude_code_projects_dir() -> Result<PathBuf, GitAiError> {
+    if let Ok(config_dir) = env::var("CLAUDE_CONFIG_DIR")
+        && !config_dir.is_empty()
+    {
+        return Ok(Path::new(&config_dir).join("projects"));
+    }
+    let home = env::var("HOME")
+        .or_else(|_| env::var("USERPROFILE"))
+        .map_err(|e| GitAiError::Generic(format!("HOME not set: {}", e)))?;
+    Ok(Path::new(&home).join(".claude").join("projects"))
+}
+
+/// Claude Code names a project's directory after its path with everything but letters and
+/// digits turned into dashes, e.g. `/Users/me/my_app` into `-Users-me-my-app`
+pub fn claude_code_project_name(working_dir: &Path) -> String {
+    working_dir
+        .to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// Files the session wrote through its editing tools, in the order it first edited them
+pub fn claude_code_edited_files(transcript: &AiTranscript) -> Vec<String> {
+    let mut files: Vec<String> = Vec::new();
+    for message in transcript.messages() {
+        let Message::ToolUse { name, input, .. } = message else {
+            continue;
+        };
+        let Some((_, field)) = CLAUDE_CODE_EDIT_TOOLS.iter().find(|(tool, _)| tool == name) else {
+            continue;
+        };
+        if let Some(file) = input.get(*field).and_then(|v| v.as_str())
+            && !files.iter().any(|seen| seen == file)
+        {
+            files.push(file.to_string());
+        }
+    }
+    files
+}
+
 // Cursor to checkpoint preset
 pub struct CursorPreset;
 
@@ -162,6 +350,11 @@ impl AgentCheckpointPreset for CursorPreset {
             )));
         }
 
+        let cursor_version = hook_data
+            .get("cursor_version")
+            .and_then(|v| v.as_str())
+            .map(|v| v.to_string());
+
         let repo_working_dir = workspace_roots.first().cloned().ok_or_else(|| {
             GitAiError::PresetError("No workspace root found in hook_input".to_string())
         })?;
@@ -179,6 +372,9 @@ impl AgentCheckpointPreset for CursorPreset {
                 repo_working_dir: Some(repo_working_dir),
                 edited_filepaths: None,
                 will_edit_filepaths: None,
+                commit_message_source: None,
+                agent_version: cursor_version.clone(),
+                file_contents: None,
             });
         }
 
@@ -247,6 +443,9 @@ impl AgentCheckpointPreset for CursorPreset {
             repo_working_dir: Some(repo_working_dir),
             edited_filepaths,
             will_edit_filepaths: None,
+            commit_message_source: None,
+            agent_version: cursor_version,
+            file_contents: None,
         })
     }
 }
@@ -600,6 +799,9 @@ impl AgentCheckpointPreset for GithubCopilotPreset {
             repo_working_dir: Some(repo_working_dir),
             edited_filepaths,
             will_edit_filepaths: None,
+            commit_message_source: None,
+            agent_version: None,
+            file_contents: None,
         })
     }
 }
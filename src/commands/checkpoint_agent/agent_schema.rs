@@ -0,0 +1,223 @@
+//! The JSON payload of `git-ai checkpoint agent --stdin-json`, for agents that integrate by
+//! piping what they did to git-ai instead of through a preset of their own.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "tool": "aider",
+//!   "session_id": "3f1c…",
+//!   "model": "gpt-4o",
+//!   "messages": [{ "type": "user", "text": "…" }, { "type": "assistant", "text": "…" }],
+//!   "edited_filepaths": ["src/lib.rs"]
+//! }
+//! ```
+
+use serde::Deserialize;
+
+use crate::{
+    authorship::{
+        transcript::{AiTranscript, Message},
+        working_log::{AgentId, CheckpointKind},
+    },
+    commands::checkpoint_agent::agent_presets::AgentRunResult,
+    error::GitAiError,
+};
+
+/// The payload version this build reads
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Tool names git-ai records for checkpoints that aren't an agent's
+const RESERVED_TOOLS: &[&str] = &["human", "mock_ai"];
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct AgentCheckpointInput {
+    #[serde(default = "schema_version")]
+    pub version: u32,
+    /// Name of the agent, e.g. `aider`. Recorded as the tool of its prompts.
+    pub tool: String,
+    /// Id of the conversation, the same for every checkpoint of it
+    pub session_id: String,
+    #[serde(default)]
+    pub model: Option<String>,
+    /// The whole conversation so far, without tool results
+    #[serde(default)]
+    pub messages: Vec<Message>,
+    /// Files the agent edited since its last checkpoint, relative to the repository root or
+    /// absolute
+    pub edited_filepaths: Vec<String>,
+    /// Defaults to the current directory
+    #[serde(default)]
+    pub repo_working_dir: Option<String>,
+    #[serde(default)]
+    pub agent_version: Option<String>,
+}
+
+fn schema_version() -> u32 {
+    SCHEMA_VERSION
+}
+
+impl AgentCheckpointInput {
+    pub fn parse(json: &str) -> Result<Self, GitAiError> {
+        let input: AgentCheckpointInput = serde_json::from_str(json).map_err(|e| {
+            GitAiError::PresetError(format!("Invalid agent checkpoint JSON: {}", e))
+        })?;
+        input.validate()?;
+        Ok(input)
+    }
+
+    fn validate(&self) -> Result<(), GitAiError> {
+        let invalid = |message: String| {
+            Err(GitAiError::PresetError(format!(
+                "Invalid agent checkpoint JSON: {}",
+                message
+            )))
+        };
+
+        if self.version != SCHEMA_VERSION {
+            return invalid(format!(
+                "version {} isn't supported, expected {}",
+                self.version, SCHEMA_VERSION
+            ));
+        }
+        if self.tool.trim().is_empty() || self.tool.chars().any(char::is_whitespace) {
+            return invalid(format!(
+                "tool must be a non-empty name without spaces, got {:?}",
+                self.tool
+            ));
+        }
+        if RESERVED_TOOLS.contains(&self.tool.as_str()) {
+            return invalid(format!("tool {:?} is reserved", self.tool));
+        }
+        if self.session_id.trim().is_empty() {
+            return invalid("session_id is empty".to_string());
+        }
+        if let Some(model) = &self.model
+            && model.trim().is_empty()
+        {
+            return invalid("model is empty; leave it out when it isn't known".to_string());
+        }
+        if self.edited_filepaths.is_empty() {
+            return invalid("edited_filepaths is empty".to_string());
+        }
+        if let Some(i) = self
+            .edited_filepaths
+            .iter()
+            .position(|path| path.trim().is_empty() || path.contains('\0'))
+        {
+            return invalid(format!("edited_filepaths[{}] isn't a path", i));
+        }
+        for (i, message) in self.messages.iter().enumerate() {
+            if let Some(timestamp) = message_timestamp(message)
+                && chrono::DateTime::parse_from_rfc3339(timestamp).is_err()
+            {
+                return invalid(format!(
+                    "messages[{}] has an invalid timestamp: {}",
+                    i, timestamp
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// The checkpoint to record, in `current_dir` unless the payload names a repository
+    pub fn agent_run_result(&self, current_dir: &str) -> AgentRunResult {
+        AgentRunResult {
+            agent_id: AgentId {
+                tool: self.tool.clone(),
+                id: self.session_id.clone(),
+                model: self.model.clone().unwrap_or_else(|| "unknown".to_string()),
+            },
+            checkpoint_kind: CheckpointKind::AiAgent,
+            transcript: Some(AiTranscript {
+                messages: self.messages.clone(),
+            }),
+            repo_working_dir: Some(
+                self.repo_working_dir
+                    .clone()
+                    .unwrap_or_else(|| current_dir.to_string()),
+            ),
+            edited_filepaths: Some(self.edited_filepaths.clone()),
+            will_edit_filepaths: None,
+            commit_message_source: None,
+            agent_version: self.agent_version.clone(),
+            file_contents: None,
+        }
+    }
+}
+
+fn message_timestamp(message: &Message) -> Option<&str> {
+    match message {
+        Message::User { timestamp, .. }
+        | Message::Assistant { timestamp, .. }
+        | Message::ToolUse { timestamp, .. } => timestamp.as_deref(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(json: serde_json::Value) -> Result<AgentCheckpointInput, GitAiError> {
+        AgentCheckpointInput::parse(&json.to_string())
+    }
+
+    #[test]
+    fn test_parse_minimal_payload() {
+        let input = parse(serde_json::json!({
+            "tool": "aider",
+            "session_id": "s-1",
+            "edited_filepaths": ["src/lib.rs"]
+        }))
+        .unwrap();
+        assert_eq!(input.version, SCHEMA_VERSION);
+
+        let result = input.agent_run_result("/repo");
+        assert_eq!(result.agent_id.tool, "aider");
+        assert_eq!(result.agent_id.model, "unknown");
+        assert_eq!(result.repo_working_dir.as_deref(), Some("/repo"));
+        assert_eq!(result.checkpoint_kind, CheckpointKind::AiAgent);
+        assert!(result.transcript.unwrap().messages.is_empty());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_payloads() {
+        let cases = [
+            (
+                serde_json::json!({"tool": "aider", "session_id": "s", "edited_filepaths": []}),
+                "edited_filepaths is empty",
+            ),
+            (
+                serde_json::json!({"tool": "my agent", "session_id": "s", "edited_filepaths": ["a"]}),
+                "without spaces",
+            ),
+            (
+                serde_json::json!({"tool": "human", "session_id": "s", "edited_filepaths": ["a"]}),
+                "reserved",
+            ),
+            (
+                serde_json::json!({"tool": "aider", "session_id": " ", "edited_filepaths": ["a"]}),
+                "session_id is empty",
+            ),
+            (
+                serde_json::json!({"version": 2, "tool": "aider", "session_id": "s", "edited_filepaths": ["a"]}),
+                "version 2 isn't supported",
+            ),
+            (
+                serde_json::json!({"tool": "aider", "session_id": "s", "edited_filepaths": ["a"], "files": []}),
+                "unknown field `files`",
+            ),
+            (
+                serde_json::json!({
+                    "tool": "aider", "session_id": "s", "edited_filepaths": ["a"],
+                    "messages": [{"type": "user", "text": "hi", "timestamp": "yesterday"}]
+                }),
+                "messages[0] has an invalid timestamp",
+            ),
+        ];
+        for (json, expected) in cases {
+            let err = parse(json.clone()).unwrap_err().to_string();
+            assert!(err.contains(expected), "{}: {}", json, err);
+        }
+    }
+}
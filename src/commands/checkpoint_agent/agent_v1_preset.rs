@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 
 use crate::{
     authorship::{
+        authorship_log::CommitMessageSource,
         transcript::AiTranscript,
         working_log::{AgentId, CheckpointKind},
     },
@@ -16,6 +18,9 @@ enum AgentV1Input {
     Human {
         repo_working_dir: String,
         will_edit_filepaths: Option<Vec<String>>,
+        /// Unsaved contents of files, checkpointed instead of what's on disk
+        #[serde(default)]
+        file_contents: Option<HashMap<String, String>>,
     },
     AiAgent {
         repo_working_dir: String,
@@ -24,6 +29,16 @@ enum AgentV1Input {
         agent_name: String,
         model: String,
         conversation_id: String,
+        /// Set to "ai" when the agent is about to write the commit message, or "human"
+        /// when the user wrote it
+        #[serde(default)]
+        commit_message_source: Option<CommitMessageSource>,
+        /// Version of the agent, recorded with `record_environment`
+        #[serde(default)]
+        agent_version: Option<String>,
+        /// Unsaved contents of files, checkpointed instead of what's on disk
+        #[serde(default)]
+        file_contents: Option<HashMap<String, String>>,
     },
     // AiTab
 }
@@ -51,6 +66,7 @@ impl AgentCheckpointPreset for AgentV1Preset {
             AgentV1Input::Human {
                 repo_working_dir,
                 will_edit_filepaths,
+                file_contents,
             } => Ok(AgentRunResult {
                 agent_id: AgentId {
                     tool: "human".to_string(),
@@ -62,6 +78,9 @@ impl AgentCheckpointPreset for AgentV1Preset {
                 transcript: None,
                 repo_working_dir: Some(repo_working_dir),
                 edited_filepaths: None,
+                commit_message_source: None,
+                agent_version: None,
+                file_contents,
             }),
             AgentV1Input::AiAgent {
                 edited_filepaths,
@@ -70,6 +89,9 @@ impl AgentCheckpointPreset for AgentV1Preset {
                 model,
                 conversation_id,
                 repo_working_dir,
+                commit_message_source,
+                agent_version,
+                file_contents,
             } => Ok(AgentRunResult {
                 agent_id: AgentId {
                     tool: agent_name,
@@ -81,6 +103,9 @@ impl AgentCheckpointPreset for AgentV1Preset {
                 checkpoint_kind: CheckpointKind::AiAgent,
                 edited_filepaths: edited_filepaths,
                 will_edit_filepaths: None,
+                commit_message_source,
+                agent_version,
+                file_contents,
             }),
         }
     }
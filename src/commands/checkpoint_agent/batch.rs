@@ -0,0 +1,245 @@
+use serde::Deserialize;
+
+use crate::{
+    authorship::{
+        transcript::AiTranscript,
+        working_log::{AgentId, CheckpointKind},
+    },
+    commands::checkpoint_agent::agent_presets::AgentRunResult,
+    error::GitAiError,
+};
+
+/// Input of `git-ai checkpoint agent --batch-json`: every edit an agent made since its last
+/// checkpoint, recorded in one go instead of one checkpoint per tool call
+#[derive(Debug, Clone, Deserialize)]
+pub struct BatchInput {
+    pub repo_working_dir: String,
+    pub agent_name: String,
+    pub model: String,
+    pub conversation_id: String,
+    #[serde(default)]
+    pub transcript: Option<AiTranscript>,
+    #[serde(default)]
+    pub agent_version: Option<String>,
+    pub events: Vec<EditEvent>,
+}
+
+/// One edit of one file. Exactly one of `content` and `patch` is set.
+#[derive(Debug, Clone, Deserialize)]
+pub struct EditEvent {
+    /// Path relative to the repository root
+    pub file: String,
+    /// The whole file after the edit
+    #[serde(default)]
+    pub content: Option<String>,
+    /// A unified diff of the file, applied to its content before the edit
+    #[serde(default)]
+    pub patch: Option<String>,
+    /// RFC 3339 time of the edit
+    #[serde(default)]
+    pub timestamp: Option<String>,
+    /// Index of the transcript message that made the edit; the checkpoint gets the transcript
+    /// up to and including it
+    #[serde(default)]
+    pub message_index: Option<usize>,
+}
+
+impl BatchInput {
+    pub fn parse(json: &str) -> Result<Self, GitAiError> {
+        let input: BatchInput = serde_json::from_str(json).map_err(|e| {
+            GitAiError::PresetError(format!("Invalid batch checkpoint JSON: {}", e))
+        })?;
+        if input.events.is_empty() {
+            return Err(GitAiError::PresetError(
+                "Batch checkpoint has no events".to_string(),
+            ));
+        }
+        for (i, event) in input.events.iter().enumerate() {
+            if event.content.is_some() == event.patch.is_some() {
+                return Err(GitAiError::PresetError(format!(
+                    "Event {} ({}) needs exactly one of content and patch",
+                    i, event.file
+                )));
+            }
+            if let Some(timestamp) = &event.timestamp
+                && chrono::DateTime::parse_from_rfc3339(timestamp).is_err()
+            {
+                return Err(GitAiError::PresetError(format!(
+                    "Event {} ({}) has an invalid timestamp: {}",
+                    i, event.file, timestamp
+                )));
+            }
+        }
+        Ok(input)
+    }
+
+    pub fn agent_run_result(&self) -> AgentRunResult {
+        let mut edited_filepaths: Vec<String> = Vec::new();
+        for event in &self.events {
+            if !edited_filepaths.contains(&event.file) {
+                edited_filepaths.push(event.file.clone());
+            }
+        }
+        AgentRunResult {
+            agent_id: AgentId {
+                tool: self.agent_name.clone(),
+                id: self.conversation_id.clone(),
+                model: self.model.clone(),
+            },
+            checkpoint_kind: CheckpointKind::AiAgent,
+            transcript: self.transcript.clone(),
+            repo_working_dir: Some(self.repo_working_dir.clone()),
+            edited_filepaths: Some(edited_filepaths),
+            will_edit_filepaths: None,
+            commit_message_source: None,
+            agent_version: self.agent_version.clone(),
+            file_contents: None,
+        }
+    }
+}
+
+impl EditEvent {
+    /// The file's content after this edit, given its content before
+    pub fn content_after(&self, before: &str) -> Result<String, GitAiError> {
+        match (&self.content, &self.patch) {
+            (Some(content), _) => Ok(content.clone()),
+            (None, Some(patch)) => apply_patch(before, patch).map_err(|e| {
+                GitAiError::PresetError(format!("Patch for {} doesn't apply: {}", self.file, e))
+            }),
+            (None, None) => Ok(before.to_string()),
+        }
+    }
+
+    pub fn timestamp_ms(&self) -> Option<u64> {
+        let timestamp = chrono::DateTime::parse_from_rfc3339(self.timestamp.as_ref()?).ok()?;
+        u64::try_from(timestamp.timestamp_millis()).ok()
+    }
+
+    /// The transcript as it stood when this edit was made
+    pub fn transcript(&self, transcript: Option<&AiTranscript>) -> AiTranscript {
+        let mut transcript = transcript.cloned().unwrap_or_default();
+        if let Some(index) = self.message_index {
+            transcript.messages.truncate(index + 1);
+        }
+        transcript
+    }
+}
+
+/// Apply a unified diff of a single file to `original`. Context and removed lines have to
+/// match; hunk positions are taken as given.
+pub fn apply_patch(original: &str, patch: &str) -> Result<String, String> {
+    let original_lines: Vec<&str> = original.split_inclusive('\n').collect();
+    let mut output = String::with_capacity(original.len());
+    let mut next_line = 0;
+    let mut in_hunk = false;
+    let mut last_was_addition = false;
+
+    for patch_line in patch.lines() {
+        if let Some(header) = patch_line.strip_prefix("@@ ") {
+            let start = parse_hunk_start(header)
+                .ok_or_else(|| format!("malformed hunk header: {}", patch_line))?;
+            if start < next_line || start > original_lines.len() {
+                return Err(format!("hunk out of range: {}", patch_line));
+            }
+            output.extend(original_lines[next_line..start].iter().copied());
+            next_line = start;
+            in_hunk = true;
+            last_was_addition = false;
+            continue;
+        }
+        if !in_hunk {
+            // `diff --git`, `---` and `+++` headers
+            continue;
+        }
+
+        if patch_line.starts_with('\\') {
+            // "\ No newline at end of file" after an added line
+            if last_was_addition && output.ends_with('\n') {
+                output.pop();
+            }
+            continue;
+        }
+
+        let (marker, text) = match patch_line.chars().next() {
+            Some(marker @ ('+' | '-' | ' ')) => (marker, &patch_line[1..]),
+            // Some tools strip the space from blank context lines
+            None => (' ', ""),
+            Some(_) => return Err(format!("unexpected patch line: {}", patch_line)),
+        };
+        last_was_addition = marker == '+';
+        if marker == '+' {
+            output.push_str(text);
+            output.push('\n');
+            continue;
+        }
+
+        let Some(line) = original_lines.get(next_line) else {
+            return Err(format!(
+                "patch runs past the end of the file at: {}",
+                patch_line
+            ));
+        };
+        if line.trim_end_matches(['\n', '\r']) != text.trim_end_matches('\r') {
+            return Err(format!(
+                "line {} is {:?}, the patch expects {:?}",
+                next_line + 1,
+                line.trim_end_matches('\n'),
+                text
+            ));
+        }
+        if marker == ' ' {
+            output.push_str(line);
+        }
+        next_line += 1;
+    }
+
+    output.extend(original_lines[next_line..].iter().copied());
+    Ok(output)
+}
+
+/// Index of the first original line a hunk covers, from `-start,count +start,count @@`
+fn parse_hunk_start(header: &str) -> Option<usize> {
+    let old_range = header.split_whitespace().next()?.strip_prefix('-')?;
+    let (start, count) = match old_range.split_once(',') {
+        Some((start, count)) => (start.parse::<usize>().ok()?, count.parse::<usize>().ok()?),
+        None => (old_range.parse::<usize>().ok()?, 1),
+    };
+    // An empty old range names the line the hunk goes after
+    Some(if count == 0 {
+        start
+    } else {
+        start.saturating_sub(1)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_patch() {
+        let original = "one\ntwo\nthree\nfour\n";
+        let patch = "--- a/f.txt\n+++ b/f.txt\n@@ -1,3 +1,3 @@\n one\n-two\n+TWO\n three\n@@ -4,0 +5,1 @@\n+five\n";
+        assert_eq!(
+            apply_patch(original, patch).unwrap(),
+            "one\nTWO\nthree\nfour\nfive\n"
+        );
+
+        // A new file, without a trailing newline
+        let patch = "@@ -0,0 +1,2 @@\n+a\n+b\n\\ No newline at end of file\n";
+        assert_eq!(apply_patch("", patch).unwrap(), "a\nb");
+    }
+
+    #[test]
+    fn test_apply_patch_rejects_mismatched_context() {
+        let patch = "@@ -1,2 +1,2 @@\n one\n-deux\n+TWO\n";
+        assert!(apply_patch("one\ntwo\n", patch).is_err());
+    }
+
+    #[test]
+    fn test_parse_requires_content_or_patch() {
+        let input = r#"{"repo_working_dir": "/repo", "agent_name": "a", "model": "m",
+            "conversation_id": "c", "events": [{"file": "f.txt"}]}"#;
+        assert!(BatchInput::parse(input).is_err());
+    }
+}
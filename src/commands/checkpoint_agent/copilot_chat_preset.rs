@@ -0,0 +1,122 @@
+//! GitHub Copilot chat sessions read straight from VS Code's workspace storage, for
+//! checkpointing Copilot's edits without the VS Code extension's hook.
+//!
+//! VS Code keeps one storage directory per workspace, named by a hash, with the workspace's
+//! folder in `workspace.json` and each chat session in `chatSessions/<session id>.json`.
+
+use crate::authorship::working_log::{AgentId, CheckpointKind};
+use crate::commands::checkpoint_agent::agent_presets::{
+    AgentCheckpointFlags, AgentCheckpointPreset, AgentRunResult, GithubCopilotPreset,
+};
+use crate::commands::checkpoint_agent::copilot_telemetry::workspace_storage_for;
+use crate::error::GitAiError;
+use std::path::PathBuf;
+
+pub struct CopilotChatPreset {
+    /// Folder opened in VS Code
+    pub working_dir: PathBuf,
+    /// Session id or session file path. Defaults to the session of `working_dir` written to
+    /// last.
+    pub session: Option<String>,
+}
+
+impl AgentCheckpointPreset for CopilotChatPreset {
+    fn run(&self, _flags: AgentCheckpointFlags) -> Result<AgentRunResult, GitAiError> {
+        let session_path = self.session_path()?;
+        let session_content = std::fs::read_to_string(&session_path)?;
+        let (transcript, model, edited_filepaths) =
+            GithubCopilotPreset::transcript_and_model_from_copilot_session_json(&session_content)?;
+
+        // Sessions name themselves, and their file after themselves
+        let session_id = serde_json::from_str::<serde_json::Value>(&session_content)
+            .ok()
+            .and_then(|json| json.get("sessionId")?.as_str().map(str::to_string))
+            .or_else(|| {
+                session_path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let edited_filepaths = edited_filepaths.unwrap_or_default();
+        if edited_filepaths.is_empty() {
+            return Err(GitAiError::PresetError(format!(
+                "Copilot chat session {} hasn't edited any files",
+                session_id
+            )));
+        }
+
+        Ok(AgentRunResult {
+            agent_id: AgentId {
+                tool: "github-copilot".to_string(),
+                id: session_id,
+                model: model.unwrap_or_else(|| "unknown".to_string()),
+            },
+            checkpoint_kind: CheckpointKind::AiAgent,
+            transcript: Some(transcript),
+            repo_working_dir: None,
+            edited_filepaths: Some(edited_filepaths),
+            will_edit_filepaths: None,
+            commit_message_source: None,
+            agent_version: None,
+            file_contents: None,
+        })
+    }
+}
+
+impl CopilotChatPreset {
+    /// The file of the requested session, or of the latest one of the workspace
+    fn session_path(&self) -> Result<PathBuf, GitAiError> {
+        if let Some(session) = &self.session
+            && (session.ends_with(".json") || session.contains(std::path::MAIN_SEPARATOR))
+        {
+            return Ok(PathBuf::from(session));
+        }
+
+        let session_dirs: Vec<PathBuf> = workspace_storage_for(&self.working_dir)
+            .into_iter()
+            .map(|dir| dir.join("chatSessions"))
+            .collect();
+        if session_dirs.is_empty() {
+            return Err(GitAiError::PresetError(format!(
+                "No VS Code workspace is opened on {}",
+                self.working_dir.display()
+            )));
+        }
+
+        if let Some(session) = &self.session {
+            return session_dirs
+                .iter()
+                .map(|dir| dir.join(format!("{}.json", session)))
+                .find(|path| path.is_file())
+                .ok_or_else(|| {
+                    GitAiError::PresetError(format!(
+                        "No Copilot chat session {} for {}",
+                        session,
+                        self.working_dir.display()
+                    ))
+                });
+        }
+
+        session_dirs
+            .iter()
+            .filter_map(|dir| std::fs::read_dir(dir).ok())
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                let modified = path.metadata().ok()?.modified().ok()?;
+                Some((modified, path))
+            })
+            .max()
+            .map(|(_, path)| path)
+            .ok_or_else(|| {
+                GitAiError::PresetError(format!(
+                    "No Copilot chat sessions for {}",
+                    self.working_dir.display()
+                ))
+            })
+    }
+}
@@ -0,0 +1,395 @@
+//! Recover GitHub Copilot tab completions accepted while git-ai wasn't running.
+//!
+//! Copilot keeps a per-workspace log of accepted inline completions:
+//!
+//! ```json
+//! {
+//!   "acceptances": [
+//!     { "uri": "file:///work/app/src/lib.rs", "line": 11, "insertText": "a + b", "timestamp": 1760000000000 }
+//!   ]
+//! }
+//! ```
+//!
+//! `line` is 0-based and `timestamp` in milliseconds. Without a checkpoint after each
+//! acceptance those lines reach the working log as human edits; when a human checkpoint lands,
+//! the completions accepted since the previous checkpoint that can still be found in the file
+//! are handed back to Copilot in a `recovered` tab checkpoint.
+
+use crate::authorship::attribution_tracker::{Attribution, attributions_to_line_attributions};
+use crate::authorship::authorship_log_serialization::generate_short_hash;
+use crate::authorship::transcript::AiTranscript;
+use crate::authorship::working_log::{AgentId, Checkpoint, CheckpointKind, WorkingLogEntry};
+use crate::git::repo_storage::PersistedWorkingLog;
+use crate::utils::debug_log;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Path of the telemetry file, overriding the lookup in VS Code's workspace storage
+pub const TELEMETRY_PATH_ENV: &str = "GIT_AI_COPILOT_TELEMETRY";
+
+/// VS Code user data directory (the one holding `workspaceStorage`), overriding the default
+/// locations of VS Code and VS Code Insiders
+pub const VSCODE_USER_DIR_ENV: &str = "GIT_AI_VSCODE_USER_DIR";
+
+/// Session id of the prompt record recovered completions are attributed to
+const RECOVERED_SESSION_ID: &str = "recovered-tab-completions";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Acceptance {
+    pub uri: String,
+    pub line: u32,
+    pub insert_text: String,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct TelemetryFile {
+    #[serde(default)]
+    acceptances: Vec<Acceptance>,
+}
+
+/// Completions accepted in `repo_root` after `since_ms`, keyed by repo-relative path
+pub fn accepted_since(repo_root: &Path, since_ms: u64) -> HashMap<String, Vec<Acceptance>> {
+    let mut by_file: HashMap<String, Vec<Acceptance>> = HashMap::new();
+    let Some(path) = telemetry_path(repo_root) else {
+        return by_file;
+    };
+    let telemetry: TelemetryFile = match std::fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+    {
+        Ok(telemetry) => telemetry,
+        Err(e) => {
+            debug_log(&format!(
+                "Ignoring Copilot telemetry at {}: {}",
+                path.display(),
+                e
+            ));
+            return by_file;
+        }
+    };
+
+    for acceptance in telemetry.acceptances {
+        if acceptance.timestamp <= since_ms || acceptance.insert_text.trim().is_empty() {
+            continue;
+        }
+        let Some(file) = repo_relative_path(repo_root, &acceptance.uri) else {
+            continue;
+        };
+        by_file.entry(file).or_default().push(acceptance);
+    }
+    by_file
+}
+
+/// Build the checkpoint that gives Copilot back the completions found in the files of
+/// `human_checkpoint`. `None` when nothing could be recovered.
+pub fn recover_tab_completions(
+    working_log: &PersistedWorkingLog,
+    human_checkpoint: &Checkpoint,
+    acceptances: &HashMap<String, Vec<Acceptance>>,
+    ts: u128,
+) -> Option<Checkpoint> {
+    let author_id = generate_short_hash(RECOVERED_SESSION_ID, "github-copilot");
+    let mut entries = Vec::new();
+    let mut recovered_lines = 0u32;
+
+    for entry in &human_checkpoint.entries {
+        let Some(file_acceptances) = acceptances.get(&entry.file) else {
+            continue;
+        };
+        let Ok(content) = working_log.get_file_version(&entry.blob_sha) else {
+            continue;
+        };
+
+        let mut attributions = entry.attributions.clone();
+        let mut recovered_any = false;
+        for acceptance in file_acceptances {
+            let Some((start, end)) = locate(&content, acceptance) else {
+                continue;
+            };
+            // Only take over text the human checkpoint claimed, never another agent's
+            let overlapping = || {
+                attributions
+                    .iter()
+                    .filter(|attribution| attribution.overlaps(start, end))
+            };
+            if overlapping().next().is_none()
+                || overlapping()
+                    .any(|attribution| attribution.author_id != CheckpointKind::Human.to_str())
+            {
+                continue;
+            }
+            attributions = reassign(attributions, start, end, &author_id, ts);
+            recovered_lines += content[start..end].lines().count() as u32;
+            recovered_any = true;
+        }
+
+        if recovered_any {
+            attributions.sort_by_key(|attribution| (attribution.start, attribution.end));
+            let line_attributions = attributions_to_line_attributions(&attributions, &content);
+            entries.push(WorkingLogEntry::new(
+                entry.file.clone(),
+                entry.blob_sha.clone(),
+                attributions,
+                line_attributions,
+            ));
+        }
+    }
+
+    if entries.is_empty() {
+        return None;
+    }
+    debug_log(&format!(
+        "Recovered {} Copilot tab completion line(s) from telemetry",
+        recovered_lines
+    ));
+
+    let mut checkpoint = Checkpoint::new(
+        CheckpointKind::AiTab,
+        human_checkpoint.diff.clone(),
+        "github-copilot".to_string(),
+        entries,
+    );
+    checkpoint.recovered = true;
+    checkpoint.agent_id = Some(AgentId {
+        tool: "github-copilot".to_string(),
+        id: RECOVERED_SESSION_ID.to_string(),
+        model: "unknown".to_string(),
+    });
+    checkpoint.transcript = Some(AiTranscript::new());
+    // The human checkpoint counted these lines as its own
+    checkpoint.line_stats = human_checkpoint.line_stats.clone();
+    checkpoint.line_stats.human_additions = checkpoint
+        .line_stats
+        .human_additions
+        .saturating_sub(recovered_lines);
+    checkpoint.line_stats.ai_tab_additions += recovered_lines;
+    Some(checkpoint)
+}
+
+/// Byte range of the accepted text, at the line it was accepted on or, if edits above moved
+/// it, at its closest occurrence
+fn locate(content: &str, acceptance: &Acceptance) -> Option<(usize, usize)> {
+    let text = acceptance.insert_text.as_str();
+    let line_start = content
+        .split_inclusive('\n')
+        .take(acceptance.line as usize)
+        .map(str::len)
+        .sum::<usize>();
+
+    // Inline completions usually start mid-line
+    let line_end = content[line_start..]
+        .find('\n')
+        .map_or(content.len(), |i| line_start + i + 1);
+    if let Some(i) = content[line_start..line_end].find(text) {
+        let start = line_start + i;
+        return Some((start, start + text.len()));
+    }
+
+    content
+        .match_indices(text)
+        .map(|(start, _)| start)
+        .min_by_key(|start| start.abs_diff(line_start))
+        .map(|start| (start, start + text.len()))
+}
+
+/// Cut `[start, end)` out of the human attributions and give it to `author_id`
+fn reassign(
+    attributions: Vec<Attribution>,
+    start: usize,
+    end: usize,
+    author_id: &str,
+    ts: u128,
+) -> Vec<Attribution> {
+    let mut result = Vec::with_capacity(attributions.len() + 2);
+    for attribution in attributions {
+        if attribution.author_id != CheckpointKind::Human.to_str()
+            || !attribution.overlaps(start, end)
+        {
+            result.push(attribution);
+            continue;
+        }
+        if attribution.start < start {
+            result.push(Attribution::new(
+                attribution.start,
+                start,
+                attribution.author_id.clone(),
+                attribution.ts,
+            ));
+        }
+        if attribution.end > end {
+            result.push(Attribution::new(
+                end,
+                attribution.end,
+                attribution.author_id.clone(),
+                attribution.ts,
+            ));
+        }
+    }
+    result.push(Attribution::new(start, end, author_id.to_string(), ts));
+    result
+}
+
+fn repo_relative_path(repo_root: &Path, uri: &str) -> Option<String> {
+    let path = PathBuf::from(percent_decode(uri.strip_prefix("file://").unwrap_or(uri)));
+    let relative = path
+        .strip_prefix(repo_root)
+        .ok()
+        .or_else(|| {
+            let canonical_root = repo_root.canonicalize().ok()?;
+            path.strip_prefix(canonical_root).ok()
+        })?
+        .to_string_lossy()
+        .replace('\\', "/");
+    Some(relative)
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && let Some(byte) = value
+                .get(i + 1..i + 3)
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        {
+            decoded.push(byte);
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// The telemetry file for `repo_root`: the env override, or the one VS Code keeps in the
+/// storage directory of the workspace opened on the repo
+fn telemetry_path(repo_root: &Path) -> Option<PathBuf> {
+    if let Ok(path) = std::env::var(TELEMETRY_PATH_ENV) {
+        return (!path.is_empty()).then(|| PathBuf::from(path));
+    }
+
+    workspace_storage_for(repo_root)
+        .into_iter()
+        .map(|dir| dir.join("GitHub.copilot").join("acceptances.json"))
+        .find(|telemetry| telemetry.exists())
+}
+
+/// VS Code's storage directories of the workspaces opened on `folder`, one per VS Code
+/// flavour it was opened in
+pub fn workspace_storage_for(folder: &Path) -> Vec<PathBuf> {
+    let canonical_folder = folder.canonicalize().ok();
+    let mut matches = Vec::new();
+    for storage in workspace_storage_dirs() {
+        let Ok(workspaces) = std::fs::read_dir(&storage) else {
+            continue;
+        };
+        for workspace in workspaces.flatten() {
+            let dir = workspace.path();
+            let opened = std::fs::read_to_string(dir.join("workspace.json"))
+                .ok()
+                .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).ok())
+                .and_then(|json| json.get("folder")?.as_str().map(str::to_string));
+            if let Some(opened) = opened {
+                let opened = PathBuf::from(percent_decode(
+                    opened.strip_prefix("file://").unwrap_or(&opened),
+                ));
+                if opened == folder || Some(&opened) == canonical_folder.as_ref() {
+                    matches.push(dir);
+                }
+            }
+        }
+    }
+    matches
+}
+
+fn workspace_storage_dirs() -> Vec<PathBuf> {
+    let mut user_dirs = Vec::new();
+    if let Ok(user_dir) = std::env::var(VSCODE_USER_DIR_ENV)
+        && !user_dir.is_empty()
+    {
+        user_dirs.push(PathBuf::from(user_dir));
+    } else if cfg!(target_os = "macos") {
+        if let Ok(home) = std::env::var("HOME") {
+            let support = PathBuf::from(home).join("Library/Application Support");
+            user_dirs.push(support.join("Code/User"));
+            user_dirs.push(support.join("Code - Insiders/User"));
+        }
+    } else if cfg!(windows) {
+        if let Ok(appdata) = std::env::var("APPDATA") {
+            user_dirs.push(PathBuf::from(&appdata).join("Code").join("User"));
+            user_dirs.push(PathBuf::from(&appdata).join("Code - Insiders").join("User"));
+        }
+    } else if let Ok(home) = std::env::var("HOME") {
+        let config = std::env::var("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from(home).join(".config"));
+        user_dirs.push(config.join("Code/User"));
+        user_dirs.push(config.join("Code - Insiders/User"));
+    }
+    user_dirs
+        .into_iter()
+        .map(|dir| dir.join("workspaceStorage"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn acceptance(line: u32, text: &str) -> Acceptance {
+        Acceptance {
+            uri: "file:///repo/lib.rs".to_string(),
+            line,
+            insert_text: text.to_string(),
+            timestamp: 1,
+        }
+    }
+
+    #[test]
+    fn test_locate_accepted_text() {
+        let content = "fn a() {}\nfn add(x: i32) -> i32 { x + 1 }\nfn b() {}\n";
+        let (start, end) = locate(content, &acceptance(1, "x + 1")).unwrap();
+        assert_eq!(&content[start..end], "x + 1");
+        assert_eq!(start, content.find("x + 1").unwrap());
+
+        // Lines inserted above moved it down
+        let (start, _) = locate(content, &acceptance(0, "fn b() {}")).unwrap();
+        assert_eq!(start, content.find("fn b").unwrap());
+
+        assert_eq!(locate(content, &acceptance(1, "x + 2")), None);
+    }
+
+    #[test]
+    fn test_reassign_splits_human_attributions() {
+        let human = CheckpointKind::Human.to_str();
+        let attributions = vec![
+            Attribution::new(0, 10, "abc1234".to_string(), 1),
+            Attribution::new(10, 40, human.clone(), 1),
+        ];
+        let result = reassign(attributions, 20, 25, "copilot", 2);
+        assert_eq!(
+            result,
+            vec![
+                Attribution::new(0, 10, "abc1234".to_string(), 1),
+                Attribution::new(10, 20, human.clone(), 1),
+                Attribution::new(25, 40, human, 1),
+                Attribution::new(20, 25, "copilot".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_repo_relative_path() {
+        let root = Path::new("/work/my app");
+        assert_eq!(
+            repo_relative_path(root, "file:///work/my%20app/src/lib.rs"),
+            Some("src/lib.rs".to_string())
+        );
+        assert_eq!(repo_relative_path(root, "file:///elsewhere/lib.rs"), None);
+    }
+}
@@ -1,2 +1,6 @@
 pub mod agent_presets;
+pub mod agent_schema;
 pub mod agent_v1_preset;
+pub mod batch;
+pub mod copilot_chat_preset;
+pub mod copilot_telemetry;
@@ -0,0 +1,196 @@
+use crate::authorship::authorship_log_serialization::{
+    AUTHORSHIP_LOG_VERSION, AuthorshipLog, SchemaCompatibility, schema_compatibility,
+};
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::refs::{commits_with_ai_notes, show_authorship_note};
+use crate::git::repository::{CommitRange, Repository};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Notes written with one schema version
+#[derive(Debug, Clone, Serialize)]
+pub struct VersionCount {
+    pub notes: usize,
+    pub compatibility: SchemaCompatibility,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CompatReport {
+    /// The range scanned, or `None` for every note in the repository
+    pub range: Option<String>,
+    /// The schema version this git-ai writes
+    pub current_version: String,
+    pub notes: usize,
+    /// Notes by the schema version they declare, "unknown" when they declare none
+    pub versions: BTreeMap<String, VersionCount>,
+    /// Commits whose note this git-ai can't deserialize at all
+    pub unreadable: Vec<String>,
+}
+
+impl CompatReport {
+    /// Whether this git-ai reads every scanned note without losing anything structural
+    pub fn is_compatible(&self) -> bool {
+        self.unreadable.is_empty()
+            && self.versions.values().all(|count| {
+                matches!(
+                    count.compatibility,
+                    SchemaCompatibility::Supported | SchemaCompatibility::NewerMinor
+                )
+            })
+    }
+}
+
+pub fn handle_compat(args: &[String]) {
+    if args.first().map(String::as_str) != Some("check") {
+        eprintln!("Usage: git-ai compat check [--range <start>..<end>] [--json]");
+        std::process::exit(1);
+    }
+
+    let mut range = None;
+    let mut json_output = false;
+
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--range" if i + 1 < args.len() => {
+                range = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--json" => {
+                json_output = true;
+                i += 1;
+            }
+            _ => {
+                eprintln!("Unknown compat check argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let report = match build_report(&repo, range.as_deref()) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Compat check failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if json_output {
+        match serde_json::to_string(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize report: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        print_report(&report);
+    }
+
+    // Lets CI fail before a team member on an older git-ai trips over the notes
+    if !report.is_compatible() {
+        std::process::exit(1);
+    }
+}
+
+/// Tally the schema versions of the notes on the commits in `range`, or of every note
+pub fn build_report(repo: &Repository, range: Option<&str>) -> Result<CompatReport, GitAiError> {
+    let noted = commits_with_ai_notes(repo)?;
+    let mut commits: Vec<String> = match range {
+        Some(range) => {
+            let Some((start, end)) = range.split_once("..") else {
+                return Err(GitAiError::Generic(format!(
+                    "Expected a range like main..feature, got {}",
+                    range
+                )));
+            };
+            CommitRange::new_infer_refname(repo, start.to_string(), end.to_string(), None)?
+                .into_iter()
+                .map(|c| c.id())
+                .filter(|commit| noted.contains(commit))
+                .collect()
+        }
+        None => noted.into_iter().collect(),
+    };
+    commits.sort();
+
+    let mut report = CompatReport {
+        range: range.map(|range| range.to_string()),
+        current_version: AUTHORSHIP_LOG_VERSION.to_string(),
+        notes: 0,
+        versions: BTreeMap::new(),
+        unreadable: Vec::new(),
+    };
+
+    for commit in commits {
+        let Some(content) = show_authorship_note(repo, &commit) else {
+            continue;
+        };
+        report.notes += 1;
+
+        let version =
+            AuthorshipLog::schema_version_of(&content).unwrap_or_else(|| "unknown".to_string());
+        report
+            .versions
+            .entry(version.clone())
+            .or_insert_with(|| VersionCount {
+                notes: 0,
+                compatibility: schema_compatibility(&version),
+            })
+            .notes += 1;
+
+        if AuthorshipLog::deserialize_from_string(&content).is_err() {
+            report.unreadable.push(commit);
+        }
+    }
+
+    Ok(report)
+}
+
+fn print_report(report: &CompatReport) {
+    let scope = report.range.as_deref().unwrap_or("this repository");
+    if report.notes == 0 {
+        println!("No authorship notes in {}", scope);
+        return;
+    }
+
+    println!(
+        "Authorship notes in {} (this git-ai writes {})",
+        scope, report.current_version
+    );
+    println!();
+    for (version, count) in &report.versions {
+        let status = match count.compatibility {
+            SchemaCompatibility::Supported => "",
+            SchemaCompatibility::NewerMinor => "newer minor, extra fields ignored",
+            SchemaCompatibility::NewerMajor => "newer major, upgrade git-ai",
+            SchemaCompatibility::Unsupported => "unsupported",
+        };
+        println!("  {:<22}{:>8}  {}", version, count.notes, status);
+    }
+
+    if !report.unreadable.is_empty() {
+        println!();
+        println!(
+            "{} note{} can't be read by this git-ai:",
+            report.unreadable.len(),
+            if report.unreadable.len() == 1 {
+                ""
+            } else {
+                "s"
+            }
+        );
+        for commit in &report.unreadable {
+            println!("  {}", commit);
+        }
+    }
+}
@@ -0,0 +1,438 @@
+use crate::commands::blame::GitAiBlameOptions;
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::repository::{Repository, exec_git};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Refs git leaves behind for the commit being merged in, in the order they're looked for
+const THEIRS_REFS: [&str; 4] = [
+    "MERGE_HEAD",
+    "REBASE_HEAD",
+    "CHERRY_PICK_HEAD",
+    "REVERT_HEAD",
+];
+
+/// Who wrote each side of every conflict in the working copy
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictsReport {
+    /// HEAD, whose lines are between `<<<<<<<` and `=======`
+    pub ours: String,
+    /// The commit being merged in, whose lines are between `=======` and `>>>>>>>`
+    pub theirs: String,
+    /// The ref `theirs` was read from, e.g. `MERGE_HEAD`
+    pub theirs_ref: String,
+    pub files: Vec<FileConflicts>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileConflicts {
+    pub file: String,
+    pub hunks: Vec<ConflictHunk>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConflictHunk {
+    /// Lines of the `<<<<<<<` and `>>>>>>>` markers in the working copy
+    pub start_line: u32,
+    pub end_line: u32,
+    pub ours: SideAuthorship,
+    pub theirs: SideAuthorship,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SideAuthorship {
+    pub lines: u32,
+    pub ai_lines: u32,
+    /// Prompts behind the side's AI lines, most lines first
+    pub prompts: Vec<ConflictPrompt>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ConflictPrompt {
+    pub prompt_id: String,
+    pub tool: String,
+    pub model: String,
+    pub lines: u32,
+}
+
+/// One conflict as it appears in the working copy. Line numbers are 1-based.
+#[derive(Debug, Clone, PartialEq)]
+struct ConflictRegion {
+    start_line: u32,
+    end_line: u32,
+    ours: Vec<String>,
+    theirs: Vec<String>,
+}
+
+pub fn handle_conflicts(args: &[String]) {
+    let mut file = None;
+    let mut json_output = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--file" if i + 1 < args.len() => {
+                file = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--json" => {
+                json_output = true;
+                i += 1;
+            }
+            _ => {
+                eprintln!("Unknown conflicts argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let report = match build_report(&repo, file.as_deref()) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Conflicts failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if json_output {
+        match serde_json::to_string(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize report: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        print_report(&report);
+    }
+}
+
+/// Find the conflicts in every unmerged file (or just `file`, relative to the repository root)
+/// and blame each side's lines in the commit it came from: HEAD for ours, and the commit being
+/// merged, rebased, cherry-picked or reverted for theirs.
+pub fn build_report(repo: &Repository, file: Option<&str>) -> Result<ConflictsReport, GitAiError> {
+    let ours = repo.revparse_single("HEAD")?.id();
+    let (theirs_ref, theirs) = THEIRS_REFS
+        .iter()
+        .find_map(|name| {
+            repo.revparse_single(name)
+                .ok()
+                .map(|commit| (name.to_string(), commit.id()))
+        })
+        .ok_or_else(|| {
+            GitAiError::Generic(
+                "No merge, rebase, cherry-pick or revert is in progress".to_string(),
+            )
+        })?;
+
+    let mut files = unmerged_files(repo)?;
+    if let Some(file) = file {
+        let file = file.trim_start_matches("./");
+        files.retain(|unmerged| unmerged == file);
+        if files.is_empty() {
+            return Err(GitAiError::Generic(format!("{} has no conflicts", file)));
+        }
+    }
+
+    let workdir = repo.workdir()?;
+    let mut report = ConflictsReport {
+        ours: ours.clone(),
+        theirs: theirs.clone(),
+        theirs_ref,
+        files: Vec::new(),
+    };
+    for file in files {
+        let Ok(content) = std::fs::read_to_string(workdir.join(&file)) else {
+            // Deleted on one side, or binary: there are no markers to read
+            continue;
+        };
+        let regions = parse_conflicts(&content);
+        if regions.is_empty() {
+            continue;
+        }
+
+        let ours_side = SideVersion::load(repo, &file, &ours);
+        let theirs_side = SideVersion::load(repo, &file, &theirs);
+        let (mut ours_cursor, mut theirs_cursor) = (0, 0);
+        let hunks = regions
+            .iter()
+            .map(|region| ConflictHunk {
+                start_line: region.start_line,
+                end_line: region.end_line,
+                ours: ours_side.authorship(&region.ours, &mut ours_cursor),
+                theirs: theirs_side.authorship(&region.theirs, &mut theirs_cursor),
+            })
+            .collect();
+        report.files.push(FileConflicts { file, hunks });
+    }
+
+    Ok(report)
+}
+
+/// One side's version of a conflicted file, blamed at that side's commit
+struct SideVersion {
+    lines: Vec<String>,
+    /// Prompt id of every AI line, and the prompts by id
+    ai_lines: BTreeMap<u32, String>,
+    prompts: BTreeMap<String, (String, String)>,
+}
+
+impl SideVersion {
+    fn load(repo: &Repository, file: &str, commit: &str) -> Self {
+        let mut side = SideVersion {
+            lines: Vec::new(),
+            ai_lines: BTreeMap::new(),
+            prompts: BTreeMap::new(),
+        };
+        // Added on the other side only: nothing here to blame
+        let Ok(content) = repo.get_file_content(file, commit) else {
+            return side;
+        };
+        side.lines = String::from_utf8_lossy(&content)
+            .lines()
+            .map(str::to_string)
+            .collect();
+        if side.lines.is_empty() {
+            return side;
+        }
+
+        let options = GitAiBlameOptions {
+            newest_commit: Some(commit.to_string()),
+            use_prompt_hashes_as_names: true,
+            return_human_authors_as_human: true,
+            no_output: true,
+            ..Default::default()
+        };
+        if let Ok((line_authors, prompt_records)) = repo.blame(file, &options) {
+            for (line, author) in line_authors {
+                if let Some(prompt) = prompt_records.get(&author) {
+                    side.prompts.insert(
+                        author.clone(),
+                        (prompt.agent_id.tool.clone(), prompt.agent_id.model.clone()),
+                    );
+                    side.ai_lines.insert(line, author);
+                }
+            }
+        }
+        side
+    }
+
+    /// Authorship of a conflict side's `lines`, found in this version at or after `cursor`.
+    /// Conflicts appear in file order, so the cursor moves past each one found.
+    fn authorship(&self, lines: &[String], cursor: &mut usize) -> SideAuthorship {
+        let mut authorship = SideAuthorship {
+            lines: lines.len() as u32,
+            ..Default::default()
+        };
+        if lines.is_empty() {
+            return authorship;
+        }
+        let Some(offset) = self.lines[(*cursor).min(self.lines.len())..]
+            .windows(lines.len())
+            .position(|window| window == lines)
+        else {
+            // Rewritten by git (e.g. a conflict inside a rename); its authorship is unknown
+            return authorship;
+        };
+        let start = *cursor + offset;
+        *cursor = start + lines.len();
+
+        let mut prompt_lines: BTreeMap<&str, u32> = BTreeMap::new();
+        for line in start + 1..=start + lines.len() {
+            if let Some(prompt_id) = self.ai_lines.get(&(line as u32)) {
+                authorship.ai_lines += 1;
+                *prompt_lines.entry(prompt_id).or_default() += 1;
+            }
+        }
+        authorship.prompts = prompt_lines
+            .into_iter()
+            .map(|(prompt_id, lines)| {
+                let (tool, model) = self.prompts.get(prompt_id).cloned().unwrap_or_default();
+                ConflictPrompt {
+                    prompt_id: prompt_id.to_string(),
+                    tool,
+                    model,
+                    lines,
+                }
+            })
+            .collect();
+        authorship.prompts.sort_by(|a, b| {
+            b.lines
+                .cmp(&a.lines)
+                .then_with(|| a.prompt_id.cmp(&b.prompt_id))
+        });
+        authorship
+    }
+}
+
+/// Files with unresolved conflicts, relative to the repository root
+fn unmerged_files(repo: &Repository) -> Result<Vec<String>, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("diff".to_string());
+    args.push("--name-only".to_string());
+    args.push("--diff-filter=U".to_string());
+    args.push("-z".to_string());
+
+    let output = exec_git(&args)?;
+    let mut files: Vec<String> = String::from_utf8(output.stdout)?
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect();
+    files.dedup();
+    Ok(files)
+}
+
+/// Conflict regions in `content`, in merge and diff3 style. The base section of diff3 markers
+/// belongs to neither side and is left out.
+fn parse_conflicts(content: &str) -> Vec<ConflictRegion> {
+    enum Section {
+        Outside,
+        Ours,
+        Base,
+        Theirs,
+    }
+
+    let mut regions = Vec::new();
+    let mut section = Section::Outside;
+    let mut current = ConflictRegion {
+        start_line: 0,
+        end_line: 0,
+        ours: Vec::new(),
+        theirs: Vec::new(),
+    };
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index as u32 + 1;
+        section = match section {
+            Section::Outside if line.starts_with("<<<<<<<") => {
+                current = ConflictRegion {
+                    start_line: line_number,
+                    end_line: line_number,
+                    ours: Vec::new(),
+                    theirs: Vec::new(),
+                };
+                Section::Ours
+            }
+            Section::Outside => Section::Outside,
+            Section::Ours | Section::Base if line.starts_with("=======") => Section::Theirs,
+            Section::Ours if line.starts_with("|||||||") => Section::Base,
+            Section::Ours => {
+                current.ours.push(line.to_string());
+                Section::Ours
+            }
+            Section::Base => Section::Base,
+            Section::Theirs if line.starts_with(">>>>>>>") => {
+                current.end_line = line_number;
+                regions.push(current.clone());
+                Section::Outside
+            }
+            Section::Theirs => {
+                current.theirs.push(line.to_string());
+                Section::Theirs
+            }
+        };
+    }
+    regions
+}
+
+fn describe_side(side: &SideAuthorship) -> String {
+    if side.lines == 0 {
+        return "no lines".to_string();
+    }
+    let lines = format!(
+        "{} line{}",
+        side.lines,
+        if side.lines == 1 { "" } else { "s" }
+    );
+    if side.ai_lines == 0 {
+        return format!("{}, human", lines);
+    }
+    let prompts: Vec<String> = side
+        .prompts
+        .iter()
+        .map(|prompt| {
+            format!(
+                "{} {}",
+                prompt.tool,
+                &prompt.prompt_id[..7.min(prompt.prompt_id.len())]
+            )
+        })
+        .collect();
+    let share = if side.ai_lines == side.lines {
+        "all AI".to_string()
+    } else {
+        format!("{} AI", side.ai_lines)
+    };
+    format!("{}, {} ({})", lines, share, prompts.join(", "))
+}
+
+fn print_report(report: &ConflictsReport) {
+    if report.files.is_empty() {
+        println!("No conflicts");
+        return;
+    }
+
+    for file in &report.files {
+        for (index, hunk) in file.hunks.iter().enumerate() {
+            println!(
+                "{}: conflict {} (lines {}-{})",
+                file.file,
+                index + 1,
+                hunk.start_line,
+                hunk.end_line
+            );
+            println!(
+                "  ours   (HEAD {}): {}",
+                &report.ours[..7.min(report.ours.len())],
+                describe_side(&hunk.ours)
+            );
+            println!(
+                "  theirs ({} {}): {}",
+                report.theirs_ref,
+                &report.theirs[..7.min(report.theirs.len())],
+                describe_side(&hunk.theirs)
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_conflicts() {
+        let content = "a\n<<<<<<< HEAD\nours 1\nours 2\n||||||| base\nbase\n=======\ntheirs\n>>>>>>> feature\nb\n<<<<<<< HEAD\n=======\nadded\n>>>>>>> feature\n";
+        let regions = parse_conflicts(content);
+        assert_eq!(regions.len(), 2);
+        assert_eq!((regions[0].start_line, regions[0].end_line), (2, 9));
+        assert_eq!(regions[0].ours, vec!["ours 1", "ours 2"]);
+        assert_eq!(regions[0].theirs, vec!["theirs"]);
+        assert!(regions[1].ours.is_empty());
+        assert_eq!(regions[1].theirs, vec!["added"]);
+    }
+
+    #[test]
+    fn test_side_authorship_follows_file_order() {
+        let side = SideVersion {
+            lines: ["x", "dup", "y", "dup"].map(str::to_string).to_vec(),
+            ai_lines: BTreeMap::from([(4, "p1".to_string())]),
+            prompts: BTreeMap::from([("p1".to_string(), ("claude".to_string(), "m".to_string()))]),
+        };
+        let mut cursor = 0;
+        let first = side.authorship(&["dup".to_string()], &mut cursor);
+        assert_eq!(first.ai_lines, 0);
+        let second = side.authorship(&["dup".to_string()], &mut cursor);
+        assert_eq!(second.ai_lines, 1);
+        assert_eq!(second.prompts[0].tool, "claude");
+    }
+}
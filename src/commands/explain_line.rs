@@ -0,0 +1,368 @@
+use crate::authorship::authorship_log::{AttributionConfidence, PromptRecord};
+use crate::authorship::transcript::Message;
+use crate::commands::blame::GitAiBlameOptions;
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::refs::get_reference_as_authorship_log_v3;
+use crate::git::repository::Repository;
+use chrono::{DateTime, FixedOffset, TimeZone, Utc};
+
+/// Maximum number of characters shown per transcript message in the excerpt
+const EXCERPT_MESSAGE_CHARS: usize = 280;
+
+/// Everything we know about a single line, gathered from blame + the authorship note
+#[derive(Debug, Clone)]
+pub struct LineExplanation {
+    pub file_path: String,
+    pub line: u32,
+    pub content: String,
+    pub commit_sha: Option<String>,
+    pub author: String,
+    pub author_time: i64,
+    pub author_tz: String,
+    pub prompt_hash: Option<String>,
+    pub prompt: Option<PromptRecord>,
+    pub confidence: AttributionConfidence,
+    /// Earlier prompts whose lines this line's prompt rewrote, most recent first, with the
+    /// agent of each one the note still knows about
+    pub lineage: Vec<(String, Option<String>)>,
+}
+
+pub fn handle_explain_line(args: &[String]) {
+    if args.len() != 1 {
+        eprintln!("Error: explain-line requires a single <file>:<line> argument");
+        eprintln!("Usage: git-ai explain-line <file>:<line>");
+        std::process::exit(1);
+    }
+
+    let (file_path, line) = match parse_file_line(&args[0]) {
+        Some(result) => result,
+        None => {
+            eprintln!("Invalid argument '{}'. Expected <file>:<line>", args[0]);
+            std::process::exit(1);
+        }
+    };
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match explain_line(&repo, &file_path, line) {
+        Ok(explanation) => print_explanation(&explanation),
+        Err(e) => {
+            eprintln!("Explain line failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parse `<file>:<line>`. Splits on the last ':' so paths containing colons still work.
+pub fn parse_file_line(arg: &str) -> Option<(String, u32)> {
+    let (file, line) = arg.rsplit_once(':')?;
+    if file.is_empty() {
+        return None;
+    }
+    let line = line.parse::<u32>().ok()?;
+    if line == 0 {
+        return None;
+    }
+    Some((file.to_string(), line))
+}
+
+pub fn explain_line(
+    repo: &Repository,
+    file_path: &str,
+    line: u32,
+) -> Result<LineExplanation, GitAiError> {
+    let options = GitAiBlameOptions {
+        line_ranges: vec![(line, line)],
+        use_prompt_hashes_as_names: true,
+        no_output: true,
+        ..Default::default()
+    };
+
+    // Resolves the AI attribution (and validates the path / line number)
+    let (line_authors, mut prompt_records) = repo.blame(file_path, &options)?;
+
+    let hunks = repo.blame_hunks(file_path, line, line, &options)?;
+    let hunk = hunks.into_iter().next().ok_or_else(|| {
+        GitAiError::Generic(format!("No blame information for {}:{}", file_path, line))
+    })?;
+
+    let content = std::fs::read_to_string(repo.workdir()?.join(file_path))
+        .ok()
+        .and_then(|c| c.lines().nth((line - 1) as usize).map(|l| l.to_string()))
+        .unwrap_or_default();
+
+    // Git reports uncommitted lines with an all-zero sha
+    let commit_sha = if hunk.commit_sha.chars().all(|c| c == '0') {
+        None
+    } else {
+        Some(hunk.commit_sha.clone())
+    };
+
+    let (prompt_hash, prompt) = match line_authors.get(&line) {
+        Some(author) => match prompt_records.remove(author) {
+            Some(record) => (Some(author.clone()), Some(record)),
+            None => (None, None),
+        },
+        None => (None, None),
+    };
+
+    // Notes written in summary mode keep only part of the transcript; the full one may still
+    // be on this machine
+    let prompt = prompt.map(|mut record| {
+        if record.summary.is_some()
+            && let Some(full) = prompt_hash
+                .as_deref()
+                .and_then(|hash| repo.storage.read_local_transcript(hash))
+        {
+            record.messages = full;
+        }
+        record
+    });
+
+    let note = commit_sha
+        .as_deref()
+        .and_then(|sha| get_reference_as_authorship_log_v3(repo, sha).ok());
+    let confidence = match (&note, &prompt_hash) {
+        (Some(log), Some(hash)) => log.confidence_for(file_path, hash),
+        _ => AttributionConfidence::Exact,
+    };
+
+    let lineage = match (&note, &prompt_hash) {
+        (Some(log), Some(hash)) => log.lineage_for(file_path, hash).to_vec(),
+        (None, Some(hash)) => uncommitted_lineage(repo, file_path, hash),
+        _ => Vec::new(),
+    };
+    let lineage = lineage
+        .into_iter()
+        .map(|hash| {
+            let agent = note
+                .as_ref()
+                .and_then(|log| log.metadata.prompts.get(&hash))
+                .map(|prompt| prompt.agent_id.tool.clone());
+            (hash, agent)
+        })
+        .collect();
+
+    Ok(LineExplanation {
+        file_path: file_path.to_string(),
+        line,
+        content,
+        commit_sha,
+        author: hunk.original_author,
+        author_time: hunk.author_time,
+        author_tz: hunk.author_tz,
+        prompt_hash,
+        prompt,
+        confidence,
+        lineage,
+    })
+}
+
+/// Lineage of a prompt's lines that aren't committed yet, from the working log
+fn uncommitted_lineage(repo: &Repository, file_path: &str, hash: &str) -> Vec<String> {
+    let Ok(head) = repo.head().and_then(|head| head.target()) else {
+        return Vec::new();
+    };
+    let checkpoints = repo
+        .storage
+        .working_log_for_base_commit(&head)
+        .read_all_checkpoints()
+        .unwrap_or_default();
+    checkpoints
+        .iter()
+        .rev()
+        .flat_map(|checkpoint| &checkpoint.entries)
+        .find(|entry| entry.file == file_path)
+        .and_then(|entry| entry.lineage.get(hash).cloned())
+        .unwrap_or_default()
+}
+
+fn print_explanation(explanation: &LineExplanation) {
+    println!("{}:{}", explanation.file_path, explanation.line);
+    println!("  {}", explanation.content.trim());
+    println!();
+
+    match &explanation.prompt {
+        Some(prompt) => {
+            println!("author:  ai");
+            println!("agent:   {}", prompt.agent_id.tool);
+            println!("model:   {}", prompt.agent_id.model);
+            if let Some(human) = &prompt.human_author {
+                println!("human:   {}", human);
+            }
+            if let Some(hash) = &explanation.prompt_hash {
+                println!("prompt:  {}", hash);
+            }
+            if explanation.confidence != AttributionConfidence::Exact {
+                println!("confidence: {}", explanation.confidence.as_str());
+            }
+            if !explanation.lineage.is_empty() {
+                let rewrote: Vec<String> = explanation
+                    .lineage
+                    .iter()
+                    .map(|(hash, agent)| match agent {
+                        Some(agent) => format!("{} ({})", hash, agent),
+                        None => hash.clone(),
+                    })
+                    .collect();
+                println!("rewrote: {}", rewrote.join(" <- "));
+            }
+            if let Some(summary) = &prompt.summary {
+                println!(
+                    "session: {} messages ({} user, {} assistant, {} tool calls)",
+                    summary.message_count,
+                    summary.user_messages,
+                    summary.assistant_messages,
+                    summary.tool_uses
+                );
+                if !summary.edited_files.is_empty() {
+                    println!("files:   {}", summary.edited_files.join(", "));
+                }
+            }
+        }
+        None => {
+            println!("author:  human ({})", explanation.author);
+        }
+    }
+
+    match &explanation.commit_sha {
+        Some(sha) => println!("commit:  {}", sha),
+        None => println!("commit:  (not committed yet)"),
+    }
+    println!(
+        "date:    {}",
+        format_date(explanation.author_time, &explanation.author_tz)
+    );
+
+    if let Some(prompt) = &explanation.prompt {
+        let excerpt = transcript_excerpt(&prompt.messages, &explanation.content);
+        if !excerpt.is_empty() {
+            println!();
+            println!("transcript:");
+            for message in excerpt {
+                let (role, text) = match message {
+                    Message::User { text, .. } => ("user", text.clone()),
+                    Message::Assistant { text, .. } => ("assistant", text.clone()),
+                    Message::ToolUse { name, .. } => ("tool", name.clone()),
+                };
+                println!("  [{}] {}", role, truncate(&text, EXCERPT_MESSAGE_CHARS));
+            }
+        }
+    }
+}
+
+/// Pick the messages around the assistant message that most likely generated the line:
+/// the last assistant message mentioning the line's content, falling back to the last
+/// assistant message. Includes the preceding user message and the following message.
+pub fn transcript_excerpt<'a>(messages: &'a [Message], line_content: &str) -> Vec<&'a Message> {
+    let needle = line_content.trim();
+    let is_assistant = |m: &Message| matches!(m, Message::Assistant { .. });
+
+    let generating_idx = messages
+        .iter()
+        .rposition(|m| match m {
+            Message::Assistant { text, .. } => !needle.is_empty() && text.contains(needle),
+            Message::ToolUse { input, .. } => {
+                !needle.is_empty() && input.to_string().contains(needle)
+            }
+            _ => false,
+        })
+        .or_else(|| messages.iter().rposition(is_assistant));
+
+    let Some(idx) = generating_idx else {
+        return Vec::new();
+    };
+
+    let start = messages[..idx]
+        .iter()
+        .rposition(|m| matches!(m, Message::User { .. }))
+        .unwrap_or(idx);
+    let end = (idx + 1).min(messages.len() - 1);
+
+    messages[start..=end].iter().collect()
+}
+
+fn truncate(text: &str, max_chars: usize) -> String {
+    let single_line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if single_line.chars().count() <= max_chars {
+        single_line
+    } else {
+        let truncated: String = single_line.chars().take(max_chars).collect();
+        format!("{}...", truncated)
+    }
+}
+
+fn format_date(author_time: i64, author_tz: &str) -> String {
+    let offset = parse_tz_offset(author_tz).unwrap_or_else(|| FixedOffset::east_opt(0).unwrap());
+    match Utc.timestamp_opt(author_time, 0).single() {
+        Some(dt) => {
+            let dt: DateTime<FixedOffset> = dt.with_timezone(&offset);
+            dt.format("%Y-%m-%d %H:%M:%S %z").to_string()
+        }
+        None => author_time.to_string(),
+    }
+}
+
+fn parse_tz_offset(tz: &str) -> Option<FixedOffset> {
+    if tz.len() != 5 {
+        return None;
+    }
+    let sign = match &tz[0..1] {
+        "+" => 1,
+        "-" => -1,
+        _ => return None,
+    };
+    let hours: i32 = tz[1..3].parse().ok()?;
+    let minutes: i32 = tz[3..5].parse().ok()?;
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_file_line() {
+        assert_eq!(
+            parse_file_line("src/main.rs:42"),
+            Some(("src/main.rs".to_string(), 42))
+        );
+        assert_eq!(
+            parse_file_line("weird:name.rs:7"),
+            Some(("weird:name.rs".to_string(), 7))
+        );
+        assert_eq!(parse_file_line("src/main.rs"), None);
+        assert_eq!(parse_file_line("src/main.rs:0"), None);
+        assert_eq!(parse_file_line(":3"), None);
+    }
+
+    #[test]
+    fn test_transcript_excerpt_prefers_message_mentioning_line() {
+        let messages = vec![
+            Message::user("add a greeting".to_string(), None),
+            Message::assistant("fn greet() { println!(\"hi\") }".to_string(), None),
+            Message::user("now add a farewell".to_string(), None),
+            Message::assistant("fn bye() {}".to_string(), None),
+        ];
+
+        let excerpt = transcript_excerpt(&messages, "  fn greet() { println!(\"hi\") }");
+        assert_eq!(excerpt.len(), 3);
+        assert_eq!(excerpt[0], &messages[0]);
+        assert_eq!(excerpt[1], &messages[1]);
+
+        let fallback = transcript_excerpt(&messages, "unrelated line");
+        assert_eq!(fallback, vec![&messages[2], &messages[3]]);
+    }
+
+    #[test]
+    fn test_transcript_excerpt_empty() {
+        assert!(transcript_excerpt(&[], "x").is_empty());
+    }
+}
@@ -0,0 +1,469 @@
+use crate::authorship::authorship_log::LineRange;
+use crate::commands::log::{CommitLogStat, log_stats};
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::refs::{get_authorship, note_blobs};
+use crate::git::repository::{RangeWalk, Repository, exec_git, exec_git_stdin};
+use rusqlite::{Connection, Transaction, params};
+use std::collections::{HashMap, HashSet};
+use std::io::Write;
+use std::path::Path;
+
+/// Version of the database layout, kept in `PRAGMA user_version`. A database written with
+/// another version is refused rather than migrated; delete it to export again.
+pub const SQLITE_SCHEMA_VERSION: i64 = 1;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS commits (
+    sha TEXT PRIMARY KEY,
+    note_blob TEXT NOT NULL,
+    author TEXT NOT NULL,
+    authored_at TEXT NOT NULL,
+    subject TEXT NOT NULL,
+    lines_added INTEGER NOT NULL,
+    lines_deleted INTEGER NOT NULL,
+    ai_lines INTEGER NOT NULL
+);
+CREATE TABLE IF NOT EXISTS files (
+    commit_sha TEXT NOT NULL REFERENCES commits(sha) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    lines_added INTEGER NOT NULL,
+    lines_deleted INTEGER NOT NULL,
+    ai_lines INTEGER NOT NULL,
+    binary INTEGER NOT NULL,
+    PRIMARY KEY (commit_sha, path)
+);
+CREATE TABLE IF NOT EXISTS agents (
+    id INTEGER PRIMARY KEY,
+    tool TEXT NOT NULL,
+    model TEXT NOT NULL,
+    UNIQUE (tool, model)
+);
+CREATE TABLE IF NOT EXISTS prompts (
+    commit_sha TEXT NOT NULL REFERENCES commits(sha) ON DELETE CASCADE,
+    hash TEXT NOT NULL,
+    agent_id INTEGER NOT NULL REFERENCES agents(id),
+    session_id TEXT NOT NULL,
+    human_author TEXT,
+    messages INTEGER NOT NULL,
+    total_additions INTEGER NOT NULL,
+    total_deletions INTEGER NOT NULL,
+    accepted_lines INTEGER NOT NULL,
+    overridden_lines INTEGER NOT NULL,
+    PRIMARY KEY (commit_sha, hash)
+);
+CREATE TABLE IF NOT EXISTS line_attributions (
+    commit_sha TEXT NOT NULL REFERENCES commits(sha) ON DELETE CASCADE,
+    path TEXT NOT NULL,
+    prompt_hash TEXT NOT NULL,
+    start_line INTEGER NOT NULL,
+    end_line INTEGER NOT NULL
+);
+CREATE INDEX IF NOT EXISTS line_attributions_by_file ON line_attributions (commit_sha, path);
+CREATE INDEX IF NOT EXISTS prompts_by_agent ON prompts (agent_id);
+";
+
+/// What an export changed in the database
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ExportSummary {
+    /// Commits written for the first time or rewritten because their note changed
+    pub written: usize,
+    pub unchanged: usize,
+    /// Commits dropped because their note (or the commit itself) is gone
+    pub removed: usize,
+}
+
+pub fn handle_export(args: &[String]) {
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match args.first().map(String::as_str) {
+        Some("sqlite") if args.len() == 2 => {
+            let path = &args[1];
+            match export_sqlite(&repo, Path::new(path)) {
+                Ok(summary) => println!(
+                    "Exported {} commit(s) to {}, {} unchanged, {} removed",
+                    summary.written, path, summary.unchanged, summary.removed
+                ),
+                Err(e) => {
+                    eprintln!("Export failed: {}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some("csv") => handle_export_csv(&repo, &args[1..]),
+        _ => {
+            eprintln!("Usage: git-ai export sqlite <path>");
+            eprintln!(
+                "       git-ai export csv [<revision range>] [--first-parent] [--no-merges] [--output <path>]"
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+fn handle_export_csv(repo: &Repository, args: &[String]) {
+    let mut walk = RangeWalk::default();
+    let mut revision = None;
+    let mut output = None;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            arg if walk.parse_arg(arg) => {
+                i += 1;
+            }
+            "--output" | "-o" => {
+                let Some(path) = args.get(i + 1) else {
+                    eprintln!("--output requires a path");
+                    std::process::exit(1);
+                };
+                output = Some(path.clone());
+                i += 2;
+            }
+            arg if !arg.starts_with('-') && revision.is_none() => {
+                revision = Some(arg.to_string());
+                i += 1;
+            }
+            arg => {
+                eprintln!("Unknown export csv argument: {}", arg);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let result = match &output {
+        Some(path) => std::fs::File::create(path)
+            .map_err(GitAiError::from)
+            .and_then(|file| {
+                let mut writer = std::io::BufWriter::new(file);
+                export_csv(
+                    repo,
+                    revision.as_deref().unwrap_or("HEAD"),
+                    walk,
+                    &mut writer,
+                )?;
+                writer.flush()?;
+                Ok(())
+            }),
+        None => {
+            let mut stdout = std::io::stdout().lock();
+            export_csv(
+                repo,
+                revision.as_deref().unwrap_or("HEAD"),
+                walk,
+                &mut stdout,
+            )
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("Export failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Write every commit with an authorship note into the SQLite database at `path`, creating it
+/// if needed. Commits are keyed by their note blob, so running it again only rewrites commits
+/// whose note was added or changed since, and drops the ones whose note was removed.
+pub fn export_sqlite(repo: &Repository, path: &Path) -> Result<ExportSummary, GitAiError> {
+    let mut conn = Connection::open(path)
+        .map_err(|e| GitAiError::Generic(format!("Failed to open {:?}: {}", path, e)))?;
+    let version: i64 = conn
+        .query_row("PRAGMA user_version", [], |row| row.get(0))
+        .map_err(db_error)?;
+    let has_tables: bool = conn
+        .query_row(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table')",
+            [],
+            |row| row.get(0),
+        )
+        .map_err(db_error)?;
+    if version != SQLITE_SCHEMA_VERSION && (version != 0 || has_tables) {
+        return Err(GitAiError::Generic(format!(
+            "{:?} isn't a git-ai export of schema version {}; delete it to export again",
+            path, SQLITE_SCHEMA_VERSION
+        )));
+    }
+    conn.execute_batch(&format!(
+        "PRAGMA foreign_keys = ON; {} PRAGMA user_version = {};",
+        SCHEMA, SQLITE_SCHEMA_VERSION
+    ))
+    .map_err(db_error)?;
+
+    let mut notes = note_blobs(repo, &repo.notes_ref())?;
+    // Notes can outlive their commit (gc after a rewrite); there is nothing to join them with
+    let present = existing_commits(repo, notes.keys())?;
+    notes.retain(|commit, _| present.contains(commit));
+
+    let mut exported: HashMap<String, String> = HashMap::new();
+    {
+        let mut stmt = conn
+            .prepare("SELECT sha, note_blob FROM commits")
+            .map_err(db_error)?;
+        let rows = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .map_err(db_error)?;
+        for row in rows {
+            let (sha, blob) = row.map_err(db_error)?;
+            exported.insert(sha, blob);
+        }
+    }
+
+    let mut summary = ExportSummary::default();
+    let tx = conn.transaction().map_err(db_error)?;
+    for sha in exported.keys() {
+        if !notes.contains_key(sha) {
+            tx.execute("DELETE FROM commits WHERE sha = ?1", params![sha])
+                .map_err(db_error)?;
+            summary.removed += 1;
+        }
+    }
+
+    let mut changed: Vec<String> = notes
+        .iter()
+        .filter(|(sha, blob)| exported.get(*sha) != Some(*blob))
+        .map(|(sha, _)| sha.clone())
+        .collect();
+    changed.sort();
+    summary.unchanged = notes.len() - changed.len();
+
+    // Read in batches to keep command lines short
+    for batch in changed.chunks(500) {
+        let mut log_args = vec![
+            "--no-walk=unsorted".to_string(),
+            "--date=iso-strict".to_string(),
+        ];
+        log_args.extend(batch.iter().cloned());
+        for commit in log_stats(repo, &log_args)? {
+            if write_commit(repo, &tx, &commit, &notes[&commit.commit])? {
+                summary.written += 1;
+            }
+        }
+    }
+    tx.commit().map_err(db_error)?;
+    Ok(summary)
+}
+
+/// Columns of `export csv`, one row per attested line range
+pub const CSV_HEADER: &str = "commit,authored_at,author,path,start_line,end_line,lines,prompt_hash,ai,tool,model,session_id,human_author";
+
+/// Every line range attested in the notes of the commits `revision` selects (a commit, which
+/// means its whole history, or a range), oldest commit first. Merges are left out unless `walk`
+/// follows first parents only, like every other command that walks a range.
+pub fn export_csv(
+    repo: &Repository,
+    revision: &str,
+    walk: RangeWalk,
+    out: &mut impl Write,
+) -> Result<(), GitAiError> {
+    let walk = RangeWalk {
+        no_merges: !walk.counts_merge_notes(),
+        ..walk
+    };
+    let mut args = repo.global_args_for_exec();
+    args.push("log".to_string());
+    args.push("--reverse".to_string());
+    args.push("--format=%H%x00%aI%x00%an <%ae>".to_string());
+    args.extend(walk.rev_list_args());
+    args.push(revision.to_string());
+    args.push("--".to_string());
+    let output = exec_git(&args)?;
+
+    writeln!(out, "{}", CSV_HEADER)?;
+    for line in String::from_utf8(output.stdout)?.lines() {
+        let mut fields = line.splitn(3, '\0');
+        let (Some(sha), Some(date), Some(author)) = (fields.next(), fields.next(), fields.next())
+        else {
+            continue;
+        };
+        let Some(log) = get_authorship(repo, sha) else {
+            continue;
+        };
+        for file in &log.attestations {
+            for entry in &file.entries {
+                let prompt = log.metadata.prompts.get(&entry.hash);
+                for range in &entry.line_ranges {
+                    let (start, end) = match range {
+                        LineRange::Single(line) => (*line, *line),
+                        LineRange::Range(start, end) => (*start, *end),
+                    };
+                    let row = [
+                        sha.to_string(),
+                        date.to_string(),
+                        author.to_string(),
+                        file.file_path.clone(),
+                        start.to_string(),
+                        end.to_string(),
+                        (end - start + 1).to_string(),
+                        entry.hash.clone(),
+                        prompt.is_some().to_string(),
+                        prompt.map(|p| p.agent_id.tool.clone()).unwrap_or_default(),
+                        prompt.map(|p| p.agent_id.model.clone()).unwrap_or_default(),
+                        prompt.map(|p| p.agent_id.id.clone()).unwrap_or_default(),
+                        prompt
+                            .and_then(|p| p.human_author.clone())
+                            .unwrap_or_default(),
+                    ];
+                    let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+                    writeln!(out, "{}", row.join(","))?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Quote a field when it holds a comma, quote or line break (RFC 4180)
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Replace everything stored for one commit with what its note says now. False when the note
+/// can't be parsed, which leaves the commit out.
+fn write_commit(
+    repo: &Repository,
+    tx: &Transaction,
+    commit: &CommitLogStat,
+    note_blob: &str,
+) -> Result<bool, GitAiError> {
+    tx.execute("DELETE FROM commits WHERE sha = ?1", params![commit.commit])
+        .map_err(db_error)?;
+    let Some(log) = get_authorship(repo, &commit.commit) else {
+        return Ok(false);
+    };
+
+    let files = commit.files.iter();
+    tx.execute(
+        "INSERT INTO commits (sha, note_blob, author, authored_at, subject, lines_added, \
+         lines_deleted, ai_lines) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        params![
+            commit.commit,
+            note_blob,
+            commit.author,
+            commit.date,
+            commit.message.lines().next().unwrap_or(""),
+            files.clone().map(|file| file.added).sum::<u32>(),
+            files.clone().map(|file| file.deleted).sum::<u32>(),
+            files.map(|file| file.ai_lines).sum::<u32>(),
+        ],
+    )
+    .map_err(db_error)?;
+
+    for file in &commit.files {
+        tx.execute(
+            "INSERT OR REPLACE INTO files (commit_sha, path, lines_added, lines_deleted, \
+             ai_lines, binary) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                commit.commit,
+                file.path,
+                file.added,
+                file.deleted,
+                file.ai_lines,
+                file.binary
+            ],
+        )
+        .map_err(db_error)?;
+    }
+
+    for (hash, prompt) in &log.metadata.prompts {
+        tx.execute(
+            "INSERT OR IGNORE INTO agents (tool, model) VALUES (?1, ?2)",
+            params![prompt.agent_id.tool, prompt.agent_id.model],
+        )
+        .map_err(db_error)?;
+        let agent: i64 = tx
+            .query_row(
+                "SELECT id FROM agents WHERE tool = ?1 AND model = ?2",
+                params![prompt.agent_id.tool, prompt.agent_id.model],
+                |row| row.get(0),
+            )
+            .map_err(db_error)?;
+        tx.execute(
+            "INSERT INTO prompts (commit_sha, hash, agent_id, session_id, human_author, \
+             messages, total_additions, total_deletions, accepted_lines, overridden_lines) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                commit.commit,
+                hash,
+                agent,
+                prompt.agent_id.id,
+                prompt.human_author,
+                prompt.messages.len(),
+                prompt.total_additions,
+                prompt.total_deletions,
+                prompt.accepted_lines,
+                prompt.overriden_lines
+            ],
+        )
+        .map_err(db_error)?;
+    }
+
+    for file in &log.attestations {
+        for entry in &file.entries {
+            for range in &entry.line_ranges {
+                let (start, end) = match range {
+                    LineRange::Single(line) => (*line, *line),
+                    LineRange::Range(start, end) => (*start, *end),
+                };
+                tx.execute(
+                    "INSERT INTO line_attributions (commit_sha, path, prompt_hash, start_line, \
+                     end_line) VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![commit.commit, file.file_path, entry.hash, start, end],
+                )
+                .map_err(db_error)?;
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// Which of `commits` are in the object database, in one git call
+fn existing_commits<'a>(
+    repo: &Repository,
+    commits: impl Iterator<Item = &'a String>,
+) -> Result<HashSet<String>, GitAiError> {
+    let mut stdin = String::new();
+    for commit in commits {
+        stdin.push_str(commit);
+        stdin.push('\n');
+    }
+    if stdin.is_empty() {
+        return Ok(HashSet::new());
+    }
+
+    let mut args = repo.global_args_for_exec();
+    args.push("cat-file".to_string());
+    args.push("--batch-check=%(objectname) %(objecttype)".to_string());
+    let output = exec_git_stdin(&args, stdin.as_bytes())?;
+    // Missing objects are reported as "<sha> missing"
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| line.strip_suffix(" commit"))
+        .map(|sha| sha.to_string())
+        .collect())
+}
+
+fn db_error(e: rusqlite::Error) -> GitAiError {
+    GitAiError::Generic(format!("SQLite error: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field_quotes_only_when_needed() {
+        assert_eq!(csv_field("src/lib.rs"), "src/lib.rs");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+    }
+}
@@ -0,0 +1,60 @@
+use crate::git::detached::unix_now;
+use crate::git::find_repository;
+use crate::git::maintenance::gc;
+
+pub fn handle_gc(args: &[String]) {
+    let mut dry_run = false;
+    let mut grace = true;
+
+    for arg in args {
+        match arg.as_str() {
+            "--dry-run" => dry_run = true,
+            "--now" => grace = false,
+            _ => {
+                eprintln!("Unknown gc argument: {}", arg);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let report = match gc(&repo, unix_now(), grace, dry_run) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("gc failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!(
+        "{} {} working log(s) of missing commits, {} unreferenced blob(s) and {} rewrite log event(s)",
+        if dry_run { "Would remove" } else { "Removed" },
+        report.working_logs,
+        report.blobs,
+        report.rewrite_events
+    );
+    println!(
+        "{} {}",
+        if dry_run {
+            "Would reclaim"
+        } else {
+            "Reclaimed"
+        },
+        format_bytes(report.bytes)
+    );
+}
+
+fn format_bytes(bytes: u64) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KiB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MiB", bytes as f64 / 1_048_576.0),
+    }
+}
@@ -2,34 +2,53 @@ use crate::authorship::range_authorship;
 use crate::authorship::stats::stats_command;
 use crate::authorship::working_log::{AgentId, CheckpointKind};
 use crate::commands;
+use crate::commands::checkpoint::CheckpointOptions;
 use crate::commands::checkpoint_agent::agent_presets::{
-    AgentCheckpointFlags, AgentCheckpointPreset, AgentRunResult, ClaudePreset, CursorPreset,
-    GithubCopilotPreset,
+    AgentCheckpointFlags, AgentCheckpointPreset, AgentRunResult, ClaudeCodeSessionPreset,
+    ClaudePreset, CursorPreset, GithubCopilotPreset,
 };
 use crate::commands::checkpoint_agent::agent_v1_preset::AgentV1Preset;
+use crate::commands::checkpoint_agent::copilot_chat_preset::CopilotChatPreset;
+use crate::commands::checkpoint_agent::agent_schema::AgentCheckpointInput;
+use crate::commands::checkpoint_agent::batch::BatchInput;
 use crate::config;
-use crate::git::find_repository;
-use crate::git::find_repository_in_path;
-use crate::git::repository::CommitRange;
+use crate::git::detached::{DETACHED_NOTE_GRACE_SECS, prune_detached_notes, unix_now};
+use crate::git::{find_repository, find_repository_cached};
+use crate::git::repo_registry::try_register_repository;
+use crate::git::repository::{CommitRange, RangeWalk, Repository};
 use crate::utils::{Timer, debug_log};
 use std::env;
 use std::io::IsTerminal;
 use std::io::Read;
+use std::path::PathBuf;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 pub fn handle_git_ai(args: &[String]) {
+    let (cli_overrides, args) = config::split_cli_overrides(args);
+    config::set_cli_overrides(cli_overrides);
+
     if args.is_empty() {
         print_help();
         return;
     }
 
     let current_dir = env::current_dir().unwrap().to_string_lossy().to_string();
-    let repository_option = find_repository_in_path(&current_dir).ok();
+    let repository_option = find_repository_cached(&vec!["-C".to_string(), current_dir]).ok();
 
     let config = config::Config::get();
 
     let allowed_repository = config.is_allowed_repository(&repository_option);
 
+    // Commits whose attribution was deferred get their notes before anything reads them. The
+    // prompt segment runs on every shell prompt and can't wait for that.
+    if allowed_repository
+        && args[0] != "process-queue"
+        && args[0] != "prompt-segment"
+        && let Some(repository) = &repository_option
+    {
+        commands::process_queue::finish_pending_work(repository);
+    }
+
     let timer = Timer::default();
 
     match args[0].as_str() {
@@ -46,6 +65,12 @@ pub fn handle_git_ai(args: &[String]) {
         "stats" => {
             handle_stats(&args[1..]);
         }
+        "backfill" => {
+            commands::backfill::handle_backfill(&args[1..]);
+        }
+        "backfill-squash" => {
+            commands::backfill_squash::handle_backfill_squash(&args[1..]);
+        }
         "checkpoint" => {
             if !allowed_repository {
                 eprintln!(
@@ -57,14 +82,83 @@ pub fn handle_git_ai(args: &[String]) {
             handle_checkpoint(&args[1..]);
             end();
         }
+        "watch" => {
+            if !allowed_repository {
+                eprintln!(
+                    "Skipping watch because repository is excluded or not in allow_repositories list"
+                );
+                std::process::exit(1);
+            }
+            commands::watch::handle_watch(&args[1..]);
+        }
         "blame" => {
             handle_ai_blame(&args[1..]);
         }
+        "explain-line" => {
+            commands::explain_line::handle_explain_line(&args[1..]);
+        }
+        "show-prompt" => {
+            commands::show_prompt::handle_show_prompt(&args[1..]);
+        }
+        "conflicts" => {
+            commands::conflicts::handle_conflicts(&args[1..]);
+        }
+        "me" => {
+            commands::me::handle_me(&args[1..]);
+        }
+        "ownership" => {
+            commands::ownership::handle_ownership(&args[1..]);
+        }
+        "release-note" => {
+            commands::release_note::handle_release_note(&args[1..]);
+        }
+        "retention" => {
+            commands::retention::handle_retention(&args[1..]);
+        }
+        "prompt-segment" => {
+            commands::prompt_segment::handle_prompt_segment(&args[1..]);
+        }
+        "sessions" => {
+            commands::sessions::handle_sessions(&args[1..]);
+        }
+        "log" => {
+            commands::log::handle_log(&args[1..]);
+        }
+        "ui" => {
+            commands::tui::handle_ui(&args[1..]);
+        }
+        "metrics" => {
+            commands::metrics::handle_metrics(&args[1..]);
+        }
+        "export" => {
+            commands::export::handle_export(&args[1..]);
+        }
+        "range-diff" => {
+            commands::range_diff::handle_range_diff(&args[1..]);
+        }
+        "last-outcome" => {
+            commands::last_outcome::handle_last_outcome(&args[1..]);
+        }
+        "config" => {
+            commands::show_config::handle_config(&args[1..]);
+        }
+        "introspect" | "completions-data" => {
+            commands::introspect::handle_introspect(&args[1..]);
+        }
         "git-path" => {
             let config = config::Config::get();
             println!("{}", config.git_cmd());
             std::process::exit(0);
         }
+        "init" => {
+            commands::init::handle_init(&args[1..]);
+        }
+        "install" => {
+            commands::install::handle_install(&args[1..]);
+        }
+        "doctor" => {
+            commands::install::handle_doctor(&args[1..]);
+        }
         "install-hooks" => {
             if let Err(e) = commands::install_hooks::run(&args[1..]) {
                 eprintln!("Install hooks failed: {}", e);
@@ -77,6 +171,33 @@ pub fn handle_git_ai(args: &[String]) {
         "ci" => {
             commands::ci_handlers::handle_ci(&args[1..]);
         }
+        "jj" => {
+            handle_jj(&args[1..]);
+        }
+        "prune-detached" => {
+            handle_prune_detached(&args[1..]);
+        }
+        "migrate" => {
+            commands::migrate::handle_migrate(&args[1..]);
+        }
+        "check" => {
+            commands::check::handle_check(&args[1..]);
+        }
+        "redact" => {
+            commands::redact::handle_redact(&args[1..]);
+        }
+        "compat" => {
+            commands::compat::handle_compat(&args[1..]);
+        }
+        "gc" => {
+            commands::gc::handle_gc(&args[1..]);
+        }
+        "maintenance" => {
+            commands::maintenance::handle_maintenance(&args[1..]);
+        }
+        "process-queue" => {
+            commands::process_queue::handle_process_queue(&args[1..]);
+        }
         _ => {
             println!("Unknown git-ai command: {}", args[0]);
             std::process::exit(1);
@@ -87,27 +208,156 @@ pub fn handle_git_ai(args: &[String]) {
 fn print_help() {
     eprintln!("git-ai - git proxy with AI authorship tracking");
     eprintln!("");
-    eprintln!("Usage: git-ai <command> [args...]");
+    eprintln!("Usage: git-ai [-c <key>=<value>...] <command> [args...]");
     eprintln!("");
     eprintln!("Commands:");
     eprintln!("  checkpoint         Checkpoint working changes and attribute author");
-    eprintln!("    Presets: claude, cursor, github-copilot, mock_ai");
+    eprintln!("    Presets: claude, claude-code, copilot-chat, cursor, github-copilot, mock_ai, scaffold");
+    eprintln!("    --agent <preset>            Name the preset as a flag instead of the first argument");
+    eprintln!("    claude-code [--session <id|path>]  Read a Claude Code session's transcript, no hooks needed");
+    eprintln!("    copilot-chat [--session <id|path>]  Read a VS Code Copilot chat session, no extension needed");
     eprintln!(
         "    --hook-input <json|stdin>   JSON payload required by presets, or 'stdin' to read from stdin"
     );
     eprintln!("    --show-working-log          Display current working log");
     eprintln!("    --reset                     Reset working log");
+    eprintln!("    --base-commit <sha>         Write to this commit's working log instead of HEAD's");
+    eprintln!("    --include-large             Check files over large_file_threshold_kb too");
+    eprintln!("    --no-cache                  Reread every changed file instead of trusting mtimes");
     eprintln!("    mock_ai [pathspecs...]      Test preset accepting optional file pathspecs");
-    eprintln!("  blame <file>       Git blame with AI authorship overlay");
+    eprintln!("    scaffold --tool <name>      Attribute every untracked file to a scaffolding tool");
+    eprintln!("    agent --batch-json <json|stdin>  Record many agent edits, in order, in one call");
+    eprintln!("    agent --stdin-json          Record an agent's edit described by JSON on stdin");
+    eprintln!("  watch              Checkpoint changes as files are saved, until interrupted");
+    eprintln!("    --debounce <ms>        Wait for changes to settle this long (default 300)");
+    eprintln!("    session start --tool <t> --id <id>  Changes are this agent's until the session ends");
+    eprintln!("      --model <m>            Model the agent runs");
+    eprintln!("      --pid <pid>            End the session when this process exits");
+    eprintln!("    session end --tool <t> --id <id>    Changes are human again");
+    eprintln!("  blame [rev] <file> Git blame with AI authorship overlay");
+    eprintln!("    -L <start>,<end>       Only these lines (git blame forms, repeatable)");
+    eprintln!("    --char-level           Mark the characters AI wrote in uncommitted lines");
+    eprintln!("    --json                 Output one JSON object per line");
+    eprintln!(
+        "  explain-line <file>:<line>  Explain who wrote a line and show the prompt behind it"
+    );
+    eprintln!("  show-prompt <commit> [<file> <line>]");
+    eprintln!("                     Show the transcript of the prompts in a commit, or of the one behind a line");
+    eprintln!("    --json                 Output in JSON format");
+    eprintln!("  conflicts          Which side of each conflict is AI-authored, and by which prompt");
+    eprintln!("    --file <path>          Only this file (relative to the repository root)");
+    eprintln!("    --json                 Output in JSON format");
     eprintln!("  stats [commit]     Show AI authorship statistics for a commit");
     eprintln!("    --json                 Output in JSON format");
+    eprintln!("    --latency              How long AI hunks waited to be committed (commit or range)");
+    eprintln!("    --oneline              One stable line, e.g. 'AI 62% (145/234 lines), 2 agents, waited 3m12s'");
+    eprintln!("    --pending              Stats of the uncommitted changes instead of a commit");
+    eprintln!("    --range <start>..<end> Stats of a range, like a positional range");
+    eprintln!("    --per-commit           One row per commit of the range (a JSON array with --json)");
+    eprintln!("    --first-parent         Walk a range's mainline only; merges count for their branch");
+    eprintln!("    --no-merges            Leave merges out of a range");
+    eprintln!("  me                 Your AI authorship stats across all repos git-ai has touched");
+    eprintln!("    --json                 Output in JSON format");
+    eprintln!("  ownership [path]   AI vs human share of a file's or directory's current lines");
+    eprintln!("    --directories          Totals per directory_policies subtree");
+    eprintln!("    --json                 Output in JSON format");
+    eprintln!("  release-note <tag> AI share of a release, stored as a note on the tag");
+    eprintln!("    --previous <tag>       Measure from this tag (default: the closest earlier tag)");
+    eprintln!("    --no-store             Print the rollup without storing it");
+    eprintln!("    --badge                Print only the badge line");
+    eprintln!("    --json                 Output in JSON format");
+    eprintln!("  retention          How many AI lines from each commit survive later commits");
+    eprintln!("    --range <a>..<b>       Required: commits to measure (survival counted at <b>)");
+    eprintln!("    --horizon <n>          Count survival n commits later instead of at <b>");
+    eprintln!("    --first-parent         Walk the mainline only; merges count for their branch");
+    eprintln!("    --no-merges            Leave merges out of the range");
+    eprintln!("    --json                 Output in JSON format");
+    eprintln!("  sessions           Agent sessions across commits, with lines produced and retained");
+    eprintln!("    --range <a>..<b>       Required: commits to group (retention counted at <b>)");
+    eprintln!("    --first-parent         Walk the mainline only; merges count for their branch");
+    eprintln!("    --no-merges            Leave merges out of the range");
+    eprintln!("    --json                 Output in JSON format");
+    eprintln!("  prompt-segment     Pending AI lines and HEAD's AI share for shell prompts, cached");
+    eprintln!("    --short                Print '<pending> <last %|-> <stale 0|1>' for prompt themes");
+    eprintln!("    --json                 Output in JSON format");
+    eprintln!("    --budget-ms <n>        Show the cached value if computing takes longer (default 50)");
+    eprintln!("  log --stat [args] git log --stat with each file's AI share, e.g. [ai 70%]");
+    eprintln!("    --json                 Output in JSON format");
+    eprintln!("  ui [args]          Browse commits, files and prompts in the terminal");
+    eprintln!("  metrics export     Aggregate AI usage metrics as JSON for org-level rollups");
+    eprintln!("    --since <YYYY-MM-DD>   Only count commits from this day (UTC) on");
+    eprintln!("    --anonymize            Leave out the repository path and per-author breakdown");
+    eprintln!("    --first-parent         Walk each branch's mainline; merges count for their branch");
+    eprintln!("    --sample <pct>%        Estimate from a sample of commits spread over time");
+    eprintln!("    --max-commits <n>      Estimate from at most n sampled commits");
+    eprintln!("    --dp-epsilon <eps>     Add differential privacy noise to the counts");
+    eprintln!("    --dp-salt <salt>       Draw different (still reproducible) noise");
+    eprintln!("  export sqlite <path>  Write authorship notes to a SQLite database, incrementally");
+    eprintln!("  export csv [range] One CSV row per attested line range, for data pipelines");
+    eprintln!("    --output <path>        Write to a file instead of stdout");
+    eprintln!("    --first-parent         Walk the mainline only; merges count for their branch");
+    eprintln!("  range-diff <old> <new>  Compare attribution of rewritten commits, like git range-diff");
+    eprintln!("    --json                 Output in JSON format");
     eprintln!(
         "  stats-delta        Generate authorship logs for children of commits with working logs"
     );
     eprintln!("    --json                 Output created notes as JSON");
+    eprintln!("  backfill [refs...] Write authorship notes for commits that don't have one");
+    eprintln!("    --chunk-size <n>       Commits per chunk between progress saves (default 500)");
+    eprintln!("    --resume               Continue from .git/ai/backfill_state");
+    eprintln!("  backfill-squash <merged-commit> --from <branch>");
+    eprintln!("                     Write the note of a branch squash-merged on the server");
+    eprintln!("    --force                Replace the commit's existing note");
+    eprintln!("  init               Set up git-ai in a repository: .gitai.toml, hooks, a checkpoint");
+    eprintln!("    --notes-ref <name>     Notes ref to store authorship in (default ai)");
+    eprintln!("    --exclude <pattern>    Leave matching files out of attribution (repeatable)");
+    eprintln!("    --hooks                Add git-ai maintenance to the pre-auto-gc hook");
+    eprintln!("    --no-checkpoint        Skip the smoke checkpoint");
+    eprintln!("    --force                Replace an existing .gitai.toml");
+    eprintln!("  install            Route git through git-ai and fetch notes from this repo's remotes");
+    eprintln!("    --alias                Alias git in the shell rc instead of putting the shim on PATH");
+    eprintln!("    --dry-run              Show what would change without changing anything");
+    eprintln!("    --check                Only run the checks, like doctor");
+    eprintln!("  doctor             Check that git, the shim, PATH and notes refspecs are set up");
     eprintln!("  install-hooks      Install git hooks for AI authorship tracking");
+    eprintln!("  last-outcome       Print what git-ai did for the last git command it proxied");
+    eprintln!("    --check                Exit with status 2 if any hook failed");
+    eprintln!("  config [key]       Print the effective repo settings");
+    eprintln!("    --show-origin          Show where each value came from");
+    eprintln!("    <key> <value>          Set a value in .gitai.toml, or at --global / --system level");
+    eprintln!("    --unset <key>          Remove a value from the --repo, --global or --system file");
+    eprintln!("  introspect         Describe commands, settings and storage as JSON for GUI clients");
     eprintln!("  ci                 Continuous integration utilities");
     eprintln!("    github                 GitHub CI helpers");
+    eprintln!("  jj                 Jujutsu (jj) colocated repo utilities");
+    eprintln!("    sync                   Remap authorship for commits jj rewrote");
+    eprintln!("  prune-detached     Drop notes of detached HEAD commits that never reached a ref");
+    eprintln!("    --now                  Skip the 30 day grace period");
+    eprintln!("  migrate            Move notes to another ref or storage to another layout");
+    eprintln!("    --notes-ref <name>     Rename the notes ref and record it in .gitai.toml");
+    eprintln!("    --storage <layout>     Move .git/ai: default (.git/ai) or info (.git/info/ai)");
+    eprintln!("    --dry-run              Show what would move without changing anything");
+    eprintln!("  check [<range>]    Hold commits to the push_policies (default: commits no remote has)");
+    eprintln!("    --json                 Output in JSON format");
+    eprintln!("  redact <range>     Strip transcripts from existing notes, keeping attribution");
+    eprintln!("    --all                  Every note instead of a commit or <a>..<b> range");
+    eprintln!("    --store <level>        messages_only, hashes_only or none (default: none)");
+    eprintln!("  compat check       Report note schema versions and whether this git-ai reads them");
+    eprintln!("    --range <a>..<b>       Only check notes on these commits (default: all notes)");
+    eprintln!("    --json                 Output in JSON format");
+    eprintln!("  gc                 Reclaim space in .git/ai: orphaned working logs, blobs, rewrite log");
+    eprintln!("    --dry-run              Report what would be removed and the space reclaimed");
+    eprintln!("    --now                  Also remove blobs written in the last hour");
+    eprintln!("  maintenance        Housekeeping of .git/ai, run from git's auto maintenance");
+    eprintln!("    register               Add git-ai maintenance to the pre-auto-gc hook");
+    eprintln!("    unregister             Remove it from the hook again");
+    eprintln!("    run                    Run the tasks now");
+    eprintln!("      --task <name>          gc, compaction or index-refresh (default: all)");
+    eprintln!("  process-queue      Attribute commits whose post-commit work was deferred");
+    eprintln!("    --status               Show hook timings and how many commits are waiting");
+    eprintln!("    --retry                Requeue tasks that failed too often first");
+    eprintln!("    --watch                Keep attributing queued commits in every registered repo");
+    eprintln!("    --interval <secs>      How often --watch checks the queues (default 2)");
     eprintln!("  squash-authorship  Generate authorship from squashed commits");
     eprintln!("    <branch> <new_sha> <old_sha>  Required: branch, new commit SHA, old commit SHA");
     eprintln!("    --dry-run             Show what would be done without making changes");
@@ -119,6 +369,15 @@ fn print_help() {
 }
 
 fn handle_checkpoint(args: &[String]) {
+    if args.first().map(String::as_str) == Some("agent") {
+        if args[1..].iter().any(|arg| arg == "--stdin-json") {
+            handle_checkpoint_stdin_json(&args[1..]);
+        } else {
+            handle_checkpoint_batch(&args[1..]);
+        }
+        return;
+    }
+
     let mut repository_working_dir = std::env::current_dir()
         .unwrap()
         .to_string_lossy()
@@ -127,7 +386,13 @@ fn handle_checkpoint(args: &[String]) {
     // Parse checkpoint-specific arguments
     let mut show_working_log = false;
     let mut reset = false;
+    let mut no_cache = false;
+    let mut include_large = false;
     let mut hook_input = None;
+    let mut base_commit: Option<String> = None;
+    let mut scaffold_tool: Option<String> = None;
+    let mut agent_preset: Option<String> = None;
+    let mut session: Option<String> = None;
 
     let mut i = 0;
     while i < args.len() {
@@ -140,6 +405,14 @@ fn handle_checkpoint(args: &[String]) {
                 reset = true;
                 i += 1;
             }
+            "--include-large" => {
+                include_large = true;
+                i += 1;
+            }
+            "--no-cache" => {
+                no_cache = true;
+                i += 1;
+            }
             "--hook-input" => {
                 if i + 1 < args.len() {
                     hook_input = Some(args[i + 1].clone());
@@ -166,6 +439,42 @@ fn handle_checkpoint(args: &[String]) {
                     std::process::exit(1);
                 }
             }
+            "--tool" => {
+                if i + 1 < args.len() && !args[i + 1].trim().is_empty() {
+                    scaffold_tool = Some(args[i + 1].trim().to_string());
+                    i += 2;
+                } else {
+                    eprintln!("Error: --tool requires a name");
+                    std::process::exit(1);
+                }
+            }
+            "--agent" => {
+                if i + 1 < args.len() && !args[i + 1].trim().is_empty() {
+                    agent_preset = Some(args[i + 1].trim().to_string());
+                    i += 2;
+                } else {
+                    eprintln!("Error: --agent requires a preset name, e.g. --agent claude-code");
+                    std::process::exit(1);
+                }
+            }
+            "--session" => {
+                if i + 1 < args.len() && !args[i + 1].trim().is_empty() {
+                    session = Some(args[i + 1].trim().to_string());
+                    i += 2;
+                } else {
+                    eprintln!("Error: --session requires a session id or session file path");
+                    std::process::exit(1);
+                }
+            }
+            "--base-commit" => {
+                if i + 1 < args.len() && !args[i + 1].trim().is_empty() {
+                    base_commit = Some(args[i + 1].clone());
+                    i += 2;
+                } else {
+                    eprintln!("Error: --base-commit requires a commit");
+                    std::process::exit(1);
+                }
+            }
 
             _ => {
                 i += 1;
@@ -174,9 +483,14 @@ fn handle_checkpoint(args: &[String]) {
     }
 
     let mut agent_run_result = None;
-    // Handle preset arguments after parsing all flags
-    if !args.is_empty() {
-        match args[0].as_str() {
+    // Handle preset arguments after parsing all flags. `--agent <preset>` names the preset
+    // like the first argument does.
+    let preset = agent_preset
+        .clone()
+        .or_else(|| args.first().cloned())
+        .unwrap_or_default();
+    if !preset.is_empty() {
+        match preset.as_str() {
             "claude" => {
                 match ClaudePreset.run(AgentCheckpointFlags {
                     hook_input: hook_input.clone(),
@@ -193,6 +507,40 @@ fn handle_checkpoint(args: &[String]) {
                     }
                 }
             }
+            "claude-code" => {
+                let preset = ClaudeCodeSessionPreset {
+                    working_dir: PathBuf::from(&repository_working_dir),
+                    session: session.clone(),
+                };
+                match preset.run(AgentCheckpointFlags {
+                    hook_input: hook_input.clone(),
+                }) {
+                    Ok(agent_run) => {
+                        agent_run_result = Some(agent_run);
+                    }
+                    Err(e) => {
+                        eprintln!("Claude Code preset error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            "copilot-chat" => {
+                let preset = CopilotChatPreset {
+                    working_dir: PathBuf::from(&repository_working_dir),
+                    session: session.clone(),
+                };
+                match preset.run(AgentCheckpointFlags {
+                    hook_input: hook_input.clone(),
+                }) {
+                    Ok(agent_run) => {
+                        agent_run_result = Some(agent_run);
+                    }
+                    Err(e) => {
+                        eprintln!("Copilot chat preset error: {}", e);
+                        std::process::exit(1);
+                    }
+                }
+            }
             "cursor" => {
                 match CursorPreset.run(AgentCheckpointFlags {
                     hook_input: hook_input.clone(),
@@ -235,6 +583,34 @@ fn handle_checkpoint(args: &[String]) {
                     }
                 }
             }
+            "scaffold" => {
+                let Some(tool) = scaffold_tool.clone() else {
+                    eprintln!("Error: scaffold requires --tool <name>, e.g. --tool cargo-new");
+                    std::process::exit(1);
+                };
+                // The files themselves are filled in once the repository is known
+                agent_run_result = Some(AgentRunResult {
+                    agent_id: AgentId {
+                        tool,
+                        id: format!(
+                            "scaffold-{}",
+                            SystemTime::now()
+                                .duration_since(UNIX_EPOCH)
+                                .map(|d| d.as_nanos())
+                                .unwrap_or_else(|_| 0)
+                        ),
+                        model: "unknown".to_string(),
+                    },
+                    checkpoint_kind: CheckpointKind::AiAgent,
+                    transcript: None,
+                    repo_working_dir: None,
+                    edited_filepaths: None,
+                    will_edit_filepaths: None,
+                    commit_message_source: None,
+                    agent_version: None,
+                    file_contents: None,
+                });
+            }
             "mock_ai" => {
                 let mock_agent_id = format!(
                     "ai-thread-{}",
@@ -247,9 +623,12 @@ fn handle_checkpoint(args: &[String]) {
                 // Collect all remaining args (after mock_ai and flags) as pathspecs
                 let edited_filepaths = if args.len() > 1 {
                     let mut paths = Vec::new();
-                    for arg in &args[1..] {
-                        // Skip flags
-                        if !arg.starts_with("--") {
+                    let mut rest = args[1..].iter();
+                    while let Some(arg) = rest.next() {
+                        // Skip flags, along with the values of those that take one
+                        if matches!(arg.as_str(), "--base-commit" | "--agent" | "--session") {
+                            rest.next();
+                        } else if !arg.starts_with("--") {
                             paths.push(arg.clone());
                         }
                     }
@@ -269,8 +648,15 @@ fn handle_checkpoint(args: &[String]) {
                     repo_working_dir: None,
                     edited_filepaths,
                     will_edit_filepaths: None,
+                    commit_message_source: None,
+                    agent_version: None,
+                    file_contents: None,
                 });
             }
+            _ if agent_preset.is_some() => {
+                eprintln!("Unknown agent preset: {}", preset);
+                std::process::exit(1);
+            }
             _ => {}
         }
     }
@@ -280,7 +666,7 @@ fn handle_checkpoint(args: &[String]) {
         .and_then(|r| r.repo_working_dir.clone())
         .unwrap_or_else(|| repository_working_dir);
     // Find the git repository
-    let repo = match find_repository_in_path(&final_working_dir) {
+    let repo = match find_repository_cached(&vec!["-C".to_string(), final_working_dir]) {
         Ok(repo) => repo,
         Err(e) => {
             eprintln!("Failed to find repository: {}", e);
@@ -288,6 +674,27 @@ fn handle_checkpoint(args: &[String]) {
         }
     };
 
+    // A scaffold claims exactly the files it generated: everything untracked, and nothing a
+    // person has already changed in tracked files
+    if preset == "scaffold"
+        && let Some(result) = agent_run_result.as_mut()
+    {
+        match repo.untracked_files() {
+            Ok(files) if files.is_empty() => {
+                eprintln!("No untracked files to attribute to {}", result.agent_id.tool);
+                return;
+            }
+            Ok(files) => result.edited_filepaths = Some(files),
+            Err(e) => {
+                eprintln!("Failed to list untracked files: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // A commit still being attributed in the background owns HEAD's working log
+    commands::process_queue::finish_pending_work(&repo);
+
     let checkpoint_kind = agent_run_result
         .as_ref()
         .map(|r| r.checkpoint_kind)
@@ -306,14 +713,158 @@ fn handle_checkpoint(args: &[String]) {
         &repo,
         &default_user_name,
         checkpoint_kind,
-        show_working_log,
-        reset,
-        false,
         agent_run_result,
+        CheckpointOptions {
+            show_working_log,
+            reset,
+            no_cache,
+            include_large,
+            base_commit: base_commit.as_deref(),
+            ..Default::default()
+        },
+    ) {
+        eprintln!("Checkpoint failed: {}", e);
+        std::process::exit(1);
+    }
+
+    if let Ok(workdir) = repo.workdir() {
+        try_register_repository(&workdir);
+    }
+}
+
+/// `git-ai checkpoint agent --batch-json <json|stdin>`: many agent edits in one invocation
+fn handle_checkpoint_batch(args: &[String]) {
+    let mut batch_json = None;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--batch-json" if i + 1 < args.len() => {
+                batch_json = Some(args[i + 1].clone());
+                i += 2;
+            }
+            _ => {
+                eprintln!("Unknown checkpoint agent argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let Some(mut batch_json) = batch_json else {
+        eprintln!("Usage: git-ai checkpoint agent <--batch-json <json|stdin>|--stdin-json>");
+        std::process::exit(1);
+    };
+    if batch_json == "stdin" {
+        batch_json = String::new();
+        if let Err(e) = std::io::stdin().read_to_string(&mut batch_json) {
+            eprintln!("Failed to read stdin for batch input: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    let input = match BatchInput::parse(&batch_json) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+    let agent_run_result = input.agent_run_result();
+
+    let repo = match find_repository_cached(&vec!["-C".to_string(), input.repo_working_dir.clone()])
+    {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // A commit still being attributed in the background owns HEAD's working log
+    commands::process_queue::finish_pending_work(&repo);
+
+    let author = match repo.config_get_str("user.name") {
+        Ok(Some(name)) if !name.trim().is_empty() => name,
+        _ => "unknown".to_string(),
+    };
+
+    match commands::checkpoint::run_batch(&repo, &author, &agent_run_result, &input.events) {
+        Ok((checkpoints, files, _)) => eprintln!(
+            "{} {} recorded {} checkpoint(s) for {} file(s) from {} edit(s)",
+            agent_run_result.checkpoint_kind.to_str(),
+            agent_run_result.agent_id.tool,
+            checkpoints,
+            files,
+            input.events.len()
+        ),
+        Err(e) => {
+            eprintln!("Checkpoint failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    if let Ok(workdir) = repo.workdir() {
+        try_register_repository(&workdir);
+    }
+}
+
+/// `git-ai checkpoint agent --stdin-json`: an edit of an agent without a preset, described by
+/// the payload in [`AgentCheckpointInput`]
+fn handle_checkpoint_stdin_json(args: &[String]) {
+    if let Some(arg) = args.iter().find(|arg| *arg != "--stdin-json") {
+        eprintln!("Unknown checkpoint agent argument: {}", arg);
+        std::process::exit(1);
+    }
+
+    let mut json = String::new();
+    if let Err(e) = std::io::stdin().read_to_string(&mut json) {
+        eprintln!("Failed to read stdin for agent checkpoint: {}", e);
+        std::process::exit(1);
+    }
+    let input = match AgentCheckpointInput::parse(&json) {
+        Ok(input) => input,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let current_dir = std::env::current_dir()
+        .unwrap()
+        .to_string_lossy()
+        .to_string();
+    let agent_run_result = input.agent_run_result(&current_dir);
+    let repo_working_dir = agent_run_result.repo_working_dir.clone().unwrap_or(current_dir);
+    let repo = match find_repository_cached(&vec!["-C".to_string(), repo_working_dir]) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    // A commit still being attributed in the background owns HEAD's working log
+    commands::process_queue::finish_pending_work(&repo);
+
+    let author = match repo.config_get_str("user.name") {
+        Ok(Some(name)) if !name.trim().is_empty() => name,
+        _ => "unknown".to_string(),
+    };
+
+    if let Err(e) = commands::checkpoint::run(
+        &repo,
+        &author,
+        CheckpointKind::AiAgent,
+        Some(agent_run_result),
+        CheckpointOptions::default(),
     ) {
         eprintln!("Checkpoint failed: {}", e);
         std::process::exit(1);
     }
+
+    if let Ok(workdir) = repo.workdir() {
+        try_register_repository(&workdir);
+    }
 }
 
 fn handle_stats_delta(args: &[String]) {
@@ -350,6 +901,66 @@ fn handle_stats_delta(args: &[String]) {
     }
 }
 
+fn handle_jj(args: &[String]) {
+    if args.first().map(String::as_str) != Some("sync") || args.len() > 1 {
+        eprintln!("Usage: git-ai jj sync");
+        std::process::exit(1);
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if !crate::git::jj::is_colocated(&repo) {
+        eprintln!("Not a jj colocated repository (no .jj directory next to .git)");
+        std::process::exit(1);
+    }
+
+    match crate::git::jj::sync_rewrites(&repo) {
+        Ok(remapped) => println!("Remapped authorship for {} rewritten commit(s)", remapped),
+        Err(e) => {
+            eprintln!("jj sync failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn handle_prune_detached(args: &[String]) {
+    let mut grace_secs = DETACHED_NOTE_GRACE_SECS;
+    for arg in args {
+        match arg.as_str() {
+            "--now" => grace_secs = 0,
+            _ => {
+                eprintln!("Unknown prune-detached argument: {}", arg);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match prune_detached_notes(&repo, grace_secs, unix_now()) {
+        Ok(summary) => println!(
+            "Pruned notes of {} detached commit(s), {} attached to a ref, {} pending",
+            summary.pruned, summary.attached, summary.pending
+        ),
+        Err(e) => {
+            eprintln!("prune-detached failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
 fn handle_ai_blame(args: &[String]) {
     if args.is_empty() {
         eprintln!("Error: blame requires a file argument");
@@ -391,6 +1002,27 @@ fn handle_ai_blame(args: &[String]) {
     }
 }
 
+fn parse_stats_range<'a>(repo: &'a Repository, arg: &str) -> CommitRange<'a> {
+    let parts: Vec<&str> = arg.split("..").collect();
+    if parts.len() != 2 {
+        eprintln!("Invalid commit range format. Expected: <commit>..<commit>");
+        std::process::exit(1);
+    }
+    match CommitRange::new_infer_refname(
+        repo,
+        parts[0].to_string(),
+        parts[1].to_string(),
+        // @todo this is probably fine, but we might want to give users an option to override from this command.
+        None,
+    ) {
+        Ok(range) => range,
+        Err(e) => {
+            eprintln!("Failed to create commit range: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
 fn handle_stats(args: &[String]) {
     // Find the git repository
     let repo = match find_repository(&Vec::<String>::new()) {
@@ -402,8 +1034,14 @@ fn handle_stats(args: &[String]) {
     };
     // Parse stats-specific arguments
     let mut json_output = false;
+    let mut latency = false;
+    let mut oneline = false;
+    let mut pending = false;
+    let mut per_commit = false;
+    let mut target = None;
     let mut commit_sha = None;
     let mut commit_range: Option<CommitRange> = None;
+    let mut walk = RangeWalk::default();
 
     let mut i = 0;
     while i < args.len() {
@@ -412,33 +1050,46 @@ fn handle_stats(args: &[String]) {
                 json_output = true;
                 i += 1;
             }
+            arg if walk.parse_arg(arg) => {
+                i += 1;
+            }
+            "--latency" => {
+                latency = true;
+                i += 1;
+            }
+            "--oneline" => {
+                oneline = true;
+                i += 1;
+            }
+            "--pending" => {
+                pending = true;
+                i += 1;
+            }
+            "--per-commit" => {
+                per_commit = true;
+                i += 1;
+            }
+            "--range" => {
+                let Some(arg) = args.get(i + 1).filter(|_| target.is_none()) else {
+                    eprintln!("--range takes one <start>..<end> range");
+                    std::process::exit(1);
+                };
+                if !arg.contains("..") {
+                    eprintln!("Invalid commit range format. Expected: <commit>..<commit>");
+                    std::process::exit(1);
+                }
+                target = Some(arg.clone());
+                commit_range = Some(parse_stats_range(&repo, arg));
+                i += 2;
+            }
             _ => {
                 // First non-flag argument is treated as commit SHA or range
-                if commit_sha.is_none() {
+                if target.is_none() {
                     let arg = &args[i];
+                    target = Some(arg.clone());
                     // Check if this is a commit range (contains "..")
                     if arg.contains("..") {
-                        let parts: Vec<&str> = arg.split("..").collect();
-                        if parts.len() == 2 {
-                            match CommitRange::new_infer_refname(
-                                &repo,
-                                parts[0].to_string(),
-                                parts[1].to_string(),
-                                // @todo this is probably fine, but we might want to give users an option to override from this command.
-                                None,
-                            ) {
-                                Ok(range) => {
-                                    commit_range = Some(range);
-                                }
-                                Err(e) => {
-                                    eprintln!("Failed to create commit range: {}", e);
-                                    std::process::exit(1);
-                                }
-                            }
-                        } else {
-                            eprintln!("Invalid commit range format. Expected: <commit>..<commit>");
-                            std::process::exit(1);
-                        }
+                        commit_range = Some(parse_stats_range(&repo, arg));
                     } else {
                         commit_sha = Some(arg.clone());
                     }
@@ -451,9 +1102,77 @@ fn handle_stats(args: &[String]) {
         }
     }
 
+    if latency {
+        let target = target.as_deref().unwrap_or("HEAD");
+        match commands::latency::build_report(&repo, target, walk) {
+            Ok(report) if json_output => println!("{}", serde_json::to_string(&report).unwrap()),
+            Ok(report) => commands::latency::print_report(&report),
+            Err(e) => {
+                eprintln!("Latency failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if oneline || pending {
+        if commit_range.is_some() || (pending && commit_sha.is_some()) {
+            eprintln!("--oneline takes a single commit or --pending");
+            std::process::exit(1);
+        }
+        let stats = if pending {
+            crate::authorship::stats::pending_stats(&repo)
+        } else {
+            let rev = commit_sha.as_deref().unwrap_or("HEAD");
+            match repo.revparse_single(rev) {
+                Ok(commit) => crate::authorship::stats::stats_for_commit_stats(
+                    &repo,
+                    &commit.id(),
+                    rev,
+                ),
+                Err(_) => {
+                    eprintln!("No commit found: {}", rev);
+                    std::process::exit(1);
+                }
+            }
+        };
+        match stats {
+            Ok(stats) if json_output => println!("{}", serde_json::to_string(&stats).unwrap()),
+            Ok(stats) if oneline => {
+                println!("{}", crate::authorship::stats::write_stats_oneline(&stats))
+            }
+            Ok(stats) => {
+                crate::authorship::stats::write_stats_to_terminal(&stats, true);
+            }
+            Err(e) => {
+                eprintln!("Stats failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if per_commit {
+        let Some(range) = commit_range else {
+            eprintln!("--per-commit takes a <start>..<end> range");
+            std::process::exit(1);
+        };
+        match range_authorship::per_commit_stats(range.with_walk(walk)) {
+            Ok(rows) if json_output => println!("{}", serde_json::to_string(&rows).unwrap()),
+            Ok(rows) => range_authorship::print_per_commit_stats(&rows),
+            Err(e) => {
+                eprintln!("Range authorship failed: {}", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     // Handle commit range if detected
     if let Some(range) = commit_range {
-        match range_authorship::range_authorship(range, true) {
+        // A bare repository is the server side already; there is nothing to fetch the range from
+        let pre_fetch = !range.repo().is_bare();
+        match range_authorship::range_authorship(range.with_walk(walk), pre_fetch) {
             Ok(stats) => {
                 if json_output {
                     let json_str = serde_json::to_string(&stats).unwrap();
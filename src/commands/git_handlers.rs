@@ -1,17 +1,30 @@
+use crate::cancellation;
+use crate::commands::hooks::am_hooks;
 use crate::commands::hooks::cherry_pick_hooks;
+use crate::commands::hooks::clean_hooks;
 use crate::commands::hooks::commit_hooks;
 use crate::commands::hooks::fetch_hooks;
+use crate::commands::hooks::gc_hooks;
+use crate::commands::hooks::head_coherence;
+use crate::commands::hooks::hook_health;
 use crate::commands::hooks::merge_hooks;
 use crate::commands::hooks::push_hooks;
 use crate::commands::hooks::rebase_hooks;
 use crate::commands::hooks::reset_hooks;
+use crate::commands::hooks::revert_hooks;
+use crate::commands::last_outcome::{CommandOutcome, write_outcome};
+use crate::commands::process_queue;
 use crate::config;
+use crate::error::GitAiError;
 use crate::git::cli_parser::{ParsedGitInvocation, parse_git_cli_args};
-use crate::git::find_repository;
+use crate::git::find_repository_cached;
+use crate::git::mailbox::PatchMail;
+use crate::git::refs::{commits_with_notes_changed, head_and_notes_tips};
 use crate::git::repository::Repository;
 
 use crate::utils::Timer;
 use crate::utils::debug_log;
+use std::collections::HashMap;
 #[cfg(unix)]
 use std::os::unix::process::CommandExt;
 #[cfg(unix)]
@@ -55,12 +68,88 @@ fn uninstall_forwarding_handlers() {
     }
 }
 
+/// Commands with a pre-command hook
+const PRE_COMMAND_HOOKS: &[&str] = &[
+    "commit",
+    "merge",
+    "rebase",
+    "reset",
+    "cherry-pick",
+    "revert",
+    "push",
+    "fetch",
+    "pull",
+    "clean",
+    "am",
+];
+
+/// Commands with a post-command hook
+const POST_COMMAND_HOOKS: &[&str] = &[
+    "commit",
+    "merge",
+    "rebase",
+    "reset",
+    "cherry-pick",
+    "revert",
+    "push",
+    "fetch",
+    "pull",
+    "gc",
+    "maintenance",
+    "clean",
+    "am",
+];
+
+/// Commands whose hooks work on the working log of HEAD: it's brought up to date with HEAD
+/// before they run, and HEAD is recorded after
+const WORKING_LOG_COMMANDS: &[&str] = &[
+    "commit",
+    "merge",
+    "rebase",
+    "reset",
+    "cherry-pick",
+    "revert",
+    "pull",
+    "am",
+    "clean",
+];
+
+/// Commands whose hooks write or send authorship notes
+const NOTES_COMMANDS: &[&str] = &[
+    "commit",
+    "merge",
+    "rebase",
+    "reset",
+    "cherry-pick",
+    "revert",
+    "pull",
+    "am",
+    "fetch",
+    "push",
+];
+
 pub struct CommandHooksContext {
     pub pre_commit_hook_result: Option<bool>,
     pub rebase_original_head: Option<String>,
     pub _rebase_onto: Option<String>,
-    pub push_authorship_handle: Option<std::thread::JoinHandle<()>>,
-    pub fetch_authorship_handle: Option<std::thread::JoinHandle<()>>,
+    pub push_authorship_handle: Option<crate::runtime::Task<Result<(), GitAiError>>>,
+    pub fetch_authorship_handle: Option<crate::runtime::Task<Result<(), GitAiError>>>,
+    /// CHERRY_PICK_HEAD existed before the command ran (a conflicted pick being concluded)
+    pub cherry_pick_in_progress: bool,
+    /// HEAD before a `git revert` ran
+    pub revert_original_head: Option<String>,
+    /// REVERT_HEAD before the command ran (a conflicted revert being concluded)
+    pub revert_head: Option<String>,
+    /// Untracked files with uncommitted AI attributions the clean is about to delete
+    pub cleaned_ai_files: Vec<String>,
+    /// Remote-tracking branches before a fetch or pull, to tell which ones it pruned
+    pub remote_branches_before: Option<HashMap<String, String>>,
+    /// HEAD before a `git am` ran
+    pub am_original_head: Option<String>,
+    /// The patch mails the am is importing
+    pub am_patches: Vec<PatchMail>,
+    /// Arguments the pre-command hooks add to the git command, e.g. commit trailers
+    pub extra_command_args: Vec<String>,
 }
 
 pub fn handle_git(args: &[String]) {
@@ -78,11 +167,33 @@ pub fn handle_git(args: &[String]) {
         _rebase_onto: None,
         push_authorship_handle: None,
         fetch_authorship_handle: None,
+        cherry_pick_in_progress: false,
+        revert_original_head: None,
+        revert_head: None,
+        cleaned_ai_files: Vec::new(),
+        remote_branches_before: None,
+        am_original_head: None,
+        am_patches: Vec::new(),
+        extra_command_args: Vec::new(),
     };
 
-    let parsed_args = parse_git_cli_args(args);
+    let mut parsed_args = parse_git_cli_args(args);
+
+    let command = parsed_args.command.as_deref().unwrap_or_default();
+    let has_pre_hook = PRE_COMMAND_HOOKS.contains(&command);
+    let has_post_hook = POST_COMMAND_HOOKS.contains(&command);
+    let uses_working_log = WORKING_LOG_COMMANDS.contains(&command);
+    // Only commands that touch HEAD or notes pay for the bookkeeping around them
+    let keeps_books = uses_working_log || NOTES_COMMANDS.contains(&command);
+
+    // Nothing to do for commands without hooks, so skip repository discovery entirely. This
+    // keeps the overhead of checkouts during `git bisect run` and the like near zero.
+    if !has_pre_hook && !has_post_hook {
+        Timer::default().print_duration("git-ai proxy overhead", Timer::default().epoch.elapsed());
+        exit_with_status(proxy_to_git(&parsed_args.to_invocation_vec(), false));
+    }
 
-    let mut repository_option = find_repository(&parsed_args.global_args).ok();
+    let mut repository_option = find_repository_cached(&parsed_args.global_args).ok();
 
     let has_repo = repository_option.is_some();
 
@@ -106,28 +217,93 @@ pub fn handle_git(args: &[String]) {
 
         let repository = repository_option.as_mut().unwrap();
 
-        let end_precommand_clock = Timer::default().start_quiet("pre-command-hooks");
+        let (head_before, notes_tip_before) = if keeps_books {
+            head_and_notes_tips(repository)
+        } else {
+            (None, None)
+        };
+
+        // Ctrl-C during a hook lets it undo its partial work instead of killing git-ai mid-step
+        cancellation::install_handlers();
+        // Commits attributed in the background have to land before anything builds on them
+        if keeps_books {
+            process_queue::finish_pending_work(repository);
+        }
 
+        let end_precommand_clock = Timer::default().start_quiet("pre-command-hooks");
+        // HEAD may have moved since the last command without the working log following it
+        if uses_working_log {
+            head_coherence::heal_working_log(repository, head_before.as_deref());
+        }
         run_pre_command_hooks(&mut command_hooks_context, &parsed_args, repository);
+        // Right after the subcommand, so they land before any `--` and pathspecs
+        parsed_args.command_args.splice(
+            0..0,
+            std::mem::take(&mut command_hooks_context.extra_command_args),
+        );
+        let pre_hook_errors = std::mem::take(&mut repository.hook_errors);
 
         let pre_command_duration = end_precommand_clock();
 
+        // Interrupted before git ran, so don't run it
+        cancellation::exit_if_cancelled();
         let exit_status = proxy_to_git(&parsed_args.to_invocation_vec(), false);
 
         let end_post_command_clock = Timer::default().start_quiet("post-command-hooks");
 
+        // Forwarding signals to git put the default handlers back
+        cancellation::install_handlers();
+
         run_post_command_hooks(
             &mut command_hooks_context,
             &parsed_args,
             exit_status,
             repository,
         );
+        let post_hook_errors = std::mem::take(&mut repository.hook_errors);
+        let attribution_losses = std::mem::take(&mut repository.attribution_losses);
+        for loss in &attribution_losses {
+            eprintln!("{}", loss.warning());
+        }
 
         let post_command_duration = end_post_command_clock();
 
+        if keeps_books {
+            let (head_after, notes_tip_after) = head_and_notes_tips(repository);
+            if uses_working_log {
+                head_coherence::record_head(repository, head_after.as_deref());
+            }
+
+            let mut outcome = CommandOutcome::new(
+                parsed_args.command.clone(),
+                parsed_args.command_args.clone(),
+                exit_status.code(),
+            );
+            if has_pre_hook {
+                let hook = format!("pre-{}", command);
+                hook_health::record_hook_duration(repository, &hook, pre_command_duration);
+                outcome.record_hook(hook, pre_hook_errors);
+            }
+            if has_post_hook {
+                let hook = format!("post-{}", command);
+                hook_health::record_hook_duration(repository, &hook, post_command_duration);
+                outcome.record_hook(hook, post_hook_errors);
+            }
+            outcome.notes_written = notes_written_since(
+                repository,
+                notes_tip_before.as_deref(),
+                notes_tip_after.as_deref(),
+            );
+            outcome.attribution_losses = attribution_losses;
+            if let Err(e) = write_outcome(repository, &outcome) {
+                debug_log(&format!("Failed to write command outcome: {}", e));
+            }
+        }
+
         Timer::default()
             .print_duration("git-ai hooks", pre_command_duration + post_command_duration);
 
+        cancellation::exit_if_cancelled();
         exit_status
     } else {
         Timer::default().print_duration("git-ai proxy overhead", Timer::default().epoch.elapsed());
@@ -145,9 +321,23 @@ fn run_pre_command_hooks(
     // Pre-command hooks
     match parsed_args.command.as_deref() {
         Some("commit") => {
+            command_hooks_context.cherry_pick_in_progress =
+                repository.path().join("CHERRY_PICK_HEAD").exists();
+            command_hooks_context.revert_head =
+                std::fs::read_to_string(repository.path().join("REVERT_HEAD"))
+                    .ok()
+                    .map(|sha| sha.trim().to_string())
+                    .filter(|sha| !sha.is_empty());
             command_hooks_context.pre_commit_hook_result = Some(
                 commit_hooks::commit_pre_command_hook(parsed_args, repository),
             );
+            if command_hooks_context.pre_commit_hook_result == Some(true) {
+                command_hooks_context.extra_command_args =
+                    commit_hooks::commit_trailer_args(parsed_args, repository);
+            }
+        }
+        Some("merge") => {
+            merge_hooks::pre_merge_hook(parsed_args, repository, command_hooks_context);
         }
         Some("rebase") => {
             rebase_hooks::pre_rebase_hook(parsed_args, repository, command_hooks_context);
@@ -158,13 +348,26 @@ fn run_pre_command_hooks(
         Some("cherry-pick") => {
             cherry_pick_hooks::pre_cherry_pick_hook(parsed_args, repository, command_hooks_context);
         }
+        Some("revert") => {
+            revert_hooks::pre_revert_hook(repository, command_hooks_context);
+        }
         Some("push") => {
+            push_hooks::enforce_push_policies(parsed_args, repository);
             command_hooks_context.push_authorship_handle =
                 push_hooks::push_pre_command_hook(parsed_args, repository);
         }
         Some("fetch") | Some("pull") => {
-            command_hooks_context.fetch_authorship_handle =
-                fetch_hooks::fetch_pull_pre_command_hook(parsed_args, repository);
+            fetch_hooks::fetch_pull_pre_command_hook(
+                parsed_args,
+                repository,
+                command_hooks_context,
+            );
+        }
+        Some("clean") => {
+            clean_hooks::pre_clean_hook(parsed_args, repository, command_hooks_context);
+        }
+        Some("am") => {
+            am_hooks::pre_am_hook(parsed_args, repository, command_hooks_context);
         }
         _ => {}
     }
@@ -197,7 +400,19 @@ fn run_post_command_hooks(
             command_hooks_context,
         ),
         Some("reset") => reset_hooks::post_reset_hook(parsed_args, repository, exit_status),
-        Some("merge") => merge_hooks::post_merge_hook(parsed_args, exit_status, repository),
+        Some("gc") => gc_hooks::post_gc_hook(parsed_args, repository, exit_status),
+        Some("maintenance") => {
+            gc_hooks::post_maintenance_hook(parsed_args, repository, exit_status)
+        }
+        Some("clean") => {
+            clean_hooks::post_clean_hook(repository, exit_status, command_hooks_context)
+        }
+        Some("merge") => merge_hooks::post_merge_hook(
+            parsed_args,
+            exit_status,
+            repository,
+            command_hooks_context,
+        ),
         Some("rebase") => rebase_hooks::handle_rebase_post_command(
             command_hooks_context,
             parsed_args,
@@ -210,10 +425,35 @@ fn run_post_command_hooks(
             exit_status,
             repository,
         ),
+        Some("revert") => revert_hooks::post_revert_hook(
+            command_hooks_context,
+            parsed_args,
+            exit_status,
+            repository,
+        ),
+        Some("am") => am_hooks::post_am_hook(command_hooks_context, exit_status, repository),
         _ => {}
     }
 }
 
+/// Commits whose authorship note changed since the notes ref was at `old_tip`
+fn notes_written_since(
+    repository: &Repository,
+    old_tip: Option<&str>,
+    new_tip: Option<&str>,
+) -> Vec<String> {
+    let Some(new_tip) = new_tip else {
+        return Vec::new();
+    };
+    if old_tip == Some(new_tip) {
+        return Vec::new();
+    }
+    commits_with_notes_changed(repository, old_tip, new_tip).unwrap_or_else(|e| {
+        debug_log(&format!("Failed to list written notes: {}", e));
+        Vec::new()
+    })
+}
+
 fn proxy_to_git(args: &[String], exit_on_completion: bool) -> std::process::ExitStatus {
     // debug_log(&format!("proxying to git with args: {:?}", args));
     // debug_log(&format!("prepended global args: {:?}", prepend_global(args)));
@@ -0,0 +1,295 @@
+use crate::authorship::authorship_log::{LineRange, PatchProvenance, PromptRecord};
+use crate::authorship::authorship_log_serialization::{
+    AttestationEntry, AuthorshipLog, generate_short_hash,
+};
+use crate::authorship::working_log::AgentId;
+use crate::commands::git_handlers::CommandHooksContext;
+use crate::commands::hooks::head_coherence::carry_working_log;
+use crate::error::GitAiError;
+use crate::git::cli_parser::ParsedGitInvocation;
+use crate::git::mailbox::{PatchMail, parse_mail, split_mbox};
+use crate::git::refs::{notes_add, show_authorship_note};
+use crate::git::repository::{Repository, exec_git, exec_git_stdin};
+use crate::utils::debug_log;
+use std::path::Path;
+
+const EMPTY_TREE: &str = "4b825dc642cb6eb9a060e54bf8d69288fbee4904";
+
+pub fn pre_am_hook(
+    parsed_args: &ParsedGitInvocation,
+    repository: &Repository,
+    command_hooks_context: &mut CommandHooksContext,
+) {
+    debug_log("=== AM PRE-COMMAND HOOK ===");
+    if parsed_args
+        .command_args
+        .iter()
+        .any(|arg| matches!(arg.as_str(), "--abort" | "--quit" | "--show-current-patch"))
+        || parsed_args
+            .command_args
+            .iter()
+            .any(|arg| arg.starts_with("--show-current-patch="))
+    {
+        return;
+    }
+
+    command_hooks_context.am_original_head =
+        repository.head().ok().and_then(|head| head.target().ok());
+    // A stopped am is concluded (`--continue`, `--skip`) from the mails it split into
+    // rebase-apply; a new one reads the mailboxes it was given
+    command_hooks_context.am_patches = if repository
+        .path()
+        .join("rebase-apply")
+        .join("applying")
+        .exists()
+    {
+        pending_patches(repository)
+    } else {
+        mailbox_patches(parsed_args)
+    };
+    debug_log(&format!(
+        "am of {} patch mail(s) from {:?}",
+        command_hooks_context.am_patches.len(),
+        command_hooks_context.am_original_head
+    ));
+}
+
+pub fn post_am_hook(
+    command_hooks_context: &CommandHooksContext,
+    exit_status: std::process::ExitStatus,
+    repository: &Repository,
+) {
+    debug_log("=== AM POST-COMMAND HOOK ===");
+    debug_log(&format!("Exit status: {}", exit_status));
+
+    // Patches applied before am stopped are committed too, so look at the commits either way
+    let Some(original_head) = &command_hooks_context.am_original_head else {
+        return;
+    };
+    let commits = new_commits_since(repository, original_head).unwrap_or_default();
+    let Some(new_head) = commits.last() else {
+        return;
+    };
+
+    // git am refuses to run with staged changes, but unstaged ones stay in the working
+    // directory on top of the imported commits
+    if let Err(e) = carry_working_log(repository, original_head, new_head) {
+        debug_log(&format!("Failed to carry the working log over am: {}", e));
+    }
+
+    let patches = &command_hooks_context.am_patches;
+    let patch_ids: Vec<Option<String>> = patches
+        .iter()
+        .map(|patch| patch_id(repository, patch.content.as_bytes()))
+        .collect();
+    let mut next_patch = 0;
+    for commit in &commits {
+        let Some(index) = match_patch(repository, commit, patches, &patch_ids, next_patch) else {
+            debug_log(&format!("No patch mail matches {}", commit));
+            continue;
+        };
+        next_patch = index + 1;
+        if show_authorship_note(repository, commit).is_some() {
+            continue;
+        }
+        let result = note_for_patch(repository, commit, &patches[index]).and_then(|log| {
+            let content = log
+                .serialize_to_string()
+                .map_err(|_| GitAiError::Generic("Failed to serialize authorship log".into()))?;
+            notes_add(repository, commit, &content)
+        });
+        match result {
+            Ok(()) => debug_log(&format!(
+                "✓ Wrote note of {} from patch {:?}",
+                commit, patches[index].subject
+            )),
+            Err(e) => debug_log(&format!("✗ Failed to write note of {}: {}", commit, e)),
+        }
+    }
+}
+
+/// The patch mail `commit` was made from: the first not yet matched one with the same
+/// `git patch-id`, or failing that the same subject
+fn match_patch(
+    repository: &Repository,
+    commit: &str,
+    patches: &[PatchMail],
+    patch_ids: &[Option<String>],
+    from: usize,
+) -> Option<usize> {
+    let commit_patch_id = commit_diff(repository, commit)
+        .ok()
+        .and_then(|diff| patch_id(repository, &diff));
+    if let Some(commit_patch_id) = &commit_patch_id
+        && let Some(index) =
+            (from..patches.len()).find(|index| patch_ids[*index].as_ref() == Some(commit_patch_id))
+    {
+        return Some(index);
+    }
+
+    // A patch applied with a three-way merge can come out different from the mail
+    let summary = repository
+        .find_commit(commit.to_string())
+        .and_then(|commit| commit.summary())
+        .ok()?;
+    (from..patches.len()).find(|index| patches[*index].summary() == summary)
+}
+
+/// The note of a commit imported from `patch`: the lines it added belong to the tool named
+/// by the mail's `X-AI-Generated` header, or to its human author without one
+fn note_for_patch(
+    repository: &Repository,
+    commit: &str,
+    patch: &PatchMail,
+) -> Result<AuthorshipLog, GitAiError> {
+    let git_commit = repository.find_commit(commit.to_string())?;
+    let parent = git_commit.parents().next().map(|parent| parent.id());
+    let mut log = AuthorshipLog::new();
+    log.metadata.base_commit_sha = parent.clone().unwrap_or_else(|| "initial".to_string());
+    log.metadata.patch_series = Some(PatchProvenance {
+        subject: patch.subject.clone(),
+        message_id: patch.message_id.clone(),
+        in_reply_to: patch.in_reply_to.clone(),
+        position: patch.position(),
+        ai_generated: patch
+            .ai_generated
+            .as_ref()
+            .map(|declaration| declaration.tool.clone()),
+    });
+
+    let Some(declaration) = &patch.ai_generated else {
+        return Ok(log);
+    };
+    let agent_id = AgentId {
+        tool: declaration.tool.clone(),
+        id: patch
+            .message_id
+            .clone()
+            .unwrap_or_else(|| commit.to_string()),
+        model: declaration
+            .model
+            .clone()
+            .unwrap_or_else(|| "unknown".to_string()),
+    };
+    let hash = generate_short_hash(&agent_id.id, &agent_id.tool);
+
+    let added_lines =
+        repository.diff_added_lines(parent.as_deref().unwrap_or(EMPTY_TREE), commit, None)?;
+    let mut files: Vec<(String, Vec<u32>)> = added_lines.into_iter().collect();
+    files.sort();
+    let settings = repository.settings();
+    let mut total_additions = 0;
+    for (file, mut lines) in files {
+        if lines.is_empty() || settings.is_excluded_path(&file) {
+            continue;
+        }
+        lines.sort_unstable();
+        lines.dedup();
+        total_additions += lines.len() as u32;
+        log.get_or_create_file(&file)
+            .add_entry(AttestationEntry::new(
+                hash.clone(),
+                LineRange::compress_lines(&lines),
+            ));
+    }
+    if total_additions == 0 {
+        return Ok(log);
+    }
+
+    let author = git_commit.author()?;
+    log.metadata.prompts.insert(
+        hash,
+        PromptRecord {
+            agent_id,
+            human_author: Some(format!(
+                "{} <{}>",
+                author.name().unwrap_or("unknown"),
+                author.email().unwrap_or_default()
+            )),
+            messages: Vec::new(),
+            total_additions,
+            total_deletions: 0,
+            accepted_lines: total_additions,
+            overriden_lines: 0,
+            summary: None,
+            environments: Vec::new(),
+            generated_at: None,
+        },
+    );
+    Ok(log)
+}
+
+/// Messages in the mailbox files named on the command line. Patches piped in on stdin can't
+/// be read without taking them from git.
+fn mailbox_patches(parsed_args: &ParsedGitInvocation) -> Vec<PatchMail> {
+    parsed_args
+        .command_args
+        .iter()
+        .filter(|arg| !arg.starts_with('-'))
+        .filter_map(|arg| std::fs::read(Path::new(arg)).ok())
+        .flat_map(|content| {
+            let content = String::from_utf8_lossy(&content).into_owned();
+            split_mbox(&content)
+                .into_iter()
+                .map(parse_mail)
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// The mails a stopped am hasn't committed yet, from the current one on
+fn pending_patches(repository: &Repository) -> Vec<PatchMail> {
+    let state_dir = repository.path().join("rebase-apply");
+    let read_number = |name: &str| -> Option<usize> {
+        std::fs::read_to_string(state_dir.join(name))
+            .ok()?
+            .trim()
+            .parse()
+            .ok()
+    };
+    let (Some(next), Some(last)) = (read_number("next"), read_number("last")) else {
+        return Vec::new();
+    };
+    (next..=last)
+        .filter_map(|number| std::fs::read(state_dir.join(format!("{:04}", number))).ok())
+        .map(|content| parse_mail(&String::from_utf8_lossy(&content)))
+        .collect()
+}
+
+fn new_commits_since(
+    repository: &Repository,
+    original_head: &str,
+) -> Result<Vec<String>, GitAiError> {
+    let mut args = repository.global_args_for_exec();
+    args.push("rev-list".to_string());
+    args.push("--reverse".to_string());
+    args.push(format!("{}..HEAD", original_head));
+    let output = exec_git(&args)?;
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+fn commit_diff(repository: &Repository, commit: &str) -> Result<Vec<u8>, GitAiError> {
+    let mut args = repository.global_args_for_exec();
+    args.push("diff-tree".to_string());
+    args.push("-p".to_string());
+    args.push("--root".to_string());
+    args.push("--no-commit-id".to_string());
+    args.push("--no-color".to_string());
+    args.push("--no-ext-diff".to_string());
+    args.push(commit.to_string());
+    Ok(exec_git(&args)?.stdout)
+}
+
+/// `git patch-id` of a diff or a patch mail; None when it holds no change
+fn patch_id(repository: &Repository, patch: &[u8]) -> Option<String> {
+    let mut args = repository.global_args_for_exec();
+    args.push("patch-id".to_string());
+    args.push("--stable".to_string());
+    let output = exec_git_stdin(&args, patch).ok()?;
+    let output = String::from_utf8(output.stdout).ok()?;
+    output.split_whitespace().next().map(str::to_string)
+}
@@ -112,7 +112,9 @@ pub fn post_cherry_pick_hook(
 
     debug_log(&format!("Original head from log: {:?}", original_head));
 
-    if !exit_status.success() {
+    // `--abort` exits cleanly, with HEAD back where the cherry-pick started
+    let aborted = parsed_args.command_args.iter().any(|arg| arg == "--abort");
+    if !exit_status.success() || aborted {
         // Cherry-pick was aborted or failed - log Abort event
         if let Some(orig_head) = original_head {
             debug_log(&format!("✗ Cherry-pick aborted/failed from {}", orig_head));
@@ -142,6 +144,34 @@ pub fn post_cherry_pick_hook(
     }
 }
 
+/// A conflicted cherry-pick can also be concluded with a plain `git commit` instead of
+/// `git cherry-pick --continue`. Once nothing is left in the sequencer, run the same
+/// completion pass the --continue path gets.
+pub fn post_commit_concluding_cherry_pick(
+    parsed_args: &ParsedGitInvocation,
+    repository: &mut Repository,
+) {
+    let cherry_pick_head = repository.path().join("CHERRY_PICK_HEAD");
+    let sequencer_dir = repository.path().join("sequencer");
+    if cherry_pick_head.exists() || sequencer_dir.exists() {
+        debug_log("Commit concluded one pick but the cherry-pick sequence is still in progress");
+        return;
+    }
+
+    if !has_active_cherry_pick_start_event(repository) {
+        debug_log("Commit concluded a cherry-pick we didn't see start, nothing to rewrite");
+        return;
+    }
+
+    if let Some(original_head) = find_cherry_pick_start_event_original_head(repository) {
+        debug_log(&format!(
+            "Commit concluded cherry-pick started from {}",
+            original_head
+        ));
+        process_completed_cherry_pick(repository, &original_head, parsed_args);
+    }
+}
+
 /// Check if there's an active cherry-pick Start event (not followed by Complete or Abort)
 fn has_active_cherry_pick_start_event(repository: &Repository) -> bool {
     let events = match repository.storage.read_rewrite_events() {
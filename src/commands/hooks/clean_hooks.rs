@@ -0,0 +1,277 @@
+use crate::commands::git_handlers::CommandHooksContext;
+use crate::error::GitAiError;
+use crate::git::cli_parser::ParsedGitInvocation;
+use crate::git::repo_storage::PersistedWorkingLog;
+use crate::git::repository::{Repository, exec_git};
+use crate::utils::debug_log;
+use std::collections::HashSet;
+
+/// Warn before `git clean` deletes untracked files whose AI attributions haven't been committed
+/// yet. With the `confirm_clean` setting on, the clean is refused instead.
+pub fn pre_clean_hook(
+    parsed_args: &ParsedGitInvocation,
+    repository: &mut Repository,
+    command_hooks_context: &mut CommandHooksContext,
+) {
+    if is_clean_dry_run(&parsed_args.command_args) || !clean_will_delete(parsed_args, repository) {
+        return;
+    }
+
+    let attributed = match current_working_log(repository).ai_attributed_files() {
+        Ok(files) if files.is_empty() => return,
+        Ok(files) => files,
+        Err(e) => {
+            debug_log(&format!("clean: failed to read working log: {}", e));
+            return;
+        }
+    };
+
+    let removed = match files_to_be_cleaned(parsed_args, repository) {
+        Ok(removed) => removed,
+        Err(e) => {
+            debug_log(&format!("clean: dry run failed: {}", e));
+            return;
+        }
+    };
+
+    let mut at_risk: Vec<String> = attributed
+        .into_iter()
+        .filter(|file| {
+            removed.iter().any(|path| {
+                path == file || (path.ends_with('/') && file.starts_with(path.as_str()))
+            })
+        })
+        .collect();
+    if at_risk.is_empty() {
+        return;
+    }
+    at_risk.sort();
+
+    eprintln!(
+        "git-ai: git clean will delete {} file(s) with AI-authored lines that haven't been committed:",
+        at_risk.len()
+    );
+    for file in &at_risk {
+        eprintln!("  {}", file);
+    }
+
+    if repository.settings().confirm_clean {
+        eprintln!(
+            "git-ai: refusing to clean (confirm_clean is set). Commit or stash these files, or re-run with GIT_AI_CONFIRM_CLEAN=false to delete them anyway."
+        );
+        std::process::exit(1);
+    }
+
+    command_hooks_context.cleaned_ai_files = at_risk;
+}
+
+/// Drop the working log entries of the attributed files the clean actually deleted
+pub fn post_clean_hook(
+    repository: &mut Repository,
+    exit_status: std::process::ExitStatus,
+    command_hooks_context: &mut CommandHooksContext,
+) {
+    let candidates = std::mem::take(&mut command_hooks_context.cleaned_ai_files);
+    if !exit_status.success() || candidates.is_empty() {
+        return;
+    }
+
+    let workdir = match repository.workdir() {
+        Ok(workdir) => workdir,
+        Err(e) => {
+            repository.hook_errors.push(e.to_string());
+            return;
+        }
+    };
+    let deleted: HashSet<String> = candidates
+        .into_iter()
+        .filter(|file| !workdir.join(file).exists())
+        .collect();
+
+    debug_log(&format!(
+        "clean: pruning {} deleted file(s) from the working log",
+        deleted.len()
+    ));
+    if let Err(e) = current_working_log(repository).remove_files(&deleted) {
+        repository.hook_errors.push(e.to_string());
+    }
+}
+
+fn current_working_log(repository: &Repository) -> PersistedWorkingLog {
+    let base_commit = repository
+        .head()
+        .ok()
+        .and_then(|head| head.target().ok())
+        .unwrap_or_else(|| "initial".to_string());
+    repository.storage.working_log_for_base_commit(&base_commit)
+}
+
+/// Without `-f`, `-i` or `clean.requireForce=false` git refuses to clean anything
+fn clean_will_delete(parsed_args: &ParsedGitInvocation, repository: &Repository) -> bool {
+    let args = &parsed_args.command_args;
+    if has_clean_flag(args, 'f', "--force") || has_clean_flag(args, 'i', "--interactive") {
+        return true;
+    }
+    matches!(
+        repository.config_get_bool("clean.requireForce"),
+        Ok(Some(false))
+    )
+}
+
+fn is_clean_dry_run(args: &[String]) -> bool {
+    has_clean_flag(args, 'n', "--dry-run")
+}
+
+/// Whether the clean options include `short` (possibly bundled, like `-fdn`) or `long`
+fn has_clean_flag(args: &[String], short: char, long: &str) -> bool {
+    for arg in args {
+        if arg == "--" {
+            break;
+        }
+        if arg == long {
+            return true;
+        }
+        if let Some(flags) = arg.strip_prefix('-')
+            && !flags.starts_with('-')
+        {
+            // Everything after -e is its exclude pattern
+            let flags = flags.split('e').next().unwrap_or_default();
+            if flags.contains(short) {
+                return true;
+            }
+        }
+    }
+    false
+}
+
+/// The user's clean options turned into a dry run that lists every path it would remove:
+/// interactive and quiet mode are dropped.
+fn dry_run_args(args: &[String]) -> Vec<String> {
+    let mut result = vec!["--dry-run".to_string()];
+    let mut in_pathspecs = false;
+    let mut takes_value = false;
+    for arg in args {
+        if in_pathspecs || takes_value {
+            result.push(arg.clone());
+            takes_value = false;
+            continue;
+        }
+        match arg.as_str() {
+            "--" => {
+                in_pathspecs = true;
+                result.push(arg.clone());
+            }
+            "--interactive" | "--quiet" => {}
+            "--exclude" => {
+                takes_value = true;
+                result.push(arg.clone());
+            }
+            _ if arg.starts_with('-') && !arg.starts_with("--") && arg.len() > 1 => {
+                let (flags, pattern) = match arg[1..].find('e') {
+                    Some(index) => arg[1..].split_at(index),
+                    None => (&arg[1..], ""),
+                };
+                let flags: String = flags.chars().filter(|c| !matches!(c, 'i' | 'q')).collect();
+                if pattern == "e" {
+                    takes_value = true;
+                }
+                if !flags.is_empty() || !pattern.is_empty() {
+                    result.push(format!("-{}{}", flags, pattern));
+                }
+            }
+            _ => result.push(arg.clone()),
+        }
+    }
+    result
+}
+
+/// Repo-relative paths `git clean` would remove. Directories keep their trailing slash.
+fn files_to_be_cleaned(
+    parsed_args: &ParsedGitInvocation,
+    repository: &Repository,
+) -> Result<Vec<String>, GitAiError> {
+    // The user's own global args, so pathspecs and output stay relative to their directory
+    let mut prefix_args = repository.global_args_for_exec();
+    prefix_args.push("rev-parse".to_string());
+    prefix_args.push("--show-prefix".to_string());
+    let prefix = String::from_utf8(exec_git(&prefix_args)?.stdout)?
+        .trim()
+        .to_string();
+
+    let mut args = repository.global_args_for_exec();
+    args.push("-c".to_string());
+    args.push("core.quotePath=false".to_string());
+    args.push("clean".to_string());
+    args.extend(dry_run_args(&parsed_args.command_args));
+    let output = String::from_utf8(exec_git(&args)?.stdout)?;
+
+    Ok(output
+        .lines()
+        .filter_map(|line| line.strip_prefix("Would remove "))
+        .map(|path| repo_relative(&prefix, path))
+        .collect())
+}
+
+/// Join a path printed relative to the current directory onto the directory's repo prefix
+fn repo_relative(prefix: &str, path: &str) -> String {
+    let mut parts: Vec<&str> = prefix.split('/').filter(|part| !part.is_empty()).collect();
+    for part in path.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    let mut joined = parts.join("/");
+    if path.ends_with('/') {
+        joined.push('/');
+    }
+    joined
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn test_clean_flags() {
+        assert!(is_clean_dry_run(&args(&["-fdn"])));
+        assert!(is_clean_dry_run(&args(&["--dry-run"])));
+        // The n belongs to the exclude pattern, and pathspecs aren't options
+        assert!(!is_clean_dry_run(&args(&["-fen"])));
+        assert!(!is_clean_dry_run(&args(&["-f", "--", "-n"])));
+        assert!(has_clean_flag(&args(&["-xdf"]), 'f', "--force"));
+    }
+
+    #[test]
+    fn test_dry_run_args() {
+        assert_eq!(
+            dry_run_args(&args(&[
+                "-fdiq",
+                "-e",
+                "*.log",
+                "--interactive",
+                "--",
+                "-q"
+            ])),
+            args(&["--dry-run", "-fd", "-e", "*.log", "--", "-q"])
+        );
+        assert_eq!(
+            dry_run_args(&args(&["-i", "-qe*.tmp", "src"])),
+            args(&["--dry-run", "-e*.tmp", "src"])
+        );
+    }
+
+    #[test]
+    fn test_repo_relative() {
+        assert_eq!(repo_relative("", "gen/out.rs"), "gen/out.rs");
+        assert_eq!(repo_relative("src/", "new.rs"), "src/new.rs");
+        assert_eq!(repo_relative("src/app/", "../gen/"), "src/gen/");
+    }
+}
@@ -1,8 +1,16 @@
 use crate::authorship::pre_commit;
+use crate::authorship::stats::{CommitScope, CommitStats, staged_stats};
 use crate::commands::git_handlers::CommandHooksContext;
+use crate::commands::hooks::cherry_pick_hooks;
+use crate::commands::hooks::hook_health;
+use crate::commands::hooks::revert_hooks;
+use crate::commands::process_queue;
+use crate::config::Settings;
 use crate::git::cli_parser::{ParsedGitInvocation, is_dry_run};
+use crate::git::detached;
+use crate::git::repo_registry::try_register_repository;
 use crate::git::repository::Repository;
-use crate::git::rewrite_log::RewriteLogEvent;
+use crate::git::rewrite_log::{CommitFixup, FixupKind, RewriteLogEvent};
 use crate::utils::debug_log;
 
 pub fn commit_pre_command_hook(
@@ -13,6 +21,12 @@ pub fn commit_pre_command_hook(
         return false;
     }
 
+    // Skipping pre-commit also skips the post-commit hook, so no note gets written
+    if !repository.settings().is_tracked_branch(repository) {
+        debug_log("Skipping git-ai commit hooks because the current branch is not tracked");
+        return false;
+    }
+
     // store HEAD context for post-command hook
     repository.require_pre_command_head();
 
@@ -28,8 +42,11 @@ pub fn commit_pre_command_hook(
             );
             return false;
         }
-        eprintln!("Pre-commit failed: {}", e);
-        std::process::exit(1);
+        // Never block the user's commit on our own bookkeeping; without the pre-commit
+        // checkpoint the post-commit hook has nothing reliable to attribute, so skip it too
+        eprintln!("git-ai pre-commit failed, commit will not be attributed: {}", e);
+        repository.hook_errors.push(format!("Pre-commit failed: {}", e));
+        return false;
     }
     return true;
 }
@@ -55,7 +72,7 @@ pub fn commit_post_command_hook(
         }
     }
 
-    let supress_output = parsed_args.has_command_flag("--porcelain")
+    let mut supress_output = parsed_args.has_command_flag("--porcelain")
         || parsed_args.has_command_flag("--quiet")
         || parsed_args.has_command_flag("-q")
         || parsed_args.has_command_flag("--no-status");
@@ -68,6 +85,14 @@ pub fn commit_post_command_hook(
         return;
     }
 
+    if let Ok(workdir) = repository.workdir() {
+        try_register_repository(&workdir);
+    }
+
+    if let Some(new_sha) = &new_sha {
+        detached::record_if_detached(repository, new_sha);
+    }
+
     let commit_author = get_commit_default_author(repository, &parsed_args.command_args);
     if parsed_args.has_command_flag("--amend") && original_commit.is_some() && new_sha.is_some() {
         repository.handle_rewrite_log_event(
@@ -76,16 +101,182 @@ pub fn commit_post_command_hook(
             supress_output,
             true,
         );
-    } else {
+    } else if let Some((kind, target_spec)) = extract_fixup_from_args(&parsed_args.command_args) {
+        // --fixup / --squash always create a new commit on top of HEAD (never an amend),
+        // the target only matters later when the commit is autosquashed
+        let target_commit =
+            resolve_fixup_target(repository, &target_spec, &original_commit).unwrap_or(target_spec);
+        debug_log(&format!(
+            "Commit is a {:?} fixup targeting {}",
+            kind, target_commit
+        ));
+
+        // reword! commits don't include the index, so there's nothing to report
+        if !kind.includes_content() {
+            supress_output = true;
+        }
+
         repository.handle_rewrite_log_event(
-            RewriteLogEvent::commit(original_commit, new_sha.unwrap()),
+            RewriteLogEvent::commit_fixup(
+                original_commit,
+                new_sha.unwrap(),
+                CommitFixup::new(kind, target_commit),
+            ),
             commit_author,
             supress_output,
             true,
         );
+    } else {
+        let event = RewriteLogEvent::commit(original_commit, new_sha.unwrap());
+        if hook_health::is_deferred(repository, hook_health::POST_COMMIT) {
+            process_queue::defer_post_commit(repository, event, commit_author, supress_output);
+        } else {
+            repository.handle_rewrite_log_event(event, commit_author, supress_output, true);
+        }
+    }
+
+    if command_hooks_context.cherry_pick_in_progress {
+        cherry_pick_hooks::post_commit_concluding_cherry_pick(parsed_args, repository);
+    }
+
+    if let Some(reverted_commit) = &command_hooks_context.revert_head {
+        revert_hooks::post_commit_concluding_revert(parsed_args, repository, reverted_commit);
     }
 }
 
+/// `--trailer` arguments with the AI share and agents of what the commit is about to record,
+/// when `commit_trailers` is on and some of it is AI-authored. Amends, fixups and interactive
+/// commits are left alone: what they record isn't known up front.
+pub fn commit_trailer_args(
+    parsed_args: &ParsedGitInvocation,
+    repository: &Repository,
+) -> Vec<String> {
+    let settings = repository.settings();
+    let options = parsed_args.commit_options();
+    if !settings.commit_trailers
+        || options.interactive
+        || parsed_args.has_command_flag("--amend")
+        || extract_fixup_from_args(&parsed_args.command_args).is_some()
+    {
+        return Vec::new();
+    }
+
+    let scope = match (options.pathspecs.is_empty(), options.include, options.all) {
+        (false, true, _) => CommitScope::Include(&options.pathspecs),
+        (false, false, _) => CommitScope::Only(&options.pathspecs),
+        (true, _, true) => CommitScope::All,
+        (true, _, false) => CommitScope::Index,
+    };
+    let stats = match staged_stats(repository, scope) {
+        Ok(stats) => stats,
+        Err(e) => {
+            debug_log(&format!(
+                "Failed to compute stats for commit trailers: {}",
+                e
+            ));
+            return Vec::new();
+        }
+    };
+
+    format_trailers(&stats, settings)
+        .into_iter()
+        .flat_map(|trailer| ["--trailer".to_string(), trailer])
+        .collect()
+}
+
+/// `AI-Assisted: 62%` and one `AI-Agent: cursor/claude-3.5` per agent, most lines first.
+/// Nothing when no agent wrote any of the lines.
+fn format_trailers(stats: &CommitStats, settings: &Settings) -> Vec<String> {
+    let mut agents: Vec<(String, u32)> = stats
+        .tool_model_breakdown
+        .iter()
+        .map(|(key, tool)| {
+            (
+                key.replacen("::", "/", 1),
+                tool.ai_additions + tool.mixed_additions,
+            )
+        })
+        .filter(|(_, lines)| *lines > 0)
+        .collect();
+    if agents.is_empty() {
+        return Vec::new();
+    }
+    agents.sort_by_key(|(_, lines)| std::cmp::Reverse(*lines));
+
+    let mut trailers = Vec::new();
+    if !settings.assisted_trailer.is_empty() {
+        let total = stats.counted_added_lines();
+        let ai = (stats.ai_additions + stats.mixed_additions).min(total);
+        let percent = if total > 0 {
+            (ai as f64 / total as f64 * 100.0).round() as u32
+        } else {
+            0
+        };
+        trailers.push(format!("{}: {}%", settings.assisted_trailer, percent));
+    }
+    if !settings.agent_trailer.is_empty() {
+        for (agent, _) in agents {
+            trailers.push(format!("{}: {}", settings.agent_trailer, agent));
+        }
+    }
+    trailers
+}
+
+/// Parse `--fixup=[(amend|reword):]<commit>` and `--squash=<commit>` (or their
+/// separate-value forms) into the fixup kind and the unresolved target commit.
+pub fn extract_fixup_from_args(args: &[String]) -> Option<(FixupKind, String)> {
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+
+        let (flag, value) = if let Some(value) = arg.strip_prefix("--fixup=") {
+            ("--fixup", Some(value.to_string()))
+        } else if let Some(value) = arg.strip_prefix("--squash=") {
+            ("--squash", Some(value.to_string()))
+        } else if arg == "--fixup" || arg == "--squash" {
+            (arg.as_str(), args.get(i + 1).cloned())
+        } else {
+            i += 1;
+            continue;
+        };
+
+        let value = value?;
+        if flag == "--squash" {
+            return Some((FixupKind::Squash, value));
+        }
+        if let Some(target) = value.strip_prefix("amend:") {
+            return Some((FixupKind::Amend, target.to_string()));
+        }
+        if let Some(target) = value.strip_prefix("reword:") {
+            return Some((FixupKind::Reword, target.to_string()));
+        }
+        return Some((FixupKind::Fixup, value));
+    }
+    None
+}
+
+/// Resolve a fixup target to a sha. HEAD has already moved to the new commit by the time
+/// the post-command hook runs, so HEAD-relative specs are resolved against the pre-command HEAD.
+fn resolve_fixup_target(
+    repository: &Repository,
+    target_spec: &str,
+    original_commit: &Option<String>,
+) -> Option<String> {
+    let spec = match (target_spec.strip_prefix("HEAD"), original_commit) {
+        (Some(rest), Some(original)) => format!("{}{}", original, rest),
+        _ => match (target_spec.strip_prefix('@'), original_commit) {
+            (Some(rest), Some(original)) if !rest.starts_with('{') => {
+                format!("{}{}", original, rest)
+            }
+            _ => target_spec.to_string(),
+        },
+    };
+    repository
+        .revparse_single(&format!("{}^{{commit}}", spec))
+        .ok()
+        .map(|obj| obj.id())
+}
+
 pub fn get_commit_default_author(repo: &Repository, args: &[String]) -> String {
     // According to git commit manual, --author flag overrides all other author information
     if let Some(author_spec) = extract_author_from_args(args) {
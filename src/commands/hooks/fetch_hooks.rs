@@ -1,18 +1,33 @@
+use crate::commands::backfill_squash::{recover_server_squashes, remote_branch_tips};
 use crate::commands::git_handlers::CommandHooksContext;
+use crate::error::GitAiError;
 use crate::git::cli_parser::{ParsedGitInvocation, is_dry_run};
 use crate::git::repository::{Repository, find_repository};
 use crate::git::sync_authorship::{fetch_authorship_notes, fetch_remote_from_args};
+use crate::runtime::{NETWORK_TIMEOUT, Task, spawn_blocking};
 use crate::utils::debug_log;
 
 pub fn fetch_pull_pre_command_hook(
     parsed_args: &ParsedGitInvocation,
     repository: &Repository,
-) -> Option<std::thread::JoinHandle<()>> {
+    command_hooks_context: &mut CommandHooksContext,
+) {
     // Early return for dry-run
     if is_dry_run(&parsed_args.command_args) {
-        return None;
+        return;
+    }
+
+    // Branches the fetch prunes may have been squash-merged on the server
+    command_hooks_context.remote_branches_before = Some(remote_branch_tips(repository));
+    if repository.settings().fetch_notes {
+        command_hooks_context.fetch_authorship_handle = spawn_notes_fetch(parsed_args, repository);
     }
+}
 
+fn spawn_notes_fetch(
+    parsed_args: &ParsedGitInvocation,
+    repository: &Repository,
+) -> Option<Task<Result<(), GitAiError>>> {
     // Extract the remote name
     let remote = match fetch_remote_from_args(repository, parsed_args) {
         Ok(remote) => remote,
@@ -22,36 +37,56 @@ pub fn fetch_pull_pre_command_hook(
         }
     };
 
-    // Clone what we need for the background thread
+    // Clone what we need for the background task
     let global_args = repository.global_args_for_exec();
 
-    // Spawn background thread to fetch authorship notes in parallel with main fetch
-    Some(std::thread::spawn(move || {
+    // Spawn a background task to fetch authorship notes in parallel with main fetch
+    Some(spawn_blocking(move || {
         debug_log(&format!(
             "started fetching authorship notes from remote: {}",
             remote
         ));
-        // Recreate repository in the background thread
-        if let Ok(repo) = find_repository(&global_args) {
-            if let Err(e) = fetch_authorship_notes(&repo, &remote) {
-                debug_log(&format!("authorship fetch failed: {}", e));
-            }
-        } else {
-            debug_log("failed to open repository for authorship fetch");
-        }
+        // Recreate repository in the background task
+        let repo = find_repository(&global_args)?;
+        fetch_authorship_notes(&repo, &remote).map_err(|e| {
+            GitAiError::Generic(format!(
+                "Authorship notes fetch from {} failed: {}",
+                remote, e
+            ))
+        })
     }))
 }
 
 pub fn fetch_pull_post_command_hook(
-    _repository: &Repository,
+    repository: &mut Repository,
     _parsed_args: &ParsedGitInvocation,
-    _exit_status: std::process::ExitStatus,
+    exit_status: std::process::ExitStatus,
     command_hooks_context: &mut CommandHooksContext,
 ) {
-    // Always wait for the authorship fetch thread to complete if it was started,
+    // Always wait for the authorship fetch to complete if it was started,
     // regardless of whether the main fetch/pull succeeded or failed.
-    // This ensures proper cleanup of the background thread.
-    if let Some(handle) = command_hooks_context.fetch_authorship_handle.take() {
-        let _ = handle.join();
+    // Give up on it if the remote stops responding.
+    // Failures never change the command's exit status; they're kept in its outcome.
+    if let Some(handle) = command_hooks_context.fetch_authorship_handle.take()
+        && let Err(e) = handle
+            .join_timeout(NETWORK_TIMEOUT)
+            .and_then(|result| result)
+    {
+        debug_log(&format!("authorship fetch: {}", e));
+        repository.hook_errors.push(e.to_string());
+    }
+
+    // The notes of the pruned branches' commits are fetched by now, so their squash commits'
+    // notes can be written from them
+    if exit_status.success()
+        && let Some(before) = command_hooks_context.remote_branches_before.take()
+    {
+        for (branch, squash_commit) in recover_server_squashes(repository, &before) {
+            eprintln!(
+                "git-ai: recovered authorship of {} for squash commit {}",
+                branch,
+                &squash_commit[..7.min(squash_commit.len())]
+            );
+        }
     }
 }
@@ -0,0 +1,36 @@
+use crate::git::cli_parser::{ParsedGitInvocation, is_dry_run};
+use crate::git::detached::unix_now;
+use crate::git::maintenance::{MaintenanceTask, run_tasks};
+use crate::git::repository::Repository;
+use crate::utils::debug_log;
+
+pub fn post_gc_hook(
+    parsed_args: &ParsedGitInvocation,
+    repository: &mut Repository,
+    exit_status: std::process::ExitStatus,
+) {
+    if !exit_status.success() || is_dry_run(&parsed_args.command_args) {
+        return;
+    }
+    run_maintenance(repository);
+}
+
+/// `git maintenance run` runs git-ai's tasks too. Other subcommands only change the schedule.
+pub fn post_maintenance_hook(
+    parsed_args: &ParsedGitInvocation,
+    repository: &mut Repository,
+    exit_status: std::process::ExitStatus,
+) {
+    if !exit_status.success() || parsed_args.command_args.first().map(String::as_str) != Some("run")
+    {
+        return;
+    }
+    run_maintenance(repository);
+}
+
+fn run_maintenance(repository: &mut Repository) {
+    match run_tasks(repository, &MaintenanceTask::ALL, unix_now()) {
+        Ok(summary) => debug_log(&format!("Ran git-ai maintenance: {:?}", summary)),
+        Err(e) => repository.hook_errors.push(e.to_string()),
+    }
+}
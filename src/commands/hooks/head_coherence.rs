@@ -0,0 +1,229 @@
+//! HEAD can move without git-ai seeing it: `git checkout` and `git stash` have no hooks, other
+//! tools write to the repository directly, and git-ai can crash mid-command. The working log
+//! stays keyed to the old base commit and silently stops applying to what's being committed.
+//!
+//! Every intercepted command that works on the working log records HEAD once it's done. Before
+//! the next one runs, HEAD is compared with that record, and a working log left on the old HEAD is carried over the same
+//! way the reset hooks carry it.
+
+use crate::authorship::attribution_loss::{
+    AttributionLoss, backfill_squash_command, committed_ai_lines, reset_loss, rev_list,
+};
+use crate::authorship::working_log::CheckpointKind;
+use crate::commands::checkpoint::CheckpointOptions;
+use crate::error::GitAiError;
+use crate::git::repository::{Repository, exec_git};
+use crate::utils::debug_log;
+
+/// How HEAD got from where the last intercepted command left it to where it is now
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum HeadMove {
+    /// Commits were added on top, e.g. committed by a tool that bypassed git-ai
+    Forward,
+    /// HEAD went back to an ancestor, like `git reset --soft` does
+    Backward,
+    /// HEAD is on another line of history, e.g. after a checkout of another branch
+    Diverged,
+}
+
+/// Bring the working log up to date with `head` before a command runs
+pub fn heal_working_log(repository: &mut Repository, head: Option<&str>) {
+    let Some(head) = head else {
+        return;
+    };
+    let Some(last_head) = repository.storage.read_last_head() else {
+        return;
+    };
+    if last_head == head || operation_in_progress(repository) {
+        return;
+    }
+
+    // Checkpoints made on the new HEAD since it moved already track the working directory
+    if checkpointed_since_record(repository, head) {
+        debug_log(&format!(
+            "HEAD moved from {} to {} outside git-ai, and has been checkpointed since",
+            last_head, head
+        ));
+        return;
+    }
+
+    let head_move = classify_move(repository, &last_head, head);
+    debug_log(&format!(
+        "HEAD moved from {} to {} outside git-ai ({:?})",
+        last_head, head, head_move
+    ));
+    if head_move == HeadMove::Diverged {
+        let loss = rewrite_outside_git_ai_loss(repository, &last_head, head);
+        repository.record_attribution_loss(loss);
+    }
+    let result = match head_move {
+        // The uncommitted changes are still in the working directory on top of the new HEAD.
+        // A working log the new HEAD has is left over from before, when a commit consumed it.
+        HeadMove::Forward | HeadMove::Diverged => {
+            match carry_working_log(repository, &last_head, head) {
+                Ok(false) => return,
+                result => result.map(|_| ()),
+            }
+        }
+        // The unwound commits' lines are uncommitted again, as after `git reset --soft`
+        HeadMove::Backward => {
+            let human_author =
+                crate::commands::hooks::commit_hooks::get_commit_default_author(repository, &[]);
+            // Capture the working directory against the old HEAD first, as the reset hook does
+            // before git moves HEAD
+            let _ = crate::commands::checkpoint::run(
+                repository,
+                &human_author,
+                CheckpointKind::Human,
+                None,
+                CheckpointOptions {
+                    quiet: true,
+                    base_commit: Some(&last_head),
+                    ..Default::default()
+                },
+            );
+            let result = crate::authorship::rebase_authorship::reconstruct_working_log_after_reset(
+                repository,
+                head,
+                &last_head,
+                &human_author,
+                None,
+            );
+            if let Err(e) = &result {
+                let loss = reset_loss(
+                    repository,
+                    "HEAD moved back outside git-ai",
+                    head,
+                    &last_head,
+                    e,
+                );
+                repository.record_attribution_loss(loss);
+            }
+            result
+        }
+    };
+    match result {
+        Ok(()) => debug_log(&format!("✓ Working log of {} now on {}", last_head, head)),
+        Err(e) => debug_log(&format!("✗ Failed to carry the working log over: {}", e)),
+    }
+}
+
+/// The branch was rewritten behind git-ai's back (an amend or rebase by another tool): its old
+/// tip is gone from every ref but still in the branch's reflog. The old commits keep their
+/// notes; the ones that replaced them have none.
+fn rewrite_outside_git_ai_loss(
+    repository: &Repository,
+    last_head: &str,
+    head: &str,
+) -> Option<AttributionLoss> {
+    let head_ref = repository.head().ok()?;
+    if !head_ref.is_branch() {
+        return None;
+    }
+    let branch = head_ref.name()?.to_string();
+    let mut args = repository.global_args_for_exec();
+    args.extend(["reflog", "show", "--format=%H", &branch].map(String::from));
+    let reflog = String::from_utf8(exec_git(&args).ok()?.stdout).ok()?;
+    if !reflog.lines().any(|sha| sha == last_head) {
+        return None;
+    }
+
+    let orphaned = rev_list(repository, &[last_head, "--not", "--all"]).ok()?;
+    if orphaned.is_empty() {
+        return None;
+    }
+    let exclude = format!("^{}", last_head);
+    let replacements = rev_list(repository, &[head, &exclude]).unwrap_or_default();
+    let recovery = match replacements.as_slice() {
+        [replacement] => Some(backfill_squash_command(replacement, last_head)),
+        _ => None,
+    };
+    AttributionLoss::new(
+        "history rewritten outside git-ai",
+        "the commits that replaced them have no authorship notes",
+        committed_ai_lines(repository, &orphaned),
+        recovery,
+    )
+}
+
+/// Move the working log of `from` onto `to`, replacing the one `to` has left over from when a
+/// commit consumed it. Returns false if `from` has no working log.
+pub fn carry_working_log(
+    repository: &Repository,
+    from: &str,
+    to: &str,
+) -> Result<bool, GitAiError> {
+    let working_log_bases = repository
+        .storage
+        .working_log_base_commits()
+        .unwrap_or_default();
+    if !working_log_bases.contains(from) {
+        return Ok(false);
+    }
+    repository.storage.delete_working_log_for_base_commit(to)?;
+    repository.storage.move_working_log(from, to)?;
+    Ok(true)
+}
+
+/// Remember `head` as this command left HEAD
+pub fn record_head(repository: &Repository, head: Option<&str>) {
+    let Some(head) = head else {
+        return;
+    };
+    if repository.storage.read_last_head().as_deref() == Some(head) {
+        return;
+    }
+    if let Err(e) = repository.storage.write_last_head(head) {
+        debug_log(&format!("Failed to record HEAD: {}", e));
+    }
+}
+
+/// Whether `head`'s working log was written after the last command recorded HEAD
+fn checkpointed_since_record(repository: &Repository, head: &str) -> bool {
+    let modified = |path: &std::path::Path| std::fs::metadata(path).and_then(|m| m.modified());
+    let checkpoints = repository
+        .storage
+        .working_logs
+        .join(head)
+        .join("checkpoints.jsonl");
+    match (
+        modified(&checkpoints),
+        modified(&repository.storage.last_head),
+    ) {
+        (Ok(checkpointed), Ok(recorded)) => checkpointed > recorded,
+        _ => false,
+    }
+}
+
+fn classify_move(repository: &Repository, from: &str, to: &str) -> HeadMove {
+    if is_ancestor(repository, from, to) {
+        HeadMove::Forward
+    } else if is_ancestor(repository, to, from) {
+        HeadMove::Backward
+    } else {
+        HeadMove::Diverged
+    }
+}
+
+/// A rebase, merge, cherry-pick or revert is stopped; its own hooks manage the working log
+pub fn operation_in_progress(repository: &Repository) -> bool {
+    let git_dir = repository.path();
+    [
+        "rebase-merge",
+        "rebase-apply",
+        "MERGE_HEAD",
+        "CHERRY_PICK_HEAD",
+        "REVERT_HEAD",
+    ]
+    .iter()
+    .any(|name| git_dir.join(name).exists())
+}
+
+fn is_ancestor(repository: &Repository, ancestor: &str, descendant: &str) -> bool {
+    let mut args = repository.global_args_for_exec();
+    args.push("merge-base".to_string());
+    args.push("--is-ancestor".to_string());
+    args.push(ancestor.to_string());
+    args.push(descendant.to_string());
+    exec_git(&args).is_ok()
+}
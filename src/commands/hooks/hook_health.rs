@@ -0,0 +1,132 @@
+use crate::git::repo_storage::HookTimings;
+use crate::git::repository::Repository;
+use crate::utils::debug_log;
+use std::time::Duration;
+
+/// Over-budget runs in a row before the user is warned (and a deferrable hook is deferred)
+pub const OVER_BUDGET_STREAK: u64 = 3;
+
+/// The only hook whose heavy work `git-ai process-queue` knows how to pick up
+pub const POST_COMMIT: &str = "post-commit";
+
+/// Count a run of `ms` against `budget_ms`. Returns true when this run completes a streak of
+/// [`OVER_BUDGET_STREAK`] over-budget runs. A budget of 0 turns the check off.
+fn record_run(timings: &mut HookTimings, ms: u64, budget_ms: u64) -> bool {
+    timings.runs += 1;
+    timings.last_ms = ms;
+    timings.max_ms = timings.max_ms.max(ms);
+    if budget_ms == 0 || ms <= budget_ms {
+        timings.over_budget_streak = 0;
+        return false;
+    }
+    timings.over_budget_runs += 1;
+    timings.over_budget_streak += 1;
+    timings.over_budget_streak == OVER_BUDGET_STREAK
+}
+
+/// Record how long `hook` (e.g. `post-commit`) took, warning once it keeps going over the
+/// `hook_budget_ms` setting. With `defer_slow_hooks` a slow post-commit is deferred from then on.
+pub fn record_hook_duration(repository: &Repository, hook: &str, duration: Duration) {
+    let settings = repository.settings();
+    let mut health = repository.storage.read_hook_health();
+    let timings = health.hooks.entry(hook.to_string()).or_default();
+    let ms = duration.as_millis() as u64;
+
+    if record_run(timings, ms, settings.hook_budget_ms) {
+        let over_budget = format!(
+            "git-ai: the {} hook took {}ms, over the {}ms budget {} times in a row",
+            hook, ms, settings.hook_budget_ms, OVER_BUDGET_STREAK
+        );
+        if hook != POST_COMMIT {
+            eprintln!("{}", over_budget);
+        } else if settings.defer_slow_hooks {
+            timings.deferred = true;
+            eprintln!(
+                "{}; attribution of new commits now runs in the background (git-ai process-queue)",
+                over_budget
+            );
+        } else if !timings.deferred {
+            eprintln!(
+                "{}; set defer_slow_hooks to run it in the background instead",
+                over_budget
+            );
+        }
+    }
+
+    if let Err(e) = repository.storage.write_hook_health(&health) {
+        debug_log(&format!("Failed to record hook timings: {}", e));
+    }
+}
+
+/// Whether `hook` should queue its work instead of doing it: always with `defer_post_commit`,
+/// otherwise once it went over budget. Turning `defer_slow_hooks` off brings it back inline
+/// right away.
+pub fn is_deferred(repository: &Repository, hook: &str) -> bool {
+    let settings = repository.settings();
+    if hook == POST_COMMIT && settings.defer_post_commit {
+        return true;
+    }
+    settings.defer_slow_hooks
+        && repository
+            .storage
+            .read_hook_health()
+            .hooks
+            .get(hook)
+            .is_some_and(|timings| timings.deferred)
+}
+
+/// Record how long a deferred run of `hook` took in the background. Once the work fits the
+/// budget again the hook goes back to running inline.
+pub fn record_deferred_run(repository: &Repository, hook: &str, duration: Duration) {
+    let budget_ms = repository.settings().hook_budget_ms;
+    let mut health = repository.storage.read_hook_health();
+    let Some(timings) = health.hooks.get_mut(hook) else {
+        return;
+    };
+    let ms = duration.as_millis() as u64;
+    if !timings.deferred || (budget_ms != 0 && ms > budget_ms) {
+        return;
+    }
+    timings.deferred = false;
+    debug_log(&format!(
+        "Deferred {} took {}ms, within the {}ms budget; running it inline again",
+        hook, ms, budget_ms
+    ));
+    if let Err(e) = repository.storage.write_hook_health(&health) {
+        debug_log(&format!("Failed to record hook timings: {}", e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_streak_of_over_budget_runs() {
+        let mut timings = HookTimings::default();
+        assert!(!record_run(&mut timings, 2500, 2000));
+        assert!(!record_run(&mut timings, 2600, 2000));
+        // A run within budget starts the streak over
+        assert!(!record_run(&mut timings, 100, 2000));
+        assert!(!record_run(&mut timings, 3000, 2000));
+        assert!(!record_run(&mut timings, 3000, 2000));
+        assert!(record_run(&mut timings, 4000, 2000));
+        // Only the run completing the streak reports it
+        assert!(!record_run(&mut timings, 4000, 2000));
+
+        assert_eq!(timings.runs, 7);
+        assert_eq!(timings.over_budget_runs, 6);
+        assert_eq!(timings.over_budget_streak, 4);
+        assert_eq!(timings.last_ms, 4000);
+        assert_eq!(timings.max_ms, 4000);
+    }
+
+    #[test]
+    fn test_zero_budget_never_reports() {
+        let mut timings = HookTimings::default();
+        for _ in 0..5 {
+            assert!(!record_run(&mut timings, 60_000, 0));
+        }
+        assert_eq!(timings.over_budget_runs, 0);
+    }
+}
@@ -1,17 +1,70 @@
 use crate::{
-    commands::hooks::commit_hooks::get_commit_default_author,
+    authorship::rebase_authorship::synthesize_merge_commit_note,
+    commands::{
+        git_handlers::CommandHooksContext,
+        hooks::commit_hooks::{self, get_commit_default_author},
+    },
     git::{
         cli_parser::{ParsedGitInvocation, is_dry_run},
         repository::Repository,
         rewrite_log::{MergeSquashEvent, RewriteLogEvent},
     },
+    utils::debug_log,
 };
 
+/// `git merge --continue` concludes a conflicted merge by committing it, so it needs the
+/// same pre-commit checkpoint that `git commit` gets.
+pub fn pre_merge_hook(
+    parsed_args: &ParsedGitInvocation,
+    repository: &mut Repository,
+    command_hooks_context: &mut CommandHooksContext,
+) {
+    if is_merge_continue(parsed_args, repository) {
+        debug_log("merge --continue with MERGE_HEAD present, running pre-commit hook");
+        command_hooks_context.pre_commit_hook_result = Some(commit_hooks::commit_pre_command_hook(
+            parsed_args,
+            repository,
+        ));
+    }
+}
+
 pub fn post_merge_hook(
     parsed_args: &ParsedGitInvocation,
     exit_status: std::process::ExitStatus,
     repository: &mut Repository,
+    command_hooks_context: &mut CommandHooksContext,
 ) {
+    // The pre-command hook only sets this for `merge --continue`
+    if command_hooks_context.pre_commit_hook_result.is_some() {
+        if repository.path().join("MERGE_HEAD").exists() {
+            debug_log("merge --continue did not conclude the merge, skipping post-commit hook");
+            return;
+        }
+        commit_hooks::commit_post_command_hook(
+            parsed_args,
+            exit_status,
+            repository,
+            command_hooks_context,
+        );
+        return;
+    }
+
+    // git commits clean merges itself, without a post-commit hook to write their note
+    if !parsed_args.has_command_flag("--squash")
+        && exit_status.success()
+        && !is_dry_run(&parsed_args.command_args)
+    {
+        let Ok(head_sha) = repository.head().and_then(|head| head.target()) else {
+            return;
+        };
+        match synthesize_merge_commit_note(repository, &head_sha) {
+            Ok(true) => debug_log(&format!("Wrote authorship note for merge {}", head_sha)),
+            Ok(false) => {}
+            Err(e) => debug_log(&format!("Failed to write merge commit note: {}", e)),
+        }
+        return;
+    }
+
     if parsed_args.has_command_flag("--squash")
         && exit_status.success()
         && !is_dry_run(&parsed_args.command_args)
@@ -40,6 +93,7 @@ pub fn post_merge_hook(
                 source_head_sha,
                 base_branch,
                 base_head,
+                None,
             )),
             commit_author,
             false,
@@ -47,3 +101,7 @@ pub fn post_merge_hook(
         );
     }
 }
+
+fn is_merge_continue(parsed_args: &ParsedGitInvocation, repository: &Repository) -> bool {
+    parsed_args.has_command_flag("--continue") && repository.path().join("MERGE_HEAD").exists()
+}
@@ -1,7 +1,13 @@
+pub mod am_hooks;
 pub mod cherry_pick_hooks;
+pub mod clean_hooks;
 pub mod commit_hooks;
 pub mod fetch_hooks;
+pub mod gc_hooks;
+pub mod head_coherence;
+pub mod hook_health;
 pub mod merge_hooks;
 pub mod push_hooks;
 pub mod rebase_hooks;
 pub mod reset_hooks;
+pub mod revert_hooks;
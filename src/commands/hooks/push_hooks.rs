@@ -1,15 +1,21 @@
+use crate::authorship::authorship_log::AttributionConfidence;
+use crate::commands::check::{check_commits, print_violations};
 use crate::commands::git_handlers::CommandHooksContext;
+use crate::config::NotesPushScope;
+use crate::error::GitAiError;
 use crate::git::cli_parser::{ParsedGitInvocation, is_dry_run};
-use crate::git::repository::{Repository, find_repository};
+use crate::git::repository::{Repository, exec_git, find_repository};
 use crate::git::sync_authorship::push_authorship_notes;
+use crate::runtime::{NETWORK_TIMEOUT, Task, spawn_blocking};
 use crate::utils::debug_log;
 
 pub fn push_pre_command_hook(
     parsed_args: &ParsedGitInvocation,
     repository: &Repository,
-) -> Option<std::thread::JoinHandle<()>> {
+) -> Option<Task<Result<(), GitAiError>>> {
     // Early returns for cases where we shouldn't push authorship notes
-    if is_dry_run(&parsed_args.command_args)
+    if !repository.settings().push_notes
+        || is_dry_run(&parsed_args.command_args)
         || parsed_args
             .command_args
             .iter()
@@ -19,6 +25,92 @@ pub fn push_pre_command_hook(
         return None;
     }
 
+    // Push authorship refs to the appropriate remote
+    let (remote, remote_names) = push_remote(parsed_args, repository);
+
+    if let Some(remote) = remote {
+        debug_log(&format!(
+            "started pushing authorship notes to remote: {}",
+            remote
+        ));
+        // Worked out before git updates the remote-tracking refs the range is measured from
+        let commits = match repository.settings().push_notes_scope {
+            NotesPushScope::Pushed => pushed_commits(
+                repository,
+                &parsed_args.command_args,
+                &remote,
+                &remote_names,
+            ),
+            NotesPushScope::All => None,
+        };
+        // Clone what we need for the background task
+        let global_args = repository.global_args_for_exec();
+
+        // Spawn a background task to push authorship notes in parallel with main push
+        Some(spawn_blocking(move || {
+            // Recreate repository in the background task
+            let repo = find_repository(&global_args)?;
+            push_authorship_notes(&repo, &remote, commits.as_deref()).map_err(|e| {
+                GitAiError::Generic(format!("Authorship notes push to {} failed: {}", remote, e))
+            })
+        }))
+    } else {
+        // No remotes configured; skip silently
+        debug_log("no remotes found for authorship push; skipping");
+        None
+    }
+}
+
+/// Refuse the push when a commit it sends breaks one of the `push_policies`. `--no-verify`
+/// skips the check, like git's own pre-push hook.
+pub fn enforce_push_policies(parsed_args: &ParsedGitInvocation, repository: &mut Repository) {
+    if repository.settings().push_policies.is_empty()
+        || parsed_args.has_command_flag("--no-verify")
+        || parsed_args
+            .command_args
+            .iter()
+            .any(|a| a == "-d" || a == "--delete" || a == "--mirror")
+    {
+        return;
+    }
+
+    let (Some(remote), remote_names) = push_remote(parsed_args, repository) else {
+        return;
+    };
+    let Some(commits) = pushed_commits(
+        repository,
+        &parsed_args.command_args,
+        &remote,
+        &remote_names,
+    ) else {
+        return;
+    };
+
+    let violations = match check_commits(repository, &commits, AttributionConfidence::Heuristic) {
+        Ok(violations) => violations,
+        Err(e) => {
+            debug_log(&format!("push policy check failed: {}", e));
+            repository
+                .hook_errors
+                .push(format!("Push policy check failed: {}", e));
+            return;
+        }
+    };
+    if violations.is_empty() {
+        return;
+    }
+
+    eprintln!("git-ai: refusing to push, commits break the repository's push policies:");
+    print_violations(repository, &violations);
+    eprintln!("git-ai: fix the commits, or push with --no-verify to skip the policies.");
+    std::process::exit(1);
+}
+
+/// The remote a push goes to, and the names of every remote
+fn push_remote(
+    parsed_args: &ParsedGitInvocation,
+    repository: &Repository,
+) -> (Option<String>, Vec<String>) {
     let remotes = repository.remotes().ok();
     let remote_names: Vec<String> = remotes
         .as_ref()
@@ -29,7 +121,6 @@ pub fn push_pre_command_hook(
         })
         .unwrap_or_default();
 
-    // Push authorship refs to the appropriate remote
     let positional_remote = extract_remote_from_push_args(&parsed_args.command_args, &remote_names);
 
     let specified_remote = positional_remote.or_else(|| {
@@ -43,45 +134,123 @@ pub fn push_pre_command_hook(
     let remote = specified_remote
         .or_else(|| repository.upstream_remote().ok().flatten())
         .or_else(|| repository.get_default_remote().ok().flatten());
-
-    if let Some(remote) = remote {
-        debug_log(&format!(
-            "started pushing authorship notes to remote: {}",
-            remote
-        ));
-        // Clone what we need for the background thread
-        let global_args = repository.global_args_for_exec();
-
-        // Spawn background thread to push authorship notes in parallel with main push
-        Some(std::thread::spawn(move || {
-            // Recreate repository in the background thread
-            if let Ok(repo) = find_repository(&global_args) {
-                if let Err(e) = push_authorship_notes(&repo, &remote) {
-                    debug_log(&format!("authorship push failed: {}", e));
-                }
-            } else {
-                debug_log("failed to open repository for authorship push");
-            }
-        }))
-    } else {
-        // No remotes configured; skip silently
-        debug_log("no remotes found for authorship push; skipping");
-        None
-    }
+    (remote, remote_names)
 }
 
 pub fn push_post_command_hook(
-    _repository: &Repository,
+    repository: &mut Repository,
     _parsed_args: &ParsedGitInvocation,
     _exit_status: std::process::ExitStatus,
     command_hooks_context: &mut CommandHooksContext,
 ) {
-    // Always wait for the authorship push thread to complete if it was started,
+    // Always wait for the authorship push to complete if it was started,
     // regardless of whether the main push succeeded or failed.
-    // This ensures proper cleanup of the background thread.
-    if let Some(handle) = command_hooks_context.push_authorship_handle.take() {
-        let _ = handle.join();
+    // A remote that stops responding shouldn't hang the user's command though.
+    // Failures never change the push's exit status; they're kept in its outcome.
+    if let Some(handle) = command_hooks_context.push_authorship_handle.take()
+        && let Err(e) = handle
+            .join_timeout(NETWORK_TIMEOUT)
+            .and_then(|result| result)
+    {
+        debug_log(&format!("authorship push: {}", e));
+        repository.hook_errors.push(e.to_string());
+    }
+}
+
+/// Commits the push sends that the remote-tracking refs of `remote` don't have, or `None` when
+/// the arguments don't say which (e.g. `--mirror`, `:`, globs, or no refspecs with
+/// `remote.<name>.push` or `push.default = matching` configured)
+fn pushed_commits(
+    repository: &Repository,
+    args: &[String],
+    remote: &str,
+    known_remotes: &[String],
+) -> Option<Vec<String>> {
+    if args.iter().any(|a| a == "--mirror") {
+        return None;
+    }
+    let mut sources = Vec::new();
+    if args.iter().any(|a| a == "--all" || a == "--branches") {
+        sources.push("--branches".to_string());
+    }
+    if args.iter().any(|a| a == "--tags") {
+        sources.push("--tags".to_string());
+    }
+    let refspecs = extract_refspecs_from_push_args(args);
+    for refspec in &refspecs {
+        let source = refspec
+            .trim_start_matches('+')
+            .split(':')
+            .next()
+            .unwrap_or("");
+        if refspec == ":" || source.contains('*') {
+            return None;
+        }
+        // An empty source deletes the destination; nothing is sent
+        if !source.is_empty() {
+            sources.push(source.to_string());
+        }
+    }
+    if sources.is_empty() {
+        if !refspecs.is_empty() {
+            return Some(Vec::new());
+        }
+        let configured = |key: &str| repository.config_get_str(key).ok().flatten();
+        if configured(&format!("remote.{}.push", remote)).is_some()
+            || configured("push.default").as_deref() == Some("matching")
+        {
+            return None;
+        }
+        sources.push("HEAD".to_string());
+    }
+
+    let mut rev_list = repository.global_args_for_exec();
+    rev_list.push("rev-list".to_string());
+    rev_list.extend(sources);
+    if known_remotes.iter().any(|r| r == remote) {
+        rev_list.push("--not".to_string());
+        rev_list.push(format!("--remotes={}", remote));
+    }
+    let output = exec_git(&rev_list).ok()?;
+    Some(
+        String::from_utf8(output.stdout)
+            .ok()?
+            .lines()
+            .map(str::to_string)
+            .collect(),
+    )
+}
+
+/// Positional arguments after the remote, or all of them when `--repo` names it
+fn extract_refspecs_from_push_args(args: &[String]) -> Vec<String> {
+    let mut positional = Vec::new();
+    let mut remote_in_option = false;
+    let mut i = 0;
+    while i < args.len() {
+        let arg = &args[i];
+        if arg == "--" {
+            positional.extend(args[i + 1..].iter().cloned());
+            break;
+        }
+        if arg.starts_with('-') {
+            if let Some((flag, _)) = is_push_option_with_inline_value(arg) {
+                remote_in_option |= flag == "--repo";
+                i += 1;
+            } else if option_consumes_separate_value(arg.as_str()) {
+                remote_in_option |= arg == "--repo";
+                i += 2;
+            } else {
+                i += 1;
+            }
+            continue;
+        }
+        positional.push(arg.clone());
+        i += 1;
+    }
+    if !remote_in_option && !positional.is_empty() {
+        positional.remove(0);
     }
+    positional
 }
 
 fn extract_remote_from_push_args(args: &[String], known_remotes: &[String]) -> Option<String> {
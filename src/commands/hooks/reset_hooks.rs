@@ -1,6 +1,9 @@
 use crate::{
-    authorship::working_log::CheckpointKind,
-    commands::hooks::commit_hooks,
+    authorship::{
+        attribution_loss::{AttributionLoss, reset_loss, uncommitted_ai_lines},
+        working_log::CheckpointKind,
+    },
+    commands::{checkpoint::CheckpointOptions, hooks::commit_hooks},
     git::{cli_parser::ParsedGitInvocation, repository::Repository, rewrite_log::ResetKind},
     utils::debug_log,
 };
@@ -15,10 +18,11 @@ pub fn pre_reset_hook(parsed_args: &ParsedGitInvocation, repository: &mut Reposi
         repository,
         &human_author,
         CheckpointKind::Human,
-        false,
-        false,
-        true,
         None,
+        CheckpointOptions {
+            quiet: true,
+            ..Default::default()
+        },
     );
 
     // Capture HEAD before reset happens
@@ -140,7 +144,17 @@ pub fn post_reset_hook(
 }
 
 /// Handle --hard reset: delete working log since all uncommitted work is discarded
-fn handle_reset_hard(repository: &Repository, old_head_sha: &str, _target_commit_sha: &str) {
+fn handle_reset_hard(repository: &mut Repository, old_head_sha: &str, _target_commit_sha: &str) {
+    if let Ok(discarded) = uncommitted_ai_lines(repository, old_head_sha) {
+        let loss = AttributionLoss::new(
+            "reset --hard",
+            "the working log was dropped with the uncommitted changes",
+            discarded,
+            None,
+        );
+        repository.record_attribution_loss(loss);
+    }
+
     // Delete working log for old HEAD - all uncommitted work is gone
     let _ = repository
         .storage
@@ -154,7 +168,7 @@ fn handle_reset_hard(repository: &Repository, old_head_sha: &str, _target_commit
 
 /// Handle --soft, --mixed, --merge: preserve working directory and reconstruct working log
 fn handle_reset_preserve_working_dir(
-    repository: &Repository,
+    repository: &mut Repository,
     old_head_sha: &str,
     target_commit_sha: &str,
     new_head_sha: &str,
@@ -209,6 +223,8 @@ fn handle_reset_preserve_working_dir(
                 "Failed to reconstruct working log after reset: {}",
                 e
             ));
+            let loss = reset_loss(repository, "reset", target_commit_sha, old_head_sha, &e);
+            repository.record_attribution_loss(loss);
         }
     }
 }
@@ -0,0 +1,201 @@
+use crate::commands::git_handlers::CommandHooksContext;
+use crate::commands::hooks::commit_hooks::get_commit_default_author;
+use crate::error::GitAiError;
+use crate::git::cli_parser::ParsedGitInvocation;
+use crate::git::repository::{Repository, exec_git};
+use crate::git::rewrite_log::{RevertMixedEvent, RewriteLogEvent};
+use crate::utils::debug_log;
+
+pub fn pre_revert_hook(
+    repository: &mut Repository,
+    command_hooks_context: &mut CommandHooksContext,
+) {
+    debug_log("=== REVERT PRE-COMMAND HOOK ===");
+
+    command_hooks_context.revert_original_head =
+        repository.head().ok().and_then(|head| head.target().ok());
+    command_hooks_context.revert_head = revert_head(repository);
+
+    debug_log(&format!(
+        "Revert starting from {:?} (REVERT_HEAD {:?})",
+        command_hooks_context.revert_original_head, command_hooks_context.revert_head
+    ));
+}
+
+pub fn post_revert_hook(
+    command_hooks_context: &CommandHooksContext,
+    parsed_args: &ParsedGitInvocation,
+    exit_status: std::process::ExitStatus,
+    repository: &mut Repository,
+) {
+    debug_log("=== REVERT POST-COMMAND HOOK ===");
+    debug_log(&format!("Exit status: {}", exit_status));
+
+    // Commits the revert made before it stopped (or all of them) still get their notes
+    let new_commits = match &command_hooks_context.revert_original_head {
+        Some(original_head) => new_commits_since(repository, original_head).unwrap_or_default(),
+        None => Vec::new(),
+    };
+    let commit_author = get_commit_default_author(repository, &parsed_args.command_args);
+    for (index, commit) in new_commits.iter().enumerate() {
+        // `--continue` concludes the pick REVERT_HEAD was stopped on with its first commit
+        let fallback = if index == 0 {
+            command_hooks_context.revert_head.as_deref()
+        } else {
+            None
+        };
+        log_completed_revert(repository, commit, fallback, commit_author.clone());
+    }
+
+    // Stopped on conflicts: record what's being reverted and the files in conflict
+    if let Some(reverted_commit) = revert_head(repository) {
+        debug_log(&format!(
+            "⏸ Revert of {} stopped on conflicts",
+            reverted_commit
+        ));
+        let conflicts = unmerged_files(repository).unwrap_or_default();
+        let event = RewriteLogEvent::revert_mixed(RevertMixedEvent::new(
+            reverted_commit,
+            false,
+            conflicts,
+            None,
+        ));
+        match repository.storage.append_rewrite_event(event) {
+            Ok(_) => debug_log("✓ Logged RevertMixed event"),
+            Err(e) => debug_log(&format!("✗ Failed to log RevertMixed event: {}", e)),
+        }
+    }
+}
+
+/// A conflicted revert can also be concluded with a plain `git commit`. The commit's note from
+/// the working log doesn't know the lines the revert brought back, so rewrite it.
+pub fn post_commit_concluding_revert(
+    parsed_args: &ParsedGitInvocation,
+    repository: &mut Repository,
+    reverted_commit: &str,
+) {
+    let Some(new_head) = repository.head().ok().and_then(|head| head.target().ok()) else {
+        return;
+    };
+    debug_log(&format!(
+        "Commit {} concluded revert of {}",
+        new_head, reverted_commit
+    ));
+    let commit_author = get_commit_default_author(repository, &parsed_args.command_args);
+    log_completed_revert(repository, &new_head, Some(reverted_commit), commit_author);
+}
+
+/// Log a revert commit and write its note. The reverted commit is read from the message git
+/// writes ("This reverts commit <sha>."), or `fallback` when the message was edited.
+fn log_completed_revert(
+    repository: &mut Repository,
+    commit: &str,
+    fallback: Option<&str>,
+    commit_author: String,
+) {
+    let reverted_commit = match reverted_commit_from_message(repository, commit) {
+        Some(reverted_commit) => reverted_commit,
+        None => match fallback {
+            Some(reverted_commit) => reverted_commit.to_string(),
+            None => {
+                debug_log(&format!(
+                    "Couldn't tell which commit {} reverts, leaving it",
+                    commit
+                ));
+                return;
+            }
+        },
+    };
+    let affected_files = changed_files(repository, commit).unwrap_or_default();
+
+    debug_log(&format!(
+        "✓ {} reverts {} ({} files)",
+        commit,
+        reverted_commit,
+        affected_files.len()
+    ));
+    repository.handle_rewrite_log_event(
+        RewriteLogEvent::revert_mixed(RevertMixedEvent::new(
+            reverted_commit,
+            true,
+            affected_files,
+            Some(commit.to_string()),
+        )),
+        commit_author,
+        false,
+        true,
+    );
+}
+
+/// The commit being reverted when a revert is stopped on conflicts
+fn revert_head(repository: &Repository) -> Option<String> {
+    let content = std::fs::read_to_string(repository.path().join("REVERT_HEAD")).ok()?;
+    let sha = content.trim();
+    (!sha.is_empty()).then(|| sha.to_string())
+}
+
+fn reverted_commit_from_message(repository: &Repository, commit: &str) -> Option<String> {
+    let mut args = repository.global_args_for_exec();
+    args.push("show".to_string());
+    args.push("-s".to_string());
+    args.push("--no-notes".to_string());
+    args.push("--format=%B".to_string());
+    args.push(commit.to_string());
+    let message = String::from_utf8(exec_git(&args).ok()?.stdout).ok()?;
+
+    let (_, rest) = message.split_once("This reverts commit ")?;
+    let sha = rest.split([',', '.', ' ', '\n']).next()?;
+    repository
+        .revparse_single(sha)
+        .ok()
+        .map(|commit| commit.id())
+}
+
+/// Commits made since `original_head`, oldest first
+fn new_commits_since(
+    repository: &Repository,
+    original_head: &str,
+) -> Result<Vec<String>, GitAiError> {
+    let mut args = repository.global_args_for_exec();
+    args.push("rev-list".to_string());
+    args.push("--reverse".to_string());
+    args.push(format!("{}..HEAD", original_head));
+    let output = exec_git(&args)?;
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .map(|line| line.trim().to_string())
+        .filter(|line| !line.is_empty())
+        .collect())
+}
+
+fn changed_files(repository: &Repository, commit: &str) -> Result<Vec<String>, GitAiError> {
+    let mut args = repository.global_args_for_exec();
+    args.push("diff-tree".to_string());
+    args.push("--no-commit-id".to_string());
+    args.push("--name-only".to_string());
+    args.push("-r".to_string());
+    args.push("-z".to_string());
+    args.push(commit.to_string());
+    let output = exec_git(&args)?;
+    Ok(String::from_utf8(output.stdout)?
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+fn unmerged_files(repository: &Repository) -> Result<Vec<String>, GitAiError> {
+    let mut args = repository.global_args_for_exec();
+    args.push("diff".to_string());
+    args.push("--name-only".to_string());
+    args.push("--diff-filter=U".to_string());
+    args.push("-z".to_string());
+    let output = exec_git(&args)?;
+    let mut files: Vec<String> = String::from_utf8(output.stdout)?
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect();
+    files.dedup();
+    Ok(files)
+}
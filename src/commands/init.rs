@@ -0,0 +1,265 @@
+use crate::authorship::working_log::CheckpointKind;
+use crate::commands::checkpoint::CheckpointOptions;
+use crate::commands::hooks::commit_hooks::get_commit_default_author;
+use crate::commands::install_hooks::get_current_binary_path;
+use crate::config::{self, REPO_CONFIG_FILE, Settings};
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::maintenance::{install_hook, pre_auto_gc_hook_path};
+use crate::git::repository::{Repository, exec_git};
+use std::fs;
+use std::path::PathBuf;
+
+/// What `git-ai init` should set up
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InitOptions {
+    /// Notes ref to record in `.gitai.toml` (default "ai")
+    pub notes_ref: Option<String>,
+    pub exclude_paths: Vec<String>,
+    /// Add git-ai maintenance to the `pre-auto-gc` hook
+    pub hooks: bool,
+    pub checkpoint: bool,
+    /// Replace an existing `.gitai.toml` with the starter one
+    pub force: bool,
+}
+
+/// What a `git-ai init` run did
+#[derive(Debug, Clone, PartialEq)]
+pub struct InitReport {
+    pub config_file: PathBuf,
+    pub config: ConfigFileAction,
+    pub notes_ref: String,
+    /// The hook path, and whether git-ai was added to it (false if it already was)
+    pub hook: Option<(PathBuf, bool)>,
+    /// Files the smoke checkpoint looked at
+    pub checkpoint_files: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFileAction {
+    Created,
+    Replaced,
+    /// The existing file was kept, with only the settings given on the command line changed
+    Updated,
+    Kept,
+}
+
+pub fn handle_init(args: &[String]) {
+    let mut options = InitOptions {
+        checkpoint: true,
+        ..Default::default()
+    };
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--notes-ref" if i + 1 < args.len() => {
+                options.notes_ref = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--exclude" if i + 1 < args.len() => {
+                options.exclude_paths.push(args[i + 1].clone());
+                i += 2;
+            }
+            "--hooks" => {
+                options.hooks = true;
+                i += 1;
+            }
+            "--no-checkpoint" => {
+                options.checkpoint = false;
+                i += 1;
+            }
+            "--force" => {
+                options.force = true;
+                i += 1;
+            }
+            _ => {
+                eprintln!("Unknown init argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {} (run `git init` first)", e);
+            std::process::exit(1);
+        }
+    };
+
+    match init(&repo, &options) {
+        Ok(report) => print_report(&report),
+        Err(e) => {
+            eprintln!("Init failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Write the starter `.gitai.toml`, install the optional hooks, then run a checkpoint to check
+/// that attribution works in the repository
+pub fn init(repo: &Repository, options: &InitOptions) -> Result<InitReport, GitAiError> {
+    let workdir = repo.workdir().map_err(|_| {
+        GitAiError::Generic("git-ai init needs a repository with a working tree".to_string())
+    })?;
+    let notes_ref = match &options.notes_ref {
+        Some(name) => Some(validate_notes_ref(repo, name)?),
+        None => None,
+    };
+
+    let config_file = workdir.join(REPO_CONFIG_FILE);
+    let exists = config_file.exists();
+    let config = if !exists || options.force {
+        fs::write(
+            &config_file,
+            starter_config(
+                notes_ref
+                    .as_deref()
+                    .unwrap_or(&Settings::default().notes_ref),
+                &options.exclude_paths,
+            ),
+        )?;
+        if exists {
+            ConfigFileAction::Replaced
+        } else {
+            ConfigFileAction::Created
+        }
+    } else if notes_ref.is_some() || !options.exclude_paths.is_empty() {
+        if let Some(name) = &notes_ref {
+            config::write_toml_setting(
+                &config_file,
+                "notes_ref",
+                toml::Value::from(name.as_str()),
+            )?;
+        }
+        if !options.exclude_paths.is_empty() {
+            config::write_toml_setting(
+                &config_file,
+                "exclude_paths",
+                toml::Value::from(options.exclude_paths.clone()),
+            )?;
+        }
+        ConfigFileAction::Updated
+    } else {
+        ConfigFileAction::Kept
+    };
+    // Read back what the repository now resolves to, so a broken file fails here and not on
+    // the next commit
+    let settings = Settings::resolve(Some(&workdir))?.settings;
+
+    let hook = if options.hooks {
+        let hook_path = pre_auto_gc_hook_path(repo)?;
+        let added = install_hook(&hook_path, &get_current_binary_path()?)?;
+        if repo.config_get_bool("maintenance.gc.enabled")?.is_none() {
+            repo.config_set_str("maintenance.gc.enabled", "true")?;
+        }
+        Some((hook_path, added))
+    } else {
+        None
+    };
+
+    let checkpoint_files = if options.checkpoint {
+        let author = get_commit_default_author(repo, &[]);
+        let (_, files, _) = crate::commands::checkpoint::run(
+            repo,
+            &author,
+            CheckpointKind::Human,
+            None,
+            CheckpointOptions {
+                quiet: true,
+                ..Default::default()
+            },
+        )
+        .map_err(|e| GitAiError::Generic(format!("Smoke checkpoint failed: {}", e)))?;
+        Some(files)
+    } else {
+        None
+    };
+
+    Ok(InitReport {
+        config_file,
+        config,
+        notes_ref: format!("refs/notes/{}", settings.notes_ref_name()),
+        hook,
+        checkpoint_files,
+    })
+}
+
+/// The notes ref name without refs/notes/, if git accepts it as a ref
+fn validate_notes_ref(repo: &Repository, name: &str) -> Result<String, GitAiError> {
+    let name = name.strip_prefix("refs/notes/").unwrap_or(name);
+    let mut args = repo.global_args_for_exec();
+    args.push("check-ref-format".to_string());
+    args.push(format!("refs/notes/{}", name));
+    if exec_git(&args).is_err() {
+        return Err(GitAiError::Generic(format!("Invalid notes ref: {}", name)));
+    }
+    Ok(name.to_string())
+}
+
+/// A commented `.gitai.toml` with the settings teams usually decide on first
+fn starter_config(notes_ref: &str, exclude_paths: &[String]) -> String {
+    format!(
+        r#"# git-ai settings shared by everyone working in this repository.
+# `git-ai config --show-origin` shows what's in effect and where each value comes from.
+
+# Notes ref authorship is stored in, relative to refs/notes/
+notes_ref = {}
+
+# Files left out of attribution
+exclude_paths = {}
+
+# Files counted apart from the AI/human split, on top of lockfiles and vendored code
+generated_paths = []
+
+# Refuse `git clean` when it would delete files with uncommitted AI attributions
+confirm_clean = true
+
+# Keep prompt records in notes but drop their transcripts
+# ignore_prompts = true
+
+# Store only a summary of each agent transcript
+# transcript_mode = "summary"
+
+# Keep no transcript text at all, only each message's SHA-256 digest
+# transcript_store = "hashes_only"
+
+# Never attribute work on these branches
+# ignore_branches = ["dependabot/*"]
+"#,
+        toml::Value::from(notes_ref),
+        toml::Value::from(exclude_paths.to_vec()),
+    )
+}
+
+fn print_report(report: &InitReport) {
+    let action = match report.config {
+        ConfigFileAction::Created => "Wrote",
+        ConfigFileAction::Replaced => "Replaced",
+        ConfigFileAction::Updated => "Updated",
+        ConfigFileAction::Kept => "Kept existing",
+    };
+    println!("{} {}", action, report.config_file.display());
+    println!("Authorship notes go to {}", report.notes_ref);
+    match &report.hook {
+        Some((hook_path, true)) => println!("Added git-ai maintenance to {}", hook_path.display()),
+        Some((hook_path, false)) => {
+            println!("git-ai maintenance is already in {}", hook_path.display())
+        }
+        None => {}
+    }
+    if let Some(files) = report.checkpoint_files {
+        println!(
+            "Checkpoint ok ({} changed file{})",
+            files,
+            if files == 1 { "" } else { "s" }
+        );
+    }
+    if matches!(
+        report.config,
+        ConfigFileAction::Created | ConfigFileAction::Replaced | ConfigFileAction::Updated
+    ) {
+        println!("Commit {} so your team picks it up", REPO_CONFIG_FILE);
+    }
+}
@@ -0,0 +1,364 @@
+use crate::commands::install_hooks::get_current_binary_path;
+use crate::config::{self, Config};
+use crate::error::GitAiError;
+use crate::git::backend::GIT_BACKEND_ENV;
+use crate::git::find_repository;
+use crate::git::refs::tracking_ref_for_remote;
+use crate::git::repository::{Repository, exec_git};
+use crate::git::sync_authorship::remote_has_notes;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// How `git` reaches git-ai in the user's shell
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellSetup {
+    /// Put `~/.git-ai/bin`, which holds a `git` shim, first on PATH
+    Path,
+    /// Alias `git` to the shim, leaving PATH alone
+    Alias,
+}
+
+/// One `git-ai doctor` check
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub name: String,
+    pub ok: bool,
+    pub detail: String,
+}
+
+pub fn handle_install(args: &[String]) {
+    let mut setup = ShellSetup::Path;
+    let mut dry_run = false;
+    let mut check_only = false;
+
+    for arg in args {
+        match arg.as_str() {
+            "--alias" => setup = ShellSetup::Alias,
+            "--dry-run" => dry_run = true,
+            "--check" => check_only = true,
+            _ => {
+                eprintln!("Unknown install argument: {}", arg);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if !check_only {
+        if let Err(e) = install(setup, dry_run) {
+            eprintln!("Install failed: {}", e);
+            std::process::exit(1);
+        }
+        if dry_run {
+            return;
+        }
+        println!();
+    }
+
+    let checks = run_checks();
+    for check in &checks {
+        println!(
+            "{} {}: {}",
+            if check.ok { "✓" } else { "✗" },
+            check.name,
+            check.detail
+        );
+    }
+    // Right after an install the shell hasn't picked up the new PATH yet, so only a
+    // checks-only run fails on them
+    if check_only && checks.iter().any(|check| !check.ok) {
+        std::process::exit(1);
+    }
+}
+
+pub fn handle_doctor(args: &[String]) {
+    if let Some(arg) = args.first() {
+        eprintln!("Unknown doctor argument: {}", arg);
+        std::process::exit(1);
+    }
+    handle_install(&["--check".to_string()]);
+}
+
+fn install(setup: ShellSetup, dry_run: bool) -> Result<(), GitAiError> {
+    let bin_dir = bin_dir()?;
+    let binary = get_current_binary_path()?;
+    let real_git = real_git_path()?;
+
+    let shim = bin_dir.join("git");
+    if fs::read_link(&shim).ok().as_deref() != Some(binary.as_path()) {
+        println!("Linking {} -> {}", shim.display(), binary.display());
+        if !dry_run {
+            fs::create_dir_all(&bin_dir)?;
+            replace_symlink(&binary, &shim)?;
+        }
+    }
+    let git_og = bin_dir.join("git-og");
+    if fs::read_link(&git_og).ok().as_deref() != Some(Path::new(&real_git)) {
+        println!("Linking {} -> {}", git_og.display(), real_git);
+        if !dry_run {
+            fs::create_dir_all(&bin_dir)?;
+            replace_symlink(Path::new(&real_git), &git_og)?;
+        }
+    }
+
+    if save_git_path(&real_git, dry_run)? {
+        println!("Saved git_path {} to config.json", real_git);
+    }
+
+    match shell_rc_file() {
+        Some((rc, fish)) => {
+            let line = shell_line(setup, &bin_dir, fish);
+            let existing = fs::read_to_string(&rc).unwrap_or_default();
+            if !existing.lines().any(|l| l.trim() == line) {
+                println!("Adding to {}: {}", rc.display(), line);
+                if !dry_run {
+                    if let Some(parent) = rc.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    let mut file = fs::OpenOptions::new().create(true).append(true).open(&rc)?;
+                    writeln!(file, "\n# Added by git-ai install\n{}", line)?;
+                }
+            }
+        }
+        None => {
+            println!(
+                "Could not detect your shell config file. Add this line to it:\n  {}",
+                shell_line(setup, &bin_dir, false)
+            );
+        }
+    }
+
+    if let Ok(repo) = find_repository(&Vec::<String>::new()) {
+        for (remote, refspec) in missing_notes_refspecs(&repo)? {
+            println!("Adding fetch refspec {} to remote {}", refspec, remote);
+            if !dry_run {
+                let mut args = repo.global_args_for_exec();
+                args.push("config".to_string());
+                args.push("--add".to_string());
+                args.push(format!("remote.{}.fetch", remote));
+                args.push(refspec);
+                exec_git(&args)?;
+            }
+        }
+    }
+
+    if dry_run {
+        println!("Dry run: nothing was changed");
+    } else {
+        println!("Restart your shell to start using git-ai");
+    }
+    Ok(())
+}
+
+/// `~/.git-ai/bin`, where install.sh puts git-ai and the `git` shim
+fn bin_dir() -> Result<PathBuf, GitAiError> {
+    config::git_ai_dir()
+        .map(|dir| dir.join("bin"))
+        .ok_or_else(|| GitAiError::Generic("Could not determine the home directory".to_string()))
+}
+
+/// The git that git-ai forwards to. Never the shim itself, or every git command would loop.
+fn real_git_path() -> Result<String, GitAiError> {
+    let git = Config::get().git_cmd().to_string();
+    if git.contains(".git-ai") {
+        return Err(GitAiError::Generic(format!(
+            "git_path {} points at the git-ai shim; set it to your real git",
+            git
+        )));
+    }
+    Ok(git)
+}
+
+#[cfg(unix)]
+fn replace_symlink(target: &Path, link: &Path) -> Result<(), GitAiError> {
+    if fs::symlink_metadata(link).is_ok() {
+        fs::remove_file(link)?;
+    }
+    std::os::unix::fs::symlink(target, link)?;
+    Ok(())
+}
+
+#[cfg(windows)]
+fn replace_symlink(_target: &Path, _link: &Path) -> Result<(), GitAiError> {
+    Err(GitAiError::Generic(
+        "git-ai install sets up a shim on macOS and Linux; use install.ps1 on Windows".to_string(),
+    ))
+}
+
+/// Record the real git in config.json when it has no usable `git_path` yet. Returns whether
+/// it would write.
+fn save_git_path(real_git: &str, dry_run: bool) -> Result<bool, GitAiError> {
+    let Some(path) = config::config_file_path() else {
+        return Ok(false);
+    };
+    let mut json: serde_json::Value = fs::read_to_string(&path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_else(|| serde_json::json!({}));
+    if json.get("git_path").and_then(|v| v.as_str()) == Some(real_git) {
+        return Ok(false);
+    }
+    let Some(object) = json.as_object_mut() else {
+        return Ok(false);
+    };
+    object.insert("git_path".to_string(), real_git.into());
+    if !dry_run {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(&json)?)?;
+    }
+    Ok(true)
+}
+
+/// The rc file of the user's shell, and whether it's fish's: by $SHELL, else whichever rc file
+/// exists, as install.sh picks it
+fn shell_rc_file() -> Option<(PathBuf, bool)> {
+    let home = PathBuf::from(std::env::var("HOME").ok()?);
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    match Path::new(&shell).file_name().and_then(|name| name.to_str()) {
+        Some("zsh") => return Some((home.join(".zshrc"), false)),
+        Some("bash") => return Some((home.join(".bashrc"), false)),
+        Some("fish") => return Some((home.join(".config/fish/config.fish"), true)),
+        _ => {}
+    }
+    if home.join(".zshrc").exists() {
+        Some((home.join(".zshrc"), false))
+    } else if home.join(".bashrc").exists() || home.join(".bash_profile").exists() {
+        Some((home.join(".bashrc"), false))
+    } else {
+        None
+    }
+}
+
+fn shell_line(setup: ShellSetup, bin_dir: &Path, fish: bool) -> String {
+    let bin_dir = bin_dir.display();
+    match (setup, fish) {
+        (ShellSetup::Path, false) => format!("export PATH=\"{}:$PATH\"", bin_dir),
+        (ShellSetup::Path, true) => format!("fish_add_path {}", bin_dir),
+        (ShellSetup::Alias, false) => format!("alias git=\"{}/git\"", bin_dir),
+        (ShellSetup::Alias, true) => format!("alias git {}/git", bin_dir),
+    }
+}
+
+/// Remotes that have authorship notes but don't fetch them into their tracking ref yet, with the
+/// refspec each needs. A remote without notes is left alone: git fails a fetch whose exact
+/// refspec has no match, and the fetch hook brings its notes once they exist.
+fn missing_notes_refspecs(repo: &Repository) -> Result<Vec<(String, String)>, GitAiError> {
+    let mut missing = Vec::new();
+    for remote in repo.remotes()?.into_iter().filter(|r| !r.is_empty()) {
+        if !remote_has_notes(repo, &remote).unwrap_or(false) {
+            continue;
+        }
+        let refspec = format!(
+            "+{}:{}",
+            repo.notes_ref(),
+            tracking_ref_for_remote(repo, &remote)
+        );
+        let mut args = repo.global_args_for_exec();
+        args.push("config".to_string());
+        args.push("--get-all".to_string());
+        args.push(format!("remote.{}.fetch", remote));
+        let existing = exec_git(&args)
+            .ok()
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .unwrap_or_default();
+        if !existing.lines().any(|line| line.trim() == refspec) {
+            missing.push((remote, refspec));
+        }
+    }
+    Ok(missing)
+}
+
+/// Whether git-ai is wired up: the real git runs, the shim points at this binary, `git` in the
+/// shell resolves to it, and the current repo's remotes fetch notes. Also reports which backend
+/// serves the current repo's object reads.
+pub fn run_checks() -> Vec<Check> {
+    let mut checks = Vec::new();
+    let check = |name: &str, result: Result<String, String>| Check {
+        name: name.to_string(),
+        ok: result.is_ok(),
+        detail: result.unwrap_or_else(|e| e),
+    };
+
+    let git = Config::get().git_cmd().to_string();
+    checks.push(check(
+        "git",
+        match Command::new(&git).arg("--version").output() {
+            Ok(output) if output.status.success() => Ok(format!(
+                "{} ({})",
+                String::from_utf8_lossy(&output.stdout).trim(),
+                git
+            )),
+            _ => Err(format!("{} does not run; set git_path in config.json", git)),
+        },
+    ));
+
+    let shim = bin_dir().map(|dir| dir.join("git"));
+    let binary = get_current_binary_path().ok();
+    checks.push(check(
+        "shim",
+        match &shim {
+            Ok(shim) if fs::canonicalize(shim).ok() == binary => Ok(shim.display().to_string()),
+            Ok(shim) => Err(format!(
+                "{} is missing or not linked to this git-ai; run git-ai install",
+                shim.display()
+            )),
+            Err(e) => Err(e.to_string()),
+        },
+    ));
+
+    checks.push(check(
+        "PATH",
+        match (first_git_on_path(), &shim) {
+            (Some(found), Ok(shim)) if found == *shim => Ok(format!("git is {}", found.display())),
+            (Some(found), _) => Err(format!(
+                "git is {}; restart your shell, or use git-ai install --alias",
+                found.display()
+            )),
+            (None, _) => Err("no git on PATH".to_string()),
+        },
+    ));
+
+    if let Ok(repo) = find_repository(&Vec::<String>::new()) {
+        checks.push(check(
+            "notes refspecs",
+            match missing_notes_refspecs(&repo) {
+                Ok(missing) if missing.is_empty() => {
+                    Ok(format!("remotes with notes fetch {}", repo.notes_ref()))
+                }
+                Ok(missing) => Err(format!(
+                    "{} don't fetch {}; run git-ai install",
+                    missing
+                        .iter()
+                        .map(|(remote, _)| remote.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    repo.notes_ref()
+                )),
+                Err(e) => Err(e.to_string()),
+            },
+        ));
+
+        let backend = match std::env::var(GIT_BACKEND_ENV) {
+            Ok(forced) => format!(
+                "object reads use {} ({}={})",
+                repo.git_backend_name(),
+                GIT_BACKEND_ENV,
+                forced
+            ),
+            Err(_) => format!("object reads use {}", repo.git_backend_name()),
+        };
+        checks.push(check("git backend", Ok(backend)));
+    }
+
+    checks
+}
+
+fn first_git_on_path() -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .map(|dir| dir.join(if cfg!(windows) { "git.exe" } else { "git" }))
+        .find(|candidate| candidate.is_file())
+}
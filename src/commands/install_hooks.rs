@@ -1089,7 +1089,7 @@ fn update_git_path_setting(
 }
 
 /// Get the absolute path to the currently running binary
-fn get_current_binary_path() -> Result<PathBuf, GitAiError> {
+pub fn get_current_binary_path() -> Result<PathBuf, GitAiError> {
     let path = std::env::current_exe()?;
 
     // Canonicalize to resolve any symlinks
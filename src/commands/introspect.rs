@@ -0,0 +1,444 @@
+use crate::authorship::authorship_log_serialization::AUTHORSHIP_LOG_VERSION;
+use crate::config::{self, ConfigOrigin, SETTINGS_KEYS, Settings};
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::repo_registry;
+use crate::git::repository::Repository;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::PathBuf;
+
+/// Version of the introspection JSON. Bumped when a field is removed or changes meaning;
+/// new fields don't bump it, so clients should ignore fields they don't know.
+pub const INTROSPECT_SCHEMA_VERSION: u32 = 1;
+
+/// Everything a GUI client needs to integrate with this git-ai without parsing CLI output
+#[derive(Debug, Clone, Serialize)]
+pub struct Introspection {
+    pub schema_version: u32,
+    pub version: &'static str,
+    pub authorship_log_version: &'static str,
+    /// Optional parts of the authorship note format this version writes
+    pub capabilities: Vec<&'static str>,
+    pub commands: Vec<CommandInfo>,
+    /// Presets accepted by `git-ai checkpoint <preset>`
+    pub checkpoint_presets: Vec<&'static str>,
+    pub settings: Vec<SettingInfo>,
+    pub storage: StorageLocations,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandInfo {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Accepts `--json` for machine-readable output
+    pub json: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SettingInfo {
+    pub key: &'static str,
+    /// `boolean`, `number`, `string`, `string[]` or `map`
+    #[serde(rename = "type")]
+    pub value_type: &'static str,
+    /// The accepted values, for settings that only take a few
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub values: Option<&'static [&'static str]>,
+    pub default: Value,
+    /// The effective value for the current repository
+    pub value: Value,
+    /// Where `value` came from, as `git-ai config --show-origin` prints it
+    pub origin: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageLocations {
+    pub user_config: Option<PathBuf>,
+    pub global_config: Option<PathBuf>,
+    pub system_config: Option<PathBuf>,
+    pub repo_registry: Option<PathBuf>,
+    /// Absent outside a repository
+    pub repository: Option<RepositoryStorage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RepositoryStorage {
+    pub workdir: Option<PathBuf>,
+    /// Full name of the notes ref authorship is written to
+    pub notes_ref: String,
+    pub working_logs: PathBuf,
+    pub rewrite_log: PathBuf,
+    pub transcripts: PathBuf,
+    pub last_outcome: PathBuf,
+    /// Commits waiting for `process-queue`, one file each
+    pub queue: PathBuf,
+    pub repo_config: Option<PathBuf>,
+    pub ignore_file: Option<PathBuf>,
+}
+
+const COMMANDS: &[CommandInfo] = &[
+    CommandInfo {
+        name: "checkpoint",
+        description: "Checkpoint working changes and attribute author",
+        json: false,
+    },
+    CommandInfo {
+        name: "watch",
+        description: "Checkpoint changes as files are saved, AI while an agent session is active",
+        json: false,
+    },
+    CommandInfo {
+        name: "blame",
+        description: "Git blame with AI authorship overlay",
+        json: false,
+    },
+    CommandInfo {
+        name: "explain-line",
+        description: "Explain who wrote a line and show the prompt behind it",
+        json: false,
+    },
+    CommandInfo {
+        name: "show-prompt",
+        description: "Show the transcript of the prompt behind a commit or line",
+        json: true,
+    },
+    CommandInfo {
+        name: "conflicts",
+        description: "Which side of each conflict is AI-authored, and by which prompt",
+        json: true,
+    },
+    CommandInfo {
+        name: "stats",
+        description: "Show AI authorship statistics for a commit",
+        json: true,
+    },
+    CommandInfo {
+        name: "me",
+        description: "Your AI authorship stats across all repos git-ai has touched",
+        json: true,
+    },
+    CommandInfo {
+        name: "ownership",
+        description: "AI vs human share of a file's or directory's current lines",
+        json: true,
+    },
+    CommandInfo {
+        name: "release-note",
+        description: "AI share of a release, stored as a note on the tag",
+        json: true,
+    },
+    CommandInfo {
+        name: "retention",
+        description: "How many AI lines from each commit survive later commits",
+        json: true,
+    },
+    CommandInfo {
+        name: "sessions",
+        description: "Agent sessions across commits, with lines produced and retained",
+        json: true,
+    },
+    CommandInfo {
+        name: "prompt-segment",
+        description: "Pending AI lines and HEAD's AI share for shell prompts, cached",
+        json: true,
+    },
+    CommandInfo {
+        name: "log",
+        description: "git log --stat with each file's AI share",
+        json: true,
+    },
+    CommandInfo {
+        name: "ui",
+        description: "Browse commits, files and prompts with AI lines highlighted, in the terminal",
+        json: false,
+    },
+    CommandInfo {
+        name: "metrics",
+        description: "Aggregate AI usage metrics as JSON for org-level rollups",
+        json: true,
+    },
+    CommandInfo {
+        name: "range-diff",
+        description: "Compare attribution of rewritten commits, like git range-diff",
+        json: true,
+    },
+    CommandInfo {
+        name: "stats-delta",
+        description: "Generate authorship logs for children of commits with working logs",
+        json: true,
+    },
+    CommandInfo {
+        name: "backfill",
+        description: "Write authorship notes for commits that don't have one",
+        json: false,
+    },
+    CommandInfo {
+        name: "backfill-squash",
+        description: "Write the note of a branch squash-merged on the server",
+        json: false,
+    },
+    CommandInfo {
+        name: "init",
+        description: "Set up git-ai in a repository: .gitai.toml, hooks, a checkpoint",
+        json: false,
+    },
+    CommandInfo {
+        name: "install",
+        description: "Route git through git-ai and fetch notes from this repo's remotes",
+        json: false,
+    },
+    CommandInfo {
+        name: "doctor",
+        description: "Check that git, the shim, PATH and notes refspecs are set up",
+        json: false,
+    },
+    CommandInfo {
+        name: "install-hooks",
+        description: "Install git hooks for AI authorship tracking",
+        json: false,
+    },
+    CommandInfo {
+        name: "last-outcome",
+        description: "Print what git-ai did for the last git command it proxied",
+        json: false,
+    },
+    CommandInfo {
+        name: "config",
+        description: "Print the effective repo settings",
+        json: false,
+    },
+    CommandInfo {
+        name: "introspect",
+        description: "Describe this git-ai (commands, settings, storage) as JSON",
+        json: false,
+    },
+    CommandInfo {
+        name: "ci",
+        description: "Continuous integration utilities",
+        json: false,
+    },
+    CommandInfo {
+        name: "jj",
+        description: "Jujutsu (jj) colocated repo utilities",
+        json: false,
+    },
+    CommandInfo {
+        name: "prune-detached",
+        description: "Drop notes of detached HEAD commits that never reached a ref",
+        json: false,
+    },
+    CommandInfo {
+        name: "migrate",
+        description: "Move notes to another ref or storage to another layout",
+        json: false,
+    },
+    CommandInfo {
+        name: "check",
+        description: "Hold commits to the repository's push policies",
+        json: true,
+    },
+    CommandInfo {
+        name: "redact",
+        description: "Strip transcripts from existing notes, keeping attribution and prompt hashes",
+        json: false,
+    },
+    CommandInfo {
+        name: "compat",
+        description: "Report the schema versions of authorship notes and whether this git-ai reads them",
+        json: true,
+    },
+    CommandInfo {
+        name: "gc",
+        description: "Reclaim space in .git/ai: orphaned working logs, blobs, rewrite log",
+        json: false,
+    },
+    CommandInfo {
+        name: "maintenance",
+        description: "Housekeeping of .git/ai, run from git's auto maintenance",
+        json: false,
+    },
+    CommandInfo {
+        name: "process-queue",
+        description: "Attribute commits whose post-commit work was deferred",
+        json: false,
+    },
+    CommandInfo {
+        name: "squash-authorship",
+        description: "Generate authorship from squashed commits",
+        json: false,
+    },
+    CommandInfo {
+        name: "git-path",
+        description: "Print the path to the underlying git executable",
+        json: false,
+    },
+    CommandInfo {
+        name: "version",
+        description: "Print the git-ai version",
+        json: false,
+    },
+];
+
+const CHECKPOINT_PRESETS: &[&str] = &[
+    "claude",
+    "claude-code",
+    "copilot-chat",
+    "cursor",
+    "github-copilot",
+    "agent-v1",
+    "agent",
+    "mock_ai",
+];
+
+const CAPABILITIES: &[&str] = &[
+    "transcript_summary",
+    "commit_message_authorship",
+    "attribution_confidence",
+    "environment_fingerprint",
+    "recovered_tab_completions",
+    "empty_commit_marker",
+    "generation_time",
+    "batch_checkpoint",
+    "patch_series_provenance",
+];
+
+const ENUM_SETTINGS: &[(&str, &[&str])] = &[
+    ("transcript_mode", &["full", "summary"]),
+    (
+        "transcript_store",
+        &["full", "messages_only", "hashes_only", "none"],
+    ),
+    ("push_notes_scope", &["pushed", "all"]),
+    ("notes_conflict", &["ours", "theirs"]),
+    ("submodules", &["ignore", "recurse"]),
+];
+
+pub fn handle_introspect(args: &[String]) {
+    if let Some(arg) = args.first() {
+        eprintln!("Unknown introspect argument: {}", arg);
+        std::process::exit(1);
+    }
+
+    let repo = find_repository(&Vec::<String>::new()).ok();
+    let introspection = match introspect(repo.as_ref()) {
+        Ok(introspection) => introspection,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match serde_json::to_string(&introspection) {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("Failed to serialize introspection: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Describe this git-ai, with settings and storage resolved for `repo` when there is one
+pub fn introspect(repo: Option<&Repository>) -> Result<Introspection, GitAiError> {
+    let workdir = repo.and_then(|repo| repo.workdir().ok());
+    let resolved = Settings::resolve(workdir.as_deref())?;
+
+    let defaults = serde_json::to_value(Settings::default())?;
+    let effective = serde_json::to_value(&resolved.settings)?;
+    let settings = SETTINGS_KEYS
+        .iter()
+        .map(|key| {
+            let default = defaults.get(*key).cloned().unwrap_or(Value::Null);
+            SettingInfo {
+                key,
+                value_type: value_type(&default),
+                values: ENUM_SETTINGS
+                    .iter()
+                    .find(|(name, _)| name == key)
+                    .map(|(_, values)| *values),
+                default,
+                value: effective.get(*key).cloned().unwrap_or(Value::Null),
+                origin: resolved
+                    .values
+                    .get(*key)
+                    .map(|(_, origin)| origin.clone())
+                    .unwrap_or(ConfigOrigin::Default)
+                    .to_string(),
+            }
+        })
+        .collect();
+
+    let repository = repo.map(|repo| RepositoryStorage {
+        repo_config: workdir
+            .as_ref()
+            .map(|workdir| workdir.join(config::REPO_CONFIG_FILE)),
+        ignore_file: workdir
+            .as_ref()
+            .map(|workdir| workdir.join(config::IGNORE_FILE)),
+        workdir: workdir.clone(),
+        notes_ref: format!("refs/notes/{}", resolved.settings.notes_ref_name()),
+        working_logs: repo.storage.working_logs.clone(),
+        rewrite_log: repo.storage.rewrite_log.clone(),
+        transcripts: repo.storage.transcripts.clone(),
+        last_outcome: repo.storage.last_outcome.clone(),
+        queue: repo.storage.queue.clone(),
+    });
+
+    Ok(Introspection {
+        schema_version: INTROSPECT_SCHEMA_VERSION,
+        version: env!("CARGO_PKG_VERSION"),
+        authorship_log_version: AUTHORSHIP_LOG_VERSION,
+        capabilities: CAPABILITIES.to_vec(),
+        commands: COMMANDS.to_vec(),
+        checkpoint_presets: CHECKPOINT_PRESETS.to_vec(),
+        settings,
+        storage: StorageLocations {
+            user_config: config::config_file_path(),
+            global_config: config::user_toml_config_path(),
+            system_config: config::system_config_path(),
+            repo_registry: repo_registry::registry_path(),
+            repository,
+        },
+    })
+}
+
+fn value_type(default: &Value) -> &'static str {
+    match default {
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::Array(_) => "string[]",
+        Value::Object(_) => "map",
+        _ => "string",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_introspect_settings_schema() {
+        let introspection = introspect(None).unwrap();
+        assert_eq!(introspection.settings.len(), SETTINGS_KEYS.len());
+
+        let setting = |key: &str| {
+            introspection
+                .settings
+                .iter()
+                .find(|setting| setting.key == key)
+                .unwrap()
+        };
+        assert_eq!(setting("confirm_clean").value_type, "boolean");
+        assert_eq!(setting("exclude_paths").value_type, "string[]");
+        assert_eq!(setting("directory_policies").value_type, "map");
+        assert_eq!(setting("hook_budget_ms").value_type, "number");
+        assert_eq!(
+            setting("notes_ref").default,
+            Value::String("ai".to_string())
+        );
+        assert_eq!(
+            setting("transcript_mode").values,
+            Some(&["full", "summary"][..])
+        );
+        assert!(introspection.storage.repository.is_none());
+    }
+}
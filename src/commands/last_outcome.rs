@@ -0,0 +1,153 @@
+use crate::authorship::attribution_loss::AttributionLoss;
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::repository::Repository;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// When set, the outcome is also written to this path. Wrapper scripts can point it at a
+/// file of their own so concurrent git commands don't overwrite each other's outcome.
+pub const OUTCOME_FILE_ENV: &str = "GIT_AI_OUTCOME_FILE";
+
+/// What git-ai did around one intercepted git command
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct CommandOutcome {
+    pub command: Option<String>,
+    pub args: Vec<String>,
+    /// Exit code of the proxied git command, `None` if it was killed by a signal
+    pub exit_code: Option<i32>,
+    pub hooks: Vec<HookOutcome>,
+    /// Commits whose authorship note was added or changed while the command ran
+    pub notes_written: Vec<String>,
+    /// AI attributions the command's hooks had to drop
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub attribution_losses: Vec<AttributionLoss>,
+    pub timestamp: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookOutcome {
+    /// e.g. `pre-commit`, `post-rebase`
+    pub name: String,
+    pub success: bool,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+impl CommandOutcome {
+    pub fn new(command: Option<String>, args: Vec<String>, exit_code: Option<i32>) -> Self {
+        Self {
+            command,
+            args,
+            exit_code,
+            hooks: Vec::new(),
+            notes_written: Vec::new(),
+            attribution_losses: Vec::new(),
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        }
+    }
+
+    pub fn record_hook(&mut self, name: String, errors: Vec<String>) {
+        self.hooks.push(HookOutcome {
+            name,
+            success: errors.is_empty(),
+            errors,
+        });
+    }
+
+    pub fn hooks_succeeded(&self) -> bool {
+        self.hooks.iter().all(|hook| hook.success)
+    }
+}
+
+pub fn write_outcome(repo: &Repository, outcome: &CommandOutcome) -> Result<(), GitAiError> {
+    let json = serde_json::to_string_pretty(outcome)?;
+    if let Some(parent) = repo.storage.last_outcome.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&repo.storage.last_outcome, &json)?;
+
+    if let Ok(path) = std::env::var(OUTCOME_FILE_ENV)
+        && !path.is_empty()
+    {
+        fs::write(path, &json)?;
+    }
+    Ok(())
+}
+
+pub fn read_outcome(repo: &Repository) -> Option<CommandOutcome> {
+    let content = fs::read_to_string(&repo.storage.last_outcome).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn handle_last_outcome(args: &[String]) {
+    let mut check = false;
+    for arg in args {
+        match arg.as_str() {
+            "--check" => check = true,
+            _ => {
+                eprintln!("Unknown last-outcome argument: {}", arg);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let Some(outcome) = read_outcome(&repo) else {
+        eprintln!("No git command has been run through git-ai in this repository yet");
+        std::process::exit(1);
+    };
+
+    match serde_json::to_string_pretty(&outcome) {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("Failed to serialize outcome: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    if check && !outcome.hooks_succeeded() {
+        std::process::exit(2);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_outcome_tracks_hook_failures() {
+        let mut outcome = CommandOutcome::new(
+            Some("commit".to_string()),
+            vec!["-m".to_string(), "msg".to_string()],
+            Some(0),
+        );
+        assert_eq!(outcome.exit_code, Some(0));
+
+        outcome.record_hook("pre-commit".to_string(), Vec::new());
+        assert!(outcome.hooks_succeeded());
+
+        outcome.record_hook(
+            "post-commit".to_string(),
+            vec!["Failed to serialize authorship log".to_string()],
+        );
+        assert!(!outcome.hooks_succeeded());
+
+        let json = serde_json::to_string(&outcome).unwrap();
+        let parsed: CommandOutcome = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, outcome);
+        // Successful hooks don't carry an empty errors list
+        assert!(!json.contains(r#""errors":[]"#));
+    }
+}
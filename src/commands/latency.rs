@@ -0,0 +1,212 @@
+use crate::error::GitAiError;
+use crate::git::refs::get_authorship;
+use crate::git::repository::{CommitRange, RangeWalk, Repository};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Percentiles of how long AI hunks waited between their last checkpoint and the commit,
+/// in seconds
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencyPercentiles {
+    pub hunks: usize,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+    pub max: u64,
+}
+
+impl LatencyPercentiles {
+    /// Nearest-rank percentiles of `samples`
+    fn from_samples(mut samples: Vec<u64>) -> Self {
+        samples.sort_unstable();
+        let percentile = |p: usize| {
+            if samples.is_empty() {
+                return 0;
+            }
+            let rank = (p * samples.len()).div_ceil(100).max(1);
+            samples[rank - 1]
+        };
+        Self {
+            hunks: samples.len(),
+            p50: percentile(50),
+            p90: percentile(90),
+            p99: percentile(99),
+            max: samples.last().copied().unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LatencyReport {
+    /// The commit or range measured
+    pub range: String,
+    pub commits: usize,
+    #[serde(flatten)]
+    pub overall: LatencyPercentiles,
+    pub by_agent: BTreeMap<String, LatencyPercentiles>,
+    /// AI hunks whose notes predate generation times, left out of the percentiles
+    pub untimed_hunks: usize,
+}
+
+/// Review latency of every AI hunk in `target`, a commit or a `<start>..<end>` range. A hunk
+/// is a contiguous run of lines from one session; it waited from the session's last
+/// checkpoint until the commit's author date, which survives rebases and amends. Merges in a
+/// range count only when `walk` stands them in for their branch.
+pub fn build_report(
+    repo: &Repository,
+    target: &str,
+    walk: RangeWalk,
+) -> Result<LatencyReport, GitAiError> {
+    let is_range = target.contains("..");
+    let commits: Vec<String> = match target.split_once("..") {
+        Some((start, end)) => {
+            CommitRange::new_infer_refname(repo, start.to_string(), end.to_string(), None)?
+                .with_walk(walk)
+                .into_iter()
+                .map(|commit| commit.id())
+                .collect()
+        }
+        None => vec![repo.revparse_single(target)?.id()],
+    };
+
+    let mut samples: Vec<u64> = Vec::new();
+    let mut samples_by_agent: BTreeMap<String, Vec<u64>> = BTreeMap::new();
+    let mut untimed_hunks = 0;
+    for commit in &commits {
+        let Some(authorship_log) = get_authorship(repo, commit) else {
+            continue;
+        };
+        let commit_info = repo.find_commit(commit.clone())?;
+        if is_range && !walk.counts_merge_notes() && commit_info.parent_count()? > 1 {
+            continue;
+        }
+        let committed_at = commit_info.author()?.when().seconds();
+
+        for file_attestation in &authorship_log.attestations {
+            for entry in &file_attestation.entries {
+                let Some(prompt) = authorship_log.metadata.prompts.get(&entry.hash) else {
+                    continue;
+                };
+                let Some(generated_at) = prompt.generated_at else {
+                    untimed_hunks += entry.line_ranges.len();
+                    continue;
+                };
+                let latency = committed_at.saturating_sub(generated_at as i64).max(0) as u64;
+                for _ in &entry.line_ranges {
+                    samples.push(latency);
+                    samples_by_agent
+                        .entry(prompt.agent_id.tool.clone())
+                        .or_default()
+                        .push(latency);
+                }
+            }
+        }
+    }
+
+    Ok(LatencyReport {
+        range: target.to_string(),
+        commits: commits.len(),
+        overall: LatencyPercentiles::from_samples(samples),
+        by_agent: samples_by_agent
+            .into_iter()
+            .map(|(agent, samples)| (agent, LatencyPercentiles::from_samples(samples)))
+            .collect(),
+        untimed_hunks,
+    })
+}
+
+pub fn print_report(report: &LatencyReport) {
+    if report.overall.hunks == 0 {
+        println!("No timed AI hunks in {}", report.range);
+        if report.untimed_hunks > 0 {
+            println!(
+                "{} AI hunk(s) were committed before git-ai recorded generation times",
+                report.untimed_hunks
+            );
+        }
+        return;
+    }
+
+    println!(
+        "AI review latency for {}, from generation to commit",
+        report.range
+    );
+    println!();
+
+    let row = |label: &str, latency: &LatencyPercentiles| {
+        println!(
+            "  {:<14}{:>6} hunks   p50 {:>8}   p90 {:>8}   p99 {:>8}   max {:>8}",
+            label,
+            latency.hunks,
+            format_duration(latency.p50),
+            format_duration(latency.p90),
+            format_duration(latency.p99),
+            format_duration(latency.max)
+        );
+    };
+
+    row("total", &report.overall);
+    println!();
+    println!("by agent");
+    for (agent, latency) in &report.by_agent {
+        row(agent, latency);
+    }
+    if report.untimed_hunks > 0 {
+        println!();
+        println!(
+            "{} older AI hunk(s) without generation times were skipped",
+            report.untimed_hunks
+        );
+    }
+}
+
+/// Two most significant units, like "3h 12m"
+fn format_duration(seconds: u64) -> String {
+    const UNITS: [(u64, &str); 4] = [(86400, "d"), (3600, "h"), (60, "m"), (1, "s")];
+    let Some(first) = UNITS.iter().position(|(size, _)| seconds >= *size) else {
+        return "0s".to_string();
+    };
+    UNITS[first..]
+        .iter()
+        .take(2)
+        .scan(seconds, |remaining, (size, unit)| {
+            let count = *remaining / size;
+            *remaining %= size;
+            Some((count, unit))
+        })
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, unit)| format!("{}{}", count, unit))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latency_percentiles() {
+        let latency = LatencyPercentiles::from_samples((1..=100).rev().collect());
+        assert_eq!(latency.hunks, 100);
+        assert_eq!(latency.p50, 50);
+        assert_eq!(latency.p90, 90);
+        assert_eq!(latency.p99, 99);
+        assert_eq!(latency.max, 100);
+
+        let single = LatencyPercentiles::from_samples(vec![42]);
+        assert_eq!((single.p50, single.p99, single.max), (42, 42, 42));
+        assert_eq!(
+            LatencyPercentiles::from_samples(Vec::new()),
+            LatencyPercentiles::default()
+        );
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(0), "0s");
+        assert_eq!(format_duration(45), "45s");
+        assert_eq!(format_duration(3600), "1h");
+        assert_eq!(format_duration(3 * 3600 + 12 * 60 + 5), "3h 12m");
+        assert_eq!(format_duration(2 * 86400 + 30), "2d");
+    }
+}
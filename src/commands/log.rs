@@ -0,0 +1,322 @@
+use crate::authorship::authorship_log::LineRange;
+use crate::authorship::stats::numstat_new_path;
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::refs::get_authorship;
+use crate::git::repository::{Repository, exec_git};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Widest the +/- graph gets, like `git log --stat` on an 80 column terminal
+const GRAPH_WIDTH: u32 = 40;
+
+/// One line of `--stat` output, with the AI lines the note attributes to the file
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileStat {
+    pub path: String,
+    pub added: u32,
+    pub deleted: u32,
+    pub ai_lines: u32,
+    pub binary: bool,
+}
+
+impl FileStat {
+    /// Share of the added lines written by AI, or `None` when nothing was added
+    pub fn ai_percentage(&self) -> Option<u32> {
+        if self.added == 0 {
+            return None;
+        }
+        let ai_lines = self.ai_lines.min(self.added);
+        Some((ai_lines as f64 * 100.0 / self.added as f64).round() as u32)
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitLogStat {
+    pub commit: String,
+    pub author: String,
+    pub date: String,
+    pub message: String,
+    /// Whether the commit has an authorship note; without one no line counts as AI
+    pub has_note: bool,
+    pub files: Vec<FileStat>,
+}
+
+pub fn handle_log(args: &[String]) {
+    let mut stat = false;
+    let mut json_output = false;
+    let mut log_args = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "--stat" => stat = true,
+            "--json" => json_output = true,
+            _ => log_args.push(arg.clone()),
+        }
+    }
+
+    if !stat {
+        eprintln!("log requires --stat (git-ai log --stat [<git log options>] [<revision range>])");
+        std::process::exit(1);
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let commits = match log_stats(&repo, &log_args) {
+        Ok(commits) => commits,
+        Err(e) => {
+            eprintln!("Log failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if json_output {
+        match serde_json::to_string(&commits) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize log: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        for (i, commit) in commits.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            print!("{}", format_commit(commit));
+        }
+    }
+}
+
+/// `git log --numstat` over `log_args`, with each file's AI lines taken from the commit's note
+pub fn log_stats(repo: &Repository, log_args: &[String]) -> Result<Vec<CommitLogStat>, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("log".to_string());
+    args.push("--numstat".to_string());
+    // Record separator before each commit, NULs between the header fields and the numstat
+    args.push("--format=%x1e%H%x00%an <%ae>%x00%ad%x00%B%x00".to_string());
+    args.extend(log_args.iter().cloned());
+    let output = exec_git(&args)?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    let mut commits = Vec::new();
+    for record in stdout.split('\x1e').filter(|record| !record.is_empty()) {
+        let mut fields = record.splitn(5, '\0');
+        let (Some(sha), Some(author), Some(date), Some(message), numstat) = (
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next(),
+            fields.next().unwrap_or(""),
+        ) else {
+            continue;
+        };
+
+        let mut files = parse_file_stats(numstat);
+        let authorship_log = get_authorship(repo, sha);
+        if let Some(log) = &authorship_log {
+            let mut ai_lines_by_file: HashMap<&str, u32> = HashMap::new();
+            for file_attestation in &log.attestations {
+                let ai_lines: u32 = file_attestation
+                    .entries
+                    .iter()
+                    .filter(|entry| log.metadata.prompts.contains_key(&entry.hash))
+                    .flat_map(|entry| &entry.line_ranges)
+                    .map(|range| match range {
+                        LineRange::Single(_) => 1,
+                        LineRange::Range(start, end) => end - start + 1,
+                    })
+                    .sum();
+                *ai_lines_by_file
+                    .entry(file_attestation.file_path.as_str())
+                    .or_default() += ai_lines;
+            }
+            for file in &mut files {
+                file.ai_lines = ai_lines_by_file
+                    .get(file.path.as_str())
+                    .copied()
+                    .unwrap_or(0);
+            }
+        }
+
+        commits.push(CommitLogStat {
+            commit: sha.to_string(),
+            author: author.to_string(),
+            date: date.to_string(),
+            message: message.trim_end().to_string(),
+            has_note: authorship_log.is_some(),
+            files,
+        });
+    }
+    Ok(commits)
+}
+
+/// `added\tdeleted\tpath` lines, with `-` counts for binary files
+fn parse_file_stats(numstat: &str) -> Vec<FileStat> {
+    numstat
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(3, '\t');
+            let (added, deleted, path) = (parts.next()?, parts.next()?, parts.next()?);
+            let binary = added == "-" && deleted == "-";
+            Some(FileStat {
+                path: numstat_new_path(path),
+                added: added.parse().unwrap_or(0),
+                deleted: deleted.parse().unwrap_or(0),
+                ai_lines: 0,
+                binary,
+            })
+        })
+        .collect()
+}
+
+/// A commit the way `git log --stat` prints it, with `[ai N%]` after files AI wrote to
+fn format_commit(commit: &CommitLogStat) -> String {
+    let mut out = String::new();
+    out.push_str(&format!("commit {}\n", commit.commit));
+    out.push_str(&format!("Author: {}\n", commit.author));
+    out.push_str(&format!("Date:   {}\n\n", commit.date));
+    for line in commit.message.lines() {
+        out.push_str(&format!("    {}\n", line));
+    }
+    if commit.files.is_empty() {
+        return out;
+    }
+    out.push('\n');
+
+    let path_width = commit.files.iter().map(|f| f.path.len()).max().unwrap_or(0);
+    let max_changes = commit
+        .files
+        .iter()
+        .map(|f| f.added + f.deleted)
+        .max()
+        .unwrap_or(0);
+    let count_width = max_changes.to_string().len();
+
+    for file in &commit.files {
+        if file.binary {
+            out.push_str(&format!(
+                " {:<path_width$} | {:>count_width$}\n",
+                file.path, "Bin"
+            ));
+            continue;
+        }
+        let (plus, minus) = scale_graph(file.added, file.deleted, max_changes);
+        let mut line = format!(
+            " {:<path_width$} | {:>count_width$} {}{}",
+            file.path,
+            file.added + file.deleted,
+            "+".repeat(plus as usize),
+            "-".repeat(minus as usize)
+        );
+        if let Some(percentage) = file.ai_percentage().filter(|_| file.ai_lines > 0) {
+            line.push_str(&format!("  [ai {}%]", percentage));
+        }
+        out.push_str(line.trim_end());
+        out.push('\n');
+    }
+
+    let added: u32 = commit.files.iter().map(|f| f.added).sum();
+    let deleted: u32 = commit.files.iter().map(|f| f.deleted).sum();
+    let ai_lines: u32 = commit.files.iter().map(|f| f.ai_lines.min(f.added)).sum();
+    let mut summary = format!(
+        " {} file{} changed",
+        commit.files.len(),
+        if commit.files.len() == 1 { "" } else { "s" }
+    );
+    if added > 0 {
+        summary.push_str(&format!(
+            ", {} insertion{}(+)",
+            added,
+            if added == 1 { "" } else { "s" }
+        ));
+    }
+    if deleted > 0 {
+        summary.push_str(&format!(
+            ", {} deletion{}(-)",
+            deleted,
+            if deleted == 1 { "" } else { "s" }
+        ));
+    }
+    if ai_lines > 0 {
+        summary.push_str(&format!(
+            ", {} by AI ({}%)",
+            ai_lines,
+            (ai_lines as f64 * 100.0 / added as f64).round() as u32
+        ));
+    }
+    out.push_str(&summary);
+    out.push('\n');
+    out
+}
+
+/// Scale a file's +/- counts so the largest change in the commit fills [`GRAPH_WIDTH`],
+/// keeping at least one mark for any non-zero count
+fn scale_graph(added: u32, deleted: u32, max_changes: u32) -> (u32, u32) {
+    if max_changes <= GRAPH_WIDTH {
+        return (added, deleted);
+    }
+    let scale = |count: u32| {
+        if count == 0 {
+            0
+        } else {
+            ((count as u64 * GRAPH_WIDTH as u64) / max_changes as u64).max(1) as u32
+        }
+    };
+    (scale(added), scale(deleted))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, added: u32, deleted: u32, ai_lines: u32) -> FileStat {
+        FileStat {
+            path: path.to_string(),
+            added,
+            deleted,
+            ai_lines,
+            binary: false,
+        }
+    }
+
+    #[test]
+    fn test_parse_file_stats() {
+        let files = parse_file_stats("3\t1\tsrc/a.rs\n-\t-\tlogo.png\n2\t0\tsrc/{old => new}.rs\n");
+        assert_eq!(files.len(), 3);
+        assert_eq!(files[0], file("src/a.rs", 3, 1, 0));
+        assert!(files[1].binary);
+        assert_eq!(files[2].path, "src/new.rs");
+    }
+
+    #[test]
+    fn test_format_commit_marks_ai_share() {
+        let commit = CommitLogStat {
+            commit: "abc123".to_string(),
+            author: "Test User <test@example.com>".to_string(),
+            date: "Thu Oct 15 12:00:00 2026 +0000".to_string(),
+            message: "Add parser".to_string(),
+            has_note: true,
+            files: vec![file("src/parser.rs", 10, 2, 7), file("README.md", 4, 0, 0)],
+        };
+        let output = format_commit(&commit);
+        assert!(output.contains(" src/parser.rs | 12 ++++++++++--  [ai 70%]\n"));
+        assert!(output.contains(" README.md     |  4 ++++\n"));
+        assert!(
+            output.contains(" 2 files changed, 14 insertions(+), 2 deletions(-), 7 by AI (50%)\n")
+        );
+    }
+
+    #[test]
+    fn test_scale_graph() {
+        assert_eq!(scale_graph(3, 1, 4), (3, 1));
+        assert_eq!(scale_graph(100, 0, 100), (40, 0));
+        assert_eq!(scale_graph(1, 99, 100), (1, 39));
+    }
+}
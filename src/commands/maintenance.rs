@@ -0,0 +1,126 @@
+use crate::commands::install_hooks::get_current_binary_path;
+use crate::error::GitAiError;
+use crate::git::detached::unix_now;
+use crate::git::find_repository;
+use crate::git::maintenance::{
+    MaintenanceTask, install_hook, pre_auto_gc_hook_path, run_tasks, uninstall_hook,
+};
+use crate::git::repository::Repository;
+
+pub fn handle_maintenance(args: &[String]) {
+    let Some(subcommand) = args.first() else {
+        eprintln!("maintenance requires a subcommand: register, unregister or run");
+        std::process::exit(1);
+    };
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let result = match subcommand.as_str() {
+        "register" => register(&repo),
+        "unregister" => unregister(&repo),
+        "run" => run(&repo, &args[1..]),
+        _ => {
+            eprintln!("Unknown maintenance subcommand: {}", subcommand);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = result {
+        eprintln!("maintenance {} failed: {}", subcommand, e);
+        std::process::exit(1);
+    }
+}
+
+fn register(repo: &Repository) -> Result<(), GitAiError> {
+    let hook_path = pre_auto_gc_hook_path(repo)?;
+    if install_hook(&hook_path, &get_current_binary_path()?)? {
+        println!("Added git-ai maintenance to {}", hook_path.display());
+    } else {
+        println!("git-ai maintenance is already in {}", hook_path.display());
+    }
+
+    // `maintenance.strategy` turns git's gc task off, and with it the hook
+    match repo.config_get_bool("maintenance.gc.enabled")? {
+        None => repo.config_set_str("maintenance.gc.enabled", "true")?,
+        Some(true) => {}
+        Some(false) => eprintln!(
+            "maintenance.gc.enabled is false, so git's auto maintenance won't run git-ai maintenance"
+        ),
+    }
+    if repo.config_get_bool("maintenance.auto")? == Some(false) {
+        eprintln!(
+            "maintenance.auto is false (`git maintenance register` sets it), so git-ai maintenance only runs on `git gc` and `git maintenance run`"
+        );
+    }
+    Ok(())
+}
+
+fn unregister(repo: &Repository) -> Result<(), GitAiError> {
+    let hook_path = pre_auto_gc_hook_path(repo)?;
+    if uninstall_hook(&hook_path)? {
+        println!("Removed git-ai maintenance from {}", hook_path.display());
+    } else {
+        println!("git-ai maintenance is not registered");
+    }
+    Ok(())
+}
+
+fn run(repo: &Repository, args: &[String]) -> Result<(), GitAiError> {
+    let mut tasks = Vec::new();
+    let mut quiet = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--task" if i + 1 < args.len() => {
+                let Some(task) = MaintenanceTask::from_name(&args[i + 1]) else {
+                    return Err(GitAiError::Generic(format!(
+                        "unknown task {} (expected gc, compaction or index-refresh)",
+                        args[i + 1]
+                    )));
+                };
+                tasks.push(task);
+                i += 2;
+            }
+            "--quiet" => {
+                quiet = true;
+                i += 1;
+            }
+            _ => {
+                return Err(GitAiError::Generic(format!(
+                    "unknown argument: {}",
+                    args[i]
+                )));
+            }
+        }
+    }
+    if tasks.is_empty() {
+        tasks = MaintenanceTask::ALL.to_vec();
+    }
+
+    let summary = run_tasks(repo, &tasks, unix_now())?;
+    if !quiet {
+        for task in &tasks {
+            match task {
+                MaintenanceTask::Gc => println!(
+                    "gc: pruned notes of {} detached commit(s), removed {} working log(s) of missing commits",
+                    summary.detached.pruned, summary.working_logs_removed
+                ),
+                MaintenanceTask::Compaction => println!(
+                    "compaction: removed {} unreferenced blob(s), dropped {} rewrite log event(s)",
+                    summary.blobs_removed, summary.rewrite_events_dropped
+                ),
+                MaintenanceTask::IndexRefresh => println!(
+                    "index-refresh: dropped {} stale file state cache entries",
+                    summary.cache_entries_dropped
+                ),
+            }
+        }
+    }
+    Ok(())
+}
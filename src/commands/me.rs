@@ -0,0 +1,259 @@
+use crate::authorship::stats::stats_for_commit_stats;
+use crate::error::GitAiError;
+use crate::git::find_repository_in_path;
+use crate::git::repo_registry::{RepoRegistry, registry_path};
+use crate::git::repository::{Repository, exec_git};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAY_SECS: i64 = 24 * 60 * 60;
+const WEEK_DAYS: i64 = 7;
+const MONTH_DAYS: i64 = 30;
+const TOP_AGENTS: usize = 5;
+const SPARKLINE_CHARS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PeriodStats {
+    pub commits: u32,
+    pub lines_added: u32,
+    pub ai_additions: u32,
+    pub ai_accepted: u32,
+}
+
+impl PeriodStats {
+    fn add(&mut self, lines_added: u32, ai_additions: u32, ai_accepted: u32) {
+        self.commits += 1;
+        self.lines_added += lines_added;
+        self.ai_additions += ai_additions;
+        self.ai_accepted += ai_accepted;
+    }
+
+    fn ai_percentage(&self) -> u32 {
+        if self.lines_added == 0 {
+            return 0;
+        }
+        ((self.ai_accepted as f64 / self.lines_added as f64) * 100.0)
+            .round()
+            .min(100.0) as u32
+    }
+}
+
+/// Personal stats for the current user across every registered repository
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MeReport {
+    pub emails: BTreeSet<String>,
+    pub repositories: Vec<String>,
+    pub week: PeriodStats,
+    pub month: PeriodStats,
+    /// Accepted AI lines per agent over the last 30 days, most used first
+    pub top_agents: Vec<(String, u32)>,
+    /// Accepted AI lines per day over the last 30 days, oldest first
+    pub daily_ai_accepted: Vec<u32>,
+}
+
+pub fn handle_me(args: &[String]) {
+    let mut json_output = false;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json_output = true,
+            _ => {
+                eprintln!("Unknown me argument: {}", arg);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let repositories = match registry_path() {
+        Some(path) => RepoRegistry::load(&path).existing_repositories(),
+        None => Vec::new(),
+    };
+
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs() as i64;
+
+    let report = build_report(&repositories, now);
+
+    if json_output {
+        match serde_json::to_string(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize report: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        print_report(&report);
+    }
+}
+
+pub fn build_report(repositories: &[PathBuf], now: i64) -> MeReport {
+    let mut report = MeReport {
+        daily_ai_accepted: vec![0; MONTH_DAYS as usize],
+        ..Default::default()
+    };
+    let mut agents: BTreeMap<String, u32> = BTreeMap::new();
+
+    for path in repositories {
+        let repo = match find_repository_in_path(&path.to_string_lossy()) {
+            Ok(repo) => repo,
+            Err(_) => continue,
+        };
+        let Ok(Some(email)) = repo.config_get_str("user.email") else {
+            continue;
+        };
+        report.repositories.push(path.to_string_lossy().to_string());
+
+        let commits = match recent_commits_by(&repo, &email, now - MONTH_DAYS * DAY_SECS) {
+            Ok(commits) => commits,
+            Err(_) => continue,
+        };
+        report.emails.insert(email);
+
+        for (sha, author_time) in commits {
+            let Ok(stats) = stats_for_commit_stats(&repo, &sha, "") else {
+                continue;
+            };
+            let age_days = (now - author_time).max(0) / DAY_SECS;
+            if age_days >= MONTH_DAYS {
+                continue;
+            }
+
+            report.month.add(
+                stats.counted_added_lines(),
+                stats.ai_additions,
+                stats.ai_accepted,
+            );
+            if age_days < WEEK_DAYS {
+                report.week.add(
+                    stats.counted_added_lines(),
+                    stats.ai_additions,
+                    stats.ai_accepted,
+                );
+            }
+            report.daily_ai_accepted[(MONTH_DAYS - 1 - age_days) as usize] += stats.ai_accepted;
+
+            for (tool_model, tool_stats) in &stats.tool_model_breakdown {
+                let tool = tool_model.split("::").next().unwrap_or(tool_model);
+                *agents.entry(tool.to_string()).or_default() += tool_stats.ai_accepted;
+            }
+        }
+    }
+
+    let mut top_agents: Vec<(String, u32)> =
+        agents.into_iter().filter(|(_, lines)| *lines > 0).collect();
+    top_agents.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    top_agents.truncate(TOP_AGENTS);
+    report.top_agents = top_agents;
+
+    report
+}
+
+/// Non-merge commits on local branches authored by `email` since `since` (unix seconds),
+/// as (sha, author time) pairs
+fn recent_commits_by(
+    repo: &Repository,
+    email: &str,
+    since: i64,
+) -> Result<Vec<(String, i64)>, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("log".to_string());
+    args.push("--branches".to_string());
+    args.push("--no-merges".to_string());
+    args.push(format!("--author=<{}>", email));
+    args.push(format!("--since=@{}", since));
+    args.push("--format=%H %at".to_string());
+
+    let output = exec_git(&args)?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    Ok(stdout
+        .lines()
+        .filter_map(|line| {
+            let (sha, time) = line.split_once(' ')?;
+            Some((sha.to_string(), time.parse().ok()?))
+        })
+        .collect())
+}
+
+fn print_report(report: &MeReport) {
+    if report.repositories.is_empty() {
+        println!("No repositories recorded yet. Commit in a repo with git-ai installed first.");
+        return;
+    }
+
+    let who = report.emails.iter().cloned().collect::<Vec<_>>().join(", ");
+    let repo_count = report.repositories.len();
+    println!(
+        "{} across {} repo{}",
+        who,
+        repo_count,
+        if repo_count == 1 { "" } else { "s" }
+    );
+    println!();
+
+    println!("{:<16}{:>10}{:>10}", "", "7 days", "30 days");
+    let (week, month) = (&report.week, &report.month);
+    let rows = [
+        ("commits", week.commits, month.commits),
+        ("lines added", week.lines_added, month.lines_added),
+        ("ai lines", week.ai_additions, month.ai_additions),
+        ("ai accepted", week.ai_accepted, month.ai_accepted),
+        ("ai share %", week.ai_percentage(), month.ai_percentage()),
+    ];
+    for (label, week_value, month_value) in rows {
+        println!("{:<16}{:>10}{:>10}", label, week_value, month_value);
+    }
+
+    if !report.top_agents.is_empty() {
+        println!();
+        println!("top agents (30 days)");
+        for (agent, lines) in &report.top_agents {
+            println!("  {:<14}{:>10} lines", agent, lines);
+        }
+    }
+
+    println!();
+    println!("ai lines per day (30 days)");
+    println!("  {}", sparkline(&report.daily_ai_accepted));
+}
+
+/// Render values as a unicode sparkline scaled to the largest value
+pub fn sparkline(values: &[u32]) -> String {
+    let max = values.iter().copied().max().unwrap_or(0);
+    values
+        .iter()
+        .map(|&value| {
+            if max == 0 {
+                SPARKLINE_CHARS[0]
+            } else {
+                let level = (value as u64 * (SPARKLINE_CHARS.len() as u64 - 1)) / max as u64;
+                SPARKLINE_CHARS[level as usize]
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sparkline() {
+        assert_eq!(sparkline(&[0, 0, 0]), "▁▁▁");
+        assert_eq!(sparkline(&[0, 7, 14]), "▁▄█");
+        assert_eq!(sparkline(&[]), "");
+    }
+
+    #[test]
+    fn test_period_ai_percentage() {
+        let mut period = PeriodStats::default();
+        assert_eq!(period.ai_percentage(), 0);
+        period.add(10, 4, 4);
+        assert_eq!(period.ai_percentage(), 40);
+        assert_eq!(period.commits, 1);
+    }
+}
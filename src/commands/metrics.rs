@@ -0,0 +1,884 @@
+use crate::authorship::sampling::{
+    Estimate, SampleSize, estimate_ratio, estimate_total, stratified_sample,
+};
+use crate::authorship::stats::stats_for_commit_stats;
+use crate::commands::log::{CommitLogStat, log_stats};
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::repository::{RangeWalk, Repository, exec_git};
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Version of the export's JSON shape. Bumped when a field is removed, renamed or changes
+/// meaning; new fields can appear without a bump, so consumers should ignore unknown ones.
+pub const METRICS_SCHEMA_VERSION: u32 = 1;
+
+/// Aggregate metrics for one repository, built to be summed with other exports into an
+/// org-level rollup. With `anonymized` set there are no paths, names or emails in it.
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricsExport {
+    pub schema_version: u32,
+    pub generated_at: String,
+    /// First day counted (UTC, `YYYY-MM-DD`), or `None` for the whole history
+    pub since: Option<String>,
+    pub anonymized: bool,
+    /// Working directory of the repository; left out when anonymized
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<String>,
+    pub totals: MetricsTotals,
+    /// By AI lines accepted, most first
+    pub agents: Vec<AgentMetrics>,
+    /// By lines added, most first
+    pub languages: Vec<LanguageMetrics>,
+    /// Per commit author, by lines added; left out when anonymized
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authors: Option<Vec<AuthorMetrics>>,
+    /// Set when the counts carry differential privacy noise; absent means they're exact
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub noise: Option<NoiseInfo>,
+    /// Set when the counts are estimated from a sample of commits; absent means every commit
+    /// was read
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sampling: Option<SamplingInfo>,
+    /// The most any one commit adds to all the counts together (their L1 norm), which is what
+    /// differential privacy noise is scaled by. Not exported; `noise.sensitivity` reports it.
+    #[serde(skip)]
+    pub commit_sensitivity: u32,
+}
+
+/// How the counts of a noised export were perturbed
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NoiseInfo {
+    pub mechanism: String,
+    /// The privacy budget spent on the whole export, not on each count
+    pub epsilon: f64,
+    /// How much one commit changes all the counts together; each count's noise has scale
+    /// `sensitivity / epsilon`
+    pub sensitivity: u32,
+    pub note: String,
+}
+
+/// How a sampled export was estimated
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SamplingInfo {
+    pub method: String,
+    /// Commits the export covers, all of which count towards `totals.commits`
+    pub population_commits: u32,
+    pub sampled_commits: u32,
+    pub strata: u32,
+    pub confidence: f64,
+    /// Confidence intervals of the estimated totals, by field
+    pub intervals: BTreeMap<String, Interval>,
+    pub note: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Interval {
+    pub low: u32,
+    pub high: u32,
+}
+
+impl Interval {
+    fn from_estimate(estimate: Estimate, scale: f64) -> Self {
+        Interval {
+            low: (estimate.low * scale).floor() as u32,
+            high: (estimate.high * scale).ceil() as u32,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct MetricsTotals {
+    pub commits: u32,
+    pub commits_with_ai: u32,
+    /// Distinct commit authors. Only the count, never who they are.
+    pub authors: u32,
+    /// Added lines outside generated files
+    pub lines_added: u32,
+    pub human_additions: u32,
+    pub mixed_additions: u32,
+    pub ai_additions: u32,
+    pub ai_accepted: u32,
+    pub ai_percentage: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AgentMetrics {
+    pub agent: String,
+    pub commits: u32,
+    pub ai_additions: u32,
+    pub ai_accepted: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LanguageMetrics {
+    pub language: String,
+    pub lines_added: u32,
+    pub ai_lines: u32,
+    pub ai_percentage: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AuthorMetrics {
+    pub author: String,
+    pub commits: u32,
+    pub lines_added: u32,
+    pub ai_accepted: u32,
+}
+
+pub fn handle_metrics(args: &[String]) {
+    if args.first().map(String::as_str) != Some("export") {
+        eprintln!(
+            "Usage: git-ai metrics export [--anonymize] [--since <YYYY-MM-DD>] [--first-parent] [--sample <pct>% | --max-commits <n>] [--dp-epsilon <eps> [--dp-salt <salt>]]"
+        );
+        std::process::exit(1);
+    }
+
+    let mut anonymize = false;
+    let mut since = None;
+    let mut epsilon = None;
+    let mut salt = String::new();
+    let mut walk = RangeWalk::default();
+    let mut sample = None;
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            arg if walk.parse_arg(arg) => {
+                i += 1;
+            }
+            "--anonymize" => {
+                anonymize = true;
+                i += 1;
+            }
+            "--since" => {
+                let Some(value) = args.get(i + 1) else {
+                    eprintln!("--since requires a date (YYYY-MM-DD)");
+                    std::process::exit(1);
+                };
+                match NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+                    Ok(date) => since = Some(date),
+                    Err(_) => {
+                        eprintln!("Invalid --since date {:?}, expected YYYY-MM-DD", value);
+                        std::process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            "--sample" => {
+                match args
+                    .get(i + 1)
+                    .and_then(|value| SampleSize::parse_percent(value))
+                {
+                    Some(size) => sample = Some(size),
+                    None => {
+                        eprintln!("--sample requires a percentage between 0 and 100, like 10%");
+                        std::process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            "--max-commits" => {
+                match args
+                    .get(i + 1)
+                    .and_then(|value| value.parse::<usize>().ok())
+                {
+                    Some(max) if max > 0 => sample = Some(SampleSize::MaxCommits(max)),
+                    _ => {
+                        eprintln!("--max-commits requires a positive number");
+                        std::process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            "--dp-epsilon" => {
+                match args.get(i + 1).and_then(|value| value.parse::<f64>().ok()) {
+                    Some(value) if value.is_finite() && value > 0.0 => epsilon = Some(value),
+                    _ => {
+                        eprintln!("--dp-epsilon requires a positive number");
+                        std::process::exit(1);
+                    }
+                }
+                i += 2;
+            }
+            "--dp-salt" => {
+                let Some(value) = args.get(i + 1) else {
+                    eprintln!("--dp-salt requires a value");
+                    std::process::exit(1);
+                };
+                salt = value.clone();
+                i += 2;
+            }
+            _ => {
+                eprintln!("Unknown metrics export argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Exports are made to be handed on, so they must never reach out: everything comes from
+    // local commits and notes, and git isn't allowed to fetch missing objects of a partial clone
+    // SAFETY: set before any threads are spawned; the git children inherit it
+    unsafe {
+        std::env::set_var("GIT_NO_LAZY_FETCH", "1");
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match export_metrics(&repo, since, anonymize, walk, sample)
+        .map(|mut export| {
+            if let Some(epsilon) = epsilon {
+                add_noise(&mut export, &repository_identity(&repo), epsilon, &salt);
+            }
+            export
+        })
+        .and_then(|export| Ok(serde_json::to_string(&export)?))
+    {
+        Ok(json) => println!("{}", json),
+        Err(e) => {
+            eprintln!("Metrics export failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Metrics over commits on local branches since the start of `since` (UTC). Merges are left
+/// out unless `walk` follows first parents only, where they stand in for their branches.
+///
+/// With a `sample`, only that many commits are read, spread over the history by time, and the
+/// counts are scaled up to the whole history; `sampling` then holds their confidence intervals.
+pub fn export_metrics(
+    repo: &Repository,
+    since: Option<NaiveDate>,
+    anonymize: bool,
+    walk: RangeWalk,
+    sample: Option<SampleSize>,
+) -> Result<MetricsExport, GitAiError> {
+    // Merge notes would repeat their branch's lines, unless the walk leaves the branch out
+    let walk = RangeWalk {
+        no_merges: !walk.counts_merge_notes(),
+        ..walk
+    };
+    let mut log_args = vec!["--branches".to_string()];
+    log_args.extend(walk.rev_list_args());
+    if let Some(since) = since {
+        let start = since.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc();
+        log_args.push(format!("--since=@{}", start.timestamp()));
+    }
+
+    let mut aggregate = Aggregate::default();
+    let mut author_set: BTreeSet<String> = BTreeSet::new();
+    let mut sampling = None;
+    match sample {
+        Some(sample) => {
+            let population = list_commits(repo, &log_args)?;
+            author_set.extend(population.iter().map(|commit| commit.author.clone()));
+            let times: Vec<(String, i64)> = population
+                .iter()
+                .map(|commit| (commit.sha.clone(), commit.time))
+                .collect();
+            let strata = stratified_sample(&times, sample);
+            let sampled: usize = strata.iter().map(|stratum| stratum.sampled.len()).sum();
+
+            if sampled < population.len() {
+                let mut estimates = SampleEstimates::default();
+                for stratum in &strata {
+                    let commits = commit_log_stats(repo, &stratum.sampled)?;
+                    let mut part = Aggregate::default();
+                    let mut values = Vec::new();
+                    for commit in &commits {
+                        if let Some(stats) = part.add_commit(repo, commit, anonymize) {
+                            values.push(stats);
+                        }
+                    }
+                    // Commits without stats are left out of the sample, not counted as zero
+                    let factor = stratum.population as f64 / values.len().max(1) as f64;
+                    aggregate.add_scaled(&part, factor);
+                    estimates.add_stratum(stratum.population, values);
+                }
+                aggregate.totals.commits = population.len() as u32;
+                sampling = Some(estimates.into_info(population.len(), sampled, strata.len()));
+            } else {
+                // The sample would be the whole history; count it exactly
+                for commit in &log_stats(repo, &log_args)? {
+                    aggregate.add_commit(repo, commit, anonymize);
+                }
+            }
+        }
+        None => {
+            for commit in &log_stats(repo, &log_args)? {
+                if aggregate.add_commit(repo, commit, anonymize).is_some() {
+                    author_set.insert(commit.author.clone());
+                }
+            }
+        }
+    }
+
+    let mut totals = aggregate.totals;
+    totals.authors = author_set.len() as u32;
+    totals.ai_percentage = percentage(totals.ai_accepted, totals.lines_added);
+
+    let mut agents: Vec<AgentMetrics> = aggregate.agents.into_values().collect();
+    agents.sort_by(|a, b| {
+        b.ai_accepted
+            .cmp(&a.ai_accepted)
+            .then_with(|| a.agent.cmp(&b.agent))
+    });
+
+    let mut languages: Vec<LanguageMetrics> = aggregate
+        .languages
+        .into_values()
+        .filter(|language| language.lines_added > 0)
+        .map(|mut language| {
+            language.ai_percentage = percentage(language.ai_lines, language.lines_added);
+            language
+        })
+        .collect();
+    languages.sort_by(|a, b| {
+        b.lines_added
+            .cmp(&a.lines_added)
+            .then_with(|| a.language.cmp(&b.language))
+    });
+
+    let authors = (!anonymize).then(|| {
+        let mut authors: Vec<AuthorMetrics> = aggregate.authors.into_values().collect();
+        authors.sort_by(|a, b| {
+            b.lines_added
+                .cmp(&a.lines_added)
+                .then_with(|| a.author.cmp(&b.author))
+        });
+        authors
+    });
+
+    Ok(MetricsExport {
+        schema_version: METRICS_SCHEMA_VERSION,
+        generated_at: Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, true),
+        since: since.map(|date| date.format("%Y-%m-%d").to_string()),
+        anonymized: anonymize,
+        repository: if anonymize {
+            None
+        } else {
+            repo.workdir()
+                .ok()
+                .map(|dir| dir.to_string_lossy().to_string())
+        },
+        totals,
+        agents,
+        languages,
+        authors,
+        noise: None,
+        sampling,
+        commit_sensitivity: aggregate.sensitivity,
+    })
+}
+
+/// Counts summed over a set of commits
+#[derive(Debug, Default)]
+struct Aggregate {
+    totals: MetricsTotals,
+    agents: BTreeMap<String, AgentMetrics>,
+    languages: BTreeMap<&'static str, LanguageMetrics>,
+    authors: BTreeMap<String, AuthorMetrics>,
+    /// The most one commit added to all the counts together
+    sensitivity: u32,
+}
+
+impl Aggregate {
+    /// Count one commit, returning the values its sample estimates are built from. Commits
+    /// whose stats can't be computed are skipped.
+    fn add_commit(
+        &mut self,
+        repo: &Repository,
+        commit: &CommitLogStat,
+        anonymize: bool,
+    ) -> Option<CommitValues> {
+        let stats = stats_for_commit_stats(repo, &commit.commit, "").ok()?;
+        let settings = repo.settings();
+        let lines_added = stats.counted_added_lines();
+        let totals = &mut self.totals;
+        totals.commits += 1;
+        totals.lines_added += lines_added;
+        totals.human_additions += stats.human_additions;
+        totals.mixed_additions += stats.mixed_additions;
+        totals.ai_additions += stats.ai_additions;
+        totals.ai_accepted += stats.ai_accepted;
+        if stats.ai_additions > 0 {
+            totals.commits_with_ai += 1;
+        }
+
+        // One to `totals.commits` and, when it's an author's first, one to `totals.authors`
+        let mut contribution = 2
+            + u32::from(stats.ai_additions > 0)
+            + lines_added
+            + stats.human_additions
+            + stats.mixed_additions
+            + stats.ai_additions
+            + stats.ai_accepted;
+
+        let mut commit_agents: BTreeMap<&str, (u32, u32)> = BTreeMap::new();
+        for (tool_model, tool_stats) in &stats.tool_model_breakdown {
+            let tool = tool_model.split("::").next().unwrap_or(tool_model);
+            let entry = commit_agents.entry(tool).or_default();
+            entry.0 += tool_stats.ai_additions;
+            entry.1 += tool_stats.ai_accepted;
+        }
+        for (tool, (ai_additions, ai_accepted)) in commit_agents {
+            let agent = self
+                .agents
+                .entry(tool.to_string())
+                .or_insert_with(|| AgentMetrics {
+                    agent: tool.to_string(),
+                    ..Default::default()
+                });
+            agent.commits += 1;
+            agent.ai_additions += ai_additions;
+            agent.ai_accepted += ai_accepted;
+            contribution += 1 + ai_additions + ai_accepted;
+        }
+
+        for file in commit
+            .files
+            .iter()
+            .filter(|file| !file.binary && !settings.is_generated_path(&file.path))
+        {
+            let language = language_for_path(&file.path);
+            let entry = self
+                .languages
+                .entry(language)
+                .or_insert_with(|| LanguageMetrics {
+                    language: language.to_string(),
+                    ..Default::default()
+                });
+            entry.lines_added += file.added;
+            entry.ai_lines += file.ai_lines.min(file.added);
+            contribution += file.added + file.ai_lines.min(file.added);
+        }
+
+        if !anonymize {
+            let author = self
+                .authors
+                .entry(commit.author.clone())
+                .or_insert_with(|| AuthorMetrics {
+                    author: commit.author.clone(),
+                    ..Default::default()
+                });
+            author.commits += 1;
+            author.lines_added += lines_added;
+            author.ai_accepted += stats.ai_accepted;
+            contribution += 1 + lines_added + stats.ai_accepted;
+        }
+        self.sensitivity = self.sensitivity.max(contribution);
+
+        Some(CommitValues {
+            lines_added: lines_added as f64,
+            ai_additions: stats.ai_additions as f64,
+            ai_accepted: stats.ai_accepted as f64,
+            with_ai: if stats.ai_additions > 0 { 1.0 } else { 0.0 },
+        })
+    }
+
+    /// Add `other`'s counts multiplied by `factor`
+    fn add_scaled(&mut self, other: &Aggregate, factor: f64) {
+        let scale = |value: u32| (value as f64 * factor).round() as u32;
+        self.sensitivity = self.sensitivity.max(scale(other.sensitivity));
+        let totals = &mut self.totals;
+        totals.commits += scale(other.totals.commits);
+        totals.commits_with_ai += scale(other.totals.commits_with_ai);
+        totals.lines_added += scale(other.totals.lines_added);
+        totals.human_additions += scale(other.totals.human_additions);
+        totals.mixed_additions += scale(other.totals.mixed_additions);
+        totals.ai_additions += scale(other.totals.ai_additions);
+        totals.ai_accepted += scale(other.totals.ai_accepted);
+
+        for (tool, agent) in &other.agents {
+            let entry = self
+                .agents
+                .entry(tool.clone())
+                .or_insert_with(|| AgentMetrics {
+                    agent: tool.clone(),
+                    ..Default::default()
+                });
+            entry.commits += scale(agent.commits);
+            entry.ai_additions += scale(agent.ai_additions);
+            entry.ai_accepted += scale(agent.ai_accepted);
+        }
+        for (name, language) in &other.languages {
+            let entry = self
+                .languages
+                .entry(name)
+                .or_insert_with(|| LanguageMetrics {
+                    language: name.to_string(),
+                    ..Default::default()
+                });
+            entry.lines_added += scale(language.lines_added);
+            entry.ai_lines += scale(language.ai_lines);
+        }
+        for (name, author) in &other.authors {
+            let entry = self
+                .authors
+                .entry(name.clone())
+                .or_insert_with(|| AuthorMetrics {
+                    author: name.clone(),
+                    ..Default::default()
+                });
+            entry.commits += scale(author.commits);
+            entry.lines_added += scale(author.lines_added);
+            entry.ai_accepted += scale(author.ai_accepted);
+        }
+    }
+}
+
+/// One sampled commit's share of the totals that get confidence intervals
+#[derive(Debug, Clone, Copy)]
+struct CommitValues {
+    lines_added: f64,
+    ai_additions: f64,
+    ai_accepted: f64,
+    with_ai: f64,
+}
+
+/// The sampled commits' values, by stratum
+#[derive(Debug, Default)]
+struct SampleEstimates {
+    strata: Vec<(usize, Vec<CommitValues>)>,
+}
+
+impl SampleEstimates {
+    fn add_stratum(&mut self, population: usize, values: Vec<CommitValues>) {
+        self.strata.push((population, values));
+    }
+
+    fn into_info(self, population: usize, sampled: usize, strata: usize) -> SamplingInfo {
+        let total = |pick: fn(&CommitValues) -> f64| {
+            let values: Vec<(usize, Vec<f64>)> = self
+                .strata
+                .iter()
+                .map(|(population, values)| (*population, values.iter().map(pick).collect()))
+                .collect();
+            Interval::from_estimate(estimate_total(&values), 1.0)
+        };
+        let ai_share: Vec<(usize, Vec<(f64, f64)>)> = self
+            .strata
+            .iter()
+            .map(|(population, values)| {
+                (
+                    *population,
+                    values
+                        .iter()
+                        .map(|value| (value.ai_accepted, value.lines_added))
+                        .collect(),
+                )
+            })
+            .collect();
+
+        SamplingInfo {
+            method: "stratified by commit time".to_string(),
+            population_commits: population as u32,
+            sampled_commits: sampled as u32,
+            strata: strata as u32,
+            confidence: 0.95,
+            intervals: BTreeMap::from([
+                ("totals.lines_added".to_string(), total(|v| v.lines_added)),
+                ("totals.ai_additions".to_string(), total(|v| v.ai_additions)),
+                ("totals.ai_accepted".to_string(), total(|v| v.ai_accepted)),
+                ("totals.commits_with_ai".to_string(), total(|v| v.with_ai)),
+                (
+                    "totals.ai_percentage".to_string(),
+                    Interval::from_estimate(estimate_ratio(&ai_share), 100.0),
+                ),
+            ]),
+            note: "Counts are estimates scaled up from a sample of commits, not exact; \
+                   intervals are 95% confidence intervals"
+                .to_string(),
+        }
+    }
+}
+
+/// A commit on one of the export's branches, from a listing that doesn't read any notes
+struct ListedCommit {
+    sha: String,
+    time: i64,
+    author: String,
+}
+
+/// The commits `log_args` selects, with their commit time and author
+fn list_commits(repo: &Repository, log_args: &[String]) -> Result<Vec<ListedCommit>, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("log".to_string());
+    args.push("--format=%H%x00%ct%x00%an <%ae>".to_string());
+    args.extend(log_args.iter().cloned());
+    let output = exec_git(&args)?;
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\0');
+            Some(ListedCommit {
+                sha: fields.next()?.to_string(),
+                time: fields.next()?.parse().ok()?,
+                author: fields.next()?.to_string(),
+            })
+        })
+        .collect())
+}
+
+/// `log_stats` of exactly `commits`, read in batches to keep command lines short
+fn commit_log_stats(
+    repo: &Repository,
+    commits: &[String],
+) -> Result<Vec<CommitLogStat>, GitAiError> {
+    let mut stats = Vec::with_capacity(commits.len());
+    for batch in commits.chunks(500) {
+        let mut log_args = vec!["--no-walk=unsorted".to_string()];
+        log_args.extend(batch.iter().cloned());
+        stats.extend(log_stats(repo, &log_args)?);
+    }
+    Ok(stats)
+}
+
+/// Add Laplace noise to every count of `export` and recompute its percentages from the noised
+/// counts. One commit moves many counts at once (lines, commits, its agents, languages and
+/// author), so the noise is scaled by the sum of all its changes: the export as a whole spends
+/// `epsilon`, however many counts it has. The noise is drawn from a hash of the repository, the
+/// period and `salt`, so exporting the same data twice gives the same numbers: repeating an
+/// export can't be used to average the noise away.
+pub fn add_noise(export: &mut MetricsExport, repository: &str, epsilon: f64, salt: &str) {
+    let mut seed = Sha256::new();
+    for part in [repository, export.since.as_deref().unwrap_or("all"), salt] {
+        seed.update(part.as_bytes());
+        seed.update([0]);
+    }
+    let seed = seed.finalize();
+    let sensitivity = export.commit_sensitivity.max(1);
+    let scale = sensitivity as f64 / epsilon;
+    let noisy = |key: &str, value: &mut u32| {
+        *value = laplace_noised(*value, scale, &seed, key);
+    };
+
+    let totals = &mut export.totals;
+    noisy("totals.commits", &mut totals.commits);
+    noisy("totals.commits_with_ai", &mut totals.commits_with_ai);
+    noisy("totals.authors", &mut totals.authors);
+    noisy("totals.lines_added", &mut totals.lines_added);
+    noisy("totals.human_additions", &mut totals.human_additions);
+    noisy("totals.mixed_additions", &mut totals.mixed_additions);
+    noisy("totals.ai_additions", &mut totals.ai_additions);
+    noisy("totals.ai_accepted", &mut totals.ai_accepted);
+    totals.ai_percentage = percentage(totals.ai_accepted, totals.lines_added);
+
+    for agent in &mut export.agents {
+        let key = format!("agents.{}", agent.agent);
+        noisy(&format!("{}.commits", key), &mut agent.commits);
+        noisy(&format!("{}.ai_additions", key), &mut agent.ai_additions);
+        noisy(&format!("{}.ai_accepted", key), &mut agent.ai_accepted);
+    }
+    for language in &mut export.languages {
+        let key = format!("languages.{}", language.language);
+        noisy(&format!("{}.lines_added", key), &mut language.lines_added);
+        noisy(&format!("{}.ai_lines", key), &mut language.ai_lines);
+        language.ai_percentage = percentage(language.ai_lines, language.lines_added);
+    }
+    for author in export.authors.iter_mut().flatten() {
+        let key = format!("authors.{}", author.author);
+        noisy(&format!("{}.commits", key), &mut author.commits);
+        noisy(&format!("{}.lines_added", key), &mut author.lines_added);
+        noisy(&format!("{}.ai_accepted", key), &mut author.ai_accepted);
+    }
+
+    export.noise = Some(NoiseInfo {
+        mechanism: "laplace".to_string(),
+        epsilon,
+        sensitivity,
+        note: "Counts include random noise for differential privacy and are not exact; \
+               percentages are computed from the noised counts"
+            .to_string(),
+    });
+}
+
+/// `value` plus Laplace noise of `scale` for `key`, rounded and kept at zero or above
+fn laplace_noised(value: u32, scale: f64, seed: &[u8], key: &str) -> u32 {
+    let digest = Sha256::new()
+        .chain_update(seed)
+        .chain_update(key.as_bytes())
+        .finalize();
+    let mut bits = [0u8; 8];
+    bits.copy_from_slice(&digest[..8]);
+    // Uniform in (-0.5, 0.5), never exactly on either end
+    let uniform = ((u64::from_be_bytes(bits) >> 11) as f64 + 0.5) / (1u64 << 53) as f64 - 0.5;
+    let noise = -scale * uniform.signum() * (1.0 - 2.0 * uniform.abs()).ln();
+    (value as f64 + noise).round().clamp(0.0, u32::MAX as f64) as u32
+}
+
+/// The repository's root commit, which stays the same across clones and machines
+fn repository_identity(repo: &Repository) -> String {
+    let mut args = repo.global_args_for_exec();
+    args.extend(["rev-list", "--max-parents=0", "HEAD"].map(str::to_string));
+    exec_git(&args)
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .and_then(|roots| roots.lines().last().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn percentage(part: u32, whole: u32) -> u32 {
+    if whole == 0 {
+        return 0;
+    }
+    ((part as f64 / whole as f64) * 100.0).round().min(100.0) as u32
+}
+
+/// Language of a file by its name, `other` when it isn't recognized. Names only, so an
+/// export never needs to say which files were changed.
+fn language_for_path(path: &str) -> &'static str {
+    let file_name = path.rsplit('/').next().unwrap_or(path);
+    match file_name {
+        "Dockerfile" => return "dockerfile",
+        "Makefile" | "GNUmakefile" => return "make",
+        _ => {}
+    }
+    let Some((_, extension)) = file_name.rsplit_once('.') else {
+        return "other";
+    };
+    match extension.to_ascii_lowercase().as_str() {
+        "rs" => "rust",
+        "ts" | "tsx" | "mts" | "cts" => "typescript",
+        "js" | "jsx" | "mjs" | "cjs" => "javascript",
+        "py" | "pyi" => "python",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "swift" => "swift",
+        "rb" => "ruby",
+        "php" => "php",
+        "cs" => "csharp",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" | "hh" | "hxx" => "cpp",
+        "m" | "mm" => "objective-c",
+        "scala" => "scala",
+        "dart" => "dart",
+        "ex" | "exs" => "elixir",
+        "sh" | "bash" | "zsh" => "shell",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" | "scss" | "sass" | "less" => "css",
+        "vue" => "vue",
+        "svelte" => "svelte",
+        "json" | "jsonc" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "xml" => "xml",
+        "md" | "mdx" => "markdown",
+        "proto" => "protobuf",
+        "tf" => "terraform",
+        _ => "other",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_language_for_path() {
+        assert_eq!(language_for_path("src/main.rs"), "rust");
+        assert_eq!(language_for_path("web/App.TSX"), "typescript");
+        assert_eq!(language_for_path("deploy/Dockerfile"), "dockerfile");
+        assert_eq!(language_for_path("LICENSE"), "other");
+        assert_eq!(language_for_path("archive.tar.gz"), "other");
+    }
+
+    #[test]
+    fn test_percentage() {
+        assert_eq!(percentage(0, 0), 0);
+        assert_eq!(percentage(1, 3), 33);
+        assert_eq!(percentage(5, 4), 100);
+    }
+
+    fn sample_export(since: Option<&str>) -> MetricsExport {
+        MetricsExport {
+            schema_version: METRICS_SCHEMA_VERSION,
+            generated_at: String::new(),
+            since: since.map(str::to_string),
+            anonymized: true,
+            repository: None,
+            totals: MetricsTotals {
+                commits: 3,
+                lines_added: 200,
+                ai_accepted: 120,
+                ai_percentage: 60,
+                ..Default::default()
+            },
+            agents: vec![AgentMetrics {
+                agent: "cursor".to_string(),
+                commits: 1,
+                ..Default::default()
+            }],
+            languages: Vec::new(),
+            authors: None,
+            noise: None,
+            sampling: None,
+            commit_sensitivity: 40,
+        }
+    }
+
+    #[test]
+    fn test_add_noise_is_deterministic() {
+        let noised = |since, salt| {
+            let mut export = sample_export(since);
+            add_noise(&mut export, "root", 0.5, salt);
+            export
+        };
+        assert_eq!(
+            noised(Some("2026-10-01"), "q4").totals,
+            noised(Some("2026-10-01"), "q4").totals
+        );
+        assert_eq!(noised(None, "").noise.unwrap().mechanism, "laplace");
+
+        // Other salts and periods draw other noise
+        let differing = ["a", "b", "c", "d"]
+            .iter()
+            .filter(|salt| noised(None, salt).totals != noised(None, "").totals)
+            .count();
+        assert!(differing > 0);
+    }
+
+    #[test]
+    fn test_laplace_noised_scales_with_scale() {
+        let keys = ["a", "b", "c", "d", "e", "f", "g", "h"];
+        // A large scale moves counts around, clamped at zero rather than going negative
+        let noised: BTreeSet<u32> = keys
+            .iter()
+            .map(|key| laplace_noised(5, 20.0, b"seed", key))
+            .collect();
+        assert!(noised.len() > 1);
+        // A small one leaves them close to exact
+        for key in keys {
+            assert!(laplace_noised(1000, 0.001, b"seed", key).abs_diff(1000) <= 1);
+        }
+    }
+
+    #[test]
+    fn test_add_noise_spends_epsilon_on_the_whole_export() {
+        let mut export = sample_export(None);
+        add_noise(&mut export, "root", 0.5, "");
+        let noise = export.noise.unwrap();
+        assert_eq!(noise.epsilon, 0.5);
+        assert_eq!(noise.sensitivity, 40);
+
+        // The noise grows with what one commit can change, not just with 1 / epsilon
+        let spread = |sensitivity| {
+            let keys = ["a", "b", "c", "d", "e", "f", "g", "h"];
+            keys.iter()
+                .map(|key| laplace_noised(1000, sensitivity as f64 / 0.5, b"seed", key))
+                .map(|value| value.abs_diff(1000))
+                .sum::<u32>()
+        };
+        assert!(spread(40) > spread(1));
+    }
+}
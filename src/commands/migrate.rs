@@ -0,0 +1,294 @@
+use crate::config::{self, ConfigOrigin, Settings};
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::refs::notes_ref_tip;
+use crate::git::repo_storage::{STORAGE_LAYOUTS, storage_dir_for_layout, storage_layout};
+use crate::git::repository::{Repository, exec_git, exec_git_stdin};
+use std::fs;
+use std::path::PathBuf;
+
+/// Everything one `git-ai migrate` run changes, checked before any of it is done
+#[derive(Debug, Clone, PartialEq)]
+pub struct MigrationPlan {
+    pub notes: Option<NotesMove>,
+    pub storage: Option<StorageMove>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotesMove {
+    pub from: String,
+    pub to: String,
+    /// (old ref, new ref, tip) of the notes ref and its remote tracking refs
+    pub refs: Vec<(String, String, String)>,
+    /// The `.gitai.toml` that will name the new ref
+    pub config_file: PathBuf,
+    /// Set when an environment variable or `-c` override still names the old ref
+    pub overridden_by: Option<ConfigOrigin>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StorageMove {
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+pub fn handle_migrate(args: &[String]) {
+    let mut notes_ref = None;
+    let mut storage = None;
+    let mut dry_run = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--notes-ref" if i + 1 < args.len() => {
+                notes_ref = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--storage" if i + 1 < args.len() => {
+                storage = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--dry-run" => {
+                dry_run = true;
+                i += 1;
+            }
+            _ => {
+                eprintln!("Unknown migrate argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if notes_ref.is_none() && storage.is_none() {
+        eprintln!("migrate requires --notes-ref <name> and/or --storage <layout>");
+        std::process::exit(1);
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let result = plan_migration(&repo, notes_ref.as_deref(), storage.as_deref()).and_then(|plan| {
+        print_plan(&plan, dry_run);
+        if dry_run {
+            Ok(())
+        } else {
+            apply_migration(&repo, &plan)
+        }
+    });
+    if let Err(e) = result {
+        eprintln!("Migration failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Work out what moving to `notes_ref` and the `storage` layout takes, failing if either
+/// destination is already taken
+pub fn plan_migration(
+    repo: &Repository,
+    notes_ref: Option<&str>,
+    storage: Option<&str>,
+) -> Result<MigrationPlan, GitAiError> {
+    let notes = match notes_ref {
+        Some(name) => plan_notes_move(repo, name)?,
+        None => None,
+    };
+
+    let storage = match storage {
+        Some(layout) => {
+            let git_dir = &repo.storage.repo_path;
+            let to = storage_dir_for_layout(git_dir, layout).ok_or_else(|| {
+                let layouts: Vec<&str> = STORAGE_LAYOUTS.iter().map(|(name, _)| *name).collect();
+                GitAiError::Generic(format!(
+                    "Unknown storage layout {} (expected one of: {})",
+                    layout,
+                    layouts.join(", ")
+                ))
+            })?;
+            if layout == storage_layout(git_dir) {
+                None
+            } else if to.exists() {
+                return Err(GitAiError::Generic(format!(
+                    "{} already exists",
+                    to.display()
+                )));
+            } else {
+                Some(StorageMove {
+                    from: repo.storage.ai_dir.clone(),
+                    to,
+                })
+            }
+        }
+        None => None,
+    };
+
+    Ok(MigrationPlan { notes, storage })
+}
+
+fn plan_notes_move(repo: &Repository, name: &str) -> Result<Option<NotesMove>, GitAiError> {
+    let to = format!(
+        "refs/notes/{}",
+        name.strip_prefix("refs/notes/").unwrap_or(name)
+    );
+    let mut args = repo.global_args_for_exec();
+    args.push("check-ref-format".to_string());
+    args.push(to.clone());
+    if exec_git(&args).is_err() {
+        return Err(GitAiError::Generic(format!("Invalid notes ref: {}", name)));
+    }
+
+    let from = repo.notes_ref();
+    if from == to {
+        return Ok(None);
+    }
+    let workdir = repo.workdir().map_err(|_| {
+        GitAiError::Generic("Moving the notes ref needs a working tree for .gitai.toml".to_string())
+    })?;
+
+    let mut refs = Vec::new();
+    if let Some(tip) = notes_ref_tip(repo) {
+        refs.push((from.clone(), to.clone(), tip));
+    }
+    // Tracking refs sit next to the notes ref (refs/notes/ai-remote/origin)
+    let mut args = repo.global_args_for_exec();
+    args.push("for-each-ref".to_string());
+    args.push("--format=%(refname) %(objectname)".to_string());
+    args.push(format!("{}-remote/", from));
+    let output = exec_git(&args)?;
+    for line in String::from_utf8(output.stdout)?.lines() {
+        if let Some((old_ref, tip)) = line.split_once(' ')
+            && let Some(remote) = old_ref.strip_prefix(&format!("{}-remote/", from))
+        {
+            refs.push((
+                old_ref.to_string(),
+                format!("{}-remote/{}", to, remote),
+                tip.to_string(),
+            ));
+        }
+    }
+
+    if let Some((_, taken, _)) = refs.iter().find(|(_, new_ref, _)| {
+        let mut args = repo.global_args_for_exec();
+        args.push("rev-parse".to_string());
+        args.push("--verify".to_string());
+        args.push("-q".to_string());
+        args.push(new_ref.clone());
+        exec_git(&args).is_ok()
+    }) {
+        return Err(GitAiError::Generic(format!(
+            "{} already exists; merge or delete it before migrating",
+            taken
+        )));
+    }
+
+    let resolved = Settings::resolve(Some(&workdir))?;
+    let overridden_by = resolved
+        .values
+        .get("notes_ref")
+        .map(|(_, origin)| origin.clone())
+        .filter(|origin| matches!(origin, ConfigOrigin::Env(_) | ConfigOrigin::Cli));
+
+    Ok(Some(NotesMove {
+        from,
+        to,
+        refs,
+        config_file: workdir.join(config::REPO_CONFIG_FILE),
+        overridden_by,
+    }))
+}
+
+/// Move the storage directory, then rename the refs in one transaction, then point
+/// `.gitai.toml` at the new ref. A failing step undoes the ones before it.
+pub fn apply_migration(repo: &Repository, plan: &MigrationPlan) -> Result<(), GitAiError> {
+    if let Some(storage) = &plan.storage {
+        if let Some(parent) = storage.to.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        // A rename within the git dir is atomic
+        fs::rename(&storage.from, &storage.to)?;
+    }
+    let undo_storage = || {
+        if let Some(storage) = &plan.storage {
+            let _ = fs::rename(&storage.to, &storage.from);
+        }
+    };
+
+    let Some(notes) = &plan.notes else {
+        return Ok(());
+    };
+    if let Err(e) = rename_refs(repo, &notes.refs, false) {
+        undo_storage();
+        return Err(e);
+    }
+    let name = notes.to.strip_prefix("refs/notes/").unwrap_or(&notes.to);
+    if let Err(e) =
+        config::write_toml_setting(&notes.config_file, "notes_ref", toml::Value::from(name))
+    {
+        let _ = rename_refs(repo, &notes.refs, true);
+        undo_storage();
+        return Err(e);
+    }
+    Ok(())
+}
+
+/// Move every (old, new, tip) ref in a single `update-ref --stdin` transaction, or back
+fn rename_refs(
+    repo: &Repository,
+    refs: &[(String, String, String)],
+    reverse: bool,
+) -> Result<(), GitAiError> {
+    if refs.is_empty() {
+        return Ok(());
+    }
+    let mut script = String::new();
+    for (old_ref, new_ref, tip) in refs {
+        let (from, to) = if reverse {
+            (new_ref, old_ref)
+        } else {
+            (old_ref, new_ref)
+        };
+        script.push_str(&format!("create {} {}\n", to, tip));
+        script.push_str(&format!("delete {} {}\n", from, tip));
+    }
+    let mut args = repo.global_args_for_exec();
+    args.push("update-ref".to_string());
+    args.push("--stdin".to_string());
+    exec_git_stdin(&args, script.as_bytes())?;
+    Ok(())
+}
+
+fn print_plan(plan: &MigrationPlan, dry_run: bool) {
+    if plan.notes.is_none() && plan.storage.is_none() {
+        println!("Nothing to migrate");
+        return;
+    }
+    let verb = if dry_run { "Would move" } else { "Moving" };
+    if let Some(storage) = &plan.storage {
+        println!(
+            "{} storage {} -> {}",
+            verb,
+            storage.from.display(),
+            storage.to.display()
+        );
+    }
+    if let Some(notes) = &plan.notes {
+        for (old_ref, new_ref, _) in &notes.refs {
+            println!("{} {} -> {}", verb, old_ref, new_ref);
+        }
+        println!(
+            "{} notes_ref in {}",
+            if dry_run { "Would set" } else { "Setting" },
+            notes.config_file.display()
+        );
+        if let Some(origin) = &notes.overridden_by {
+            println!(
+                "Warning: notes_ref is also set by {}, which takes precedence; update it to {}",
+                origin, notes.to
+            );
+        }
+    }
+}
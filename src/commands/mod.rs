@@ -1,10 +1,40 @@
 pub mod blame;
+pub mod check;
 pub mod checkpoint;
 pub mod checkpoint_agent;
+pub mod backfill;
+pub mod backfill_squash;
 pub mod ci_handlers;
+pub mod compat;
+pub mod conflicts;
+pub mod explain_line;
+pub mod export;
+pub mod gc;
 pub mod git_ai_handlers;
 pub mod git_handlers;
 pub mod hooks;
+pub mod init;
+pub mod install;
 pub mod install_hooks;
+pub mod introspect;
+pub mod last_outcome;
+pub mod latency;
+pub mod log;
+pub mod maintenance;
+pub mod me;
+pub mod metrics;
+pub mod migrate;
+pub mod ownership;
+pub mod process_queue;
+pub mod prompt_segment;
+pub mod range_diff;
+pub mod redact;
+pub mod release_note;
+pub mod retention;
+pub mod sessions;
+pub mod show_config;
+pub mod show_prompt;
 pub mod squash_authorship;
 pub mod stats_delta;
+pub mod tui;
+pub mod watch;
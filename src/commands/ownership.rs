@@ -0,0 +1,517 @@
+use crate::authorship::authorship_log::PromptRecord;
+use crate::commands::blame::GitAiBlameOptions;
+use crate::config::{DirectoryPolicy, Settings, normalize_directory};
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::repository::{Repository, exec_git};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Who the current lines of a file or directory tree belong to, from `git-ai blame`
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OwnershipReport {
+    /// The path asked about, relative to the repository root (empty for the root)
+    pub path: String,
+    pub total_lines: u32,
+    pub ai_lines: u32,
+    pub human_lines: u32,
+    pub ai_percentage: u32,
+    /// By lines, most first
+    pub agents: Vec<AgentOwnership>,
+    /// One per prompt record still behind at least one line, by lines, most first
+    pub sessions: Vec<SessionOwnership>,
+    /// By path
+    pub files: Vec<FileOwnership>,
+    /// One per `directory_policies` subtree, then the files outside all of them. Only filled in
+    /// with `--directories`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub directories: Vec<DirectoryOwnership>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct AgentOwnership {
+    pub tool: String,
+    pub lines: u32,
+    pub percentage: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SessionOwnership {
+    pub prompt_id: String,
+    /// The agent's own id for the session (conversation or thread id)
+    pub session_id: String,
+    pub tool: String,
+    pub model: String,
+    pub lines: u32,
+    pub percentage: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct FileOwnership {
+    pub file: String,
+    pub total_lines: u32,
+    pub ai_lines: u32,
+    pub ai_percentage: u32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct DirectoryOwnership {
+    /// The subtree, empty for the files outside every configured one
+    pub directory: String,
+    pub policy: String,
+    pub files: usize,
+    pub total_lines: u32,
+    pub ai_lines: u32,
+    pub ai_percentage: u32,
+}
+
+pub fn handle_ownership(args: &[String]) {
+    let mut path = None;
+    let mut json_output = false;
+    let mut by_directory = false;
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json_output = true,
+            "--directories" => by_directory = true,
+            _ if path.is_none() && !arg.starts_with("--") => path = Some(arg.clone()),
+            _ => {
+                eprintln!("Unknown ownership argument: {}", arg);
+                std::process::exit(1);
+            }
+        }
+    }
+    let path = path.unwrap_or_else(|| ".".to_string());
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let mut report = match build_report(&repo, &path) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Ownership failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if by_directory {
+        report.directories = directory_totals(&report.files, repo.settings());
+    }
+
+    if json_output {
+        match serde_json::to_string(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize report: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        print_report(&report);
+    }
+}
+
+/// Blame every tracked file at or under `path` (relative to the current directory) and add up
+/// whose each line is. A line is AI when the authorship note of the commit blame traces it to
+/// attributes it to a prompt; everything else, uncommitted lines included, is human.
+pub fn build_report(repo: &Repository, path: &str) -> Result<OwnershipReport, GitAiError> {
+    let relative_path = repo_relative_path(repo, Path::new(path))?;
+    let files = tracked_files(repo, &relative_path)?;
+    if files.is_empty() {
+        return Err(GitAiError::Generic(format!("No tracked files at {}", path)));
+    }
+
+    let options = GitAiBlameOptions {
+        use_prompt_hashes_as_names: true,
+        return_human_authors_as_human: true,
+        no_output: true,
+        ..Default::default()
+    };
+    let mut report = OwnershipReport {
+        path: relative_path,
+        ..Default::default()
+    };
+    let mut agents: BTreeMap<String, u32> = BTreeMap::new();
+    let mut sessions: BTreeMap<String, SessionOwnership> = BTreeMap::new();
+
+    for file in files {
+        let Ok(content) = std::fs::read(repo.workdir()?.join(&file)) else {
+            // Deleted in the working copy; it has no current lines
+            continue;
+        };
+        if content.contains(&0) || content.is_empty() {
+            continue;
+        }
+        let (line_authors, prompt_records) = repo.blame(&file, &options)?;
+        add_file(
+            &mut report,
+            &mut agents,
+            &mut sessions,
+            file,
+            &line_authors,
+            &prompt_records,
+        );
+    }
+
+    report.human_lines = report.total_lines - report.ai_lines;
+    report.ai_percentage = percentage(report.ai_lines, report.total_lines);
+
+    report.agents = agents
+        .into_iter()
+        .map(|(tool, lines)| AgentOwnership {
+            tool,
+            lines,
+            percentage: percentage(lines, report.total_lines),
+        })
+        .collect();
+    report
+        .agents
+        .sort_by(|a, b| b.lines.cmp(&a.lines).then_with(|| a.tool.cmp(&b.tool)));
+
+    report.sessions = sessions
+        .into_values()
+        .map(|mut session| {
+            session.percentage = percentage(session.lines, report.total_lines);
+            session
+        })
+        .collect();
+    report.sessions.sort_by(|a, b| {
+        b.lines
+            .cmp(&a.lines)
+            .then_with(|| a.prompt_id.cmp(&b.prompt_id))
+    });
+
+    Ok(report)
+}
+
+/// Count one file's blamed lines into the report. `line_authors` maps each line to its prompt
+/// id, or to `human`.
+fn add_file(
+    report: &mut OwnershipReport,
+    agents: &mut BTreeMap<String, u32>,
+    sessions: &mut BTreeMap<String, SessionOwnership>,
+    file: String,
+    line_authors: &HashMap<u32, String>,
+    prompt_records: &HashMap<String, PromptRecord>,
+) {
+    let mut ai_lines = 0;
+    for author in line_authors.values() {
+        let Some(prompt) = prompt_records.get(author) else {
+            continue;
+        };
+        ai_lines += 1;
+        *agents.entry(prompt.agent_id.tool.clone()).or_default() += 1;
+        sessions
+            .entry(author.clone())
+            .or_insert_with(|| SessionOwnership {
+                prompt_id: author.clone(),
+                session_id: prompt.agent_id.id.clone(),
+                tool: prompt.agent_id.tool.clone(),
+                model: prompt.agent_id.model.clone(),
+                ..Default::default()
+            })
+            .lines += 1;
+    }
+
+    let total_lines = line_authors.len() as u32;
+    report.total_lines += total_lines;
+    report.ai_lines += ai_lines;
+    report.files.push(FileOwnership {
+        file,
+        total_lines,
+        ai_lines,
+        ai_percentage: percentage(ai_lines, total_lines),
+    });
+}
+
+/// Add up the files by the `directory_policies` subtree they fall in. Every configured subtree
+/// is listed, files or not, so a policy that matches nothing shows up.
+fn directory_totals(files: &[FileOwnership], settings: &Settings) -> Vec<DirectoryOwnership> {
+    let mut directories: BTreeMap<String, DirectoryOwnership> = settings
+        .directory_policies
+        .iter()
+        .map(|(directory, policy)| {
+            let directory = normalize_directory(directory).to_string();
+            (
+                directory.clone(),
+                DirectoryOwnership {
+                    directory,
+                    policy: policy.to_string(),
+                    ..Default::default()
+                },
+            )
+        })
+        .collect();
+    let mut rest = DirectoryOwnership {
+        policy: DirectoryPolicy::Track.to_string(),
+        ..Default::default()
+    };
+
+    for file in files {
+        let totals = match settings.directory_policy(&file.file) {
+            Some((directory, _)) => directories.get_mut(directory).unwrap_or(&mut rest),
+            None => &mut rest,
+        };
+        totals.files += 1;
+        totals.total_lines += file.total_lines;
+        totals.ai_lines += file.ai_lines;
+    }
+
+    directories
+        .into_values()
+        .chain((rest.files > 0).then_some(rest))
+        .map(|mut totals| {
+            totals.ai_percentage = percentage(totals.ai_lines, totals.total_lines);
+            totals
+        })
+        .collect()
+}
+
+/// `path` relative to the repository root, resolving it against the current directory
+fn repo_relative_path(repo: &Repository, path: &Path) -> Result<String, GitAiError> {
+    let absolute = std::env::current_dir()?.join(path);
+    let canonical = absolute
+        .canonicalize()
+        .map_err(|e| GitAiError::Generic(format!("Failed to resolve {}: {}", path.display(), e)))?;
+    let root = repo.workdir()?.canonicalize()?;
+    let relative = canonical.strip_prefix(&root).map_err(|_| {
+        GitAiError::Generic(format!(
+            "{} is not within repository root {}",
+            path.display(),
+            root.display()
+        ))
+    })?;
+    Ok(relative.to_string_lossy().replace('\\', "/"))
+}
+
+/// Tracked files at or under `relative_path`, relative to the repository root
+fn tracked_files(repo: &Repository, relative_path: &str) -> Result<Vec<String>, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("ls-files".to_string());
+    args.push("--full-name".to_string());
+    args.push("-z".to_string());
+    args.push("--".to_string());
+    args.push(format!(":/{}", relative_path));
+
+    let output = exec_git(&args)?;
+    Ok(String::from_utf8(output.stdout)?
+        .split('\0')
+        .filter(|path| !path.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+fn percentage(part: u32, whole: u32) -> u32 {
+    if whole == 0 {
+        return 0;
+    }
+    ((part as f64 / whole as f64) * 100.0).round().min(100.0) as u32
+}
+
+fn print_report(report: &OwnershipReport) {
+    let path = if report.path.is_empty() {
+        "."
+    } else {
+        report.path.as_str()
+    };
+    println!(
+        "{} ({} file{}, {} lines)",
+        path,
+        report.files.len(),
+        if report.files.len() == 1 { "" } else { "s" },
+        report.total_lines
+    );
+    println!(
+        "  AI     {:>6} lines  {:>3}%",
+        report.ai_lines, report.ai_percentage
+    );
+    println!(
+        "  Human  {:>6} lines  {:>3}%",
+        report.human_lines,
+        percentage(report.human_lines, report.total_lines)
+    );
+
+    if !report.agents.is_empty() {
+        println!();
+        println!("By agent:");
+        let width = report
+            .agents
+            .iter()
+            .map(|agent| agent.tool.len())
+            .max()
+            .unwrap_or(0);
+        for agent in &report.agents {
+            println!(
+                "  {:<width$}  {:>6} lines  {:>3}%",
+                agent.tool,
+                agent.lines,
+                agent.percentage,
+                width = width
+            );
+        }
+    }
+
+    if !report.directories.is_empty() {
+        println!();
+        println!("By directory policy:");
+        let label = |totals: &DirectoryOwnership| {
+            if totals.directory.is_empty() {
+                "(elsewhere)".to_string()
+            } else {
+                totals.directory.clone()
+            }
+        };
+        let width = report
+            .directories
+            .iter()
+            .map(|d| label(d).len())
+            .max()
+            .unwrap_or(0);
+        let policy_width = report
+            .directories
+            .iter()
+            .map(|d| d.policy.len())
+            .max()
+            .unwrap_or(0);
+        for totals in &report.directories {
+            println!(
+                "  {:<width$}  {:<policy_width$}  {:>4} file{}  {:>6} lines  {:>6} AI  {:>3}%",
+                label(totals),
+                totals.policy,
+                totals.files,
+                if totals.files == 1 { " " } else { "s" },
+                totals.total_lines,
+                totals.ai_lines,
+                totals.ai_percentage,
+                width = width,
+                policy_width = policy_width
+            );
+        }
+    }
+
+    if !report.sessions.is_empty() {
+        println!();
+        println!("By session:");
+        for session in &report.sessions {
+            println!(
+                "  {}  {} ({})  {} lines  {}%",
+                session.prompt_id, session.tool, session.model, session.lines, session.percentage
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authorship::working_log::AgentId;
+
+    fn prompt(tool: &str, session: &str) -> PromptRecord {
+        PromptRecord {
+            agent_id: AgentId {
+                tool: tool.to_string(),
+                id: session.to_string(),
+                model: "model".to_string(),
+            },
+            human_author: None,
+            messages: Vec::new(),
+            total_additions: 0,
+            total_deletions: 0,
+            accepted_lines: 0,
+            overriden_lines: 0,
+            summary: None,
+            environments: Vec::new(),
+            generated_at: None,
+        }
+    }
+
+    #[test]
+    fn test_add_file_counts_agents_and_sessions() {
+        let prompt_records = HashMap::from([
+            ("p1".to_string(), prompt("claude", "s1")),
+            ("p2".to_string(), prompt("claude", "s2")),
+        ]);
+        let line_authors = HashMap::from([
+            (1, "p1".to_string()),
+            (2, "p1".to_string()),
+            (3, "p2".to_string()),
+            (4, "human".to_string()),
+        ]);
+
+        let mut report = OwnershipReport::default();
+        let mut agents = BTreeMap::new();
+        let mut sessions = BTreeMap::new();
+        add_file(
+            &mut report,
+            &mut agents,
+            &mut sessions,
+            "a.rs".to_string(),
+            &line_authors,
+            &prompt_records,
+        );
+
+        assert_eq!(report.total_lines, 4);
+        assert_eq!(report.ai_lines, 3);
+        assert_eq!(report.files[0].ai_percentage, 75);
+        assert_eq!(agents["claude"], 3);
+        assert_eq!(sessions["p1"].lines, 2);
+        assert_eq!(sessions["p2"].session_id, "s2");
+    }
+
+    #[test]
+    fn test_directory_totals() {
+        let file = |file: &str, total_lines, ai_lines| FileOwnership {
+            file: file.to_string(),
+            total_lines,
+            ai_lines,
+            ai_percentage: percentage(ai_lines, total_lines),
+        };
+        let files = [
+            file("gen/client.ts", 100, 0),
+            file("gen/handwritten/util.ts", 10, 5),
+            file("proto/user.ts", 40, 40),
+            file("src/main.rs", 30, 10),
+        ];
+        let settings = Settings {
+            directory_policies: BTreeMap::from([
+                ("gen/".to_string(), DirectoryPolicy::Ignore),
+                ("gen/handwritten".to_string(), DirectoryPolicy::Track),
+                (
+                    "proto".to_string(),
+                    DirectoryPolicy::Tool("protoc".to_string()),
+                ),
+                ("docs".to_string(), DirectoryPolicy::Ignore),
+            ]),
+            ..Default::default()
+        };
+
+        let totals = directory_totals(&files, &settings);
+        let summary: Vec<(&str, &str, usize, u32, u32)> = totals
+            .iter()
+            .map(|d| {
+                (
+                    d.directory.as_str(),
+                    d.policy.as_str(),
+                    d.files,
+                    d.total_lines,
+                    d.ai_lines,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("docs", "ignore", 0, 0, 0),
+                ("gen", "ignore", 1, 100, 0),
+                ("gen/handwritten", "track", 1, 10, 5),
+                ("proto", "tool:protoc", 1, 40, 40),
+                ("", "track", 1, 30, 10),
+            ]
+        );
+        assert_eq!(totals[3].ai_percentage, 100);
+    }
+}
@@ -0,0 +1,356 @@
+use crate::authorship::rebase_authorship::rewrite_authorship_if_needed;
+use crate::commands::hooks::hook_health::{self, POST_COMMIT};
+use crate::commands::install_hooks::get_current_binary_path;
+use crate::error::GitAiError;
+use crate::git::detached::unix_now;
+use crate::git::refs::show_authorship_note;
+use crate::git::repo_registry::{RepoRegistry, registry_path};
+use crate::git::repo_storage::QueuedTask;
+use crate::git::repository::Repository;
+use crate::git::rewrite_log::RewriteLogEvent;
+use crate::git::{find_repository, find_repository_in_path};
+use crate::utils::debug_log;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+/// Failed attempts before a task is set aside in `queue/failed`
+const MAX_ATTEMPTS: u32 = 3;
+/// How often `--watch` checks the queues
+const DEFAULT_WATCH_INTERVAL: Duration = Duration::from_secs(2);
+/// How often `--watch` rereads the repository registry
+const REGISTRY_RELOAD: Duration = Duration::from_secs(60);
+/// How long a run waits for the run holding the queue before giving up
+const LOCK_WAIT: Duration = Duration::from_secs(120);
+/// A lock older than this was left behind by a run that died
+const STALE_LOCK: Duration = Duration::from_secs(600);
+
+pub fn handle_process_queue(args: &[String]) {
+    let mut status = false;
+    let mut retry = false;
+    let mut watch = false;
+    let mut interval = DEFAULT_WATCH_INTERVAL;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--status" => status = true,
+            "--retry" => retry = true,
+            "--watch" => watch = true,
+            "--interval" if i + 1 < args.len() => {
+                match args[i + 1].parse::<u64>() {
+                    Ok(secs) if secs > 0 => interval = Duration::from_secs(secs),
+                    _ => {
+                        eprintln!("--interval takes a number of seconds");
+                        std::process::exit(1);
+                    }
+                }
+                i += 1;
+            }
+            _ => {
+                eprintln!("Unknown process-queue argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+        i += 1;
+    }
+
+    if watch {
+        watch_registered_repositories(interval);
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if status {
+        print_status(&repo);
+        return;
+    }
+
+    if retry {
+        match repo.storage.retry_failed_tasks() {
+            Ok(count) => println!("Queued {} failed task(s) again", count),
+            Err(e) => {
+                eprintln!("Failed to requeue failed tasks: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    match process_queue(&repo) {
+        Ok(attributed) => println!("Attributed {} queued commit(s)", attributed),
+        Err(e) => {
+            eprintln!("process-queue failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Work through the queues of every repository git-ai has registered, checking every
+/// `interval` until killed. Suited to running as a login service.
+fn watch_registered_repositories(interval: Duration) -> ! {
+    let mut repositories: Vec<Repository> = Vec::new();
+    let mut loaded_at: Option<Instant> = None;
+    loop {
+        // Pick up repositories registered since the last look
+        if loaded_at.is_none_or(|loaded_at| loaded_at.elapsed() >= REGISTRY_RELOAD) {
+            let paths = match registry_path() {
+                Some(path) => RepoRegistry::load(&path).existing_repositories(),
+                None => Vec::new(),
+            };
+            repositories = paths
+                .iter()
+                .filter_map(|path| find_repository_in_path(&path.to_string_lossy()).ok())
+                .collect();
+            loaded_at = Some(Instant::now());
+        }
+        for repo in &repositories {
+            finish_pending_work(repo);
+        }
+        std::thread::sleep(interval);
+    }
+}
+
+fn print_status(repo: &Repository) {
+    let settings = repo.settings();
+    let mode = if settings.defer_post_commit {
+        "always deferred"
+    } else if settings.defer_slow_hooks {
+        "deferred when slow"
+    } else {
+        "inline"
+    };
+    println!(
+        "Hook budget {}ms, post-commit {}",
+        settings.hook_budget_ms, mode
+    );
+    println!(
+        "{} commit(s) waiting to be attributed",
+        repo.storage.pending_tasks()
+    );
+    let failed = repo.storage.failed_tasks();
+    if failed > 0 {
+        println!(
+            "{} commit(s) failed to be attributed {} times, see {} (requeue with --retry)",
+            failed,
+            MAX_ATTEMPTS,
+            repo.storage.queue.join("failed").display()
+        );
+    }
+
+    let health = repo.storage.read_hook_health();
+    if health.hooks.is_empty() {
+        return;
+    }
+    println!();
+    for (hook, timings) in &health.hooks {
+        println!(
+            "  {:<18}{:>6} runs {:>6} over budget ({} in a row)   last {:>6}ms   max {:>6}ms{}",
+            hook,
+            timings.runs,
+            timings.over_budget_runs,
+            timings.over_budget_streak,
+            timings.last_ms,
+            timings.max_ms,
+            if hook_health::is_deferred(repo, hook) {
+                "   deferred"
+            } else {
+                ""
+            }
+        );
+    }
+}
+
+/// Queue a commit's attribution for a background `git-ai process-queue` run. If it can't be
+/// queued the work is done right away like it would be without deferral.
+pub fn defer_post_commit(
+    repository: &mut Repository,
+    event: RewriteLogEvent,
+    commit_author: String,
+    supress_output: bool,
+) {
+    let task = QueuedTask {
+        event: event.clone(),
+        commit_author: commit_author.clone(),
+        queued_at: unix_now(),
+        attempts: 0,
+        last_error: None,
+    };
+    if let Err(e) = repository.storage.enqueue_task(&task) {
+        debug_log(&format!(
+            "Failed to queue post-commit, running it now: {}",
+            e
+        ));
+        repository.handle_rewrite_log_event(event, commit_author, supress_output, true);
+        return;
+    }
+
+    repository.handle_rewrite_log_event(event, commit_author, supress_output, false);
+    // Not fatal: queued tasks are also run by the next git-ai invocation in the repository
+    if let Err(e) = spawn_background(repository) {
+        repository
+            .hook_errors
+            .push(format!("Failed to start git-ai process-queue: {}", e));
+    }
+}
+
+/// Start `git-ai process-queue` detached from the terminal, so git returns right away and a
+/// Ctrl-C at the prompt doesn't reach it
+fn spawn_background(repository: &Repository) -> Result<(), GitAiError> {
+    let mut command = Command::new(get_current_binary_path()?);
+    command
+        .arg("process-queue")
+        .current_dir(repository.workdir()?)
+        .env_remove("GIT_AI")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    command.spawn()?;
+    Ok(())
+}
+
+/// Finish deferred post-commit work before anything else reads or rewrites the working logs
+/// and notes it touches. Does nothing unless tasks are queued.
+pub fn finish_pending_work(repository: &Repository) {
+    if repository.storage.pending_tasks() == 0 {
+        return;
+    }
+    if let Err(e) = process_queue(repository) {
+        eprintln!("git-ai: failed to attribute queued commits: {}", e);
+    }
+}
+
+/// Run every queued task, oldest first, including ones queued while this runs. Returns how
+/// many commits were attributed. A task that fails stays queued for the next run until it
+/// has failed [`MAX_ATTEMPTS`] times.
+pub fn process_queue(repository: &Repository) -> Result<usize, GitAiError> {
+    let storage = &repository.storage;
+    let _lock = QueueLock::acquire(storage.queue.with_extension("lock"))?;
+
+    let mut attempted: HashSet<PathBuf> = HashSet::new();
+    let mut attributed = 0;
+    loop {
+        let paths: Vec<PathBuf> = storage
+            .queued_task_paths()?
+            .into_iter()
+            .filter(|path| !attempted.contains(path))
+            .collect();
+        if paths.is_empty() {
+            return Ok(attributed);
+        }
+
+        for path in paths {
+            attempted.insert(path.clone());
+            let mut task = match storage.read_queued_task(&path) {
+                Ok(task) => task,
+                Err(e) => {
+                    eprintln!("git-ai: unreadable queued task {}: {}", path.display(), e);
+                    storage.fail_queued_task(&path)?;
+                    continue;
+                }
+            };
+
+            match run_task(repository, &task) {
+                Ok(done) => {
+                    storage.remove_queued_task(&path)?;
+                    if done {
+                        attributed += 1;
+                    }
+                }
+                Err(e) => {
+                    eprintln!("git-ai: failed to attribute queued commit: {}", e);
+                    task.attempts += 1;
+                    task.last_error = Some(e.to_string());
+                    storage.update_queued_task(&path, &task)?;
+                    if task.attempts >= MAX_ATTEMPTS {
+                        storage.fail_queued_task(&path)?;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Returns false when there was nothing left to do
+fn run_task(repository: &Repository, task: &QueuedTask) -> Result<bool, GitAiError> {
+    let RewriteLogEvent::Commit { commit } = &task.event else {
+        debug_log("Skipping queued task that isn't a commit");
+        return Ok(false);
+    };
+    // Attributed by a run that was interrupted before it could remove the task
+    if show_authorship_note(repository, &commit.commit_sha).is_some() {
+        return Ok(false);
+    }
+
+    let started = Instant::now();
+    let log = repository.storage.read_rewrite_events()?;
+    rewrite_authorship_if_needed(
+        repository,
+        &task.event,
+        task.commit_author.clone(),
+        &log,
+        true,
+    )?;
+    hook_health::record_deferred_run(repository, POST_COMMIT, started.elapsed());
+    Ok(true)
+}
+
+/// Only one run works through the queue at a time
+struct QueueLock {
+    path: PathBuf,
+}
+
+impl QueueLock {
+    fn acquire(path: PathBuf) -> Result<QueueLock, GitAiError> {
+        let deadline = Instant::now() + LOCK_WAIT;
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(_) => return Ok(QueueLock { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    if is_stale(&path) {
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+                    if Instant::now() >= deadline {
+                        return Err(GitAiError::Generic(format!(
+                            "another git-ai process-queue is still running ({} exists)",
+                            path.display()
+                        )));
+                    }
+                    std::thread::sleep(Duration::from_millis(50));
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+impl Drop for QueueLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn is_stale(path: &Path) -> bool {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.elapsed().ok())
+        .is_some_and(|age| age > STALE_LOCK)
+}
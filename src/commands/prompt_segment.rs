@@ -0,0 +1,242 @@
+use crate::authorship::attribution_loss::uncommitted_ai_lines;
+use crate::authorship::stats::stats_for_commit_stats;
+use crate::commands::install_hooks::get_current_binary_path;
+use crate::error::GitAiError;
+use crate::git::find_repository_cached;
+use crate::git::refs::show_authorship_note;
+use crate::git::repo_storage::PromptSegmentCache;
+use crate::git::repository::Repository;
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// How long the prompt waits for a cache miss to be computed before showing what's cached
+pub const DEFAULT_BUDGET_MS: u64 = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Human,
+    Short,
+    Json,
+}
+
+pub fn handle_prompt_segment(args: &[String]) {
+    let mut format = Format::Human;
+    let mut budget_ms = DEFAULT_BUDGET_MS;
+    let mut refresh = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--short" => {
+                format = Format::Short;
+                i += 1;
+            }
+            "--json" => {
+                format = Format::Json;
+                i += 1;
+            }
+            "--budget-ms" if i + 1 < args.len() => match args[i + 1].parse::<u64>() {
+                Ok(ms) => {
+                    budget_ms = ms;
+                    i += 2;
+                }
+                Err(_) => {
+                    eprintln!("--budget-ms expects milliseconds, got {}", args[i + 1]);
+                    std::process::exit(1);
+                }
+            },
+            "--refresh" => {
+                refresh = true;
+                i += 1;
+            }
+            _ => {
+                eprintln!("Unknown prompt-segment argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Prompts run this in every directory; outside a repository there's just nothing to show
+    let current_dir = std::env::current_dir()
+        .map(|dir| dir.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let Ok(repo) = find_repository_cached(&vec!["-C".to_string(), current_dir]) else {
+        return;
+    };
+
+    if refresh {
+        if let Ok(segment) = compute(&repo) {
+            let _ = repo.storage.write_prompt_segment(&segment);
+        }
+        return;
+    }
+
+    let (segment, stale) = match segment(&repo, Duration::from_millis(budget_ms)) {
+        Ok(result) => result,
+        Err(_) => return,
+    };
+
+    match format {
+        Format::Human => {
+            let line = format_human(&segment);
+            if !line.is_empty() {
+                println!("{}", line);
+            }
+        }
+        Format::Short => println!("{}", format_short(&segment, stale)),
+        Format::Json => {
+            let json = serde_json::json!({
+                "pending_ai_lines": segment.pending_ai_lines,
+                "last_commit_ai_percent": segment.last_commit_ai_percent,
+                "stale": stale,
+            });
+            println!("{}", json);
+        }
+    }
+}
+
+/// The segment for the repository's current state, and whether it's stale. A cache miss is
+/// computed on a worker thread; when that takes longer than `budget` the last cached segment
+/// is returned as stale and a background `git-ai prompt-segment --refresh` fills the cache for
+/// the next prompt.
+pub fn segment(
+    repo: &Repository,
+    budget: Duration,
+) -> Result<(PromptSegmentCache, bool), GitAiError> {
+    let head = head_sha(repo);
+    let stamp = working_log_stamp(repo, &head);
+    let cached = repo.storage.read_prompt_segment();
+    if let Some(cached) = &cached
+        && cached.head == head
+        && cached.working_log_stamp == stamp
+    {
+        return Ok((cached.clone(), false));
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let worker_repo = repo.clone();
+    std::thread::spawn(move || {
+        let _ = sender.send(compute(&worker_repo));
+    });
+
+    match receiver.recv_timeout(budget) {
+        Ok(segment) => {
+            let segment = segment?;
+            let _ = repo.storage.write_prompt_segment(&segment);
+            Ok((segment, false))
+        }
+        Err(_) => {
+            let _ = spawn_refresh(repo);
+            Ok((cached.unwrap_or_default(), true))
+        }
+    }
+}
+
+/// Count the working log's pending AI lines and HEAD's AI share from scratch
+pub fn compute(repo: &Repository) -> Result<PromptSegmentCache, GitAiError> {
+    let head = head_sha(repo);
+    let working_log_stamp = working_log_stamp(repo, &head);
+
+    let pending_ai_lines = uncommitted_ai_lines(repo, &head)?.lines;
+
+    let last_commit_ai_percent = if head == "initial" || show_authorship_note(repo, &head).is_none()
+    {
+        None
+    } else {
+        let stats = stats_for_commit_stats(repo, &head, "HEAD")?;
+        let added = stats.counted_added_lines();
+        (added > 0)
+            .then(|| (stats.ai_additions.min(added) as f64 / added as f64 * 100.0).round() as u32)
+    };
+
+    Ok(PromptSegmentCache {
+        head,
+        working_log_stamp,
+        pending_ai_lines,
+        last_commit_ai_percent,
+    })
+}
+
+/// HEAD's commit, or "initial" before the first commit, matching the working log's name
+fn head_sha(repo: &Repository) -> String {
+    repo.revparse_single("HEAD")
+        .map(|head| head.id())
+        .unwrap_or_else(|_| "initial".to_string())
+}
+
+/// Size and modification time of the working log's checkpoints, which change on every append
+fn working_log_stamp(repo: &Repository, head: &str) -> Option<(u64, u128)> {
+    let metadata = std::fs::metadata(
+        repo.storage
+            .working_logs
+            .join(head)
+            .join("checkpoints.jsonl"),
+    )
+    .ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((metadata.len(), modified.as_nanos()))
+}
+
+/// Recompute the segment detached from the shell, so the prompt never waits on it
+fn spawn_refresh(repo: &Repository) -> Result<(), GitAiError> {
+    let mut command = Command::new(get_current_binary_path()?);
+    command
+        .args(["prompt-segment", "--refresh"])
+        .current_dir(repo.workdir()?)
+        .env_remove("GIT_AI")
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null());
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        command.process_group(0);
+    }
+    command.spawn()?;
+    Ok(())
+}
+
+/// e.g. `ai +12 last 70%`, or nothing when there's neither pending AI work nor AI in HEAD
+fn format_human(segment: &PromptSegmentCache) -> String {
+    let mut parts = Vec::new();
+    if segment.pending_ai_lines > 0 {
+        parts.push(format!("ai +{}", segment.pending_ai_lines));
+    }
+    if let Some(percent) = segment.last_commit_ai_percent
+        && percent > 0
+    {
+        parts.push(format!("last {}%", percent));
+    }
+    parts.join(" ")
+}
+
+/// `<pending ai lines> <last commit ai %, or -> <1 if stale, else 0>`
+fn format_short(segment: &PromptSegmentCache, stale: bool) -> String {
+    format!(
+        "{} {} {}",
+        segment.pending_ai_lines,
+        segment
+            .last_commit_ai_percent
+            .map(|percent| percent.to_string())
+            .unwrap_or_else(|| "-".to_string()),
+        if stale { 1 } else { 0 }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_segment_formats() {
+        let mut segment = PromptSegmentCache::default();
+        assert_eq!(format_human(&segment), "");
+        assert_eq!(format_short(&segment, false), "0 - 0");
+
+        segment.pending_ai_lines = 12;
+        segment.last_commit_ai_percent = Some(70);
+        assert_eq!(format_human(&segment), "ai +12 last 70%");
+        assert_eq!(format_short(&segment, true), "12 70 1");
+    }
+}
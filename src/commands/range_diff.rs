@@ -0,0 +1,377 @@
+use crate::authorship::authorship_log_serialization::AuthorshipLog;
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::refs::get_authorship;
+use crate::git::repository::{Repository, exec_git};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// What happened to a commit's AI attribution when it was rewritten
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AttributionStatus {
+    /// Every AI line of the old commit is attributed in the new one
+    Preserved,
+    /// The new commit has a note, but fewer or different AI lines
+    Partial,
+    /// The old commit had AI lines and the new one has none
+    Lost,
+    /// The old commit had no AI lines to carry over
+    NoAttribution,
+    /// Only in the old range
+    Dropped,
+    /// Only in the new range
+    Added,
+}
+
+impl AttributionStatus {
+    fn label(&self) -> &'static str {
+        match self {
+            AttributionStatus::Preserved => "preserved",
+            AttributionStatus::Partial => "partial",
+            AttributionStatus::Lost => "lost",
+            AttributionStatus::NoAttribution => "no AI lines",
+            AttributionStatus::Dropped => "dropped",
+            AttributionStatus::Added => "added",
+        }
+    }
+}
+
+/// One line of `git range-diff`, with the attribution of both sides
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitPair {
+    pub old_commit: Option<String>,
+    pub new_commit: Option<String>,
+    /// `=` same patch, `!` patch changed, `<` only in the old range, `>` only in the new one
+    pub relation: char,
+    pub subject: String,
+    pub status: AttributionStatus,
+    pub old_ai_lines: u32,
+    pub new_ai_lines: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RangeDiffReport {
+    pub old_range: String,
+    pub new_range: String,
+    pub preserved: usize,
+    pub partial: usize,
+    pub lost: usize,
+    pub pairs: Vec<CommitPair>,
+}
+
+pub fn handle_range_diff(args: &[String]) {
+    let mut json_output = false;
+    let mut ranges = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json_output = true,
+            _ if arg.starts_with('-') => {
+                eprintln!("Unknown range-diff argument: {}", arg);
+                std::process::exit(1);
+            }
+            _ => ranges.push(arg.clone()),
+        }
+    }
+
+    if ranges.is_empty() || ranges.len() > 3 {
+        eprintln!(
+            "Usage: git-ai range-diff <old-range> <new-range> (or <rev1>...<rev2>, or <base> <rev1> <rev2>)"
+        );
+        std::process::exit(1);
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let report = match build_report(&repo, &ranges) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Range-diff failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if json_output {
+        match serde_json::to_string(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize report: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        print_report(&report);
+    }
+}
+
+/// Pair up the commits of two ranges the way `git range-diff` does, and compare the AI lines
+/// in the authorship notes of each pair
+pub fn build_report(repo: &Repository, ranges: &[String]) -> Result<RangeDiffReport, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("range-diff".to_string());
+    args.push("--no-color".to_string());
+    args.push("--no-patch".to_string());
+    args.extend(ranges.iter().cloned());
+    let output = String::from_utf8(exec_git(&args)?.stdout)?;
+
+    let lines: Vec<RangeDiffLine> = output.lines().filter_map(parse_range_diff_line).collect();
+    let full_shas = resolve_commits(
+        repo,
+        lines
+            .iter()
+            .flat_map(|line| [line.old_commit, line.new_commit])
+            .flatten(),
+    )?;
+
+    let mut notes: HashMap<&str, Option<AuthorshipLog>> = HashMap::new();
+    let mut ai_lines = |sha: &str| -> HashMap<(String, String), u32> {
+        notes
+            .entry(full_shas[sha].as_str())
+            .or_insert_with(|| get_authorship(repo, &full_shas[sha]))
+            .as_ref()
+            .map(ai_lines_by_file_and_prompt)
+            .unwrap_or_default()
+    };
+
+    let (old_range, new_range) = match ranges {
+        [old, new] => (old.clone(), new.clone()),
+        [symmetric] => match symmetric.split_once("...") {
+            Some((old, new)) => (old.to_string(), new.to_string()),
+            None => (symmetric.clone(), String::new()),
+        },
+        [base, old, new] => (format!("{}..{}", base, old), format!("{}..{}", base, new)),
+        _ => (ranges.join(" "), String::new()),
+    };
+    let mut report = RangeDiffReport {
+        old_range,
+        new_range,
+        preserved: 0,
+        partial: 0,
+        lost: 0,
+        pairs: Vec::new(),
+    };
+
+    for line in &lines {
+        let old = line.old_commit.map(&mut ai_lines);
+        let new = line.new_commit.map(&mut ai_lines);
+        let status = match (&old, &new) {
+            (Some(old), Some(new)) => compare_attribution(old, new),
+            (Some(_), None) => AttributionStatus::Dropped,
+            _ => AttributionStatus::Added,
+        };
+        match status {
+            AttributionStatus::Preserved => report.preserved += 1,
+            AttributionStatus::Partial => report.partial += 1,
+            AttributionStatus::Lost => report.lost += 1,
+            _ => {}
+        }
+
+        report.pairs.push(CommitPair {
+            old_commit: line.old_commit.map(|sha| full_shas[sha].clone()),
+            new_commit: line.new_commit.map(|sha| full_shas[sha].clone()),
+            relation: line.relation,
+            subject: line.subject.to_string(),
+            status,
+            old_ai_lines: old.map(|lines| lines.values().sum()).unwrap_or(0),
+            new_ai_lines: new.map(|lines| lines.values().sum()).unwrap_or(0),
+        });
+    }
+
+    Ok(report)
+}
+
+/// AI line counts keyed by file and prompt. Line numbers aren't compared: a rebase onto new
+/// upstream work moves lines around without changing who wrote them.
+fn ai_lines_by_file_and_prompt(authorship_log: &AuthorshipLog) -> HashMap<(String, String), u32> {
+    let mut counts = HashMap::new();
+    for file_attestation in &authorship_log.attestations {
+        for entry in &file_attestation.entries {
+            if !authorship_log.metadata.prompts.contains_key(&entry.hash) {
+                continue;
+            }
+            let lines: u32 = entry
+                .line_ranges
+                .iter()
+                .map(|range| range.expand().len() as u32)
+                .sum();
+            *counts
+                .entry((file_attestation.file_path.clone(), entry.hash.clone()))
+                .or_default() += lines;
+        }
+    }
+    counts
+}
+
+fn compare_attribution(
+    old: &HashMap<(String, String), u32>,
+    new: &HashMap<(String, String), u32>,
+) -> AttributionStatus {
+    if old.is_empty() {
+        AttributionStatus::NoAttribution
+    } else if new.is_empty() {
+        AttributionStatus::Lost
+    } else if old == new {
+        AttributionStatus::Preserved
+    } else {
+        AttributionStatus::Partial
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct RangeDiffLine<'a> {
+    old_commit: Option<&'a str>,
+    new_commit: Option<&'a str>,
+    relation: char,
+    subject: &'a str,
+}
+
+/// Parse a pairing line like `1:  bf7b29e ! 1:  23f0447 Add parser`. The side a commit is
+/// missing from shows as `-:  -------`.
+fn parse_range_diff_line<'a>(line: &'a str) -> Option<RangeDiffLine<'a>> {
+    let mut rest = line.trim_start();
+    let mut fields = Vec::with_capacity(5);
+    for _ in 0..5 {
+        let end = rest.find(' ').unwrap_or(rest.len());
+        fields.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    let [old_index, old_sha, relation, new_index, new_sha] = fields[..] else {
+        return None;
+    };
+    if !old_index.ends_with(':') || !new_index.ends_with(':') {
+        return None;
+    }
+    let relation = match relation {
+        "=" | "!" | "<" | ">" => relation.chars().next()?,
+        _ => return None,
+    };
+    let commit = |sha: &'a str| (!sha.starts_with('-')).then_some(sha);
+    Some(RangeDiffLine {
+        old_commit: commit(old_sha),
+        new_commit: commit(new_sha),
+        relation,
+        subject: rest,
+    })
+}
+
+/// Full SHAs for the abbreviated ones range-diff prints
+fn resolve_commits<'a>(
+    repo: &Repository,
+    abbreviated: impl Iterator<Item = &'a str>,
+) -> Result<HashMap<&'a str, String>, GitAiError> {
+    let abbreviated: Vec<&str> = abbreviated.collect();
+    if abbreviated.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let mut args = repo.global_args_for_exec();
+    args.push("rev-parse".to_string());
+    args.extend(abbreviated.iter().map(|sha| format!("{}^{{commit}}", sha)));
+    let output = String::from_utf8(exec_git(&args)?.stdout)?;
+    Ok(abbreviated
+        .into_iter()
+        .zip(output.lines().map(str::to_string))
+        .collect())
+}
+
+fn print_report(report: &RangeDiffReport) {
+    if report.pairs.is_empty() {
+        println!("No commits to compare");
+        return;
+    }
+
+    for pair in &report.pairs {
+        let short = |sha: &Option<String>| match sha {
+            Some(sha) => sha[..7.min(sha.len())].to_string(),
+            None => "-------".to_string(),
+        };
+        let lines = match pair.status {
+            AttributionStatus::Preserved | AttributionStatus::Partial | AttributionStatus::Lost => {
+                format!("{}/{} AI lines", pair.new_ai_lines, pair.old_ai_lines)
+            }
+            AttributionStatus::Dropped => format!("{} AI lines", pair.old_ai_lines),
+            AttributionStatus::Added => format!("{} AI lines", pair.new_ai_lines),
+            AttributionStatus::NoAttribution => String::new(),
+        };
+        println!(
+            "{} {} {}  {:<12}{:>16}  {}",
+            short(&pair.old_commit),
+            pair.relation,
+            short(&pair.new_commit),
+            pair.status.label(),
+            lines,
+            pair.subject
+        );
+    }
+
+    println!();
+    println!(
+        "{} preserved, {} partial, {} lost",
+        report.preserved, report.partial, report.lost
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range_diff_line() {
+        assert_eq!(
+            parse_range_diff_line("1:  bf7b29e = 1:  23f0447 Add parser"),
+            Some(RangeDiffLine {
+                old_commit: Some("bf7b29e"),
+                new_commit: Some("23f0447"),
+                relation: '=',
+                subject: "Add parser",
+            })
+        );
+        assert_eq!(
+            parse_range_diff_line(" 9:  f85b292 < -:  ------- Drop  the cache"),
+            Some(RangeDiffLine {
+                old_commit: Some("f85b292"),
+                new_commit: None,
+                relation: '<',
+                subject: "Drop  the cache",
+            })
+        );
+        let added = parse_range_diff_line("-:  ------- > 10:  a7ebed4 Fix").unwrap();
+        assert_eq!(
+            (added.old_commit, added.new_commit),
+            (None, Some("a7ebed4"))
+        );
+        assert_eq!(parse_range_diff_line("    ## src/lib.rs ##"), None);
+    }
+
+    #[test]
+    fn test_compare_attribution() {
+        let counts = |items: &[(&str, u32)]| -> HashMap<(String, String), u32> {
+            items
+                .iter()
+                .map(|(file, lines)| ((file.to_string(), "abc1234".to_string()), *lines))
+                .collect()
+        };
+        let old = counts(&[("a.rs", 4), ("b.rs", 2)]);
+        assert_eq!(
+            compare_attribution(&old, &old.clone()),
+            AttributionStatus::Preserved
+        );
+        assert_eq!(
+            compare_attribution(&old, &counts(&[("a.rs", 4)])),
+            AttributionStatus::Partial
+        );
+        assert_eq!(
+            compare_attribution(&old, &HashMap::new()),
+            AttributionStatus::Lost
+        );
+        assert_eq!(
+            compare_attribution(&HashMap::new(), &old),
+            AttributionStatus::NoAttribution
+        );
+    }
+}
@@ -0,0 +1,172 @@
+use crate::authorship::transcript::redact_transcript;
+use crate::config::TranscriptStore;
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::refs::{
+    commits_with_ai_notes, get_reference_as_authorship_log_v3, notes_add, squash_notes_history,
+};
+use crate::git::repository::{CommitRange, Repository};
+
+/// What one `git-ai redact` run rewrote
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RedactReport {
+    /// Notes in the range
+    pub notes: usize,
+    /// Notes that held more than the level allows and were rewritten
+    pub rewritten: usize,
+    /// Full transcripts kept on this machine by `keep_local_transcripts` that were redacted too
+    pub local_transcripts: usize,
+    /// Commits whose note couldn't be read, left as they are
+    pub skipped: Vec<String>,
+}
+
+pub fn handle_redact(args: &[String]) {
+    let mut range = None;
+    let mut all = false;
+    let mut store = TranscriptStore::None;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--store" if i + 1 < args.len() => {
+                store = match parse_store(&args[i + 1]) {
+                    Some(TranscriptStore::Full) | None => {
+                        eprintln!(
+                            "Invalid --store {} (expected messages_only, hashes_only or none)",
+                            args[i + 1]
+                        );
+                        std::process::exit(1);
+                    }
+                    Some(store) => store,
+                };
+                i += 2;
+            }
+            "--all" => {
+                all = true;
+                i += 1;
+            }
+            arg if !arg.starts_with("--") && range.is_none() => {
+                range = Some(arg.to_string());
+                i += 1;
+            }
+            _ => {
+                eprintln!("Unknown redact argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if range.is_some() == all {
+        eprintln!("Usage: git-ai redact (<commit-range> | --all) [--store <level>]");
+        std::process::exit(1);
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    match redact_notes(&repo, range.as_deref(), store) {
+        Ok(report) => {
+            println!("Redacted {} of {} notes", report.rewritten, report.notes);
+            if report.local_transcripts > 0 {
+                println!("Redacted {} local transcripts", report.local_transcripts);
+            }
+            if report.rewritten > 0 {
+                println!(
+                    "Rewrote the history of {} so earlier versions of the notes are unreachable; \
+                     `git gc --prune=now` deletes them from disk. Copies already pushed or \
+                     fetched elsewhere keep their transcripts until they are replaced there.",
+                    repo.notes_ref()
+                );
+            }
+            for commit in &report.skipped {
+                eprintln!("Skipped {}: its note could not be read", commit);
+            }
+        }
+        Err(e) => {
+            eprintln!("Redact failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn parse_store(value: &str) -> Option<TranscriptStore> {
+    serde_json::from_value(serde_json::Value::String(value.to_string())).ok()
+}
+
+/// Cut the transcripts in the notes of `range` (a single commit or `<start>..<end>`), or of
+/// every note, down to `store`. Attestations and prompt hashes are kept, so blame and stats
+/// read the same afterwards. The local copies of those transcripts are cut down too, and when
+/// a note was rewritten the notes ref's history is squashed so the old versions are dropped.
+pub fn redact_notes(
+    repo: &Repository,
+    range: Option<&str>,
+    store: TranscriptStore,
+) -> Result<RedactReport, GitAiError> {
+    let noted = commits_with_ai_notes(repo)?;
+    let mut commits: Vec<String> = match range {
+        Some(range) => match range.split_once("..") {
+            Some((start, end)) => {
+                CommitRange::new_infer_refname(repo, start.to_string(), end.to_string(), None)?
+                    .into_iter()
+                    .map(|c| c.id())
+                    .filter(|commit| noted.contains(commit))
+                    .collect()
+            }
+            None => {
+                let commit = repo.revparse_single(&format!("{}^{{commit}}", range))?.id();
+                noted
+                    .contains(&commit)
+                    .then_some(commit)
+                    .into_iter()
+                    .collect()
+            }
+        },
+        None => noted.into_iter().collect(),
+    };
+    commits.sort();
+
+    let mut report = RedactReport::default();
+    for commit in commits {
+        report.notes += 1;
+        let Ok(mut log) = get_reference_as_authorship_log_v3(repo, &commit) else {
+            report.skipped.push(commit);
+            continue;
+        };
+
+        // Local copies hold the full transcript even when the note only carries a summary, so
+        // they're cut down whether or not the note changes
+        for hash in log.metadata.prompts.keys() {
+            let Some(messages) = repo.storage.read_local_transcript(hash) else {
+                continue;
+            };
+            let redacted = redact_transcript(messages.clone(), store);
+            if redacted != messages {
+                repo.storage.write_local_transcript(hash, &redacted)?;
+                report.local_transcripts += 1;
+            }
+        }
+
+        let before = log.metadata.prompts.clone();
+        log.redact_transcripts(store);
+        if log.metadata.prompts == before {
+            continue;
+        }
+
+        let content = log
+            .serialize_to_string()
+            .map_err(|_| GitAiError::Generic("Failed to serialize authorship log".to_string()))?;
+        notes_add(repo, &commit, &content)?;
+        report.rewritten += 1;
+    }
+
+    if report.rewritten > 0 {
+        squash_notes_history(repo, "Notes redacted by git-ai")?;
+    }
+
+    Ok(report)
+}
@@ -0,0 +1,282 @@
+use crate::authorship::range_authorship::range_authorship;
+use crate::authorship::stats::{CommitStats, stats_for_commit_stats};
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::repository::{CommitRange, Repository, exec_git, exec_git_stdin};
+use serde::{Deserialize, Serialize};
+
+/// AI share of the lines added between two releases, stored as a note on the release tag
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReleaseRollup {
+    pub tag: String,
+    /// The tag the release is measured from; unset for the first release, which counts every
+    /// commit up to the tag
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous_tag: Option<String>,
+    /// The object the rollup is stored on: the tag object, or the commit of a lightweight tag
+    pub object: String,
+    pub commit: String,
+    pub commits: usize,
+    pub commits_with_authorship: usize,
+    pub added_lines: u32,
+    pub ai_lines: u32,
+    pub human_lines: u32,
+    pub ai_percentage: u32,
+}
+
+impl ReleaseRollup {
+    /// One line for a changelog or badge, e.g. "Release v1.4: 38% AI-assisted"
+    pub fn badge(&self) -> String {
+        format!("Release {}: {}% AI-assisted", self.tag, self.ai_percentage)
+    }
+}
+
+pub fn handle_release_note(args: &[String]) {
+    let mut tag = None;
+    let mut previous_tag = None;
+    let mut store = true;
+    let mut badge = false;
+    let mut json_output = false;
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--previous" if i + 1 < args.len() => {
+                previous_tag = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--no-store" => {
+                store = false;
+                i += 1;
+            }
+            "--badge" => {
+                badge = true;
+                i += 1;
+            }
+            "--json" => {
+                json_output = true;
+                i += 1;
+            }
+            arg if tag.is_none() && !arg.starts_with("--") => {
+                tag = Some(arg.to_string());
+                i += 1;
+            }
+            _ => {
+                eprintln!("Unknown release-note argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+    let Some(tag) = tag else {
+        eprintln!(
+            "Usage: git-ai release-note <tag> [--previous <tag>] [--no-store] [--badge] [--json]"
+        );
+        std::process::exit(1);
+    };
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let rollup = match build_rollup(&repo, &tag, previous_tag.as_deref()) {
+        Ok(rollup) => rollup,
+        Err(e) => {
+            eprintln!("Release note failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if store && let Err(e) = store_rollup(&repo, &rollup) {
+        eprintln!("Failed to store release note: {}", e);
+        std::process::exit(1);
+    }
+
+    if json_output {
+        match serde_json::to_string(&rollup) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize release note: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else if badge {
+        println!("{}", rollup.badge());
+    } else {
+        print_rollup(&repo, &rollup, store);
+    }
+}
+
+/// Add up the lines added between `previous_tag` (by default the closest tag before `tag`) and
+/// `tag`, blamed at `tag` like `git-ai stats <previous>..<tag>`
+pub fn build_rollup(
+    repo: &Repository,
+    tag: &str,
+    previous_tag: Option<&str>,
+) -> Result<ReleaseRollup, GitAiError> {
+    let object =
+        rev_parse(repo, tag).ok_or_else(|| GitAiError::Generic(format!("Unknown tag {}", tag)))?;
+    let commit = rev_parse(repo, &format!("{}^{{commit}}", tag))
+        .ok_or_else(|| GitAiError::Generic(format!("{} doesn't point at a commit", tag)))?;
+    let previous_tag = match previous_tag {
+        Some(previous_tag) => Some(previous_tag.to_string()),
+        None => closest_earlier_tag(repo, &commit),
+    };
+
+    let (stats, commits, commits_with_authorship) = match &previous_tag {
+        Some(previous_tag) => {
+            let start =
+                rev_parse(repo, &format!("{}^{{commit}}", previous_tag)).ok_or_else(|| {
+                    GitAiError::Generic(format!("Unknown previous tag {}", previous_tag))
+                })?;
+            range_stats(repo, &start, &commit)?
+        }
+        None => first_release_stats(repo, &commit)?,
+    };
+
+    let ai_lines = stats.ai_additions + stats.mixed_additions;
+    let human_lines = stats.human_additions;
+    Ok(ReleaseRollup {
+        tag: tag.to_string(),
+        previous_tag,
+        object,
+        commit,
+        commits,
+        commits_with_authorship,
+        added_lines: ai_lines + human_lines,
+        ai_lines,
+        human_lines,
+        ai_percentage: percentage(ai_lines, ai_lines + human_lines),
+    })
+}
+
+/// Range stats of `start..end`, with how many commits the range has and how many have notes
+fn range_stats(
+    repo: &Repository,
+    start: &str,
+    end: &str,
+) -> Result<(CommitStats, usize, usize), GitAiError> {
+    let range = CommitRange::new(repo, start.to_string(), end.to_string(), end.to_string())?;
+    let stats = range_authorship(range, false)?;
+    Ok((
+        stats.range_stats,
+        stats.authorship_stats.total_commits,
+        stats.authorship_stats.commits_with_authorship,
+    ))
+}
+
+/// With no earlier tag, the release is every commit up to it: the root commit's own lines plus
+/// everything after it
+fn first_release_stats(
+    repo: &Repository,
+    commit: &str,
+) -> Result<(CommitStats, usize, usize), GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("rev-list".to_string());
+    args.push("--max-parents=0".to_string());
+    args.push(commit.to_string());
+    let output = String::from_utf8(exec_git(&args)?.stdout)?;
+    let root = output
+        .lines()
+        .last()
+        .ok_or_else(|| GitAiError::Generic(format!("No root commit for {}", commit)))?
+        .trim()
+        .to_string();
+
+    let root_stats = stats_for_commit_stats(repo, &root, "")?;
+    let root_has_note = crate::git::refs::show_authorship_note(repo, &root).is_some() as usize;
+    if root == commit {
+        return Ok((root_stats, 1, root_has_note));
+    }
+
+    let (mut stats, commits, commits_with_authorship) = range_stats(repo, &root, commit)?;
+    stats.ai_additions += root_stats.ai_additions;
+    stats.mixed_additions += root_stats.mixed_additions;
+    stats.human_additions += root_stats.human_additions;
+    Ok((stats, commits + 1, commits_with_authorship + root_has_note))
+}
+
+/// The notes ref release rollups are stored in, next to the authorship notes ref
+pub fn release_notes_ref(repo: &Repository) -> String {
+    format!("{}-releases", repo.notes_ref())
+}
+
+/// Store the rollup as a note on the tag object. A lightweight tag has no object of its own, so
+/// its rollup goes on the commit.
+pub fn store_rollup(repo: &Repository, rollup: &ReleaseRollup) -> Result<(), GitAiError> {
+    let content = serde_json::to_string_pretty(rollup)?;
+    let mut args = repo.global_args_for_exec();
+    args.push("notes".to_string());
+    args.push(format!("--ref={}", release_notes_ref(repo)));
+    args.push("add".to_string());
+    args.push("-f".to_string());
+    args.push("-F".to_string());
+    args.push("-".to_string());
+    args.push(rollup.object.clone());
+    exec_git_stdin(&args, content.as_bytes())?;
+    Ok(())
+}
+
+/// The closest tag reachable from `commit`'s parents
+fn closest_earlier_tag(repo: &Repository, commit: &str) -> Option<String> {
+    let mut args = repo.global_args_for_exec();
+    args.push("describe".to_string());
+    args.push("--tags".to_string());
+    args.push("--abbrev=0".to_string());
+    args.push(format!("{}^", commit));
+    let output = exec_git(&args).ok()?;
+    let tag = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!tag.is_empty()).then_some(tag)
+}
+
+fn rev_parse(repo: &Repository, rev: &str) -> Option<String> {
+    let mut args = repo.global_args_for_exec();
+    args.push("rev-parse".to_string());
+    args.push("--verify".to_string());
+    args.push("--quiet".to_string());
+    args.push(rev.to_string());
+    let output = exec_git(&args).ok()?;
+    let sha = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!sha.is_empty()).then_some(sha)
+}
+
+fn percentage(part: u32, whole: u32) -> u32 {
+    if whole == 0 {
+        return 0;
+    }
+    ((part as f64 / whole as f64) * 100.0).round().min(100.0) as u32
+}
+
+fn print_rollup(repo: &Repository, rollup: &ReleaseRollup, stored: bool) {
+    println!("{}", rollup.badge());
+    let range = match &rollup.previous_tag {
+        Some(previous_tag) => format!("{}..{}", previous_tag, rollup.tag),
+        None => format!("up to {}", rollup.tag),
+    };
+    println!(
+        "  {}, {} commit{} ({} with authorship logs)",
+        range,
+        rollup.commits,
+        if rollup.commits == 1 { "" } else { "s" },
+        rollup.commits_with_authorship
+    );
+    println!(
+        "  AI     {:>6} lines  {:>3}%",
+        rollup.ai_lines, rollup.ai_percentage
+    );
+    println!(
+        "  Human  {:>6} lines  {:>3}%",
+        rollup.human_lines,
+        percentage(rollup.human_lines, rollup.added_lines)
+    );
+    if stored {
+        println!(
+            "Stored on {} in {}",
+            &rollup.object[..7.min(rollup.object.len())],
+            release_notes_ref(repo)
+        );
+    }
+}
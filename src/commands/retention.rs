@@ -0,0 +1,328 @@
+use crate::commands::blame::{BlameHunk, GitAiBlameOptions};
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::refs::get_authorship;
+use crate::git::repository::{CommitRange, RangeWalk, Repository};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+/// AI lines written vs AI lines still present later on
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RetentionCounts {
+    pub generated: u32,
+    pub retained: u32,
+    pub retention_rate: f64,
+}
+
+impl RetentionCounts {
+    fn add(&mut self, generated: u32, retained: u32) {
+        self.generated += generated;
+        self.retained += retained;
+        self.retention_rate = if self.generated == 0 {
+            0.0
+        } else {
+            self.retained as f64 / self.generated as f64
+        };
+    }
+
+    fn percentage(&self) -> u32 {
+        (self.retention_rate * 100.0).round() as u32
+    }
+}
+
+/// Retention of the AI lines introduced by a single commit
+#[derive(Debug, Clone, Serialize)]
+pub struct CommitRetention {
+    pub commit: String,
+    /// The commit the surviving lines were counted at
+    pub measured_at: String,
+    #[serde(flatten)]
+    pub counts: RetentionCounts,
+    pub by_tool: BTreeMap<String, RetentionCounts>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RetentionReport {
+    pub range: String,
+    /// How many commits later lines were counted, or `None` for the tip of the range
+    pub horizon: Option<usize>,
+    #[serde(flatten)]
+    pub totals: RetentionCounts,
+    pub by_tool: BTreeMap<String, RetentionCounts>,
+    /// Commits that introduced AI lines, oldest first
+    pub commits: Vec<CommitRetention>,
+}
+
+pub fn handle_retention(args: &[String]) {
+    let mut range = None;
+    let mut horizon = None;
+    let mut json_output = false;
+    let mut walk = RangeWalk::default();
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            arg if walk.parse_arg(arg) => {
+                i += 1;
+            }
+            "--range" if i + 1 < args.len() => {
+                range = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--horizon" if i + 1 < args.len() => match args[i + 1].parse::<usize>() {
+                Ok(n) => {
+                    horizon = Some(n);
+                    i += 2;
+                }
+                Err(_) => {
+                    eprintln!("--horizon expects a number of commits, got {}", args[i + 1]);
+                    std::process::exit(1);
+                }
+            },
+            "--json" => {
+                json_output = true;
+                i += 1;
+            }
+            _ => {
+                eprintln!("Unknown retention argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let Some(range) = range else {
+        eprintln!("retention requires --range <start>..<end>");
+        std::process::exit(1);
+    };
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let report = match build_report(&repo, &range, horizon, walk) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Retention failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if json_output {
+        match serde_json::to_string(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize report: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        print_report(&report);
+    }
+}
+
+/// For every commit in `range` with an authorship note, count its AI lines and how many of
+/// them `git blame` still traces back to it at the range's tip, or `horizon` commits later.
+/// With a first-parent `walk`, blame traces merged lines to the merge that brought them in.
+pub fn build_report(
+    repo: &Repository,
+    range: &str,
+    horizon: Option<usize>,
+    walk: RangeWalk,
+) -> Result<RetentionReport, GitAiError> {
+    let Some((start, end)) = range.split_once("..") else {
+        return Err(GitAiError::Generic(format!(
+            "Expected a range like main..feature, got {}",
+            range
+        )));
+    };
+    let commit_range =
+        CommitRange::new_infer_refname(repo, start.to_string(), end.to_string(), None)?
+            .with_walk(walk);
+    let tip = commit_range.end_oid.clone();
+
+    // rev-list lists newest first
+    let mut commits: Vec<String> = commit_range.into_iter().map(|c| c.id()).collect();
+    commits.reverse();
+
+    let mut report = RetentionReport {
+        range: range.to_string(),
+        horizon,
+        totals: RetentionCounts::default(),
+        by_tool: BTreeMap::new(),
+        commits: Vec::new(),
+    };
+    let mut blame_cache: HashMap<(String, String), Vec<BlameHunk>> = HashMap::new();
+
+    for (index, commit) in commits.iter().enumerate() {
+        let Some(authorship_log) = get_authorship(repo, commit) else {
+            continue;
+        };
+        // A merge's note repeats lines of the merged branch; unless the walk stands the merge
+        // in for the branch, they count at the commits that wrote them
+        if !walk.counts_merge_notes() && repo.find_commit(commit.clone())?.parent_count()? > 1 {
+            continue;
+        }
+        let measured_at = match horizon {
+            Some(n) => commits
+                .get(index + n)
+                .cloned()
+                .unwrap_or_else(|| tip.clone()),
+            None => tip.clone(),
+        };
+
+        let mut commit_retention = CommitRetention {
+            commit: commit.clone(),
+            measured_at: measured_at.clone(),
+            counts: RetentionCounts::default(),
+            by_tool: BTreeMap::new(),
+        };
+
+        for file_attestation in &authorship_log.attestations {
+            // AI line numbers in this commit's version of the file, with the tool that wrote them
+            let mut ai_lines: HashMap<u32, &str> = HashMap::new();
+            for entry in &file_attestation.entries {
+                let Some(prompt) = authorship_log.metadata.prompts.get(&entry.hash) else {
+                    continue;
+                };
+                for range in &entry.line_ranges {
+                    for line in range.expand() {
+                        ai_lines.insert(line, &prompt.agent_id.tool);
+                    }
+                }
+            }
+            if ai_lines.is_empty() {
+                continue;
+            }
+
+            let hunks = blame_cache
+                .entry((measured_at.clone(), file_attestation.file_path.clone()))
+                .or_insert_with(|| {
+                    blame_at(
+                        repo,
+                        &file_attestation.file_path,
+                        &measured_at,
+                        walk.first_parent,
+                    )
+                    .unwrap_or_default()
+                });
+
+            let mut retained: HashMap<&str, u32> = HashMap::new();
+            for hunk in hunks.iter().filter(|hunk| &hunk.commit_sha == commit) {
+                for line in hunk.orig_range.0..=hunk.orig_range.1 {
+                    if let Some(tool) = ai_lines.get(&line) {
+                        *retained.entry(tool).or_default() += 1;
+                    }
+                }
+            }
+
+            let mut generated: HashMap<&str, u32> = HashMap::new();
+            for tool in ai_lines.values() {
+                *generated.entry(tool).or_default() += 1;
+            }
+            for (tool, generated) in generated {
+                let retained = retained.get(tool).copied().unwrap_or(0);
+                commit_retention.counts.add(generated, retained);
+                commit_retention
+                    .by_tool
+                    .entry(tool.to_string())
+                    .or_default()
+                    .add(generated, retained);
+            }
+        }
+
+        if commit_retention.counts.generated == 0 {
+            continue;
+        }
+        report.totals.add(
+            commit_retention.counts.generated,
+            commit_retention.counts.retained,
+        );
+        for (tool, counts) in &commit_retention.by_tool {
+            report
+                .by_tool
+                .entry(tool.clone())
+                .or_default()
+                .add(counts.generated, counts.retained);
+        }
+        report.commits.push(commit_retention);
+    }
+
+    Ok(report)
+}
+
+/// Blame hunks for the whole file as of `commit`. Fails if the file doesn't exist there.
+pub fn blame_at(
+    repo: &Repository,
+    file_path: &str,
+    commit: &str,
+    first_parent: bool,
+) -> Result<Vec<BlameHunk>, GitAiError> {
+    let options = GitAiBlameOptions {
+        newest_commit: Some(commit.to_string()),
+        first_parent,
+        ..Default::default()
+    };
+    repo.blame_hunks(file_path, 1, u32::MAX, &options)
+}
+
+fn print_report(report: &RetentionReport) {
+    if report.commits.is_empty() {
+        println!("No AI-authored lines in {}", report.range);
+        return;
+    }
+
+    match report.horizon {
+        Some(n) => println!(
+            "AI code retention for {}, {} commit{} later",
+            report.range,
+            n,
+            if n == 1 { "" } else { "s" }
+        ),
+        None => println!("AI code retention for {} at its tip", report.range),
+    }
+    println!();
+
+    let row = |label: &str, counts: &RetentionCounts| {
+        println!(
+            "  {:<14}{:>10} generated{:>10} retained{:>6}%",
+            label,
+            counts.generated,
+            counts.retained,
+            counts.percentage()
+        );
+    };
+
+    row("total", &report.totals);
+
+    println!();
+    println!("by agent");
+    for (tool, counts) in &report.by_tool {
+        row(tool, counts);
+    }
+
+    println!();
+    println!("by commit");
+    for commit in &report.commits {
+        row(&commit.commit[..7.min(commit.commit.len())], &commit.counts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retention_counts_rate() {
+        let mut counts = RetentionCounts::default();
+        assert_eq!(counts.retention_rate, 0.0);
+        counts.add(8, 6);
+        counts.add(2, 0);
+        assert_eq!(counts.generated, 10);
+        assert_eq!(counts.retained, 6);
+        assert_eq!(counts.percentage(), 60);
+    }
+}
@@ -0,0 +1,323 @@
+use crate::authorship::authorship_log::PromptRecord;
+use crate::authorship::transcript::Message;
+use crate::commands::blame::BlameHunk;
+use crate::commands::retention::blame_at;
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::refs::get_authorship;
+use crate::git::repository::{CommitRange, RangeWalk, Repository};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// One agent session, put together from the prompt records it left in the range's notes
+#[derive(Debug, Clone, Serialize)]
+pub struct Session {
+    /// Key of the session's prompt record in every note it appears in
+    pub prompt_id: String,
+    /// The agent's own id for the session (conversation or thread id)
+    pub session_id: String,
+    pub tool: String,
+    pub model: String,
+    pub human_author: Option<String>,
+    /// Unix seconds of the earliest and latest sign of activity: transcript timestamps and
+    /// checkpoint times, or commit dates for sessions that recorded neither
+    pub started_at: Option<i64>,
+    pub ended_at: Option<i64>,
+    /// Commits carrying the session's prompt record, oldest first
+    pub commits: Vec<String>,
+    /// AI lines the session's commits attributed to it
+    pub lines_produced: u32,
+    /// How many of those `git blame` still traces back to those commits at the range's tip
+    pub lines_retained: u32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionsReport {
+    pub range: String,
+    /// Sessions by start time
+    pub sessions: Vec<Session>,
+}
+
+pub fn handle_sessions(args: &[String]) {
+    let mut range = None;
+    let mut json_output = false;
+    let mut walk = RangeWalk::default();
+
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            arg if walk.parse_arg(arg) => {
+                i += 1;
+            }
+            "--range" if i + 1 < args.len() => {
+                range = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--json" => {
+                json_output = true;
+                i += 1;
+            }
+            _ => {
+                eprintln!("Unknown sessions argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let Some(range) = range else {
+        eprintln!("sessions requires --range <start>..<end>");
+        std::process::exit(1);
+    };
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let report = match build_report(&repo, &range, walk) {
+        Ok(report) => report,
+        Err(e) => {
+            eprintln!("Sessions failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if json_output {
+        match serde_json::to_string(&report) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize report: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        print_report(&report);
+    }
+}
+
+/// Group the prompt records of every commit in `range` by session. A session keeps the same
+/// prompt record key across commits, since the key is derived from the agent's session id.
+pub fn build_report(
+    repo: &Repository,
+    range: &str,
+    walk: RangeWalk,
+) -> Result<SessionsReport, GitAiError> {
+    let Some((start, end)) = range.split_once("..") else {
+        return Err(GitAiError::Generic(format!(
+            "Expected a range like main..feature, got {}",
+            range
+        )));
+    };
+    let commit_range =
+        CommitRange::new_infer_refname(repo, start.to_string(), end.to_string(), None)?
+            .with_walk(walk);
+    let tip = commit_range.end_oid.clone();
+
+    // rev-list lists newest first
+    let mut commits: Vec<String> = commit_range.into_iter().map(|c| c.id()).collect();
+    commits.reverse();
+
+    let mut sessions: BTreeMap<String, Session> = BTreeMap::new();
+    let mut blame_cache: HashMap<String, Vec<BlameHunk>> = HashMap::new();
+
+    for commit in &commits {
+        let Some(authorship_log) = get_authorship(repo, commit) else {
+            continue;
+        };
+        // A merge's note repeats lines its branch's commits already count, unless the walk
+        // stands the merge in for them
+        let commit_info = repo.find_commit(commit.clone())?;
+        if !walk.counts_merge_notes() && commit_info.parent_count()? > 1 {
+            continue;
+        }
+        let committed_at = commit_info.author()?.when().seconds();
+
+        for (prompt_id, prompt) in &authorship_log.metadata.prompts {
+            let session = sessions
+                .entry(prompt_id.clone())
+                .or_insert_with(|| new_session(prompt_id, prompt));
+            session.commits.push(commit.clone());
+            let mut times = activity_times(prompt);
+            if times.is_empty() {
+                times.push(committed_at);
+            }
+            for time in times {
+                session.started_at = Some(session.started_at.map_or(time, |t| t.min(time)));
+                session.ended_at = Some(session.ended_at.map_or(time, |t| t.max(time)));
+            }
+        }
+
+        for file_attestation in &authorship_log.attestations {
+            // Line numbers in this commit's version of the file, by session
+            let mut lines_by_session: HashMap<&str, HashSet<u32>> = HashMap::new();
+            for entry in &file_attestation.entries {
+                if !authorship_log.metadata.prompts.contains_key(&entry.hash) {
+                    continue;
+                }
+                let lines = lines_by_session.entry(&entry.hash).or_default();
+                for range in &entry.line_ranges {
+                    lines.extend(range.expand());
+                }
+            }
+            if lines_by_session.is_empty() {
+                continue;
+            }
+
+            let hunks = blame_cache
+                .entry(file_attestation.file_path.clone())
+                .or_insert_with(|| {
+                    blame_at(repo, &file_attestation.file_path, &tip, walk.first_parent)
+                        .unwrap_or_default()
+                });
+            for (prompt_id, lines) in lines_by_session {
+                let retained = hunks
+                    .iter()
+                    .filter(|hunk| &hunk.commit_sha == commit)
+                    .flat_map(|hunk| hunk.orig_range.0..=hunk.orig_range.1)
+                    .filter(|line| lines.contains(line))
+                    .count() as u32;
+                if let Some(session) = sessions.get_mut(prompt_id) {
+                    session.lines_produced += lines.len() as u32;
+                    session.lines_retained += retained;
+                }
+            }
+        }
+    }
+
+    let mut sessions: Vec<Session> = sessions.into_values().collect();
+    sessions.sort_by(|a, b| {
+        (a.started_at.is_none(), a.started_at, &a.prompt_id).cmp(&(
+            b.started_at.is_none(),
+            b.started_at,
+            &b.prompt_id,
+        ))
+    });
+
+    Ok(SessionsReport {
+        range: range.to_string(),
+        sessions,
+    })
+}
+
+fn new_session(prompt_id: &str, prompt: &PromptRecord) -> Session {
+    Session {
+        prompt_id: prompt_id.to_string(),
+        session_id: prompt.agent_id.id.clone(),
+        tool: prompt.agent_id.tool.clone(),
+        model: prompt.agent_id.model.clone(),
+        human_author: prompt.human_author.clone(),
+        started_at: None,
+        ended_at: None,
+        commits: Vec::new(),
+        lines_produced: 0,
+        lines_retained: 0,
+    }
+}
+
+/// Timestamps of the transcript messages and the last checkpoint in one prompt record
+fn activity_times(prompt: &PromptRecord) -> Vec<i64> {
+    let mut times: Vec<i64> = prompt
+        .messages
+        .iter()
+        .filter_map(|message| match message {
+            Message::User { timestamp, .. }
+            | Message::Assistant { timestamp, .. }
+            | Message::ToolUse { timestamp, .. } => timestamp.as_deref(),
+        })
+        .filter_map(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+        .map(|time| time.timestamp())
+        .collect();
+    times.extend(prompt.generated_at.map(|time| time as i64));
+    times
+}
+
+fn format_time(seconds: Option<i64>) -> String {
+    seconds
+        .and_then(|seconds| DateTime::<Utc>::from_timestamp(seconds, 0))
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+fn print_report(report: &SessionsReport) {
+    if report.sessions.is_empty() {
+        println!("No agent sessions in {}", report.range);
+        return;
+    }
+
+    println!("Agent sessions in {} (times in UTC)", report.range);
+    for session in &report.sessions {
+        println!();
+        println!(
+            "{}  {} ({})  {}",
+            session.prompt_id, session.tool, session.model, session.session_id
+        );
+        println!(
+            "  {} to {}",
+            format_time(session.started_at),
+            format_time(session.ended_at)
+        );
+        let short: Vec<&str> = session
+            .commits
+            .iter()
+            .map(|commit| &commit[..7.min(commit.len())])
+            .collect();
+        println!(
+            "  {} commit{}: {}",
+            session.commits.len(),
+            if session.commits.len() == 1 { "" } else { "s" },
+            short.join(" ")
+        );
+        let retention = if session.lines_produced == 0 {
+            0
+        } else {
+            (session.lines_retained as f64 / session.lines_produced as f64 * 100.0).round() as u32
+        };
+        println!(
+            "  {} lines produced, {} retained ({}%)",
+            session.lines_produced, session.lines_retained, retention
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authorship::working_log::AgentId;
+
+    #[test]
+    fn test_activity_times_from_transcript_and_checkpoints() {
+        let mut prompt = PromptRecord {
+            agent_id: AgentId {
+                tool: "claude".to_string(),
+                id: "session-1".to_string(),
+                model: "sonnet".to_string(),
+            },
+            human_author: None,
+            messages: vec![
+                Message::user(
+                    "Add a parser".to_string(),
+                    Some("2025-03-01T10:00:00Z".to_string()),
+                ),
+                Message::assistant("Done".to_string(), None),
+            ],
+            total_additions: 0,
+            total_deletions: 0,
+            accepted_lines: 0,
+            overriden_lines: 0,
+            summary: None,
+            environments: Vec::new(),
+            generated_at: Some(1_740_823_200),
+        };
+        assert_eq!(activity_times(&prompt), vec![1_740_823_200, 1_740_823_200]);
+
+        prompt.messages.clear();
+        prompt.generated_at = None;
+        assert!(activity_times(&prompt).is_empty());
+        assert_eq!(format_time(Some(1_740_823_200)), "2025-03-01 10:00");
+        assert_eq!(format_time(None), "unknown");
+    }
+}
@@ -0,0 +1,173 @@
+use crate::config::{self, ConfigLevel, ConfigOrigin, ResolvedSettings, SETTINGS_KEYS, Settings};
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+pub fn handle_config(args: &[String]) {
+    let mut show_origin = false;
+    let mut level = None;
+    let mut unset = false;
+    let mut positional = Vec::new();
+    for arg in args {
+        match arg.as_str() {
+            "--show-origin" => show_origin = true,
+            "--system" => level = Some(ConfigLevel::System),
+            "--global" => level = Some(ConfigLevel::Global),
+            "--repo" => level = Some(ConfigLevel::Repo),
+            "--unset" => unset = true,
+            other if other.starts_with('-') => {
+                eprintln!("Unknown config argument: {}", other);
+                std::process::exit(1);
+            }
+            other => positional.push(other),
+        }
+    }
+    if let Some(key) = positional.first()
+        && !SETTINGS_KEYS.contains(key)
+    {
+        eprintln!("Unknown setting: {}", key);
+        eprintln!("Known settings: {}", SETTINGS_KEYS.join(", "));
+        std::process::exit(1);
+    }
+
+    // Outside a repository only the system, user, environment and command line layers apply
+    let workdir = find_repository(&Vec::<String>::new())
+        .ok()
+        .and_then(|repo| repo.workdir().ok());
+
+    match (positional.as_slice(), unset, level) {
+        ([key, value], false, level) => {
+            let level = level.unwrap_or(ConfigLevel::Repo);
+            exit_on_error(set_setting(level, workdir.as_deref(), key, value));
+            return;
+        }
+        ([key], true, Some(level)) => {
+            let path = exit_on_error(level_path(level, workdir.as_deref()));
+            if !exit_on_error(config::remove_toml_setting(&path, key)) {
+                eprintln!("{} isn't set in {}", key, path.display());
+                std::process::exit(1);
+            }
+            return;
+        }
+        ([_], true, None) => {
+            eprintln!("--unset needs --system, --global or --repo");
+            std::process::exit(1);
+        }
+        ([key], false, Some(level)) => {
+            let path = exit_on_error(level_path(level, workdir.as_deref()));
+            let values = exit_on_error(config::read_settings_file(&path));
+            match values.get(*key) {
+                Some(value) => println!("{} = {}", key, value),
+                // Like git config, a key the file doesn't set is a quiet failure
+                None => std::process::exit(1),
+            }
+            return;
+        }
+        ([] | [_], false, None) => {}
+        _ => {
+            eprintln!(
+                "Usage: git-ai config [--show-origin] [<key>] | [--system|--global|--repo] <key> [<value>] | --unset <key>"
+            );
+            std::process::exit(1);
+        }
+    }
+
+    let resolved = exit_on_error(Settings::resolve(workdir.as_deref()));
+    let keys: Vec<&str> = match positional.first() {
+        Some(key) => vec![key],
+        None => SETTINGS_KEYS.to_vec(),
+    };
+    for line in format_settings(&resolved, &keys, show_origin) {
+        println!("{}", line);
+    }
+}
+
+/// Validate `raw` for `key` and write it to the file of `level`
+fn set_setting(
+    level: ConfigLevel,
+    workdir: Option<&Path>,
+    key: &str,
+    raw: &str,
+) -> Result<(), GitAiError> {
+    let path = level_path(level, workdir)?;
+    let value = config::parse_setting_value(key, raw)?;
+    let value = toml::Value::try_from(&value)
+        .map_err(|e| GitAiError::Generic(format!("Invalid value for {}: {}", key, e)))?;
+    config::write_toml_setting(&path, key, value)
+}
+
+fn level_path(level: ConfigLevel, workdir: Option<&Path>) -> Result<PathBuf, GitAiError> {
+    level.path(workdir).ok_or_else(|| {
+        GitAiError::Generic(match level {
+            ConfigLevel::Repo => "Not in a git repository; pass --global or --system".to_string(),
+            _ => "Can't locate the config file for this level".to_string(),
+        })
+    })
+}
+
+fn exit_on_error<T>(result: Result<T, GitAiError>) -> T {
+    result.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    })
+}
+
+/// One `key = value` line per key, prefixed with where the value came from when `show_origin`
+fn format_settings(resolved: &ResolvedSettings, keys: &[&str], show_origin: bool) -> Vec<String> {
+    // Serializing the resolved settings fills in defaults for keys that weren't set anywhere
+    let effective = serde_json::to_value(&resolved.settings).unwrap_or(Value::Null);
+    keys.iter()
+        .map(|key| {
+            let value = effective.get(*key).cloned().unwrap_or(Value::Null);
+            let line = format!("{} = {}", key, value);
+            if !show_origin {
+                return line;
+            }
+            let origin = resolved
+                .values
+                .get(*key)
+                .map(|(_, origin)| origin.clone())
+                .unwrap_or(ConfigOrigin::Default);
+            format!("{}\t{}", origin, line)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_format_settings_with_origin() {
+        let mut values = BTreeMap::new();
+        values.insert(
+            "notes_ref".to_string(),
+            (
+                Value::String("team".to_string()),
+                ConfigOrigin::Repo(PathBuf::from("/work/.gitai.toml")),
+            ),
+        );
+        let resolved = ResolvedSettings {
+            settings: Settings {
+                notes_ref: "team".to_string(),
+                ..Default::default()
+            },
+            values,
+        };
+
+        assert_eq!(
+            format_settings(&resolved, &["notes_ref", "ignore_prompts"], true),
+            vec![
+                "/work/.gitai.toml\tnotes_ref = \"team\"".to_string(),
+                "default\tignore_prompts = false".to_string(),
+            ]
+        );
+        assert_eq!(
+            format_settings(&resolved, &["transcript_mode"], false),
+            vec!["transcript_mode = \"full\"".to_string()]
+        );
+    }
+}
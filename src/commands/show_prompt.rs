@@ -0,0 +1,193 @@
+use crate::authorship::authorship_log::PromptRecord;
+use crate::authorship::transcript::Message;
+use crate::commands::blame::GitAiBlameOptions;
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::refs::get_reference_as_authorship_log_v3;
+use crate::git::repository::Repository;
+use serde::Serialize;
+
+/// A prompt from an authorship note, with its full transcript when this machine has it
+#[derive(Debug, Clone, Serialize)]
+pub struct ShownPrompt {
+    pub hash: String,
+    #[serde(flatten)]
+    pub record: PromptRecord,
+}
+
+pub fn handle_show_prompt(args: &[String]) {
+    let mut positional = Vec::new();
+    let mut json_output = false;
+
+    for arg in args {
+        match arg.as_str() {
+            "--json" => json_output = true,
+            _ if arg.starts_with("--") => {
+                eprintln!("Unknown show-prompt argument: {}", arg);
+                std::process::exit(1);
+            }
+            _ => positional.push(arg.clone()),
+        }
+    }
+
+    let target = match positional.as_slice() {
+        [commit] => (commit.clone(), None),
+        [commit, file, line] => match line.parse::<u32>() {
+            Ok(line) if line > 0 => (commit.clone(), Some((file.clone(), line))),
+            _ => {
+                eprintln!("Invalid line number '{}'", line);
+                std::process::exit(1);
+            }
+        },
+        _ => {
+            eprintln!("Usage: git-ai show-prompt <commit> [<file> <line>] [--json]");
+            std::process::exit(1);
+        }
+    };
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let (commit, file_line) = target;
+    let prompts = match file_line {
+        Some((file, line)) => prompt_for_line(&repo, &commit, &file, line).map(|p| vec![p]),
+        None => prompts_for_commit(&repo, &commit),
+    };
+    let prompts = match prompts {
+        Ok(prompts) => prompts,
+        Err(e) => {
+            eprintln!("Show prompt failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if json_output {
+        match serde_json::to_string(&prompts) {
+            Ok(json) => println!("{}", json),
+            Err(e) => {
+                eprintln!("Failed to serialize prompts: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        for (i, prompt) in prompts.iter().enumerate() {
+            if i > 0 {
+                println!();
+            }
+            print!("{}", format_prompt(prompt));
+        }
+    }
+}
+
+/// Every prompt recorded in the commit's authorship note
+pub fn prompts_for_commit(repo: &Repository, commit: &str) -> Result<Vec<ShownPrompt>, GitAiError> {
+    let sha = repo
+        .revparse_single(&format!("{}^{{commit}}", commit))?
+        .id();
+    let log = get_reference_as_authorship_log_v3(repo, &sha)
+        .map_err(|_| GitAiError::Generic(format!("No authorship note for commit {}", sha)))?;
+    if log.metadata.prompts.is_empty() {
+        return Err(GitAiError::Generic(format!(
+            "Commit {} has no AI prompts",
+            sha
+        )));
+    }
+
+    Ok(log
+        .metadata
+        .prompts
+        .into_iter()
+        .map(|(hash, record)| with_local_transcript(repo, hash, record))
+        .collect())
+}
+
+/// The prompt that wrote `line` of `file` as of `commit`. The line may have been written by an
+/// earlier commit, so this blames it rather than reading `commit`'s note alone.
+pub fn prompt_for_line(
+    repo: &Repository,
+    commit: &str,
+    file: &str,
+    line: u32,
+) -> Result<ShownPrompt, GitAiError> {
+    let sha = repo
+        .revparse_single(&format!("{}^{{commit}}", commit))?
+        .id();
+    let options = GitAiBlameOptions {
+        line_ranges: vec![(line, line)],
+        newest_commit: Some(sha.clone()),
+        use_prompt_hashes_as_names: true,
+        no_output: true,
+        ..Default::default()
+    };
+    let (line_authors, mut prompt_records) = repo.blame(file, &options)?;
+
+    line_authors
+        .get(&line)
+        .and_then(|hash| {
+            prompt_records
+                .remove(hash)
+                .map(|record| (hash.clone(), record))
+        })
+        .map(|(hash, record)| with_local_transcript(repo, hash, record))
+        .ok_or_else(|| {
+            GitAiError::Generic(format!(
+                "{}:{} was not written by an AI prompt as of {}",
+                file, line, sha
+            ))
+        })
+}
+
+/// Notes written in summary mode keep only part of the transcript; the full one may still be
+/// on this machine
+fn with_local_transcript(repo: &Repository, hash: String, mut record: PromptRecord) -> ShownPrompt {
+    if record.summary.is_some()
+        && let Some(full) = repo.storage.read_local_transcript(&hash)
+    {
+        record.messages = full;
+        record.summary = None;
+    }
+    ShownPrompt { hash, record }
+}
+
+/// A prompt the way `show-prompt` prints it: its agent and model, then each message
+pub fn format_prompt(prompt: &ShownPrompt) -> String {
+    let record = &prompt.record;
+    let mut out = String::new();
+    out.push_str(&format!("prompt:  {}\n", prompt.hash));
+    out.push_str(&format!("agent:   {}\n", record.agent_id.tool));
+    out.push_str(&format!("model:   {}\n", record.agent_id.model));
+    if let Some(human) = &record.human_author {
+        out.push_str(&format!("human:   {}\n", human));
+    }
+    if let Some(summary) = &record.summary {
+        out.push_str(&format!(
+            "summary: {} of {} messages kept in the note\n",
+            record.messages.len(),
+            summary.message_count
+        ));
+    }
+
+    for message in &record.messages {
+        let (role, text, timestamp) = match message {
+            Message::User { text, timestamp } => ("user", text.clone(), timestamp),
+            Message::Assistant { text, timestamp } => ("assistant", text.clone(), timestamp),
+            Message::ToolUse {
+                name, timestamp, ..
+            } => ("tool", name.clone(), timestamp),
+        };
+        out.push('\n');
+        match timestamp {
+            Some(timestamp) => out.push_str(&format!("[{}] {}\n", role, timestamp)),
+            None => out.push_str(&format!("[{}]\n", role)),
+        }
+        for line in text.lines() {
+            out.push_str(&format!("  {}\n", line));
+        }
+    }
+    out
+}
@@ -115,13 +115,16 @@ pub fn run(repo: &Repository, json_output: bool) -> Result<(), GitAiError> {
             // Check if authorship log already exists for this child
             if show_authorship_note(repo, child_commit).is_none() {
                 // No authorship log exists, create one
-                let authorship_log =
+                let mut authorship_log =
                     AuthorshipLog::from_working_log_with_base_commit_and_human_author(
                         &checkpoints,
                         commit_hash,
                         None,
                         None,
                     );
+                if repo.settings().ignore_prompts {
+                    authorship_log.clear_transcripts();
+                }
 
                 // Serialize the authorship log
                 let authorship_json = authorship_log.serialize_to_string().map_err(|_| {
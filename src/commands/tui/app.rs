@@ -0,0 +1,783 @@
+//! What `git-ai ui` shows and how keys move through it, kept apart from the terminal so tests
+//! can drive it with canned data and render it into a fixed-size buffer.
+
+use crate::commands::log::{CommitLogStat, FileStat};
+use crate::commands::show_prompt::{ShownPrompt, format_prompt};
+use crate::error::GitAiError;
+use ratatui::Frame;
+use ratatui::crossterm::event::KeyCode;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{List, ListItem, ListState, Paragraph};
+
+const AI_COLOR: Color = Color::Magenta;
+const DIM_COLOR: Color = Color::DarkGray;
+
+/// Widest the author column of a file gets
+const AUTHOR_WIDTH: usize = 12;
+
+/// A line of a file as of a commit, and who wrote it: the agent for AI lines, the commit
+/// author otherwise
+#[derive(Debug, Clone)]
+pub struct FileLine {
+    pub content: String,
+    pub author: String,
+    pub ai: bool,
+}
+
+/// Where the UI reads a commit's details from when the user opens it
+pub trait Source {
+    /// The stats bar `git-ai stats` prints for the commit
+    fn stats_bar(&self, commit: &str) -> Result<String, GitAiError>;
+    fn file_lines(&self, commit: &str, path: &str) -> Result<Vec<FileLine>, GitAiError>;
+    fn commit_prompts(&self, commit: &str) -> Result<Vec<ShownPrompt>, GitAiError>;
+    fn line_prompt(&self, commit: &str, path: &str, line: u32) -> Result<ShownPrompt, GitAiError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tone {
+    Plain,
+    Ai,
+    Dim,
+}
+
+impl Tone {
+    fn style(self) -> Style {
+        match self {
+            Tone::Plain => Style::new(),
+            Tone::Ai => Style::new().fg(AI_COLOR),
+            Tone::Dim => Style::new().fg(DIM_COLOR),
+        }
+    }
+}
+
+struct Row {
+    text: String,
+    tone: Tone,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum View {
+    Commits,
+    Files { commit: usize },
+    Lines { commit: usize, path: String },
+    Prompt,
+}
+
+/// One screen, stacked on the one it was opened from
+struct Pane {
+    view: View,
+    title: String,
+    /// Shown above the rows and never scrolled, like a commit's stats bar
+    header: Vec<Line<'static>>,
+    rows: Vec<Row>,
+    /// The selected row, or the top row of a prompt, which has no selection
+    selected: usize,
+    /// The top row on screen as of the last draw
+    scroll: usize,
+}
+
+impl Pane {
+    fn selectable(&self) -> bool {
+        !matches!(self.view, View::Prompt)
+    }
+}
+
+pub struct App {
+    commits: Vec<CommitLogStat>,
+    panes: Vec<Pane>,
+    message: Option<String>,
+    /// Rows below the title bar, the header and above the footer, as of the last draw
+    page: usize,
+    quit: bool,
+}
+
+impl App {
+    pub fn new(commits: Vec<CommitLogStat>) -> Self {
+        let rows = commits.iter().map(commit_row).collect();
+        App {
+            commits,
+            panes: vec![Pane {
+                view: View::Commits,
+                title: "git-ai ui".to_string(),
+                header: Vec::new(),
+                rows,
+                selected: 0,
+                scroll: 0,
+            }],
+            message: None,
+            page: 1,
+            quit: false,
+        }
+    }
+
+    pub fn should_quit(&self) -> bool {
+        self.quit
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode, source: &dyn Source) {
+        self.message = None;
+        let page = self.page as isize;
+        match key {
+            KeyCode::Char('q') => self.quit = true,
+            // The commit list is never closed
+            KeyCode::Esc | KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h')
+                if self.panes.len() > 1 =>
+            {
+                self.panes.pop();
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.move_by(1),
+            KeyCode::PageUp => self.move_by(-page),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.move_by(page),
+            KeyCode::Home | KeyCode::Char('g') => self.move_by(isize::MIN),
+            KeyCode::End | KeyCode::Char('G') => self.move_by(isize::MAX),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => self.open(source),
+            KeyCode::Char('p') => self.open_prompt(source),
+            _ => {}
+        }
+    }
+
+    /// The title bar, the current pane and a footer with the last message or the keys
+    pub fn draw(&mut self, frame: &mut Frame) {
+        let title = self
+            .panes
+            .iter()
+            .map(|pane| pane.title.as_str())
+            .collect::<Vec<_>>()
+            .join(" › ");
+        let footer = match &self.message {
+            Some(message) => Line::raw(expand_tabs(message)),
+            None => Line::styled(self.help(), Tone::Dim.style()),
+        };
+
+        let pane = self
+            .panes
+            .last_mut()
+            .expect("the commit list is never closed");
+        let [title_area, header_area, body_area, footer_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Length(pane.header.len() as u16),
+            Constraint::Fill(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        self.page = (body_area.height as usize).max(1);
+
+        frame.render_widget(Paragraph::new(expand_tabs(&title)).reversed(), title_area);
+        frame.render_widget(Paragraph::new(pane.header.clone()), header_area);
+        if pane.rows.is_empty() {
+            frame.render_widget(
+                Paragraph::new("Nothing to show").style(Tone::Dim.style()),
+                body_area,
+            );
+        } else if pane.selectable() {
+            let items: Vec<ListItem> = pane
+                .rows
+                .iter()
+                .map(|row| ListItem::new(expand_tabs(&row.text)).style(row.tone.style()))
+                .collect();
+            let mut state = ListState::default()
+                .with_selected(Some(pane.selected))
+                .with_offset(pane.scroll);
+            frame.render_stateful_widget(
+                List::new(items).highlight_style(Style::new().reversed()),
+                body_area,
+                &mut state,
+            );
+            pane.scroll = state.offset();
+        } else {
+            let lines: Vec<Line> = pane
+                .rows
+                .iter()
+                .skip(pane.selected)
+                .take(self.page)
+                .map(|row| Line::styled(expand_tabs(&row.text), row.tone.style()))
+                .collect();
+            frame.render_widget(Paragraph::new(lines), body_area);
+            pane.scroll = pane.selected;
+        }
+        frame.render_widget(Paragraph::new(footer), footer_area);
+    }
+
+    fn help(&self) -> &'static str {
+        match self.panes.last().map(|pane| &pane.view) {
+            Some(View::Commits) | None => "↑/↓ move  enter files  p prompts  q quit",
+            Some(View::Files { .. }) => "↑/↓ move  enter lines  p prompts  esc back  q quit",
+            Some(View::Lines { .. }) => "↑/↓ move  enter/p prompt for line  esc back  q quit",
+            Some(View::Prompt) => "↑/↓ scroll  esc back  q quit",
+        }
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        let page = self.page;
+        let pane = self
+            .panes
+            .last_mut()
+            .expect("the commit list is never closed");
+        let last = if pane.selectable() {
+            pane.rows.len().saturating_sub(1)
+        } else {
+            pane.rows.len().saturating_sub(page)
+        };
+        pane.selected = pane.selected.saturating_add_signed(delta).min(last);
+    }
+
+    fn open(&mut self, source: &dyn Source) {
+        let Some(pane) = self.panes.last() else {
+            return;
+        };
+        let selected = pane.selected;
+        match &pane.view {
+            View::Commits => {
+                if selected < self.commits.len() {
+                    self.open_commit(selected, source);
+                }
+            }
+            View::Files { commit } => {
+                let commit = *commit;
+                if let Some(file) = self.commits[commit].files.get(selected).cloned() {
+                    self.open_file(commit, &file, source);
+                }
+            }
+            View::Lines { .. } => self.open_prompt(source),
+            View::Prompt => {}
+        }
+    }
+
+    fn open_commit(&mut self, index: usize, source: &dyn Source) {
+        let commit = &self.commits[index];
+        let mut header: Vec<Line<'static>> = match source.stats_bar(&commit.commit) {
+            Ok(bar) => bar.lines().map(ansi_line).collect(),
+            Err(e) => vec![Line::styled(
+                format!("Stats unavailable: {}", e),
+                Tone::Dim.style(),
+            )],
+        };
+        header.push(Line::default());
+        let path_width = commit.files.iter().map(|f| f.path.len()).max().unwrap_or(0);
+        let rows = commit
+            .files
+            .iter()
+            .map(|file| file_row(file, path_width))
+            .collect();
+        let title = format!("{} {}", short_sha(&commit.commit), subject(commit));
+        self.panes.push(Pane {
+            view: View::Files { commit: index },
+            title,
+            header,
+            rows,
+            selected: 0,
+            scroll: 0,
+        });
+    }
+
+    fn open_file(&mut self, commit: usize, file: &FileStat, source: &dyn Source) {
+        if file.binary {
+            self.message = Some(format!("{} is a binary file", file.path));
+            return;
+        }
+        let lines = match source.file_lines(&self.commits[commit].commit, &file.path) {
+            Ok(lines) => lines,
+            Err(e) => {
+                self.message = Some(format!("Can't show {}: {}", file.path, e));
+                return;
+            }
+        };
+        let number_width = lines.len().to_string().len();
+        let rows = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| Row {
+                text: format!(
+                    "{:>number_width$} {:<AUTHOR_WIDTH$} {}",
+                    i + 1,
+                    line.author.chars().take(AUTHOR_WIDTH).collect::<String>(),
+                    line.content
+                ),
+                tone: if line.ai { Tone::Ai } else { Tone::Plain },
+            })
+            .collect();
+        self.panes.push(Pane {
+            view: View::Lines {
+                commit,
+                path: file.path.clone(),
+            },
+            title: file.path.clone(),
+            header: Vec::new(),
+            rows,
+            selected: 0,
+            scroll: 0,
+        });
+    }
+
+    /// The prompts of the selected commit, or the prompt that wrote the selected line
+    fn open_prompt(&mut self, source: &dyn Source) {
+        let Some(pane) = self.panes.last() else {
+            return;
+        };
+        let (title, prompts) = match &pane.view {
+            View::Commits | View::Files { .. } => {
+                let index = match pane.view {
+                    View::Files { commit } => commit,
+                    _ => pane.selected,
+                };
+                let Some(commit) = self.commits.get(index) else {
+                    return;
+                };
+                ("prompts".to_string(), source.commit_prompts(&commit.commit))
+            }
+            View::Lines { commit, path } => {
+                let line = pane.selected + 1;
+                if pane.rows.get(pane.selected).map(|row| row.tone) != Some(Tone::Ai) {
+                    self.message = Some(format!("Line {} wasn't written by AI", line));
+                    return;
+                }
+                (
+                    format!("prompt for line {}", line),
+                    source
+                        .line_prompt(&self.commits[*commit].commit, path, line as u32)
+                        .map(|prompt| vec![prompt]),
+                )
+            }
+            View::Prompt => return,
+        };
+        let prompts = match prompts {
+            Ok(prompts) => prompts,
+            Err(e) => {
+                self.message = Some(e.to_string());
+                return;
+            }
+        };
+
+        let mut rows = Vec::new();
+        for (i, prompt) in prompts.iter().enumerate() {
+            if i > 0 {
+                rows.push(Row {
+                    text: String::new(),
+                    tone: Tone::Plain,
+                });
+            }
+            rows.extend(format_prompt(prompt).lines().map(|line| Row {
+                text: line.to_string(),
+                tone: Tone::Plain,
+            }));
+        }
+        self.panes.push(Pane {
+            view: View::Prompt,
+            title,
+            header: Vec::new(),
+            rows,
+            selected: 0,
+            scroll: 0,
+        });
+    }
+}
+
+/// `abc1234 2026-10-15  ai  70%  Add parser (Jane Doe)`, greyed out for commits without a note
+fn commit_row(commit: &CommitLogStat) -> Row {
+    let added: u32 = commit.files.iter().map(|f| f.added).sum();
+    let ai_lines: u32 = commit.files.iter().map(|f| f.ai_lines.min(f.added)).sum();
+    let share = if !commit.has_note {
+        "      -".to_string()
+    } else if added == 0 {
+        "ai   0%".to_string()
+    } else {
+        format!(
+            "ai {:>3}%",
+            (ai_lines as f64 * 100.0 / added as f64).round() as u32
+        )
+    };
+    let author = commit
+        .author
+        .split_once(" <")
+        .map_or(commit.author.as_str(), |(name, _)| name);
+    Row {
+        text: format!(
+            "{} {}  {}  {} ({})",
+            short_sha(&commit.commit),
+            commit.date,
+            share,
+            subject(commit),
+            author
+        ),
+        tone: if commit.has_note {
+            Tone::Plain
+        } else {
+            Tone::Dim
+        },
+    }
+}
+
+/// `src/parser.rs  +120  -14  ai 70%`
+fn file_row(file: &FileStat, path_width: usize) -> Row {
+    let changes = if file.binary {
+        "Bin".to_string()
+    } else {
+        format!(
+            "{:>6} {:>6}",
+            format!("+{}", file.added),
+            format!("-{}", file.deleted)
+        )
+    };
+    let share = match file.ai_percentage().filter(|_| file.ai_lines > 0) {
+        Some(percentage) => format!("  ai {}%", percentage),
+        None => String::new(),
+    };
+    Row {
+        text: format!("{:<path_width$} {}{}", file.path, changes, share),
+        tone: if file.ai_lines > 0 {
+            Tone::Ai
+        } else {
+            Tone::Plain
+        },
+    }
+}
+
+fn short_sha(sha: &str) -> &str {
+    &sha[..sha.len().min(7)]
+}
+
+fn subject(commit: &CommitLogStat) -> &str {
+    commit.message.lines().next().unwrap_or("")
+}
+
+/// `text` with tabs expanded, which the terminal would otherwise move the cursor for
+fn expand_tabs(text: &str) -> String {
+    text.replace('\t', "    ")
+}
+
+/// A line of the stats bar, which greys parts out with ANSI escapes, as styled spans. Other
+/// escapes are dropped.
+fn ansi_line(text: &str) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut style = Style::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("\x1b[") {
+        if start > 0 {
+            spans.push(Span::styled(rest[..start].to_string(), style));
+        }
+        let escape = &rest[start + 2..];
+        let end = escape.find('m').unwrap_or(escape.len());
+        style = match &escape[..end] {
+            "90" => Tone::Dim.style(),
+            _ => Style::new(),
+        };
+        rest = escape.get(end + 1..).unwrap_or("");
+    }
+    if !rest.is_empty() {
+        spans.push(Span::styled(rest.to_string(), style));
+    }
+    Line::from(spans)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::authorship::authorship_log::PromptRecord;
+    use crate::authorship::transcript::Message;
+    use crate::authorship::working_log::AgentId;
+    use ratatui::Terminal;
+    use ratatui::backend::TestBackend;
+    use ratatui::buffer::Buffer;
+    use ratatui::style::Modifier;
+
+    struct CannedSource;
+
+    impl Source for CannedSource {
+        fn stats_bar(&self, _commit: &str) -> Result<String, GitAiError> {
+            Ok("you  ████████████████████ ai\n     50% human 50% ai".to_string())
+        }
+
+        fn file_lines(&self, _commit: &str, _path: &str) -> Result<Vec<FileLine>, GitAiError> {
+            Ok(vec![
+                FileLine {
+                    content: "fn human() {}".to_string(),
+                    author: "Jane Doe".to_string(),
+                    ai: false,
+                },
+                FileLine {
+                    content: "fn generated() {}".to_string(),
+                    author: "mock_ai".to_string(),
+                    ai: true,
+                },
+            ])
+        }
+
+        fn commit_prompts(&self, commit: &str) -> Result<Vec<ShownPrompt>, GitAiError> {
+            if commit.starts_with('1') {
+                Ok(vec![self.line_prompt(commit, "src/parser.rs", 2)?])
+            } else {
+                Err(GitAiError::Generic("Commit has no AI prompts".to_string()))
+            }
+        }
+
+        fn line_prompt(
+            &self,
+            _commit: &str,
+            _path: &str,
+            _line: u32,
+        ) -> Result<ShownPrompt, GitAiError> {
+            Ok(ShownPrompt {
+                hash: "abcd1234".to_string(),
+                record: PromptRecord {
+                    agent_id: AgentId {
+                        tool: "mock_ai".to_string(),
+                        id: "session".to_string(),
+                        model: "test_model".to_string(),
+                    },
+                    human_author: None,
+                    messages: vec![Message::User {
+                        text: "Write a generated function".to_string(),
+                        timestamp: None,
+                    }],
+                    total_additions: 1,
+                    total_deletions: 0,
+                    accepted_lines: 1,
+                    overriden_lines: 0,
+                    summary: None,
+                    environments: Vec::new(),
+                    generated_at: None,
+                },
+            })
+        }
+    }
+
+    fn commit(sha: &str, message: &str, has_note: bool, files: Vec<FileStat>) -> CommitLogStat {
+        CommitLogStat {
+            commit: sha.to_string(),
+            author: "Jane Doe <jane@example.com>".to_string(),
+            date: "2026-10-15".to_string(),
+            message: message.to_string(),
+            has_note,
+            files,
+        }
+    }
+
+    fn file(path: &str, added: u32, ai_lines: u32) -> FileStat {
+        FileStat {
+            path: path.to_string(),
+            added,
+            deleted: 0,
+            ai_lines,
+            binary: false,
+        }
+    }
+
+    fn app() -> App {
+        App::new(vec![
+            commit(
+                "1111111111111111111111111111111111111111",
+                "Add parser\n\nWith tests",
+                true,
+                vec![file("src/parser.rs", 10, 7), file("README.md", 2, 0)],
+            ),
+            commit(
+                "2222222222222222222222222222222222222222",
+                "Initial commit",
+                false,
+                vec![file("README.md", 1, 0)],
+            ),
+        ])
+    }
+
+    /// `app` drawn into a `width` x `height` buffer
+    fn draw(app: &mut App, width: u16, height: u16) -> Buffer {
+        let mut terminal = Terminal::new(TestBackend::new(width, height)).unwrap();
+        terminal.draw(|frame| app.draw(frame)).unwrap();
+        terminal.backend().buffer().clone()
+    }
+
+    /// The text of each row of `buffer`, without trailing blanks
+    fn rows(buffer: &Buffer) -> Vec<String> {
+        (0..buffer.area.height)
+            .map(|y| {
+                (0..buffer.area.width)
+                    .map(|x| buffer[(x, y)].symbol())
+                    .collect::<String>()
+                    .trim_end()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    fn selected(app: &App) -> usize {
+        app.panes.last().unwrap().selected
+    }
+
+    #[test]
+    fn test_commit_list_renders_into_a_fixed_size_buffer() {
+        let mut app = app();
+        let buffer = draw(&mut app, 60, 5);
+        assert_eq!(
+            rows(&buffer),
+            vec![
+                "git-ai ui",
+                "1111111 2026-10-15  ai  58%  Add parser (Jane Doe)",
+                "2222222 2026-10-15        -  Initial commit (Jane Doe)",
+                "",
+                "↑/↓ move  enter files  p prompts  q quit",
+            ]
+        );
+        // The title bar and the selected commit are reversed across the whole width
+        for y in [0, 1] {
+            assert!(buffer[(59, y)].modifier.contains(Modifier::REVERSED));
+        }
+        assert!(!buffer[(0, 2)].modifier.contains(Modifier::REVERSED));
+        // Commits without a note are greyed out, like the keys in the footer
+        assert_eq!(buffer[(0, 2)].fg, DIM_COLOR);
+        assert_eq!(buffer[(0, 4)].fg, DIM_COLOR);
+    }
+
+    #[test]
+    fn test_navigation_moves_and_clamps_the_selection() {
+        let commits = (0..20)
+            .map(|i| {
+                commit(
+                    &format!("{:040}", i),
+                    &format!("Commit {}", i),
+                    true,
+                    vec![],
+                )
+            })
+            .collect();
+        let mut app = App::new(commits);
+        // Four rows between the title bar and the footer
+        draw(&mut app, 80, 6);
+
+        app.handle_key(KeyCode::Up, &CannedSource);
+        assert_eq!(selected(&app), 0);
+        app.handle_key(KeyCode::Down, &CannedSource);
+        app.handle_key(KeyCode::Char('j'), &CannedSource);
+        assert_eq!(selected(&app), 2);
+        app.handle_key(KeyCode::Char('k'), &CannedSource);
+        assert_eq!(selected(&app), 1);
+        app.handle_key(KeyCode::PageDown, &CannedSource);
+        assert_eq!(selected(&app), 5);
+        app.handle_key(KeyCode::End, &CannedSource);
+        assert_eq!(selected(&app), 19);
+        app.handle_key(KeyCode::Down, &CannedSource);
+        assert_eq!(selected(&app), 19);
+
+        // The list scrolls to keep the selection on screen
+        let buffer = draw(&mut app, 80, 6);
+        let screen = rows(&buffer);
+        assert!(screen[1].contains("Commit 16"), "{:?}", screen);
+        assert!(screen[4].contains("Commit 19"), "{:?}", screen);
+        assert!(buffer[(0, 4)].modifier.contains(Modifier::REVERSED));
+
+        app.handle_key(KeyCode::PageUp, &CannedSource);
+        assert_eq!(selected(&app), 15);
+        app.handle_key(KeyCode::Home, &CannedSource);
+        assert_eq!(selected(&app), 0);
+        assert!(rows(&draw(&mut app, 80, 6))[1].contains("Commit 0"));
+    }
+
+    #[test]
+    fn test_drill_into_commit_file_and_line_prompt() {
+        let mut app = app();
+        draw(&mut app, 80, 10);
+        app.handle_key(KeyCode::Enter, &CannedSource);
+        assert_eq!(app.panes.last().unwrap().view, View::Files { commit: 0 });
+        let buffer = draw(&mut app, 80, 10);
+        let screen = rows(&buffer);
+        assert_eq!(screen[0], "git-ai ui › 1111111 Add parser");
+        assert_eq!(screen[2], "     50% human 50% ai");
+        assert_eq!(screen[4], "src/parser.rs    +10     -0  ai 70%");
+        assert_eq!(buffer[(0, 4)].fg, AI_COLOR);
+        assert!(buffer[(0, 4)].modifier.contains(Modifier::REVERSED));
+        assert!(screen[5].starts_with("README.md"));
+
+        // The selected file is the one opened
+        app.handle_key(KeyCode::Down, &CannedSource);
+        app.handle_key(KeyCode::Up, &CannedSource);
+        app.handle_key(KeyCode::Enter, &CannedSource);
+        assert_eq!(
+            app.panes.last().unwrap().view,
+            View::Lines {
+                commit: 0,
+                path: "src/parser.rs".to_string()
+            }
+        );
+        let buffer = draw(&mut app, 80, 10);
+        let screen = rows(&buffer);
+        assert_eq!(screen[0], "git-ai ui › 1111111 Add parser › src/parser.rs");
+        assert_eq!(screen[1], "1 Jane Doe     fn human() {}");
+        assert_eq!(screen[2], "2 mock_ai      fn generated() {}");
+        assert_eq!(buffer[(0, 2)].fg, AI_COLOR);
+
+        // The first line was written by a person, so it has no prompt
+        app.handle_key(KeyCode::Char('p'), &CannedSource);
+        assert_eq!(
+            rows(&draw(&mut app, 80, 10))[9],
+            "Line 1 wasn't written by AI"
+        );
+
+        app.handle_key(KeyCode::Down, &CannedSource);
+        app.handle_key(KeyCode::Enter, &CannedSource);
+        assert_eq!(app.panes.last().unwrap().view, View::Prompt);
+        let screen = rows(&draw(&mut app, 80, 10));
+        assert!(screen[0].ends_with("prompt for line 2"));
+        assert!(screen.iter().any(|line| line.contains("agent:   mock_ai")));
+        assert!(
+            screen
+                .iter()
+                .any(|line| line.contains("Write a generated function"))
+        );
+
+        // Back out to the commit list, where going back does nothing
+        for _ in 0..4 {
+            app.handle_key(KeyCode::Esc, &CannedSource);
+        }
+        assert_eq!(app.panes.len(), 1);
+        assert_eq!(rows(&draw(&mut app, 80, 10))[0], "git-ai ui");
+        assert!(!app.should_quit());
+        app.handle_key(KeyCode::Char('q'), &CannedSource);
+        assert!(app.should_quit());
+    }
+
+    #[test]
+    fn test_prompts_of_the_selected_commit() {
+        let mut app = app();
+        draw(&mut app, 80, 6);
+
+        // The second commit has none, which the footer says
+        app.handle_key(KeyCode::Down, &CannedSource);
+        app.handle_key(KeyCode::Char('p'), &CannedSource);
+        assert_eq!(app.panes.len(), 1);
+        assert!(rows(&draw(&mut app, 80, 6))[5].ends_with("Commit has no AI prompts"));
+
+        // The first one's prompts open from the list and from its files alike
+        app.handle_key(KeyCode::Up, &CannedSource);
+        app.handle_key(KeyCode::Char('p'), &CannedSource);
+        assert_eq!(app.panes.last().unwrap().view, View::Prompt);
+        assert_eq!(rows(&draw(&mut app, 80, 6))[0], "git-ai ui › prompts");
+        app.handle_key(KeyCode::Esc, &CannedSource);
+        app.handle_key(KeyCode::Enter, &CannedSource);
+        app.handle_key(KeyCode::Char('p'), &CannedSource);
+        assert_eq!(
+            rows(&draw(&mut app, 80, 6))[0],
+            "git-ai ui › 1111111 Add parser › prompts"
+        );
+    }
+
+    #[test]
+    fn test_stats_bar_escapes_become_styles() {
+        let line = ansi_line("you  \x1b[90m    \x1b[0m ai");
+        let spans: Vec<(&str, Style)> = line
+            .spans
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style))
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("you  ", Style::new()),
+                ("    ", Style::new().fg(DIM_COLOR)),
+                (" ai", Style::new()),
+            ]
+        );
+        assert_eq!(expand_tabs("\tab"), "    ab");
+    }
+}
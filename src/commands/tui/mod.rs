@@ -0,0 +1,127 @@
+//! `git-ai ui`: browse commits with their stats bars, open a commit's files to see which lines
+//! AI wrote, and read the prompts behind them.
+
+mod app;
+
+use crate::authorship::stats::{stats_for_commit_stats, write_stats_to_terminal};
+use crate::commands::blame::GitAiBlameOptions;
+use crate::commands::log::log_stats;
+use crate::commands::show_prompt::{ShownPrompt, prompt_for_line, prompts_for_commit};
+use crate::error::GitAiError;
+use crate::git::find_repository;
+use crate::git::repository::Repository;
+use app::{App, FileLine, Source};
+use ratatui::DefaultTerminal;
+use ratatui::crossterm::event::{self, Event, KeyEventKind};
+use std::io::IsTerminal;
+
+/// Commits listed when no `-n`/`--max-count` is given
+const DEFAULT_MAX_COUNT: usize = 200;
+
+pub fn handle_ui(args: &[String]) {
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        eprintln!("git-ai ui needs an interactive terminal; try git-ai log --stat instead");
+        std::process::exit(1);
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = run(&repo, args) {
+        eprintln!("UI failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn run(repo: &Repository, args: &[String]) -> Result<(), GitAiError> {
+    let mut log_args = vec!["--date=short".to_string()];
+    if !args
+        .iter()
+        .any(|arg| arg.starts_with("-n") || arg.starts_with("--max-count"))
+    {
+        log_args.push(format!("--max-count={}", DEFAULT_MAX_COUNT));
+    }
+    log_args.extend(args.iter().cloned());
+    let commits = log_stats(repo, &log_args)?;
+
+    let mut app = App::new(commits);
+    // Raw mode on the alternate screen, put back on the way out and on panics
+    let mut terminal = ratatui::try_init()?;
+    let result = event_loop(&mut terminal, &mut app, repo);
+    ratatui::restore();
+    result
+}
+
+fn event_loop(
+    terminal: &mut DefaultTerminal,
+    app: &mut App,
+    repo: &Repository,
+) -> Result<(), GitAiError> {
+    while !app.should_quit() {
+        terminal.draw(|frame| app.draw(frame))?;
+        // Resizes and other events only redraw
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            app.handle_key(key.code, repo);
+        }
+    }
+    Ok(())
+}
+
+impl Source for Repository {
+    fn stats_bar(&self, commit: &str) -> Result<String, GitAiError> {
+        let stats = stats_for_commit_stats(self, commit, "")?;
+        Ok(write_stats_to_terminal(&stats, false))
+    }
+
+    fn file_lines(&self, commit: &str, path: &str) -> Result<Vec<FileLine>, GitAiError> {
+        let content = self.get_file_content(path, commit)?;
+        if content.contains(&0) {
+            return Err(GitAiError::Generic("it's a binary file".to_string()));
+        }
+        let options = GitAiBlameOptions {
+            newest_commit: Some(commit.to_string()),
+            use_prompt_hashes_as_names: true,
+            no_output: true,
+            ..Default::default()
+        };
+        let (line_authors, prompt_records) = self.blame(path, &options)?;
+
+        Ok(String::from_utf8_lossy(&content)
+            .lines()
+            .enumerate()
+            .map(|(i, content)| {
+                let author = line_authors
+                    .get(&(i as u32 + 1))
+                    .cloned()
+                    .unwrap_or_default();
+                match prompt_records.get(&author) {
+                    Some(record) => FileLine {
+                        content: content.to_string(),
+                        author: record.agent_id.tool.clone(),
+                        ai: true,
+                    },
+                    None => FileLine {
+                        content: content.to_string(),
+                        author,
+                        ai: false,
+                    },
+                }
+            })
+            .collect())
+    }
+
+    fn commit_prompts(&self, commit: &str) -> Result<Vec<ShownPrompt>, GitAiError> {
+        prompts_for_commit(self, commit)
+    }
+
+    fn line_prompt(&self, commit: &str, path: &str, line: u32) -> Result<ShownPrompt, GitAiError> {
+        prompt_for_line(self, commit, path, line)
+    }
+}
@@ -0,0 +1,378 @@
+use crate::authorship::working_log::{AgentId, CheckpointKind};
+use crate::cancellation::{self, CancellationToken};
+use crate::commands::checkpoint::CheckpointOptions;
+use crate::commands::checkpoint_agent::agent_presets::AgentRunResult;
+use crate::commands::hooks::commit_hooks::get_commit_default_author;
+use crate::commands::hooks::head_coherence::operation_in_progress;
+use crate::commands::process_queue::finish_pending_work;
+use crate::error::GitAiError;
+use crate::fs_notify::{Changes, Notifier};
+use crate::git::find_repository;
+use crate::git::repo_storage::AgentSession;
+use crate::git::repository::{Repository, exec_git, exec_git_stdin};
+use std::collections::{BTreeSet, HashSet};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+const DEFAULT_DEBOUNCE_MS: u64 = 300;
+
+/// How long to wait for the next change while nothing is pending
+const IDLE_WAIT: Duration = Duration::from_secs(1);
+
+pub fn handle_watch(args: &[String]) {
+    if args.first().map(String::as_str) == Some("session") {
+        handle_session(&args[1..]);
+        return;
+    }
+
+    let mut debounce = Duration::from_millis(DEFAULT_DEBOUNCE_MS);
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--debounce" if i + 1 < args.len() => {
+                debounce = match args[i + 1].parse::<u64>() {
+                    Ok(ms) => Duration::from_millis(ms),
+                    Err(_) => {
+                        eprintln!("--debounce takes milliseconds, got: {}", args[i + 1]);
+                        std::process::exit(1);
+                    }
+                };
+                i += 2;
+            }
+            _ => {
+                eprintln!("Unknown watch argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Err(e) = watch(&repo, debounce) {
+        eprintln!("Watch failed: {}", e);
+        std::process::exit(1);
+    }
+    cancellation::exit_if_cancelled();
+}
+
+/// Checkpoint the worktree's changes once they've settled for `debounce`, until interrupted.
+/// Changes made while a registered agent session is active are the agent's, the rest human.
+pub fn watch(repo: &Repository, debounce: Duration) -> Result<(), GitAiError> {
+    let workdir = repo.workdir().map_err(|_| {
+        GitAiError::Generic("git-ai watch needs a repository with a working tree".to_string())
+    })?;
+    let ignored_dirs = ignored_dirs(repo)?;
+    let mut notifier = Notifier::new(&workdir, move |dir| ignored_dirs.contains(dir))?;
+
+    // Interrupting flushes what is pending before exiting
+    cancellation::install_handlers();
+    let interrupted = CancellationToken::signals();
+
+    println!(
+        "Watching {}{} (Ctrl-C to stop)",
+        workdir.display(),
+        if notifier.is_native() {
+            ""
+        } else {
+            ", polling for changes"
+        }
+    );
+
+    let mut pending = Changes::default();
+    let mut last_change = Instant::now();
+    loop {
+        let wait = if pending.is_empty() {
+            IDLE_WAIT
+        } else {
+            debounce.saturating_sub(last_change.elapsed())
+        };
+        let changes = notifier.wait(wait)?;
+        if !changes.is_empty() {
+            pending.paths.extend(changes.paths);
+            pending.everything |= changes.everything;
+            last_change = Instant::now();
+        }
+
+        if interrupted.is_cancelled() {
+            // Changes already queued by the notifier but not yet read belong to this batch too
+            let changes = notifier.wait(Duration::ZERO)?;
+            pending.paths.extend(changes.paths);
+            pending.everything |= changes.everything;
+            if !pending.is_empty() {
+                checkpoint_changes(repo, &pending)?;
+            }
+            return Ok(());
+        }
+
+        if pending.is_empty() || last_change.elapsed() < debounce || git_busy(repo) {
+            continue;
+        }
+        checkpoint_changes(repo, &pending)?;
+        pending = Changes::default();
+    }
+}
+
+/// A git command is writing the worktree or stopped mid-operation; its hooks attribute what it
+/// changes, so wait for it to finish
+fn git_busy(repo: &Repository) -> bool {
+    repo.path().join("index.lock").exists() || operation_in_progress(repo)
+}
+
+fn checkpoint_changes(repo: &Repository, changes: &Changes) -> Result<(), GitAiError> {
+    // Commits made since the last batch may have left post-commit work for later
+    finish_pending_work(repo);
+
+    let paths = if changes.everything {
+        None
+    } else {
+        let paths = not_ignored(repo, &changes.paths)?;
+        if paths.is_empty() {
+            return Ok(());
+        }
+        Some(paths)
+    };
+
+    let session = active_session(repo);
+    let agent_run_result = match &session {
+        Some(session) => Some(AgentRunResult {
+            agent_id: session.agent_id.clone(),
+            checkpoint_kind: CheckpointKind::AiAgent,
+            transcript: None,
+            repo_working_dir: None,
+            edited_filepaths: paths.clone(),
+            will_edit_filepaths: None,
+            commit_message_source: None,
+            agent_version: None,
+            file_contents: None,
+        }),
+        None => paths.clone().map(|paths| AgentRunResult {
+            agent_id: AgentId {
+                tool: "human".to_string(),
+                id: "human".to_string(),
+                model: "human".to_string(),
+            },
+            checkpoint_kind: CheckpointKind::Human,
+            transcript: None,
+            repo_working_dir: None,
+            edited_filepaths: None,
+            will_edit_filepaths: Some(paths),
+            commit_message_source: None,
+            agent_version: None,
+            file_contents: None,
+        }),
+    };
+    let kind = agent_run_result
+        .as_ref()
+        .map_or(CheckpointKind::Human, |result| result.checkpoint_kind);
+
+    let author = get_commit_default_author(repo, &[]);
+    let (_, files, _) = crate::commands::checkpoint::run(
+        repo,
+        &author,
+        kind,
+        agent_run_result,
+        CheckpointOptions {
+            quiet: true,
+            ..Default::default()
+        },
+    )?;
+    if files == 0 {
+        return Ok(());
+    }
+
+    let by = match &session {
+        Some(session) => format!("ai {} {}", session.agent_id.tool, session.agent_id.id),
+        None => "human".to_string(),
+    };
+    match &paths {
+        Some(paths) => println!("Checkpoint ({}): {}", by, paths.join(", ")),
+        None => println!("Checkpoint ({}): {} changed file(s)", by, files),
+    }
+    Ok(())
+}
+
+/// The active session refreshed last, whose agent is taken to be the one editing
+fn active_session(repo: &Repository) -> Option<AgentSession> {
+    let now = unix_now();
+    repo.storage
+        .agent_sessions()
+        .into_iter()
+        .filter(|session| session.is_active(now))
+        .max_by_key(|session| session.updated_at)
+}
+
+/// Directories git ignores, relative to the worktree, so the notifier leaves them alone
+fn ignored_dirs(repo: &Repository) -> Result<HashSet<PathBuf>, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.extend(
+        [
+            "ls-files",
+            "--others",
+            "--ignored",
+            "--exclude-standard",
+            "--directory",
+            "-z",
+        ]
+        .map(String::from),
+    );
+    let output = exec_git(&args)?;
+    Ok(output
+        .stdout
+        .split(|byte| *byte == 0)
+        .filter_map(|entry| std::str::from_utf8(entry).ok())
+        .filter_map(|entry| entry.strip_suffix('/'))
+        .map(PathBuf::from)
+        .collect())
+}
+
+/// The changed paths git doesn't ignore, as git paths. Tracked files are never ignored.
+fn not_ignored(repo: &Repository, paths: &BTreeSet<PathBuf>) -> Result<Vec<String>, GitAiError> {
+    let paths: Vec<String> = paths
+        .iter()
+        .filter_map(|path| path.to_str())
+        .map(|path| path.replace(std::path::MAIN_SEPARATOR, "/"))
+        .collect();
+    let mut input = Vec::new();
+    for path in &paths {
+        input.extend_from_slice(path.as_bytes());
+        input.push(0);
+    }
+    let mut args = repo.global_args_for_exec();
+    args.extend(["check-ignore", "--stdin", "-z"].map(String::from));
+    // check-ignore exits 1 when none of the paths is ignored
+    let ignored: HashSet<String> = match exec_git_stdin(&args, &input) {
+        Ok(output) => output
+            .stdout
+            .split(|byte| *byte == 0)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| String::from_utf8_lossy(entry).into_owned())
+            .collect(),
+        Err(_) => HashSet::new(),
+    };
+    Ok(paths
+        .into_iter()
+        .filter(|path| !ignored.contains(path))
+        .collect())
+}
+
+fn handle_session(args: &[String]) {
+    let Some(action) = args.first().map(String::as_str) else {
+        eprintln!("Usage: git-ai watch session <start|end> --tool <tool> --id <id>");
+        std::process::exit(1);
+    };
+    if !matches!(action, "start" | "end") {
+        eprintln!("Unknown watch session action: {}", action);
+        std::process::exit(1);
+    }
+
+    let mut tool: Option<String> = None;
+    let mut id: Option<String> = None;
+    let mut model: Option<String> = None;
+    let mut pid: Option<u32> = None;
+    let mut i = 1;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--tool" if i + 1 < args.len() => {
+                tool = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--id" if i + 1 < args.len() => {
+                id = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--model" if action == "start" && i + 1 < args.len() => {
+                model = Some(args[i + 1].clone());
+                i += 2;
+            }
+            "--pid" if action == "start" && i + 1 < args.len() => {
+                pid = match args[i + 1].parse() {
+                    Ok(pid) => Some(pid),
+                    Err(_) => {
+                        eprintln!("Invalid --pid: {}", args[i + 1]);
+                        std::process::exit(1);
+                    }
+                };
+                i += 2;
+            }
+            _ => {
+                eprintln!("Unknown watch session argument: {}", args[i]);
+                std::process::exit(1);
+            }
+        }
+    }
+    let (Some(tool), Some(id)) = (tool, id) else {
+        eprintln!("watch session {} needs --tool and --id", action);
+        std::process::exit(1);
+    };
+    let agent_id = AgentId {
+        tool,
+        id,
+        model: model.unwrap_or_else(|| "unknown".to_string()),
+    };
+
+    let repo = match find_repository(&Vec::<String>::new()) {
+        Ok(repo) => repo,
+        Err(e) => {
+            eprintln!("Failed to find repository: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let result = if action == "start" {
+        start_session(&repo, agent_id, pid).map(|_| ())
+    } else {
+        repo.storage.remove_agent_session(&agent_id).map(|removed| {
+            if !removed {
+                eprintln!(
+                    "No session {} of {} was registered",
+                    agent_id.id, agent_id.tool
+                );
+            }
+        })
+    };
+    if let Err(e) = result {
+        eprintln!("Failed to update the agent session: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// Register a session, or refresh it when it is already registered. Without a pid the session
+/// ends [`AgentSession::TTL_SECS`] after the last refresh.
+pub fn start_session(
+    repo: &Repository,
+    agent_id: AgentId,
+    pid: Option<u32>,
+) -> Result<AgentSession, GitAiError> {
+    let now = unix_now();
+    let previous = repo.storage.read_agent_session(&agent_id);
+    let session = AgentSession {
+        // A refresh keeps the model it was registered with unless given a new one
+        agent_id: match &previous {
+            Some(previous) if agent_id.model == "unknown" => AgentId {
+                model: previous.agent_id.model.clone(),
+                ..agent_id
+            },
+            _ => agent_id,
+        },
+        pid: pid.or(previous.as_ref().and_then(|previous| previous.pid)),
+        started_at: previous
+            .as_ref()
+            .map_or(now, |previous| previous.started_at),
+        updated_at: now,
+    };
+    repo.storage.write_agent_session(&session)?;
+    Ok(session)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
@@ -1,17 +1,22 @@
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::env;
+use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::OnceLock;
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 
+use crate::authorship::attribution_tracker::AttributionMethod;
+use crate::error::GitAiError;
 use crate::git::repository::Repository;
 
 /// Centralized configuration for the application
 pub struct Config {
     git_path: String,
-    ignore_prompts: bool,
+    jj_path: String,
+    keep_local_transcripts: bool,
     allow_repositories: HashSet<String>,
     exclude_repositories: HashSet<String>,
 }
@@ -20,13 +25,904 @@ struct FileConfig {
     #[serde(default)]
     git_path: Option<String>,
     #[serde(default)]
-    ignore_prompts: Option<bool>,
+    jj_path: Option<String>,
+    #[serde(default)]
+    keep_local_transcripts: Option<bool>,
     #[serde(default)]
     allow_repositories: Option<Vec<String>>,
     #[serde(default)]
     exclude_repositories: Option<Vec<String>>,
 }
 
+/// How much of each agent transcript is written to authorship notes
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptMode {
+    #[default]
+    Full,
+    /// First user and last assistant message plus message counts and edited files
+    Summary,
+}
+
+/// How much of each agent transcript checkpoints and notes keep at all, applied before
+/// anything is written to disk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TranscriptStore {
+    #[default]
+    Full,
+    /// User and assistant messages, without tool calls and their inputs
+    #[serde(alias = "messages-only")]
+    MessagesOnly,
+    /// Every message with its text replaced by a SHA-256 digest
+    #[serde(alias = "hashes-only")]
+    HashesOnly,
+    /// No messages; prompt records keep their hash, agent and line counts
+    None,
+}
+
+/// Which authorship notes a `git push` sends to the remote
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotesPushScope {
+    /// Only the notes of the commits the push sends that the remote doesn't have yet
+    #[default]
+    Pushed,
+    /// The whole notes ref
+    All,
+}
+
+/// Which note is kept when a commit has one locally and a different one on the remote
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotesConflict {
+    #[default]
+    Ours,
+    Theirs,
+}
+
+impl NotesConflict {
+    /// The `git notes merge` strategy of the policy
+    pub fn merge_strategy(self) -> &'static str {
+        match self {
+            NotesConflict::Ours => "ours",
+            NotesConflict::Theirs => "theirs",
+        }
+    }
+}
+
+/// What checkpoints do with edits inside submodules
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SubmodulePolicy {
+    /// Leave them out: the parent repo only sees the submodule's commit change
+    #[default]
+    Ignore,
+    /// Checkpoint each checked out submodule in its own repository, with its own working logs
+    Recurse,
+}
+
+/// Name of the team config file committed at the repository root
+pub const REPO_CONFIG_FILE: &str = ".gitai.toml";
+
+/// Gitignore-style list of files git-ai leaves alone, at the repository root
+pub const IGNORE_FILE: &str = ".gitaiignore";
+
+/// Overrides the path of the system config.toml; empty to read no system file
+pub const SYSTEM_CONFIG_ENV: &str = "GIT_AI_SYSTEM_CONFIG";
+
+/// Policy settings a team can share through a committed `.gitai.toml`. Each one takes its value
+/// from the first place it's set: `git-ai -c <key>=<value>`, a `GIT_AI_<KEY>` environment
+/// variable, the repo's `.gitai.toml`, the user's config.toml, the user's config.json, then the
+/// system config.toml. Machine-level options (git_path, allow/exclude_repositories, ...) stay in
+/// config.json only.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Keep prompt records in notes but drop their transcripts
+    pub ignore_prompts: bool,
+    pub transcript_mode: TranscriptMode,
+    pub transcript_store: TranscriptStore,
+    /// Only attribute work on branches matching these patterns (every branch when empty)
+    pub track_branches: Vec<String>,
+    /// Never attribute work on branches matching these patterns
+    pub ignore_branches: Vec<String>,
+    /// Files matching these patterns are left out of checkpoints
+    pub exclude_paths: Vec<String>,
+    /// Notes ref authorship is stored in, relative to refs/notes/
+    pub notes_ref: String,
+    /// Refuse `git clean` when it would delete files with uncommitted AI attributions
+    pub confirm_clean: bool,
+    /// Record a fingerprint of the machine and agent (hashed hostname, OS, versions) with each
+    /// checkpoint, and summarize it into the prompt records of notes
+    pub record_environment: bool,
+    /// Attribute Copilot tab completions accepted while git-ai wasn't running, from Copilot's
+    /// workspace telemetry
+    pub recover_copilot_telemetry: bool,
+    /// Files matching these patterns count as generated, on top of [`BUILTIN_GENERATED_PATHS`]
+    pub generated_paths: Vec<String>,
+    /// How long one git-ai hook may take before it counts as over budget, in milliseconds
+    pub hook_budget_ms: u64,
+    /// Move post-commit attribution to a background `git-ai process-queue` run once it keeps
+    /// going over budget
+    pub defer_slow_hooks: bool,
+    /// Always attribute commits in the background, so `git commit` returns right away
+    pub defer_post_commit: bool,
+    /// Push the notes ref to the remote along with every `git push`
+    pub push_notes: bool,
+    /// Fetch the remote's notes ref and merge it in with every `git fetch` and `git pull`
+    pub fetch_notes: bool,
+    pub push_notes_scope: NotesPushScope,
+    /// Whose note wins when notes are synced with a remote that has another one for a commit
+    pub notes_conflict: NotesConflict,
+    /// Attribution policy per directory subtree, relative to the repo root. The most specific
+    /// subtree holding a file decides its policy; files outside all of them are tracked.
+    pub directory_policies: BTreeMap<String, DirectoryPolicy>,
+    /// Changed files bigger than this many KiB are left out of checkpoints without being read;
+    /// 0 checks files of any size
+    pub large_file_threshold_kb: u64,
+    /// Trust the size and mtime recorded for a file at the last checkpoint, and reuse its
+    /// content hash instead of reading it again when neither changed
+    pub file_state_cache: bool,
+    /// How many files a checkpoint diffs and blames at once
+    pub checkpoint_concurrency: u64,
+    /// Add trailers with the AI share and agents of each commit made through git-ai to its
+    /// message
+    pub commit_trailers: bool,
+    /// Trailer holding the AI share of the commit's added lines; left out when empty
+    pub assisted_trailer: String,
+    /// Trailer naming each agent (`tool/model`) that wrote lines of the commit; left out when
+    /// empty
+    pub agent_trailer: String,
+    /// Rules every commit a `git push` sends is held to, by name; `git-ai check` runs them on
+    /// any range
+    pub push_policies: BTreeMap<String, PushPolicy>,
+    /// Whether checkpoints also checkpoint the submodules edits land in
+    pub submodules: SubmodulePolicy,
+    /// Keep the original author of code that only changed in whitespace (reindented,
+    /// rewrapped) instead of attributing it to whoever reformatted it
+    pub ignore_whitespace: bool,
+    /// Follow blocks of code that moved within or between files, so they keep their authors
+    pub detect_moves: bool,
+    /// How checkpoints carry authorship through edits
+    pub attribution_algorithm: AttributionMethod,
+    /// Patterns of the repository's `.gitaiignore`; not a setting of its own
+    #[serde(skip)]
+    pub ignore_file: IgnoreRules,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ignore_prompts: false,
+            transcript_mode: TranscriptMode::default(),
+            transcript_store: TranscriptStore::default(),
+            track_branches: Vec::new(),
+            ignore_branches: Vec::new(),
+            exclude_paths: Vec::new(),
+            notes_ref: "ai".to_string(),
+            confirm_clean: false,
+            record_environment: false,
+            recover_copilot_telemetry: true,
+            generated_paths: Vec::new(),
+            hook_budget_ms: 2000,
+            defer_slow_hooks: false,
+            defer_post_commit: false,
+            push_notes: true,
+            fetch_notes: true,
+            push_notes_scope: NotesPushScope::default(),
+            notes_conflict: NotesConflict::default(),
+            directory_policies: BTreeMap::new(),
+            large_file_threshold_kb: 5120,
+            file_state_cache: true,
+            checkpoint_concurrency: 30,
+            commit_trailers: false,
+            assisted_trailer: "AI-Assisted".to_string(),
+            agent_trailer: "AI-Agent".to_string(),
+            push_policies: BTreeMap::new(),
+            submodules: SubmodulePolicy::default(),
+            ignore_whitespace: false,
+            detect_moves: true,
+            attribution_algorithm: AttributionMethod::default(),
+            ignore_file: IgnoreRules::default(),
+        }
+    }
+}
+
+/// Every [`Settings`] key, in the order `git-ai config` lists them
+pub const SETTINGS_KEYS: &[&str] = &[
+    "ignore_prompts",
+    "transcript_mode",
+    "transcript_store",
+    "track_branches",
+    "ignore_branches",
+    "exclude_paths",
+    "notes_ref",
+    "confirm_clean",
+    "record_environment",
+    "recover_copilot_telemetry",
+    "generated_paths",
+    "hook_budget_ms",
+    "defer_slow_hooks",
+    "defer_post_commit",
+    "push_notes",
+    "fetch_notes",
+    "push_notes_scope",
+    "notes_conflict",
+    "directory_policies",
+    "large_file_threshold_kb",
+    "file_state_cache",
+    "checkpoint_concurrency",
+    "commit_trailers",
+    "assisted_trailer",
+    "agent_trailer",
+    "push_policies",
+    "submodules",
+    "ignore_whitespace",
+    "detect_moves",
+    "attribution_algorithm",
+];
+
+/// Keys holding lists, given comma separated in `-c` and environment overrides
+const LIST_SETTINGS: &[&str] = &[
+    "track_branches",
+    "ignore_branches",
+    "exclude_paths",
+    "generated_paths",
+];
+
+/// Keys holding whole numbers
+const NUMBER_SETTINGS: &[&str] = &[
+    "hook_budget_ms",
+    "large_file_threshold_kb",
+    "checkpoint_concurrency",
+];
+
+/// Keys holding tables, given as comma separated `name=value` pairs in `-c` and environment
+/// overrides
+const MAP_SETTINGS: &[&str] = &["directory_policies", "push_policies"];
+
+/// How attribution treats the files of a `directory_policies` subtree. Written as `track`,
+/// `ignore` or `tool:<name>`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum DirectoryPolicy {
+    /// Attribute edits to whoever made them, as everywhere else
+    Track,
+    /// Leave the subtree out of attribution, like `exclude_paths`
+    Ignore,
+    /// Attribute every edit to the named tool, e.g. `tool:protoc` for generated code
+    Tool(String),
+}
+
+impl TryFrom<String> for DirectoryPolicy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.trim() {
+            "track" => Ok(DirectoryPolicy::Track),
+            "ignore" => Ok(DirectoryPolicy::Ignore),
+            other => match other.strip_prefix("tool:").map(str::trim) {
+                Some(tool) if !tool.is_empty() && !tool.contains(char::is_whitespace) => {
+                    Ok(DirectoryPolicy::Tool(tool.to_string()))
+                }
+                _ => Err(format!(
+                    "invalid directory policy {:?}, expected track, ignore or tool:<name>",
+                    value
+                )),
+            },
+        }
+    }
+}
+
+impl From<DirectoryPolicy> for String {
+    fn from(policy: DirectoryPolicy) -> Self {
+        policy.to_string()
+    }
+}
+
+impl fmt::Display for DirectoryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DirectoryPolicy::Track => write!(f, "track"),
+            DirectoryPolicy::Ignore => write!(f, "ignore"),
+            DirectoryPolicy::Tool(tool) => write!(f, "tool:{}", tool),
+        }
+    }
+}
+
+/// A rule of `push_policies`. Written as `max_ai_percent:<n>` (at most n% of a commit's added
+/// lines may be AI-authored lines no human edited) or `no_ai:<glob>` (no AI-authored lines in
+/// matching files).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum PushPolicy {
+    MaxAiPercent(u32),
+    NoAi(String),
+}
+
+impl PushPolicy {
+    /// Whether a `no_ai` rule covers `path` (relative to the repo root)
+    pub fn forbids_ai_in(&self, path: &str) -> bool {
+        match self {
+            PushPolicy::NoAi(pattern) => glob_matches(pattern, path),
+            PushPolicy::MaxAiPercent(_) => false,
+        }
+    }
+}
+
+impl TryFrom<String> for PushPolicy {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let invalid = || {
+            format!(
+                "invalid push policy {:?}, expected max_ai_percent:<n> or no_ai:<glob>",
+                value
+            )
+        };
+        match value.trim().split_once(':') {
+            Some(("max_ai_percent", percent)) => match percent.trim().parse::<u32>() {
+                Ok(percent) if percent <= 100 => Ok(PushPolicy::MaxAiPercent(percent)),
+                _ => Err(invalid()),
+            },
+            Some(("no_ai", pattern)) if !pattern.trim().is_empty() => {
+                Ok(PushPolicy::NoAi(pattern.trim().to_string()))
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl From<PushPolicy> for String {
+    fn from(policy: PushPolicy) -> Self {
+        policy.to_string()
+    }
+}
+
+impl fmt::Display for PushPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PushPolicy::MaxAiPercent(percent) => write!(f, "max_ai_percent:{}", percent),
+            PushPolicy::NoAi(pattern) => write!(f, "no_ai:{}", pattern),
+        }
+    }
+}
+
+/// Lockfiles, generated code and vendored dependencies. Their lines are counted apart from the
+/// AI/human split in stats. A pattern without a `/` matches the file name in any directory.
+pub const BUILTIN_GENERATED_PATHS: &[&str] = &[
+    "Cargo.lock",
+    "package-lock.json",
+    "npm-shrinkwrap.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "bun.lock",
+    "go.sum",
+    "Gemfile.lock",
+    "composer.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "uv.lock",
+    "*.pb.go",
+    "*_pb2.py",
+    "vendor/*",
+    "*/vendor/*",
+    "node_modules/*",
+    "*/node_modules/*",
+];
+
+/// The patterns of a `.gitaiignore`, which follows `.gitignore` syntax: `#` comments, `!` to
+/// re-include, a leading `/` to anchor at the root, a trailing `/` for directories only, and
+/// `*`, `?` and `**` wildcards. A pattern without a `/` matches a name at any depth.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IgnoreRules {
+    rules: Vec<IgnoreRule>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IgnoreRule {
+    pattern: String,
+    negated: bool,
+    directory_only: bool,
+    /// Matched against the whole path rather than each name in it
+    anchored: bool,
+}
+
+impl IgnoreRules {
+    pub fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(|line| {
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line.strip_prefix('\\').unwrap_or(line)),
+                };
+                let (directory_only, line) = match line.strip_suffix('/') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let anchored = line.contains('/');
+                let pattern = line.strip_prefix('/').unwrap_or(line);
+                (!pattern.is_empty()).then(|| IgnoreRule {
+                    pattern: pattern.to_string(),
+                    negated,
+                    directory_only,
+                    anchored,
+                })
+            })
+            .collect();
+        Self { rules }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// Whether `path` (relative to the repo root) is ignored. Like git, the last matching
+    /// pattern decides, and a file inside an ignored directory is ignored.
+    pub fn is_ignored(&self, path: &str) -> bool {
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.negated == ignored && rule.matches(path) {
+                ignored = !rule.negated;
+            }
+        }
+        ignored
+    }
+}
+
+impl IgnoreRule {
+    fn matches(&self, path: &str) -> bool {
+        // Each directory holding the file, then the file itself
+        let mut prefixes: Vec<&str> = path.match_indices('/').map(|(i, _)| &path[..i]).collect();
+        if !self.directory_only {
+            prefixes.push(path);
+        }
+        prefixes.into_iter().any(|prefix| {
+            if self.anchored {
+                path_glob_matches(&self.pattern, prefix)
+            } else {
+                let name = prefix.rsplit('/').next().unwrap_or(prefix);
+                glob_matches(&self.pattern, name)
+            }
+        })
+    }
+}
+
+/// Where a setting's value came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigOrigin {
+    Cli,
+    Env(String),
+    Repo(PathBuf),
+    User(PathBuf),
+    System(PathBuf),
+    Default,
+}
+
+impl fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigOrigin::Cli => write!(f, "command line"),
+            ConfigOrigin::Env(var) => write!(f, "env {}", var),
+            ConfigOrigin::Repo(path) | ConfigOrigin::User(path) | ConfigOrigin::System(path) => {
+                write!(f, "{}", path.display())
+            }
+            ConfigOrigin::Default => write!(f, "default"),
+        }
+    }
+}
+
+/// A settings file `git-ai config` can read and write
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigLevel {
+    /// `/etc/git-ai/config.toml`, for settings an organization rolls out to every machine
+    System,
+    /// The user's `config.toml` under `~/.config/git-ai`
+    Global,
+    /// The repository's committed `.gitai.toml`
+    Repo,
+}
+
+impl ConfigLevel {
+    /// The level's file; `workdir` is only needed for the repository level
+    pub fn path(self, workdir: Option<&Path>) -> Option<PathBuf> {
+        match self {
+            ConfigLevel::System => system_config_path(),
+            ConfigLevel::Global => user_toml_config_path(),
+            ConfigLevel::Repo => workdir.map(|workdir| workdir.join(REPO_CONFIG_FILE)),
+        }
+    }
+}
+
+/// The files settings are read from, any of which may be missing
+#[derive(Debug, Clone, Copy, Default)]
+struct SettingsFiles<'a> {
+    system: Option<&'a Path>,
+    user_json: Option<&'a Path>,
+    user_toml: Option<&'a Path>,
+    repo: Option<&'a Path>,
+    /// `.gitai.toml` as committed, for bare repositories that have no file to read
+    repo_committed: Option<&'a str>,
+}
+
+/// Resolved settings plus the raw value and origin of every key set somewhere
+#[derive(Debug, Clone)]
+pub struct ResolvedSettings {
+    pub settings: Settings,
+    pub values: BTreeMap<String, (Value, ConfigOrigin)>,
+}
+
+static CLI_OVERRIDES: OnceLock<Vec<(String, String)>> = OnceLock::new();
+
+/// Split leading `-c <key>=<value>` pairs off a git-ai command line, returning them and the
+/// remaining arguments
+pub fn split_cli_overrides(args: &[String]) -> (Vec<(String, String)>, &[String]) {
+    let mut overrides = Vec::new();
+    let mut rest = args;
+    while let [flag, pair, tail @ ..] = rest {
+        if flag != "-c" {
+            break;
+        }
+        let Some((key, value)) = pair.split_once('=') else {
+            break;
+        };
+        overrides.push((key.trim().to_string(), value.to_string()));
+        rest = tail;
+    }
+    (overrides, rest)
+}
+
+/// Install command line overrides as the highest precedence settings layer. Only the first
+/// call has an effect.
+pub fn set_cli_overrides(overrides: Vec<(String, String)>) {
+    let _ = CLI_OVERRIDES.set(overrides);
+}
+
+impl Settings {
+    /// Resolve settings for the repository checked out at `workdir`. Without a working
+    /// directory only the user, environment and command line layers apply.
+    pub fn resolve(workdir: Option<&Path>) -> Result<ResolvedSettings, GitAiError> {
+        let cli_overrides = CLI_OVERRIDES.get().map(Vec::as_slice).unwrap_or_default();
+        let system = system_config_path();
+        let user_json = config_file_path();
+        let user_toml = user_toml_config_path();
+        let repo = ConfigLevel::Repo.path(workdir);
+        let mut resolved = resolve_layers(
+            SettingsFiles {
+                system: system.as_deref(),
+                user_json: user_json.as_deref(),
+                user_toml: user_toml.as_deref(),
+                repo: repo.as_deref(),
+                repo_committed: None,
+            },
+            |var| env::var(var).ok(),
+            cli_overrides,
+        )?;
+        if let Some(workdir) = workdir
+            && let Ok(content) = fs::read_to_string(workdir.join(IGNORE_FILE))
+        {
+            resolved.settings.ignore_file = IgnoreRules::parse(&content);
+        }
+        Ok(resolved)
+    }
+
+    /// Resolve settings for a bare repository from the `.gitai.toml` and `.gitaiignore`
+    /// committed at its HEAD
+    pub fn resolve_committed(
+        repo_config: Option<&str>,
+        ignore_file: Option<&str>,
+    ) -> Result<ResolvedSettings, GitAiError> {
+        let cli_overrides = CLI_OVERRIDES.get().map(Vec::as_slice).unwrap_or_default();
+        let system = system_config_path();
+        let user_json = config_file_path();
+        let user_toml = user_toml_config_path();
+        let mut resolved = resolve_layers(
+            SettingsFiles {
+                system: system.as_deref(),
+                user_json: user_json.as_deref(),
+                user_toml: user_toml.as_deref(),
+                repo_committed: repo_config,
+                ..Default::default()
+            },
+            |var| env::var(var).ok(),
+            cli_overrides,
+        )?;
+        if let Some(content) = ignore_file {
+            resolved.settings.ignore_file = IgnoreRules::parse(content);
+        }
+        Ok(resolved)
+    }
+
+    /// The notes ref name without its refs/notes/ prefix
+    pub fn notes_ref_name(&self) -> &str {
+        self.notes_ref
+            .strip_prefix("refs/notes/")
+            .unwrap_or(&self.notes_ref)
+    }
+
+    /// Whether `path` (relative to the repo root) is left out of attribution
+    pub fn is_excluded_path(&self, path: &str) -> bool {
+        self.exclude_paths
+            .iter()
+            .any(|pattern| glob_matches(pattern, path))
+            || self.ignore_file.is_ignored(path)
+            || matches!(
+                self.directory_policy(path),
+                Some((_, DirectoryPolicy::Ignore))
+            )
+    }
+
+    /// The most specific `directory_policies` subtree holding `path` (relative to the repo
+    /// root), without trailing slash, and its policy
+    pub fn directory_policy(&self, path: &str) -> Option<(&str, &DirectoryPolicy)> {
+        self.directory_policies
+            .iter()
+            .map(|(directory, policy)| (normalize_directory(directory), policy))
+            .filter(|(directory, _)| {
+                directory.is_empty()
+                    || path
+                        .strip_prefix(directory)
+                        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+            })
+            .max_by_key(|(directory, _)| directory.len())
+    }
+
+    /// The tool `path`'s subtree attributes every edit to, if any
+    pub fn directory_tool(&self, path: &str) -> Option<&str> {
+        match self.directory_policy(path) {
+            Some((_, DirectoryPolicy::Tool(tool))) => Some(tool),
+            _ => None,
+        }
+    }
+
+    /// Whether `path` (relative to the repo root) holds generated content, by the built-in list
+    /// or `generated_paths`. Files in `.gitaiignore` count as generated too, so stats leave them
+    /// out of the AI/human split.
+    pub fn is_generated_path(&self, path: &str) -> bool {
+        if self.ignore_file.is_ignored(path) {
+            return true;
+        }
+        let file_name = path.rsplit('/').next().unwrap_or(path);
+        BUILTIN_GENERATED_PATHS
+            .iter()
+            .copied()
+            .chain(self.generated_paths.iter().map(String::as_str))
+            .any(|pattern| {
+                if pattern.contains('/') {
+                    glob_matches(pattern, path)
+                } else {
+                    glob_matches(pattern, file_name)
+                }
+            })
+    }
+
+    /// Whether checkpoints and commits on the repository's current branch should be attributed.
+    /// Detached HEADs (rebases, bisects, ...) are always tracked since they aren't on a branch.
+    pub fn is_tracked_branch(&self, repository: &Repository) -> bool {
+        if self.track_branches.is_empty() && self.ignore_branches.is_empty() {
+            return true;
+        }
+        match repository.head() {
+            Ok(head) => match head
+                .name()
+                .and_then(|name| name.strip_prefix("refs/heads/"))
+            {
+                Some(branch) => self.branch_matches_tracking(branch),
+                None => true,
+            },
+            Err(_) => true,
+        }
+    }
+
+    /// Ignore patterns take precedence; an empty track list tracks every other branch
+    fn branch_matches_tracking(&self, branch: &str) -> bool {
+        if self
+            .ignore_branches
+            .iter()
+            .any(|pattern| glob_matches(pattern, branch))
+        {
+            return false;
+        }
+        self.track_branches.is_empty()
+            || self
+                .track_branches
+                .iter()
+                .any(|pattern| glob_matches(pattern, branch))
+    }
+}
+
+/// Stack the settings layers from lowest to highest precedence: system file, user config.json,
+/// user config.toml, repo file, environment, command line
+fn resolve_layers(
+    files: SettingsFiles,
+    env_var: impl Fn(&str) -> Option<String>,
+    cli_overrides: &[(String, String)],
+) -> Result<ResolvedSettings, GitAiError> {
+    let mut values: BTreeMap<String, (Value, ConfigOrigin)> = BTreeMap::new();
+    let mut set = |key: &str, value: Value, origin: ConfigOrigin| {
+        if SETTINGS_KEYS.contains(&key) {
+            values.insert(key.to_string(), (value, origin));
+        }
+    };
+
+    if let Some(path) = files.system {
+        for (key, value) in read_settings_file(path)? {
+            set(&key, value, ConfigOrigin::System(path.to_path_buf()));
+        }
+    }
+
+    // A broken user config.json is ignored here just like it is for the machine-level options
+    if let Some(path) = files.user_json
+        && let Ok(data) = fs::read(path)
+        && let Ok(user) = serde_json::from_slice::<Map<String, Value>>(&data)
+    {
+        for (key, value) in user {
+            set(&key, value, ConfigOrigin::User(path.to_path_buf()));
+        }
+    }
+
+    if let Some(path) = files.user_toml {
+        for (key, value) in read_settings_file(path)? {
+            set(&key, value, ConfigOrigin::User(path.to_path_buf()));
+        }
+    }
+
+    if let Some(path) = files.repo {
+        for (key, value) in read_settings_file(path)? {
+            set(&key, value, ConfigOrigin::Repo(path.to_path_buf()));
+        }
+    }
+
+    if let Some(content) = files.repo_committed {
+        let path = PathBuf::from(format!("HEAD:{}", REPO_CONFIG_FILE));
+        for (key, value) in parse_settings_toml(content, &path)? {
+            set(&key, value, ConfigOrigin::Repo(path.clone()));
+        }
+    }
+
+    for key in SETTINGS_KEYS {
+        let var = format!("GIT_AI_{}", key.to_uppercase());
+        if let Some(raw) = env_var(&var) {
+            set(key, parse_override(key, &raw), ConfigOrigin::Env(var));
+        }
+    }
+
+    for (key, raw) in cli_overrides {
+        set(key, parse_override(key, raw), ConfigOrigin::Cli);
+    }
+
+    let merged: Map<String, Value> = values
+        .iter()
+        .map(|(key, (value, _))| (key.clone(), value.clone()))
+        .collect();
+    let settings = serde_json::from_value(Value::Object(merged))
+        .map_err(|e| GitAiError::Generic(format!("Invalid git-ai setting: {}", e)))?;
+    Ok(ResolvedSettings { settings, values })
+}
+
+/// The top-level keys of a TOML settings file as JSON values; nothing when the file is missing
+pub fn read_settings_file(path: &Path) -> Result<Map<String, Value>, GitAiError> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Ok(Map::new());
+    };
+    parse_settings_toml(&content, path)
+}
+
+/// The top-level keys of TOML settings read from `path` as JSON values
+fn parse_settings_toml(content: &str, path: &Path) -> Result<Map<String, Value>, GitAiError> {
+    let table: toml::Table = toml::from_str(content)
+        .map_err(|e| GitAiError::Generic(format!("Invalid {}: {}", path.display(), e)))?;
+    table
+        .into_iter()
+        .map(|(key, value)| Ok((key, serde_json::to_value(value)?)))
+        .collect()
+}
+
+/// Parse `raw` like a `-c` override of `key` and check it's a valid value for it
+pub fn parse_setting_value(key: &str, raw: &str) -> Result<Value, GitAiError> {
+    if !SETTINGS_KEYS.contains(&key) {
+        return Err(GitAiError::Generic(format!("Unknown setting: {}", key)));
+    }
+    let value = parse_override(key, raw);
+    let mut single = Map::new();
+    single.insert(key.to_string(), value.clone());
+    serde_json::from_value::<Settings>(Value::Object(single))
+        .map_err(|e| GitAiError::Generic(format!("Invalid value for {}: {}", key, e)))?;
+    Ok(value)
+}
+
+/// Turn a string override into the JSON value its key expects
+fn parse_override(key: &str, raw: &str) -> Value {
+    if MAP_SETTINGS.contains(&key) {
+        return Value::Object(
+            raw.split(',')
+                .filter_map(|pair| pair.split_once('='))
+                .map(|(name, value)| {
+                    (
+                        name.trim().to_string(),
+                        Value::String(value.trim().to_string()),
+                    )
+                })
+                .filter(|(name, _)| !name.is_empty())
+                .collect(),
+        );
+    }
+    if LIST_SETTINGS.contains(&key) {
+        return Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| Value::String(item.to_string()))
+                .collect(),
+        );
+    }
+    if NUMBER_SETTINGS.contains(&key)
+        && let Ok(number) = raw.trim().parse::<u64>()
+    {
+        return Value::from(number);
+    }
+    match raw.trim() {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        other => Value::String(other.to_string()),
+    }
+}
+
+/// Set `key` in a TOML settings file (`.gitai.toml` or a config.toml), editing the parsed
+/// document so the rest of the file (comments included) is kept. The file and its directory
+/// are created if missing.
+pub fn write_toml_setting(path: &Path, key: &str, value: toml::Value) -> Result<(), GitAiError> {
+    let mut document = read_toml_document(path)?;
+    // toml::Value displays arrays and tables inline, which toml_edit reads back as one value
+    let value: toml_edit::Value = value
+        .to_string()
+        .parse()
+        .map_err(|e| GitAiError::Generic(format!("Invalid value for {}: {}", key, e)))?;
+    match document.get_mut(key) {
+        // Replacing just the value keeps the comments around the key
+        Some(toml_edit::Item::Value(existing)) => {
+            let decor = existing.decor().clone();
+            *existing = value;
+            *existing.decor_mut() = decor;
+        }
+        _ => {
+            document.insert(key, toml_edit::Item::Value(value));
+        }
+    }
+    if let Some(parent) = path.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, document.to_string())?;
+    Ok(())
+}
+
+/// Remove `key` from a TOML settings file. False when it wasn't set there.
+pub fn remove_toml_setting(path: &Path, key: &str) -> Result<bool, GitAiError> {
+    let mut document = read_toml_document(path)?;
+    if document.remove(key).is_none() {
+        return Ok(false);
+    }
+    fs::write(path, document.to_string())?;
+    Ok(true)
+}
+
+fn read_toml_document(path: &Path) -> Result<toml_edit::DocumentMut, GitAiError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    content
+        .parse()
+        .map_err(|e| GitAiError::Generic(format!("Failed to parse {}: {}", path.display(), e)))
+}
+
 static CONFIG: OnceLock<Config> = OnceLock::new();
 
 impl Config {
@@ -47,8 +943,14 @@ impl Config {
         &self.git_path
     }
 
-    pub fn get_ignore_prompts(&self) -> bool {
-        self.ignore_prompts
+    /// Returns the command to invoke jj, for repos colocated with Jujutsu.
+    pub fn jj_cmd(&self) -> &str {
+        &self.jj_path
+    }
+
+    /// Whether full transcripts are kept under .git/ai/transcripts when notes only get a summary
+    pub fn keep_local_transcripts(&self) -> bool {
+        self.keep_local_transcripts
     }
 
     pub fn is_allowed_repository(&self, repository: &Option<Repository>) -> bool {
@@ -84,19 +986,13 @@ impl Config {
             false // No repository provided, deny by default when allowlist is active
         }
     }
-
-    /// Returns whether prompts should be ignored (currently unused by internal APIs).
-    #[allow(dead_code)]
-    pub fn ignore_prompts(&self) -> bool {
-        self.ignore_prompts
-    }
 }
 
 fn build_config() -> Config {
     let file_cfg = load_file_config();
-    let ignore_prompts = file_cfg
+    let keep_local_transcripts = file_cfg
         .as_ref()
-        .and_then(|c| c.ignore_prompts)
+        .and_then(|c| c.keep_local_transcripts)
         .unwrap_or(false);
     let allow_repositories = file_cfg
         .as_ref()
@@ -112,15 +1008,73 @@ fn build_config() -> Config {
         .collect();
 
     let git_path = resolve_git_path(&file_cfg);
+    let jj_path = file_cfg
+        .as_ref()
+        .and_then(|c| c.jj_path.clone())
+        .filter(|path| !path.trim().is_empty())
+        .unwrap_or_else(|| "jj".to_string());
 
     Config {
         git_path,
-        ignore_prompts,
+        jj_path,
+        keep_local_transcripts,
         allow_repositories,
         exclude_repositories,
     }
 }
 
+/// A `directory_policies` subtree as a repo-relative prefix: `./gen/` is `gen`, `.` the root
+pub fn normalize_directory(directory: &str) -> &str {
+    let directory = directory.trim_end_matches('/');
+    let directory = directory.strip_prefix("./").unwrap_or(directory);
+    if directory == "." { "" } else { directory }
+}
+
+/// Match a branch name against a pattern where `*` matches any run of characters (including
+/// `/`) and `?` matches exactly one, e.g. `release/*` or `mirror-?`
+fn glob_matches(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Position of the last `*` and the text position it is currently matched up to
+    let mut star: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, t));
+            p += 1;
+        } else if let Some((star_p, star_t)) = star {
+            // Let the last `*` swallow one more character and retry
+            p = star_p + 1;
+            t = star_t + 1;
+            star = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Match a whole path the way `.gitignore` does: `*` and `?` stay within one name, and a `**`
+/// name matches any number of directories, including none
+fn path_glob_matches(pattern: &str, path: &str) -> bool {
+    fn names_match(pattern: &[&str], path: &[&str]) -> bool {
+        match pattern.split_first() {
+            None => path.is_empty(),
+            Some((&"**", rest)) => (0..=path.len()).any(|skip| names_match(rest, &path[skip..])),
+            Some((first, rest)) => path
+                .split_first()
+                .is_some_and(|(name, path)| glob_matches(first, name) && names_match(rest, path)),
+        }
+    }
+    let pattern: Vec<&str> = pattern.split('/').collect();
+    let path: Vec<&str> = path.split('/').collect();
+    names_match(&pattern, &path)
+}
+
 fn resolve_git_path(file_cfg: &Option<FileConfig>) -> String {
     // 1) From config file
     if let Some(cfg) = file_cfg {
@@ -172,16 +1126,58 @@ fn load_file_config() -> Option<FileConfig> {
     serde_json::from_slice::<FileConfig>(&data).ok()
 }
 
-fn config_file_path() -> Option<PathBuf> {
+/// The user's config.json
+pub fn config_file_path() -> Option<PathBuf> {
+    Some(git_ai_dir()?.join("config.json"))
+}
+
+/// The user's config.toml: `$XDG_CONFIG_HOME/git-ai/config.toml`, by default under
+/// `~/.config` (`%APPDATA%` on Windows)
+pub fn user_toml_config_path() -> Option<PathBuf> {
+    #[cfg(windows)]
+    {
+        let appdata = env::var("APPDATA").ok()?;
+        Some(Path::new(&appdata).join("git-ai").join("config.toml"))
+    }
+    #[cfg(not(windows))]
+    {
+        let config_home = env::var("XDG_CONFIG_HOME")
+            .ok()
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| Some(Path::new(&env::var("HOME").ok()?).join(".config")))?;
+        Some(config_home.join("git-ai").join("config.toml"))
+    }
+}
+
+/// The machine-wide config.toml an organization can deploy: `/etc/git-ai/config.toml`
+/// (`%ProgramData%\git-ai\config.toml` on Windows), or `GIT_AI_SYSTEM_CONFIG` when set
+pub fn system_config_path() -> Option<PathBuf> {
+    if let Ok(path) = env::var(SYSTEM_CONFIG_ENV) {
+        return (!path.is_empty()).then(|| PathBuf::from(path));
+    }
+    #[cfg(windows)]
+    {
+        let program_data = env::var("ProgramData").ok()?;
+        Some(Path::new(&program_data).join("git-ai").join("config.toml"))
+    }
+    #[cfg(not(windows))]
+    {
+        Some(PathBuf::from("/etc/git-ai/config.toml"))
+    }
+}
+
+/// The user-level git-ai directory (~/.git-ai)
+pub fn git_ai_dir() -> Option<PathBuf> {
     #[cfg(windows)]
     {
         let home = env::var("USERPROFILE").ok()?;
-        Some(Path::new(&home).join(".git-ai").join("config.json"))
+        Some(Path::new(&home).join(".git-ai"))
     }
     #[cfg(not(windows))]
     {
         let home = env::var("HOME").ok()?;
-        Some(Path::new(&home).join(".git-ai").join("config.json"))
+        Some(Path::new(&home).join(".git-ai"))
     }
 }
 
@@ -204,7 +1200,8 @@ mod tests {
     ) -> Config {
         Config {
             git_path: "/usr/bin/git".to_string(),
-            ignore_prompts: false,
+            jj_path: "jj".to_string(),
+            keep_local_transcripts: false,
             allow_repositories: allow_repositories.into_iter().collect(),
             exclude_repositories: exclude_repositories.into_iter().collect(),
         }
@@ -246,4 +1243,399 @@ mod tests {
         // With allowlist but no exclusions, should deny when no repository provided
         assert!(!config.is_allowed_repository(&None));
     }
+
+    #[test]
+    fn test_glob_matches() {
+        assert!(glob_matches("main", "main"));
+        assert!(!glob_matches("main", "main2"));
+        assert!(glob_matches("release/*", "release/1.2"));
+        assert!(glob_matches("release/*", "release/1.2/hotfix"));
+        assert!(!glob_matches("release/*", "releases/1.2"));
+        assert!(glob_matches("*-mirror", "vendor/openssl-mirror"));
+        assert!(glob_matches("mirror-?", "mirror-a"));
+        assert!(!glob_matches("mirror-?", "mirror-ab"));
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("a*b*c", "aXbYbZc"));
+    }
+
+    #[test]
+    fn test_branch_tracking_ignore_takes_precedence() {
+        let mut settings = Settings::default();
+        assert!(settings.branch_matches_tracking("anything"));
+
+        settings.ignore_branches = vec!["release/*".to_string()];
+        assert!(!settings.branch_matches_tracking("release/1.0"));
+        assert!(settings.branch_matches_tracking("main"));
+
+        settings.track_branches = vec!["main".to_string(), "release/*".to_string()];
+        assert!(settings.branch_matches_tracking("main"));
+        assert!(!settings.branch_matches_tracking("release/1.0"));
+        assert!(!settings.branch_matches_tracking("feature/x"));
+    }
+
+    #[test]
+    fn test_generated_paths() {
+        let mut settings = Settings::default();
+        assert!(settings.is_generated_path("Cargo.lock"));
+        assert!(settings.is_generated_path("web/package-lock.json"));
+        assert!(settings.is_generated_path("api/v1/service.pb.go"));
+        assert!(settings.is_generated_path("vendor/github.com/pkg/errors/errors.go"));
+        assert!(settings.is_generated_path("tools/vendor/lib.rb"));
+        assert!(!settings.is_generated_path("src/lockfile.rs"));
+        assert!(!settings.is_generated_path("docs/Cargo.lock.md"));
+        assert!(!settings.is_generated_path("src/schema.rs"));
+
+        settings.generated_paths = vec!["schema.rs".to_string(), "dist/*".to_string()];
+        assert!(settings.is_generated_path("src/schema.rs"));
+        assert!(settings.is_generated_path("dist/app.js"));
+        assert!(!settings.is_generated_path("web/dist/app.js"));
+    }
+
+    #[test]
+    fn test_directory_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_file = dir.path().join(REPO_CONFIG_FILE);
+        fs::write(
+            &repo_file,
+            "[directory_policies]\n\"gen/\" = \"ignore\"\n\"gen/handwritten\" = \"track\"\n\"./proto\" = \"tool:protoc\"\n",
+        )
+        .unwrap();
+        let settings = resolve_layers(repo_only(&repo_file), |_| None, &[])
+            .unwrap()
+            .settings;
+
+        assert_eq!(
+            settings.directory_policy("gen/api/client.ts"),
+            Some(("gen", &DirectoryPolicy::Ignore))
+        );
+        assert!(settings.is_excluded_path("gen/api/client.ts"));
+        // The most specific subtree wins
+        assert_eq!(
+            settings.directory_policy("gen/handwritten/util.ts"),
+            Some(("gen/handwritten", &DirectoryPolicy::Track))
+        );
+        assert!(!settings.is_excluded_path("gen/handwritten/util.ts"));
+        assert_eq!(settings.directory_tool("proto/user.pb.ts"), Some("protoc"));
+        assert_eq!(settings.directory_policy("generated/a.ts"), None);
+        assert_eq!(settings.directory_tool("src/main.rs"), None);
+
+        let cli = vec![(
+            "directory_policies".to_string(),
+            "gen=tool:openapi-generator, docs = ignore".to_string(),
+        )];
+        let settings = resolve_layers(SettingsFiles::default(), |_| None, &cli)
+            .unwrap()
+            .settings;
+        assert_eq!(
+            settings.directory_tool("gen/client.ts"),
+            Some("openapi-generator")
+        );
+        assert!(settings.is_excluded_path("docs/index.md"));
+
+        let cli = vec![("directory_policies".to_string(), "gen=skip".to_string())];
+        let err = resolve_layers(SettingsFiles::default(), |_| None, &cli).unwrap_err();
+        assert!(
+            err.to_string().contains("invalid directory policy"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn test_push_policies() {
+        let cli = vec![(
+            "push_policies".to_string(),
+            "mostly-human=max_ai_percent:80, security=no_ai:*security/*".to_string(),
+        )];
+        let settings = resolve_layers(SettingsFiles::default(), |_| None, &cli)
+            .unwrap()
+            .settings;
+        assert_eq!(
+            settings.push_policies["mostly-human"],
+            PushPolicy::MaxAiPercent(80)
+        );
+        let security = &settings.push_policies["security"];
+        assert!(security.forbids_ai_in("src/security/auth.rs"));
+        assert!(!security.forbids_ai_in("src/main.rs"));
+
+        for invalid in ["x=max_ai_percent:120", "x=no_ai:", "x=review"] {
+            let cli = vec![("push_policies".to_string(), invalid.to_string())];
+            let err = resolve_layers(SettingsFiles::default(), |_| None, &cli).unwrap_err();
+            assert!(err.to_string().contains("invalid push policy"), "{}", err);
+        }
+    }
+
+    #[test]
+    fn test_committed_repo_settings_for_bare_repositories() {
+        let files = SettingsFiles {
+            repo_committed: Some("notes_ref = \"team\"\nexclude_paths = [\"vendor/**\"]\n"),
+            ..Default::default()
+        };
+        let resolved = resolve_layers(files, |_| None, &[]).unwrap();
+        assert_eq!(resolved.settings.notes_ref_name(), "team");
+        assert!(resolved.settings.is_excluded_path("vendor/lib.rs"));
+        assert_eq!(
+            resolved.values["notes_ref"].1,
+            ConfigOrigin::Repo(PathBuf::from("HEAD:.gitai.toml"))
+        );
+
+        let files = SettingsFiles {
+            repo_committed: Some("notes_ref = "),
+            ..Default::default()
+        };
+        assert!(resolve_layers(files, |_| None, &[]).is_err());
+    }
+
+    fn repo_only(repo_file: &Path) -> SettingsFiles<'_> {
+        SettingsFiles {
+            repo: Some(repo_file),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_write_toml_setting_keeps_the_rest_of_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(REPO_CONFIG_FILE);
+
+        write_toml_setting(&path, "notes_ref", toml::Value::from("team")).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "notes_ref = \"team\"\n");
+
+        fs::write(
+            &path,
+            "# Team policy\nnotes_ref = \"ai\"\nconfirm_clean = true\n\n[extra]\nnotes_ref = 1\n",
+        )
+        .unwrap();
+        write_toml_setting(&path, "notes_ref", toml::Value::from("team")).unwrap();
+        write_toml_setting(&path, "exclude_paths", toml::Value::from(vec!["dist/*"])).unwrap();
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "# Team policy\nnotes_ref = \"team\"\nconfirm_clean = true\nexclude_paths = [\"dist/*\"]\n\n[extra]\nnotes_ref = 1\n"
+        );
+    }
+
+    #[test]
+    fn test_toml_settings_with_multi_line_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(REPO_CONFIG_FILE);
+        let original = "exclude_paths = [\n  \"vendor/**\",\n]\ntrack_branches = { main = true }\nnotes_ref = \"ai\" # shared\n\n[directory_policies]\n\"gen/\" = \"ignore\"\n";
+        fs::write(&path, original).unwrap();
+
+        write_toml_setting(&path, "exclude_paths", toml::Value::from(vec!["a/**"])).unwrap();
+        write_toml_setting(&path, "track_branches", toml::Value::from(vec!["main"])).unwrap();
+        let mut policies = toml::Table::new();
+        policies.insert("docs".to_string(), toml::Value::from("ignore"));
+        write_toml_setting(&path, "directory_policies", toml::Value::Table(policies)).unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert_eq!(
+            content,
+            "exclude_paths = [\"a/**\"]\ntrack_branches = [\"main\"]\nnotes_ref = \"ai\" # shared\ndirectory_policies = { docs = \"ignore\" }\n"
+        );
+        assert!(content.parse::<toml::Table>().is_ok());
+
+        fs::write(&path, original).unwrap();
+        assert!(remove_toml_setting(&path, "exclude_paths").unwrap());
+        assert!(remove_toml_setting(&path, "track_branches").unwrap());
+        assert!(remove_toml_setting(&path, "directory_policies").unwrap());
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "notes_ref = \"ai\" # shared\n"
+        );
+    }
+
+    #[test]
+    fn test_settings_layer_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let user_file = dir.path().join("config.json");
+        fs::write(
+            &user_file,
+            r#"{"git_path": "/usr/bin/git", "ignore_prompts": true, "notes_ref": "user-notes", "track_branches": ["main"]}"#,
+        )
+        .unwrap();
+        let repo_file = dir.path().join(REPO_CONFIG_FILE);
+        fs::write(
+            &repo_file,
+            "notes_ref = \"refs/notes/team\"\nexclude_paths = [\"vendor/*\"]\ntranscript_mode = \"summary\"\n",
+        )
+        .unwrap();
+
+        let env_var = |var: &str| match var {
+            "GIT_AI_TRANSCRIPT_MODE" => Some("full".to_string()),
+            "GIT_AI_EXCLUDE_PATHS" => Some("vendor/*, *.lock".to_string()),
+            _ => None,
+        };
+        let cli = vec![("exclude_paths".to_string(), "dist/*".to_string())];
+
+        let files = SettingsFiles {
+            user_json: Some(&user_file),
+            repo: Some(&repo_file),
+            ..Default::default()
+        };
+        let resolved = resolve_layers(files, env_var, &cli).unwrap();
+        let settings = &resolved.settings;
+        assert!(settings.ignore_prompts);
+        assert_eq!(settings.track_branches, vec!["main"]);
+        assert_eq!(settings.notes_ref_name(), "team");
+        assert_eq!(settings.transcript_mode, TranscriptMode::Full);
+        assert_eq!(settings.exclude_paths, vec!["dist/*"]);
+        assert!(settings.is_excluded_path("dist/app.js"));
+        assert!(!settings.is_excluded_path("vendor/lib.rs"));
+
+        let origin = |key: &str| resolved.values[key].1.clone();
+        assert_eq!(
+            origin("ignore_prompts"),
+            ConfigOrigin::User(user_file.clone())
+        );
+        assert_eq!(origin("notes_ref"), ConfigOrigin::Repo(repo_file.clone()));
+        assert_eq!(
+            origin("transcript_mode"),
+            ConfigOrigin::Env("GIT_AI_TRANSCRIPT_MODE".to_string())
+        );
+        assert_eq!(origin("exclude_paths"), ConfigOrigin::Cli);
+        // Machine-level options never show up as settings
+        assert!(!resolved.values.contains_key("git_path"));
+    }
+
+    #[test]
+    fn test_system_and_user_toml_layers() {
+        let dir = tempfile::tempdir().unwrap();
+        let system_file = dir.path().join("system.toml");
+        fs::write(
+            &system_file,
+            "notes_ref = \"org\"\nconfirm_clean = true\nhook_budget_ms = 100\n",
+        )
+        .unwrap();
+        let user_json = dir.path().join("config.json");
+        fs::write(
+            &user_json,
+            r#"{"hook_budget_ms": 200, "confirm_clean": false}"#,
+        )
+        .unwrap();
+        let user_toml = dir.path().join("config.toml");
+        fs::write(&user_toml, "hook_budget_ms = 300\n").unwrap();
+        let repo_file = dir.path().join(REPO_CONFIG_FILE);
+        fs::write(&repo_file, "notes_ref = \"team\"\n").unwrap();
+
+        let files = SettingsFiles {
+            system: Some(&system_file),
+            user_json: Some(&user_json),
+            user_toml: Some(&user_toml),
+            repo: Some(&repo_file),
+            repo_committed: None,
+        };
+        let resolved = resolve_layers(files, |_| None, &[]).unwrap();
+        assert_eq!(resolved.settings.notes_ref_name(), "team");
+        assert!(!resolved.settings.confirm_clean);
+        assert_eq!(resolved.settings.hook_budget_ms, 300);
+        let origin = |key: &str| resolved.values[key].1.clone();
+        assert_eq!(
+            origin("hook_budget_ms"),
+            ConfigOrigin::User(user_toml.clone())
+        );
+        assert_eq!(
+            origin("confirm_clean"),
+            ConfigOrigin::User(user_json.clone())
+        );
+
+        // Without the user and repo layers the system file decides
+        let files = SettingsFiles {
+            system: Some(&system_file),
+            ..Default::default()
+        };
+        let resolved = resolve_layers(files, |_| None, &[]).unwrap();
+        assert_eq!(resolved.settings.notes_ref_name(), "org");
+        assert!(resolved.settings.confirm_clean);
+        assert_eq!(
+            resolved.values["notes_ref"].1,
+            ConfigOrigin::System(system_file.clone())
+        );
+    }
+
+    #[test]
+    fn test_ignore_rules() {
+        let rules = IgnoreRules::parse(
+            "# Generated\n*.lock\nbuild/\n/dist\ndocs/**/*.html\n**/snapshots/*.snap\n!keep.lock\nnotes/*.md\n\n",
+        );
+        assert!(rules.is_ignored("Cargo.lock"));
+        assert!(rules.is_ignored("web/yarn.lock"));
+        assert!(!rules.is_ignored("web/keep.lock"));
+        // Directory patterns match what's inside, at any depth unless anchored
+        assert!(rules.is_ignored("build/out.js"));
+        assert!(rules.is_ignored("web/build/out.js"));
+        assert!(!rules.is_ignored("build"));
+        assert!(rules.is_ignored("dist/app.js"));
+        assert!(!rules.is_ignored("web/dist/app.js"));
+        assert!(rules.is_ignored("docs/api/index.html"));
+        assert!(rules.is_ignored("snapshots/a.snap"));
+        assert!(rules.is_ignored("tests/snapshots/a.snap"));
+        assert!(!rules.is_ignored("src/main.rs"));
+        // A single `*` stays within one directory; `**` crosses them
+        assert!(rules.is_ignored("notes/todo.md"));
+        assert!(!rules.is_ignored("notes/2024/todo.md"));
+        assert!(!rules.is_ignored("tests/snapshots/nested/a.snap"));
+        assert!(rules.is_ignored("docs/index.html"));
+
+        let settings = Settings {
+            ignore_file: rules,
+            ..Default::default()
+        };
+        assert!(settings.is_excluded_path("build/out.js"));
+        assert!(settings.is_generated_path("docs/api/index.html"));
+        assert!(!settings.is_excluded_path("src/main.rs"));
+    }
+
+    #[test]
+    fn test_remove_toml_setting() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("git-ai").join("config.toml");
+        write_toml_setting(&path, "notes_ref", toml::Value::from("team")).unwrap();
+        write_toml_setting(&path, "confirm_clean", toml::Value::from(true)).unwrap();
+        assert!(remove_toml_setting(&path, "notes_ref").unwrap());
+        assert!(!remove_toml_setting(&path, "notes_ref").unwrap());
+        assert_eq!(fs::read_to_string(&path).unwrap(), "confirm_clean = true\n");
+    }
+
+    #[test]
+    fn test_parse_setting_value() {
+        assert_eq!(
+            parse_setting_value("exclude_paths", "dist/*, *.lock").unwrap(),
+            serde_json::json!(["dist/*", "*.lock"])
+        );
+        assert!(parse_setting_value("transcript_mode", "verbose").is_err());
+        assert!(parse_setting_value("git_path", "/usr/bin/git").is_err());
+    }
+
+    #[test]
+    fn test_invalid_repo_settings_are_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo_file = dir.path().join(REPO_CONFIG_FILE);
+        fs::write(&repo_file, "ignore_prompts = \"sometimes\"\n").unwrap();
+        assert!(resolve_layers(repo_only(&repo_file), |_| None, &[]).is_err());
+
+        fs::write(&repo_file, "not toml at all [").unwrap();
+        assert!(resolve_layers(repo_only(&repo_file), |_| None, &[]).is_err());
+    }
+
+    #[test]
+    fn test_split_cli_overrides() {
+        let args: Vec<String> = ["-c", "notes_ref=team", "-c", "ignore_prompts=true", "stats"]
+            .iter()
+            .map(|arg| arg.to_string())
+            .collect();
+        let (overrides, rest) = split_cli_overrides(&args);
+        assert_eq!(
+            overrides,
+            vec![
+                ("notes_ref".to_string(), "team".to_string()),
+                ("ignore_prompts".to_string(), "true".to_string()),
+            ]
+        );
+        assert_eq!(rest, &args[4..]);
+        assert_eq!(parse_override("ignore_prompts", "true"), Value::Bool(true));
+        assert_eq!(
+            parse_override("track_branches", "main, release/*"),
+            serde_json::json!(["main", "release/*"])
+        );
+        assert_eq!(parse_override("hook_budget_ms", "500"), Value::from(500));
+    }
 }
@@ -16,6 +16,8 @@ pub enum GitAiError {
     FromUtf8Error(std::string::FromUtf8Error),
     PresetError(String),
     Generic(String),
+    /// A multi-step operation stopped because git-ai received this signal
+    Cancelled(i32),
 }
 
 impl fmt::Display for GitAiError {
@@ -39,6 +41,7 @@ impl fmt::Display for GitAiError {
             GitAiError::FromUtf8Error(e) => write!(f, "From UTF-8 error: {}", e),
             GitAiError::PresetError(e) => write!(f, "{}", e),
             GitAiError::Generic(e) => write!(f, "Generic error: {}", e),
+            GitAiError::Cancelled(sig) => write!(f, "Interrupted by signal {}", sig),
         }
     }
 }
@@ -94,6 +97,7 @@ impl Clone for GitAiError {
             GitAiError::FromUtf8Error(e) => GitAiError::FromUtf8Error(e.clone()),
             GitAiError::PresetError(s) => GitAiError::PresetError(s.clone()),
             GitAiError::Generic(s) => GitAiError::Generic(s.clone()),
+            GitAiError::Cancelled(sig) => GitAiError::Cancelled(*sig),
         }
     }
 }
@@ -0,0 +1,212 @@
+//! Fault injection for hardening hooks against a flaky git and a failing disk.
+//!
+//! Built with the `fault-injection` feature, `GIT_AI_FAULTS` makes a share of git-ai's own git
+//! calls and storage writes fail, e.g. `GIT_AI_FAULTS=git=0.2,write=0.1,enospc=0.05`:
+//!
+//! - `git` fails a call made through [`exec_git`](crate::git::repository::exec_git) and its
+//!   siblings before git runs, as if git had exited with status 128
+//! - `write` writes only part of the contents, then fails the way an interrupted write would
+//! - `enospc` fails with "No space left on device", possibly after writing part of the contents
+//!
+//! Set `GIT_AI_FAULT_SEED` to replay the same failures. The git command being proxied is never
+//! failed, only the work git-ai does around it. Without the feature the hooks below are the
+//! plain operations.
+
+#[cfg(not(feature = "fault-injection"))]
+pub use passthrough::{append, check_git, write};
+
+#[cfg(not(feature = "fault-injection"))]
+mod passthrough {
+    use crate::error::GitAiError;
+    use std::io::{self, Write};
+    use std::path::Path;
+
+    /// Fail a git call before it runs
+    #[inline]
+    pub fn check_git(_args: &[String]) -> Result<(), GitAiError> {
+        Ok(())
+    }
+
+    /// `fs::write` for storage files
+    #[inline]
+    pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        std::fs::write(path, contents)
+    }
+
+    /// Append `contents` to `path`, creating it if needed, in a single write
+    pub fn append(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(contents.as_ref())
+    }
+}
+
+#[cfg(feature = "fault-injection")]
+pub use injection::{append, check_git, write};
+
+#[cfg(feature = "fault-injection")]
+mod injection {
+    use crate::error::GitAiError;
+    use std::io::{self, Write};
+    use std::path::Path;
+    use std::sync::{Mutex, OnceLock};
+
+    /// Which faults to inject and how often, e.g. `git=0.2,write=0.1,enospc=0.05`
+    pub const FAULTS_ENV: &str = "GIT_AI_FAULTS";
+    /// Seed for the fault sequence; a fresh one each run when unset
+    pub const FAULT_SEED_ENV: &str = "GIT_AI_FAULT_SEED";
+
+    #[derive(Debug, Clone, Copy, Default, PartialEq)]
+    pub struct FaultRates {
+        pub git: f64,
+        pub write: f64,
+        pub enospc: f64,
+    }
+
+    impl FaultRates {
+        /// `name=rate` pairs separated by commas. Unknown names and unparseable rates are
+        /// ignored and rates are clamped to 0..=1, so a typo never fails the command itself.
+        pub fn parse(spec: &str) -> FaultRates {
+            let mut rates = FaultRates::default();
+            for pair in spec.split(',') {
+                let Some((name, rate)) = pair.split_once('=') else {
+                    continue;
+                };
+                let Ok(rate) = rate.trim().parse::<f64>() else {
+                    continue;
+                };
+                let rate = rate.clamp(0.0, 1.0);
+                match name.trim() {
+                    "git" => rates.git = rate,
+                    "write" => rates.write = rate,
+                    "enospc" => rates.enospc = rate,
+                    _ => {}
+                }
+            }
+            rates
+        }
+    }
+
+    struct Injector {
+        rates: FaultRates,
+        state: Mutex<u64>,
+    }
+
+    impl Injector {
+        /// splitmix64; good enough to spread faults and tiny enough to need no dependency
+        fn next(&self) -> u64 {
+            let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+            *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = *state;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        }
+
+        fn roll(&self, rate: f64) -> bool {
+            rate > 0.0 && ((self.next() >> 11) as f64 / (1u64 << 53) as f64) < rate
+        }
+    }
+
+    fn injector() -> Option<&'static Injector> {
+        static INJECTOR: OnceLock<Option<Injector>> = OnceLock::new();
+        INJECTOR
+            .get_or_init(|| {
+                let rates = FaultRates::parse(&std::env::var(FAULTS_ENV).ok()?);
+                let seed = std::env::var(FAULT_SEED_ENV)
+                    .ok()
+                    .and_then(|seed| seed.trim().parse().ok())
+                    .unwrap_or_else(|| {
+                        let nanos = std::time::SystemTime::now()
+                            .duration_since(std::time::UNIX_EPOCH)
+                            .map(|d| d.as_nanos() as u64)
+                            .unwrap_or(0);
+                        nanos ^ ((std::process::id() as u64) << 32)
+                    });
+                Some(Injector {
+                    rates,
+                    state: Mutex::new(seed),
+                })
+            })
+            .as_ref()
+    }
+
+    pub fn check_git(args: &[String]) -> Result<(), GitAiError> {
+        match injector() {
+            Some(injector) if injector.roll(injector.rates.git) => Err(GitAiError::GitCliError {
+                code: Some(128),
+                stderr: "fatal: injected fault".to_string(),
+                args: args.to_vec(),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        let file = std::fs::File::create(path)?;
+        write_with_faults(file, contents.as_ref())
+    }
+
+    pub fn append(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+        let file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        write_with_faults(file, contents.as_ref())
+    }
+
+    fn write_with_faults(mut file: std::fs::File, contents: &[u8]) -> io::Result<()> {
+        // Creating an empty file is left alone: there is nothing to tear
+        let Some(injector) = injector().filter(|_| !contents.is_empty()) else {
+            return file.write_all(contents);
+        };
+        let error = if injector.roll(injector.rates.enospc) {
+            io::Error::from_raw_os_error(libc::ENOSPC)
+        } else if injector.roll(injector.rates.write) {
+            io::Error::new(io::ErrorKind::Interrupted, "injected partial write")
+        } else {
+            return file.write_all(contents);
+        };
+        let written = (injector.next() % contents.len() as u64) as usize;
+        file.write_all(&contents[..written])?;
+        Err(error)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_parse_fault_rates() {
+            assert_eq!(
+                FaultRates::parse("git=0.2, write=1,enospc=0.05"),
+                FaultRates {
+                    git: 0.2,
+                    write: 1.0,
+                    enospc: 0.05,
+                }
+            );
+            assert_eq!(
+                FaultRates::parse("git=2,write=lots,disk=0.5,nonsense"),
+                FaultRates {
+                    git: 1.0,
+                    ..Default::default()
+                }
+            );
+        }
+
+        #[test]
+        fn test_rolls_follow_the_rate() {
+            let injector = Injector {
+                rates: FaultRates::default(),
+                state: Mutex::new(7),
+            };
+            assert!(!(0..1000).any(|_| injector.roll(0.0)));
+            assert!((0..1000).all(|_| injector.roll(1.0)));
+            let hits = (0..10_000).filter(|_| injector.roll(0.25)).count();
+            assert!((2000..3000).contains(&hits), "{} hits", hits);
+        }
+    }
+}
@@ -0,0 +1,345 @@
+//! Change notifications for the files of a worktree, for `git-ai watch`.
+//!
+//! On Linux every directory of the worktree is watched with inotify, directories created later
+//! included. Elsewhere, or when inotify can't be set up (e.g. the watch limit is reached), the
+//! worktree is walked each time changes are waited for and compared with the previous walk.
+//! `.git` is never watched, and neither is any directory the caller says to skip.
+
+use crate::error::GitAiError;
+use crate::utils::debug_log;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// What changed while waiting, relative to the worktree
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Changes {
+    /// Files written, created, deleted or renamed
+    pub paths: BTreeSet<PathBuf>,
+    /// Changes were lost or can't be told file by file (a directory was removed, the event
+    /// queue overflowed); anything in the worktree may have changed
+    pub everything: bool,
+}
+
+impl Changes {
+    pub fn is_empty(&self) -> bool {
+        self.paths.is_empty() && !self.everything
+    }
+}
+
+type SkipDir = Box<dyn Fn(&Path) -> bool>;
+
+pub struct Notifier {
+    root: PathBuf,
+    /// Given a directory relative to the root, whether to leave it unwatched
+    skip: SkipDir,
+    backend: Backend,
+}
+
+enum Backend {
+    #[cfg(target_os = "linux")]
+    Inotify(inotify::Watches),
+    Poll(HashMap<PathBuf, (Option<SystemTime>, u64)>),
+}
+
+impl Notifier {
+    pub fn new(
+        root: &Path,
+        skip: impl Fn(&Path) -> bool + 'static,
+    ) -> Result<Notifier, GitAiError> {
+        let skip: SkipDir = Box::new(skip);
+        #[cfg(target_os = "linux")]
+        match inotify::Watches::new(root, &skip) {
+            Ok(watches) => {
+                return Ok(Notifier {
+                    root: root.to_path_buf(),
+                    skip,
+                    backend: Backend::Inotify(watches),
+                });
+            }
+            Err(e) => debug_log(&format!("inotify unavailable, polling instead: {}", e)),
+        }
+        let snapshot = snapshot(root, &skip)?;
+        Ok(Notifier {
+            root: root.to_path_buf(),
+            skip,
+            backend: Backend::Poll(snapshot),
+        })
+    }
+
+    /// Whether changes are delivered as they happen rather than found by walking the worktree
+    pub fn is_native(&self) -> bool {
+        !matches!(self.backend, Backend::Poll(_))
+    }
+
+    /// Wait up to `timeout` for changes, returning as soon as there are some
+    pub fn wait(&mut self, timeout: Duration) -> Result<Changes, GitAiError> {
+        match &mut self.backend {
+            #[cfg(target_os = "linux")]
+            Backend::Inotify(watches) => watches.wait(&self.root, &self.skip, timeout),
+            Backend::Poll(previous) => {
+                std::thread::sleep(timeout);
+                let current = snapshot(&self.root, &self.skip)?;
+                let mut changes = Changes::default();
+                for (path, stamp) in &current {
+                    if previous.get(path) != Some(stamp) {
+                        changes.paths.insert(path.clone());
+                    }
+                }
+                for path in previous.keys() {
+                    if !current.contains_key(path) {
+                        changes.paths.insert(path.clone());
+                    }
+                }
+                *previous = current;
+                Ok(changes)
+            }
+        }
+    }
+}
+
+/// Modification time and size of every file under `root`, by path relative to it
+fn snapshot(
+    root: &Path,
+    skip: &SkipDir,
+) -> Result<HashMap<PathBuf, (Option<SystemTime>, u64)>, GitAiError> {
+    let mut files = HashMap::new();
+    let mut dirs = vec![PathBuf::new()];
+    while let Some(dir) = dirs.pop() {
+        let Ok(entries) = fs::read_dir(root.join(&dir)) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let relative = dir.join(entry.file_name());
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                if !is_skipped(&relative, skip) {
+                    dirs.push(relative);
+                }
+            } else if let Ok(metadata) = entry.metadata() {
+                files.insert(relative, (metadata.modified().ok(), metadata.len()));
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn is_skipped(relative_dir: &Path, skip: &SkipDir) -> bool {
+    relative_dir.file_name().is_some_and(|name| name == ".git") || skip(relative_dir)
+}
+
+#[cfg(target_os = "linux")]
+mod inotify {
+    use super::{Changes, SkipDir, is_skipped};
+    use crate::error::GitAiError;
+    use std::collections::HashMap;
+    use std::ffi::CString;
+    use std::fs;
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::{Path, PathBuf};
+    use std::time::Duration;
+
+    const WATCH_MASK: u32 = libc::IN_CLOSE_WRITE
+        | libc::IN_MODIFY
+        | libc::IN_CREATE
+        | libc::IN_DELETE
+        | libc::IN_MOVED_FROM
+        | libc::IN_MOVED_TO
+        | libc::IN_DELETE_SELF
+        | libc::IN_ONLYDIR;
+
+    /// Size of `struct inotify_event` before its name
+    const EVENT_HEADER: usize = std::mem::size_of::<libc::inotify_event>();
+
+    pub struct Watches {
+        fd: libc::c_int,
+        /// Watched directories by watch descriptor, relative to the root
+        dirs: HashMap<libc::c_int, PathBuf>,
+    }
+
+    impl Watches {
+        pub fn new(root: &Path, skip: &SkipDir) -> Result<Watches, GitAiError> {
+            let fd = unsafe { libc::inotify_init1(libc::IN_NONBLOCK | libc::IN_CLOEXEC) };
+            if fd < 0 {
+                return Err(std::io::Error::last_os_error().into());
+            }
+            let mut watches = Watches {
+                fd,
+                dirs: HashMap::new(),
+            };
+            watches.watch_tree(root, PathBuf::new(), skip, &mut Vec::new())?;
+            Ok(watches)
+        }
+
+        /// Watch `dir` and every directory below it, adding the files found in them to `files`
+        fn watch_tree(
+            &mut self,
+            root: &Path,
+            dir: PathBuf,
+            skip: &SkipDir,
+            files: &mut Vec<PathBuf>,
+        ) -> Result<(), GitAiError> {
+            let mut dirs = vec![dir];
+            while let Some(dir) = dirs.pop() {
+                let path = CString::new(root.join(&dir).as_os_str().as_bytes())
+                    .map_err(|e| GitAiError::Generic(e.to_string()))?;
+                let wd = unsafe { libc::inotify_add_watch(self.fd, path.as_ptr(), WATCH_MASK) };
+                if wd < 0 {
+                    let error = std::io::Error::last_os_error();
+                    // Removed again before it could be watched
+                    if error.raw_os_error() == Some(libc::ENOENT) {
+                        continue;
+                    }
+                    return Err(error.into());
+                }
+                self.dirs.insert(wd, dir.clone());
+
+                let Ok(entries) = fs::read_dir(root.join(&dir)) else {
+                    continue;
+                };
+                for entry in entries.filter_map(|entry| entry.ok()) {
+                    let relative = dir.join(entry.file_name());
+                    match entry.file_type() {
+                        Ok(file_type) if file_type.is_dir() => {
+                            if !is_skipped(&relative, skip) {
+                                dirs.push(relative);
+                            }
+                        }
+                        Ok(_) => files.push(relative),
+                        Err(_) => {}
+                    }
+                }
+            }
+            Ok(())
+        }
+
+        pub fn wait(
+            &mut self,
+            root: &Path,
+            skip: &SkipDir,
+            timeout: Duration,
+        ) -> Result<Changes, GitAiError> {
+            let mut changes = Changes::default();
+            let mut poll_fd = libc::pollfd {
+                fd: self.fd,
+                events: libc::POLLIN,
+                revents: 0,
+            };
+            let timeout_ms = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+            let ready = unsafe { libc::poll(&mut poll_fd, 1, timeout_ms) };
+            if ready < 0 {
+                let error = std::io::Error::last_os_error();
+                if error.kind() == std::io::ErrorKind::Interrupted {
+                    return Ok(changes);
+                }
+                return Err(error.into());
+            }
+            if ready == 0 {
+                return Ok(changes);
+            }
+
+            let mut buffer = vec![0u8; 64 * 1024];
+            loop {
+                let read = unsafe {
+                    libc::read(
+                        self.fd,
+                        buffer.as_mut_ptr() as *mut libc::c_void,
+                        buffer.len(),
+                    )
+                };
+                if read < 0 {
+                    let error = std::io::Error::last_os_error();
+                    match error.kind() {
+                        std::io::ErrorKind::WouldBlock => break,
+                        std::io::ErrorKind::Interrupted => continue,
+                        _ => return Err(error.into()),
+                    }
+                }
+                if read == 0 {
+                    break;
+                }
+                let mut offset = 0;
+                while offset + EVENT_HEADER <= read as usize {
+                    let field = |at: usize| {
+                        let start = offset + at;
+                        [
+                            buffer[start],
+                            buffer[start + 1],
+                            buffer[start + 2],
+                            buffer[start + 3],
+                        ]
+                    };
+                    let wd = libc::c_int::from_ne_bytes(field(0));
+                    let mask = u32::from_ne_bytes(field(4));
+                    let name_len = u32::from_ne_bytes(field(12)) as usize;
+                    let name_bytes =
+                        &buffer[offset + EVENT_HEADER..offset + EVENT_HEADER + name_len];
+                    let name_end = name_bytes
+                        .iter()
+                        .position(|byte| *byte == 0)
+                        .unwrap_or(name_bytes.len());
+                    let name = std::ffi::OsStr::from_bytes(&name_bytes[..name_end]).to_owned();
+                    offset += EVENT_HEADER + name_len;
+                    self.handle_event(root, skip, wd, mask, &name, &mut changes)?;
+                }
+            }
+            Ok(changes)
+        }
+
+        fn handle_event(
+            &mut self,
+            root: &Path,
+            skip: &SkipDir,
+            wd: libc::c_int,
+            mask: u32,
+            name: &std::ffi::OsStr,
+            changes: &mut Changes,
+        ) -> Result<(), GitAiError> {
+            if mask & libc::IN_Q_OVERFLOW != 0 {
+                changes.everything = true;
+                return Ok(());
+            }
+            if mask & libc::IN_IGNORED != 0 {
+                self.dirs.remove(&wd);
+                return Ok(());
+            }
+            let Some(dir) = self.dirs.get(&wd) else {
+                return Ok(());
+            };
+            if name.is_empty() {
+                // The watched directory itself went away; its parent reports it too
+                return Ok(());
+            }
+            let path = dir.join(name);
+
+            if mask & libc::IN_ISDIR == 0 {
+                changes.paths.insert(path);
+                return Ok(());
+            }
+            if is_skipped(&path, skip) {
+                return Ok(());
+            }
+            if mask & (libc::IN_CREATE | libc::IN_MOVED_TO) != 0 {
+                // Files can land in a new directory before it's watched, so report what it holds
+                let mut files = Vec::new();
+                self.watch_tree(root, path, skip, &mut files)?;
+                changes.paths.extend(files);
+            } else if mask & (libc::IN_DELETE | libc::IN_MOVED_FROM) != 0 {
+                // The files that went with it aren't reported one by one
+                changes.everything = true;
+            }
+            Ok(())
+        }
+    }
+
+    impl Drop for Watches {
+        fn drop(&mut self) {
+            unsafe {
+                libc::close(self.fd);
+            }
+        }
+    }
+}
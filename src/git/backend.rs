@@ -0,0 +1,481 @@
+//! Read-only object access for [`Repository`](crate::git::repository::Repository).
+//!
+//! Every lookup through the git CLI costs a process spawn, which adds up in hooks that read a
+//! few hundred blobs. The CLI backend reads objects named by id through long-lived
+//! `git cat-file --batch` processes instead. With the `libgit2` feature, reads go through
+//! libgit2 in-process, and anything libgit2 can't answer falls back to the CLI. Writes, and
+//! everything that has to honor hooks and the user's config, always use one-off commands.
+
+use crate::config;
+use crate::error::GitAiError;
+use crate::git::repository::exec_git;
+use std::fmt;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::sync::{Arc, Mutex};
+
+/// Forces a backend: `cli`, or `libgit2` (the default when built with the `libgit2` feature)
+pub const GIT_BACKEND_ENV: &str = "GIT_AI_GIT_BACKEND";
+
+pub trait GitBackend: fmt::Debug + Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// The object id `spec` names, like `git rev-parse --verify <spec>`
+    fn rev_parse(&self, spec: &str) -> Result<String, GitAiError>;
+
+    /// `commit`, `tree`, `blob` or `tag`, like `git cat-file -t <spec>`
+    fn object_type(&self, spec: &str) -> Result<String, GitAiError>;
+
+    fn commit_parents(&self, commit: &str) -> Result<Vec<String>, GitAiError>;
+
+    fn blob_content(&self, blob: &str) -> Result<Vec<u8>, GitAiError>;
+
+    /// The content of `path` in `commit`, like `git show <commit>:<path>`
+    fn file_content(&self, commit: &str, path: &str) -> Result<Vec<u8>, GitAiError>;
+}
+
+/// The backend for a repository: libgit2 when it's compiled in, opens the repository, and
+/// `global_args` don't change how git reads objects; the CLI otherwise
+pub fn backend_for(global_args: &[String], git_dir: &Path) -> Arc<dyn GitBackend> {
+    let cli = CliBackend::new(global_args);
+
+    #[cfg(feature = "libgit2")]
+    if std::env::var(GIT_BACKEND_ENV).as_deref() != Ok("cli")
+        && !needs_cli_parity(global_args)
+        && let Some(backend) = Git2Backend::open(git_dir, cli.clone())
+    {
+        return Arc::new(backend);
+    }
+    #[cfg(not(feature = "libgit2"))]
+    let _ = git_dir;
+
+    Arc::new(cli)
+}
+
+/// Whether `global_args` carry config or object-visibility overrides libgit2 would ignore
+#[cfg_attr(not(feature = "libgit2"), allow(dead_code))]
+fn needs_cli_parity(global_args: &[String]) -> bool {
+    global_args.iter().any(|arg| {
+        matches!(
+            arg.as_str(),
+            "-c" | "--config-env" | "--namespace" | "--no-replace-objects"
+        ) || arg.starts_with("--config-env=")
+            || arg.starts_with("--namespace=")
+    })
+}
+
+/// Whether `name` is a full object id, which always names the same object. Anything else
+/// (a ref, `HEAD~2`) can move while a batch process is running.
+fn is_object_id(name: &str) -> bool {
+    matches!(name.len(), 40 | 64) && name.bytes().all(|b| b.is_ascii_hexdigit())
+}
+
+/// Reads through `git` subprocesses
+#[derive(Debug, Clone)]
+pub struct CliBackend {
+    global_args: Vec<String>,
+    batch: Arc<BatchObjectReader>,
+}
+
+impl CliBackend {
+    pub fn new(global_args: &[String]) -> Self {
+        let mut global_args = global_args.to_vec();
+        if !global_args.iter().any(|arg| arg == "--no-pager") {
+            global_args.push("--no-pager".to_string());
+        }
+        let batch = Arc::new(BatchObjectReader::new(&global_args));
+        Self { global_args, batch }
+    }
+
+    fn git(&self, command: &[&str]) -> Result<Vec<u8>, GitAiError> {
+        let mut args = self.global_args.clone();
+        args.extend(command.iter().map(|arg| arg.to_string()));
+        Ok(exec_git(&args)?.stdout)
+    }
+
+    fn git_line(&self, command: &[&str]) -> Result<String, GitAiError> {
+        Ok(String::from_utf8(self.git(command)?)?.trim().to_string())
+    }
+}
+
+impl GitBackend for CliBackend {
+    fn name(&self) -> &'static str {
+        "cli"
+    }
+
+    fn rev_parse(&self, spec: &str) -> Result<String, GitAiError> {
+        self.git_line(&["rev-parse", "--verify", spec])
+    }
+
+    fn object_type(&self, spec: &str) -> Result<String, GitAiError> {
+        if is_object_id(spec)
+            && let Some(kind) = self.batch.object_type(spec)
+        {
+            return Ok(kind);
+        }
+        self.git_line(&["cat-file", "-t", spec])
+    }
+
+    fn commit_parents(&self, commit: &str) -> Result<Vec<String>, GitAiError> {
+        if is_object_id(commit)
+            && let Some((kind, content)) = self.batch.object(commit)
+            && kind == "commit"
+        {
+            // The header ends at the first blank line; parents follow the tree
+            return Ok(content
+                .split(|b| *b == b'\n')
+                .take_while(|line| !line.is_empty())
+                .filter_map(|line| line.strip_prefix(b"parent "))
+                .map(|parent| String::from_utf8_lossy(parent).to_string())
+                .collect());
+        }
+        let parents = self.git_line(&["show", "-s", "--format=%P", commit])?;
+        Ok(parents.split_whitespace().map(str::to_string).collect())
+    }
+
+    fn blob_content(&self, blob: &str) -> Result<Vec<u8>, GitAiError> {
+        if is_object_id(blob)
+            && let Some((kind, content)) = self.batch.object(blob)
+            && kind == "blob"
+        {
+            return Ok(content);
+        }
+        self.git(&["cat-file", "blob", blob])
+    }
+
+    fn file_content(&self, commit: &str, path: &str) -> Result<Vec<u8>, GitAiError> {
+        let spec = format!("{}:{}", commit, path);
+        // Anything but a blob at the path (a tree listing, say) is left to `git show`
+        if is_object_id(commit)
+            && let Some((kind, content)) = self.batch.object(&spec)
+            && kind == "blob"
+        {
+            return Ok(content);
+        }
+        self.git(&["show", &spec])
+    }
+}
+
+/// Long-lived `git cat-file --batch` and `--batch-check` processes serving object reads, so
+/// reading a few hundred objects costs two process spawns rather than a few hundred. Each
+/// process is started on its first read and restarted on the read after one fails. Reads
+/// return `None` for anything the batch can't answer, and callers fall back to a one-off
+/// command, which reports the error.
+#[derive(Debug)]
+pub struct BatchObjectReader {
+    global_args: Vec<String>,
+    contents: Mutex<Option<BatchProcess>>,
+    info: Mutex<Option<BatchProcess>>,
+}
+
+impl BatchObjectReader {
+    pub fn new(global_args: &[String]) -> Self {
+        Self {
+            global_args: global_args.to_vec(),
+            contents: Mutex::new(None),
+            info: Mutex::new(None),
+        }
+    }
+
+    /// The type and content of the object `name` names, e.g. `<commit>:<path>`
+    pub fn object(&self, name: &str) -> Option<(String, Vec<u8>)> {
+        self.read(&self.contents, "--batch", name)
+    }
+
+    /// `commit`, `tree`, `blob` or `tag`, without reading the object's content
+    pub fn object_type(&self, name: &str) -> Option<String> {
+        self.read(&self.info, "--batch-check", name)
+            .map(|(kind, _)| kind)
+    }
+
+    fn read(
+        &self,
+        process: &Mutex<Option<BatchProcess>>,
+        mode: &str,
+        name: &str,
+    ) -> Option<(String, Vec<u8>)> {
+        // Names are sent one per line
+        if name.contains('\n') {
+            return None;
+        }
+        let mut process = process.lock().ok()?;
+        if process.is_none() {
+            *process = BatchProcess::spawn(&self.global_args, mode).ok();
+        }
+        match process.as_mut()?.read(name) {
+            Ok(object) => object,
+            Err(_) => {
+                // The process is out of step or gone; start over on the next read
+                *process = None;
+                None
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct BatchProcess {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    /// `--batch` prints each object's content after its header, `--batch-check` doesn't
+    with_contents: bool,
+}
+
+impl BatchProcess {
+    fn spawn(global_args: &[String], mode: &str) -> Result<Self, GitAiError> {
+        let mut args = global_args.to_vec();
+        args.push("cat-file".to_string());
+        args.push(mode.to_string());
+        crate::fault::check_git(&args)?;
+        let mut child = Command::new(config::Config::get().git_cmd())
+            .args(&args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            let _ = child.kill();
+            let _ = child.wait();
+            return Err(GitAiError::Generic(
+                "git cat-file started without pipes".to_string(),
+            ));
+        };
+        Ok(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+            with_contents: mode == "--batch",
+        })
+    }
+
+    /// `Ok(None)` when git reports the name missing or ambiguous
+    fn read(&mut self, name: &str) -> std::io::Result<Option<(String, Vec<u8>)>> {
+        writeln!(self.stdin, "{}", name)?;
+        self.stdin.flush()?;
+
+        let mut header = String::new();
+        if self.stdout.read_line(&mut header)? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        // `<oid> <type> <size>`, or `<name> missing` with nothing after it
+        let mut fields = header.trim_end().rsplitn(3, ' ');
+        let (Some(size), Some(kind)) = (fields.next(), fields.next()) else {
+            return Ok(None);
+        };
+        let Ok(size) = size.parse::<usize>() else {
+            return Ok(None);
+        };
+        if !self.with_contents {
+            return Ok(Some((kind.to_string(), Vec::new())));
+        }
+
+        // The content, then a newline
+        let mut content = vec![0u8; size + 1];
+        self.stdout.read_exact(&mut content)?;
+        content.pop();
+        Ok(Some((kind.to_string(), content)))
+    }
+}
+
+impl Drop for BatchProcess {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Reads through libgit2, retrying on the CLI whatever libgit2 fails on, so callers see the
+/// CLI's answers and errors either way
+#[cfg(feature = "libgit2")]
+pub struct Git2Backend {
+    // git2::Repository isn't Sync; lookups are short enough that serializing them is fine
+    repo: std::sync::Mutex<git2::Repository>,
+    cli: CliBackend,
+}
+
+#[cfg(feature = "libgit2")]
+impl fmt::Debug for Git2Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Git2Backend")
+            .field("cli", &self.cli)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "libgit2")]
+impl Git2Backend {
+    pub fn open(git_dir: &Path, cli: CliBackend) -> Option<Self> {
+        let repo = git2::Repository::open(git_dir).ok()?;
+        Some(Self {
+            repo: std::sync::Mutex::new(repo),
+            cli,
+        })
+    }
+
+    fn read<T>(
+        &self,
+        read: impl FnOnce(&git2::Repository) -> Result<T, git2::Error>,
+        fallback: impl FnOnce(&CliBackend) -> Result<T, GitAiError>,
+    ) -> Result<T, GitAiError> {
+        let result = match self.repo.lock() {
+            Ok(repo) => read(&repo).ok(),
+            Err(_) => None,
+        };
+        match result {
+            Some(value) => Ok(value),
+            None => fallback(&self.cli),
+        }
+    }
+}
+
+#[cfg(feature = "libgit2")]
+impl GitBackend for Git2Backend {
+    fn name(&self) -> &'static str {
+        "libgit2"
+    }
+
+    fn rev_parse(&self, spec: &str) -> Result<String, GitAiError> {
+        self.read(
+            |repo| Ok(repo.revparse_single(spec)?.id().to_string()),
+            |cli| cli.rev_parse(spec),
+        )
+    }
+
+    fn object_type(&self, spec: &str) -> Result<String, GitAiError> {
+        self.read(
+            |repo| {
+                let kind = repo.revparse_single(spec)?.kind();
+                kind.map(|kind| kind.str().to_string())
+                    .ok_or_else(|| git2::Error::from_str("unknown object type"))
+            },
+            |cli| cli.object_type(spec),
+        )
+    }
+
+    fn commit_parents(&self, commit: &str) -> Result<Vec<String>, GitAiError> {
+        self.read(
+            |repo| {
+                let commit = repo.revparse_single(commit)?.peel_to_commit()?;
+                Ok(commit.parent_ids().map(|id| id.to_string()).collect())
+            },
+            |cli| cli.commit_parents(commit),
+        )
+    }
+
+    fn blob_content(&self, blob: &str) -> Result<Vec<u8>, GitAiError> {
+        self.read(
+            |repo| {
+                Ok(repo
+                    .revparse_single(blob)?
+                    .peel_to_blob()?
+                    .content()
+                    .to_vec())
+            },
+            |cli| cli.blob_content(blob),
+        )
+    }
+
+    fn file_content(&self, commit: &str, path: &str) -> Result<Vec<u8>, GitAiError> {
+        // Anything but a blob at the path (a tree listing, say) is left to `git show`
+        self.read(
+            |repo| {
+                let spec = format!("{}:{}", commit, path);
+                Ok(repo
+                    .revparse_single(&spec)?
+                    .peel_to_blob()?
+                    .content()
+                    .to_vec())
+            },
+            |cli| cli.file_content(commit, path),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::git::test_utils::TmpRepo;
+
+    #[test]
+    fn test_needs_cli_parity() {
+        assert!(!needs_cli_parity(&["-C".to_string(), "/repo".to_string()]));
+        assert!(needs_cli_parity(&[
+            "-c".to_string(),
+            "core.abbrev=12".to_string()
+        ]));
+        assert!(needs_cli_parity(&["--namespace=review".to_string()]));
+    }
+
+    fn assert_backend_reads(backend: &dyn GitBackend, tmp_repo: &TmpRepo) {
+        let head = tmp_repo.head_commit_sha().unwrap();
+        assert_eq!(backend.rev_parse("HEAD").unwrap(), head);
+        assert_eq!(backend.object_type(&head).unwrap(), "commit");
+        assert_eq!(
+            backend.commit_parents(&head).unwrap(),
+            vec![backend.rev_parse("HEAD~1").unwrap()]
+        );
+        assert_eq!(
+            backend.file_content(&head, "lines.md").unwrap(),
+            std::fs::read(tmp_repo.path().join("lines.md")).unwrap()
+        );
+        let blob = backend.rev_parse(&format!("{}:lines.md", head)).unwrap();
+        assert_eq!(backend.object_type(&blob).unwrap(), "blob");
+        assert_eq!(
+            backend.blob_content(&blob).unwrap(),
+            backend.file_content(&head, "lines.md").unwrap()
+        );
+        assert!(backend.rev_parse("no-such-branch").is_err());
+        assert!(backend.file_content(&head, "missing.md").is_err());
+    }
+
+    fn repo_with_two_commits() -> TmpRepo {
+        let (tmp_repo, mut lines, _) = TmpRepo::new_with_base_commit().unwrap();
+        lines.append("one more line\n").unwrap();
+        tmp_repo
+            .trigger_checkpoint_with_author("test_user")
+            .unwrap();
+        tmp_repo.commit_with_message("second commit").unwrap();
+        tmp_repo
+    }
+
+    #[test]
+    fn test_batch_object_reader() {
+        let tmp_repo = repo_with_two_commits();
+        let reader =
+            BatchObjectReader::new(&["-C".to_string(), tmp_repo.path().display().to_string()]);
+        let head = tmp_repo.head_commit_sha().unwrap();
+        let lines = std::fs::read(tmp_repo.path().join("lines.md")).unwrap();
+
+        assert_eq!(reader.object_type(&head).as_deref(), Some("commit"));
+        let (kind, content) = reader.object(&format!("{}:lines.md", head)).unwrap();
+        assert_eq!((kind.as_str(), content), ("blob", lines.clone()));
+        // A miss leaves the process in step for the next read
+        assert!(reader.object(&format!("{}:missing.md", head)).is_none());
+        assert!(
+            reader
+                .object_type("0000000000000000000000000000000000000000")
+                .is_none()
+        );
+        assert!(reader.object("one\ntwo").is_none());
+        let (_, content) = reader.object(&format!("{}:lines.md", head)).unwrap();
+        assert_eq!(content, lines);
+    }
+
+    #[test]
+    fn test_cli_backend_reads() {
+        let tmp_repo = repo_with_two_commits();
+        let backend = CliBackend::new(&["-C".to_string(), tmp_repo.path().display().to_string()]);
+        assert_backend_reads(&backend, &tmp_repo);
+    }
+
+    #[cfg(feature = "libgit2")]
+    #[test]
+    fn test_git2_backend_matches_cli() {
+        let tmp_repo = repo_with_two_commits();
+        let cli = CliBackend::new(&["-C".to_string(), tmp_repo.path().display().to_string()]);
+        let backend = Git2Backend::open(tmp_repo.repo().path(), cli).unwrap();
+        assert_backend_reads(&backend, &tmp_repo);
+    }
+}
@@ -46,6 +46,11 @@ impl ParsedGitInvocation {
         v.extend(self.command_args.iter().cloned());
         v
     }
+    /// `command_args` read as the arguments of `git commit`
+    pub fn commit_options(&self) -> CommitOptions {
+        parse_commit_options(&self.command_args)
+    }
+
     pub fn has_command_flag(&self, flag: &str) -> bool {
         self.command_args.iter().any(|arg| arg == flag)
     }
@@ -96,6 +101,99 @@ impl ParsedGitInvocation {
     }
 }
 
+/// The options of a `git commit` that decide what it records
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CommitOptions {
+    /// `-a`/`--all`: modified and deleted tracked files are staged first
+    pub all: bool,
+    /// `-i`/`--include`: the pathspecs' working tree is committed along with the index
+    pub include: bool,
+    /// `--interactive`, `--patch` or `--pathspec-from-file`: what gets recorded isn't known
+    /// from the arguments
+    pub interactive: bool,
+    /// Without `--include`, only these paths are committed, as they are in the working tree
+    pub pathspecs: Vec<String>,
+}
+
+/// `git commit` short options whose value is the rest of the cluster or the next argument
+const COMMIT_SHORT_OPTIONS_WITH_VALUE: &str = "mFCct";
+
+/// `git commit` short options whose optional value can only be attached (`-S<keyid>`)
+const COMMIT_SHORT_OPTIONS_WITH_ATTACHED_VALUE: &str = "Su";
+
+/// `git commit` long options that take the next argument as their value without `=`
+const COMMIT_LONG_OPTIONS_WITH_VALUE: &[&str] = &[
+    "message",
+    "file",
+    "reuse-message",
+    "reedit-message",
+    "fixup",
+    "squash",
+    "author",
+    "date",
+    "template",
+    "cleanup",
+    "trailer",
+    "pathspec-from-file",
+];
+
+/// Read `args` as the arguments of `git commit`, telling option values apart from flags and
+/// pathspecs, so `-m -all` is a message and not `-a`
+pub fn parse_commit_options(args: &[String]) -> CommitOptions {
+    let mut options = CommitOptions::default();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            options.pathspecs.extend(args.cloned());
+            break;
+        }
+
+        if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline_value) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value)),
+                None => (long, None),
+            };
+            match name {
+                "all" => options.all = true,
+                "include" => options.include = true,
+                "interactive" | "patch" | "pathspec-from-file" => options.interactive = true,
+                _ => {}
+            }
+            if inline_value.is_none() && COMMIT_LONG_OPTIONS_WITH_VALUE.contains(&name) {
+                args.next();
+            }
+            continue;
+        }
+
+        if let Some(cluster) = arg.strip_prefix('-')
+            && !cluster.is_empty()
+        {
+            for (i, c) in cluster.char_indices() {
+                match c {
+                    'a' => options.all = true,
+                    'i' => options.include = true,
+                    'p' => options.interactive = true,
+                    _ => {}
+                }
+                if COMMIT_SHORT_OPTIONS_WITH_VALUE.contains(c) {
+                    // The value is the rest of the cluster, or the next argument if that's empty
+                    if i + c.len_utf8() == cluster.len() {
+                        args.next();
+                    }
+                    break;
+                }
+                if COMMIT_SHORT_OPTIONS_WITH_ATTACHED_VALUE.contains(c) {
+                    break;
+                }
+            }
+            continue;
+        }
+
+        options.pathspecs.push(arg.clone());
+    }
+    options
+}
+
 /// Returns true if the given flag typically takes a value as the next argument.
 /// This is a heuristic for common git command flags that take values.
 fn is_flag_with_value(flag: &str) -> bool {
@@ -525,6 +623,28 @@ mod tests {
         assert_eq!(parsed.pos_command(1), None);
     }
 
+    #[test]
+    fn test_parse_commit_options() {
+        let parse = |args: &[&str]| {
+            parse_commit_options(&args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>())
+        };
+
+        let options = parse(&["-am", "message"]);
+        assert!(options.all);
+        assert!(options.pathspecs.is_empty());
+
+        // Values of options aren't flags or pathspecs, however they start
+        let options = parse(&["-m", "-all", "--author", "-a <a@b>", "-Sa", "-ma"]);
+        assert_eq!(options, CommitOptions::default());
+
+        let options = parse(&["--include", "--message=x", "src/lib.rs", "--", "-a"]);
+        assert!(options.include && !options.all);
+        assert_eq!(options.pathspecs, vec!["src/lib.rs", "-a"]);
+
+        assert!(parse(&["--pathspec-from-file", "paths.txt"]).interactive);
+        assert!(parse(&["-pm", "message"]).interactive);
+    }
+
     #[test]
     fn test_pos_command_inline_flag_value() {
         // Test: git merge --strategy=recursive abc
@@ -0,0 +1,146 @@
+//! Commits made on a detached HEAD (bisect sessions, experiments, edits in the middle of a
+//! rebase) get authorship notes like any other commit, but many of them are thrown away soon
+//! after. Each one is kept reachable under `refs/ai/detached/<sha>` when it's made, so `git gc`
+//! can't drop it while its note might still matter. Once the commit is on a branch or tag its
+//! ref goes; once it's clear it never will be, the ref and its note go.
+
+use crate::error::GitAiError;
+use crate::git::refs::notes_remove;
+use crate::git::repository::{Repository, exec_git, exec_git_stdin};
+use crate::utils::debug_log;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Where detached commits are kept reachable, one ref per commit
+pub const DETACHED_REFS_PREFIX: &str = "refs/ai/detached/";
+
+/// How long a detached commit that's on no branch keeps its ref and note, counted from when it
+/// was committed. Matches git's default `gc.reflogExpireUnreachable`.
+pub const DETACHED_NOTE_GRACE_SECS: u64 = 30 * 24 * 60 * 60;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PruneSummary {
+    /// Commits that ended up on a ref; their notes stay
+    pub attached: usize,
+    /// Commits whose notes were removed
+    pub pruned: usize,
+    /// Commits still within the grace period or checked out
+    pub pending: usize,
+}
+
+pub fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+pub fn is_detached(repo: &Repository) -> bool {
+    repo.head().map(|head| !head.is_branch()).unwrap_or(false)
+}
+
+/// Keep `commit_sha` reachable under its detached ref if it was just made on a detached HEAD
+pub fn record_if_detached(repo: &Repository, commit_sha: &str) {
+    if !is_detached(repo) {
+        return;
+    }
+    let mut args = repo.global_args_for_exec();
+    args.push("update-ref".to_string());
+    args.push(format!("{}{}", DETACHED_REFS_PREFIX, commit_sha));
+    args.push(commit_sha.to_string());
+    if let Err(e) = exec_git(&args) {
+        debug_log(&format!("Failed to record detached commit: {}", e));
+    }
+}
+
+/// Resolve the detached refs: delete the ones whose commit is now on a branch or tag, delete
+/// the ones that have been on none for longer than `grace_secs` along with their commits'
+/// notes, and keep the rest for a later run.
+pub fn prune_detached_notes(
+    repo: &Repository,
+    grace_secs: u64,
+    now: u64,
+) -> Result<PruneSummary, GitAiError> {
+    let commits = detached_commits(repo)?;
+    if commits.is_empty() {
+        return Ok(PruneSummary::default());
+    }
+
+    let mut summary = PruneSummary::default();
+    let mut resolved = Vec::new();
+    let mut prune = Vec::new();
+    for (commit, committed_at) in commits {
+        if is_on_a_ref(repo, &commit)? {
+            summary.attached += 1;
+            resolved.push(commit);
+        } else if now.saturating_sub(committed_at) < grace_secs
+            || is_ancestor_of_head(repo, &commit)
+        {
+            summary.pending += 1;
+        } else {
+            prune.push(commit.clone());
+            resolved.push(commit);
+        }
+    }
+
+    notes_remove(repo, &prune)?;
+    delete_detached_refs(repo, &resolved)?;
+    summary.pruned = prune.len();
+    Ok(summary)
+}
+
+/// The commits under the detached refs, with their commit times (unix seconds)
+fn detached_commits(repo: &Repository) -> Result<Vec<(String, u64)>, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("for-each-ref".to_string());
+    args.push("--format=%(objectname) %(committerdate:unix)".to_string());
+    args.push(DETACHED_REFS_PREFIX.to_string());
+
+    let output = exec_git(&args)?;
+    Ok(String::from_utf8(output.stdout)?
+        .lines()
+        .filter_map(|line| {
+            let (sha, committed_at) = line.trim().split_once(' ')?;
+            Some((sha.to_string(), committed_at.parse().ok()?))
+        })
+        .collect())
+}
+
+fn delete_detached_refs(repo: &Repository, commits: &[String]) -> Result<(), GitAiError> {
+    if commits.is_empty() {
+        return Ok(());
+    }
+    let mut args = repo.global_args_for_exec();
+    args.push("update-ref".to_string());
+    args.push("--stdin".to_string());
+    let stdin: String = commits
+        .iter()
+        .map(|commit| format!("delete {}{}\n", DETACHED_REFS_PREFIX, commit))
+        .collect();
+    exec_git_stdin(&args, stdin.as_bytes())?;
+    Ok(())
+}
+
+/// Whether any branch, tag or remote-tracking branch contains the commit
+fn is_on_a_ref(repo: &Repository, commit: &str) -> Result<bool, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("for-each-ref".to_string());
+    args.push("--count=1".to_string());
+    args.push(format!("--contains={}", commit));
+    args.push("--format=%(refname)".to_string());
+    args.push("refs/heads".to_string());
+    args.push("refs/tags".to_string());
+    args.push("refs/remotes".to_string());
+
+    let output = exec_git(&args)?;
+    Ok(!String::from_utf8(output.stdout)?.trim().is_empty())
+}
+
+/// A detached HEAD still sitting on (or on top of) the commit means it's in use
+fn is_ancestor_of_head(repo: &Repository, commit: &str) -> bool {
+    let mut args = repo.global_args_for_exec();
+    args.push("merge-base".to_string());
+    args.push("--is-ancestor".to_string());
+    args.push(commit.to_string());
+    args.push("HEAD".to_string());
+    exec_git(&args).is_ok()
+}
@@ -0,0 +1,803 @@
+use crate::error::GitAiError;
+use crate::git::repository::{Repository, exec_git};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+
+/// One record from a unified diff, in the order it appears in the diff output.
+///
+/// Paths are repo-relative with the `a/` / `b/` prefixes removed. Added lines carry new-file
+/// line numbers, deleted lines carry old-file line numbers. Hunk and line records share their
+/// file's path rather than each copying it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffRecord {
+    /// Start of a file section. `None` means /dev/null (file added or deleted).
+    File {
+        old_path: Option<String>,
+        new_path: Option<String>,
+    },
+    Renamed {
+        from: String,
+        to: String,
+        similarity: Option<u32>,
+    },
+    Copied {
+        from: String,
+        to: String,
+        similarity: Option<u32>,
+    },
+    /// Binary file; no line records follow for it
+    Binary { path: String },
+    Hunk {
+        path: Arc<str>,
+        old_start: u32,
+        old_count: u32,
+        new_start: u32,
+        new_count: u32,
+    },
+    Added {
+        path: Arc<str>,
+        line: u32,
+        content: String,
+    },
+    Deleted {
+        path: Arc<str>,
+        line: u32,
+        content: String,
+    },
+}
+
+/// Streaming parser over the lines of `git diff` / `git show` output.
+///
+/// Works on any iterator of lines so callers can feed it a `String`'s `.lines()` or a
+/// `BufRead` reading straight from a git child process.
+pub struct DiffParser<I> {
+    lines: I,
+    pending: VecDeque<DiffRecord>,
+    old_path: Option<String>,
+    new_path: Option<String>,
+    rename_from: Option<String>,
+    copy_from: Option<String>,
+    similarity: Option<u32>,
+    file_emitted: bool,
+    /// Shared copies of the paths for the line records of the current hunk
+    hunk_old_path: Option<Arc<str>>,
+    hunk_new_path: Option<Arc<str>>,
+    old_line: u32,
+    new_line: u32,
+    old_remaining: u32,
+    new_remaining: u32,
+}
+
+impl<I, L> DiffParser<I>
+where
+    I: Iterator<Item = L>,
+    L: AsRef<str>,
+{
+    pub fn new(lines: I) -> Self {
+        Self {
+            lines,
+            pending: VecDeque::new(),
+            old_path: None,
+            new_path: None,
+            rename_from: None,
+            copy_from: None,
+            similarity: None,
+            file_emitted: false,
+            hunk_old_path: None,
+            hunk_new_path: None,
+            old_line: 0,
+            new_line: 0,
+            old_remaining: 0,
+            new_remaining: 0,
+        }
+    }
+
+    fn in_hunk(&self) -> bool {
+        self.old_remaining > 0 || self.new_remaining > 0
+    }
+
+    fn start_file(&mut self, header: &str) {
+        self.flush_file_header();
+        let (old_path, new_path) = parse_diff_git_header(header);
+        self.old_path = old_path;
+        self.new_path = new_path;
+        self.rename_from = None;
+        self.copy_from = None;
+        self.similarity = None;
+        self.file_emitted = false;
+        self.old_remaining = 0;
+        self.new_remaining = 0;
+    }
+
+    /// Emit the File (and rename/copy) records once the extended header is complete
+    fn flush_file_header(&mut self) {
+        if self.file_emitted || (self.old_path.is_none() && self.new_path.is_none()) {
+            return;
+        }
+        self.file_emitted = true;
+        self.pending.push_back(DiffRecord::File {
+            old_path: self.old_path.clone(),
+            new_path: self.new_path.clone(),
+        });
+        if let (Some(from), Some(to)) = (self.rename_from.clone(), self.new_path.clone()) {
+            self.pending.push_back(DiffRecord::Renamed {
+                from,
+                to,
+                similarity: self.similarity,
+            });
+        } else if let (Some(from), Some(to)) = (self.copy_from.clone(), self.new_path.clone()) {
+            self.pending.push_back(DiffRecord::Copied {
+                from,
+                to,
+                similarity: self.similarity,
+            });
+        }
+    }
+
+    fn current_path(&self) -> Option<String> {
+        self.new_path.clone().or_else(|| self.old_path.clone())
+    }
+
+    fn parse_line(&mut self, line: &str) {
+        if self.in_hunk() {
+            self.parse_hunk_line(line);
+            return;
+        }
+
+        if line.starts_with("diff --git ") {
+            self.start_file(line);
+        } else if let Some(path) = line.strip_prefix("--- ") {
+            self.old_path = strip_path_prefix(path, "a/");
+        } else if let Some(path) = line.strip_prefix("+++ ") {
+            self.new_path = strip_path_prefix(path, "b/");
+        } else if let Some(path) = line.strip_prefix("rename from ") {
+            self.rename_from = Some(unquote_path(path));
+            self.old_path = Some(unquote_path(path));
+        } else if let Some(path) = line.strip_prefix("rename to ") {
+            self.new_path = Some(unquote_path(path));
+        } else if let Some(path) = line.strip_prefix("copy from ") {
+            self.copy_from = Some(unquote_path(path));
+            self.old_path = Some(unquote_path(path));
+        } else if let Some(path) = line.strip_prefix("copy to ") {
+            self.new_path = Some(unquote_path(path));
+        } else if let Some(value) = line.strip_prefix("similarity index ") {
+            self.similarity = value.trim_end_matches('%').parse().ok();
+        } else if line.starts_with("new file mode") {
+            self.old_path = None;
+        } else if line.starts_with("deleted file mode") {
+            self.new_path = None;
+        } else if line.starts_with("Binary files ") || line == "GIT binary patch" {
+            if let Some((old_path, new_path)) = parse_binary_line(line) {
+                self.old_path = old_path;
+                self.new_path = new_path;
+            }
+            self.flush_file_header();
+            if let Some(path) = self.current_path() {
+                self.pending.push_back(DiffRecord::Binary { path });
+            }
+        } else if line.starts_with("@@ ") {
+            self.flush_file_header();
+            if let Some((old_start, old_count, new_start, new_count)) = parse_hunk_header(line)
+                && let Some(path) = self.current_path()
+            {
+                self.hunk_old_path = self.old_path.as_deref().map(Arc::from);
+                self.hunk_new_path = self.new_path.as_deref().map(Arc::from);
+                self.old_line = old_start;
+                self.new_line = new_start;
+                self.old_remaining = old_count;
+                self.new_remaining = new_count;
+                self.pending.push_back(DiffRecord::Hunk {
+                    path: Arc::from(path),
+                    old_start,
+                    old_count,
+                    new_start,
+                    new_count,
+                });
+            }
+        }
+    }
+
+    fn parse_hunk_line(&mut self, line: &str) {
+        if let Some(content) = line.strip_prefix('+') {
+            if let Some(path) = self.hunk_new_path.clone() {
+                self.pending.push_back(DiffRecord::Added {
+                    path,
+                    line: self.new_line,
+                    content: content.to_string(),
+                });
+            }
+            self.new_line += 1;
+            self.new_remaining = self.new_remaining.saturating_sub(1);
+        } else if let Some(content) = line.strip_prefix('-') {
+            if let Some(path) = self.hunk_old_path.clone() {
+                self.pending.push_back(DiffRecord::Deleted {
+                    path,
+                    line: self.old_line,
+                    content: content.to_string(),
+                });
+            }
+            self.old_line += 1;
+            self.old_remaining = self.old_remaining.saturating_sub(1);
+        } else if line.starts_with('\\') {
+            // "\ No newline at end of file"
+        } else {
+            // Context line
+            self.old_line += 1;
+            self.new_line += 1;
+            self.old_remaining = self.old_remaining.saturating_sub(1);
+            self.new_remaining = self.new_remaining.saturating_sub(1);
+        }
+    }
+}
+
+impl<I, L> Iterator for DiffParser<I>
+where
+    I: Iterator<Item = L>,
+    L: AsRef<str>,
+{
+    type Item = DiffRecord;
+
+    fn next(&mut self) -> Option<DiffRecord> {
+        loop {
+            if let Some(record) = self.pending.pop_front() {
+                return Some(record);
+            }
+            match self.lines.next() {
+                Some(line) => self.parse_line(line.as_ref()),
+                None => {
+                    // Files with only an extended header (pure renames, mode changes)
+                    self.flush_file_header();
+                    return self.pending.pop_front();
+                }
+            }
+        }
+    }
+}
+
+/// Added line numbers per file (new-file coordinates), sorted and deduplicated.
+/// Files whose hunks only delete lines are present with an empty list.
+pub fn added_lines_by_file<I>(records: I) -> HashMap<String, Vec<u32>>
+where
+    I: IntoIterator<Item = DiffRecord>,
+{
+    let mut result: HashMap<String, Vec<u32>> = HashMap::new();
+    let mut has_new_file = false;
+    for record in records {
+        match record {
+            DiffRecord::File { new_path, .. } => {
+                has_new_file = new_path.is_some();
+            }
+            DiffRecord::Hunk { path, .. } if has_new_file => {
+                lines_for_path(&mut result, &path);
+            }
+            DiffRecord::Added { path, line, .. } => {
+                lines_for_path(&mut result, &path).push(line);
+            }
+            _ => {}
+        }
+    }
+    for lines in result.values_mut() {
+        lines.sort_unstable();
+        lines.dedup();
+    }
+    result
+}
+
+/// The entry for `path`, only copying the path the first time it's seen
+fn lines_for_path<'a>(result: &'a mut HashMap<String, Vec<u32>>, path: &str) -> &'a mut Vec<u32> {
+    if !result.contains_key(path) {
+        result.insert(path.to_string(), Vec::new());
+    }
+    result.get_mut(path).unwrap()
+}
+
+/// The header of one `-U0` hunk. A side with no lines starts at the line the other side's
+/// lines come after, so `-3,0 +4,2` inserts two lines after old line 3.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffHunk {
+    pub old_start: u32,
+    pub old_count: u32,
+    pub new_start: u32,
+    pub new_count: u32,
+}
+
+/// Which side of a diff a line number is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffSide {
+    Old,
+    New,
+}
+
+impl DiffHunk {
+    fn start_and_count(&self, side: DiffSide) -> (u32, u32) {
+        match side {
+            DiffSide::Old => (self.old_start, self.old_count),
+            DiffSide::New => (self.new_start, self.new_count),
+        }
+    }
+}
+
+/// Where `line` on the `from` side of `hunks` (in diff order) sits on the other side, or
+/// `None` when the line only exists on its own side
+pub fn map_line(hunks: &[DiffHunk], from: DiffSide, line: u32) -> Option<u32> {
+    let to = match from {
+        DiffSide::Old => DiffSide::New,
+        DiffSide::New => DiffSide::Old,
+    };
+    let mut offset: i64 = 0;
+    for hunk in hunks {
+        let (start, count) = hunk.start_and_count(from);
+        let (_, other_count) = hunk.start_and_count(to);
+        if count > 0 && line >= start && line < start + count {
+            return None;
+        }
+        // An empty side sits between `start` and `start + 1`
+        let end = if count == 0 { start + 1 } else { start + count };
+        if line < end {
+            break;
+        }
+        offset += other_count as i64 - count as i64;
+    }
+    u32::try_from(line as i64 + offset).ok()
+}
+
+/// Hunk headers per file (new path), in diff order. Deleted files are left out.
+///
+/// Lines a hunk removes and adds back unchanged are trimmed off it. Git only writes those
+/// when a file's missing final newline is added or dropped, as in `-last` `+last` `+more`,
+/// and the line itself is unchanged.
+pub fn hunks_by_file<I>(records: I) -> HashMap<String, Vec<DiffHunk>>
+where
+    I: IntoIterator<Item = DiffRecord>,
+{
+    let mut result: HashMap<String, Vec<DiffHunk>> = HashMap::new();
+    let mut has_new_file = false;
+    let mut current: Option<(Arc<str>, DiffHunk)> = None;
+    let mut deleted: Vec<String> = Vec::new();
+    let mut added: Vec<String> = Vec::new();
+    for record in records {
+        match record {
+            DiffRecord::File { new_path, .. } => {
+                finish_hunk(&mut result, current.take(), &deleted, &added);
+                has_new_file = new_path.is_some();
+            }
+            DiffRecord::Hunk {
+                path,
+                old_start,
+                old_count,
+                new_start,
+                new_count,
+            } => {
+                finish_hunk(&mut result, current.take(), &deleted, &added);
+                deleted.clear();
+                added.clear();
+                if has_new_file {
+                    let hunk = DiffHunk {
+                        old_start,
+                        old_count,
+                        new_start,
+                        new_count,
+                    };
+                    current = Some((path, hunk));
+                }
+            }
+            DiffRecord::Deleted { content, .. } => deleted.push(content),
+            DiffRecord::Added { content, .. } => added.push(content),
+            _ => {}
+        }
+    }
+    finish_hunk(&mut result, current, &deleted, &added);
+    result
+}
+
+fn finish_hunk(
+    result: &mut HashMap<String, Vec<DiffHunk>>,
+    current: Option<(Arc<str>, DiffHunk)>,
+    deleted: &[String],
+    added: &[String],
+) {
+    let Some((path, mut hunk)) = current else {
+        return;
+    };
+    let prefix = deleted
+        .iter()
+        .zip(added)
+        .take_while(|(old, new)| old == new)
+        .count();
+    let suffix = deleted[prefix..]
+        .iter()
+        .rev()
+        .zip(added[prefix..].iter().rev())
+        .take_while(|(old, new)| old == new)
+        .count();
+    let trimmed = (prefix + suffix) as u32;
+    // Counts come from the header; only trim when the lines seen agree with it
+    if trimmed > 0 && deleted.len() as u32 == hunk.old_count && added.len() as u32 == hunk.new_count
+    {
+        hunk.old_start += prefix as u32;
+        hunk.new_start += prefix as u32;
+        hunk.old_count -= trimmed;
+        hunk.new_count -= trimmed;
+        // An emptied side sits after the line before it
+        if hunk.old_count == 0 {
+            hunk.old_start -= 1;
+        }
+        if hunk.new_count == 0 {
+            hunk.new_start -= 1;
+        }
+        if hunk.old_count == 0 && hunk.new_count == 0 {
+            return;
+        }
+    }
+    if !result.contains_key(path.as_ref()) {
+        result.insert(path.to_string(), Vec::new());
+    }
+    result.get_mut(path.as_ref()).unwrap().push(hunk);
+}
+
+impl Repository {
+    /// Run `git diff -U0` from `from_ref` to `to_ref` (or the working directory when `None`)
+    /// and collect its [`added_lines_by_file`], consuming records as they're parsed. Renames
+    /// and copies are followed when git detects them.
+    pub fn diff_records_added_lines(
+        &self,
+        from_ref: &str,
+        to_ref: Option<&str>,
+        pathspecs: Option<&HashSet<String>>,
+    ) -> Result<HashMap<String, Vec<u32>>, GitAiError> {
+        let revs: Vec<&str> = std::iter::once(from_ref).chain(to_ref).collect();
+        let diff_output = self.diff_zero_context(&revs, pathspecs)?;
+        Ok(added_lines_by_file(DiffParser::new(diff_output.lines())))
+    }
+
+    /// [`hunks_by_file`] from the index to the working directory
+    pub fn diff_workdir_hunks_from_index(
+        &self,
+        pathspecs: Option<&HashSet<String>>,
+    ) -> Result<HashMap<String, Vec<DiffHunk>>, GitAiError> {
+        let diff_output = self.diff_zero_context(&[], pathspecs)?;
+        Ok(hunks_by_file(DiffParser::new(diff_output.lines())))
+    }
+
+    /// [`hunks_by_file`] from `commit` to the index
+    pub fn diff_index_hunks(
+        &self,
+        commit: &str,
+        pathspecs: Option<&HashSet<String>>,
+    ) -> Result<HashMap<String, Vec<DiffHunk>>, GitAiError> {
+        let diff_output = self.diff_zero_context(&["--cached", commit], pathspecs)?;
+        Ok(hunks_by_file(DiffParser::new(diff_output.lines())))
+    }
+
+    /// `git diff -U0 <revs>`; no revs compares the index with the working directory
+    fn diff_zero_context(
+        &self,
+        revs: &[&str],
+        pathspecs: Option<&HashSet<String>>,
+    ) -> Result<String, GitAiError> {
+        let mut args = self.global_args_for_exec();
+        args.push("diff".to_string());
+        args.push("-U0".to_string()); // Zero context lines
+        args.push("--no-color".to_string());
+        args.extend(revs.iter().map(|rev| rev.to_string()));
+
+        // Add pathspecs if provided
+        if let Some(paths) = pathspecs {
+            args.reserve(paths.len() + 1);
+            args.push("--".to_string());
+            args.extend(paths.iter().cloned());
+        }
+
+        let output = exec_git(&args)?;
+        Ok(String::from_utf8(output.stdout)?)
+    }
+}
+
+/// Parse a hunk header: @@ -old_start,old_count +new_start,new_count @@
+/// A missing count means 1 line.
+fn parse_hunk_header(line: &str) -> Option<(u32, u32, u32, u32)> {
+    let hunk_info = line.split("@@").nth(1)?.trim();
+    let mut ranges = hunk_info.split_whitespace();
+    let (old_start, old_count) = parse_range(ranges.next()?.strip_prefix('-')?)?;
+    let (new_start, new_count) = parse_range(ranges.next()?.strip_prefix('+')?)?;
+    Some((old_start, old_count, new_start, new_count))
+}
+
+fn parse_range(range: &str) -> Option<(u32, u32)> {
+    match range.split_once(',') {
+        Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+/// Paths from `diff --git a/<old> b/<new>`. Only used until the ---/+++ or rename lines
+/// give unambiguous paths; for unquoted paths with spaces we assume old == new.
+fn parse_diff_git_header(line: &str) -> (Option<String>, Option<String>) {
+    let rest = &line["diff --git ".len()..];
+    if rest.starts_with('"') {
+        let mut parts = split_quoted_pair(rest);
+        let old_path = parts.next().and_then(|p| strip_path_prefix(&p, "a/"));
+        let new_path = parts.next().and_then(|p| strip_path_prefix(&p, "b/"));
+        return (old_path, new_path);
+    }
+    let Some(rest) = rest.strip_prefix("a/") else {
+        return (None, None);
+    };
+    // Identical halves are the common case: "a/x b/x" has length 2 * len(x) + 3
+    if rest.len() >= 3 && (rest.len() - 3) % 2 == 0 {
+        let half = (rest.len() - 3) / 2;
+        if rest.is_char_boundary(half)
+            && rest[half..].starts_with(" b/")
+            && rest[..half] == rest[half + 3..]
+        {
+            let path = rest[..half].to_string();
+            return (Some(path.clone()), Some(path));
+        }
+    }
+    match rest.split_once(" b/") {
+        Some((old, new)) => (Some(old.to_string()), Some(new.to_string())),
+        None => (None, None),
+    }
+}
+
+fn split_quoted_pair(s: &str) -> impl Iterator<Item = String> {
+    let mut parts = Vec::new();
+    let mut rest = s.trim();
+    while !rest.is_empty() {
+        if rest.starts_with('"') {
+            let mut end = 1;
+            let bytes = rest.as_bytes();
+            while end < bytes.len() && !(bytes[end] == b'"' && bytes[end - 1] != b'\\') {
+                end += 1;
+            }
+            parts.push(rest[..(end + 1).min(rest.len())].to_string());
+            rest = rest[(end + 1).min(rest.len())..].trim_start();
+        } else {
+            let end = rest.find(' ').unwrap_or(rest.len());
+            parts.push(rest[..end].to_string());
+            rest = rest[end..].trim_start();
+        }
+    }
+    parts.into_iter()
+}
+
+/// "Binary files a/x and b/y differ"
+fn parse_binary_line(line: &str) -> Option<(Option<String>, Option<String>)> {
+    let rest = line
+        .strip_prefix("Binary files ")?
+        .strip_suffix(" differ")?;
+    let (old, new) = rest.split_once(" and ")?;
+    Some((strip_path_prefix(old, "a/"), strip_path_prefix(new, "b/")))
+}
+
+/// Strip the a/ or b/ prefix, mapping /dev/null to None and unquoting C-style quoted paths
+fn strip_path_prefix(path: &str, prefix: &str) -> Option<String> {
+    let path = unquote_path(path.trim_end_matches('\t'));
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(&path).to_string())
+}
+
+/// Undo git's C-style path quoting ("\"t\\303\\251st.txt\"" -> "tést.txt")
+fn unquote_path(path: &str) -> String {
+    let Some(inner) = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) else {
+        return path.to_string();
+    };
+
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut chars = inner.bytes().peekable();
+    while let Some(b) = chars.next() {
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        match chars.next() {
+            Some(b'n') => bytes.push(b'\n'),
+            Some(b't') => bytes.push(b'\t'),
+            Some(d @ b'0'..=b'7') => {
+                let mut value = (d - b'0') as u32;
+                for _ in 0..2 {
+                    if let Some(&next @ b'0'..=b'7') = chars.peek() {
+                        value = value * 8 + (next - b'0') as u32;
+                        chars.next();
+                    }
+                }
+                bytes.push(value as u8);
+            }
+            Some(other) => bytes.push(other),
+            None => bytes.push(b'\\'),
+        }
+    }
+    String::from_utf8_lossy(&bytes).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_diff(diff_output: &str) -> Vec<DiffRecord> {
+        DiffParser::new(diff_output.lines()).collect()
+    }
+
+    #[test]
+    fn test_parse_added_and_deleted_lines() {
+        let diff = "\
+diff --git a/src/lib.rs b/src/lib.rs
+index 1111111..2222222 100644
+--- a/src/lib.rs
++++ b/src/lib.rs
+@@ -2,2 +2,3 @@
+-old two
+-old three
++new two
+++++ looks like a header
++new four
+";
+        let records = parse_diff(diff);
+        assert_eq!(
+            records[0],
+            DiffRecord::File {
+                old_path: Some("src/lib.rs".to_string()),
+                new_path: Some("src/lib.rs".to_string()),
+            }
+        );
+        assert_eq!(
+            records[2],
+            DiffRecord::Deleted {
+                path: "src/lib.rs".into(),
+                line: 2,
+                content: "old two".to_string(),
+            }
+        );
+        assert_eq!(
+            records[5],
+            DiffRecord::Added {
+                path: "src/lib.rs".into(),
+                line: 3,
+                content: "+++ looks like a header".to_string(),
+            }
+        );
+
+        let added = added_lines_by_file(records);
+        assert_eq!(added["src/lib.rs"], vec![2, 3, 4]);
+    }
+
+    #[test]
+    fn test_parse_rename_copy_and_binary() {
+        let diff = "\
+diff --git a/old name.txt b/new name.txt
+similarity index 90%
+rename from old name.txt
+rename to new name.txt
+index 1111111..2222222 100644
+--- a/old name.txt
++++ b/new name.txt
+@@ -1,0 +2 @@
++added
+diff --git a/a.txt b/c.txt
+similarity index 100%
+copy from a.txt
+copy to c.txt
+diff --git a/img.png b/img.png
+new file mode 100644
+index 0000000..3333333
+Binary files /dev/null and b/img.png differ
+diff --git a/gone.txt b/gone.txt
+deleted file mode 100644
+index 4444444..0000000
+--- a/gone.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+";
+        let records = parse_diff(diff);
+        assert!(records.contains(&DiffRecord::Renamed {
+            from: "old name.txt".to_string(),
+            to: "new name.txt".to_string(),
+            similarity: Some(90),
+        }));
+        assert!(records.contains(&DiffRecord::Copied {
+            from: "a.txt".to_string(),
+            to: "c.txt".to_string(),
+            similarity: Some(100),
+        }));
+        assert!(records.contains(&DiffRecord::File {
+            old_path: None,
+            new_path: Some("img.png".to_string()),
+        }));
+        assert!(records.contains(&DiffRecord::Binary {
+            path: "img.png".to_string(),
+        }));
+        assert!(records.contains(&DiffRecord::Deleted {
+            path: "gone.txt".into(),
+            line: 1,
+            content: "bye".to_string(),
+        }));
+
+        let added = added_lines_by_file(records);
+        assert_eq!(added["new name.txt"], vec![2]);
+        // Deleted files have no new-file path, so no added lines
+        assert!(!added.contains_key("gone.txt"));
+    }
+
+    #[test]
+    fn test_hunks_by_file_and_map_line() {
+        let diff = "\
+diff --git a/a.txt b/a.txt
+index 1111111..2222222 100644
+--- a/a.txt
++++ b/a.txt
+@@ -2 +1,0 @@
+-two
+@@ -3,0 +3,2 @@
++new one
++new two
+@@ -5 +6 @@
+-five
++FIVE
+diff --git a/gone.txt b/gone.txt
+deleted file mode 100644
+index 1111111..0000000
+--- a/gone.txt
++++ /dev/null
+@@ -1 +0,0 @@
+-bye
+";
+        let hunks = hunks_by_file(parse_diff(diff));
+        assert!(!hunks.contains_key("gone.txt"));
+        let hunks = &hunks["a.txt"];
+        assert_eq!(hunks.len(), 3);
+
+        // old: 1 two 3 4 five 6 -> new: 1 3 n1 n2 4 FIVE 6
+        let old_to_new: Vec<Option<u32>> = (1..=6)
+            .map(|line| map_line(hunks, DiffSide::Old, line))
+            .collect();
+        assert_eq!(
+            old_to_new,
+            vec![Some(1), None, Some(2), Some(5), None, Some(7)]
+        );
+        let new_to_old: Vec<Option<u32>> = (1..=7)
+            .map(|line| map_line(hunks, DiffSide::New, line))
+            .collect();
+        assert_eq!(
+            new_to_old,
+            vec![Some(1), Some(3), None, None, Some(4), None, Some(6)]
+        );
+
+        // Appending to a file without a final newline rewrites its last line
+        let diff = "\
+diff --git a/b.txt b/b.txt
+index 1111111..2222222 100644
+--- a/b.txt
++++ b/b.txt
+@@ -2 +2,2 @@
+-last
+\\ No newline at end of file
++last
++more
+";
+        let hunks = hunks_by_file(parse_diff(diff));
+        assert_eq!(
+            hunks["b.txt"],
+            vec![DiffHunk {
+                old_start: 2,
+                old_count: 0,
+                new_start: 3,
+                new_count: 1,
+            }]
+        );
+        assert_eq!(map_line(&hunks["b.txt"], DiffSide::New, 2), Some(2));
+    }
+
+    #[test]
+    fn test_unquote_path() {
+        assert_eq!(unquote_path("plain.txt"), "plain.txt");
+        assert_eq!(unquote_path("\"t\\303\\251st.txt\""), "tést.txt");
+        assert_eq!(unquote_path("\"say \\\"hi\\\".txt\""), "say \"hi\".txt");
+        assert_eq!(
+            strip_path_prefix("\"b/t\\303\\251st.txt\"", "b/"),
+            Some("tést.txt".to_string())
+        );
+    }
+}
@@ -0,0 +1,215 @@
+use crate::config;
+use crate::error::GitAiError;
+use crate::utils::debug_log;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Overrides the cache location (used by tests to keep the user's cache untouched)
+pub const DISCOVERY_CACHE_ENV: &str = "GIT_AI_DISCOVERY_CACHE";
+
+/// Directories remembered at once; the least recently discovered are dropped first
+const MAX_ENTRIES: usize = 256;
+
+/// Environment that changes which repository git discovers. With any of it set we ask git.
+const DISCOVERY_ENV: &[&str] = &[
+    "GIT_DIR",
+    "GIT_WORK_TREE",
+    "GIT_COMMON_DIR",
+    "GIT_CEILING_DIRECTORIES",
+    "GIT_DISCOVERY_ACROSS_FILESYSTEM",
+];
+
+/// Which git dir `git rev-parse --absolute-git-dir` found for each working directory,
+/// stored in ~/.git-ai/discovery.json. Saves a git process on every proxied command.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiscoveryCache {
+    #[serde(default)]
+    pub entries: BTreeMap<String, CachedDiscovery>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedDiscovery {
+    pub git_dir: PathBuf,
+    /// Modification times (ns) of every directory discovery walked through, from the
+    /// working directory up to the one holding `.git`, plus `.git` itself. Creating,
+    /// removing or replacing a `.git` anywhere on that walk changes one of them.
+    pub mtimes: Vec<(PathBuf, u128)>,
+    pub discovered_at: u64,
+}
+
+pub fn cache_path() -> Option<PathBuf> {
+    if let Ok(path) = env::var(DISCOVERY_CACHE_ENV)
+        && !path.is_empty()
+    {
+        return Some(PathBuf::from(path));
+    }
+    Some(config::git_ai_dir()?.join("discovery.json"))
+}
+
+impl DiscoveryCache {
+    /// Load the cache. A missing or unreadable cache is treated as empty.
+    pub fn load(path: &Path) -> DiscoveryCache {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Written through a temporary file, so concurrent git commands never read half a cache
+    pub fn save(&self, path: &Path) -> Result<(), GitAiError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&tmp, serde_json::to_string(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+/// The cached git dir for these global args, if the directories it was found through
+/// haven't changed since
+pub fn lookup(global_args: &[String]) -> Option<PathBuf> {
+    let dir = discovery_dir(global_args)?;
+    let cache = DiscoveryCache::load(&cache_path()?);
+    let entry = cache.entries.get(dir.to_str()?)?;
+    is_fresh(entry).then(|| entry.git_dir.clone())
+}
+
+fn is_fresh(entry: &CachedDiscovery) -> bool {
+    entry.git_dir.is_dir()
+        && entry
+            .mtimes
+            .iter()
+            .all(|(path, mtime)| modified_nanos(path) == Some(*mtime))
+}
+
+/// Remember the git dir git found for these global args. Failures only cost the next lookup.
+pub fn record(global_args: &[String], git_dir: &Path) {
+    let Some(dir) = discovery_dir(global_args) else {
+        return;
+    };
+    let Some(mtimes) = discovery_mtimes(&dir, git_dir) else {
+        return;
+    };
+    let (Some(path), Some(key)) = (cache_path(), dir.to_str()) else {
+        return;
+    };
+
+    let mut cache = DiscoveryCache::load(&path);
+    cache.entries.insert(
+        key.to_string(),
+        CachedDiscovery {
+            git_dir: git_dir.to_path_buf(),
+            mtimes,
+            discovered_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        },
+    );
+    while cache.entries.len() > MAX_ENTRIES {
+        let Some(oldest) = cache
+            .entries
+            .iter()
+            .min_by_key(|(_, entry)| entry.discovered_at)
+            .map(|(dir, _)| dir.clone())
+        else {
+            break;
+        };
+        cache.entries.remove(&oldest);
+    }
+    if let Err(e) = cache.save(&path) {
+        debug_log(&format!("Failed to save discovery cache: {}", e));
+    }
+}
+
+/// The directory git would start discovery from, or `None` when the global args or
+/// environment can point git somewhere else
+fn discovery_dir(global_args: &[String]) -> Option<PathBuf> {
+    if DISCOVERY_ENV.iter().any(|var| env::var_os(var).is_some()) {
+        return None;
+    }
+    let mut dir = env::current_dir().ok()?;
+    let mut args = global_args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-C" => dir.push(args.next()?),
+            "-c" => {
+                args.next()?;
+            }
+            "--no-pager" | "-P" | "-p" | "--paginate" => {}
+            _ => return None,
+        }
+    }
+    dir.canonicalize().ok()
+}
+
+/// Modification times of `dir` and its ancestors up to the first one holding `.git`, and
+/// of that `.git`. `None` for directories inside the git dir, or when `.git` isn't found.
+fn discovery_mtimes(dir: &Path, git_dir: &Path) -> Option<Vec<(PathBuf, u128)>> {
+    if dir.starts_with(git_dir) {
+        return None;
+    }
+    let mut mtimes = Vec::new();
+    for ancestor in dir.ancestors() {
+        mtimes.push((ancestor.to_path_buf(), modified_nanos(ancestor)?));
+        let dot_git = ancestor.join(".git");
+        if let Some(mtime) = modified_nanos(&dot_git) {
+            mtimes.push((dot_git, mtime));
+            return Some(mtimes);
+        }
+    }
+    None
+}
+
+fn modified_nanos(path: &Path) -> Option<u128> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_nanos())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_discovery_mtimes_stop_at_dot_git() {
+        let root = std::env::temp_dir().join(format!("git-ai-discovery-{}", std::process::id()));
+        let nested = root.join("a").join("b");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        let root = root.canonicalize().unwrap();
+        let nested = nested.canonicalize().unwrap();
+
+        let mtimes = discovery_mtimes(&nested, &root.join(".git")).unwrap();
+        let paths: Vec<&PathBuf> = mtimes.iter().map(|(path, _)| path).collect();
+        assert_eq!(
+            paths,
+            vec![&nested, &root.join("a"), &root, &root.join(".git")]
+        );
+        assert!(discovery_mtimes(&root.join(".git"), &root.join(".git")).is_none());
+
+        let entry = CachedDiscovery {
+            git_dir: root.join(".git"),
+            mtimes,
+            discovered_at: 0,
+        };
+        assert!(is_fresh(&entry));
+
+        // A repository created in between would now be found first. Directory mtimes
+        // can be as coarse as a clock tick, so step past one.
+        std::thread::sleep(std::time::Duration::from_millis(50));
+        fs::create_dir_all(root.join("a").join(".git")).unwrap();
+        assert!(!is_fresh(&entry));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,292 @@
+//! Compatibility with Jujutsu (jj) repos colocated with git.
+//!
+//! jj rewrites commits constantly (describe, squash, rebase of descendants, ...) without going
+//! through git or our hooks, so those rewrites never reach the rewrite log. Every jj commit
+//! carries a change id that survives rewrites, which is enough to find them after the fact: a
+//! commit with an authorship note that jj no longer shows has been replaced by the visible
+//! commit with the same change id. The heads of jj's operation log, read from `.jj` without
+//! running jj, tell us cheaply whether anything happened since the last sync.
+
+use crate::authorship::rebase_authorship::rewrite_authorship_after_cherry_pick;
+use crate::config;
+use crate::error::GitAiError;
+use crate::git::refs::commits_with_ai_notes;
+use crate::git::repository::Repository;
+use crate::utils::debug_log;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// How many commit ids go into one `jj log` revset
+const REVSET_CHUNK_SIZE: usize = 100;
+
+/// True when the repo's working directory also holds a jj repo
+pub fn is_colocated(repo: &Repository) -> bool {
+    repo.workdir()
+        .map(|workdir| workdir.join(".jj").is_dir())
+        .unwrap_or(false)
+}
+
+/// Carry authorship notes and working logs over to the commits jj rewrote them into since the
+/// last sync. Returns the number of rewritten commits whose notes were remapped.
+pub fn sync_rewrites(repo: &Repository) -> Result<usize, GitAiError> {
+    if !is_colocated(repo) {
+        return Ok(0);
+    }
+
+    let operation = match operation_heads(&repo.workdir()?.join(".jj")) {
+        Some(heads) => heads,
+        None => current_operation(repo)?,
+    };
+    if repo.storage.read_jj_last_operation().as_deref() == Some(operation.as_str()) {
+        return Ok(0);
+    }
+
+    let visible = parse_change_commit_pairs(&jj(
+        repo,
+        &[
+            "log",
+            "--no-graph",
+            "-r",
+            "all()",
+            "-T",
+            r#"change_id ++ " " ++ commit_id ++ "\n""#,
+        ],
+    )?);
+    let visible_commits: HashSet<&String> = visible.iter().map(|(_, commit)| commit).collect();
+
+    let noted = commits_with_ai_notes(repo)?;
+    let working_log_bases = repo.storage.working_log_base_commits()?;
+    // Commits we hold state for that jj no longer shows: candidates for having been rewritten
+    let hidden: Vec<String> = noted
+        .iter()
+        .chain(working_log_bases.iter())
+        .filter(|commit| is_commit_id(commit) && !visible_commits.contains(commit))
+        .cloned()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+
+    let mut hidden_changes = HashMap::new();
+    for chunk in hidden.chunks(REVSET_CHUNK_SIZE) {
+        let revset = chunk.join(" | ");
+        // Commits jj has already garbage collected can't be resolved; skip their chunk
+        match jj(
+            repo,
+            &[
+                "log",
+                "--no-graph",
+                "-r",
+                &revset,
+                "-T",
+                r#"change_id ++ " " ++ commit_id ++ "\n""#,
+            ],
+        ) {
+            Ok(output) => {
+                for (change, commit) in parse_change_commit_pairs(&output) {
+                    hidden_changes.insert(commit, change);
+                }
+            }
+            Err(e) => debug_log(&format!("jj: could not resolve hidden commits: {}", e)),
+        }
+    }
+
+    let rewrites = plan_rewrites(&hidden_changes, &visible);
+
+    let mut remapped = 0;
+    for (old_commit, new_commit) in &rewrites {
+        if working_log_bases.contains(old_commit) {
+            repo.storage.move_working_log(old_commit, new_commit)?;
+        }
+        if noted.contains(old_commit) && !noted.contains(new_commit) {
+            rewrite_authorship_after_cherry_pick(
+                repo,
+                std::slice::from_ref(old_commit),
+                std::slice::from_ref(new_commit),
+                "",
+            )?;
+            remapped += 1;
+        }
+    }
+
+    repo.storage.write_jj_last_operation(&operation)?;
+    debug_log(&format!(
+        "jj: synced operation {}, remapped {} authorship notes",
+        operation, remapped
+    ));
+    Ok(remapped)
+}
+
+/// Pair each hidden commit (commit id -> change id) with the single visible commit that now
+/// has its change id. Changes that are divergent (several visible commits) or gone (abandoned)
+/// are skipped since there is no one commit to carry authorship to.
+pub fn plan_rewrites(
+    hidden_changes: &HashMap<String, String>,
+    visible: &[(String, String)],
+) -> Vec<(String, String)> {
+    let mut visible_by_change: HashMap<&str, Vec<&str>> = HashMap::new();
+    for (change, commit) in visible {
+        visible_by_change.entry(change).or_default().push(commit);
+    }
+
+    let mut rewrites: Vec<(String, String)> = hidden_changes
+        .iter()
+        .filter_map(
+            |(old_commit, change)| match visible_by_change.get(change.as_str()) {
+                Some(commits) if commits.len() == 1 && commits[0] != old_commit => {
+                    Some((old_commit.clone(), commits[0].to_string()))
+                }
+                _ => None,
+            },
+        )
+        .collect();
+    rewrites.sort();
+    rewrites
+}
+
+/// Working logs can be keyed by "initial" in a repo without commits
+fn is_commit_id(value: &str) -> bool {
+    matches!(value.len(), 40 | 64) && value.chars().all(|ch| ch.is_ascii_hexdigit())
+}
+
+/// Parse `<change id> <commit id>` lines
+fn parse_change_commit_pairs(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (change, commit) = line.trim().split_once(' ')?;
+            Some((change.to_string(), commit.to_string()))
+        })
+        .collect()
+}
+
+/// Id of the most recent jj operation
+/// The heads of jj's operation log, comma separated. jj keeps one empty file per head in
+/// `repo/op_heads/heads`, named by operation id; secondary workspaces have a `repo` file
+/// pointing at the main workspace's store instead. None when the layout isn't there.
+fn operation_heads(jj_dir: &Path) -> Option<String> {
+    let mut store = jj_dir.join("repo");
+    if store.is_file() {
+        store = jj_dir.join(fs::read_to_string(&store).ok()?.trim());
+    }
+    let mut heads: Vec<String> = fs::read_dir(store.join("op_heads").join("heads"))
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|name| !name.is_empty() && name.chars().all(|ch| ch.is_ascii_hexdigit()))
+        .collect();
+    heads.sort();
+    (!heads.is_empty()).then(|| heads.join(","))
+}
+
+/// The latest operation in jj's operation log, asked of jj itself
+fn current_operation(repo: &Repository) -> Result<String, GitAiError> {
+    let output = jj(
+        repo,
+        &[
+            "op",
+            "log",
+            "--no-graph",
+            "--limit",
+            "1",
+            "-T",
+            r#"id ++ "\n""#,
+        ],
+    )?;
+    output
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| GitAiError::Generic("jj op log returned no operations".to_string()))
+}
+
+/// Run a read-only jj command against the repo. `--ignore-working-copy` keeps jj from
+/// snapshotting the working copy, which would itself record a new operation.
+fn jj(repo: &Repository, args: &[&str]) -> Result<String, GitAiError> {
+    let workdir = repo.workdir()?;
+    let output = Command::new(config::Config::get().jj_cmd())
+        .arg("--repository")
+        .arg(&workdir)
+        .args(["--ignore-working-copy", "--no-pager", "--color=never"])
+        .args(args)
+        .output()?;
+
+    if !output.status.success() {
+        return Err(GitAiError::Generic(format!(
+            "jj {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(items: &[(&str, &str)]) -> Vec<(String, String)> {
+        items
+            .iter()
+            .map(|(a, b)| (a.to_string(), b.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_plan_rewrites_follows_change_ids() {
+        let hidden_changes: HashMap<String, String> = pairs(&[
+            ("old1", "kxyz"),
+            ("old2", "divergent"),
+            ("old3", "abandoned"),
+        ])
+        .into_iter()
+        .collect();
+        let visible = pairs(&[
+            ("kxyz", "new1"),
+            ("divergent", "new2a"),
+            ("divergent", "new2b"),
+            ("other", "new4"),
+        ]);
+
+        assert_eq!(
+            plan_rewrites(&hidden_changes, &visible),
+            pairs(&[("old1", "new1")])
+        );
+    }
+
+    #[test]
+    fn test_operation_heads_read_from_jj_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let jj_dir = dir.path().join(".jj");
+        assert_eq!(operation_heads(&jj_dir), None);
+
+        let heads = jj_dir.join("repo").join("op_heads").join("heads");
+        fs::create_dir_all(&heads).unwrap();
+        fs::write(heads.join("bb22"), "").unwrap();
+        fs::write(heads.join("aa11"), "").unwrap();
+        assert_eq!(operation_heads(&jj_dir), Some("aa11,bb22".to_string()));
+
+        // A secondary workspace points at the main workspace's store
+        let workspace_jj = dir.path().join("workspace").join(".jj");
+        fs::create_dir_all(&workspace_jj).unwrap();
+        fs::write(
+            workspace_jj.join("repo"),
+            jj_dir.join("repo").to_str().unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            operation_heads(&workspace_jj),
+            Some("aa11,bb22".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_change_commit_pairs() {
+        assert_eq!(
+            parse_change_commit_pairs("kxyz abc123\n\nqrst def456\n"),
+            pairs(&[("kxyz", "abc123"), ("qrst", "def456")])
+        );
+    }
+}
@@ -0,0 +1,209 @@
+//! Reading the patch mails `git am` imports, for the headers git-ai attributes them by
+
+/// Header a patch mail declares the AI tool that generated it with, e.g.
+/// `X-AI-Generated: claude; model=claude-sonnet-4`
+pub const AI_GENERATED_HEADER: &str = "X-AI-Generated";
+
+/// One patch mail of a series
+#[derive(Debug, Clone, PartialEq)]
+pub struct PatchMail {
+    pub subject: String,
+    pub message_id: Option<String>,
+    /// The message this one replied to, usually the series' cover letter
+    pub in_reply_to: Option<String>,
+    pub ai_generated: Option<AiDeclaration>,
+    /// The whole message, headers included
+    pub content: String,
+}
+
+/// The tool (and model, if given) an `X-AI-Generated` header names
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AiDeclaration {
+    pub tool: String,
+    pub model: Option<String>,
+}
+
+impl PatchMail {
+    /// The subject as `git am` turns it into the commit summary: without `Re:` and the
+    /// `[PATCH ...]` prefix, whitespace collapsed
+    pub fn summary(&self) -> String {
+        let mut subject = self.subject.trim();
+        loop {
+            if let Some(rest) = subject
+                .get(..3)
+                .filter(|prefix| prefix.eq_ignore_ascii_case("re:"))
+                .map(|_| &subject[3..])
+            {
+                subject = rest.trim_start();
+            } else if subject.starts_with('[')
+                && let Some(end) = subject.find(']')
+            {
+                subject = subject[end + 1..].trim_start();
+            } else {
+                break;
+            }
+        }
+        subject.split_whitespace().collect::<Vec<_>>().join(" ")
+    }
+
+    /// Where the patch sits in its series, from a `[PATCH 2/5]` subject prefix
+    pub fn position(&self) -> Option<String> {
+        let subject = self.subject.trim_start();
+        let prefix = subject.get(subject.find('[')? + 1..subject.find(']')?)?;
+        prefix
+            .split_whitespace()
+            .find(|word| {
+                word.split_once('/').is_some_and(|(n, m)| {
+                    !n.is_empty()
+                        && !m.is_empty()
+                        && n.chars().all(|c| c.is_ascii_digit())
+                        && m.chars().all(|c| c.is_ascii_digit())
+                })
+            })
+            .map(str::to_string)
+    }
+}
+
+/// Split an mbox into its messages. A file that doesn't start with a `From ` line holds a
+/// single message.
+pub fn split_mbox(content: &str) -> Vec<&str> {
+    if !content.starts_with("From ") {
+        return if content.trim().is_empty() {
+            Vec::new()
+        } else {
+            vec![content]
+        };
+    }
+
+    let mut starts = vec![0];
+    let mut offset = 0;
+    let mut previous_blank = false;
+    for line in content.split_inclusive('\n') {
+        if offset > 0 && previous_blank && line.starts_with("From ") {
+            starts.push(offset);
+        }
+        previous_blank = line.trim_end_matches(['\n', '\r']).is_empty();
+        offset += line.len();
+    }
+    starts.push(content.len());
+    starts
+        .windows(2)
+        .map(|bounds| &content[bounds[0]..bounds[1]])
+        .collect()
+}
+
+/// Read the headers git-ai cares about from one message
+pub fn parse_mail(message: &str) -> PatchMail {
+    let mut headers: Vec<(String, String)> = Vec::new();
+    for line in message.lines() {
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            break;
+        }
+        if line.starts_with([' ', '\t']) {
+            // A folded header continues the previous one
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':')
+            && !name.contains(' ')
+        {
+            headers.push((name.to_string(), value.trim().to_string()));
+        }
+    }
+    let header = |name: &str| {
+        headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.clone())
+    };
+
+    PatchMail {
+        subject: header("Subject").unwrap_or_default(),
+        message_id: header("Message-ID").map(|id| strip_angle_brackets(&id)),
+        in_reply_to: header("In-Reply-To").map(|id| strip_angle_brackets(&id)),
+        ai_generated: header(AI_GENERATED_HEADER).and_then(|value| parse_ai_declaration(&value)),
+        content: message.to_string(),
+    }
+}
+
+/// `claude; model=claude-sonnet-4` names a tool and model. `no`, `false` and `none` say the
+/// patch wasn't generated.
+fn parse_ai_declaration(value: &str) -> Option<AiDeclaration> {
+    let mut parts = value.split(';').map(str::trim);
+    let tool = parts.next()?.to_string();
+    if tool.is_empty() || ["no", "false", "none", "0"].contains(&tool.to_lowercase().as_str()) {
+        return None;
+    }
+    let model = parts
+        .filter_map(|part| part.split_once('='))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case("model"))
+        .map(|(_, model)| model.trim().to_string())
+        .filter(|model| !model.is_empty());
+    Some(AiDeclaration { tool, model })
+}
+
+fn strip_angle_brackets(id: &str) -> String {
+    id.trim()
+        .trim_start_matches('<')
+        .trim_end_matches('>')
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERIES: &str = "From 1111111111111111111111111111111111111111 Mon Sep 17 00:00:00 2001
+From: Dev <dev@example.com>
+Subject: [PATCH v2 1/2] Add the
+ parser
+Message-ID: <1.series@example.com>
+X-AI-Generated: claude; model=claude-sonnet-4
+
+---
+ parser.rs | 1 +
+
+From 2222222222222222222222222222222222222222 Mon Sep 17 00:00:00 2001
+From: Dev <dev@example.com>
+Subject: [PATCH v2 2/2] Use it
+In-Reply-To: <0.series@example.com>
+X-AI-Generated: no
+
+body
+";
+
+    #[test]
+    fn test_split_and_parse_series() {
+        let messages = split_mbox(SERIES);
+        assert_eq!(messages.len(), 2);
+
+        let first = parse_mail(messages[0]);
+        assert_eq!(first.subject, "[PATCH v2 1/2] Add the parser");
+        assert_eq!(first.summary(), "Add the parser");
+        assert_eq!(first.position().as_deref(), Some("1/2"));
+        assert_eq!(first.message_id.as_deref(), Some("1.series@example.com"));
+        assert_eq!(
+            first.ai_generated,
+            Some(AiDeclaration {
+                tool: "claude".to_string(),
+                model: Some("claude-sonnet-4".to_string()),
+            })
+        );
+
+        let second = parse_mail(messages[1]);
+        assert_eq!(second.summary(), "Use it");
+        assert_eq!(second.in_reply_to.as_deref(), Some("0.series@example.com"));
+        assert_eq!(second.ai_generated, None);
+    }
+
+    #[test]
+    fn test_single_message_without_from_line() {
+        let messages = split_mbox("Subject: Re: [RFC] Tweak\n\nbody\n");
+        assert_eq!(messages.len(), 1);
+        let mail = parse_mail(messages[0]);
+        assert_eq!(mail.summary(), "Tweak");
+        assert_eq!(mail.position(), None);
+    }
+}
@@ -0,0 +1,309 @@
+//! Housekeeping for git-ai's storage, and the hook that lets git's own maintenance run it.
+//!
+//! git's maintenance scheduler only runs git's built-in tasks, so git-ai can't add tasks to it.
+//! Instead `git-ai maintenance register` installs a `pre-auto-gc` hook. git runs that hook
+//! whenever its auto maintenance (after commits, fetches, merges...) decides the repository
+//! needs a gc. `git gc` and `git maintenance run` going through git-ai run the tasks as well.
+
+use crate::error::GitAiError;
+use crate::git::detached::{DETACHED_NOTE_GRACE_SECS, PruneSummary, prune_detached_notes};
+use crate::git::repository::{Repository, exec_git};
+use crate::utils::debug_log;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// How long an unreferenced blob is kept. A checkpoint writes its blobs before the checkpoint
+/// line that refers to them.
+pub const BLOB_GRACE_SECS: u64 = 60 * 60;
+
+/// Marks the line git-ai adds to the `pre-auto-gc` hook
+const HOOK_MARKER: &str = "# git-ai maintenance";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaintenanceTask {
+    /// Prune notes of abandoned detached commits and working logs of commits that are gone
+    Gc,
+    /// Remove blobs no checkpoint refers to anymore and trim the rewrite log
+    Compaction,
+    /// Drop stale entries from the file state caches checkpoints use to skip unchanged files
+    IndexRefresh,
+}
+
+impl MaintenanceTask {
+    pub const ALL: [MaintenanceTask; 3] = [
+        MaintenanceTask::Gc,
+        MaintenanceTask::Compaction,
+        MaintenanceTask::IndexRefresh,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            MaintenanceTask::Gc => "gc",
+            MaintenanceTask::Compaction => "compaction",
+            MaintenanceTask::IndexRefresh => "index-refresh",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<MaintenanceTask> {
+        MaintenanceTask::ALL
+            .into_iter()
+            .find(|task| task.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MaintenanceSummary {
+    pub detached: PruneSummary,
+    pub working_logs_removed: usize,
+    pub blobs_removed: usize,
+    pub rewrite_events_dropped: usize,
+    pub cache_entries_dropped: usize,
+}
+
+/// What `git-ai gc` removed, or would remove with `--dry-run`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct GcReport {
+    pub working_logs: usize,
+    pub blobs: usize,
+    pub rewrite_events: usize,
+    /// Bytes of `.git/ai` freed by all of the above
+    pub bytes: u64,
+}
+
+/// Run `tasks` in order. `now` is in seconds since the epoch.
+pub fn run_tasks(
+    repo: &Repository,
+    tasks: &[MaintenanceTask],
+    now: u64,
+) -> Result<MaintenanceSummary, GitAiError> {
+    let mut summary = MaintenanceSummary::default();
+    for task in tasks {
+        match task {
+            MaintenanceTask::Gc => {
+                summary.detached = prune_detached_notes(repo, DETACHED_NOTE_GRACE_SECS, now)?;
+                summary.working_logs_removed = remove_orphaned_working_logs(repo)?;
+            }
+            MaintenanceTask::Compaction => {
+                let older_than =
+                    UNIX_EPOCH + Duration::from_secs(now.saturating_sub(BLOB_GRACE_SECS));
+                for base_commit in repo.storage.working_log_base_commits()? {
+                    let working_log = repo.storage.working_log_for_base_commit(&base_commit);
+                    summary.blobs_removed += working_log.remove_unreferenced_blobs(older_than)?;
+                }
+                summary.rewrite_events_dropped = repo.storage.compact_rewrite_log(false)?.0;
+            }
+            MaintenanceTask::IndexRefresh => {
+                for base_commit in repo.storage.working_log_base_commits()? {
+                    let working_log = repo.storage.working_log_for_base_commit(&base_commit);
+                    summary.cache_entries_dropped += working_log.refresh_file_state_cache()?;
+                }
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Delete working logs whose base commit no longer exists, e.g. after an amended commit was
+/// garbage collected. Nothing can be committed on top of them anymore.
+fn remove_orphaned_working_logs(repo: &Repository) -> Result<usize, GitAiError> {
+    let orphaned = orphaned_working_logs(repo)?;
+    for base_commit in &orphaned {
+        debug_log(&format!(
+            "Removing working log of missing commit {}",
+            base_commit
+        ));
+        repo.storage
+            .delete_working_log_for_base_commit(base_commit)?;
+    }
+    Ok(orphaned.len())
+}
+
+/// Base commits of working logs whose commit no longer exists
+fn orphaned_working_logs(repo: &Repository) -> Result<Vec<String>, GitAiError> {
+    let mut orphaned: Vec<String> = repo
+        .storage
+        .working_log_base_commits()?
+        .into_iter()
+        // "initial" and anything else that isn't an object id could resolve as a ref name
+        .filter(|base_commit| {
+            is_object_id(base_commit) && repo.find_commit(base_commit.clone()).is_err()
+        })
+        .collect();
+    orphaned.sort();
+    Ok(orphaned)
+}
+
+/// Reclaim space in `.git/ai`: delete working logs of commits that are gone, blobs no
+/// checkpoint refers to, and rewrite log events nothing reads anymore. With `grace`, blobs
+/// written in the last `BLOB_GRACE_SECS` are kept. `now` is in seconds since the epoch. With
+/// `dry_run` nothing is deleted.
+pub fn gc(repo: &Repository, now: u64, grace: bool, dry_run: bool) -> Result<GcReport, GitAiError> {
+    let mut report = GcReport::default();
+
+    let orphaned = orphaned_working_logs(repo)?;
+    for base_commit in &orphaned {
+        report.bytes += dir_size(&repo.storage.working_logs.join(base_commit));
+        if !dry_run {
+            repo.storage
+                .delete_working_log_for_base_commit(base_commit)?;
+        }
+    }
+    report.working_logs = orphaned.len();
+
+    let older_than = if grace {
+        UNIX_EPOCH + Duration::from_secs(now.saturating_sub(BLOB_GRACE_SECS))
+    } else {
+        UNIX_EPOCH + Duration::from_secs(now + 1)
+    };
+    for base_commit in repo.storage.working_log_base_commits()? {
+        if orphaned.contains(&base_commit) {
+            continue;
+        }
+        let working_log = repo.storage.working_log_for_base_commit(&base_commit);
+        for (path, size) in working_log.unreferenced_blobs(older_than)? {
+            report.blobs += 1;
+            report.bytes += size;
+            if !dry_run {
+                fs::remove_file(path)?;
+            }
+        }
+    }
+
+    let (events, bytes) = repo.storage.compact_rewrite_log(dry_run)?;
+    report.rewrite_events = events;
+    report.bytes += bytes;
+
+    Ok(report)
+}
+
+fn dir_size(dir: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .map(|entry| match entry.metadata() {
+            Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        })
+        .sum()
+}
+
+fn is_object_id(name: &str) -> bool {
+    matches!(name.len(), 40 | 64) && name.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+/// Path of the repository's `pre-auto-gc` hook, honoring `core.hooksPath`
+pub fn pre_auto_gc_hook_path(repo: &Repository) -> Result<PathBuf, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("rev-parse".to_string());
+    args.push("--git-path".to_string());
+    args.push("hooks/pre-auto-gc".to_string());
+    let output = exec_git(&args)?;
+    let path = PathBuf::from(String::from_utf8(output.stdout)?.trim());
+    if path.is_absolute() {
+        Ok(path)
+    } else {
+        Ok(repo.workdir()?.join(path))
+    }
+}
+
+/// Add the git-ai line to the `pre-auto-gc` hook, creating the hook if needed. The line goes
+/// right after the shebang, so an existing hook's exit status still decides whether git gcs.
+/// Returns false if the hook already had it.
+pub fn install_hook(hook_path: &Path, binary_path: &Path) -> Result<bool, GitAiError> {
+    let existing = fs::read_to_string(hook_path).unwrap_or_default();
+    if existing.lines().any(|line| line.ends_with(HOOK_MARKER)) {
+        return Ok(false);
+    }
+
+    let hook_line = format!(
+        "\"{}\" maintenance run --quiet || true {}",
+        binary_path.display(),
+        HOOK_MARKER
+    );
+    let content = match existing.split_once('\n') {
+        Some((shebang, rest)) if shebang.starts_with("#!") => {
+            format!("{}\n{}\n{}", shebang, hook_line, rest)
+        }
+        _ if existing.is_empty() => format!("#!/bin/sh\n{}\n", hook_line),
+        _ => format!("#!/bin/sh\n{}\n{}", hook_line, existing),
+    };
+
+    if let Some(parent) = hook_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(hook_path, content)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(hook_path, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(true)
+}
+
+/// Remove the git-ai line from the `pre-auto-gc` hook, and the hook itself if nothing else is
+/// left in it. Returns false if the hook didn't have it.
+pub fn uninstall_hook(hook_path: &Path) -> Result<bool, GitAiError> {
+    let Ok(existing) = fs::read_to_string(hook_path) else {
+        return Ok(false);
+    };
+    if !existing.lines().any(|line| line.ends_with(HOOK_MARKER)) {
+        return Ok(false);
+    }
+
+    let remaining: Vec<&str> = existing
+        .lines()
+        .filter(|line| !line.ends_with(HOOK_MARKER))
+        .collect();
+    if remaining
+        .iter()
+        .all(|line| line.trim().is_empty() || line.starts_with("#!"))
+    {
+        fs::remove_file(hook_path)?;
+    } else {
+        fs::write(hook_path, format!("{}\n", remaining.join("\n")))?;
+    }
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_line_keeps_existing_hook_and_is_removed_cleanly() {
+        let dir = tempfile::tempdir().unwrap();
+        let hook_path = dir.path().join("hooks").join("pre-auto-gc");
+        let binary = Path::new("/opt/git-ai/bin/git-ai");
+
+        assert!(install_hook(&hook_path, binary).unwrap());
+        assert!(!install_hook(&hook_path, binary).unwrap());
+        assert!(uninstall_hook(&hook_path).unwrap());
+        assert!(!hook_path.exists());
+
+        fs::write(&hook_path, "#!/bin/bash\nexit 1\n").unwrap();
+        assert!(install_hook(&hook_path, binary).unwrap());
+        let content = fs::read_to_string(&hook_path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines[0], "#!/bin/bash");
+        assert!(lines[1].starts_with("\"/opt/git-ai/bin/git-ai\" maintenance run"));
+        assert_eq!(lines[2], "exit 1");
+
+        assert!(uninstall_hook(&hook_path).unwrap());
+        assert_eq!(
+            fs::read_to_string(&hook_path).unwrap(),
+            "#!/bin/bash\nexit 1\n"
+        );
+    }
+
+    #[test]
+    fn test_task_names_round_trip() {
+        for task in MaintenanceTask::ALL {
+            assert_eq!(MaintenanceTask::from_name(task.name()), Some(task));
+        }
+        assert_eq!(MaintenanceTask::from_name("repack"), None);
+    }
+}
@@ -1,8 +1,17 @@
+pub mod backend;
 pub mod cli_parser;
+pub mod detached;
+pub mod diff_parser;
 pub mod diff_tree_to_tree;
+pub mod discovery_cache;
+pub mod jj;
+pub mod mailbox;
+pub mod maintenance;
+pub mod partial_clone;
 pub mod refs;
 pub mod repository;
-pub use repository::{find_repository, find_repository_in_path};
+pub use repository::{find_repository, find_repository_cached, find_repository_in_path};
+pub mod repo_registry;
 pub mod repo_storage;
 pub mod rewrite_log;
 pub mod status;
@@ -0,0 +1,239 @@
+//! Partial clones (`git clone --filter=blob:none`) leave blobs on a promisor remote until
+//! something reads them, and git fetches each one on its own the first time it's read. Blame
+//! walks every version of a file and rewrites read the files of every commit they carry over,
+//! so attribution prefetches the blobs it's about to read in one batch instead, and reads what
+//! still can't be fetched (offline, say) as unknown rather than failing.
+
+use crate::error::GitAiError;
+use crate::git::refs::note_blobs;
+use crate::git::repository::{Repository, exec_git, exec_git_stdin};
+use crate::utils::debug_log;
+use std::collections::HashSet;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// The remote a partial clone fetches missing objects from, or `None` when every object is
+/// local
+pub fn promisor_remote(repo: &Repository) -> Option<String> {
+    let mut args = repo.global_args_for_exec();
+    args.push("config".to_string());
+    args.push("--get-regexp".to_string());
+    args.push(r"^(extensions\.partialclone|remote\..*\.promisor)$".to_string());
+    let output = exec_git(&args).ok()?;
+    let config = String::from_utf8(output.stdout).ok()?;
+
+    let mut promisor = None;
+    for (key, value) in config.lines().filter_map(|line| line.split_once(' ')) {
+        let key = key.to_ascii_lowercase();
+        if key == "extensions.partialclone" {
+            return Some(value.trim().to_string());
+        }
+        if value.trim() == "true"
+            && let Some(remote) = key
+                .strip_prefix("remote.")
+                .and_then(|rest| rest.strip_suffix(".promisor"))
+        {
+            promisor.get_or_insert_with(|| remote.to_string());
+        }
+    }
+    promisor
+}
+
+/// Which of `oids` aren't in the local object store, found without fetching any of them
+pub fn missing_objects(repo: &Repository, oids: &[String]) -> Vec<String> {
+    let mut missing = Vec::new();
+    let mut rest = oids;
+    while !rest.is_empty() {
+        // Older git gives up at the first object it would have to fetch: that one is
+        // missing, and the check goes on after it
+        let answered = batch_check(repo, rest, &mut missing);
+        match rest.get(answered) {
+            Some(oid) => {
+                missing.push(oid.clone());
+                rest = &rest[answered + 1..];
+            }
+            None => break,
+        }
+    }
+    missing
+}
+
+/// `git cat-file --batch-check` over `oids` with lazy fetching off. Returns how many it
+/// answered before it stopped.
+fn batch_check(repo: &Repository, oids: &[String], missing: &mut Vec<String>) -> usize {
+    let mut args = repo.global_args_for_exec();
+    args.push("cat-file".to_string());
+    args.push("--batch-check".to_string());
+    let child = Command::new(crate::config::Config::get().git_cmd())
+        .args(&args)
+        .env("GIT_NO_LAZY_FETCH", "1")
+        // Git before 2.44 ignores GIT_NO_LAZY_FETCH; no transport keeps it offline all the same
+        .env("GIT_ALLOW_PROTOCOL", "none")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn();
+    let Ok(mut child) = child else {
+        return oids.len();
+    };
+
+    let input = oids.join("\n") + "\n";
+    let stdin = child.stdin.take();
+    let writer = std::thread::spawn(move || {
+        if let Some(mut stdin) = stdin {
+            let _ = stdin.write_all(input.as_bytes());
+        }
+    });
+    let Ok(output) = child.wait_with_output() else {
+        return oids.len();
+    };
+    let _ = writer.join();
+
+    let mut answered = 0;
+    for line in String::from_utf8_lossy(&output.stdout).lines() {
+        if let Some(oid) = line.strip_suffix(" missing") {
+            missing.push(oid.to_string());
+        }
+        answered += 1;
+    }
+    answered
+}
+
+/// Fetch whichever of `oids` are missing from the promisor remote, all in one fetch. Returns
+/// how many were fetched; nothing is fetched outside partial clones.
+pub fn prefetch_objects(repo: &Repository, oids: &[String]) -> Result<usize, GitAiError> {
+    let Some(remote) = promisor_remote(repo) else {
+        return Ok(0);
+    };
+    let missing = missing_objects(repo, oids);
+    if missing.is_empty() {
+        return Ok(0);
+    }
+
+    // The same fetch git runs for one lazily fetched object, given all of them at once
+    let mut args = repo.global_args_for_exec();
+    args.extend(
+        [
+            "-c",
+            "fetch.negotiationAlgorithm=noop",
+            "fetch",
+            &remote,
+            "--no-tags",
+            "--no-write-fetch-head",
+            "--recurse-submodules=no",
+            "--filter=blob:none",
+            "--stdin",
+        ]
+        .map(str::to_string),
+    );
+    exec_git_stdin(&args, (missing.join("\n") + "\n").as_bytes())?;
+    debug_log(&format!(
+        "Prefetched {} objects from {}",
+        missing.len(),
+        remote
+    ));
+    Ok(missing.len())
+}
+
+/// Every blob `path` had in the history of `rev` that blame reads, following renames
+pub fn file_history_blobs(
+    repo: &Repository,
+    rev: &str,
+    path: &str,
+) -> Result<Vec<String>, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.extend(
+        [
+            "log",
+            "--follow",
+            "--format=",
+            "--raw",
+            "--no-abbrev",
+            rev,
+            "--",
+            path,
+        ]
+        .map(str::to_string),
+    );
+    let output = exec_git(&args)?;
+    Ok(raw_diff_blobs(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// The blobs on both sides of the changes `commits` made to their first parents
+pub fn commit_blobs(repo: &Repository, commits: &[String]) -> Result<Vec<String>, GitAiError> {
+    if commits.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut args = repo.global_args_for_exec();
+    args.extend(
+        [
+            "diff-tree",
+            "--stdin",
+            "-r",
+            "--root",
+            "--raw",
+            "--no-abbrev",
+            "--no-commit-id",
+            "--no-renames",
+        ]
+        .map(str::to_string),
+    );
+    let output = exec_git_stdin(&args, (commits.join("\n") + "\n").as_bytes())?;
+    Ok(raw_diff_blobs(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Prefetch the authorship notes of `commits`, which a partial clone fetches without their
+/// blobs like any other
+pub fn prefetch_notes(repo: &Repository, commits: &[String]) -> Result<usize, GitAiError> {
+    let notes = note_blobs(repo, &repo.notes_ref())?;
+    let blobs: Vec<String> = commits
+        .iter()
+        .filter_map(|commit| notes.get(commit).cloned())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    prefetch_objects(repo, &blobs)
+}
+
+/// The old and new blob of each `:<mode> <mode> <old> <new> <status>\t<path>` line, leaving
+/// out the null id of added and deleted files and gitlinks
+fn raw_diff_blobs(raw: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    raw.lines()
+        .filter_map(|line| line.strip_prefix(':'))
+        .filter_map(|line| line.split('\t').next())
+        .flat_map(|meta| {
+            let fields: Vec<&str> = meta.split_whitespace().collect();
+            match fields.as_slice() {
+                [old_mode, new_mode, old, new, ..] => vec![(*old_mode, *old), (*new_mode, *new)],
+                _ => Vec::new(),
+            }
+        })
+        .filter(|(mode, oid)| *mode != "160000" && oid.bytes().any(|b| b != b'0'))
+        .filter(|(_, oid)| seen.insert(oid.to_string()))
+        .map(|(_, oid)| oid.to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_raw_diff_blobs() {
+        let raw = "\
+:100644 100644 1111111111111111111111111111111111111111 2222222222222222222222222222222222222222 M\tsrc/lib.rs
+:000000 100644 0000000000000000000000000000000000000000 3333333333333333333333333333333333333333 A\tnew.rs
+:160000 160000 4444444444444444444444444444444444444444 5555555555555555555555555555555555555555 M\tvendor/sub
+:100644 000000 2222222222222222222222222222222222222222 0000000000000000000000000000000000000000 D\told.rs
+";
+        assert_eq!(
+            raw_diff_blobs(raw),
+            vec![
+                "1111111111111111111111111111111111111111",
+                "2222222222222222222222222222222222222222",
+                "3333333333333333333333333333333333333333",
+            ]
+        );
+    }
+}
@@ -1,14 +1,23 @@
-use crate::authorship::authorship_log_serialization::{AUTHORSHIP_LOG_VERSION, AuthorshipLog};
+use crate::authorship::authorship_log_serialization::{
+    AUTHORSHIP_LOG_VERSION, AuthorshipLog, SchemaCompatibility,
+};
 use crate::authorship::working_log::Checkpoint;
 use crate::error::GitAiError;
-use crate::git::repository::{Repository, exec_git, exec_git_stdin};
+use crate::git::repository::{Repository, exec_git, exec_git_stdin, exec_git_stdin_with_env};
 use crate::utils::debug_log;
 use serde_json;
 use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 
-// Modern refspecs without force to enable proper merging
-pub const AI_AUTHORSHIP_REFNAME: &str = "ai";
-pub const AI_AUTHORSHIP_PUSH_REFSPEC: &str = "refs/notes/ai:refs/notes/ai";
+/// Set once the newer-major warning has been printed, so a walk over many notes warns once
+static WARNED_NEWER_MAJOR: AtomicBool = AtomicBool::new(false);
+
+/// Refspec pushing the repo's notes ref to the same ref on a remote. No force, so concurrent
+/// updates get merged instead of overwritten.
+pub fn authorship_push_refspec(repo: &Repository) -> String {
+    let notes_ref = repo.notes_ref();
+    format!("{}:{}", notes_ref, notes_ref)
+}
 
 pub fn notes_add(
     repo: &Repository,
@@ -17,7 +26,7 @@ pub fn notes_add(
 ) -> Result<(), GitAiError> {
     let mut args = repo.global_args_for_exec();
     args.push("notes".to_string());
-    args.push("--ref=ai".to_string());
+    args.push(format!("--ref={}", repo.notes_ref()));
     args.push("add".to_string());
     args.push("-f".to_string()); // Always force overwrite
     args.push("-F".to_string());
@@ -29,6 +38,22 @@ pub fn notes_add(
     Ok(())
 }
 
+/// Remove the authorship notes of the given commits. Commits without a note are skipped.
+pub fn notes_remove(repo: &Repository, commit_shas: &[String]) -> Result<(), GitAiError> {
+    if commit_shas.is_empty() {
+        return Ok(());
+    }
+    let mut args = repo.global_args_for_exec();
+    args.push("notes".to_string());
+    args.push(format!("--ref={}", repo.notes_ref()));
+    args.push("remove".to_string());
+    args.push("--ignore-missing".to_string());
+    args.extend(commit_shas.iter().cloned());
+
+    exec_git(&args)?;
+    Ok(())
+}
+
 // Check which commits from the given list have authorship notes.
 // Uses git cat-file --batch-check to efficiently check multiple commits in one invocation.
 // Returns a Vec of CommitAuthorship for each commit.
@@ -42,7 +67,7 @@ pub enum CommitAuthorship {
     Log {
         sha: String,
         git_author: String,
-        authorship_log: AuthorshipLog,
+        authorship_log: Box<AuthorshipLog>,
     },
 }
 pub fn get_commits_with_notes_from_list(
@@ -103,7 +128,7 @@ pub fn get_commits_with_notes_from_list(
             result.push(CommitAuthorship::Log {
                 sha: sha.clone(),
                 git_author,
-                authorship_log,
+                authorship_log: Box::new(authorship_log),
             });
         } else {
             result.push(CommitAuthorship::NoLog {
@@ -116,11 +141,183 @@ pub fn get_commits_with_notes_from_list(
     Ok(result)
 }
 
+/// All commits that currently have an authorship note, listed in a single git call.
+/// Returns an empty set when the notes ref doesn't exist yet.
+pub fn commits_with_ai_notes(repo: &Repository) -> Result<HashSet<String>, GitAiError> {
+    if !ref_exists(repo, &repo.notes_ref()) {
+        return Ok(HashSet::new());
+    }
+
+    let mut args = repo.global_args_for_exec();
+    args.push("notes".to_string());
+    args.push(format!("--ref={}", repo.notes_ref()));
+    args.push("list".to_string());
+
+    let output = exec_git(&args)?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    // Each line is "<note blob sha> <annotated object sha>"
+    Ok(stdout
+        .lines()
+        .filter_map(|line| line.split_whitespace().nth(1))
+        .map(|sha| sha.to_string())
+        .collect())
+}
+
+/// The note blob of every commit annotated in `notes_ref`, by commit
+pub fn note_blobs(
+    repo: &Repository,
+    notes_ref: &str,
+) -> Result<HashMap<String, String>, GitAiError> {
+    if !ref_exists(repo, notes_ref) {
+        return Ok(HashMap::new());
+    }
+
+    let mut args = repo.global_args_for_exec();
+    args.push("notes".to_string());
+    args.push(format!("--ref={}", notes_ref));
+    args.push("list".to_string());
+
+    let output = exec_git(&args)?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    // Each line is "<note blob sha> <annotated object sha>"
+    Ok(stdout
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .map(|(blob, commit)| (commit.to_string(), blob.to_string()))
+        .collect())
+}
+
+/// The commit the notes ref currently points at, or `None` when no notes exist yet
+pub fn notes_ref_tip(repo: &Repository) -> Option<String> {
+    let mut args = repo.global_args_for_exec();
+    args.push("rev-parse".to_string());
+    args.push("--verify".to_string());
+    args.push("-q".to_string());
+    args.push(repo.notes_ref());
+
+    let output = exec_git(&args).ok()?;
+    let tip = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    if tip.is_empty() { None } else { Some(tip) }
+}
+
+/// HEAD and the notes ref tip, read in one git call. `None` for an unborn HEAD or when no
+/// notes exist yet.
+pub fn head_and_notes_tips(repo: &Repository) -> (Option<String>, Option<String>) {
+    let notes_ref = repo.notes_ref();
+    let mut args = repo.global_args_for_exec();
+    args.push("show-ref".to_string());
+    args.push("--head".to_string());
+    args.push(notes_ref.clone());
+
+    let Some(stdout) = exec_git(&args)
+        .ok()
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+    else {
+        return (None, None);
+    };
+    // Each line is "<sha> <ref>"; the pattern also matches refs merely ending in the notes ref
+    let tip = |name: &str| {
+        stdout
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .find(|(_, ref_name)| *ref_name == name)
+            .map(|(sha, _)| sha.to_string())
+    };
+    (tip("HEAD"), tip(&notes_ref))
+}
+
+/// Point the notes ref at `tip`, or delete it when `None`, provided it still points at
+/// `expected` (`None`: doesn't exist). Fails without touching the ref when it has moved.
+pub fn set_notes_ref_tip(
+    repo: &Repository,
+    tip: Option<&str>,
+    expected: Option<&str>,
+) -> Result<(), GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    args.push("update-ref".to_string());
+    match tip {
+        Some(tip) => {
+            args.push(repo.notes_ref());
+            args.push(tip.to_string());
+        }
+        None => {
+            args.push("-d".to_string());
+            args.push(repo.notes_ref());
+        }
+    }
+    // An empty old value makes git check that the ref doesn't exist
+    args.push(expected.unwrap_or_default().to_string());
+    exec_git(&args)?;
+    Ok(())
+}
+
+/// Replace the notes ref's history with one parentless commit holding its current notes, so
+/// earlier versions of rewritten notes are no longer reachable from it or its reflog. Their
+/// objects stay in the object store until `git gc` prunes them.
+pub fn squash_notes_history(repo: &Repository, message: &str) -> Result<(), GitAiError> {
+    let Some(tip) = notes_ref_tip(repo) else {
+        return Ok(());
+    };
+
+    let mut args = repo.global_args_for_exec();
+    args.push("commit-tree".to_string());
+    args.push(format!("{}^{{tree}}", tip));
+    args.push("-m".to_string());
+    args.push(message.to_string());
+    let commit = String::from_utf8(exec_git(&args)?.stdout)?;
+    set_notes_ref_tip(repo, Some(commit.trim()), Some(&tip))?;
+
+    let mut args = repo.global_args_for_exec();
+    args.push("reflog".to_string());
+    args.push("expire".to_string());
+    args.push("--expire=now".to_string());
+    args.push("--expire-unreachable=now".to_string());
+    args.push(repo.notes_ref());
+    exec_git(&args)?;
+    Ok(())
+}
+
+/// Commits whose authorship note was added or changed between two tips of the notes ref
+pub fn commits_with_notes_changed(
+    repo: &Repository,
+    old_tip: Option<&str>,
+    new_tip: &str,
+) -> Result<Vec<String>, GitAiError> {
+    let mut args = repo.global_args_for_exec();
+    match old_tip {
+        Some(old_tip) => {
+            args.push("diff-tree".to_string());
+            args.push("-r".to_string());
+            args.push("--name-only".to_string());
+            args.push("--diff-filter=AM".to_string());
+            args.push(old_tip.to_string());
+        }
+        None => {
+            args.push("ls-tree".to_string());
+            args.push("-r".to_string());
+            args.push("--name-only".to_string());
+        }
+    }
+    args.push(new_tip.to_string());
+
+    let output = exec_git(&args)?;
+    let stdout = String::from_utf8(output.stdout)?;
+
+    // Notes trees may fan out paths (ab/cdef...), the joined path is the annotated commit
+    Ok(stdout
+        .lines()
+        .map(|path| path.replace('/', ""))
+        .filter(|sha| !sha.is_empty())
+        .collect())
+}
+
 // Show an authorship note and return its JSON content if found, or None if it doesn't exist.
 pub fn show_authorship_note(repo: &Repository, commit_sha: &str) -> Option<String> {
     let mut args = repo.global_args_for_exec();
     args.push("notes".to_string());
-    args.push("--ref=ai".to_string());
+    args.push(format!("--ref={}", repo.notes_ref()));
     args.push("show".to_string());
     args.push(commit_sha.to_string());
 
@@ -138,9 +335,24 @@ pub fn show_authorship_note(repo: &Repository, commit_sha: &str) -> Option<Strin
 pub fn get_authorship(repo: &Repository, commit_sha: &str) -> Option<AuthorshipLog> {
     let content = show_authorship_note(repo, commit_sha)?;
     let authorship_log = AuthorshipLog::deserialize_from_string(&content).ok()?;
+    if authorship_log.metadata.schema_compatibility() == SchemaCompatibility::NewerMajor {
+        warn_newer_major(commit_sha, &authorship_log.metadata.schema_version);
+    }
     Some(authorship_log)
 }
 
+fn warn_newer_major(commit_sha: &str, schema_version: &str) {
+    if WARNED_NEWER_MAJOR.swap(true, Ordering::Relaxed) {
+        return;
+    }
+    eprintln!(
+        "Warning: the authorship note on {} is {}, newer than this git-ai reads ({}). Upgrade git-ai; `git-ai compat check` lists affected notes.",
+        &commit_sha[..7.min(commit_sha.len())],
+        schema_version,
+        AUTHORSHIP_LOG_VERSION
+    );
+}
+
 #[allow(dead_code)]
 pub fn get_reference_as_working_log(
     repo: &Repository,
@@ -169,12 +381,22 @@ pub fn get_reference_as_authorship_log_v3(
         }
     };
 
-    // Check version compatibility
-    if authorship_log.metadata.schema_version != AUTHORSHIP_LOG_VERSION {
-        return Err(GitAiError::Generic(format!(
-            "Unsupported authorship log version: {} (expected: {})",
-            authorship_log.metadata.schema_version, AUTHORSHIP_LOG_VERSION
-        )));
+    // Notes from a newer minor only add fields, which deserializing already dropped. Callers
+    // rewrite these logs onto new commits, so anything else is refused rather than guessed at.
+    match authorship_log.metadata.schema_compatibility() {
+        SchemaCompatibility::Supported | SchemaCompatibility::NewerMinor => {}
+        SchemaCompatibility::NewerMajor => {
+            return Err(GitAiError::Generic(format!(
+                "Authorship log version {} is newer than this git-ai supports ({}), upgrade git-ai",
+                authorship_log.metadata.schema_version, AUTHORSHIP_LOG_VERSION
+            )));
+        }
+        SchemaCompatibility::Unsupported => {
+            return Err(GitAiError::Generic(format!(
+                "Unsupported authorship log version: {} (expected: {})",
+                authorship_log.metadata.schema_version, AUTHORSHIP_LOG_VERSION
+            )));
+        }
     }
 
     Ok(authorship_log)
@@ -221,6 +443,41 @@ mod tests {
         );
         assert!(non_existent_content.is_none());
     }
+
+    #[test]
+    fn test_write_notes_onto_replaces_notes_and_keeps_the_rest() {
+        let tmp_repo = TmpRepo::new().expect("Failed to create tmp repo");
+        let repo = tmp_repo.gitai_repo();
+        let mut commits = Vec::new();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            tmp_repo.write_file(name, "line\n", true).unwrap();
+            tmp_repo.commit_with_message(name).unwrap();
+            let commit = tmp_repo.get_head_commit_sha().unwrap();
+            notes_add(repo, &commit, &format!("note for {}", name)).unwrap();
+            commits.push(commit);
+        }
+        let base = note_blobs(repo, &repo.notes_ref()).unwrap();
+
+        let mut args = repo.global_args_for_exec();
+        args.extend(["hash-object", "-w", "--stdin"].map(String::from));
+        let blob = String::from_utf8(exec_git_stdin(&args, b"replaced").unwrap().stdout).unwrap();
+        let blob = blob.trim().to_string();
+
+        // A base with a note for every commit, one of them replaced
+        let dest = "refs/notes/ai-write-test";
+        write_notes_onto(repo, Some(&repo.notes_ref()), dest, &[(&commits[1], &blob)]).unwrap();
+        let written = note_blobs(repo, dest).unwrap();
+        assert_eq!(written.len(), 3);
+        assert_eq!(written[&commits[0]], base[&commits[0]]);
+        assert_eq!(written[&commits[1]], blob);
+        assert_eq!(written[&commits[2]], base[&commits[2]]);
+
+        // Without a base, only the given notes
+        write_notes_onto(repo, None, dest, &[(&commits[2], &blob)]).unwrap();
+        let written = note_blobs(repo, dest).unwrap();
+        assert_eq!(written.len(), 1);
+        assert_eq!(written[&commits[2]], blob);
+    }
 }
 
 /// Sanitize a remote name to create a safe ref name
@@ -239,7 +496,7 @@ fn sanitize_remote_name(remote: &str) -> String {
 }
 
 /// Generate a tracking ref name for notes from a specific remote
-/// Returns a ref like "refs/notes/ai-remote/origin"
+/// Returns a ref like "refs/notes/ai-remote/origin" (next to the repo's notes ref)
 ///
 /// SAFETY: These tracking refs are stored under refs/notes/ai-remote/* which:
 /// - Won't be pushed by `git push` (only pushes refs/heads/* by default)
@@ -247,8 +504,21 @@ fn sanitize_remote_name(remote: &str) -> String {
 /// - Won't be pushed by `git push --tags` (only pushes refs/tags/*)
 /// - **WILL** be pushed by `git push --mirror` (usually only used for backups, etc.)
 /// - **WILL** be pushed if user explicitly specifies refs/notes/ai-remote/* (extremely rare)
-pub fn tracking_ref_for_remote(remote_name: &str) -> String {
-    format!("refs/notes/ai-remote/{}", sanitize_remote_name(remote_name))
+pub fn tracking_ref_for_remote(repo: &Repository, remote_name: &str) -> String {
+    format!(
+        "{}-remote/{}",
+        repo.notes_ref(),
+        sanitize_remote_name(remote_name)
+    )
+}
+
+/// Scratch notes ref a selective push to `remote_name` is assembled in
+pub fn push_ref_for_remote(repo: &Repository, remote_name: &str) -> String {
+    format!(
+        "{}-push/{}",
+        repo.notes_ref(),
+        sanitize_remote_name(remote_name)
+    )
 }
 
 /// Check if a ref exists in the repository
@@ -262,21 +532,22 @@ pub fn ref_exists(repo: &Repository, ref_name: &str) -> bool {
     exec_git(&args).is_ok()
 }
 
-/// Merge notes from a source ref into refs/notes/ai
-/// Uses the 'ours' strategy to combine notes without data loss
+/// Merge notes from a source ref into the repo's notes ref
+/// Commits with a note on both sides keep the one the `notes_conflict` setting picks
 pub fn merge_notes_from_ref(repo: &Repository, source_ref: &str) -> Result<(), GitAiError> {
     let mut args = repo.global_args_for_exec();
     args.push("notes".to_string());
-    args.push(format!("--ref={}", AI_AUTHORSHIP_REFNAME));
+    args.push(format!("--ref={}", repo.notes_ref()));
     args.push("merge".to_string());
     args.push("-s".to_string());
-    args.push("ours".to_string());
+    args.push(repo.settings().notes_conflict.merge_strategy().to_string());
     args.push("--quiet".to_string());
     args.push(source_ref.to_string());
 
     debug_log(&format!(
-        "Merging notes from {} into refs/notes/ai",
-        source_ref
+        "Merging notes from {} into {}",
+        source_ref,
+        repo.notes_ref()
     ));
     exec_git(&args)?;
     Ok(())
@@ -294,31 +565,98 @@ pub fn copy_ref(repo: &Repository, source_ref: &str, dest_ref: &str) -> Result<(
     Ok(())
 }
 
+/// Point `dest_ref` at a notes commit holding the notes of `base_ref`, if given, plus `notes`:
+/// pairs of annotated commit and note blob, replacing any note `base_ref` has for the commit.
+/// The tree is built in a scratch index, so this takes the same few git calls for any number
+/// of notes.
+pub fn write_notes_onto(
+    repo: &Repository,
+    base_ref: Option<&str>,
+    dest_ref: &str,
+    notes: &[(&String, &String)],
+) -> Result<(), GitAiError> {
+    let index = repo
+        .path()
+        .join(format!("ai-notes-index-{}", std::process::id()));
+    let env = vec![(
+        "GIT_INDEX_FILE".to_string(),
+        index.to_string_lossy().to_string(),
+    )];
+    let git = |command: &[&str], stdin: &[u8]| -> Result<String, GitAiError> {
+        let mut args = repo.global_args_for_exec();
+        args.extend(command.iter().map(|arg| arg.to_string()));
+        Ok(String::from_utf8(
+            exec_git_stdin_with_env(&args, &env, stdin)?.stdout,
+        )?)
+    };
+
+    let result = (|| {
+        let mut index_info = String::new();
+        match base_ref {
+            Some(base_ref) => {
+                git(&["read-tree", base_ref], b"")?;
+                // Notes can sit in fanout directories (`ab/cdef...`); drop the base's copy of
+                // every note that gets replaced
+                let replaced: HashSet<&str> =
+                    notes.iter().map(|(commit, _)| commit.as_str()).collect();
+                for path in git(&["ls-files"], b"")?.lines() {
+                    let commit = path.replace('/', "");
+                    if replaced.contains(commit.as_str()) {
+                        index_info.push_str(&format!("0 {}\t{}\n", "0".repeat(commit.len()), path));
+                    }
+                }
+            }
+            None => {
+                git(&["read-tree", "--empty"], b"")?;
+            }
+        }
+        for (commit, blob) in notes {
+            index_info.push_str(&format!("100644 {}\t{}\n", blob, commit));
+        }
+        git(&["update-index", "--index-info"], index_info.as_bytes())?;
+
+        let tree = git(&["write-tree"], b"")?;
+        let mut commit_tree = vec!["commit-tree", tree.trim()];
+        if let Some(base_ref) = base_ref {
+            commit_tree.push("-p");
+            commit_tree.push(base_ref);
+        }
+        let commit = git(&commit_tree, b"Notes added by git-ai\n")?;
+        git(&["update-ref", dest_ref, commit.trim()], b"")?;
+        Ok(())
+    })();
+    let _ = std::fs::remove_file(&index);
+    result
+}
+
 /// Search AI notes for a pattern and return matching commit SHAs ordered by commit date (newest first)
-/// Uses git grep to search through refs/notes/ai
+/// Uses git grep to search through the notes ref
 pub fn grep_ai_notes(repo: &Repository, pattern: &str) -> Result<Vec<String>, GitAiError> {
     let mut args = repo.global_args_for_exec();
     args.push("--no-pager".to_string());
     args.push("grep".to_string());
     args.push("-nI".to_string());
     args.push(pattern.to_string());
-    args.push("refs/notes/ai".to_string());
+    let notes_ref = repo.notes_ref();
+    args.push(notes_ref.clone());
 
     let output = exec_git(&args)?;
     let stdout = String::from_utf8(output.stdout)
         .map_err(|_| GitAiError::Generic("Failed to parse git grep output".to_string()))?;
 
-    // Parse output format: refs/notes/ai:ab/cdef123...:line_number:matched_content
+    // Parse output format: <notes ref>:ab/cdef123...:line_number:matched_content
     // Extract the commit SHA from the path
     let mut shas = HashSet::new();
     for line in stdout.lines() {
-        if let Some(path_and_rest) = line.strip_prefix("refs/notes/ai:") {
-            if let Some(path_end) = path_and_rest.find(':') {
-                let path = &path_and_rest[..path_end];
-                // Path is in format "ab/cdef123..." - combine to get full SHA
-                let sha = path.replace('/', "");
-                shas.insert(sha);
-            }
+        if let Some(path_and_rest) = line
+            .strip_prefix(notes_ref.as_str())
+            .and_then(|rest| rest.strip_prefix(':'))
+            && let Some(path_end) = path_and_rest.find(':')
+        {
+            let path = &path_and_rest[..path_end];
+            // Path is in format "ab/cdef123..." - combine to get full SHA
+            let sha = path.replace('/', "");
+            shas.insert(sha);
         }
     }
 
@@ -0,0 +1,114 @@
+use crate::config;
+use crate::error::GitAiError;
+use crate::utils::debug_log;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Overrides the registry location (used by tests to keep the user's registry untouched)
+pub const REPO_REGISTRY_ENV: &str = "GIT_AI_REPO_REGISTRY";
+
+/// Every repository git-ai has touched on this machine, stored in ~/.git-ai/repos.json.
+/// Used by `git-ai me` to aggregate stats across repositories.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RepoRegistry {
+    #[serde(default)]
+    pub repositories: BTreeSet<String>,
+}
+
+pub fn registry_path() -> Option<PathBuf> {
+    if let Ok(path) = env::var(REPO_REGISTRY_ENV)
+        && !path.is_empty()
+    {
+        return Some(PathBuf::from(path));
+    }
+    Some(config::git_ai_dir()?.join("repos.json"))
+}
+
+impl RepoRegistry {
+    /// Load the registry. A missing or unreadable registry is treated as empty.
+    pub fn load(path: &Path) -> RepoRegistry {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), GitAiError> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Registered repositories that still exist on disk
+    pub fn existing_repositories(&self) -> Vec<PathBuf> {
+        self.repositories
+            .iter()
+            .map(PathBuf::from)
+            .filter(|path| path.exists())
+            .collect()
+    }
+}
+
+/// Add a repository working directory to the registry. Only writes when it's new.
+pub fn register_repository(workdir: &Path) -> Result<(), GitAiError> {
+    match registry_path() {
+        Some(path) => register_repository_in(&path, workdir),
+        None => Ok(()),
+    }
+}
+
+/// Add a repository working directory to the registry at `path`
+pub fn register_repository_in(path: &Path, workdir: &Path) -> Result<(), GitAiError> {
+    let workdir = workdir
+        .canonicalize()
+        .unwrap_or_else(|_| workdir.to_path_buf())
+        .to_string_lossy()
+        .to_string();
+
+    let mut registry = RepoRegistry::load(path);
+    if registry.repositories.insert(workdir) {
+        registry.save(path)?;
+    }
+    Ok(())
+}
+
+/// Best-effort registration; failures never interrupt the git command being proxied
+pub fn try_register_repository(workdir: &Path) {
+    if let Err(e) = register_repository(workdir) {
+        debug_log(&format!("Failed to register repository: {}", e));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_registry_round_trip_and_existing_repositories() {
+        let dir = std::env::temp_dir().join(format!("git-ai-registry-{}", std::process::id()));
+        let path = dir.join("repos.json");
+        let _ = fs::remove_dir_all(&dir);
+
+        assert!(RepoRegistry::load(&path).repositories.is_empty());
+
+        let mut registry = RepoRegistry::default();
+        registry
+            .repositories
+            .insert(dir.to_string_lossy().to_string());
+        registry
+            .repositories
+            .insert(dir.join("deleted-repo").to_string_lossy().to_string());
+        registry.save(&path).unwrap();
+
+        let loaded = RepoRegistry::load(&path);
+        assert_eq!(loaded.repositories.len(), 2);
+        assert_eq!(loaded.existing_repositories(), vec![dir.clone()]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,12 +1,17 @@
 use crate::authorship::attribution_tracker::LineAttribution;
-use crate::authorship::authorship_log::PromptRecord;
-use crate::authorship::working_log::{CHECKPOINT_API_VERSION, Checkpoint};
+use crate::authorship::authorship_log::{CommitMessageAuthorship, PromptRecord};
+use crate::authorship::authorship_log_serialization::generate_short_hash;
+use crate::authorship::transcript::Message;
+use crate::authorship::working_log::{AgentId, CHECKPOINT_API_VERSION, Checkpoint, CheckpointKind};
 use crate::error::GitAiError;
-use crate::git::rewrite_log::{RewriteLogEvent, append_event_to_file};
-use crate::utils::debug_log;
+use crate::fault;
+use crate::git::rewrite_log::{
+    RewriteLogEvent, append_event_to_file, compact_events, deserialize_events_from_jsonl,
+};
+use crate::utils::{debug_log, join_repo_path};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 
@@ -19,23 +24,196 @@ pub struct InitialAttributions {
     pub prompts: HashMap<String, PromptRecord>,
 }
 
+/// Stat information recorded for a file the last time a checkpoint read it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileState {
+    pub mtime_ns: u128,
+    pub size: u64,
+    pub blob_sha: String,
+}
+
+/// path -> (mtime, size, content hash) cache stored in the working log dir.
+/// Lets high-frequency checkpoints skip reading files that haven't changed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FileStateCache {
+    /// When the cache was written. Entries whose mtime is not strictly older than this
+    /// are "racy" (the file could have been modified again within the same clock tick).
+    pub written_at_ns: u128,
+    pub files: HashMap<String, FileState>,
+}
+
+impl FileStateCache {
+    /// Returns the cached blob sha if the file's current metadata matches what we recorded
+    pub fn lookup(&self, file_path: &str, metadata: &fs::Metadata) -> Option<&str> {
+        let state = self.files.get(file_path)?;
+        let mtime_ns = mtime_ns(metadata)?;
+        if mtime_ns >= self.written_at_ns {
+            return None;
+        }
+        if state.mtime_ns == mtime_ns && state.size == metadata.len() {
+            Some(state.blob_sha.as_str())
+        } else {
+            None
+        }
+    }
+
+    pub fn record(&mut self, file_path: &str, metadata: &fs::Metadata, blob_sha: &str) {
+        if let Some(mtime_ns) = mtime_ns(metadata) {
+            self.files.insert(
+                file_path.to_string(),
+                FileState {
+                    mtime_ns,
+                    size: metadata.len(),
+                    blob_sha: blob_sha.to_string(),
+                },
+            );
+        }
+    }
+}
+
+fn mtime_ns(metadata: &fs::Metadata) -> Option<u128> {
+    metadata
+        .modified()
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_nanos())
+}
+
+/// Layouts git-ai storage can have inside the git dir, by name. `git-ai migrate --storage`
+/// moves between them; a repository uses the first non-default one that exists.
+pub const STORAGE_LAYOUTS: &[(&str, &str)] = &[("default", "ai"), ("info", "info/ai")];
+
+/// Name of the storage layout the repository at `repo_path` (its git dir) uses
+pub fn storage_layout(repo_path: &Path) -> &'static str {
+    STORAGE_LAYOUTS[1..]
+        .iter()
+        .find(|(_, dir)| repo_path.join(dir).is_dir())
+        .map_or(STORAGE_LAYOUTS[0].0, |(name, _)| name)
+}
+
+/// Storage directory of `layout` in the git dir at `repo_path`, None for an unknown layout
+pub fn storage_dir_for_layout(repo_path: &Path, layout: &str) -> Option<PathBuf> {
+    STORAGE_LAYOUTS
+        .iter()
+        .find(|(name, _)| *name == layout)
+        .map(|(_, dir)| repo_path.join(dir))
+}
+
+/// The common git dir of the repository whose git dir is `repo_path`. In a linked worktree
+/// (`git worktree add`) the git dir is `.git/worktrees/<name>`, whose `commondir` file points
+/// back at the main `.git`; everywhere else the two are the same.
+pub fn common_git_dir(repo_path: &Path) -> PathBuf {
+    fs::read_to_string(repo_path.join("commondir"))
+        .ok()
+        .map(|common| repo_path.join(common.trim()))
+        .and_then(|common| common.canonicalize().ok())
+        .unwrap_or_else(|| repo_path.to_path_buf())
+}
+
+/// The root of the worktree checked out for the git dir at `repo_path`. A linked worktree's
+/// git dir records it in its `gitdir` file, as the path of the worktree's `.git` file; a
+/// submodule's, under `.git/modules`, in its `core.worktree`.
+pub fn worktree_root(repo_path: &Path) -> PathBuf {
+    fs::read_to_string(repo_path.join("gitdir"))
+        .ok()
+        .filter(|_| repo_path.join("commondir").is_file())
+        .and_then(|dot_git| {
+            PathBuf::from(dot_git.trim())
+                .parent()
+                .map(Path::to_path_buf)
+        })
+        .or_else(|| core_worktree(repo_path))
+        .unwrap_or_else(|| {
+            repo_path
+                .parent()
+                .map_or_else(|| repo_path.to_path_buf(), Path::to_path_buf)
+        })
+}
+
+/// `core.worktree` of the git dir's own config, resolved against the git dir
+fn core_worktree(repo_path: &Path) -> Option<PathBuf> {
+    let config = fs::read_to_string(repo_path.join("config")).ok()?;
+    let mut in_core = false;
+    for line in config.lines().map(str::trim) {
+        if line.starts_with('[') {
+            in_core = line.eq_ignore_ascii_case("[core]");
+        } else if in_core
+            && let Some((key, value)) = line.split_once('=')
+            && key.trim().eq_ignore_ascii_case("worktree")
+        {
+            let worktree = repo_path.join(value.trim().trim_matches('"'));
+            return Some(worktree.canonicalize().unwrap_or(worktree));
+        }
+    }
+    None
+}
+
 #[derive(Debug, Clone)]
 pub struct RepoStorage {
     pub repo_path: PathBuf,
+    /// Root of the storage, `.git/ai` unless it was migrated to another layout. Linked
+    /// worktrees have their own, under `.git/worktrees/<name>`, so their working logs and
+    /// in-progress rebases don't collide.
+    pub ai_dir: PathBuf,
+    /// Storage shared by all worktrees, in the common git dir: state that follows the
+    /// authorship notes (transcripts, detached commits, backfill and jj progress) rather than
+    /// a checkout. Same as `ai_dir` outside linked worktrees.
+    pub common_ai_dir: PathBuf,
+    /// Root of the checked out files
+    pub worktree_root: PathBuf,
     pub working_logs: PathBuf,
     pub rewrite_log: PathBuf,
+    pub backfill_state: PathBuf,
+    pub jj_last_operation: PathBuf,
+    pub transcripts: PathBuf,
+    pub last_outcome: PathBuf,
+    pub hook_health: PathBuf,
+    /// Deferred post-commit work, one file per task
+    pub queue: PathBuf,
+    /// Last summary `git-ai prompt-segment` computed
+    pub prompt_segment: PathBuf,
+    /// HEAD as the last intercepted git command left it
+    pub last_head: PathBuf,
+    /// Agent sessions registered with `git-ai watch`, one file per session
+    pub agent_sessions: PathBuf,
 }
 
 impl RepoStorage {
     pub fn for_repo_path(repo_path: &Path) -> RepoStorage {
-        let ai_dir = repo_path.join("ai");
+        let ai_dir = storage_dir_for_layout(repo_path, storage_layout(repo_path))
+            .unwrap_or_else(|| repo_path.join("ai"));
+        let common_dir = common_git_dir(repo_path);
+        let common_ai_dir = storage_dir_for_layout(&common_dir, storage_layout(&common_dir))
+            .unwrap_or_else(|| common_dir.join("ai"));
         let working_logs_dir = ai_dir.join("working_logs");
         let rewrite_log_file = ai_dir.join("rewrite_log");
+        let backfill_state_file = common_ai_dir.join("backfill_state");
+        let jj_last_operation_file = common_ai_dir.join("jj_last_operation");
+        let transcripts_dir = common_ai_dir.join("transcripts");
+        let last_outcome_file = ai_dir.join("last_outcome.json");
+        let hook_health_file = ai_dir.join("hook_health.json");
+        let queue_dir = ai_dir.join("queue");
+        let prompt_segment_file = ai_dir.join("prompt_segment.json");
+        let last_head_file = ai_dir.join("last_head");
+        let agent_sessions_dir = ai_dir.join("agent_sessions");
 
         let config = RepoStorage {
             repo_path: repo_path.to_path_buf(),
+            worktree_root: worktree_root(repo_path),
+            ai_dir,
+            common_ai_dir,
             working_logs: working_logs_dir,
             rewrite_log: rewrite_log_file,
+            backfill_state: backfill_state_file,
+            jj_last_operation: jj_last_operation_file,
+            transcripts: transcripts_dir,
+            last_outcome: last_outcome_file,
+            hook_health: hook_health_file,
+            queue: queue_dir,
+            prompt_segment: prompt_segment_file,
+            last_head: last_head_file,
+            agent_sessions: agent_sessions_dir,
         };
 
         // @todo - @acunniffe, make this lazy on a read or write.
@@ -46,15 +224,18 @@ impl RepoStorage {
     }
 
     fn ensure_config_directory(&self) -> Result<(), GitAiError> {
-        let ai_dir = self.repo_path.join("ai");
-
-        fs::create_dir_all(ai_dir)?;
+        // Already set up on every run after the first, so skip the directory creation
+        if self.rewrite_log.is_file() && self.working_logs.is_dir() {
+            return Ok(());
+        }
+        fs::create_dir_all(&self.ai_dir)?;
+        fs::create_dir_all(&self.common_ai_dir)?;
 
         // Create working_logs directory
         fs::create_dir_all(&self.working_logs)?;
 
         if !&self.rewrite_log.exists() && !&self.rewrite_log.is_file() {
-            fs::write(&self.rewrite_log, "")?;
+            fault::write(&self.rewrite_log, "")?;
         }
 
         Ok(())
@@ -65,9 +246,7 @@ impl RepoStorage {
     pub fn working_log_for_base_commit(&self, sha: &str) -> PersistedWorkingLog {
         let working_log_dir = self.working_logs.join(sha);
         fs::create_dir_all(&working_log_dir).unwrap();
-        // The repo_path is the .git directory, so we need to go up one level to get the actual repo root
-        let repo_root = self.repo_path.parent().unwrap().to_path_buf();
-        PersistedWorkingLog::new(working_log_dir, sha, repo_root)
+        PersistedWorkingLog::new(working_log_dir, sha, self.worktree_root.clone())
     }
 
     #[allow(dead_code)]
@@ -79,6 +258,17 @@ impl RepoStorage {
         Ok(())
     }
 
+    /// Move a working log to a new base commit after that commit was rewritten. Does nothing
+    /// if there is no working log for `old_sha` or one already exists for `new_sha`.
+    pub fn move_working_log(&self, old_sha: &str, new_sha: &str) -> Result<(), GitAiError> {
+        let old_dir = self.working_logs.join(old_sha);
+        let new_dir = self.working_logs.join(new_sha);
+        if old_dir.is_dir() && !new_dir.exists() {
+            fs::rename(&old_dir, &new_dir)?;
+        }
+        Ok(())
+    }
+
     #[allow(dead_code)]
     pub fn delete_all_working_logs(&self) -> Result<(), GitAiError> {
         if self.working_logs.exists() {
@@ -109,8 +299,414 @@ impl RepoStorage {
         let content = fs::read_to_string(&self.rewrite_log)?;
         crate::git::rewrite_log::deserialize_events_from_jsonl(&content)
     }
+
+    /// Rewrite the rewrite log with only the events `compact_events` keeps, dropping lines
+    /// that don't parse. Returns how many lines were dropped and how many bytes that saves.
+    pub fn compact_rewrite_log(&self, dry_run: bool) -> Result<(usize, u64), GitAiError> {
+        let Ok(content) = fs::read_to_string(&self.rewrite_log) else {
+            return Ok((0, 0));
+        };
+        let lines = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .count();
+        let kept = compact_events(deserialize_events_from_jsonl(&content)?);
+        if kept.len() == lines {
+            return Ok((0, 0));
+        }
+
+        let compacted = kept
+            .iter()
+            .map(serde_json::to_string)
+            .collect::<Result<Vec<_>, _>>()?
+            .join("\n");
+        if !dry_run {
+            fault::write(&self.rewrite_log, &compacted)?;
+        }
+        Ok((
+            lines - kept.len(),
+            (content.len() as u64).saturating_sub(compacted.len() as u64),
+        ))
+    }
+
+    /* Backfill State Persistance */
+
+    /// Read the backfill progress. A missing or unreadable state file means nothing was processed.
+    pub fn read_backfill_state(&self) -> BackfillState {
+        fs::read_to_string(&self.backfill_state)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Write the backfill progress via a temp file + rename so an interrupted write never
+    /// leaves a truncated state file behind
+    pub fn write_backfill_state(&self, state: &BackfillState) -> Result<(), GitAiError> {
+        let tmp = self.backfill_state.with_extension("tmp");
+        fault::write(&tmp, serde_json::to_string_pretty(state)?)?;
+        fs::rename(&tmp, &self.backfill_state)?;
+        Ok(())
+    }
+
+    pub fn clear_backfill_state(&self) -> Result<(), GitAiError> {
+        if self.backfill_state.exists() {
+            fs::remove_file(&self.backfill_state)?;
+        }
+        Ok(())
+    }
+
+    /* Local Transcripts */
+
+    /// Keep a session's full transcript on this machine, keyed by prompt hash. Later commits
+    /// from the same session overwrite it with the longer transcript.
+    pub fn write_local_transcript(
+        &self,
+        prompt_hash: &str,
+        messages: &[Message],
+    ) -> Result<(), GitAiError> {
+        fs::create_dir_all(&self.transcripts)?;
+        let path = self.transcripts.join(format!("{}.json", prompt_hash));
+        fault::write(path, serde_json::to_string(messages)?)?;
+        Ok(())
+    }
+
+    pub fn read_local_transcript(&self, prompt_hash: &str) -> Option<Vec<Message>> {
+        let path = self.transcripts.join(format!("{}.json", prompt_hash));
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+    }
+
+    /* Hook Health */
+
+    /// Timings of git-ai's hooks. A missing or unreadable file means nothing was recorded yet.
+    pub fn read_hook_health(&self) -> HookHealth {
+        fs::read_to_string(&self.hook_health)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn write_hook_health(&self, health: &HookHealth) -> Result<(), GitAiError> {
+        let tmp = self.hook_health.with_extension("tmp");
+        fault::write(&tmp, serde_json::to_string_pretty(health)?)?;
+        fs::rename(&tmp, &self.hook_health)?;
+        Ok(())
+    }
+
+    /* Prompt Segment */
+
+    /// The cached prompt segment. A missing or unreadable file means nothing is cached yet.
+    pub fn read_prompt_segment(&self) -> Option<PromptSegmentCache> {
+        fs::read_to_string(&self.prompt_segment)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+    }
+
+    pub fn write_prompt_segment(&self, cache: &PromptSegmentCache) -> Result<(), GitAiError> {
+        let tmp = self.prompt_segment.with_extension("tmp");
+        fault::write(&tmp, serde_json::to_string(cache)?)?;
+        fs::rename(&tmp, &self.prompt_segment)?;
+        Ok(())
+    }
+
+    /* Task Queue */
+
+    /// Add a task to the queue, one file per task named so they sort oldest first. The file
+    /// only shows up once it's completely written.
+    pub fn enqueue_task(&self, task: &QueuedTask) -> Result<PathBuf, GitAiError> {
+        fs::create_dir_all(&self.queue)?;
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0);
+        let name = format!("{:024}-{}", nanos, std::process::id());
+        let tmp = self.queue.join(format!("{}.tmp", name));
+        let path = self.queue.join(format!("{}.json", name));
+        fault::write(&tmp, serde_json::to_string_pretty(task)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(path)
+    }
+
+    /// Files of the queued tasks, oldest first
+    pub fn queued_task_paths(&self) -> Result<Vec<PathBuf>, GitAiError> {
+        task_files(&self.queue)
+    }
+
+    pub fn read_queued_task(&self, path: &Path) -> Result<QueuedTask, GitAiError> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn update_queued_task(&self, path: &Path, task: &QueuedTask) -> Result<(), GitAiError> {
+        let tmp = path.with_extension("tmp");
+        fault::write(&tmp, serde_json::to_string_pretty(task)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn remove_queued_task(&self, path: &Path) -> Result<(), GitAiError> {
+        fs::remove_file(path)?;
+        Ok(())
+    }
+
+    /// Set a task that keeps failing aside in `queue/failed`
+    pub fn fail_queued_task(&self, path: &Path) -> Result<(), GitAiError> {
+        let failed = self.queue.join("failed");
+        fs::create_dir_all(&failed)?;
+        if let Some(name) = path.file_name() {
+            fs::rename(path, failed.join(name))?;
+        }
+        Ok(())
+    }
+
+    /// Put every failed task back in the queue, returning how many there were
+    pub fn retry_failed_tasks(&self) -> Result<usize, GitAiError> {
+        let failed = task_files(&self.queue.join("failed"))?;
+        for path in &failed {
+            let mut task = self.read_queued_task(path).ok();
+            if let Some(task) = task.as_mut() {
+                task.attempts = 0;
+                self.update_queued_task(path, task)?;
+            }
+            if let Some(name) = path.file_name() {
+                fs::rename(path, self.queue.join(name))?;
+            }
+        }
+        Ok(failed.len())
+    }
+
+    pub fn pending_tasks(&self) -> usize {
+        task_files(&self.queue)
+            .map(|paths| paths.len())
+            .unwrap_or(0)
+    }
+
+    pub fn failed_tasks(&self) -> usize {
+        task_files(&self.queue.join("failed"))
+            .map(|paths| paths.len())
+            .unwrap_or(0)
+    }
+
+    /* Jujutsu Sync State */
+
+    /// The jj operation whose rewrites were last synced, if any
+    pub fn read_jj_last_operation(&self) -> Option<String> {
+        fs::read_to_string(&self.jj_last_operation)
+            .ok()
+            .map(|content| content.trim().to_string())
+            .filter(|id| !id.is_empty())
+    }
+
+    pub fn write_jj_last_operation(&self, operation_id: &str) -> Result<(), GitAiError> {
+        fault::write(&self.jj_last_operation, operation_id)?;
+        Ok(())
+    }
+
+    /* Last Seen HEAD */
+
+    /// HEAD as the last intercepted git command left it, if one has run
+    pub fn read_last_head(&self) -> Option<String> {
+        fs::read_to_string(&self.last_head)
+            .ok()
+            .map(|content| content.trim().to_string())
+            .filter(|sha| !sha.is_empty())
+    }
+
+    pub fn write_last_head(&self, sha: &str) -> Result<(), GitAiError> {
+        fault::write(&self.last_head, sha)?;
+        Ok(())
+    }
+
+    /* Agent Sessions */
+
+    /// Register an agent session, or refresh one registered before
+    pub fn write_agent_session(&self, session: &AgentSession) -> Result<(), GitAiError> {
+        fs::create_dir_all(&self.agent_sessions)?;
+        let path = self.agent_session_path(&session.agent_id);
+        let tmp = path.with_extension("tmp");
+        fault::write(&tmp, serde_json::to_string_pretty(session)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    pub fn read_agent_session(&self, agent_id: &AgentId) -> Option<AgentSession> {
+        fs::read_to_string(self.agent_session_path(agent_id))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+    }
+
+    /// Returns whether the session was registered
+    pub fn remove_agent_session(&self, agent_id: &AgentId) -> Result<bool, GitAiError> {
+        let path = self.agent_session_path(agent_id);
+        if !path.exists() {
+            return Ok(false);
+        }
+        fs::remove_file(path)?;
+        Ok(true)
+    }
+
+    /// Every registered session, unreadable files skipped
+    pub fn agent_sessions(&self) -> Vec<AgentSession> {
+        let Ok(entries) = fs::read_dir(&self.agent_sessions) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .filter_map(|content| serde_json::from_str(&content).ok())
+            .collect()
+    }
+
+    fn agent_session_path(&self, agent_id: &AgentId) -> PathBuf {
+        self.agent_sessions.join(format!(
+            "{}.json",
+            generate_short_hash(&agent_id.id, &agent_id.tool)
+        ))
+    }
+
+    /// Base commits that currently have a working log directory
+    pub fn working_log_base_commits(&self) -> Result<HashSet<String>, GitAiError> {
+        let mut base_commits = HashSet::new();
+        if !self.working_logs.exists() {
+            return Ok(base_commits);
+        }
+        for entry in fs::read_dir(&self.working_logs)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                base_commits.insert(entry.file_name().to_string_lossy().to_string());
+            }
+        }
+        Ok(base_commits)
+    }
+}
+
+/// An agent session registered with `git-ai watch session start`. Changes `git-ai watch` sees
+/// while one is active are checkpointed as AI edits of that session.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentSession {
+    pub agent_id: AgentId,
+    /// The agent's process; the session ends with it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// Unix seconds
+    pub started_at: u64,
+    /// Unix seconds of the last `session start` for the session
+    pub updated_at: u64,
+}
+
+impl AgentSession {
+    /// Sessions without a pid that aren't refreshed within this many seconds are over
+    pub const TTL_SECS: u64 = 10 * 60;
+
+    /// Whether the session still runs at unix time `now`
+    pub fn is_active(&self, now: u64) -> bool {
+        match self.pid {
+            Some(pid) => process_alive(pid),
+            None => now.saturating_sub(self.updated_at) <= Self::TTL_SECS,
+        }
+    }
+}
+
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    // Signal 0 only checks the process exists; EPERM means it does but belongs to someone else
+    let result = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    result == 0 || std::io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
 }
 
+#[cfg(not(unix))]
+fn process_alive(_pid: u32) -> bool {
+    true
+}
+
+/// Progress of `git-ai backfill`, keyed by ref name
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BackfillState {
+    #[serde(default)]
+    pub refs: BTreeMap<String, BackfillRefProgress>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BackfillRefProgress {
+    /// Last commit (in oldest-first order) whose note was checked or written
+    pub last_processed: Option<String>,
+    pub processed: usize,
+    pub total: usize,
+    #[serde(default)]
+    pub completed: bool,
+}
+
+/// Timings of each git-ai hook, keyed by hook name (`post-commit`, `pre-rebase`, ...)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HookHealth {
+    #[serde(default)]
+    pub hooks: BTreeMap<String, HookTimings>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HookTimings {
+    pub runs: u64,
+    pub over_budget_runs: u64,
+    /// Over-budget runs since the last run within budget
+    pub over_budget_streak: u64,
+    pub last_ms: u64,
+    pub max_ms: u64,
+    /// The hook's heavy work is queued for `git-ai process-queue` instead of run inline
+    #[serde(default)]
+    pub deferred: bool,
+}
+
+/// What the shell prompt shows, with what it was computed from so it can tell when it's stale
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PromptSegmentCache {
+    /// HEAD when this was computed
+    pub head: String,
+    /// Size and modification time of HEAD's checkpoints file, `None` when it had none
+    pub working_log_stamp: Option<(u64, u128)>,
+    /// AI lines in the working log, waiting to be committed
+    pub pending_ai_lines: u32,
+    /// Share of HEAD's added lines written by AI, `None` when HEAD has no note or added nothing
+    pub last_commit_ai_percent: Option<u32>,
+}
+
+/// Post-commit work left for `git-ai process-queue`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedTask {
+    pub event: RewriteLogEvent,
+    pub commit_author: String,
+    /// Unix seconds
+    pub queued_at: u64,
+    /// Failed attempts so far
+    #[serde(default)]
+    pub attempts: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+/// `.json` task files in `dir`, sorted by name. A missing directory holds no tasks.
+fn task_files(dir: &Path) -> Result<Vec<PathBuf>, GitAiError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut paths = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+#[derive(Clone)]
 pub struct PersistedWorkingLog {
     pub dir: PathBuf,
     #[allow(dead_code)]
@@ -136,7 +732,13 @@ impl PersistedWorkingLog {
 
         // Clear checkpoints by truncating the JSONL file
         let checkpoints_file = self.dir.join("checkpoints.jsonl");
-        fs::write(&checkpoints_file, "")?;
+        fault::write(&checkpoints_file, "")?;
+
+        // Cached file states point at blobs that no longer exist
+        let file_states = self.dir.join("file_states.json");
+        if file_states.exists() {
+            fs::remove_file(&file_states)?;
+        }
 
         Ok(())
     }
@@ -159,28 +761,168 @@ impl PersistedWorkingLog {
 
         // Write content to blob file
         let blob_path = blobs_dir.join(&sha);
-        fs::write(blob_path, content)?;
+        fault::write(blob_path, content)?;
 
         Ok(sha)
     }
 
+    pub fn has_file_version(&self, sha: &str) -> bool {
+        self.dir.join("blobs").join(sha).exists()
+    }
+
+    /* file state cache */
+
+    /// Read the file state cache. A missing or unreadable cache is treated as empty.
+    pub fn read_file_state_cache(&self) -> FileStateCache {
+        let cache_file = self.dir.join("file_states.json");
+        fs::read_to_string(&cache_file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn write_file_state_cache(&self, cache: &mut FileStateCache) -> Result<(), GitAiError> {
+        cache.written_at_ns = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let cache_file = self.dir.join("file_states.json");
+        fault::write(cache_file, serde_json::to_string(cache)?)?;
+        Ok(())
+    }
+
+    /// Drop cache entries that are racy or point at a missing file or blob. Keeps the time the
+    /// cache was written, so entries that were racy before don't become trusted. Returns how many
+    /// entries were dropped.
+    pub fn refresh_file_state_cache(&self) -> Result<usize, GitAiError> {
+        let mut cache = self.read_file_state_cache();
+        let before = cache.files.len();
+        let written_at_ns = cache.written_at_ns;
+        cache.files.retain(|path, state| {
+            state.mtime_ns < written_at_ns
+                && self.has_file_version(&state.blob_sha)
+                && join_repo_path(&self.repo_root, path).is_file()
+        });
+        let dropped = before - cache.files.len();
+        if dropped > 0 {
+            let cache_file = self.dir.join("file_states.json");
+            fault::write(cache_file, serde_json::to_string(&cache)?)?;
+        }
+        Ok(dropped)
+    }
+
+    /// Remove blobs that no checkpoint or cache entry refers to and that were last written
+    /// before `older_than`. Newer blobs may belong to a checkpoint that is still being written.
+    /// Returns how many blobs were removed.
+    pub fn remove_unreferenced_blobs(
+        &self,
+        older_than: std::time::SystemTime,
+    ) -> Result<usize, GitAiError> {
+        let blobs = self.unreferenced_blobs(older_than)?;
+        for (path, _) in &blobs {
+            fs::remove_file(path)?;
+        }
+        Ok(blobs.len())
+    }
+
+    /// The blobs `remove_unreferenced_blobs` would remove, with their sizes in bytes
+    pub fn unreferenced_blobs(
+        &self,
+        older_than: std::time::SystemTime,
+    ) -> Result<Vec<(PathBuf, u64)>, GitAiError> {
+        let blobs_dir = self.dir.join("blobs");
+        if !blobs_dir.is_dir() {
+            return Ok(Vec::new());
+        }
+
+        // Read raw lines rather than parsed checkpoints, so blobs of checkpoints another
+        // git-ai version wrote are kept too
+        let mut referenced: HashSet<String> = HashSet::new();
+        let checkpoints_file = self.dir.join("checkpoints.jsonl");
+        if checkpoints_file.exists() {
+            for line in fs::read_to_string(&checkpoints_file)?.lines() {
+                let Ok(checkpoint) = serde_json::from_str::<serde_json::Value>(line) else {
+                    continue;
+                };
+                let entries = checkpoint.get("entries").and_then(|e| e.as_array());
+                for entry in entries.into_iter().flatten() {
+                    if let Some(sha) = entry.get("blob_sha").and_then(|s| s.as_str()) {
+                        referenced.insert(sha.to_string());
+                    }
+                }
+            }
+        }
+        referenced.extend(
+            self.read_file_state_cache()
+                .files
+                .into_values()
+                .map(|state| state.blob_sha),
+        );
+
+        let mut unreferenced = Vec::new();
+        for entry in fs::read_dir(&blobs_dir)? {
+            let entry = entry?;
+            let sha = entry.file_name().to_string_lossy().to_string();
+            if referenced.contains(&sha) {
+                continue;
+            }
+            let metadata = entry.metadata()?;
+            if metadata.modified()? < older_than {
+                unreferenced.push((entry.path(), metadata.len()));
+            }
+        }
+        Ok(unreferenced)
+    }
+
+    /* commit message authorship */
+
+    /// Record who is writing the next commit message. Overwrites any earlier value.
+    pub fn write_commit_message_authorship(
+        &self,
+        authorship: &CommitMessageAuthorship,
+    ) -> Result<(), GitAiError> {
+        let file = self.dir.join("commit_message.json");
+        fault::write(file, serde_json::to_string(authorship)?)?;
+        Ok(())
+    }
+
+    /// Read and clear the recorded commit message authorship, so it only applies to one commit.
+    pub fn take_commit_message_authorship(&self) -> Option<CommitMessageAuthorship> {
+        let file = self.dir.join("commit_message.json");
+        let content = fs::read_to_string(&file).ok()?;
+        let _ = fs::remove_file(&file);
+        serde_json::from_str(&content).ok()
+    }
+
     /* append checkpoint */
     pub fn append_checkpoint(&self, checkpoint: &Checkpoint) -> Result<(), GitAiError> {
-        let checkpoints_file = self.dir.join("checkpoints.jsonl");
+        self.append_checkpoints(std::slice::from_ref(checkpoint))
+    }
 
-        // Serialize checkpoint to JSON and append to JSONL file
-        let json_line = serde_json::to_string(checkpoint)?;
+    /// Append checkpoints in one write, so a crash leaves either all of them or none intact
+    pub fn append_checkpoints(&self, checkpoints: &[Checkpoint]) -> Result<(), GitAiError> {
+        if checkpoints.is_empty() {
+            return Ok(());
+        }
+        let checkpoints_file = self.dir.join("checkpoints.jsonl");
 
-        // Open file in append mode and write the JSON line
-        use std::fs::OpenOptions;
-        use std::io::Write;
+        // Serialize checkpoints to JSON lines for the JSONL file
+        let mut json_lines = String::new();
+        for checkpoint in checkpoints {
+            json_lines.push_str(&serde_json::to_string(checkpoint)?);
+            json_lines.push('\n');
+        }
 
-        let mut file = OpenOptions::new()
-            .create(true)
-            .append(true)
-            .open(&checkpoints_file)?;
+        // An append torn by a crash or a full disk leaves a partial line behind; start on a
+        // fresh line so this checkpoint doesn't get glued onto it
+        let separator = if ends_mid_line(&checkpoints_file) {
+            "\n"
+        } else {
+            ""
+        };
 
-        writeln!(file, "{}", json_line)?;
+        // Append the JSON lines in a single write
+        fault::append(&checkpoints_file, format!("{}{}", separator, json_lines))?;
 
         Ok(())
     }
@@ -201,8 +943,16 @@ impl PersistedWorkingLog {
                 continue;
             }
 
-            let checkpoint: Checkpoint = serde_json::from_str(line)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            let checkpoint: Checkpoint = match serde_json::from_str(line) {
+                Ok(checkpoint) => checkpoint,
+                Err(e) => {
+                    debug_log(&format!(
+                        "malformed checkpoint line: {} (silently skipping checkpoint)",
+                        e
+                    ));
+                    continue;
+                }
+            };
 
             if checkpoint.api_version != CHECKPOINT_API_VERSION {
                 debug_log(&format!(
@@ -218,6 +968,71 @@ impl PersistedWorkingLog {
         Ok(checkpoints)
     }
 
+    /// Files whose latest attribution in this working log (checkpoints, or INITIAL for files
+    /// not checkpointed since) still credits some lines to an AI
+    pub fn ai_attributed_files(&self) -> Result<HashSet<String>, GitAiError> {
+        let human = CheckpointKind::Human.to_str();
+        let has_ai_lines = |attributions: &[LineAttribution]| {
+            attributions.iter().any(|attr| attr.author_id != human)
+        };
+
+        let mut latest: HashMap<String, bool> = self
+            .read_initial_attributions()
+            .files
+            .iter()
+            .map(|(file, attributions)| (file.clone(), has_ai_lines(attributions)))
+            .collect();
+        for checkpoint in self.read_all_checkpoints()? {
+            for entry in &checkpoint.entries {
+                latest.insert(entry.file.clone(), has_ai_lines(&entry.line_attributions));
+            }
+        }
+
+        Ok(latest
+            .into_iter()
+            .filter_map(|(file, has_ai)| has_ai.then_some(file))
+            .collect())
+    }
+
+    /// Forget everything recorded about `files`, e.g. after they were deleted without being
+    /// committed. Checkpoints left without entries are dropped along with them.
+    pub fn remove_files(&self, files: &HashSet<String>) -> Result<(), GitAiError> {
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        let mut content = String::new();
+        for mut checkpoint in self.read_all_checkpoints()? {
+            let had_entries = !checkpoint.entries.is_empty();
+            checkpoint
+                .entries
+                .retain(|entry| !files.contains(&entry.file));
+            if had_entries && checkpoint.entries.is_empty() {
+                continue;
+            }
+            content.push_str(&serde_json::to_string(&checkpoint)?);
+            content.push('\n');
+        }
+        fault::write(self.dir.join("checkpoints.jsonl"), content)?;
+
+        let mut initial = self.read_initial_attributions();
+        if initial.files.keys().any(|file| files.contains(file)) {
+            initial.files.retain(|file, _| !files.contains(file));
+            fault::write(
+                self.dir.join("INITIAL"),
+                serde_json::to_string_pretty(&initial)?,
+            )?;
+        }
+
+        let mut cache = self.read_file_state_cache();
+        if cache.files.keys().any(|file| files.contains(file)) {
+            cache.files.retain(|file, _| !files.contains(file));
+            self.write_file_state_cache(&mut cache)?;
+        }
+
+        Ok(())
+    }
+
     /* INITIAL attributions file */
 
     /// Write initial attributions to the INITIAL file.
@@ -247,7 +1062,7 @@ impl PersistedWorkingLog {
 
         let initial_file = self.dir.join("INITIAL");
         let json = serde_json::to_string_pretty(&initial_data)?;
-        fs::write(initial_file, json)?;
+        fault::write(initial_file, json)?;
 
         Ok(())
     }
@@ -283,6 +1098,17 @@ impl PersistedWorkingLog {
     }
 }
 
+/// Whether `path` is non-empty and its last byte isn't a newline
+fn ends_mid_line(path: &Path) -> bool {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let Ok(mut file) = fs::File::open(path) else {
+        return false;
+    };
+    let mut last = [0u8; 1];
+    file.seek(SeekFrom::End(-1)).is_ok() && file.read_exact(&mut last).is_ok() && last[0] != b'\n'
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -509,6 +1335,43 @@ mod tests {
         assert_eq!(checkpoints[0].api_version, CHECKPOINT_API_VERSION);
     }
 
+    #[test]
+    fn test_checkpoints_survive_a_torn_append() {
+        use crate::authorship::working_log::CheckpointKind;
+
+        let tmp_repo = TmpRepo::new().expect("Failed to create tmp repo");
+        let repo_storage = RepoStorage::for_repo_path(tmp_repo.repo().path());
+        let working_log = repo_storage.working_log_for_base_commit("test-commit-sha");
+
+        let checkpoint = Checkpoint::new(
+            CheckpointKind::Human,
+            "diff --git a/file b/file".to_string(),
+            "first-author".to_string(),
+            vec![],
+        );
+        let json = serde_json::to_string(&checkpoint).unwrap();
+        let checkpoints_file = working_log.dir.join("checkpoints.jsonl");
+        fs::write(
+            &checkpoints_file,
+            format!("{}\n{}", json, &json[..json.len() / 2]),
+        )
+        .expect("Failed to write checkpoints.jsonl");
+
+        let mut second = checkpoint.clone();
+        second.author = "second-author".to_string();
+        working_log
+            .append_checkpoint(&second)
+            .expect("Failed to append checkpoint");
+
+        let authors: Vec<String> = working_log
+            .read_all_checkpoints()
+            .expect("Failed to read checkpoints")
+            .into_iter()
+            .map(|checkpoint| checkpoint.author)
+            .collect();
+        assert_eq!(authors, vec!["first-author", "second-author"]);
+    }
+
     #[test]
     fn test_persisted_working_log_reset() {
         use crate::authorship::working_log::CheckpointKind;
@@ -579,6 +1442,167 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_file_state_cache_round_trip_and_racy_entries() {
+        let tmp_repo = TmpRepo::new().expect("Failed to create tmp repo");
+        let repo_storage = RepoStorage::for_repo_path(tmp_repo.repo().path());
+        let working_log = repo_storage.working_log_for_base_commit("test-commit-sha");
+
+        let file_path = tmp_repo.path().join("cached.txt");
+        fs::write(&file_path, "hello\n").unwrap();
+        let metadata = fs::metadata(&file_path).unwrap();
+
+        let mut cache = working_log.read_file_state_cache();
+        assert!(cache.files.is_empty());
+        cache.record("cached.txt", &metadata, "abc");
+
+        // Not written yet, so the entry is racy and must not be trusted
+        assert_eq!(cache.lookup("cached.txt", &metadata), None);
+
+        working_log.write_file_state_cache(&mut cache).unwrap();
+        let cache = working_log.read_file_state_cache();
+        assert_eq!(cache.lookup("cached.txt", &metadata), Some("abc"));
+
+        // A size change invalidates the entry
+        fs::write(&file_path, "hello world\n").unwrap();
+        let metadata = fs::metadata(&file_path).unwrap();
+        assert_eq!(cache.lookup("cached.txt", &metadata), None);
+
+        // Reset drops the cache entirely
+        working_log.reset_working_log().unwrap();
+        assert!(working_log.read_file_state_cache().files.is_empty());
+    }
+
+    #[test]
+    fn test_maintenance_keeps_referenced_blobs_and_live_cache_entries() {
+        let tmp_repo = TmpRepo::new().expect("Failed to create tmp repo");
+        let repo_storage = RepoStorage::for_repo_path(tmp_repo.repo().path());
+        let working_log = repo_storage.working_log_for_base_commit("test-commit-sha");
+
+        let kept = working_log.persist_file_version("checkpointed\n").unwrap();
+        let cached = working_log.persist_file_version("cached\n").unwrap();
+        let orphan = working_log.persist_file_version("orphan\n").unwrap();
+        let entry = crate::authorship::working_log::WorkingLogEntry::new(
+            "kept.txt".to_string(),
+            kept.clone(),
+            vec![],
+            vec![],
+        );
+        let checkpoint = Checkpoint::new(
+            CheckpointKind::Human,
+            "".to_string(),
+            "author".to_string(),
+            vec![entry],
+        );
+        working_log.append_checkpoint(&checkpoint).unwrap();
+
+        let file_path = tmp_repo.path().join("cached.txt");
+        fs::write(&file_path, "cached\n").unwrap();
+        let mut cache = working_log.read_file_state_cache();
+        cache.record("cached.txt", &fs::metadata(&file_path).unwrap(), &cached);
+        cache.record("deleted.txt", &fs::metadata(&file_path).unwrap(), &cached);
+        working_log.write_file_state_cache(&mut cache).unwrap();
+
+        // Blobs newer than the cutoff are left alone
+        let past = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        assert_eq!(working_log.remove_unreferenced_blobs(past).unwrap(), 0);
+        let future = std::time::SystemTime::now() + std::time::Duration::from_secs(3600);
+        assert_eq!(working_log.remove_unreferenced_blobs(future).unwrap(), 1);
+        assert!(working_log.has_file_version(&kept));
+        assert!(working_log.has_file_version(&cached));
+        assert!(!working_log.has_file_version(&orphan));
+
+        assert_eq!(working_log.refresh_file_state_cache().unwrap(), 1);
+        let cache = working_log.read_file_state_cache();
+        assert!(cache.files.contains_key("cached.txt"));
+        assert!(!cache.files.contains_key("deleted.txt"));
+    }
+
+    #[test]
+    fn test_move_working_log_to_rewritten_commit() {
+        let tmp_repo = TmpRepo::new().expect("Failed to create tmp repo");
+        let repo_storage = RepoStorage::for_repo_path(tmp_repo.repo().path());
+
+        let working_log = repo_storage.working_log_for_base_commit("old");
+        let blob = working_log.persist_file_version("content").unwrap();
+
+        repo_storage.move_working_log("old", "new").unwrap();
+        let base_commits = repo_storage.working_log_base_commits().unwrap();
+        assert!(!base_commits.contains("old"));
+        assert!(base_commits.contains("new"));
+        assert_eq!(
+            repo_storage
+                .working_log_for_base_commit("new")
+                .get_file_version(&blob)
+                .unwrap(),
+            "content"
+        );
+
+        // An existing working log for the new commit is never overwritten
+        repo_storage.working_log_for_base_commit("other");
+        repo_storage.move_working_log("other", "new").unwrap();
+        assert!(
+            repo_storage
+                .working_log_base_commits()
+                .unwrap()
+                .contains("other")
+        );
+    }
+
+    #[test]
+    fn test_ai_attributed_files_and_remove_files() {
+        use crate::authorship::working_log::WorkingLogEntry;
+
+        let tmp_repo = TmpRepo::new().expect("Failed to create tmp repo");
+        let repo_storage = RepoStorage::for_repo_path(tmp_repo.repo().path());
+        let working_log = repo_storage.working_log_for_base_commit("test-commit-sha");
+
+        let entry = |file: &str, author: &str| {
+            WorkingLogEntry::new(
+                file.to_string(),
+                "blob".to_string(),
+                vec![],
+                vec![LineAttribution::new(1, 3, author.to_string(), false)],
+            )
+        };
+        let ai_checkpoint = Checkpoint::new(
+            CheckpointKind::AiAgent,
+            String::new(),
+            "ai".to_string(),
+            vec![
+                entry("generated.rs", "prompt-hash"),
+                entry("rewritten.rs", "prompt-hash"),
+            ],
+        );
+        let human_checkpoint = Checkpoint::new(
+            CheckpointKind::Human,
+            String::new(),
+            "human".to_string(),
+            vec![entry("rewritten.rs", "human"), entry("notes.md", "human")],
+        );
+        working_log.append_checkpoint(&ai_checkpoint).unwrap();
+        working_log.append_checkpoint(&human_checkpoint).unwrap();
+
+        // A human rewrote every AI line of rewritten.rs since
+        assert_eq!(
+            working_log.ai_attributed_files().unwrap(),
+            HashSet::from(["generated.rs".to_string()])
+        );
+
+        working_log
+            .remove_files(&HashSet::from([
+                "generated.rs".to_string(),
+                "rewritten.rs".to_string(),
+            ]))
+            .unwrap();
+        let checkpoints = working_log.read_all_checkpoints().unwrap();
+        assert_eq!(checkpoints.len(), 1);
+        assert_eq!(checkpoints[0].author, "human");
+        assert_eq!(checkpoints[0].entries.len(), 1);
+        assert_eq!(checkpoints[0].entries[0].file, "notes.md");
+        assert!(working_log.ai_attributed_files().unwrap().is_empty());
+    }
+
     #[test]
     fn test_working_log_for_base_commit_creates_directory() {
         // Create a temporary repository
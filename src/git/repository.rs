@@ -1,16 +1,21 @@
+use crate::authorship::attribution_loss::{AttributionLoss, rewrite_loss};
 use crate::authorship::authorship_log_serialization::AuthorshipLog;
 use crate::authorship::rebase_authorship::rewrite_authorship_if_needed;
-use crate::config;
+use crate::cancellation::CancellationToken;
+use crate::config::{self, Settings};
 use crate::error::GitAiError;
+use crate::git::backend::{GitBackend, backend_for};
 use crate::git::cli_parser::ParsedGitInvocation;
+use crate::git::discovery_cache;
 use crate::git::refs::{get_authorship, show_authorship_note};
 use crate::git::repo_storage::RepoStorage;
 use crate::git::rewrite_log::RewriteLogEvent;
 use crate::git::sync_authorship::{fetch_authorship_notes, push_authorship_notes};
+use crate::utils::to_repo_path;
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Output};
-use std::sync::OnceLock;
+use std::sync::{Arc, OnceLock};
 
 pub struct Object<'a> {
     repo: &'a Repository,
@@ -38,12 +43,56 @@ impl<'a> Object<'a> {
     }
 }
 
+/// Which commits of a range are walked, like the `git rev-list` flags of the same names.
+///
+/// A merge's authorship note attests the lines it brought in from the merged branch, which
+/// the branch's own commits attest too. Each walk counts those lines once:
+/// - by default every commit is walked and merge notes are skipped, so the lines count at the
+///   commits that wrote them
+/// - `--no-merges` leaves merges out of the walk altogether, with the same counts
+/// - `--first-parent` walks only the mainline; the merged branch's commits aren't walked and
+///   the merge's note counts in their place
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RangeWalk {
+    pub first_parent: bool,
+    pub no_merges: bool,
+}
+
+impl RangeWalk {
+    /// Take `--first-parent` or `--no-merges`; false for any other argument
+    pub fn parse_arg(&mut self, arg: &str) -> bool {
+        match arg {
+            "--first-parent" => self.first_parent = true,
+            "--no-merges" => self.no_merges = true,
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn rev_list_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.first_parent {
+            args.push("--first-parent".to_string());
+        }
+        if self.no_merges {
+            args.push("--no-merges".to_string());
+        }
+        args
+    }
+
+    /// Whether a walked merge's note counts, rather than repeating its branch's commits
+    pub fn counts_merge_notes(&self) -> bool {
+        self.first_parent && !self.no_merges
+    }
+}
+
 #[derive(Debug)]
 pub struct CommitRange<'a> {
     repo: &'a Repository,
     pub start_oid: String,
     pub end_oid: String,
     pub refname: String,
+    pub walk: RangeWalk,
 }
 
 impl<'a> CommitRange<'a> {
@@ -63,12 +112,14 @@ impl<'a> CommitRange<'a> {
             start_oid: resolved_start,
             end_oid: resolved_end,
             refname,
+            walk: RangeWalk::default(),
         })
     }
 
     /// Create a new CommitRange with automatic refname inference.
     /// If refname is None, tries to find a single ref pointing to end_oid.
-    /// If exactly one ref is found, uses that. Otherwise falls back to current HEAD.
+    /// If exactly one ref is found, uses that. Otherwise falls back to the current branch, or to
+    /// end_oid itself when HEAD is detached.
     pub fn new_infer_refname(
         repo: &'a Repository,
         start_oid: String,
@@ -102,14 +153,24 @@ impl<'a> CommitRange<'a> {
                     Err(_) => Vec::new(),
                 };
 
-                // If exactly one ref found, use it
+                let local_branches: Vec<&String> = refs
+                    .iter()
+                    .filter(|r| r.starts_with("refs/heads/"))
+                    .collect();
+
+                // If exactly one ref (or one local branch among several) is found, use it
                 if refs.len() == 1 {
                     refs[0].clone()
+                } else if local_branches.len() == 1 {
+                    local_branches[0].clone()
                 } else {
-                    // Fall back to current HEAD
+                    // Fall back to the current branch. A detached HEAD isn't a stable name
+                    // (bisect and rebase move it around), so use the end commit itself.
                     match repo.head() {
-                        Ok(head_ref) => head_ref.name().unwrap_or("HEAD").to_string(),
-                        Err(_) => "HEAD".to_string(),
+                        Ok(head_ref) if head_ref.is_branch() => {
+                            head_ref.name().unwrap_or("HEAD").to_string()
+                        }
+                        _ => resolved_end.clone(),
                     }
                 }
             }
@@ -120,9 +181,15 @@ impl<'a> CommitRange<'a> {
             start_oid: resolved_start,
             end_oid: resolved_end,
             refname: inferred_refname,
+            walk: RangeWalk::default(),
         })
     }
 
+    pub fn with_walk(mut self, walk: RangeWalk) -> Self {
+        self.walk = walk;
+        self
+    }
+
     pub fn repo(&self) -> &'a Repository {
         self.repo
     }
@@ -184,6 +251,7 @@ impl<'a> CommitRange<'a> {
         let mut args = self.repo.global_args_for_exec();
         args.push("rev-list".to_string());
         args.push("--count".to_string());
+        args.extend(self.walk.rev_list_args());
         args.push(format!("{}..{}", self.start_oid, self.end_oid));
 
         match exec_git(&args) {
@@ -214,6 +282,7 @@ impl<'a> IntoIterator for CommitRange<'a> {
         // Format: start_oid..end_oid means commits reachable from end_oid but not from start_oid
         let mut args = self.repo.global_args_for_exec();
         args.push("rev-list".to_string());
+        args.extend(self.walk.rev_list_args());
         args.push(format!("{}..{}", self.start_oid, self.end_oid));
 
         let commit_oids: Vec<String> = match exec_git(&args) {
@@ -337,49 +406,34 @@ impl<'a> Commit<'a> {
     }
 
     pub fn tree(&self) -> Result<Tree<'a>, GitAiError> {
-        let mut args = self.repo.global_args_for_exec();
-        args.push("rev-parse".to_string());
-        // args.push("-q".to_string());
-        args.push("--verify".to_string());
-        args.push(format!("{}^{}", self.oid, "{tree}"));
-        let output = exec_git(&args)?;
         Ok(Tree {
             repo: self.repo,
-            oid: String::from_utf8(output.stdout)?.trim().to_string(),
+            oid: self
+                .repo
+                .backend
+                .rev_parse(&format!("{}^{}", self.oid, "{tree}"))?,
         })
     }
 
     pub fn parent(&self, i: usize) -> Result<Commit<'a>, GitAiError> {
-        let mut args = self.repo.global_args_for_exec();
-        args.push("rev-parse".to_string());
-        // args.push("-q".to_string());
-        args.push("--verify".to_string());
         // libgit2 uses 0-based indexing; Git's rev syntax uses 1-based parent selectors.
-        args.push(format!("{}^{}", self.oid, i + 1));
-        let output = exec_git(&args)?;
         Ok(Commit {
             repo: self.repo,
-            oid: String::from_utf8(output.stdout)?.trim().to_string(),
+            oid: self
+                .repo
+                .backend
+                .rev_parse(&format!("{}^{}", self.oid, i + 1))?,
             authorship_log: std::cell::OnceCell::new(),
         })
     }
 
     // Return an iterator over the parents of this commit.
     pub fn parents(&self) -> Parents<'a> {
-        // Use `git show -s --format=%P <oid>` to get whitespace-separated parent OIDs
-        let mut args = self.repo.global_args_for_exec();
-        args.push("show".to_string());
-        args.push("-s".to_string());
-        args.push("--format=%P".to_string());
-        args.push(self.oid.clone());
-
-        let parent_oids: Vec<String> = match exec_git(&args) {
-            Ok(output) => {
-                let stdout = String::from_utf8(output.stdout).unwrap_or_default();
-                stdout.split_whitespace().map(|s| s.to_string()).collect()
-            }
-            Err(_) => Vec::new(),
-        };
+        let parent_oids = self
+            .repo
+            .backend
+            .commit_parents(&self.oid)
+            .unwrap_or_default();
 
         Parents {
             repo: self.repo,
@@ -395,6 +449,34 @@ impl<'a> Commit<'a> {
         Ok(self.parents().count())
     }
 
+    /// Whether the commit leaves its parent's tree unchanged, as `git commit --allow-empty` or
+    /// a rebase keeping a commit whose changes are already upstream does. A root commit is
+    /// empty when its tree is; merge commits never are.
+    pub fn is_empty(&self) -> Result<bool, GitAiError> {
+        let mut args = self.repo.global_args_for_exec();
+        args.push("show".to_string());
+        args.push("-s".to_string());
+        args.push("--format=%T %P".to_string());
+        args.push(self.oid.clone());
+        let output = String::from_utf8(exec_git(&args)?.stdout)?;
+
+        let mut args = self.repo.global_args_for_exec();
+        match output.split_whitespace().collect::<Vec<_>>()[..] {
+            [tree] => {
+                args.push("ls-tree".to_string());
+                args.push(tree.to_string());
+                Ok(exec_git(&args)?.stdout.is_empty())
+            }
+            [tree, parent] => {
+                args.push("rev-parse".to_string());
+                args.push("--verify".to_string());
+                args.push(format!("{}^{{tree}}", parent));
+                Ok(String::from_utf8(exec_git(&args)?.stdout)?.trim() == tree)
+            }
+            _ => Ok(false),
+        }
+    }
+
     // Get the short “summary” of the git commit message. The returned message is the summary of the commit, comprising the first paragraph of the message with whitespace trimmed and squashed. None may be returned if an error occurs or if the summary is not valid utf-8.
     pub fn summary(&self) -> Result<String, GitAiError> {
         let mut args = self.repo.global_args_for_exec();
@@ -596,7 +678,8 @@ impl<'a> Tree<'a> {
         args.push("-r".to_string());
         args.push(self.oid.clone());
         args.push("--".to_string());
-        let path_str = path.to_string_lossy().to_string();
+        // Tree paths are `/` separated on every platform
+        let path_str = to_repo_path(&path.to_string_lossy());
         args.push(path_str.clone());
 
         let output = exec_git(&args)?;
@@ -663,12 +746,7 @@ impl<'a> Blob<'a> {
 
     // Get the content of this blob.
     pub fn content(&self) -> Result<Vec<u8>, GitAiError> {
-        let mut args = self.repo.global_args_for_exec();
-        args.push("cat-file".to_string());
-        args.push("blob".to_string());
-        args.push(self.oid.clone());
-        let output = exec_git(&args)?;
-        Ok(output.stdout)
+        self.repo.backend.blob_content(&self.oid)
     }
 }
 
@@ -789,13 +867,25 @@ pub struct Repository {
     pub storage: RepoStorage,
     pub pre_command_base_commit: Option<String>,
     pub pre_command_refname: Option<String>,
+    /// Errors hooks ran into and swallowed so the git command itself isn't affected
+    pub hook_errors: Vec<String>,
+    /// AI attributions hooks had to drop, warned about once the command is done
+    pub attribution_losses: Vec<AttributionLoss>,
+    /// Checked between the steps of multi-step hooks
+    pub cancellation: CancellationToken,
     workdir_cache: OnceLock<Result<PathBuf, GitAiError>>,
+    settings_cache: OnceLock<Settings>,
+    /// Where read-only object lookups go; see [`crate::git::backend`]
+    backend: Arc<dyn GitBackend>,
 }
 
 impl Repository {
     // Util for preparing global args for execution
     pub fn global_args_for_exec(&self) -> Vec<String> {
-        let mut args = self.global_args.clone();
+        // Room for the subcommand and its usual handful of arguments, so callers pushing
+        // them don't reallocate
+        let mut args = Vec::with_capacity(self.global_args.len() + 8);
+        args.extend(self.global_args.iter().cloned());
         if !args.iter().any(|arg| arg == "--no-pager") {
             args.push("--no-pager".to_string());
         }
@@ -825,11 +915,14 @@ impl Repository {
         supress_output: bool,
         apply_side_effects: bool,
     ) {
-        let log = self
-            .storage
-            .append_rewrite_event(rewrite_log_event.clone())
-            .ok()
-            .expect("Error writing .git/ai/rewrite_log");
+        let log = match self.storage.append_rewrite_event(rewrite_log_event.clone()) {
+            Ok(log) => log,
+            Err(e) => {
+                self.hook_errors
+                    .push(format!("Error writing .git/ai/rewrite_log: {}", e));
+                return;
+            }
+        };
 
         if apply_side_effects {
             match rewrite_authorship_if_needed(
@@ -840,19 +933,24 @@ impl Repository {
                 supress_output,
             ) {
                 Ok(_) => (),
-                Err(_) => {}
+                Err(e) => {
+                    let loss = rewrite_loss(self, &rewrite_log_event, &e);
+                    self.record_attribution_loss(loss);
+                    self.hook_errors.push(e.to_string());
+                }
             }
         }
     }
 
+    pub fn record_attribution_loss(&mut self, loss: Option<AttributionLoss>) {
+        if let Some(loss) = loss {
+            self.attribution_losses.push(loss);
+        }
+    }
+
     // Internal util to get the git object type for a given OID
     fn object_type(&self, oid: &str) -> Result<String, GitAiError> {
-        let mut args = self.global_args_for_exec();
-        args.push("cat-file".to_string());
-        args.push("-t".to_string());
-        args.push(oid.to_string());
-        let output = exec_git(&args)?;
-        Ok(String::from_utf8(output.stdout)?.trim().to_string())
+        self.backend.object_type(oid)
     }
 
     // Retrieve and resolve the reference pointed at by HEAD.
@@ -881,6 +979,81 @@ impl Repository {
         }
     }
 
+    /// Settings for this repository, layered over its `.gitai.toml`. Resolved once; an invalid
+    /// configuration is reported and replaced by the defaults so git itself keeps working.
+    pub fn settings(&self) -> &Settings {
+        self.settings_cache.get_or_init(|| {
+            let resolved = match self.workdir() {
+                Ok(workdir) => Settings::resolve(Some(&workdir)),
+                // A bare repository has no checkout; its settings are the ones committed at HEAD
+                Err(_) if self.is_bare() => Settings::resolve_committed(
+                    self.head_file_text(config::REPO_CONFIG_FILE).as_deref(),
+                    self.head_file_text(config::IGNORE_FILE).as_deref(),
+                ),
+                Err(_) => Settings::resolve(None),
+            };
+            match resolved {
+                Ok(resolved) => resolved.settings,
+                Err(e) => {
+                    eprintln!("git-ai: ignoring invalid configuration: {}", e);
+                    Settings::default()
+                }
+            }
+        })
+    }
+
+    /// The full notes ref authorship is stored in, e.g. refs/notes/ai
+    pub fn notes_ref(&self) -> String {
+        format!("refs/notes/{}", self.settings().notes_ref_name())
+    }
+
+    /// Whether this is a bare repository, like the ones code hosts and CI mirrors keep. Read-only
+    /// commands work on them against HEAD instead of a working copy.
+    pub fn is_bare(&self) -> bool {
+        let mut args = self.global_args_for_exec();
+        args.push("rev-parse".to_string());
+        args.push("--is-bare-repository".to_string());
+        exec_git(&args)
+            .ok()
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .is_some_and(|out| out.trim() == "true")
+    }
+
+    /// Paths of the submodules declared in the working copy's `.gitmodules`, relative to the
+    /// repository root. Empty when there is none, without running git.
+    pub fn submodule_paths(&self) -> Vec<String> {
+        let Ok(gitmodules) = self.workdir().map(|workdir| workdir.join(".gitmodules")) else {
+            return Vec::new();
+        };
+        if !gitmodules.is_file() {
+            return Vec::new();
+        }
+        let args = vec![
+            "config".to_string(),
+            "--file".to_string(),
+            gitmodules.to_string_lossy().to_string(),
+            "--get-regexp".to_string(),
+            r"^submodule\..*\.path$".to_string(),
+        ];
+        exec_git(&args)
+            .ok()
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|out| {
+                out.lines()
+                    .filter_map(|line| line.split_once(' '))
+                    .map(|(_, path)| path.trim().trim_end_matches('/').to_string())
+                    .filter(|path| !path.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// A text file as committed at HEAD, if there is one
+    fn head_file_text(&self, file_path: &str) -> Option<String> {
+        let head = self.revparse_single("HEAD").ok()?.id();
+        String::from_utf8(self.get_file_content(file_path, &head).ok()?).ok()
+    }
+
     // Returns the path to the .git folder for normal repositories or the repository itself for bare repositories.
     // TODO Test on bare repositories.
     pub fn path(&self) -> &Path {
@@ -962,11 +1135,22 @@ impl Repository {
         }
     }
 
-    #[allow(dead_code)]
+    pub fn config_get_bool(&self, key: &str) -> Result<Option<bool>, GitAiError> {
+        let mut args = self.global_args_for_exec();
+        args.push("config".to_string());
+        args.push("--type=bool".to_string());
+        args.push("--get".to_string());
+        args.push(key.to_string());
+        match exec_git(&args) {
+            Ok(output) => Ok(Some(String::from_utf8(output.stdout)?.trim() == "true")),
+            Err(GitAiError::GitCliError { code: Some(1), .. }) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
     pub fn config_set_str(&self, key: &str, value: &str) -> Result<(), GitAiError> {
         let mut args = self.global_args_for_exec();
         args.push("config".to_string());
-        args.push("set".to_string());
         args.push(key.to_string());
         args.push(value.to_string());
         exec_git(&args)?;
@@ -1297,15 +1481,9 @@ impl Repository {
 
     // Find a single object, as specified by a revision string.
     pub fn revparse_single(&self, spec: &str) -> Result<Object<'_>, GitAiError> {
-        let mut args = self.global_args_for_exec();
-        args.push("rev-parse".to_string());
-        // args.push("-q".to_string());
-        args.push("--verify".to_string());
-        args.push(spec.to_string());
-        let output = exec_git(&args)?;
         Ok(Object {
             repo: self,
-            oid: String::from_utf8(output.stdout)?.trim().to_string(),
+            oid: self.backend.rev_parse(spec)?,
         })
     }
 
@@ -1333,7 +1511,7 @@ impl Repository {
 
     #[allow(dead_code)]
     pub fn push_authorship<'a>(&'a self, remote_name: &str) -> Result<(), GitAiError> {
-        push_authorship_notes(self, remote_name)
+        push_authorship_notes(self, remote_name, None)
     }
 
     pub fn upstream_remote(&self) -> Result<Option<String>, GitAiError> {
@@ -1448,18 +1626,18 @@ impl Repository {
         Ok(Tree { repo: self, oid })
     }
 
-    /// Get the content of a file at a specific commit
-    /// Uses `git show <commit>:<path>` for efficient single-call retrieval
+    /// Get the content of a file at a specific commit, as `git show <commit>:<path>` prints it
     pub fn get_file_content(
         &self,
         file_path: &str,
         commit_hash: &str,
     ) -> Result<Vec<u8>, GitAiError> {
-        let mut args = self.global_args_for_exec();
-        args.push("show".to_string());
-        args.push(format!("{}:{}", commit_hash, file_path));
-        let output = exec_git(&args)?;
-        Ok(output.stdout)
+        self.backend.file_content(commit_hash, file_path)
+    }
+
+    /// Name of the backend serving object reads, `cli` or `libgit2`
+    pub fn git_backend_name(&self) -> &'static str {
+        self.backend.name()
     }
 
     /// Get content of all staged files concurrently
@@ -1509,6 +1687,55 @@ impl Repository {
         Ok(staged_files)
     }
 
+    /// Blob of each staged file (`git ls-files -s`), the index's side of a snapshot
+    pub fn index_snapshot(
+        &self,
+        pathspecs: Option<&HashSet<String>>,
+    ) -> Result<HashMap<String, String>, GitAiError> {
+        let mut args = self.global_args_for_exec();
+        args.push("ls-files".to_string());
+        args.push("-s".to_string());
+        args.push("-z".to_string());
+        push_pathspecs(&mut args, pathspecs);
+        // <mode> <blob> <stage>\t<path>
+        self.blobs_by_path(&args, 1)
+    }
+
+    /// Blob of each file in `commit`'s tree (`git ls-tree -r`)
+    pub fn tree_snapshot(
+        &self,
+        commit: &str,
+        pathspecs: Option<&HashSet<String>>,
+    ) -> Result<HashMap<String, String>, GitAiError> {
+        let mut args = self.global_args_for_exec();
+        args.push("ls-tree".to_string());
+        args.push("-r".to_string());
+        args.push("-z".to_string());
+        args.push(commit.to_string());
+        push_pathspecs(&mut args, pathspecs);
+        // <mode> <type> <blob>\t<path>
+        self.blobs_by_path(&args, 2)
+    }
+
+    /// Run a NUL-separated listing and take the blob from the `blob_field`th space-separated
+    /// field before each tab
+    fn blobs_by_path(
+        &self,
+        args: &[String],
+        blob_field: usize,
+    ) -> Result<HashMap<String, String>, GitAiError> {
+        let output = exec_git(args)?;
+        let stdout = String::from_utf8(output.stdout)?;
+        Ok(stdout
+            .split('\0')
+            .filter_map(|entry| {
+                let (fields, path) = entry.split_once('\t')?;
+                let blob = fields.split(' ').nth(blob_field)?;
+                Some((path.to_string(), blob.to_string()))
+            })
+            .collect())
+    }
+
     /// List all files changed in a commit
     /// Returns a HashSet of file paths relative to the repository root
     pub fn list_commit_files(
@@ -1557,7 +1784,7 @@ impl Repository {
     /// Returns a HashMap of file paths to vectors of added line numbers
     ///
     /// Uses `git diff -U0` to get unified diff with zero context lines,
-    /// then parses the hunk headers to extract line numbers directly.
+    /// then collects the added lines from the parsed diff records (see `diff_parser`).
     /// This is much faster than fetching blobs and running TextDiff manually.
     pub fn diff_added_lines(
         &self,
@@ -1565,25 +1792,7 @@ impl Repository {
         to_ref: &str,
         pathspecs: Option<&HashSet<String>>,
     ) -> Result<HashMap<String, Vec<u32>>, GitAiError> {
-        let mut args = self.global_args_for_exec();
-        args.push("diff".to_string());
-        args.push("-U0".to_string()); // Zero context lines
-        args.push("--no-color".to_string());
-        args.push(from_ref.to_string());
-        args.push(to_ref.to_string());
-
-        // Add pathspecs if provided
-        if let Some(paths) = pathspecs {
-            args.push("--".to_string());
-            for path in paths {
-                args.push(path.clone());
-            }
-        }
-
-        let output = exec_git(&args)?;
-        let diff_output = String::from_utf8(output.stdout)?;
-
-        parse_diff_added_lines(&diff_output)
+        self.diff_records_added_lines(from_ref, Some(to_ref), pathspecs)
     }
 
     /// Get list of changed files between two refs using `git diff --name-only`
@@ -1611,35 +1820,6 @@ impl Repository {
         Ok(files)
     }
 
-    /// Get added line ranges from git diff between a commit and the working directory
-    /// Returns a HashMap of file paths to vectors of added line numbers
-    ///
-    /// Similar to diff_added_lines but compares against the working directory
-    pub fn diff_workdir_added_lines(
-        &self,
-        from_ref: &str,
-        pathspecs: Option<&HashSet<String>>,
-    ) -> Result<HashMap<String, Vec<u32>>, GitAiError> {
-        let mut args = self.global_args_for_exec();
-        args.push("diff".to_string());
-        args.push("-U0".to_string()); // Zero context lines
-        args.push("--no-color".to_string());
-        args.push(from_ref.to_string());
-
-        // Add pathspecs if provided
-        if let Some(paths) = pathspecs {
-            args.push("--".to_string());
-            for path in paths {
-                args.push(path.clone());
-            }
-        }
-
-        let output = exec_git(&args)?;
-        let diff_output = String::from_utf8(output.stdout)?;
-
-        parse_diff_added_lines(&diff_output)
-    }
-
     pub fn fetch_branch(&self, branch_name: &str, remote_name: &str) -> Result<(), GitAiError> {
         let mut args = self.global_args_for_exec();
         args.push("fetch".to_string());
@@ -1667,14 +1847,34 @@ pub fn find_repository(global_args: &Vec<String>) -> Result<Repository, GitAiErr
         )));
     }
 
-    Ok(Repository {
-        global_args: global_args.clone(),
+    Ok(repository_for_git_dir(global_args, path))
+}
+
+/// [`find_repository`] for the hot paths (the git proxy, checkpoints), which skips
+/// `git rev-parse` when ~/.git-ai/discovery.json already knows the directory's git dir
+pub fn find_repository_cached(global_args: &Vec<String>) -> Result<Repository, GitAiError> {
+    if let Some(git_dir) = discovery_cache::lookup(global_args) {
+        return Ok(repository_for_git_dir(global_args, git_dir));
+    }
+    let repo = find_repository(global_args)?;
+    discovery_cache::record(global_args, &repo.git_dir);
+    Ok(repo)
+}
+
+fn repository_for_git_dir(global_args: &[String], path: PathBuf) -> Repository {
+    Repository {
+        backend: backend_for(global_args, &path),
+        global_args: global_args.to_vec(),
         storage: RepoStorage::for_repo_path(&path),
         git_dir: path,
         pre_command_base_commit: None,
         pre_command_refname: None,
+        hook_errors: Vec::new(),
+        attribution_losses: Vec::new(),
+        cancellation: CancellationToken::signals(),
         workdir_cache: OnceLock::new(),
-    })
+        settings_cache: OnceLock::new(),
+    }
 }
 
 pub fn find_repository_in_path(path: &str) -> Result<Repository, GitAiError> {
@@ -1682,8 +1882,16 @@ pub fn find_repository_in_path(path: &str) -> Result<Repository, GitAiError> {
     return find_repository(&global_args);
 }
 
+fn push_pathspecs(args: &mut Vec<String>, pathspecs: Option<&HashSet<String>>) {
+    if let Some(paths) = pathspecs {
+        args.push("--".to_string());
+        args.extend(paths.iter().cloned());
+    }
+}
+
 /// Helper to execute a git command
 pub fn exec_git(args: &[String]) -> Result<Output, GitAiError> {
+    crate::fault::check_git(args)?;
     // TODO Make sure to handle process signals, etc.
     let output = Command::new(config::Config::get().git_cmd())
         .args(args)
@@ -1705,6 +1913,7 @@ pub fn exec_git(args: &[String]) -> Result<Output, GitAiError> {
 
 /// Helper to execute a git command with data provided on stdin
 pub fn exec_git_stdin(args: &[String], stdin_data: &[u8]) -> Result<Output, GitAiError> {
+    crate::fault::check_git(args)?;
     // TODO Make sure to handle process signals, etc.
     let mut child = Command::new(config::Config::get().git_cmd())
         .args(args)
@@ -1743,6 +1952,7 @@ pub fn exec_git_stdin_with_env(
     env: &Vec<(String, String)>,
     stdin_data: &[u8],
 ) -> Result<Output, GitAiError> {
+    crate::fault::check_git(args)?;
     // TODO Make sure to handle process signals, etc.
     let mut cmd = Command::new(config::Config::get().git_cmd());
     cmd.args(args)
@@ -1778,87 +1988,3 @@ pub fn exec_git_stdin_with_env(
 
     Ok(output)
 }
-
-/// Parse git diff output to extract added line numbers per file
-///
-/// Parses unified diff format hunk headers like:
-/// @@ -10,2 +15,5 @@
-///
-/// This means: old file line 10 (2 lines), new file line 15 (5 lines)
-/// We extract the "new file" line numbers to know which lines were added.
-fn parse_diff_added_lines(diff_output: &str) -> Result<HashMap<String, Vec<u32>>, GitAiError> {
-    let mut result: HashMap<String, Vec<u32>> = HashMap::new();
-    let mut current_file: Option<String> = None;
-
-    for line in diff_output.lines() {
-        // Track current file being diffed
-        if line.starts_with("+++ b/") {
-            current_file = Some(line[6..].to_string());
-        } else if line.starts_with("+++ /dev/null") {
-            // File was deleted
-            current_file = None;
-        } else if line.starts_with("@@ ") {
-            // Parse hunk header: @@ -old_start,old_count +new_start,new_count @@
-            if let Some(ref file) = current_file {
-                if let Some(added_lines) = parse_hunk_header(line) {
-                    result
-                        .entry(file.clone())
-                        .or_insert_with(Vec::new)
-                        .extend(added_lines);
-                }
-            }
-        }
-    }
-
-    // Sort and deduplicate line numbers for each file
-    for lines in result.values_mut() {
-        lines.sort_unstable();
-        lines.dedup();
-    }
-
-    Ok(result)
-}
-
-/// Parse a hunk header line to extract added line numbers
-///
-/// Format: @@ -old_start,old_count +new_start,new_count @@
-/// Returns the line numbers that were added in the new file
-fn parse_hunk_header(line: &str) -> Option<Vec<u32>> {
-    // Find the part between @@ and @@
-    let parts: Vec<&str> = line.split("@@").collect();
-    if parts.len() < 2 {
-        return None;
-    }
-
-    let hunk_info = parts[1].trim();
-
-    // Split by space to get old and new ranges
-    let ranges: Vec<&str> = hunk_info.split_whitespace().collect();
-    if ranges.len() < 2 {
-        return None;
-    }
-
-    // Parse the new file range (starts with '+')
-    let new_range = ranges
-        .iter()
-        .find(|r| r.starts_with('+'))?
-        .trim_start_matches('+');
-
-    // Parse "start,count" or just "start"
-    let new_parts: Vec<&str> = new_range.split(',').collect();
-    let start: u32 = new_parts[0].parse().ok()?;
-    let count: u32 = if new_parts.len() > 1 {
-        new_parts[1].parse().ok()?
-    } else {
-        1 // If no count specified, it's 1 line
-    };
-
-    // If count is 0, no lines were added (only deleted)
-    if count == 0 {
-        return Some(Vec::new());
-    }
-
-    // Generate all line numbers in the range
-    let lines: Vec<u32> = (start..start + count).collect();
-    Some(lines)
-}
@@ -111,7 +111,6 @@ impl RewriteLogEvent {
         }
     }
 
-    #[allow(dead_code)]
     pub fn revert_mixed(event: RevertMixedEvent) -> Self {
         Self::RevertMixed {
             revert_mixed: event,
@@ -135,6 +134,20 @@ impl RewriteLogEvent {
         }
     }
 
+    pub fn commit_fixup(
+        base_commit: Option<String>,
+        commit_sha: String,
+        fixup: CommitFixup,
+    ) -> Self {
+        Self::Commit {
+            commit: CommitEvent {
+                base_commit,
+                commit_sha,
+                fixup: Some(fixup),
+            },
+        }
+    }
+
     #[allow(dead_code)]
     pub fn stash(event: StashEvent) -> Self {
         Self::Stash { stash: event }
@@ -183,6 +196,10 @@ pub struct MergeSquashEvent {
     pub source_head: String,
     pub base_branch: String,
     pub base_head: String,
+    /// The squash commit, when it was made elsewhere (e.g. by the server) and is already
+    /// committed; unset for a local `merge --squash`, whose commit is still to come
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub squash_commit: Option<String>,
 }
 
 impl MergeSquashEvent {
@@ -191,12 +208,14 @@ impl MergeSquashEvent {
         source_head: String,
         base_branch: String,
         base_head: String,
+        squash_commit: Option<String>,
     ) -> Self {
         Self {
             source_branch,
             source_head,
             base_branch,
             base_head,
+            squash_commit,
         }
     }
 }
@@ -309,15 +328,23 @@ pub struct RevertMixedEvent {
     pub reverted_commit: String,
     pub success: bool,
     pub affected_files: Vec<String>,
+    /// The commit the revert made; unset while the revert is stopped on conflicts
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revert_commit: Option<String>,
 }
 
 impl RevertMixedEvent {
-    #[allow(dead_code)]
-    pub fn new(reverted_commit: String, success: bool, affected_files: Vec<String>) -> Self {
+    pub fn new(
+        reverted_commit: String,
+        success: bool,
+        affected_files: Vec<String>,
+        revert_commit: Option<String>,
+    ) -> Self {
         Self {
             reverted_commit,
             success,
             affected_files,
+            revert_commit,
         }
     }
 }
@@ -378,6 +405,9 @@ impl CommitAmendEvent {
 pub struct CommitEvent {
     pub base_commit: Option<String>,
     pub commit_sha: String,
+    /// Set when the commit was made with --fixup / --squash and is meant to be autosquashed later
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fixup: Option<CommitFixup>,
 }
 
 impl CommitEvent {
@@ -386,6 +416,43 @@ impl CommitEvent {
         Self {
             base_commit,
             commit_sha,
+            fixup: None,
+        }
+    }
+}
+
+/// The flavours of `git commit --fixup` / `--squash`.
+/// - Fixup:  `--fixup=<commit>`          new content, message discarded on autosquash
+/// - Amend:  `--fixup=amend:<commit>`    new content, message replaces the target's
+/// - Reword: `--fixup=reword:<commit>`   message only, the index is NOT committed
+/// - Squash: `--squash=<commit>`         new content, messages combined on autosquash
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FixupKind {
+    Fixup,
+    Amend,
+    Reword,
+    Squash,
+}
+
+impl FixupKind {
+    /// Whether the commit carries content from the index (reword commits are always empty)
+    pub fn includes_content(&self) -> bool {
+        !matches!(self, FixupKind::Reword)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CommitFixup {
+    pub kind: FixupKind,
+    pub target_commit: String,
+}
+
+impl CommitFixup {
+    pub fn new(kind: FixupKind, target_commit: String) -> Self {
+        Self {
+            kind,
+            target_commit,
         }
     }
 }
@@ -491,6 +558,38 @@ pub fn deserialize_events_from_jsonl(jsonl: &str) -> Result<Vec<RewriteLogEvent>
     Ok(events)
 }
 
+/// Number of events `git-ai gc` keeps in the rewrite log. Readers only look at the newest
+/// events: the commit being attributed and the rebase or cherry-pick in progress.
+pub const COMPACT_KEEP_EVENTS: usize = 20;
+
+/// The events `git-ai gc` keeps, newest first: the newest `COMPACT_KEEP_EVENTS`, and
+/// further back to a rebase or cherry-pick that hasn't completed or been aborted yet
+pub fn compact_events(mut events: Vec<RewriteLogEvent>) -> Vec<RewriteLogEvent> {
+    let mut rebase_ended = false;
+    let mut cherry_pick_ended = false;
+    let mut keep = COMPACT_KEEP_EVENTS;
+    for (i, event) in events.iter().enumerate() {
+        match event {
+            RewriteLogEvent::RebaseComplete { .. } | RewriteLogEvent::RebaseAbort { .. } => {
+                rebase_ended = true
+            }
+            RewriteLogEvent::CherryPickComplete { .. }
+            | RewriteLogEvent::CherryPickAbort { .. } => cherry_pick_ended = true,
+            RewriteLogEvent::RebaseStart { .. } if !rebase_ended => {
+                keep = keep.max(i + 1);
+                rebase_ended = true;
+            }
+            RewriteLogEvent::CherryPickStart { .. } if !cherry_pick_ended => {
+                keep = keep.max(i + 1);
+                cherry_pick_ended = true;
+            }
+            _ => {}
+        }
+    }
+    events.truncate(keep);
+    events
+}
+
 /// Append a single event to JSONL file (prepends to maintain newest-first order)
 pub fn append_event_to_file(
     file_path: &std::path::Path,
@@ -501,7 +600,7 @@ pub fn append_event_to_file(
 
     if !file_path.exists() {
         // File doesn't exist - create it with just the new event
-        std::fs::write(file_path, format!("{}\n", new_event_json))?;
+        crate::fault::write(file_path, format!("{}\n", new_event_json))?;
         return Ok(());
     }
 
@@ -510,7 +609,7 @@ pub fn append_event_to_file(
 
     if existing_content.trim().is_empty() {
         // Empty file - just write the new event
-        std::fs::write(file_path, format!("{}\n", new_event_json))?;
+        crate::fault::write(file_path, format!("{}\n", new_event_json))?;
         return Ok(());
     }
 
@@ -529,7 +628,7 @@ pub fn append_event_to_file(
     }
 
     // Write back to file
-    std::fs::write(file_path, lines.join("\n"))?;
+    crate::fault::write(file_path, lines.join("\n"))?;
 
     Ok(())
 }
@@ -538,6 +637,28 @@ pub fn append_event_to_file(
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_commit_fixup_event_serialization() {
+        let event = RewriteLogEvent::commit_fixup(
+            Some("base123".to_string()),
+            "new456".to_string(),
+            CommitFixup::new(FixupKind::Reword, "target789".to_string()),
+        );
+
+        let json = serde_json::to_string(&event).unwrap();
+        assert!(json.contains("\"kind\":\"reword\""));
+        let deserialized: RewriteLogEvent = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, event);
+
+        // Plain commit events stay backwards compatible (no fixup key written or required)
+        let plain = RewriteLogEvent::commit(None, "abc".to_string());
+        let json = serde_json::to_string(&plain).unwrap();
+        assert!(!json.contains("fixup"));
+        let deserialized: RewriteLogEvent =
+            serde_json::from_str(r#"{"commit":{"base_commit":null,"commit_sha":"abc"}}"#).unwrap();
+        assert_eq!(deserialized, plain);
+    }
+
     #[test]
     fn test_merge_event_serialization() {
         let event = RewriteLogEvent::merge(
@@ -629,6 +750,33 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_compact_events_keeps_newest_and_open_operations() {
+        let commit = |i: usize| RewriteLogEvent::commit(None, format!("commit{}", i));
+        let events: Vec<_> = (0..COMPACT_KEEP_EVENTS + 10).map(commit).collect();
+        assert_eq!(
+            compact_events(events.clone()),
+            events[..COMPACT_KEEP_EVENTS]
+        );
+
+        // A rebase still in progress keeps everything back to its start
+        let mut in_rebase = events.clone();
+        in_rebase.push(RewriteLogEvent::rebase_start(RebaseStartEvent::new(
+            "head".to_string(),
+            false,
+        )));
+        in_rebase.push(commit(99));
+        let compacted = compact_events(in_rebase.clone());
+        assert_eq!(compacted, in_rebase[..in_rebase.len() - 1]);
+
+        // A finished one doesn't
+        let mut finished = vec![RewriteLogEvent::rebase_abort(RebaseAbortEvent::new(
+            "head".to_string(),
+        ))];
+        finished.extend(in_rebase);
+        assert_eq!(compact_events(finished).len(), COMPACT_KEEP_EVENTS);
+    }
+
     #[test]
     fn test_append_event_to_jsonl() {
         let event1 = RewriteLogEvent::merge(
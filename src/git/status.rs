@@ -44,21 +44,24 @@ pub enum EntryKind {
     Ignored,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct StatusEntry {
-    pub path: String,
+/// A status entry borrowing its paths from the raw `git status` output, so scanning the
+/// status of a large working tree only allocates for the paths the caller keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StatusEntry<'a> {
+    pub path: &'a str,
     pub staged: StatusCode,
     pub unstaged: StatusCode,
     pub kind: EntryKind,
-    pub orig_path: Option<String>,
+    pub orig_path: Option<&'a str>,
 }
 
 impl Repository {
-    // Run status porcelain v2 on the repository. Will fail for bare repositories.
-    pub fn status(
+    /// Run status porcelain v2 on the repository, returning the raw output to be read with
+    /// [`porcelain_v2_entries`]. Will fail for bare repositories.
+    pub fn status_porcelain_v2<S: AsRef<str>>(
         &self,
-        pathspecs: Option<&HashSet<String>>,
-    ) -> Result<Vec<StatusEntry>, GitAiError> {
+        pathspecs: Option<&HashSet<S>>,
+    ) -> Result<Vec<u8>, GitAiError> {
         let mut args = self.global_args_for_exec();
         args.push("status".to_string());
         args.push("--porcelain=v2".to_string());
@@ -66,10 +69,9 @@ impl Repository {
 
         // Add pathspecs if provided
         if let Some(paths) = pathspecs {
+            args.reserve(paths.len() + 1);
             args.push("--".to_string());
-            for path in paths {
-                args.push(path.clone());
-            }
+            args.extend(paths.iter().map(|path| path.as_ref().to_string()));
         }
 
         let output = exec_git(&args)?;
@@ -81,51 +83,67 @@ impl Repository {
             )));
         }
 
-        parse_porcelain_v2(&output.stdout)
+        Ok(output.stdout)
+    }
+
+    /// Every untracked file that isn't ignored, one by one rather than collapsed into its
+    /// directory the way `git status` shows new directories
+    pub fn untracked_files(&self) -> Result<Vec<String>, GitAiError> {
+        let mut args = self.global_args_for_exec();
+        args.push("ls-files".to_string());
+        args.push("--others".to_string());
+        args.push("--exclude-standard".to_string());
+        args.push("--full-name".to_string());
+        args.push("-z".to_string());
+        args.push("--".to_string());
+        args.push(":/".to_string());
+
+        let output = exec_git(&args)?;
+        Ok(String::from_utf8(output.stdout)?
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+/// Iterate over the entries of `git status --porcelain=v2 -z` output without copying it
+pub fn porcelain_v2_entries(data: &[u8]) -> PorcelainV2Entries<'_> {
+    PorcelainV2Entries {
+        parts: data.split(|byte| *byte == 0),
     }
 }
 
-fn parse_porcelain_v2(data: &[u8]) -> Result<Vec<StatusEntry>, GitAiError> {
-    let mut entries = Vec::new();
-    let mut parts = data
-        .split(|byte| *byte == 0)
-        .filter(|slice| !slice.is_empty())
-        .peekable();
+pub struct PorcelainV2Entries<'a> {
+    parts: std::slice::Split<'a, u8, fn(&u8) -> bool>,
+}
+
+impl<'a> Iterator for PorcelainV2Entries<'a> {
+    type Item = Result<StatusEntry<'a>, GitAiError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let raw = self.parts.find(|slice| !slice.is_empty())?;
+        Some(self.parse_record(raw))
+    }
+}
 
-    while let Some(raw) = parts.next() {
+impl<'a> PorcelainV2Entries<'a> {
+    fn parse_record(&mut self, raw: &'a [u8]) -> Result<StatusEntry<'a>, GitAiError> {
         let record = str::from_utf8(raw)?;
-        let mut chars = record.chars();
-        let tag = chars
-            .next()
-            .ok_or_else(|| GitAiError::Generic("Unexpected empty porcelain v2 record".into()))?;
+        let tag = record.as_bytes()[0];
 
         match tag {
-            '1' | 'u' => {
+            b'1' | b'u' => {
                 let mut fields = record.splitn(9, ' ');
                 let _ = fields.next(); // tag
-                let xy = fields
-                    .next()
-                    .ok_or_else(|| GitAiError::Generic("Missing XY field".into()))?;
-                if xy.len() != 2 {
-                    return Err(GitAiError::Generic(format!(
-                        "Unexpected XY field length: {}",
-                        xy
-                    )));
-                }
-                let staged = StatusCode::from(xy.chars().next().unwrap());
-                let unstaged = StatusCode::from(xy.chars().nth(1).unwrap());
+                let (staged, unstaged) = parse_xy(fields.next())?;
 
                 // skip submodule/metadata fields to capture path
-                for _ in 0..6 {
-                    fields.next();
-                }
-
                 let path = fields
-                    .next()
-                    .ok_or_else(|| GitAiError::Generic("Missing path field".into()))?
-                    .to_string();
+                    .nth(6)
+                    .ok_or_else(|| GitAiError::Generic("Missing path field".into()))?;
 
-                entries.push(StatusEntry {
+                Ok(StatusEntry {
                     path,
                     staged,
                     unstaged,
@@ -137,37 +155,22 @@ fn parse_porcelain_v2(data: &[u8]) -> Result<Vec<StatusEntry>, GitAiError> {
                         EntryKind::Ordinary
                     },
                     orig_path: None,
-                });
+                })
             }
-            '2' => {
+            b'2' => {
                 let mut fields = record.splitn(10, ' ');
                 let _ = fields.next(); // tag
-                let xy = fields
-                    .next()
-                    .ok_or_else(|| GitAiError::Generic("Missing XY field".into()))?;
-                if xy.len() != 2 {
-                    return Err(GitAiError::Generic(format!(
-                        "Unexpected XY field length: {}",
-                        xy
-                    )));
-                }
-                let staged = StatusCode::from(xy.chars().next().unwrap());
-                let unstaged = StatusCode::from(xy.chars().nth(1).unwrap());
+                let (staged, unstaged) = parse_xy(fields.next())?;
 
                 // skip submodule/metadata fields
-                for _ in 0..7 {
-                    fields.next();
-                }
-
                 let path = fields
-                    .next()
-                    .ok_or_else(|| GitAiError::Generic("Missing path field".into()))?
-                    .to_string();
+                    .nth(7)
+                    .ok_or_else(|| GitAiError::Generic("Missing path field".into()))?;
 
-                let orig_path_bytes = parts.next().ok_or_else(|| {
+                let orig_path_bytes = self.parts.next().ok_or_else(|| {
                     GitAiError::Generic("Missing original path for rename/copy".into())
                 })?;
-                let orig_path = str::from_utf8(orig_path_bytes)?.to_string();
+                let orig_path = str::from_utf8(orig_path_bytes)?;
 
                 let kind = match staged {
                     StatusCode::Renamed => EntryKind::Rename,
@@ -175,46 +178,45 @@ fn parse_porcelain_v2(data: &[u8]) -> Result<Vec<StatusEntry>, GitAiError> {
                     _ => EntryKind::Ordinary,
                 };
 
-                entries.push(StatusEntry {
+                Ok(StatusEntry {
                     path,
                     staged,
                     unstaged,
                     kind,
                     orig_path: Some(orig_path),
-                });
-            }
-            '?' => {
-                let path = record.strip_prefix("? ").unwrap_or(record).to_string();
-
-                entries.push(StatusEntry {
-                    path,
-                    staged: StatusCode::Unmodified,
-                    unstaged: StatusCode::Untracked,
-                    kind: EntryKind::Untracked,
-                    orig_path: None,
-                });
-            }
-            '!' => {
-                let path = record.strip_prefix("! ").unwrap_or(record).to_string();
-
-                entries.push(StatusEntry {
-                    path,
-                    staged: StatusCode::Unmodified,
-                    unstaged: StatusCode::Ignored,
-                    kind: EntryKind::Ignored,
-                    orig_path: None,
-                });
-            }
-            other => {
-                return Err(GitAiError::Generic(format!(
-                    "Unsupported porcelain v2 record tag: {}",
-                    other
-                )));
+                })
             }
+            b'?' => Ok(StatusEntry {
+                path: record.strip_prefix("? ").unwrap_or(record),
+                staged: StatusCode::Unmodified,
+                unstaged: StatusCode::Untracked,
+                kind: EntryKind::Untracked,
+                orig_path: None,
+            }),
+            b'!' => Ok(StatusEntry {
+                path: record.strip_prefix("! ").unwrap_or(record),
+                staged: StatusCode::Unmodified,
+                unstaged: StatusCode::Ignored,
+                kind: EntryKind::Ignored,
+                orig_path: None,
+            }),
+            _ => Err(GitAiError::Generic(format!(
+                "Unsupported porcelain v2 record tag: {}",
+                record.chars().next().unwrap_or_default()
+            ))),
         }
     }
+}
 
-    Ok(entries)
+fn parse_xy(field: Option<&str>) -> Result<(StatusCode, StatusCode), GitAiError> {
+    let xy = field.ok_or_else(|| GitAiError::Generic("Missing XY field".into()))?;
+    match xy.as_bytes() {
+        [x, y] => Ok((StatusCode::from(*x as char), StatusCode::from(*y as char))),
+        _ => Err(GitAiError::Generic(format!(
+            "Unexpected XY field length: {}",
+            xy
+        ))),
+    }
 }
 
 #[cfg(test)]
@@ -240,7 +242,9 @@ mod tests {
         raw.extend_from_slice(b"! target/.keep\0");
         raw.extend_from_slice(b"u UU N... 100644 100644 100644 eeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeeee ffffffffffffffffffffffffffffffffffffffff 1 2 3 some unmerged/path.txt\0");
 
-        let entries: Vec<StatusEntry> = parse_porcelain_v2(&raw).expect("parse succeeds");
+        let entries: Vec<StatusEntry> = porcelain_v2_entries(&raw)
+            .collect::<Result<_, _>>()
+            .expect("parse succeeds");
 
         // High-level assertions about the parsed content
         assert_eq!(entries.len(), 12);
@@ -250,10 +254,11 @@ mod tests {
                 .any(|e| e.path == "src/lib.rs" && e.staged == StatusCode::Modified)
         );
         assert!(entries.iter().any(|e| e.kind == EntryKind::Rename
-            && e.orig_path.as_deref() == Some("old utils/helpers.rs")));
+            && e.orig_path == Some("old utils/helpers.rs")));
         assert!(
-            entries.iter().any(|e| e.kind == EntryKind::Copy
-                && e.orig_path.as_deref() == Some("scripts/setup-old.sh"))
+            entries
+                .iter()
+                .any(|e| e.kind == EntryKind::Copy && e.orig_path == Some("scripts/setup-old.sh"))
         );
         assert!(entries.iter().any(|e| e.kind == EntryKind::Unmerged));
         assert!(
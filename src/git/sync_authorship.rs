@@ -1,5 +1,7 @@
+use crate::config::NotesConflict;
 use crate::git::refs::{
-    AI_AUTHORSHIP_PUSH_REFSPEC, copy_ref, merge_notes_from_ref, ref_exists, tracking_ref_for_remote,
+    authorship_push_refspec, copy_ref, merge_notes_from_ref, note_blobs, push_ref_for_remote,
+    ref_exists, tracking_ref_for_remote, write_notes_onto,
 };
 use crate::{
     error::GitAiError,
@@ -38,7 +40,27 @@ pub fn fetch_remote_from_args(
         .or_else(|| repository.upstream_remote().ok().flatten())
         .or_else(|| repository.get_default_remote().ok().flatten());
 
-    Ok(remote.unwrap().to_string())
+    remote
+        .ok_or_else(|| GitAiError::Generic("No remote to fetch authorship notes from".to_string()))
+}
+
+/// Whether `remote_name` has the authorship notes ref, by `git ls-remote`
+pub fn remote_has_notes(repository: &Repository, remote_name: &str) -> Result<bool, GitAiError> {
+    let mut ls_remote_args = repository.global_args_for_exec();
+    ls_remote_args.push("ls-remote".to_string());
+    ls_remote_args.push(remote_name.to_string());
+    ls_remote_args.push(repository.notes_ref());
+
+    debug_log(&format!("ls-remote command: {:?}", ls_remote_args));
+
+    let output = exec_git(&ls_remote_args)?;
+    let result = String::from_utf8_lossy(&output.stdout).to_string();
+    debug_log(&format!("ls-remote stdout: '{}'", result));
+    debug_log(&format!(
+        "ls-remote stderr: '{}'",
+        String::from_utf8_lossy(&output.stderr)
+    ));
+    Ok(!result.trim().is_empty())
 }
 
 // for use with post-fetch and post-pull and post-clone hooks
@@ -47,42 +69,28 @@ pub fn fetch_authorship_notes(
     remote_name: &str,
 ) -> Result<(), GitAiError> {
     // Generate tracking ref for this remote
-    let tracking_ref = tracking_ref_for_remote(&remote_name);
+    let tracking_ref = tracking_ref_for_remote(repository, remote_name);
 
     debug_log(&format!(
         "fetching authorship notes for remote '{}' to tracking ref '{}'",
         remote_name, tracking_ref
     ));
 
-    // First, check if the remote has refs/notes/ai using ls-remote
+    // First, check if the remote has the notes ref using ls-remote
     // This is important for bare repos where the refmap might not be configured
-    let mut ls_remote_args = repository.global_args_for_exec();
-    ls_remote_args.push("ls-remote".to_string());
-    ls_remote_args.push(remote_name.to_string());
-    ls_remote_args.push("refs/notes/ai".to_string());
-
-    debug_log(&format!("ls-remote command: {:?}", ls_remote_args));
-
-    match exec_git(&ls_remote_args) {
-        Ok(output) => {
-            let result = String::from_utf8_lossy(&output.stdout).to_string();
-            debug_log(&format!("ls-remote stdout: '{}'", result));
+    match remote_has_notes(repository, remote_name) {
+        Ok(true) => {
             debug_log(&format!(
-                "ls-remote stderr: '{}'",
-                String::from_utf8_lossy(&output.stderr)
+                "found authorship notes on remote '{}'",
+                remote_name
             ));
-
-            if result.trim().is_empty() {
-                debug_log(&format!(
-                    "no authorship notes found on remote '{}', nothing to sync",
-                    remote_name
-                ));
-                return Ok(());
-            }
+        }
+        Ok(false) => {
             debug_log(&format!(
-                "found authorship notes on remote '{}'",
+                "no authorship notes found on remote '{}', nothing to sync",
                 remote_name
             ));
+            return Ok(());
         }
         Err(e) => {
             debug_log(&format!(
@@ -94,7 +102,7 @@ pub fn fetch_authorship_notes(
     }
 
     // Now fetch the notes to the tracking ref with explicit refspec
-    let fetch_refspec = format!("+refs/notes/ai:{}", tracking_ref);
+    let fetch_refspec = format!("+{}:{}", repository.notes_ref(), tracking_ref);
 
     // Build the internal authorship fetch with explicit flags and disabled hooks
     // IMPORTANT: use repository.global_args_for_exec() to ensure -C flag is present for bare repos
@@ -129,8 +137,8 @@ pub fn fetch_authorship_notes(
         }
     }
 
-    // After successful fetch, merge the tracking ref into refs/notes/ai
-    let local_notes_ref = "refs/notes/ai";
+    // After successful fetch, merge the tracking ref into the local notes ref
+    let local_notes_ref = &repository.notes_ref();
 
     if crate::git::refs::ref_exists(&repository, &tracking_ref) {
         if crate::git::refs::ref_exists(&repository, local_notes_ref) {
@@ -164,11 +172,16 @@ pub fn fetch_authorship_notes(
     Ok(())
 }
 // for use with post-push hook
-pub fn push_authorship_notes(repository: &Repository, remote_name: &str) -> Result<(), GitAiError> {
+/// Push the notes of `commits` to the remote, or the whole notes ref when `None`
+pub fn push_authorship_notes(
+    repository: &Repository,
+    remote_name: &str,
+    commits: Option<&[String]>,
+) -> Result<(), GitAiError> {
     // STEP 1: Fetch remote notes into tracking ref and merge before pushing
     // This ensures we don't lose notes from other branches/clones
-    let tracking_ref = tracking_ref_for_remote(&remote_name);
-    let fetch_refspec = format!("+refs/notes/ai:{}", tracking_ref);
+    let tracking_ref = tracking_ref_for_remote(repository, remote_name);
+    let fetch_refspec = format!("+{}:{}", repository.notes_ref(), tracking_ref);
 
     let mut fetch_before_push: Vec<String> = repository.global_args_for_exec();
     fetch_before_push.push("-c".to_string());
@@ -189,8 +202,8 @@ pub fn push_authorship_notes(repository: &Repository, remote_name: &str) -> Resu
 
     // Fetch is best-effort; if it fails (e.g., no remote notes yet), continue
     if exec_git(&fetch_before_push).is_ok() {
-        // Merge fetched notes into local refs/notes/ai
-        let local_notes_ref = "refs/notes/ai";
+        // Merge fetched notes into the local notes ref
+        let local_notes_ref = &repository.notes_ref();
 
         if ref_exists(repository, &tracking_ref) {
             if ref_exists(repository, local_notes_ref) {
@@ -215,6 +228,23 @@ pub fn push_authorship_notes(repository: &Repository, remote_name: &str) -> Resu
         }
     }
 
+    // Nothing written here or fetched from the remote yet, so nothing to push
+    if !ref_exists(repository, &repository.notes_ref()) {
+        debug_log("no authorship notes to push");
+        return Ok(());
+    }
+
+    let refspec = match commits {
+        Some(commits) => match prepare_selective_push(repository, remote_name, commits)? {
+            Some(push_ref) => format!("{}:{}", push_ref, repository.notes_ref()),
+            None => {
+                debug_log("the remote has the notes of every pushed commit");
+                return Ok(());
+            }
+        },
+        None => authorship_push_refspec(repository),
+    };
+
     // STEP 2: Push notes without force (requires fast-forward)
     let mut push_authorship: Vec<String> = repository.global_args_for_exec();
     push_authorship.push("-c".to_string());
@@ -224,13 +254,17 @@ pub fn push_authorship_notes(repository: &Repository, remote_name: &str) -> Resu
     push_authorship.push("--no-recurse-submodules".to_string());
     push_authorship.push("--no-verify".to_string());
     push_authorship.push(remote_name.to_string());
-    push_authorship.push(AI_AUTHORSHIP_PUSH_REFSPEC.to_string());
+    push_authorship.push(refspec);
 
     debug_log(&format!(
         "pushing authorship refs (no force): {:?}",
         &push_authorship
     ));
-    if let Err(e) = exec_git(&push_authorship) {
+    let result = exec_git(&push_authorship);
+    if commits.is_some() {
+        delete_ref(repository, &push_ref_for_remote(repository, remote_name));
+    }
+    if let Err(e) = result {
         // Best-effort; don't fail user operation due to authorship sync issues
         debug_log(&format!("authorship push skipped due to error: {}", e));
         return Err(e);
@@ -239,6 +273,51 @@ pub fn push_authorship_notes(repository: &Repository, remote_name: &str) -> Resu
     Ok(())
 }
 
+/// Build the notes ref a selective push sends: the remote's notes as last fetched, plus the
+/// local notes of `commits` it lacks or, with `notes_conflict = "ours"`, has another version
+/// of. Returns the ref, or `None` when it would add nothing to the remote's notes.
+fn prepare_selective_push(
+    repository: &Repository,
+    remote_name: &str,
+    commits: &[String],
+) -> Result<Option<String>, GitAiError> {
+    let local_notes = note_blobs(repository, &repository.notes_ref())?;
+    let tracking_ref = tracking_ref_for_remote(repository, remote_name);
+    let remote_notes = note_blobs(repository, &tracking_ref)?;
+    let keep_theirs = repository.settings().notes_conflict == NotesConflict::Theirs;
+
+    let notes: Vec<(&String, &String)> = commits
+        .iter()
+        .filter_map(|commit| local_notes.get(commit).map(|blob| (commit, blob)))
+        .filter(|(commit, blob)| match remote_notes.get(*commit) {
+            Some(remote_blob) => remote_blob != *blob && !keep_theirs,
+            None => true,
+        })
+        .collect();
+    if notes.is_empty() {
+        return Ok(None);
+    }
+    debug_log(&format!(
+        "pushing the notes of {} of {} pushed commits",
+        notes.len(),
+        commits.len()
+    ));
+
+    // Built on top of the remote's notes, so the push is a fast-forward
+    let push_ref = push_ref_for_remote(repository, remote_name);
+    let base_ref = ref_exists(repository, &tracking_ref).then_some(tracking_ref.as_str());
+    write_notes_onto(repository, base_ref, &push_ref, &notes)?;
+    Ok(Some(push_ref))
+}
+
+fn delete_ref(repository: &Repository, ref_name: &str) {
+    let mut args = repository.global_args_for_exec();
+    args.push("update-ref".to_string());
+    args.push("-d".to_string());
+    args.push(ref_name.to_string());
+    let _ = exec_git(&args);
+}
+
 fn extract_remote_from_fetch_args(args: &[String]) -> Option<String> {
     let mut after_double_dash = false;
 
@@ -2,6 +2,7 @@ use crate::authorship::attribution_tracker::Attribution;
 use crate::authorship::authorship_log_serialization::AuthorshipLog;
 use crate::authorship::post_commit::post_commit;
 use crate::authorship::working_log::{Checkpoint, CheckpointKind};
+use crate::commands::checkpoint::CheckpointOptions;
 use crate::commands::checkpoint_agent::agent_presets::AgentRunResult;
 use crate::commands::{blame, checkpoint::run as checkpoint};
 use crate::error::GitAiError;
@@ -349,10 +350,11 @@ impl TmpRepo {
             &self.repo_gitai,
             author,
             CheckpointKind::Human,
-            false, // show_working_log
-            false, // reset
-            true,
-            None, // agent_run_result
+            None,
+            CheckpointOptions {
+                quiet: true,
+                ..Default::default()
+            },
         )
     }
 
@@ -397,16 +399,20 @@ impl TmpRepo {
             repo_working_dir: None,
             edited_filepaths: None,
             will_edit_filepaths: None,
+            commit_message_source: None,
+            agent_version: None,
+            file_contents: None,
         };
 
         checkpoint(
             &self.repo_gitai,
             agent_name,
             CheckpointKind::AiAgent,
-            false, // show_working_log
-            false, // reset
-            true,
             Some(agent_run_result),
+            CheckpointOptions {
+                quiet: true,
+                ..Default::default()
+            },
         )
     }
 
@@ -424,10 +430,11 @@ impl TmpRepo {
             &self.repo_gitai,
             author,
             checkpoint_kind,
-            false, // show_working_log
-            false, // reset
-            true,  // quiet
             agent_run_result,
+            CheckpointOptions {
+                quiet: true,
+                ..Default::default()
+            },
         )
     }
 
@@ -1,7 +1,12 @@
+pub mod api;
 pub mod authorship;
+pub mod cancellation;
 pub mod commands;
 pub mod config;
 pub mod error;
+pub mod fault;
+pub mod fs_notify;
 pub mod git;
+pub mod runtime;
 pub mod ci;
 pub mod utils;
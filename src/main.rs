@@ -1,8 +1,12 @@
 mod authorship;
+mod cancellation;
 mod commands;
 mod config;
 mod error;
+mod fault;
+mod fs_notify;
 mod git;
+mod runtime;
 mod ci;
 mod utils;
 
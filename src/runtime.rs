@@ -0,0 +1,125 @@
+//! Runtime for network-bound background work, such as syncing authorship notes with a remote
+//! while the user's push or fetch runs. Subsystems that talk to the network spawn their work
+//! here instead of on threads of their own, so they share one executor and handle timeouts
+//! and cancellation the same way.
+//!
+//! By default this runs on smol, which git-ai already uses for local concurrency. Building with
+//! the `tokio-runtime` feature moves it onto a shared multi-threaded tokio runtime, for
+//! integrations whose clients (and connection pools) need tokio.
+
+use crate::error::GitAiError;
+use std::future::Future;
+use std::time::Duration;
+
+/// How long a git command waits for its background network work before giving up on it
+pub const NETWORK_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[cfg(feature = "tokio-runtime")]
+fn tokio_runtime() -> &'static tokio::runtime::Runtime {
+    static RUNTIME: std::sync::OnceLock<tokio::runtime::Runtime> = std::sync::OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .thread_name("git-ai-runtime")
+            .enable_all()
+            .build()
+            .expect("failed to start the git-ai runtime")
+    })
+}
+
+/// Run a future to completion on the current thread
+pub fn block_on<F: Future>(future: F) -> F::Output {
+    #[cfg(feature = "tokio-runtime")]
+    {
+        tokio_runtime().block_on(future)
+    }
+    #[cfg(not(feature = "tokio-runtime"))]
+    {
+        smol::block_on(future)
+    }
+}
+
+/// Handle to work running in the background. Dropping it cancels work that hasn't started yet;
+/// blocking work that has started runs to completion and its result is discarded.
+pub struct Task<T> {
+    #[cfg(feature = "tokio-runtime")]
+    handle: tokio::task::JoinHandle<T>,
+    #[cfg(not(feature = "tokio-runtime"))]
+    task: smol::Task<T>,
+}
+
+/// Run blocking work (a git subprocess, a synchronous client call) in the background
+pub fn spawn_blocking<T, F>(work: F) -> Task<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    #[cfg(feature = "tokio-runtime")]
+    {
+        Task {
+            handle: tokio_runtime().spawn_blocking(work),
+        }
+    }
+    #[cfg(not(feature = "tokio-runtime"))]
+    {
+        Task {
+            task: smol::spawn(smol::unblock(work)),
+        }
+    }
+}
+
+impl<T> Task<T> {
+    /// Wait for the task to finish, giving up on it after `timeout`
+    pub fn join_timeout(self, timeout: Duration) -> Result<T, GitAiError> {
+        let timed_out = || {
+            GitAiError::Generic(format!(
+                "background task timed out after {}s",
+                timeout.as_secs_f32()
+            ))
+        };
+
+        #[cfg(feature = "tokio-runtime")]
+        {
+            let mut handle = self.handle;
+            // The timer has to be created inside the runtime
+            match block_on(async { tokio::time::timeout(timeout, &mut handle).await }) {
+                Ok(Ok(value)) => Ok(value),
+                Ok(Err(e)) => Err(GitAiError::Generic(format!(
+                    "background task failed: {}",
+                    e
+                ))),
+                Err(_) => {
+                    handle.abort();
+                    Err(timed_out())
+                }
+            }
+        }
+        #[cfg(not(feature = "tokio-runtime"))]
+        {
+            let task = self.task;
+            block_on(smol::future::or(async { Some(task.await) }, async {
+                smol::Timer::after(timeout).await;
+                None
+            }))
+            .ok_or_else(timed_out)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spawn_blocking_returns_result() {
+        let task = spawn_blocking(|| 21 * 2);
+        assert_eq!(task.join_timeout(Duration::from_secs(10)).unwrap(), 42);
+    }
+
+    #[test]
+    fn test_join_timeout_gives_up_on_slow_work() {
+        let task = spawn_blocking(|| std::thread::sleep(Duration::from_secs(5)));
+        let started = std::time::Instant::now();
+        assert!(task.join_timeout(Duration::from_millis(50)).is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::git::diff_tree_to_tree::Diff;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 
 /// Debug logging utility function
@@ -15,6 +16,32 @@ pub fn debug_log(msg: &str) {
     }
 }
 
+/// `content` with CRLF line endings turned into LF. Working-log file versions are hashed and
+/// diffed this way, so a checkout with `core.autocrlf` (or an editor saving CRLF) doesn't
+/// make every line differ from the LF version git stores.
+pub fn normalize_line_endings(content: String) -> String {
+    if content.contains("\r\n") {
+        content.replace("\r\n", "\n")
+    } else {
+        content
+    }
+}
+
+/// `path` as a repository path: `/` separated, as git writes paths on every platform, whether
+/// it came from `Path` or from a Windows tool using `\`
+pub fn to_repo_path(path: &str) -> String {
+    path.replace('\\', "/")
+}
+
+/// The file at repository path `relative` under `root`, joined a component at a time so the
+/// result uses the platform's separators throughout
+pub fn join_repo_path(root: &Path, relative: &str) -> PathBuf {
+    relative
+        .split('/')
+        .filter(|component| !component.is_empty() && *component != ".")
+        .fold(root.to_path_buf(), |path, component| path.join(component))
+}
+
 /// Print a git diff in a readable format
 ///
 /// Prints the diff between two commits/trees showing which files changed and their status.
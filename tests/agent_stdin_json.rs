@@ -0,0 +1,97 @@
+#[macro_use]
+mod repos;
+use repos::test_repo::TestRepo;
+use std::fs;
+use std::io::Write;
+use std::process::Stdio;
+
+fn checkpoint_stdin_json(repo: &TestRepo, payload: &serde_json::Value) -> Result<String, String> {
+    let mut child = repo
+        .git_ai_command(&["checkpoint", "agent", "--stdin-json"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(payload.to_string().as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+    if output.status.success() {
+        Ok(stderr)
+    } else {
+        Err(stderr)
+    }
+}
+
+#[test]
+fn test_stdin_json_checkpoint_attributes_agent_edits() {
+    let repo = TestRepo::new();
+    let mut lib = repo.filename("lib.rs");
+    lib.set_contents(lines!["fn base() {}"]);
+    repo.stage_all_and_commit("Base").unwrap();
+
+    repo.git_ai(&["checkpoint"]).unwrap();
+    fs::write(repo.path().join("lib.rs"), "fn base() {}\nfn parse() {}\n").unwrap();
+    checkpoint_stdin_json(
+        &repo,
+        &serde_json::json!({
+            "version": 1,
+            "tool": "aider",
+            "session_id": "session-1",
+            "model": "gpt-4o",
+            "messages": [
+                { "type": "user", "text": "Add parse", "timestamp": "2025-01-15T10:30:00Z" },
+                { "type": "assistant", "text": "Adding parse" }
+            ],
+            "edited_filepaths": ["lib.rs"],
+            "agent_version": "0.80.0"
+        }),
+    )
+    .unwrap();
+
+    let commit = repo.stage_all_and_commit("Agent work").unwrap();
+    let prompts: Vec<_> = commit.authorship_log.metadata.prompts.values().collect();
+    assert_eq!(prompts.len(), 1);
+    assert_eq!(prompts[0].agent_id.tool, "aider");
+    assert_eq!(prompts[0].agent_id.id, "session-1");
+    assert_eq!(prompts[0].agent_id.model, "gpt-4o");
+    assert_eq!(prompts[0].messages.len(), 2);
+
+    let blame = repo.git_ai(&["blame", "lib.rs"]).unwrap();
+    let lines: Vec<&str> = blame.lines().collect();
+    assert!(!lines[0].contains("aider"), "{}", blame);
+    assert!(lines[1].contains("aider"), "{}", blame);
+}
+
+#[test]
+fn test_stdin_json_checkpoint_rejects_invalid_payload() {
+    let repo = TestRepo::new();
+    let mut lib = repo.filename("lib.rs");
+    lib.set_contents(lines!["fn base() {}"]);
+    repo.stage_all_and_commit("Base").unwrap();
+    fs::write(repo.path().join("lib.rs"), "fn base() {}\nfn parse() {}\n").unwrap();
+
+    let err = checkpoint_stdin_json(
+        &repo,
+        &serde_json::json!({ "tool": "aider", "session_id": "", "edited_filepaths": ["lib.rs"] }),
+    )
+    .unwrap_err();
+    assert!(err.contains("session_id is empty"), "{}", err);
+
+    let err = repo
+        .git_ai(&["checkpoint", "agent", "--stdin-json", "--verbose"])
+        .unwrap_err();
+    assert!(
+        err.contains("Unknown checkpoint agent argument: --verbose"),
+        "{}",
+        err
+    );
+
+    let checkpoints = repo.current_working_logs().read_all_checkpoints().unwrap();
+    assert!(checkpoints.is_empty());
+}
@@ -0,0 +1,85 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+use std::path::PathBuf;
+
+/// Commit parser.rs and then main.rs on a branch, mail them as a two patch series with the
+/// first one declared AI-generated, and go back to where the branch started
+fn mail_series(repo: &TestRepo) -> PathBuf {
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Project"]);
+    repo.stage_all_and_commit("Initial commit").unwrap();
+    let main_branch = repo.current_branch();
+
+    repo.git(&["checkout", "-b", "contributor"]).unwrap();
+    let mut parser = repo.filename("parser.rs");
+    parser.set_contents(lines!["fn parse() {}", "fn tokenize() {}"]);
+    repo.stage_all_and_commit("Add the parser").unwrap();
+    let mut main = repo.filename("main.rs");
+    main.set_contents(lines!["fn main() { parse(); }"]);
+    repo.stage_all_and_commit("Call the parser").unwrap();
+
+    let series = repo
+        .git(&["format-patch", "-2", "--stdout", "--numbered"])
+        .unwrap();
+    let series = series.replacen(
+        "\nSubject: ",
+        "\nX-AI-Generated: claude; model=claude-sonnet-4\nSubject: ",
+        1,
+    );
+    let path = repo.path().join(".git").join("series.mbox");
+    fs::write(&path, series).unwrap();
+
+    repo.git(&["checkout", &main_branch]).unwrap();
+    repo.git(&["branch", "-D", "contributor"]).unwrap();
+    // The maintainer's branch moved on since the contributor branched off
+    let mut license = repo.filename("LICENSE");
+    license.set_contents(lines!["MIT"]);
+    repo.stage_all_and_commit("Add a license").unwrap();
+    path
+}
+
+#[test]
+fn test_am_attributes_declared_patches_to_their_tool() {
+    let repo = TestRepo::new();
+    let series = mail_series(&repo);
+
+    repo.git(&["am", "--3way", series.to_str().unwrap()])
+        .unwrap();
+
+    let first = repo.note_for("HEAD~1");
+    let provenance = first.metadata.patch_series.as_ref().unwrap();
+    assert_eq!(provenance.position.as_deref(), Some("1/2"));
+    assert_eq!(provenance.ai_generated.as_deref(), Some("claude"));
+    let prompt = first.metadata.prompts.values().next().unwrap();
+    assert_eq!(prompt.agent_id.tool, "claude");
+    assert_eq!(prompt.agent_id.model, "claude-sonnet-4");
+    assert_eq!(prompt.total_additions, 2);
+
+    let second = repo.note_for("HEAD");
+    let provenance = second.metadata.patch_series.as_ref().unwrap();
+    assert_eq!(provenance.position.as_deref(), Some("2/2"));
+    assert_eq!(provenance.ai_generated, None);
+    assert!(second.metadata.prompts.is_empty());
+
+    let mut parser = repo.filename("parser.rs");
+    parser.assert_lines_and_blame(lines!["fn parse() {}".ai(), "fn tokenize() {}".ai()]);
+    let mut main = repo.filename("main.rs");
+    main.assert_lines_and_blame(lines!["fn main() { parse(); }".human()]);
+}
+
+#[test]
+fn test_am_keeps_uncommitted_ai_work() {
+    let repo = TestRepo::new();
+    let series = mail_series(&repo);
+
+    // Unstaged AI edits are allowed while am runs and stay in the working directory
+    let mut notes = repo.filename("NOTES.md");
+    notes.set_contents_no_stage(lines!["Draft".ai()]);
+    repo.git(&["am", series.to_str().unwrap()]).unwrap();
+
+    repo.stage_all_and_commit("Notes").unwrap();
+    notes.assert_lines_and_blame(lines!["Draft".ai()]);
+}
@@ -445,3 +445,60 @@ fn test_amend_with_unstaged_middle_section() {
         "// AI section 3 line 2".ai()
     ]);
 }
+
+/// `--fixup=amend:` creates a new commit (not an amend) that carries the staged content.
+#[test]
+fn test_fixup_amend_creates_new_commit_with_attribution() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+
+    file.set_contents(lines!["line 1", "line 2"]);
+    let initial = repo.stage_all_and_commit("Initial commit").unwrap();
+
+    file.insert_at(2, lines!["// AI fixup line".ai()]);
+    repo.git(&["add", "-A"]).unwrap();
+    repo.git_with_env(&["commit", "--fixup=amend:HEAD"], &[("GIT_EDITOR", "true")])
+        .unwrap();
+
+    // The original commit must be untouched
+    let parent = repo.git(&["rev-parse", "HEAD~1"]).unwrap();
+    assert_eq!(parent.trim(), initial.commit_sha);
+
+    file.assert_lines_and_blame(lines![
+        "line 1".human(),
+        "line 2".human(),
+        "// AI fixup line".ai()
+    ]);
+}
+
+/// `--fixup=reword:` commits only a message, so staged AI lines must stay pending.
+#[test]
+fn test_fixup_reword_keeps_staged_ai_lines_pending() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+
+    file.set_contents(lines!["line 1", "line 2"]);
+    repo.stage_all_and_commit("Initial commit").unwrap();
+
+    file.insert_at(2, lines!["// AI staged line".ai()]);
+    repo.git(&["add", "-A"]).unwrap();
+    repo.git_with_env(
+        &["commit", "--fixup=reword:HEAD"],
+        &[("GIT_EDITOR", "true")],
+    )
+    .unwrap();
+
+    // The reword commit didn't include the index
+    let changed = repo
+        .git(&["show", "--name-only", "--format=", "HEAD"])
+        .unwrap();
+    assert!(changed.trim().is_empty());
+
+    repo.stage_all_and_commit("Add AI line").unwrap();
+
+    file.assert_lines_and_blame(lines![
+        "line 1".human(),
+        "line 2".human(),
+        "// AI staged line".ai()
+    ]);
+}
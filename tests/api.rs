@@ -0,0 +1,67 @@
+#[macro_use]
+mod repos;
+use git_ai::api::{CheckpointRequest, GitAi};
+use git_ai::authorship::working_log::AgentId;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+fn agent() -> AgentId {
+    AgentId {
+        tool: "plugin".to_string(),
+        id: "session-1".to_string(),
+        model: "sonnet".to_string(),
+    }
+}
+
+#[test]
+fn test_api_checkpoint_blame_and_stats() {
+    let repo = TestRepo::new();
+
+    fs::write(repo.path().join("lib.rs"), "// base\n").unwrap();
+    repo.stage_all_and_commit("Base").unwrap();
+
+    // Keep the checkpoint's repository registration out of the real home directory
+    let git_ai = GitAi::open(repo.path())
+        .unwrap()
+        .with_repo_registry(repo.repo_registry_path());
+    fs::write(
+        repo.path().join("lib.rs"),
+        "// base\nfn one() {}\nfn two() {}\n",
+    )
+    .unwrap();
+    let summary = git_ai
+        .checkpoint(CheckpointRequest::agent(agent()).with_files(vec!["lib.rs".to_string()]))
+        .unwrap();
+    assert_eq!(summary.files_changed, 1);
+    repo.stage_all_and_commit("Agent work").unwrap();
+
+    let lines = git_ai.blame("lib.rs").unwrap();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[0].line, 1);
+    assert!(lines[0].prompt.is_none());
+    assert_eq!(lines[1].author, "plugin");
+    assert_eq!(lines[2].prompt.as_ref().unwrap().agent_id.id, "session-1");
+
+    let stats = git_ai.stats("HEAD").unwrap();
+    assert_eq!(stats.ai_additions, 2);
+
+    let log = git_ai.authorship("HEAD").unwrap().unwrap();
+    assert_eq!(log.attestations[0].file_path, "lib.rs");
+
+    let registry = fs::read_to_string(repo.repo_registry_path()).unwrap();
+    assert!(registry.contains(&*repo.path().canonicalize().unwrap().to_string_lossy()));
+}
+
+#[test]
+fn test_api_errors_instead_of_exiting() {
+    let dir = tempfile::tempdir().unwrap();
+    assert!(GitAi::open(dir.path()).is_err());
+
+    let repo = TestRepo::new();
+    let git_ai = GitAi::open(repo.path()).unwrap();
+    let mut request = CheckpointRequest::agent(agent());
+    request.agent_id = None;
+    assert!(git_ai.checkpoint(request).is_err());
+    assert!(git_ai.blame("missing.rs").is_err());
+    assert!(git_ai.stats("no-such-ref").is_err());
+}
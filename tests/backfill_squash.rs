@@ -0,0 +1,122 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::{TestRepo, plain_git};
+use std::path::{Path, PathBuf};
+
+/// A repo with a bare `origin`, and a feature branch with AI lines pushed to it
+fn repo_with_pushed_feature() -> (TestRepo, PathBuf) {
+    let repo = TestRepo::new();
+    let origin = repo.path().with_extension("origin.git");
+    plain_git(
+        repo.path().parent().unwrap(),
+        &["init", "-q", "--bare", origin.to_str().unwrap()],
+    );
+    repo.git(&["remote", "add", "origin", origin.to_str().unwrap()])
+        .unwrap();
+
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Project"]);
+    repo.stage_all_and_commit("Initial").unwrap();
+    let main = repo.current_branch();
+    repo.git(&["push", "-u", "origin", &main]).unwrap();
+
+    repo.git(&["checkout", "-b", "feature"]).unwrap();
+    let mut parser = repo.filename("parser.py");
+    parser.set_contents(lines!["def parse():".ai(), "    return 1".ai()]);
+    repo.stage_all_and_commit("Add parser").unwrap();
+    let mut lexer = repo.filename("lexer.py");
+    lexer.set_contents(lines!["def lex():", "    return []".ai()]);
+    repo.stage_all_and_commit("Add lexer").unwrap();
+    repo.git(&["push", "origin", "feature"]).unwrap();
+    repo.git(&["checkout", &main]).unwrap();
+    (repo, origin)
+}
+
+/// Squash-merge feature into the default branch on "the server" and delete the branch there
+fn squash_on_server(repo: &TestRepo, origin: &Path) {
+    let main = repo.current_branch();
+    let server = origin.with_extension("work");
+    plain_git(
+        origin.parent().unwrap(),
+        &[
+            "clone",
+            "-q",
+            origin.to_str().unwrap(),
+            server.to_str().unwrap(),
+        ],
+    );
+    plain_git(&server, &["config", "user.name", "Server"]);
+    plain_git(&server, &["config", "user.email", "server@example.com"]);
+    plain_git(&server, &["merge", "-q", "--squash", "origin/feature"]);
+    plain_git(
+        &server,
+        &["commit", "-q", "-m", "Add parser and lexer (#1)"],
+    );
+    plain_git(&server, &["push", "-q", "origin", &main]);
+    plain_git(&server, &["push", "-q", "origin", "--delete", "feature"]);
+}
+
+#[test]
+fn test_pull_recovers_authorship_of_server_squash() {
+    let (repo, origin) = repo_with_pushed_feature();
+    squash_on_server(&repo, &origin);
+
+    let output = repo.git(&["pull", "--prune", "origin"]).unwrap();
+    assert!(
+        output.contains("recovered authorship of origin/feature"),
+        "{}",
+        output
+    );
+
+    let mut parser = repo.filename("parser.py");
+    parser.assert_lines_and_blame(lines!["def parse():".ai(), "    return 1".ai()]);
+    let mut lexer = repo.filename("lexer.py");
+    lexer.assert_lines_and_blame(lines!["def lex():".human(), "    return []".ai()]);
+}
+
+#[test]
+fn test_backfill_squash_command() {
+    let (repo, origin) = repo_with_pushed_feature();
+    squash_on_server(&repo, &origin);
+    // Fetched without pruning, so nothing is detected
+    repo.git(&["pull", "origin"]).unwrap();
+    let squash_commit = plain_git(repo.path(), &["rev-parse", "HEAD"]);
+    assert!(
+        repo.git_ai(&["stats", &squash_commit, "--json"])
+            .unwrap()
+            .contains("\"ai_additions\":0")
+    );
+
+    // The local branch survives as the record of what was squashed
+    let output = repo
+        .git_ai(&["backfill-squash", &squash_commit, "--from", "feature"])
+        .unwrap();
+    assert!(
+        output.contains("Recovered authorship of feature"),
+        "{}",
+        output
+    );
+    let mut parser = repo.filename("parser.py");
+    parser.assert_lines_and_blame(lines!["def parse():".ai(), "    return 1".ai()]);
+
+    // Once it has a note, replacing it takes --force
+    assert!(
+        repo.git_ai(&["backfill-squash", &squash_commit, "--from", "feature"])
+            .is_err()
+    );
+    repo.git_ai(&[
+        "backfill-squash",
+        &squash_commit,
+        "--from",
+        "feature",
+        "--force",
+    ])
+    .unwrap();
+
+    // A branch that's in the commit's history wasn't squashed
+    assert!(
+        repo.git_ai(&["backfill-squash", &squash_commit, "--from", "HEAD~1"])
+            .is_err()
+    );
+}
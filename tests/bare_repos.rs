@@ -0,0 +1,77 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::{TestRepo, plain_git};
+use std::path::{Path, PathBuf};
+
+/// A bare clone of `repo` with its notes, like the copy a code host keeps
+fn bare_clone(repo: &TestRepo, notes_ref: &str) -> PathBuf {
+    let bare = repo.path().with_extension("bare.git");
+    plain_git(
+        repo.path(),
+        &["clone", "-q", "--bare", ".", bare.to_str().unwrap()],
+    );
+    plain_git(
+        repo.path(),
+        &[
+            "push",
+            "-q",
+            bare.to_str().unwrap(),
+            &format!("{}:{}", notes_ref, notes_ref),
+        ],
+    );
+    bare
+}
+
+fn git_ai_in(repo: &TestRepo, dir: &Path, args: &[&str]) -> String {
+    let output = repo.git_ai_command(args).current_dir(dir).output().unwrap();
+    assert!(
+        output.status.success(),
+        "git-ai {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+#[test]
+fn test_read_only_commands_work_in_bare_repositories() {
+    let repo = TestRepo::new();
+    // The bare copy only has the committed .gitai.toml to learn the notes ref from
+    let mut config = repo.filename(".gitai.toml");
+    config.set_contents(lines!["notes_ref = \"team\""]);
+    repo.stage_all_and_commit("Configure git-ai").unwrap();
+
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn human() {}", "fn generated() {}".ai()]);
+    let commit = repo.stage_all_and_commit("Add lib").unwrap();
+
+    let bare = bare_clone(&repo, "refs/notes/team");
+
+    let stats: serde_json::Value =
+        serde_json::from_str(&git_ai_in(&repo, &bare, &["stats", "--json"])).unwrap();
+    assert_eq!(stats["ai_additions"], 1);
+
+    let range = format!("{}~1..{}", commit.commit_sha, commit.commit_sha);
+    let stats: serde_json::Value =
+        serde_json::from_str(&git_ai_in(&repo, &bare, &["stats", &range, "--json"])).unwrap();
+    // Without a remote to fetch the range from, this would fail if it tried
+    assert_eq!(stats["authorship_stats"]["commits_with_authorship"], 1);
+
+    let blame = git_ai_in(&repo, &bare, &["blame", "lib.rs"]);
+    let lines: Vec<&str> = blame.lines().collect();
+    assert_eq!(lines.len(), 2, "{}", blame);
+    assert!(lines[1].contains("mock_ai"), "{}", blame);
+    assert!(!lines[0].contains("mock_ai"), "{}", blame);
+
+    let prompt = git_ai_in(&repo, &bare, &["show-prompt", "HEAD", "lib.rs", "2"]);
+    assert!(prompt.contains("agent:   mock_ai"), "{}", prompt);
+
+    let csv = git_ai_in(&repo, &bare, &["export", "csv"]);
+    assert!(csv.contains(&commit.commit_sha), "{}", csv);
+
+    let check = git_ai_in(&repo, &bare, &["check", "HEAD"]);
+    assert!(check.contains("1 commit(s) pass"), "{}", check);
+
+    std::fs::remove_dir_all(&bare).unwrap();
+}
@@ -0,0 +1,109 @@
+mod repos;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+fn batch_json(repo: &TestRepo, events: serde_json::Value) -> String {
+    serde_json::json!({
+        "repo_working_dir": repo.path().to_str().unwrap(),
+        "agent_name": "my-agent",
+        "model": "model-1",
+        "conversation_id": "conv-1",
+        "transcript": {
+            "messages": [
+                { "type": "user", "text": "Add one and two" },
+                { "type": "assistant", "text": "Adding one" },
+                { "type": "assistant", "text": "Adding two" }
+            ]
+        },
+        "events": events,
+    })
+    .to_string()
+}
+
+#[test]
+fn test_batch_checkpoint_records_edits_in_order() {
+    let repo = TestRepo::new();
+    fs::write(repo.path().join("lib.rs"), "// base\n").unwrap();
+    repo.stage_all_and_commit("Base").unwrap();
+
+    // The agent is done by the time it reports its edits, so disk has the final state
+    fs::write(
+        repo.path().join("lib.rs"),
+        "// base\nfn one() {}\nfn two() {}\n",
+    )
+    .unwrap();
+    fs::write(repo.path().join("util.rs"), "fn util() {}\n").unwrap();
+
+    let input = batch_json(
+        &repo,
+        serde_json::json!([
+            {
+                "file": "lib.rs",
+                "content": "// base\nfn one() {}\n",
+                "timestamp": "2025-01-15T10:30:00Z",
+                "message_index": 1
+            },
+            { "file": "util.rs", "content": "fn util() {}\n", "message_index": 1 },
+            {
+                "file": "lib.rs",
+                "patch": "--- a/lib.rs\n+++ b/lib.rs\n@@ -2,0 +3,1 @@\n+fn two() {}\n",
+                "message_index": 2
+            }
+        ]),
+    );
+    let output = repo
+        .git_ai(&["checkpoint", "agent", "--batch-json", &input])
+        .unwrap();
+    assert!(output.contains("recorded 3 checkpoint(s) for 2 file(s) from 3 edit(s)"));
+
+    let checkpoints = repo
+        .current_working_logs()
+        .read_all_checkpoints()
+        .unwrap();
+    assert_eq!(checkpoints.len(), 3);
+    let files: Vec<&str> = checkpoints
+        .iter()
+        .map(|checkpoint| checkpoint.entries[0].file.as_str())
+        .collect();
+    assert_eq!(files, vec!["lib.rs", "util.rs", "lib.rs"]);
+    assert!(checkpoints.windows(2).all(|w| w[0].sequence < w[1].sequence));
+    assert_eq!(checkpoints[0].timestamp, 1736937000);
+    let message_counts: Vec<usize> = checkpoints
+        .iter()
+        .map(|checkpoint| checkpoint.transcript.as_ref().unwrap().messages().len())
+        .collect();
+    assert_eq!(message_counts, vec![2, 2, 3]);
+
+    let commit = repo.stage_all_and_commit("Agent work").unwrap();
+    let prompts: Vec<_> = commit.authorship_log.metadata.prompts.values().collect();
+    assert_eq!(prompts.len(), 1);
+    assert_eq!(prompts[0].agent_id.tool, "my-agent");
+    assert_eq!(prompts[0].accepted_lines, 3);
+    assert_eq!(commit.authorship_log.attestations.len(), 2);
+}
+
+#[test]
+fn test_batch_checkpoint_rejects_a_patch_that_does_not_apply() {
+    let repo = TestRepo::new();
+    fs::write(repo.path().join("lib.rs"), "// base\n").unwrap();
+    repo.stage_all_and_commit("Base").unwrap();
+
+    let input = batch_json(
+        &repo,
+        serde_json::json!([
+            { "file": "lib.rs", "content": "// base\nfn one() {}\n" },
+            { "file": "lib.rs", "patch": "@@ -1,1 +1,1 @@\n-// other\n+// changed\n" }
+        ]),
+    );
+    let err = repo
+        .git_ai(&["checkpoint", "agent", "--batch-json", &input])
+        .unwrap_err();
+    assert!(err.contains("Patch for lib.rs doesn't apply"));
+
+    // Nothing is recorded unless the whole batch is
+    let checkpoints = repo
+        .current_working_logs()
+        .read_all_checkpoints()
+        .unwrap();
+    assert!(checkpoints.is_empty());
+}
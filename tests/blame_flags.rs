@@ -123,6 +123,30 @@ fn test_blame_basic_format() {
     );
 }
 
+#[test]
+fn test_blame_color_marks_only_ai_authors() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+
+    file.set_contents(lines!["Line 1", "Line 2".ai()]);
+
+    repo.stage_all_and_commit("Initial commit").unwrap();
+
+    let colored = repo
+        .git_ai(&["blame", "--color=always", "test.txt"])
+        .unwrap();
+    let plain = repo.git_ai(&["blame", "--no-color", "test.txt"]).unwrap();
+
+    let colored_lines: Vec<&str> = colored.lines().collect();
+    assert!(!colored_lines[0].contains('\x1b'));
+    assert!(colored_lines[1].contains("\x1b[35mmock_ai\x1b[0m"));
+    // Stripping the escapes gives the plain output, so blame-aware pagers can still parse it
+    assert_eq!(
+        colored.replace("\x1b[35m", "").replace("\x1b[0m", ""),
+        plain
+    );
+}
+
 #[test]
 fn test_blame_line_range() {
     let repo = TestRepo::new();
@@ -153,6 +177,118 @@ fn test_blame_line_range() {
     );
 }
 
+#[test]
+fn test_blame_line_range_forms_match_git() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+
+    file.set_contents(lines![
+        "Line 1",
+        "Line 2",
+        "Line 3".ai(),
+        "Line 4".ai(),
+        "Line 5",
+        "Line 6".ai()
+    ]);
+
+    repo.stage_all_and_commit("Initial commit").unwrap();
+
+    for range in ["-L4", "-L3,+2", "-L5,-3", "-L,2", "-L5,100"] {
+        let git_output = repo.git(&["blame", range, "test.txt"]).unwrap();
+        let git_ai_output = repo.git_ai(&["blame", range, "test.txt"]).unwrap();
+        assert_eq!(
+            normalize_for_snapshot(&git_output),
+            normalize_for_snapshot(&git_ai_output),
+            "{}",
+            range
+        );
+    }
+
+    let err = repo
+        .git_ai(&["blame", "-L", "7,8", "test.txt"])
+        .unwrap_err();
+    assert!(err.contains("has only 6 lines"), "{}", err);
+}
+
+#[test]
+fn test_blame_at_revision() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+
+    file.set_contents(lines!["Line 1", "Line 2".ai(), "Line 3".ai()]);
+    repo.stage_all_and_commit("Release").unwrap();
+    repo.git(&["tag", "v1.2.0"]).unwrap();
+
+    // The AI lines are rewritten after the release
+    file.set_contents(lines!["Line 1", "Rewritten"]);
+    repo.stage_all_and_commit("After release").unwrap();
+
+    let output = repo
+        .git_ai(&["blame", "-L", "2,3", "v1.2.0", "test.txt"])
+        .unwrap();
+    let git_output = repo
+        .git(&["blame", "-L", "2,3", "v1.2.0", "test.txt"])
+        .unwrap();
+    assert_eq!(
+        normalize_for_snapshot(&git_output),
+        normalize_for_snapshot(&output)
+    );
+    let authors = extract_authors(&output);
+    assert_eq!(authors.len(), 2, "{}", output);
+    assert!(authors.iter().all(|a| a.contains("mock_ai")), "{}", output);
+
+    let output = repo.git_ai(&["blame", "HEAD", "--", "test.txt"]).unwrap();
+    assert!(!output.contains("mock_ai"), "{}", output);
+}
+
+#[test]
+fn test_blame_ignore_revs_see_through_reformat() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("main.js");
+
+    file.set_contents(lines!["let a = 1".ai(), "let b = 2".ai(), "let c = 3".ai()]);
+    repo.stage_all_and_commit("Agent code").unwrap();
+
+    // A bulk reformat rewrites every line
+    file.set_contents(lines![
+        "const a = 1;",
+        "const b = 2;",
+        "const c = 3;"
+    ]);
+    let reformat = repo.stage_all_and_commit("Reformat").unwrap().commit_sha;
+
+    let ai_authors = |output: &str| {
+        extract_authors(output)
+            .iter()
+            .filter(|author| author.contains("mock_ai"))
+            .count()
+    };
+    let output = repo.git_ai(&["blame", "main.js"]).unwrap();
+    assert_eq!(ai_authors(&output), 0, "{}", output);
+
+    let output = repo
+        .git_ai(&["blame", "--ignore-rev", &reformat, "main.js"])
+        .unwrap();
+    assert_eq!(ai_authors(&output), 3, "{}", output);
+
+    // Read from blame.ignoreRevsFile like git blame does
+    std::fs::write(
+        repo.path().join(".git-blame-ignore-revs"),
+        format!("{}\n", reformat),
+    )
+    .unwrap();
+    repo.git(&["config", "blame.ignoreRevsFile", ".git-blame-ignore-revs"])
+        .unwrap();
+    let output = repo.git_ai(&["blame", "main.js"]).unwrap();
+    assert_eq!(ai_authors(&output), 3, "{}", output);
+
+    // An empty --ignore-revs-file clears the configured ones
+    let output = repo
+        .git_ai(&["blame", "--ignore-revs-file=", "main.js"])
+        .unwrap();
+    assert_eq!(ai_authors(&output), 0, "{}", output);
+}
+
 #[test]
 fn test_blame_porcelain_format() {
     let repo = TestRepo::new();
@@ -656,3 +792,150 @@ fn test_blame_with_ai_authorship() {
         git_ai_authors
     );
 }
+
+#[test]
+fn test_blame_ignore_whitespace_config_keeps_ai_through_reformat() {
+    let repo = TestRepo::new();
+    std::fs::write(
+        repo.path().join(".gitai.toml"),
+        "ignore_whitespace = true\n",
+    )
+    .unwrap();
+
+    let mut file = repo.filename("main.rs");
+    file.set_contents(lines![
+        "fn main() {".ai(),
+        "    let items = vec![1, 2, 3];".ai(),
+        "    for item in items {".ai(),
+        "        if item > 1 { println!(\"{}\", item); }".ai(),
+        "    }".ai(),
+        "}".ai()
+    ]);
+    repo.stage_all_and_commit("AI code").unwrap();
+
+    // A human reformats the code without changing anything else
+    file.set_contents(lines![
+        "fn main() {",
+        "  let items = vec![1,2,3];",
+        "  for item in items {",
+        "    if item > 1 {",
+        "      println!(\"{}\", item);",
+        "    }",
+        "  }",
+        "}"
+    ]);
+    repo.stage_all_and_commit("Reformat").unwrap();
+
+    // Even without `git blame -w`, the reformat commit's note keeps the AI attribution
+    let output = repo
+        .git_ai(&["blame", "--no-ignore-whitespace", "main.rs"])
+        .unwrap();
+    let authors = extract_authors(&output);
+    assert_eq!(authors.len(), 8);
+    assert!(
+        authors.iter().all(|a| a.contains("mock_ai")),
+        "Every line should stay AI. Got: {:?}",
+        authors
+    );
+
+    // With `-w`, lines git maps back to the original commit stay AI as well
+    let output = repo.git_ai(&["blame", "-w", "main.rs"]).unwrap();
+    let authors = extract_authors(&output);
+    assert_eq!(authors.len(), 8);
+    assert!(
+        authors[1..5].iter().all(|a| a.contains("mock_ai")),
+        "Reformatted lines should stay AI. Got: {:?}",
+        authors
+    );
+}
+
+#[test]
+fn test_anchored_attribution_keeps_ai_through_reordering() {
+    let repo = TestRepo::new();
+    std::fs::write(
+        repo.path().join(".gitai.toml"),
+        "attribution_algorithm = \"anchored\"\n",
+    )
+    .unwrap();
+
+    let mut file = repo.filename("main.rs");
+    file.set_contents(lines![
+        "fn main() {".ai(),
+        "    let first_value = compute_first(alpha, beta);".ai(),
+        "    let second_value = compute_second(gamma, delta);".ai(),
+        "}".ai()
+    ]);
+    repo.stage_all_and_commit("AI code").unwrap();
+
+    // A human swaps the two statements and reformats them. Each line alone is too short to
+    // count as moved code for the diff algorithm, but its tokens still match.
+    file.set_contents(lines![
+        "fn main() {",
+        "  let second_value = compute_second(gamma,delta);",
+        "  let first_value = compute_first(alpha,beta);",
+        "  println!(\"{}\", first_value + second_value);",
+        "}"
+    ]);
+    repo.stage_all_and_commit("Reorder").unwrap();
+
+    let output = repo
+        .git_ai(&["blame", "--no-ignore-whitespace", "main.rs"])
+        .unwrap();
+    let authors = extract_authors(&output);
+    assert_eq!(authors.len(), 5);
+    assert!(
+        authors[1].contains("mock_ai") && authors[2].contains("mock_ai"),
+        "Swapped lines should stay AI. Got: {:?}",
+        authors
+    );
+    assert_eq!(authors[3], "Test", "Got: {:?}", authors);
+}
+
+#[test]
+fn test_blame_char_level_splits_uncommitted_lines() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("main.rs");
+    file.set_contents(lines!["fn main() {", "    let total = 0;", "}"]);
+    repo.stage_all_and_commit("Human code").unwrap();
+
+    // The AI rewrites the end of a human line
+    std::fs::write(
+        repo.path().join("main.rs"),
+        "fn main() {\n    let total = compute_total(items);\n}\n",
+    )
+    .unwrap();
+    repo.git_ai(&["checkpoint", "mock_ai"]).unwrap();
+
+    let output = repo
+        .git_ai(&["blame", "--char-level", "--json", "main.rs"])
+        .unwrap();
+    let json_end = output.rfind(']').unwrap();
+    let lines: serde_json::Value = serde_json::from_str(&output[..=json_end]).unwrap();
+    assert_eq!(lines.as_array().unwrap().len(), 3);
+    assert_eq!(lines[0]["segments"].as_array().unwrap().len(), 1);
+    assert_eq!(lines[0]["segments"][0]["ai"], false);
+
+    let segments = lines[1]["segments"].as_array().unwrap();
+    assert!(segments.len() > 1, "Got: {:?}", segments);
+    assert_eq!(segments[0]["start"], 0);
+    assert_eq!(segments[0]["ai"], false);
+    assert!(
+        segments
+            .iter()
+            .any(|segment| segment["ai"] == true && segment["author"] == "mock_ai"),
+        "Got: {:?}",
+        segments
+    );
+    assert_eq!(
+        segments.last().unwrap()["end"],
+        "    let total = compute_total(items);".len()
+    );
+
+    // The default output marks the AI's characters under the line
+    let output = repo.git_ai(&["blame", "--char-level", "main.rs"]).unwrap();
+    let marker = output
+        .lines()
+        .find(|line| line.trim_start().starts_with('^'))
+        .unwrap_or_else(|| panic!("No marker line in:\n{}", output));
+    assert!(marker.ends_with(" mock_ai"), "Got: {:?}", marker);
+}
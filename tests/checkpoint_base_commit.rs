@@ -0,0 +1,59 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+#[test]
+fn test_checkpoint_base_commit_pins_working_log() {
+    let repo = TestRepo::new();
+    let mut base = repo.filename("base.txt");
+    base.set_contents(lines!["first"]);
+    repo.stage_all_and_commit("First").unwrap();
+    let first_sha = repo.git(&["rev-parse", "HEAD"]).unwrap().trim().to_string();
+    base.set_contents(lines!["first", "second"]);
+    repo.stage_all_and_commit("Second").unwrap();
+
+    // The agent knows its change will be committed on top of the first commit, not HEAD
+    fs::write(repo.path().join("agent.txt"), "from the agent\n").unwrap();
+    repo.git_ai(&[
+        "checkpoint",
+        "mock_ai",
+        "--base-commit",
+        &first_sha,
+        "agent.txt",
+    ])
+    .unwrap();
+    assert!(
+        repo.current_working_logs()
+            .read_all_checkpoints()
+            .unwrap()
+            .is_empty()
+    );
+
+    repo.git(&["checkout", "--detach", &first_sha]).unwrap();
+    repo.git(&["add", "agent.txt"]).unwrap();
+    repo.commit("Agent change").unwrap();
+
+    let mut file = repo.filename("agent.txt");
+    file.assert_lines_and_blame(lines!["from the agent".ai()]);
+}
+
+#[test]
+fn test_checkpoint_base_commit_must_be_a_commit() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("a.txt");
+    file.set_contents(lines!["one"]);
+    repo.stage_all_and_commit("Initial").unwrap();
+
+    fs::write(repo.path().join("a.txt"), "one\ntwo\n").unwrap();
+    assert!(
+        repo.git_ai(&["checkpoint", "mock_ai", "--base-commit", "HEAD^{tree}"])
+            .is_err()
+    );
+    assert!(
+        repo.git_ai(&["checkpoint", "mock_ai", "--base-commit", "no-such-ref"])
+            .is_err()
+    );
+    assert!(repo.git_ai(&["checkpoint", "--base-commit"]).is_err());
+}
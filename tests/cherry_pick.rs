@@ -171,6 +171,19 @@ fn test_cherry_pick_abort() {
         "Line 1".human(),
         "Human modification of line 2".human(),
     ]);
+
+    // The rewrite log records that the cherry-pick ended
+    let rewrite_log =
+        std::fs::read_to_string(repo.path().join(".git").join("ai").join("rewrite_log")).unwrap();
+    assert!(
+        rewrite_log
+            .lines()
+            .next()
+            .unwrap()
+            .contains("cherry_pick_abort"),
+        "{}",
+        rewrite_log
+    );
 }
 
 /// Test cherry-picking from branch without AI authorship
@@ -318,3 +331,78 @@ fn test_cherry_pick_empty_commits() {
         "File content should be preserved after cherry-pick/abort"
     );
 }
+
+/// Test a conflicted cherry-pick concluded with `git commit` instead of --continue
+#[test]
+fn test_cherry_pick_with_conflict_concluded_by_commit() {
+    let repo = TestRepo::new();
+
+    let mut file = repo.filename("file.txt");
+    file.set_contents(lines!["Line 1", "Line 2", "Line 3"]);
+    repo.stage_all_and_commit("Initial commit").unwrap();
+
+    let main_branch = repo.current_branch();
+
+    repo.git(&["checkout", "-b", "feature"]).unwrap();
+    file.replace_at(1, "AI_FEATURE_VERSION".ai());
+    repo.stage_all_and_commit("AI feature").unwrap();
+    let feature_commit = repo.git(&["rev-parse", "HEAD"]).unwrap().trim().to_string();
+
+    repo.git(&["checkout", &main_branch]).unwrap();
+    file.replace_at(1, "MAIN_BRANCH_VERSION".human());
+    repo.stage_all_and_commit("Human change").unwrap();
+
+    let cherry_pick_result = repo.git(&["cherry-pick", &feature_commit]);
+    assert!(cherry_pick_result.is_err(), "Should have conflict");
+
+    use std::fs;
+    fs::write(
+        repo.path().join("file.txt"),
+        "Line 1\nAI_FEATURE_VERSION\nLine 3",
+    )
+    .unwrap();
+    repo.git(&["add", "file.txt"]).unwrap();
+
+    repo.git_with_env(&["commit", "--no-edit"], &[("GIT_EDITOR", "true")])
+        .unwrap();
+
+    file.assert_lines_and_blame(lines![
+        "Line 1".human(),
+        "AI_FEATURE_VERSION".ai(),
+        "Line 3".human(),
+    ]);
+}
+
+/// Test a multi-commit cherry-pick where a conflicting commit is dropped with --skip
+#[test]
+fn test_cherry_pick_skip_keeps_authorship_of_remaining_commits() {
+    let repo = TestRepo::new();
+
+    let mut file = repo.filename("file.txt");
+    file.set_contents(lines!["Line 1", "Line 2"]);
+    let mut other = repo.filename("other.txt");
+    other.set_contents(lines!["Other"]);
+    repo.stage_all_and_commit("Initial commit").unwrap();
+
+    let main_branch = repo.current_branch();
+
+    repo.git(&["checkout", "-b", "feature"]).unwrap();
+    file.replace_at(1, "AI_FEATURE_VERSION".ai());
+    repo.stage_all_and_commit("AI change that will conflict")
+        .unwrap();
+    let conflicting = repo.git(&["rev-parse", "HEAD"]).unwrap().trim().to_string();
+    other.insert_at(1, lines!["AI other line".ai()]);
+    repo.stage_all_and_commit("AI change that applies").unwrap();
+    let applies = repo.git(&["rev-parse", "HEAD"]).unwrap().trim().to_string();
+
+    repo.git(&["checkout", &main_branch]).unwrap();
+    file.replace_at(1, "MAIN_BRANCH_VERSION".human());
+    repo.stage_all_and_commit("Human change").unwrap();
+
+    let cherry_pick_result = repo.git(&["cherry-pick", &conflicting, &applies]);
+    assert!(cherry_pick_result.is_err(), "Should have conflict");
+    repo.git(&["cherry-pick", "--skip"]).unwrap();
+
+    file.assert_lines_and_blame(lines!["Line 1".human(), "MAIN_BRANCH_VERSION".human()]);
+    other.assert_lines_and_blame(lines!["Other".human(), "AI other line".ai()]);
+}
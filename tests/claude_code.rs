@@ -1,9 +1,16 @@
+#[macro_use]
+mod repos;
 mod test_utils;
 
 use git_ai::authorship::transcript::{AiTranscript, Message};
 use git_ai::commands::checkpoint_agent::agent_presets::{
-    AgentCheckpointFlags, AgentCheckpointPreset, ClaudePreset,
+    AgentCheckpointFlags, AgentCheckpointPreset, ClaudeCodeSessionPreset, ClaudePreset,
+    claude_code_project_name,
 };
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+use std::path::{Path, PathBuf};
 use test_utils::load_fixture;
 
 #[test]
@@ -90,3 +97,110 @@ fn test_claude_preset_no_filepath_when_tool_input_missing() {
     // Verify edited_filepaths is None when tool_input is missing
     assert!(result.edited_filepaths.is_none());
 }
+
+#[test]
+fn test_claude_code_session_preset_reads_transcript() {
+    let preset = ClaudeCodeSessionPreset {
+        working_dir: PathBuf::from("/Users/aidancunniffe/Desktop/ghq"),
+        session: Some("tests/fixtures/example-claude-code.jsonl".to_string()),
+    };
+    let result = preset
+        .run(AgentCheckpointFlags { hook_input: None })
+        .expect("Failed to run ClaudeCodeSessionPreset");
+
+    assert_eq!(result.agent_id.tool, "claude");
+    assert_eq!(result.agent_id.id, "example-claude-code");
+    assert_eq!(result.agent_id.model, "claude-sonnet-4-20250514");
+    let mut edited = result.edited_filepaths.unwrap();
+    edited.sort();
+    assert_eq!(
+        edited,
+        vec![
+            "/Users/aidancunniffe/Desktop/ghq/src/app/globals.css",
+            "/Users/aidancunniffe/Desktop/ghq/src/game/HistoryLog.tsx",
+        ]
+    );
+    let transcript = result.transcript.unwrap();
+    assert!(transcript.messages().iter().any(|message| matches!(
+        message,
+        Message::User {
+            timestamp: Some(_),
+            ..
+        }
+    )));
+
+    assert_eq!(
+        claude_code_project_name(Path::new("/Users/me/my_app.v2")),
+        "-Users-me-my-app-v2"
+    );
+}
+
+#[test]
+fn test_checkpoint_agent_claude_code_finds_latest_session() {
+    let repo = TestRepo::new();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Project"]);
+    repo.stage_all_and_commit("Initial commit").unwrap();
+
+    // Claude Code wrote lib.rs; the person edited the README meanwhile
+    let lib_path = repo.path().join("lib.rs");
+    fs::write(&lib_path, "fn generated() {}\n").unwrap();
+    fs::write(repo.path().join("README.md"), "# Project\nBy hand\n").unwrap();
+
+    let config_dir = repo.path().join(".git").join("claude");
+    let project_dir = config_dir
+        .join("projects")
+        .join(claude_code_project_name(repo.path()));
+    fs::create_dir_all(&project_dir).unwrap();
+    let lines = [
+        serde_json::json!({
+            "type": "user",
+            "timestamp": "2025-01-01T00:00:00Z",
+            "message": {"role": "user", "content": "Add a generated function"}
+        }),
+        serde_json::json!({
+            "type": "assistant",
+            "timestamp": "2025-01-01T00:00:05Z",
+            "version": "1.0.50",
+            "message": {
+                "model": "claude-opus-4",
+                "content": [
+                    {"type": "text", "text": "Writing lib.rs"},
+                    {"type": "tool_use", "name": "Write", "input": {
+                        "file_path": lib_path.to_str().unwrap(),
+                        "content": "fn generated() {}\n"
+                    }}
+                ]
+            }
+        }),
+    ];
+    let transcript: String = lines.iter().map(|line| format!("{}\n", line)).collect();
+    fs::write(project_dir.join("session-1.jsonl"), transcript).unwrap();
+
+    repo.git_ai_with_env(
+        &["checkpoint", "--agent", "claude-code"],
+        &[("CLAUDE_CONFIG_DIR", config_dir.to_str().unwrap())],
+    )
+    .unwrap();
+    repo.stage_all_and_commit("Generated").unwrap();
+
+    let mut lib = repo.filename("lib.rs");
+    lib.assert_lines_and_blame(lines!["fn generated() {}".ai()]);
+    readme.assert_lines_and_blame(lines!["# Project".human(), "By hand".human()]);
+
+    let result = repo.git_ai_with_env(
+        &[
+            "checkpoint",
+            "--agent",
+            "claude-code",
+            "--session",
+            "missing",
+        ],
+        &[("CLAUDE_CONFIG_DIR", config_dir.to_str().unwrap())],
+    );
+    assert!(
+        result
+            .unwrap_err()
+            .contains("No Claude Code session missing")
+    );
+}
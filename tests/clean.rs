@@ -0,0 +1,64 @@
+#[macro_use]
+mod repos;
+use repos::test_repo::TestRepo;
+use std::collections::HashSet;
+use std::fs;
+
+fn pending_ai_files(repo: &TestRepo) -> HashSet<String> {
+    repo.current_working_logs().ai_attributed_files().unwrap()
+}
+
+/// Write an untracked file as the mock AI (TestFile stages what it writes)
+fn write_untracked_ai_file(repo: &TestRepo, path: &str, contents: &str) {
+    let full_path = repo.path().join(path);
+    fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+    fs::write(full_path, contents).unwrap();
+    repo.git_ai(&["checkpoint", "mock_ai", path]).unwrap();
+}
+
+#[test]
+fn test_clean_warns_and_prunes_ai_attributed_files() {
+    let repo = TestRepo::new();
+    let mut base = repo.filename("lib.rs");
+    base.set_contents(lines!["fn base() {}"]);
+    repo.stage_all_and_commit("Base").unwrap();
+
+    write_untracked_ai_file(&repo, "generated/schema.rs", "struct Schema;\n");
+    fs::write(repo.path().join("scratch.txt"), "human notes\n").unwrap();
+    assert!(pending_ai_files(&repo).contains("generated/schema.rs"));
+
+    // A dry run only lists what would go
+    let output = repo.git(&["clean", "-fdn"]).unwrap();
+    assert!(!output.contains("git-ai"));
+    assert!(repo.path().join("generated/schema.rs").exists());
+
+    let output = repo.git(&["clean", "-fd"]).unwrap();
+    assert!(output.contains("AI-authored lines that haven't been committed"));
+    assert!(output.contains("  generated/schema.rs"));
+    assert!(!output.contains("  scratch.txt"));
+    assert!(!repo.path().join("generated").exists());
+    assert!(pending_ai_files(&repo).is_empty());
+}
+
+#[test]
+fn test_clean_refused_with_confirm_clean() {
+    let repo = TestRepo::new();
+    fs::write(repo.path().join(".gitai.toml"), "confirm_clean = true\n").unwrap();
+    repo.git(&["add", ".gitai.toml"]).unwrap();
+    repo.git(&["commit", "-m", "Team config"]).unwrap();
+
+    write_untracked_ai_file(&repo, "generated.rs", "fn generated() {}\n");
+
+    let err = repo.git(&["clean", "-f"]).unwrap_err();
+    assert!(err.contains("refusing to clean"));
+    assert!(repo.path().join("generated.rs").exists());
+
+    // Excluding the file from the clean is fine
+    repo.git(&["clean", "-f", "-e", "generated.rs"]).unwrap();
+    assert!(repo.path().join("generated.rs").exists());
+
+    repo.git_with_env(&["clean", "-f"], &[("GIT_AI_CONFIRM_CLEAN", "false")])
+        .unwrap();
+    assert!(!repo.path().join("generated.rs").exists());
+    assert!(pending_ai_files(&repo).is_empty());
+}
@@ -0,0 +1,65 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+#[test]
+fn test_commit_author_env_attributes_whole_commit_to_agent() {
+    let repo = TestRepo::new();
+    fs::write(
+        repo.path().join("script.py"),
+        "print('one')\nprint('two')\n",
+    )
+    .unwrap();
+    repo.git(&["add", "-A"]).unwrap();
+    repo.git_with_env(
+        &["commit", "-m", "Agent commit"],
+        &[("GIT_AI_COMMIT_AUTHOR", "agent:claude")],
+    )
+    .unwrap();
+
+    let mut file = repo.filename("script.py");
+    file.assert_lines_and_blame(lines!["print('one')".ai(), "print('two')".ai()]);
+}
+
+#[test]
+fn test_commit_author_config_keeps_checkpointed_human_lines() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("notes.md");
+    file.set_contents(lines!["written by hand"]);
+    fs::write(
+        repo.path().join("notes.md"),
+        "written by hand\nwritten by the agent\n",
+    )
+    .unwrap();
+    repo.git(&["add", "-A"]).unwrap();
+    repo.git(&[
+        "-c",
+        "gitai.author=agent:cursor",
+        "commit",
+        "-m",
+        "Agent commit",
+    ])
+    .unwrap();
+
+    file.assert_lines_and_blame(lines![
+        "written by hand".human(),
+        "written by the agent".ai()
+    ]);
+}
+
+#[test]
+fn test_commit_author_human_value_changes_nothing() {
+    let repo = TestRepo::new();
+    fs::write(repo.path().join("a.txt"), "plain\n").unwrap();
+    repo.git(&["add", "-A"]).unwrap();
+    repo.git_with_env(
+        &["commit", "-m", "Human commit"],
+        &[("GIT_AI_COMMIT_AUTHOR", "human")],
+    )
+    .unwrap();
+
+    let mut file = repo.filename("a.txt");
+    file.assert_lines_and_blame(lines!["plain".human()]);
+}
@@ -0,0 +1,101 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+fn head_message(repo: &TestRepo) -> String {
+    repo.git(&["log", "-1", "--format=%B"]).unwrap()
+}
+
+#[test]
+fn test_commit_trailers_report_ai_share_and_agents() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+    file.set_contents(lines!["Base"]);
+    repo.stage_all_and_commit("Base").unwrap();
+
+    // Off by default
+    file.set_contents(lines!["Base", "Human line", "AI line".ai()]);
+    repo.stage_all_and_commit("Without trailers").unwrap();
+    assert!(!head_message(&repo).contains("AI-Assisted"));
+
+    let mut second = repo.filename("second.txt");
+    second.set_contents(lines!["Human line", "AI line".ai()]);
+    repo.git(&["add", "-A"]).unwrap();
+    repo.git_with_env(
+        &["commit", "-m", "With trailers"],
+        &[("GIT_AI_COMMIT_TRAILERS", "true")],
+    )
+    .unwrap();
+    let message = head_message(&repo);
+    assert!(message.contains("AI-Assisted: 50%"), "{}", message);
+    assert!(message.contains("AI-Agent: mock_ai/"), "{}", message);
+
+    // Custom names, and `-a` counts the tracked changes git stages itself
+    second.set_contents(lines!["Human line", "AI line", "Another AI line".ai()]);
+    repo.git_with_env(
+        &["commit", "-am", "Custom trailers"],
+        &[
+            ("GIT_AI_COMMIT_TRAILERS", "true"),
+            ("GIT_AI_ASSISTED_TRAILER", "Assisted-By-AI"),
+            ("GIT_AI_AGENT_TRAILER", ""),
+        ],
+    )
+    .unwrap();
+    let message = head_message(&repo);
+    assert!(message.contains("Assisted-By-AI: "), "{}", message);
+    assert!(!message.contains("AI-Agent"), "{}", message);
+}
+
+#[test]
+fn test_commit_trailers_skip_human_commits() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+    file.set_contents(lines!["Human line"]);
+    repo.git(&["add", "-A"]).unwrap();
+    repo.git_with_env(
+        &["commit", "-m", "Human"],
+        &[("GIT_AI_COMMIT_TRAILERS", "true")],
+    )
+    .unwrap();
+    assert!(!head_message(&repo).contains("AI-Assisted"));
+}
+
+#[test]
+fn test_commit_trailers_count_what_the_commit_records() {
+    let repo = TestRepo::new();
+    let mut human = repo.filename("human.txt");
+    human.set_contents(lines!["Base"]);
+    repo.stage_all_and_commit("Base").unwrap();
+
+    let mut ai = repo.filename("ai.txt");
+    ai.set_contents(lines!["AI line".ai(), "Another AI line".ai()]);
+    repo.git(&["add", "ai.txt"]).unwrap();
+    human.set_contents_no_stage(lines!["Base", "Human line"]);
+
+    // A pathspec commit leaves the staged AI file out
+    repo.git_with_env(
+        &["commit", "human.txt", "-m", "Human only"],
+        &[("GIT_AI_COMMIT_TRAILERS", "true")],
+    )
+    .unwrap();
+    let message = head_message(&repo);
+    assert!(!message.contains("AI-Assisted"), "{}", message);
+    assert_eq!(
+        repo.git(&["diff", "--cached", "--name-only"])
+            .unwrap()
+            .trim(),
+        "ai.txt"
+    );
+
+    // `-all` is the message, not `-a`: the unstaged human lines aren't committed
+    human.set_contents_no_stage(lines!["Base", "Human line", "More human", "Even more"]);
+    repo.git_with_env(
+        &["commit", "-m", "-all"],
+        &[("GIT_AI_COMMIT_TRAILERS", "true")],
+    )
+    .unwrap();
+    let message = head_message(&repo);
+    assert!(message.starts_with("-all"), "{}", message);
+    assert!(message.contains("AI-Assisted: 100%"), "{}", message);
+}
@@ -0,0 +1,70 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+fn compat_json(repo: &TestRepo, args: &[&str]) -> Result<serde_json::Value, String> {
+    let mut full_args = vec!["compat", "check", "--json"];
+    full_args.extend_from_slice(args);
+    let output = repo.git_ai(&full_args)?;
+    Ok(serde_json::from_str(output.trim()).unwrap())
+}
+
+/// Replace the schema version of HEAD's note, as a newer git-ai would have written it
+fn rewrite_head_note_version(repo: &TestRepo, version: &str) {
+    let note = repo.git(&["notes", "--ref=ai", "show", "HEAD"]).unwrap();
+    let note = note.replace("authorship/3.1.0", version);
+    repo.git(&["notes", "--ref=ai", "add", "-f", "-m", &note, "HEAD"])
+        .unwrap();
+}
+
+#[test]
+fn test_compat_check_reports_version_spread() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+
+    file.set_contents(lines!["fn one() {}".ai()]);
+    let base = repo.stage_all_and_commit("AI 1").unwrap().commit_sha;
+    file.set_contents(lines!["fn one() {}".ai(), "fn two() {}".ai()]);
+    repo.stage_all_and_commit("AI 2").unwrap();
+    rewrite_head_note_version(&repo, "authorship/3.2.0");
+
+    let report = compat_json(&repo, &[]).unwrap();
+    assert_eq!(report["current_version"], "authorship/3.1.0");
+    assert_eq!(report["notes"], 2);
+    assert_eq!(report["versions"]["authorship/3.1.0"]["notes"], 1);
+    assert_eq!(
+        report["versions"]["authorship/3.2.0"]["compatibility"],
+        "newer_minor"
+    );
+    assert!(report["unreadable"].as_array().unwrap().is_empty());
+
+    // A newer minor still attributes lines
+    let blame = repo.git_ai(&["blame", "lib.rs"]).unwrap();
+    assert!(blame.contains("mock_ai"));
+
+    let range = format!("{}..HEAD", base);
+    let report = compat_json(&repo, &["--range", &range]).unwrap();
+    assert_eq!(report["notes"], 1);
+}
+
+#[test]
+fn test_compat_check_fails_on_newer_major() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn one() {}".ai()]);
+    repo.stage_all_and_commit("AI").unwrap();
+    rewrite_head_note_version(&repo, "authorship/4.0.0");
+
+    assert!(compat_json(&repo, &[]).is_err());
+
+    // Readers warn instead of silently misreading the note
+    let output = repo.git_ai(&["log", "--stat", "-n", "1"]).unwrap();
+    assert!(output.contains("newer than this git-ai reads"));
+}
+
+#[test]
+fn test_compat_check_requires_subcommand() {
+    let repo = TestRepo::new();
+    assert!(repo.git_ai(&["compat"]).is_err());
+}
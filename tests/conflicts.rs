@@ -0,0 +1,47 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+#[test]
+fn test_conflicts_show_which_side_is_ai() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn base() {}", "fn shared() {}", "fn end() {}"]);
+    repo.stage_all_and_commit("Base").unwrap();
+    let main_branch = repo.current_branch();
+
+    repo.git(&["checkout", "-b", "feature"]).unwrap();
+    file.set_contents(lines![
+        "fn base() {}",
+        "fn from_agent() {}".ai(),
+        "fn also_agent() {}".ai(),
+        "fn end() {}",
+    ]);
+    repo.stage_all_and_commit("Agent change").unwrap();
+
+    repo.git(&["checkout", &main_branch]).unwrap();
+    file.set_contents(lines!["fn base() {}", "fn by_hand() {}", "fn end() {}"]);
+    repo.stage_all_and_commit("Human change").unwrap();
+
+    assert!(repo.git_ai(&["conflicts"]).is_err());
+    assert!(repo.git(&["merge", "feature"]).is_err());
+
+    let output = repo.git_ai(&["conflicts", "--json"]).unwrap();
+    let report: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+    assert_eq!(report["theirs_ref"], "MERGE_HEAD");
+    assert_eq!(report["files"][0]["file"], "lib.rs");
+    let hunk = &report["files"][0]["hunks"][0];
+    assert_eq!(hunk["ours"]["lines"], 1);
+    assert_eq!(hunk["ours"]["ai_lines"], 0);
+    assert_eq!(hunk["theirs"]["lines"], 2);
+    assert_eq!(hunk["theirs"]["ai_lines"], 2);
+    assert_eq!(hunk["theirs"]["prompts"][0]["tool"], "mock_ai");
+
+    let output = repo.git_ai(&["conflicts", "--file", "lib.rs"]).unwrap();
+    assert!(output.contains("lib.rs: conflict 1"));
+    assert!(output.contains("1 line, human"));
+    assert!(output.contains("2 lines, all AI (mock_ai"));
+
+    assert!(repo.git_ai(&["conflicts", "--file", "other.rs"]).is_err());
+}
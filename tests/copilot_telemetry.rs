@@ -0,0 +1,102 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[test]
+fn test_recovers_copilot_completions_from_telemetry() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn main() {", "}"]);
+    repo.stage_all_and_commit("Base").unwrap();
+
+    // Edited without any checkpoint, as if git-ai wasn't running when the completion was accepted
+    fs::write(
+        repo.path().join("lib.rs"),
+        "fn main() {\n    println!(\"hello\");\n    let total = 1 + 2;\n}\n",
+    )
+    .unwrap();
+
+    let now_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64;
+    let telemetry_path = repo.path().join(".git").join("copilot-acceptances.json");
+    let telemetry = serde_json::json!({
+        "acceptances": [
+            {
+                "uri": format!("file://{}", repo.path().join("lib.rs").display()),
+                "line": 2,
+                "insertText": "let total = 1 + 2;",
+                "timestamp": now_ms + 1000,
+            },
+            {
+                "uri": format!("file://{}", repo.path().join("missing.rs").display()),
+                "line": 0,
+                "insertText": "fn gone() {}",
+                "timestamp": now_ms + 1000,
+            }
+        ]
+    });
+    fs::write(&telemetry_path, telemetry.to_string()).unwrap();
+
+    repo.git(&["add", "-A"]).unwrap();
+    repo.git_with_env(
+        &["commit", "-m", "Accept a completion"],
+        &[("GIT_AI_COPILOT_TELEMETRY", telemetry_path.to_str().unwrap())],
+    )
+    .unwrap();
+
+    file = repo.filename("lib.rs");
+    file.assert_lines_and_blame(lines![
+        "fn main() {".human(),
+        "    println!(\"hello\");".human(),
+        "    let total = 1 + 2;".ai(),
+        "}".human(),
+    ]);
+
+    let note = repo.git(&["notes", "--ref=ai", "show", "HEAD"]).unwrap();
+    assert!(note.contains("recovered-tab-completions"));
+    assert!(note.contains("github-copilot"));
+}
+
+#[test]
+fn test_ignores_completions_accepted_before_last_checkpoint() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn main() {", "}"]);
+    repo.stage_all_and_commit("Base").unwrap();
+
+    fs::write(
+        repo.path().join("lib.rs"),
+        "fn main() {\n    let total = 1 + 2;\n}\n",
+    )
+    .unwrap();
+
+    let telemetry_path = repo.path().join(".git").join("copilot-acceptances.json");
+    let telemetry = serde_json::json!({
+        "acceptances": [{
+            "uri": format!("file://{}", repo.path().join("lib.rs").display()),
+            "line": 1,
+            "insertText": "let total = 1 + 2;",
+            "timestamp": 1000,
+        }]
+    });
+    fs::write(&telemetry_path, telemetry.to_string()).unwrap();
+
+    repo.git(&["add", "-A"]).unwrap();
+    repo.git_with_env(
+        &["commit", "-m", "Stale completion"],
+        &[("GIT_AI_COPILOT_TELEMETRY", telemetry_path.to_str().unwrap())],
+    )
+    .unwrap();
+
+    file = repo.filename("lib.rs");
+    file.assert_lines_and_blame(lines![
+        "fn main() {".human(),
+        "    let total = 1 + 2;".human(),
+        "}".human(),
+    ]);
+}
@@ -0,0 +1,85 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+fn has_note(repo: &TestRepo, commit: &str) -> bool {
+    repo.git(&["notes", "--ref=ai", "show", commit]).is_ok()
+}
+
+fn has_detached_ref(repo: &TestRepo, commit: &str) -> bool {
+    repo.git(&[
+        "rev-parse",
+        "--verify",
+        &format!("refs/ai/detached/{}", commit),
+    ])
+    .is_ok()
+}
+
+/// Commit AI work on a detached HEAD and return the new commit
+fn commit_on_detached_head(repo: &TestRepo) -> String {
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn base() {}"]);
+    repo.stage_all_and_commit("Base").unwrap();
+    repo.git(&["checkout", "--detach"]).unwrap();
+
+    file.set_contents(lines!["fn base() {}", "fn experiment() {}".ai()]);
+    repo.stage_all_and_commit("Experiment").unwrap().commit_sha
+}
+
+#[test]
+fn test_discarded_detached_commit_note_is_pruned() {
+    let repo = TestRepo::new();
+    let default_branch = repo.git(&["branch", "--show-current"]).unwrap();
+    let experiment = commit_on_detached_head(&repo);
+    assert!(has_note(&repo, &experiment));
+    assert!(has_detached_ref(&repo, &experiment));
+
+    // Abandon the experiment
+    repo.git(&["checkout", default_branch.trim()]).unwrap();
+
+    // The detached ref keeps the commit from being collected while its note is kept
+    repo.git(&["reflog", "expire", "--expire-unreachable=now", "--all"])
+        .unwrap();
+    repo.git(&["gc", "--prune=now", "--quiet"]).unwrap();
+    assert!(repo.git(&["cat-file", "-e", &experiment]).is_ok());
+
+    let output = repo.git_ai(&["prune-detached"]).unwrap();
+    assert!(output.contains("1 pending"), "{}", output);
+    assert!(has_note(&repo, &experiment));
+    assert!(has_detached_ref(&repo, &experiment));
+
+    let output = repo.git_ai(&["prune-detached", "--now"]).unwrap();
+    assert!(output.contains("Pruned notes of 1"), "{}", output);
+    assert!(!has_note(&repo, &experiment));
+    assert!(!has_detached_ref(&repo, &experiment));
+}
+
+#[test]
+fn test_detached_commit_attached_to_branch_keeps_note() {
+    let repo = TestRepo::new();
+    let experiment = commit_on_detached_head(&repo);
+
+    repo.git(&["checkout", "-b", "keep-experiment"]).unwrap();
+
+    let output = repo.git_ai(&["prune-detached", "--now"]).unwrap();
+    assert!(output.contains("1 attached"), "{}", output);
+    assert!(has_note(&repo, &experiment));
+    assert!(!has_detached_ref(&repo, &experiment));
+
+    // Resolved commits aren't looked at again
+    let output = repo.git_ai(&["prune-detached", "--now"]).unwrap();
+    assert!(output.contains("0 attached"), "{}", output);
+}
+
+#[test]
+fn test_commits_on_branch_are_not_tracked_as_detached() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn main() {}".ai()]);
+    let commit = repo.stage_all_and_commit("On a branch").unwrap().commit_sha;
+    assert!(!has_detached_ref(&repo, &commit));
+
+    let output = repo.git_ai(&["prune-detached", "--now"]).unwrap();
+    assert!(output.contains("Pruned notes of 0"), "{}", output);
+}
@@ -0,0 +1,63 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+fn cached_git_dir(repo: &TestRepo) -> Option<String> {
+    let cache: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(repo.discovery_cache_path()).ok()?).ok()?;
+    let key = repo.path().canonicalize().unwrap();
+    cache["entries"][key.to_str().unwrap()]["git_dir"]
+        .as_str()
+        .map(|dir| dir.to_string())
+}
+
+#[test]
+fn test_proxied_commands_remember_the_git_dir() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("a.txt");
+    file.set_contents(lines!["one".ai()]);
+    repo.stage_all_and_commit("First").unwrap();
+
+    let git_dir = repo.path().join(".git").canonicalize().unwrap();
+    assert_eq!(
+        cached_git_dir(&repo).map(|dir| fs::canonicalize(dir).unwrap()),
+        Some(git_dir)
+    );
+
+    // Served from the cache now, and still attributed correctly
+    file.insert_at(1, lines!["two".ai()]);
+    repo.stage_all_and_commit("Second").unwrap();
+    file.assert_lines_and_blame(lines!["one".ai(), "two".ai()]);
+}
+
+#[test]
+fn test_stale_discovery_entry_is_ignored() {
+    let repo = TestRepo::new();
+    let elsewhere = TestRepo::new();
+    let key = repo.path().canonicalize().unwrap();
+    // Points at another repository, recorded when the directory looked different
+    let cache = serde_json::json!({
+        "entries": {
+            key.to_str().unwrap(): {
+                "git_dir": elsewhere.path().join(".git"),
+                "mtimes": [[key, 0]],
+                "discovered_at": 0
+            }
+        }
+    });
+    fs::create_dir_all(repo.discovery_cache_path().parent().unwrap()).unwrap();
+    fs::write(repo.discovery_cache_path(), cache.to_string()).unwrap();
+
+    let mut file = repo.filename("a.txt");
+    file.set_contents(lines!["one".ai()]);
+    repo.stage_all_and_commit("First").unwrap();
+    file.assert_lines_and_blame(lines!["one".ai()]);
+
+    let git_dir = repo.path().join(".git").canonicalize().unwrap();
+    assert_eq!(
+        cached_git_dir(&repo).map(|dir| fs::canonicalize(dir).unwrap()),
+        Some(git_dir)
+    );
+}
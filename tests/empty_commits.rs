@@ -0,0 +1,81 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+#[test]
+fn test_allow_empty_commit_writes_empty_note() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn ai() {}".ai()]);
+    repo.stage_all_and_commit("AI work").unwrap();
+
+    repo.git(&["commit", "--allow-empty", "-m", "Trigger CI"])
+        .unwrap();
+
+    let log = repo.note_for("HEAD");
+    assert!(log.metadata.empty_commit);
+    assert!(log.attestations.is_empty());
+    assert!(log.metadata.prompts.is_empty());
+
+    // The commit before it keeps its attribution
+    let previous = repo.note_for("HEAD~1");
+    assert!(!previous.metadata.empty_commit);
+    assert_eq!(previous.attestations.len(), 1);
+
+    // The JSON is on stdout, which comes before the debug lines stats writes to stderr
+    let output = repo.git_ai(&["stats", "--json"]).unwrap();
+    let stats: serde_json::Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+    assert_eq!(stats["empty_commit"], true);
+    assert_eq!(stats["human_additions"], 0);
+    assert_eq!(stats["ai_additions"], 0);
+}
+
+#[test]
+fn test_empty_commit_keeps_uncommitted_ai_work() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn base() {}"]);
+    repo.stage_all_and_commit("Base").unwrap();
+
+    file.insert_at(1, lines!["fn ai() {}".ai()]);
+    repo.git(&["reset"]).unwrap();
+    repo.git(&["commit", "--allow-empty", "-m", "Empty"])
+        .unwrap();
+    assert!(repo.note_for("HEAD").metadata.empty_commit);
+
+    repo.stage_all_and_commit("AI work").unwrap();
+    file.assert_lines_and_blame(lines!["fn base() {}".human(), "fn ai() {}".ai()]);
+}
+
+#[test]
+fn test_rebase_marks_kept_empty_commits() {
+    let repo = TestRepo::new();
+    let mut base = repo.filename("base.txt");
+    base.set_contents(lines!["base"]);
+    repo.stage_all_and_commit("Base").unwrap();
+    let default_branch = repo.current_branch();
+
+    repo.git(&["checkout", "-b", "feature"]).unwrap();
+    let mut feature = repo.filename("feature.rs");
+    feature.set_contents(lines!["fn feature() {}".ai()]);
+    repo.stage_all_and_commit("AI feature").unwrap();
+    repo.git(&["commit", "--allow-empty", "-m", "Empty marker"])
+        .unwrap();
+
+    repo.git(&["checkout", &default_branch]).unwrap();
+    let mut other = repo.filename("other.txt");
+    other.set_contents(lines!["upstream"]);
+    repo.stage_all_and_commit("Upstream").unwrap();
+    repo.git(&["checkout", "feature"]).unwrap();
+    repo.git(&["rebase", "--empty=keep", &default_branch])
+        .unwrap();
+
+    let log = repo.note_for("HEAD");
+    assert!(log.metadata.empty_commit);
+    assert!(log.attestations.is_empty());
+    assert!(!repo.note_for("HEAD~1").metadata.empty_commit);
+
+    feature = repo.filename("feature.rs");
+    feature.assert_lines_and_blame(lines!["fn feature() {}".ai()]);
+}
@@ -0,0 +1,35 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+#[test]
+fn test_explain_line_ai_and_human() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+
+    file.set_contents(lines!["Human line", "AI line".ai()]);
+
+    let commit = repo.stage_all_and_commit("Initial commit").unwrap();
+
+    let human = repo.git_ai(&["explain-line", "test.txt:1"]).unwrap();
+    assert!(human.contains("author:  human"), "{}", human);
+    assert!(human.contains(&commit.commit_sha), "{}", human);
+
+    let ai = repo.git_ai(&["explain-line", "test.txt:2"]).unwrap();
+    assert!(ai.contains("author:  ai"), "{}", ai);
+    assert!(ai.contains("agent:   mock_ai"), "{}", ai);
+    assert!(ai.contains(&commit.commit_sha), "{}", ai);
+}
+
+#[test]
+fn test_explain_line_rejects_invalid_line() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+
+    file.set_contents(lines!["Only line"]);
+    repo.stage_all_and_commit("Initial commit").unwrap();
+
+    assert!(repo.git_ai(&["explain-line", "test.txt:5"]).is_err());
+    assert!(repo.git_ai(&["explain-line", "test.txt"]).is_err());
+}
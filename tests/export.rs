@@ -0,0 +1,148 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use rusqlite::Connection;
+
+fn count(conn: &Connection, sql: &str) -> i64 {
+    conn.query_row(sql, [], |row| row.get(0)).unwrap()
+}
+
+#[test]
+fn test_export_sqlite_writes_tables_and_updates_incrementally() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn a() {}".ai(), "fn b() {}".ai(), "fn c() {}"]);
+    let first = repo.stage_all_and_commit("First").unwrap();
+    let mut other = repo.filename("other.rs");
+    other.set_contents(lines!["fn d() {}".ai()]);
+    repo.stage_all_and_commit("Second").unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let db = dir.path().join("authorship.db");
+    let db_arg = db.to_str().unwrap();
+    let output = repo.git_ai(&["export", "sqlite", db_arg]).unwrap();
+    assert!(output.contains("Exported 2 commit(s)"), "{}", output);
+    assert!(output.contains("0 unchanged, 0 removed"), "{}", output);
+
+    let conn = Connection::open(&db).unwrap();
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM commits"), 2);
+    let (subject, added, ai): (String, i64, i64) = conn
+        .query_row(
+            "SELECT subject, lines_added, ai_lines FROM commits WHERE sha = ?1",
+            [&first.commit_sha],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .unwrap();
+    assert_eq!((subject.as_str(), added, ai), ("First", 3, 2));
+    assert_eq!(
+        count(
+            &conn,
+            "SELECT SUM(end_line - start_line + 1) FROM line_attributions \
+             JOIN prompts ON prompts.commit_sha = line_attributions.commit_sha \
+             AND prompts.hash = line_attributions.prompt_hash"
+        ),
+        3
+    );
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM agents"), 1);
+    assert_eq!(
+        count(&conn, "SELECT COUNT(*) FROM files WHERE ai_lines > 0"),
+        2
+    );
+    drop(conn);
+
+    // A new commit is written, the others are left alone
+    let mut third = repo.filename("third.rs");
+    third.set_contents(lines!["fn e() {}".ai()]);
+    repo.stage_all_and_commit("Third").unwrap();
+    let output = repo.git_ai(&["export", "sqlite", db_arg]).unwrap();
+    assert!(output.contains("Exported 1 commit(s)"), "{}", output);
+    assert!(output.contains("2 unchanged, 0 removed"), "{}", output);
+
+    // A removed note takes its rows with it
+    repo.git(&["notes", "--ref=ai", "remove", &first.commit_sha])
+        .unwrap();
+    let output = repo.git_ai(&["export", "sqlite", db_arg]).unwrap();
+    assert!(output.contains("Exported 0 commit(s)"), "{}", output);
+    assert!(output.contains("2 unchanged, 1 removed"), "{}", output);
+    let conn = Connection::open(&db).unwrap();
+    assert_eq!(count(&conn, "SELECT COUNT(*) FROM commits"), 2);
+    assert_eq!(
+        count(
+            &conn,
+            &format!(
+                "SELECT COUNT(*) FROM line_attributions WHERE commit_sha = '{}'",
+                first.commit_sha
+            )
+        ),
+        0
+    );
+}
+
+#[test]
+fn test_export_sqlite_refuses_other_databases() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn a() {}".ai()]);
+    repo.stage_all_and_commit("First").unwrap();
+
+    let dir = tempfile::tempdir().unwrap();
+    let db = dir.path().join("other.db");
+    Connection::open(&db)
+        .unwrap()
+        .execute_batch("CREATE TABLE unrelated (id INTEGER)")
+        .unwrap();
+    let result = repo.git_ai(&["export", "sqlite", db.to_str().unwrap()]);
+    assert!(result.unwrap_err().contains("delete it to export again"));
+}
+
+#[test]
+fn test_export_csv_rows_per_attested_range() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn a() {}".ai(), "fn b() {}".ai(), "fn c() {}"]);
+    let first = repo.stage_all_and_commit("First").unwrap();
+    let mut other = repo.filename("other, with comma.rs");
+    other.set_contents(lines!["fn d() {}", "fn e() {}".ai()]);
+    let second = repo.stage_all_and_commit("Second").unwrap();
+
+    let output = repo.git_ai(&["export", "csv"]).unwrap();
+    let rows: Vec<&str> = output.lines().collect();
+    assert!(rows[0].starts_with("commit,authored_at,author,path,start_line,end_line,lines"));
+    let ai_rows: Vec<&&str> = rows[1..]
+        .iter()
+        .filter(|row| row.contains(",true,"))
+        .collect();
+    assert_eq!(ai_rows.len(), 2, "{}", output);
+    assert!(ai_rows[0].starts_with(&first.commit_sha), "{}", output);
+    assert!(ai_rows[0].contains(",lib.rs,1,2,2,"), "{}", output);
+    assert!(ai_rows[1].starts_with(&second.commit_sha), "{}", output);
+    assert!(
+        ai_rows[1].contains(",\"other, with comma.rs\",2,2,1,"),
+        "{}",
+        output
+    );
+
+    // A range only covers its own commits
+    let dir = tempfile::tempdir().unwrap();
+    let csv = dir.path().join("attribution.csv");
+    repo.git_ai(&[
+        "export",
+        "csv",
+        "HEAD~1..HEAD",
+        "--output",
+        csv.to_str().unwrap(),
+    ])
+    .unwrap();
+    let written = std::fs::read_to_string(&csv).unwrap();
+    assert!(
+        written
+            .lines()
+            .skip(1)
+            .all(|row| row.starts_with(&second.commit_sha))
+    );
+    assert_eq!(
+        written.lines().filter(|row| row.contains(",true,")).count(),
+        1
+    );
+}
@@ -0,0 +1,124 @@
+//! Stress tests that run hooks and checkpoints while git calls and storage writes randomly fail.
+//! Run with `cargo test --features fault-injection --test fault_injection`.
+#![cfg(feature = "fault-injection")]
+
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+const FAULTS: &str = "git=0.2,write=0.15,enospc=0.05";
+
+fn assert_no_panic(output: &str, context: &str) {
+    assert!(
+        !output.contains("panicked at"),
+        "git-ai panicked during {}:\n{}",
+        context,
+        output
+    );
+}
+
+/// Checkpoints and commits under faults: the commits always land and nothing panics
+fn commit_under_faults(repo: &TestRepo, seed: u64, rounds: usize) {
+    let mut file = repo.filename("stress.txt");
+    file.set_contents(lines!["base"]);
+    repo.stage_all_and_commit("base").unwrap();
+
+    for round in 0..rounds {
+        let seed = (seed + round as u64).to_string();
+        let env = [
+            ("GIT_AI_FAULTS", FAULTS),
+            ("GIT_AI_FAULT_SEED", seed.as_str()),
+        ];
+
+        std::fs::write(
+            repo.path().join("stress.txt"),
+            format!("base\nround {}\n", round),
+        )
+        .unwrap();
+        let checkpoint = repo.git_ai_with_env(&["checkpoint", "mock_ai", "stress.txt"], &env);
+        assert_no_panic(&checkpoint.unwrap_or_else(|stderr| stderr), "checkpoint");
+
+        repo.git_with_env(&["add", "-A"], &env).unwrap();
+        let message = format!("round {}", round);
+        let commit = repo.git_with_env(&["commit", "-m", &message], &env);
+        let output = commit.unwrap_or_else(|stderr| panic!("commit failed:\n{}", stderr));
+        assert_no_panic(&output, "commit");
+
+        let subject = repo.git(&["log", "-1", "--format=%s"]).unwrap();
+        assert_eq!(subject.trim(), message);
+    }
+}
+
+#[test]
+fn test_commits_survive_injected_faults() {
+    let repo = TestRepo::new();
+    commit_under_faults(&repo, 1, 25);
+}
+
+#[test]
+fn test_repo_recovers_once_faults_stop() {
+    let repo = TestRepo::new();
+    commit_under_faults(&repo, 1000, 15);
+
+    let mut file = repo.filename("after.txt");
+    file.set_contents(lines!["human line", "ai line".ai()]);
+    repo.stage_all_and_commit("after faults").unwrap();
+    file.assert_lines_and_blame(lines!["human line".human(), "ai line".ai()]);
+}
+
+#[test]
+fn test_rewrites_survive_injected_faults() {
+    let repo = TestRepo::new();
+    commit_under_faults(&repo, 5000, 5);
+
+    for seed in 0..10 {
+        let seed = seed.to_string();
+        let env = [
+            ("GIT_AI_FAULTS", FAULTS),
+            ("GIT_AI_FAULT_SEED", seed.as_str()),
+        ];
+        let amend = repo.git_with_env(&["commit", "--amend", "-m", "amended"], &env);
+        assert_no_panic(
+            &amend.unwrap_or_else(|stderr| panic!("amend failed:\n{}", stderr)),
+            "amend",
+        );
+        let reset = repo.git_with_env(&["reset", "--soft", "HEAD~1"], &env);
+        assert_no_panic(
+            &reset.unwrap_or_else(|stderr| panic!("reset failed:\n{}", stderr)),
+            "reset",
+        );
+        let commit = repo.git_with_env(&["commit", "-m", "recommit"], &env);
+        assert_no_panic(
+            &commit.unwrap_or_else(|stderr| panic!("commit failed:\n{}", stderr)),
+            "commit",
+        );
+    }
+}
+
+#[test]
+fn test_attribution_resumes_after_torn_checkpoint_writes() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("torn.txt");
+    file.set_contents(lines!["base"]);
+    repo.stage_all_and_commit("base").unwrap();
+
+    for seed in 0..20 {
+        let seed = seed.to_string();
+        let env = [
+            ("GIT_AI_FAULTS", "write=0.5,enospc=0.2"),
+            ("GIT_AI_FAULT_SEED", seed.as_str()),
+        ];
+        std::fs::write(
+            repo.path().join("scratch.txt"),
+            format!("scratch {}\n", seed),
+        )
+        .unwrap();
+        let checkpoint = repo.git_ai_with_env(&["checkpoint", "mock_ai", "scratch.txt"], &env);
+        assert_no_panic(&checkpoint.unwrap_or_else(|stderr| stderr), "checkpoint");
+    }
+
+    file.insert_at(1, lines!["ai line".ai()]);
+    repo.stage_all_and_commit("after torn writes").unwrap();
+    file.assert_lines_and_blame(lines!["base".human(), "ai line".ai()]);
+}
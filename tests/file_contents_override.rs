@@ -0,0 +1,69 @@
+mod repos;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+fn agent_v1_checkpoint(repo: &TestRepo, file_contents: serde_json::Value) {
+    let hook_input = serde_json::json!({
+        "type": "ai_agent",
+        "repo_working_dir": repo.path().to_str().unwrap(),
+        "transcript": { "messages": [{ "type": "user", "text": "Add ai()" }] },
+        "agent_name": "editor-plugin",
+        "model": "model-1",
+        "conversation_id": "conv-1",
+        "file_contents": file_contents,
+    })
+    .to_string();
+    repo.git_ai(&["checkpoint", "agent-v1", "--hook-input", &hook_input])
+        .unwrap();
+}
+
+#[test]
+fn test_unsaved_buffer_is_attributed_before_it_is_saved() {
+    let repo = TestRepo::new();
+    fs::write(repo.path().join("lib.rs"), "// base\n").unwrap();
+    repo.stage_all_and_commit("Base").unwrap();
+
+    // The agent's edit is only in the editor's buffer; disk still matches HEAD
+    let buffer = "// base\nfn ai() {}\n";
+    agent_v1_checkpoint(&repo, serde_json::json!({ "lib.rs": buffer }));
+
+    let checkpoints = repo
+        .current_working_logs()
+        .read_all_checkpoints()
+        .unwrap();
+    assert_eq!(checkpoints.len(), 1);
+    assert_eq!(checkpoints[0].entries[0].file, "lib.rs");
+
+    // Saving the buffer later doesn't make the line the user's
+    fs::write(repo.path().join("lib.rs"), buffer).unwrap();
+    repo.git_ai(&["checkpoint"]).unwrap();
+    let commit = repo.stage_all_and_commit("Agent work").unwrap();
+    let prompts: Vec<_> = commit.authorship_log.metadata.prompts.values().collect();
+    assert_eq!(prompts.len(), 1);
+    assert_eq!(prompts[0].agent_id.tool, "editor-plugin");
+    assert_eq!(prompts[0].accepted_lines, 1);
+}
+
+#[test]
+fn test_file_contents_accept_absolute_paths_inside_the_repo() {
+    let repo = TestRepo::new();
+    fs::write(repo.path().join("lib.rs"), "// base\n").unwrap();
+    repo.stage_all_and_commit("Base").unwrap();
+
+    let absolute = repo.path().join("new.rs").to_str().unwrap().to_string();
+    agent_v1_checkpoint(
+        &repo,
+        serde_json::json!({ absolute: "fn new() {}\n", "/elsewhere/x.rs": "outside\n" }),
+    );
+
+    let checkpoints = repo
+        .current_working_logs()
+        .read_all_checkpoints()
+        .unwrap();
+    let files: Vec<&str> = checkpoints[0]
+        .entries
+        .iter()
+        .map(|entry| entry.file.as_str())
+        .collect();
+    assert_eq!(files, vec!["new.rs"]);
+}
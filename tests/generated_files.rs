@@ -0,0 +1,46 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+fn stats_json(repo: &TestRepo) -> serde_json::Value {
+    // The JSON is on stdout, which comes before the debug lines stats writes to stderr
+    let output = repo.git_ai(&["stats", "--json"]).unwrap();
+    serde_json::from_str(output.lines().next().unwrap()).unwrap()
+}
+
+#[test]
+fn test_lockfile_lines_are_left_out_of_the_split() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("main.rs");
+    file.set_contents(lines!["fn main() {}".ai(), "// entry point".ai()]);
+    let lockfile: String = (0..50).map(|i| format!("dep-{}\n", i)).collect();
+    fs::write(repo.path().join("Cargo.lock"), lockfile).unwrap();
+    repo.stage_all_and_commit("Add main and lockfile").unwrap();
+
+    let stats = stats_json(&repo);
+    assert_eq!(stats["git_diff_added_lines"], 52);
+    assert_eq!(stats["generated_added_lines"], 50);
+    assert_eq!(stats["ai_additions"], 2);
+    assert_eq!(stats["human_additions"], 0);
+}
+
+#[test]
+fn test_generated_paths_setting_extends_builtin_list() {
+    let repo = TestRepo::new();
+    fs::write(
+        repo.path().join(".gitai.toml"),
+        "generated_paths = [\"schema.graphql\"]\n",
+    )
+    .unwrap();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn handwritten() {}"]);
+    fs::create_dir_all(repo.path().join("api")).unwrap();
+    fs::write(repo.path().join("api/schema.graphql"), "type A\ntype B\n").unwrap();
+    repo.stage_all_and_commit("Add schema").unwrap();
+
+    let stats = stats_json(&repo);
+    assert_eq!(stats["generated_added_lines"], 2);
+    assert_eq!(stats["human_additions"], 2);
+}
@@ -1,8 +1,17 @@
+#[macro_use]
+mod repos;
 mod test_utils;
 
 use git_ai::authorship::transcript::Message;
-use git_ai::commands::checkpoint_agent::agent_presets::GithubCopilotPreset;
+use git_ai::commands::checkpoint_agent::agent_presets::{
+    AgentCheckpointFlags, AgentCheckpointPreset, GithubCopilotPreset,
+};
+use git_ai::commands::checkpoint_agent::copilot_chat_preset::CopilotChatPreset;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
 use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
 use test_utils::load_fixture;
 
 #[test]
@@ -202,3 +211,99 @@ fn test_copilot_deduplicates_edited_filepaths() {
     assert!(paths.contains(&"/Users/test/file.ts".to_string()));
     assert!(paths.contains(&"/Users/test/other.ts".to_string()));
 }
+
+#[test]
+fn test_copilot_chat_preset_reads_session_file() {
+    let preset = CopilotChatPreset {
+        working_dir: PathBuf::from("/Users/svarlamov/projects/testing-git"),
+        session: Some("tests/fixtures/copilot_session_simple.json".to_string()),
+    };
+    let result = preset
+        .run(AgentCheckpointFlags { hook_input: None })
+        .expect("Failed to run CopilotChatPreset");
+
+    assert_eq!(result.agent_id.tool, "github-copilot");
+    assert_eq!(result.agent_id.id, "331fed7f-cfd6-409c-8502-06a56322b636");
+    assert_eq!(result.agent_id.model, "copilot/claude-sonnet-4");
+    assert_eq!(
+        result.edited_filepaths.unwrap(),
+        vec!["/Users/svarlamov/projects/testing-git/index.ts"]
+    );
+    assert!(!result.transcript.unwrap().messages.is_empty());
+}
+
+#[test]
+fn test_checkpoint_copilot_chat_finds_workspace_session() {
+    let repo = TestRepo::new();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Project"]);
+    repo.stage_all_and_commit("Initial commit").unwrap();
+
+    // Copilot wrote lib.rs; the person edited the README meanwhile
+    let lib_path = repo.path().join("lib.rs");
+    fs::write(&lib_path, "fn generated() {}\n").unwrap();
+    fs::write(repo.path().join("README.md"), "# Project\nBy hand\n").unwrap();
+
+    let user_dir = repo.path().join(".git").join("vscode");
+    // Another workspace's sessions are left alone
+    let other = user_dir.join("workspaceStorage").join("0123");
+    fs::create_dir_all(other.join("chatSessions")).unwrap();
+    fs::write(
+        other.join("workspace.json"),
+        json!({"folder": "file:///elsewhere"}).to_string(),
+    )
+    .unwrap();
+    let workspace = user_dir.join("workspaceStorage").join("4567");
+    fs::create_dir_all(workspace.join("chatSessions")).unwrap();
+    fs::write(
+        workspace.join("workspace.json"),
+        json!({"folder": format!("file://{}", repo.path().display())}).to_string(),
+    )
+    .unwrap();
+    let session = json!({
+        "sessionId": "chat-1",
+        "requests": [{
+            "timestamp": 1728308673835u64,
+            "message": {"text": "Add a generated function"},
+            "response": [
+                {"kind": "markdown", "value": "Writing lib.rs"},
+                {"kind": "textEditGroup", "uri": {"fsPath": lib_path.to_str().unwrap()}}
+            ],
+            "modelId": "copilot/gpt-4.1"
+        }]
+    });
+    fs::write(
+        workspace.join("chatSessions").join("chat-1.json"),
+        session.to_string(),
+    )
+    .unwrap();
+
+    repo.git_ai_with_env(
+        &["checkpoint", "--agent", "copilot-chat"],
+        &[("GIT_AI_VSCODE_USER_DIR", user_dir.to_str().unwrap())],
+    )
+    .unwrap();
+    repo.stage_all_and_commit("Generated").unwrap();
+
+    let mut lib = repo.filename("lib.rs");
+    lib.assert_lines_and_blame(lines!["fn generated() {}".ai()]);
+    readme.assert_lines_and_blame(lines!["# Project".human(), "By hand".human()]);
+    let note = repo.git(&["notes", "--ref=ai", "show", "HEAD"]).unwrap();
+    assert!(note.contains("chat-1"), "{}", note);
+
+    let result = repo.git_ai_with_env(
+        &[
+            "checkpoint",
+            "--agent",
+            "copilot-chat",
+            "--session",
+            "missing",
+        ],
+        &[("GIT_AI_VSCODE_USER_DIR", user_dir.to_str().unwrap())],
+    );
+    assert!(
+        result
+            .unwrap_err()
+            .contains("No Copilot chat session missing")
+    );
+}
@@ -0,0 +1,109 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::{TestRepo, plain_git};
+
+#[test]
+fn test_commit_outside_git_ai_keeps_uncommitted_ai_lines() {
+    let repo = TestRepo::new();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Project"]);
+    repo.stage_all_and_commit("Initial").unwrap();
+
+    let mut ai_file = repo.filename("ai.py");
+    ai_file.set_contents(lines!["def parse():".ai(), "    return 1".ai()]);
+
+    // Another tool commits an unrelated file, moving HEAD past the working log's base
+    std::fs::write(repo.path().join("notes.txt"), "external\n").unwrap();
+    plain_git(repo.path(), &["add", "notes.txt"]);
+    plain_git(
+        repo.path(),
+        &["commit", "-q", "-m", "External", "--", "notes.txt"],
+    );
+
+    repo.stage_all_and_commit("Add parser").unwrap();
+    ai_file.assert_lines_and_blame(lines!["def parse():".ai(), "    return 1".ai()]);
+}
+
+#[test]
+fn test_reset_outside_git_ai_restores_unwound_ai_lines() {
+    let repo = TestRepo::new();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Project"]);
+    repo.stage_all_and_commit("Initial").unwrap();
+
+    let mut parser = repo.filename("parser.py");
+    parser.set_contents(lines!["def parse():".ai(), "    return 1".ai()]);
+    repo.stage_all_and_commit("Add parser").unwrap();
+    let mut lexer = repo.filename("lexer.py");
+    lexer.set_contents(lines!["def lex():".ai(), "    return []".ai()]);
+    repo.stage_all_and_commit("Add lexer").unwrap();
+
+    // Squash both commits behind git-ai's back, then commit through it
+    plain_git(repo.path(), &["reset", "-q", "--soft", "HEAD~2"]);
+    repo.stage_all_and_commit("Add parser and lexer").unwrap();
+    parser.assert_lines_and_blame(lines!["def parse():".ai(), "    return 1".ai()]);
+    lexer.assert_lines_and_blame(lines!["def lex():".ai(), "    return []".ai()]);
+}
+
+#[test]
+fn test_checkpoints_after_head_moved_are_kept() {
+    let repo = TestRepo::new();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Project"]);
+    repo.stage_all_and_commit("Initial").unwrap();
+
+    let mut parser = repo.filename("parser.py");
+    parser.set_contents(lines!["def parse():".ai(), "    return 1".ai()]);
+    repo.stage_all_and_commit("Add parser").unwrap();
+
+    // The agent keeps working after HEAD moved; its checkpoints are already on the new HEAD
+    plain_git(repo.path(), &["reset", "-q", "--soft", "HEAD~1"]);
+    let mut lexer = repo.filename("lexer.py");
+    lexer.set_contents(lines!["def lex():".ai(), "    return []".ai()]);
+
+    repo.stage_all_and_commit("Add parser and lexer").unwrap();
+    lexer.assert_lines_and_blame(lines!["def lex():".ai(), "    return []".ai()]);
+}
+
+#[test]
+fn test_amend_outside_git_ai_warns_with_recovery() {
+    let repo = TestRepo::new();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Project"]);
+    repo.stage_all_and_commit("Initial").unwrap();
+
+    let mut parser = repo.filename("parser.py");
+    parser.set_contents(lines!["def parse():".ai(), "    return 1".ai()]);
+    let original = repo.stage_all_and_commit("Add parser").unwrap().commit_sha;
+
+    // Another tool rewrites the commit, so the new one has no note
+    plain_git(
+        repo.path(),
+        &["commit", "-q", "--amend", "-m", "Add the parser"],
+    );
+    let amended = repo.git(&["rev-parse", "HEAD"]).unwrap().trim().to_string();
+
+    // The next command git-ai runs notices
+    let output = repo.git(&["reset", "--soft", "HEAD"]).unwrap();
+    assert!(
+        output.contains(
+            "git-ai: lost the attribution of 2 AI lines in 1 file (history rewritten outside git-ai:"
+        ),
+        "{}",
+        output
+    );
+    let recovery = format!(
+        "git-ai: recover them with: git-ai backfill-squash {} --from {}",
+        amended, original
+    );
+    assert!(output.contains(&recovery), "{}", output);
+
+    repo.git_ai(&["backfill-squash", &amended, "--from", &original])
+        .unwrap();
+    parser.assert_lines_and_blame(lines!["def parse():".ai(), "    return 1".ai()]);
+
+    // Only warned about once
+    let output = repo.git(&["reset", "--soft", "HEAD"]).unwrap();
+    assert!(!output.contains("lost the attribution"), "{}", output);
+}
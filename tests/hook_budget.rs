@@ -0,0 +1,70 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+/// Every post-commit goes over a 1ms budget
+const SLOW_HOOKS: &[(&str, &str)] = &[
+    ("GIT_AI_HOOK_BUDGET_MS", "1"),
+    ("GIT_AI_DEFER_SLOW_HOOKS", "true"),
+];
+
+fn commit_ai_line(repo: &TestRepo, name: &str, envs: &[(&str, &str)]) -> String {
+    let mut file = repo.filename(name);
+    file.set_contents(lines!["fn generated() {}".ai()]);
+    repo.git(&["add", "-A"]).unwrap();
+    repo.git_with_env(&["commit", "-m", name], envs).unwrap()
+}
+
+#[test]
+fn test_slow_post_commit_is_deferred_to_process_queue() {
+    let repo = TestRepo::new();
+    commit_ai_line(&repo, "one.rs", SLOW_HOOKS);
+    commit_ai_line(&repo, "two.rs", SLOW_HOOKS);
+    let output = commit_ai_line(&repo, "three.rs", SLOW_HOOKS);
+    assert!(
+        output.contains("over the 1ms budget 3 times in a row")
+            && output.contains("now runs in the background"),
+        "{}",
+        output
+    );
+
+    let health = fs::read_to_string(repo.path().join(".git/ai/hook_health.json")).unwrap();
+    let health: serde_json::Value = serde_json::from_str(&health).unwrap();
+    assert_eq!(health["hooks"]["post-commit"]["deferred"], true);
+
+    // The fourth commit only queues its attribution
+    commit_ai_line(&repo, "four.rs", SLOW_HOOKS);
+    repo.git_ai_with_env(&["process-queue"], SLOW_HOOKS)
+        .unwrap();
+    let note = repo.git(&["notes", "--ref=ai", "show", "HEAD"]).unwrap();
+    assert!(note.contains("four.rs"), "{}", note);
+    repo.filename("four.rs")
+        .assert_lines_and_blame(lines!["fn generated() {}".ai()]);
+
+    let status = repo
+        .git_ai_with_env(&["process-queue", "--status"], SLOW_HOOKS)
+        .unwrap();
+    assert!(status.contains("0 commit(s) waiting"), "{}", status);
+    assert!(status.contains("post-commit"), "{}", status);
+
+    // Without the setting post-commit runs inline again
+    commit_ai_line(&repo, "five.rs", &[]);
+    let note = repo.git(&["notes", "--ref=ai", "show", "HEAD"]).unwrap();
+    assert!(note.contains("five.rs"), "{}", note);
+}
+
+#[test]
+fn test_slow_hooks_only_warn_by_default() {
+    let repo = TestRepo::new();
+    let budget = [("GIT_AI_HOOK_BUDGET_MS", "1")];
+    commit_ai_line(&repo, "one.rs", &budget);
+    commit_ai_line(&repo, "two.rs", &budget);
+    let output = commit_ai_line(&repo, "three.rs", &budget);
+    assert!(output.contains("set defer_slow_hooks"), "{}", output);
+
+    // Still attributed inline
+    let note = repo.git(&["notes", "--ref=ai", "show", "HEAD"]).unwrap();
+    assert!(note.contains("three.rs"), "{}", note);
+}
@@ -0,0 +1,76 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+#[test]
+fn test_init_sets_up_fresh_repository() {
+    let repo = TestRepo::new();
+    let output = repo
+        .git_ai(&[
+            "init",
+            "--notes-ref",
+            "team",
+            "--exclude",
+            "generated/**",
+            "--hooks",
+        ])
+        .unwrap();
+    assert!(output.contains("Wrote"), "{}", output);
+    assert!(output.contains("refs/notes/team"), "{}", output);
+    assert!(output.contains("Checkpoint ok"), "{}", output);
+
+    let config = fs::read_to_string(repo.path().join(".gitai.toml")).unwrap();
+    assert!(config.contains("notes_ref = \"team\""), "{}", config);
+    assert!(
+        config.contains("exclude_paths = [\"generated/**\"]"),
+        "{}",
+        config
+    );
+    let hook = repo.path().join(".git").join("hooks").join("pre-auto-gc");
+    assert!(
+        fs::read_to_string(hook)
+            .unwrap()
+            .contains("maintenance run")
+    );
+
+    // The starter config is in effect for the first commit
+    fs::create_dir_all(repo.path().join("generated")).unwrap();
+    let mut generated = repo.filename("generated/schema.rs");
+    generated.set_contents(lines!["struct Schema;".ai()]);
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn ai() {}".ai()]);
+    let commit = repo.stage_all_and_commit("First commit").unwrap();
+
+    let note = repo
+        .git(&["notes", "--ref=team", "show", &commit.commit_sha])
+        .unwrap();
+    assert!(note.contains("lib.rs"));
+    assert!(!note.contains("generated/schema.rs"));
+}
+
+#[test]
+fn test_init_keeps_existing_config() {
+    let repo = TestRepo::new();
+    let path = repo.path().join(".gitai.toml");
+    fs::write(&path, "# ours\ntranscript_mode = \"summary\"\n").unwrap();
+
+    let output = repo.git_ai(&["init", "--no-checkpoint"]).unwrap();
+    assert!(output.contains("Kept existing"), "{}", output);
+    assert!(!output.contains("Checkpoint"), "{}", output);
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "# ours\ntranscript_mode = \"summary\"\n"
+    );
+
+    repo.git_ai(&["init", "--notes-ref", "team", "--no-checkpoint"])
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "# ours\ntranscript_mode = \"summary\"\nnotes_ref = \"team\"\n"
+    );
+
+    let result = repo.git_ai(&["init", "--notes-ref", "bad..ref"]);
+    assert!(result.unwrap_err().contains("Invalid notes ref"));
+}
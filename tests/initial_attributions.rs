@@ -63,6 +63,9 @@ fn test_initial_only_no_blame_data() {
             total_deletions: 0,
             accepted_lines: 0,
             overriden_lines: 0,
+            summary: None,
+            environments: Vec::new(),
+            generated_at: None,
         },
     );
 
@@ -151,6 +154,9 @@ fn test_initial_wins_overlaps() {
             total_deletions: 0,
             accepted_lines: 0,
             overriden_lines: 0,
+            summary: None,
+            environments: Vec::new(),
+            generated_at: None,
         },
     );
 
@@ -226,6 +232,9 @@ fn test_initial_and_blame_merge() {
             total_deletions: 0,
             accepted_lines: 0,
             overriden_lines: 0,
+            summary: None,
+            environments: Vec::new(),
+            generated_at: None,
         },
     );
     prompts.insert(
@@ -242,6 +251,9 @@ fn test_initial_and_blame_merge() {
             total_deletions: 0,
             accepted_lines: 0,
             overriden_lines: 0,
+            summary: None,
+            environments: Vec::new(),
+            generated_at: None,
         },
     );
 
@@ -312,6 +324,9 @@ fn test_partial_file_coverage() {
             total_deletions: 0,
             accepted_lines: 0,
             overriden_lines: 0,
+            summary: None,
+            environments: Vec::new(),
+            generated_at: None,
         },
     );
 
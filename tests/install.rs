@@ -0,0 +1,135 @@
+#[macro_use]
+mod repos;
+use repos::test_repo::{TestRepo, plain_git};
+use std::fs;
+
+fn home_envs(home: &std::path::Path) -> Vec<(&'static str, String)> {
+    vec![
+        ("HOME", home.to_str().unwrap().to_string()),
+        ("SHELL", "/bin/bash".to_string()),
+        (
+            "XDG_CONFIG_HOME",
+            home.join(".config").to_str().unwrap().to_string(),
+        ),
+    ]
+}
+
+/// A bare repo next to `repo`, added to it as remote `name`
+fn bare_remote(repo: &TestRepo, name: &str) -> std::path::PathBuf {
+    let path = repo.path().with_extension(format!("{}.git", name));
+    plain_git(
+        repo.path().parent().unwrap(),
+        &["init", "-q", "--bare", path.to_str().unwrap()],
+    );
+    repo.git(&["remote", "add", name, path.to_str().unwrap()])
+        .unwrap();
+    path
+}
+
+fn install(repo: &TestRepo, home: &std::path::Path, args: &[&str]) -> Result<String, String> {
+    let envs = home_envs(home);
+    let envs: Vec<(&str, &str)> = envs.iter().map(|(k, v)| (*k, v.as_str())).collect();
+    repo.git_ai_with_env(args, &envs)
+}
+
+#[test]
+fn test_install_sets_up_shim_rc_and_refspecs() {
+    let repo = TestRepo::new();
+    let home = tempfile::tempdir().unwrap();
+    let origin = bare_remote(&repo, "origin");
+    fs::write(repo.path().join("README.md"), "# Repo\n").unwrap();
+    repo.stage_all_and_commit("Initial").unwrap();
+    plain_git(
+        repo.path(),
+        &["notes", "--ref=ai", "add", "-f", "-m", "{}", "HEAD"],
+    );
+    plain_git(
+        repo.path(),
+        &["push", "-q", "origin", "HEAD", "refs/notes/ai"],
+    );
+    assert!(!plain_git(&origin, &["show-ref", "refs/notes/ai"]).is_empty());
+
+    let output = install(&repo, home.path(), &["install"]).unwrap();
+    assert!(output.contains("notes refspecs"), "{}", output);
+
+    let bin = home.path().join(".git-ai").join("bin");
+    assert!(fs::read_link(bin.join("git")).is_ok());
+    assert!(fs::read_link(bin.join("git-og")).is_ok());
+
+    let config = fs::read_to_string(home.path().join(".git-ai").join("config.json")).unwrap();
+    assert!(config.contains("git_path"), "{}", config);
+
+    let rc = fs::read_to_string(home.path().join(".bashrc")).unwrap();
+    let path_line = format!("export PATH=\"{}:$PATH\"", bin.display());
+    assert!(rc.contains(&path_line), "{}", rc);
+
+    let fetch = repo
+        .git(&["config", "--get-all", "remote.origin.fetch"])
+        .unwrap();
+    assert!(
+        fetch.contains("+refs/notes/ai:refs/notes/ai-remote/origin"),
+        "{}",
+        fetch
+    );
+
+    // A second run changes nothing
+    install(&repo, home.path(), &["install"]).unwrap();
+    let rc = fs::read_to_string(home.path().join(".bashrc")).unwrap();
+    assert_eq!(rc.matches(&path_line).count(), 1);
+    let fetch = repo
+        .git(&["config", "--get-all", "remote.origin.fetch"])
+        .unwrap();
+    assert_eq!(fetch.matches("refs/notes/ai-remote/origin").count(), 1);
+}
+
+#[test]
+fn test_install_leaves_remotes_without_notes_fetchable() {
+    let repo = TestRepo::new();
+    let home = tempfile::tempdir().unwrap();
+    bare_remote(&repo, "origin");
+    fs::write(repo.path().join("README.md"), "# Repo\n").unwrap();
+    repo.stage_all_and_commit("Initial").unwrap();
+    plain_git(repo.path(), &["push", "-q", "origin", "HEAD"]);
+
+    install(&repo, home.path(), &["install"]).unwrap();
+    let fetch = repo
+        .git(&["config", "--get-all", "remote.origin.fetch"])
+        .unwrap();
+    assert!(!fetch.contains("refs/notes/ai"), "{}", fetch);
+
+    // Plain git, so nothing but the configured refspecs is fetched
+    plain_git(repo.path(), &["fetch", "origin"]);
+}
+
+#[test]
+fn test_install_dry_run_and_alias() {
+    let repo = TestRepo::new();
+    let home = tempfile::tempdir().unwrap();
+
+    let output = install(&repo, home.path(), &["install", "--alias", "--dry-run"]).unwrap();
+    assert!(output.contains("alias git="), "{}", output);
+    assert!(output.contains("Dry run"), "{}", output);
+    assert!(!home.path().join(".git-ai").exists());
+    assert!(!home.path().join(".bashrc").exists());
+}
+
+#[test]
+fn test_doctor_fails_before_install() {
+    let repo = TestRepo::new();
+    let home = tempfile::tempdir().unwrap();
+
+    let output = repo
+        .git_ai_command(&["doctor"])
+        .env("HOME", home.path())
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("✓ git:"), "{}", stdout);
+    assert!(stdout.contains("✗ shim:"), "{}", stdout);
+    assert!(
+        stdout.contains("✓ git backend: object reads use "),
+        "{}",
+        stdout
+    );
+}
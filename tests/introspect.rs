@@ -0,0 +1,58 @@
+mod repos;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+fn introspect(repo: &TestRepo, command: &str) -> serde_json::Value {
+    let output = repo.git_ai(&[command]).unwrap();
+    serde_json::from_str(output.trim()).unwrap()
+}
+
+#[test]
+fn test_introspect_describes_repository() {
+    let repo = TestRepo::new();
+    fs::write(repo.path().join(".gitai.toml"), "notes_ref = \"team\"\n").unwrap();
+
+    let introspection = introspect(&repo, "introspect");
+    assert_eq!(introspection["schema_version"], 1);
+    assert_eq!(introspection["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(introspection["authorship_log_version"], "authorship/3.1.0");
+
+    let commands = introspection["commands"].as_array().unwrap();
+    let stats = commands
+        .iter()
+        .find(|command| command["name"] == "stats")
+        .unwrap();
+    assert_eq!(stats["json"], true);
+
+    let notes_ref = introspection["settings"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|setting| setting["key"] == "notes_ref")
+        .unwrap();
+    assert_eq!(notes_ref["default"], "ai");
+    assert_eq!(notes_ref["value"], "team");
+    assert!(
+        notes_ref["origin"]
+            .as_str()
+            .unwrap()
+            .ends_with(".gitai.toml")
+    );
+
+    let storage = &introspection["storage"]["repository"];
+    assert_eq!(storage["notes_ref"], "refs/notes/team");
+    assert!(
+        storage["working_logs"]
+            .as_str()
+            .unwrap()
+            .replace('\\', "/")
+            .ends_with(".git/ai/working_logs")
+    );
+    assert_eq!(
+        introspection["storage"]["repo_registry"],
+        repo.repo_registry_path().to_str().unwrap()
+    );
+
+    // The name GUI clients were first told about
+    assert_eq!(introspect(&repo, "completions-data"), introspection);
+}
@@ -0,0 +1,67 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+fn last_outcome(repo: &TestRepo) -> serde_json::Value {
+    let output = repo.git_ai(&["last-outcome"]).unwrap();
+    serde_json::from_str(output.trim()).unwrap()
+}
+
+#[test]
+fn test_last_outcome_records_commit_hooks_and_notes() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn human() {}", "fn ai() {}".ai()]);
+    let commit = repo.stage_all_and_commit("AI work").unwrap().commit_sha;
+
+    let outcome = last_outcome(&repo);
+    assert_eq!(outcome["command"], "commit");
+    assert_eq!(outcome["exit_code"], 0);
+    let hooks: Vec<(&str, bool)> = outcome["hooks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|hook| {
+            (
+                hook["name"].as_str().unwrap(),
+                hook["success"].as_bool().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(hooks, vec![("pre-commit", true), ("post-commit", true)]);
+    assert_eq!(outcome["notes_written"], serde_json::json!([commit]));
+
+    // Commands without hooks leave the previous outcome alone, as do hooked commands that
+    // touch neither HEAD nor notes
+    repo.git(&["status"]).unwrap();
+    assert_eq!(last_outcome(&repo)["command"], "commit");
+    repo.git(&["gc", "--auto"]).unwrap();
+    assert_eq!(last_outcome(&repo)["command"], "commit");
+    repo.git_ai(&["last-outcome", "--check"]).unwrap();
+}
+
+#[test]
+fn test_last_outcome_written_to_env_path() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn ai() {}".ai()]);
+    repo.git(&["add", "-A"]).unwrap();
+
+    let outcome_path = repo.path().join("outcome.json");
+    repo.git_with_env(
+        &["commit", "-m", "AI work"],
+        &[("GIT_AI_OUTCOME_FILE", outcome_path.to_str().unwrap())],
+    )
+    .unwrap();
+
+    let outcome: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&outcome_path).unwrap()).unwrap();
+    assert_eq!(outcome, last_outcome(&repo));
+}
+
+#[test]
+fn test_last_outcome_without_any_command() {
+    let repo = TestRepo::new();
+    assert!(repo.git_ai(&["last-outcome"]).is_err());
+}
@@ -0,0 +1,80 @@
+#[macro_use]
+mod repos;
+use git_ai::authorship::authorship_log_serialization::AuthorshipLog;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn latency_json(repo: &TestRepo, args: &[&str]) -> serde_json::Value {
+    let mut full_args = vec!["stats", "--latency", "--json"];
+    full_args.extend(args);
+    let output = repo.git_ai(&full_args).unwrap();
+    serde_json::from_str(output.lines().next().unwrap()).unwrap()
+}
+
+/// Commit everything with an author date `delay_secs` from now
+fn commit_after(repo: &TestRepo, message: &str, delay_secs: u64) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let date = format!("@{} +0000", now + delay_secs);
+    repo.git(&["add", "-A"]).unwrap();
+    repo.git_with_env(
+        &["commit", "-m", message],
+        &[("GIT_AUTHOR_DATE", date.as_str())],
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_latency_from_checkpoint_to_author_date() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines![
+        "fn human() {}",
+        "fn ai() {}".ai(),
+        "",
+        "fn more_ai() {}".ai()
+    ]);
+    commit_after(&repo, "Reviewed for an hour", 3600);
+
+    let note = repo.git(&["notes", "--ref=ai", "show", "HEAD"]).unwrap();
+    let log = AuthorshipLog::deserialize_from_string(&note).unwrap();
+    assert!(
+        log.metadata
+            .prompts
+            .values()
+            .all(|prompt| prompt.generated_at.is_some())
+    );
+
+    let report = latency_json(&repo, &[]);
+    assert_eq!(report["commits"], 1);
+    assert_eq!(report["hunks"], 2);
+    let p50 = report["p50"].as_u64().unwrap();
+    assert!((3600..3700).contains(&p50), "p50 was {}", p50);
+    assert_eq!(report["by_agent"]["mock_ai"]["hunks"], 2);
+    assert_eq!(report["untimed_hunks"], 0);
+}
+
+#[test]
+fn test_latency_over_a_range() {
+    let repo = TestRepo::new();
+    let mut base = repo.filename("base.txt");
+    base.set_contents(lines!["base"]);
+    repo.stage_all_and_commit("Base").unwrap();
+
+    let mut quick = repo.filename("quick.rs");
+    quick.set_contents(lines!["fn quick() {}".ai()]);
+    commit_after(&repo, "Quick", 0);
+
+    let mut slow = repo.filename("slow.rs");
+    slow.set_contents(lines!["fn slow() {}".ai()]);
+    commit_after(&repo, "Slow", 86400);
+
+    let report = latency_json(&repo, &["HEAD~2..HEAD"]);
+    assert_eq!(report["commits"], 2);
+    assert_eq!(report["hunks"], 2);
+    assert!(report["p50"].as_u64().unwrap() < 100);
+    assert!(report["max"].as_u64().unwrap() >= 86400);
+}
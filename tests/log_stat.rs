@@ -0,0 +1,52 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+#[test]
+fn test_log_stat_annotates_ai_share_per_file() {
+    let repo = TestRepo::new();
+    let mut parser = repo.filename("parser.rs");
+    parser.set_contents(lines![
+        "fn parse() {}".ai(),
+        "fn lex() {}".ai(),
+        "fn tokens() {}".ai(),
+        "// reviewed",
+    ]);
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Parser"]);
+    repo.stage_all_and_commit("Add parser").unwrap();
+
+    let output = repo.git_ai(&["log", "--stat", "-n", "1"]).unwrap();
+    assert!(output.contains("    Add parser"), "{}", output);
+    assert!(
+        output.contains(" parser.rs | 4 ++++  [ai 75%]"),
+        "{}",
+        output
+    );
+    assert!(output.contains(" README.md | 1 +\n"), "{}", output);
+    assert!(
+        output.contains(" 2 files changed, 5 insertions(+), 3 by AI (60%)"),
+        "{}",
+        output
+    );
+}
+
+#[test]
+fn test_log_stat_json_covers_each_commit() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("a.txt");
+    file.set_contents(lines!["one".ai()]);
+    repo.stage_all_and_commit("First").unwrap();
+    file.insert_at(1, lines!["two"]);
+    repo.stage_all_and_commit("Second").unwrap();
+
+    let output = repo.git_ai(&["log", "--stat", "--json"]).unwrap();
+    let commits: serde_json::Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+    let commits = commits.as_array().unwrap();
+    assert_eq!(commits.len(), 2);
+    assert_eq!(commits[0]["message"], "Second");
+    assert_eq!(commits[0]["files"][0]["ai_lines"], 0);
+    assert_eq!(commits[1]["message"], "First");
+    assert_eq!(commits[1]["files"][0]["ai_lines"], 1);
+}
@@ -0,0 +1,145 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+const MISSING_COMMIT: &str = "0123456789abcdef0123456789abcdef01234567";
+
+/// A working log whose base commit isn't in the repository, as left behind after git
+/// garbage collected an amended commit
+fn orphaned_working_log(repo: &TestRepo) -> PathBuf {
+    let dir = repo
+        .path()
+        .join(".git")
+        .join("ai")
+        .join("working_logs")
+        .join(MISSING_COMMIT);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join("checkpoints.jsonl"), "").unwrap();
+    dir
+}
+
+fn commit_ai_file(repo: &TestRepo, name: &str) {
+    let mut file = repo.filename(name);
+    file.set_contents(lines!["fn generated() {}".ai()]);
+    repo.stage_all_and_commit(name).unwrap();
+}
+
+#[test]
+fn test_maintenance_run_removes_orphaned_working_logs() {
+    let repo = TestRepo::new();
+    commit_ai_file(&repo, "lib.rs");
+    let orphan = orphaned_working_log(&repo);
+    let head_log = repo.current_working_logs().dir;
+
+    let output = repo
+        .git_ai(&["maintenance", "run", "--task", "gc"])
+        .unwrap();
+    assert!(
+        output.contains("removed 1 working log(s) of missing commits"),
+        "{}",
+        output
+    );
+    assert!(!orphan.exists());
+    assert!(head_log.exists());
+
+    let result = repo.git_ai(&["maintenance", "run", "--task", "repack"]);
+    assert!(result.unwrap_err().contains("unknown task repack"));
+}
+
+#[test]
+fn test_git_auto_gc_runs_registered_maintenance() {
+    let repo = TestRepo::new();
+    repo.git_ai(&["maintenance", "register"]).unwrap();
+    let hook = repo.path().join(".git").join("hooks").join("pre-auto-gc");
+    assert!(
+        fs::read_to_string(&hook)
+            .unwrap()
+            .contains("maintenance run --quiet")
+    );
+    assert_eq!(
+        repo.git(&["config", "maintenance.gc.enabled"])
+            .unwrap()
+            .trim(),
+        "true"
+    );
+
+    // Two packs are more than gc.autoPackLimit allows, so git decides a gc is due
+    commit_ai_file(&repo, "one.rs");
+    repo.git(&["repack", "-q"]).unwrap();
+    commit_ai_file(&repo, "two.rs");
+    repo.git(&["repack", "-q"]).unwrap();
+    let orphan = orphaned_working_log(&repo);
+
+    // Real git, not git-ai: the hook is what runs git-ai's tasks
+    let status = Command::new("git")
+        .args(["-c", "gc.autoPackLimit=1", "-c", "gc.autoDetach=false"])
+        .args(["gc", "--auto", "--quiet"])
+        .current_dir(repo.path())
+        .env_remove("GIT_AI")
+        .env("GIT_AI_REPO_REGISTRY", repo.repo_registry_path())
+        .status()
+        .unwrap();
+    assert!(status.success());
+    assert!(!orphan.exists());
+
+    repo.git_ai(&["maintenance", "unregister"]).unwrap();
+    assert!(!hook.exists());
+}
+
+#[test]
+fn test_gc_dry_run_reports_and_gc_reclaims() {
+    let repo = TestRepo::new();
+    commit_ai_file(&repo, "lib.rs");
+    let orphan = orphaned_working_log(&repo);
+    fs::write(orphan.join("checkpoints.jsonl"), "x".repeat(2048)).unwrap();
+
+    let blobs = repo.current_working_logs().dir.join("blobs");
+    fs::create_dir_all(&blobs).unwrap();
+    let stale_blob = blobs.join("0".repeat(64));
+    fs::write(&stale_blob, "unreferenced\n").unwrap();
+
+    let rewrite_log = repo.path().join(".git").join("ai").join("rewrite_log");
+    let events: Vec<String> = (0..30)
+        .map(|i| {
+            format!(
+                r#"{{"commit":{{"base_commit":null,"commit_sha":"{:040}"}}}}"#,
+                i
+            )
+        })
+        .collect();
+    fs::write(&rewrite_log, events.join("\n")).unwrap();
+
+    let output = repo.git_ai(&["gc", "--dry-run", "--now"]).unwrap();
+    assert!(
+        output.contains(
+            "Would remove 1 working log(s) of missing commits, 1 unreferenced blob(s) and 10 rewrite log event(s)"
+        ),
+        "{}",
+        output
+    );
+    assert!(output.contains("Would reclaim 2."), "{}", output);
+    assert!(orphan.exists());
+    assert!(stale_blob.exists());
+
+    // Without --now the fresh blob is kept
+    let output = repo.git_ai(&["gc"]).unwrap();
+    assert!(output.contains("0 unreferenced blob(s)"), "{}", output);
+    assert!(!orphan.exists());
+    assert!(stale_blob.exists());
+    assert_eq!(
+        fs::read_to_string(&rewrite_log).unwrap().lines().count(),
+        20
+    );
+
+    let output = repo.git_ai(&["gc", "--now"]).unwrap();
+    assert!(
+        output.contains("Removed 0 working log(s) of missing commits, 1 unreferenced blob(s) and 0 rewrite log event(s)"),
+        "{}",
+        output
+    );
+    assert!(!stale_blob.exists());
+}
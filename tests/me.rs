@@ -0,0 +1,35 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+#[test]
+fn test_me_aggregates_registered_repo() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+
+    file.set_contents(lines!["Human line", "AI line 1".ai(), "AI line 2".ai()]);
+    repo.stage_all_and_commit("Initial commit").unwrap();
+
+    // Committing through git-ai registers the repo
+    let registry = std::fs::read_to_string(repo.repo_registry_path()).unwrap();
+    let workdir = repo.path().canonicalize().unwrap();
+    assert!(registry.contains(workdir.to_str().unwrap()), "{}", registry);
+
+    let json = repo.git_ai(&["me", "--json"]).unwrap();
+    let report: serde_json::Value = serde_json::from_str(json.trim()).unwrap();
+    assert_eq!(report["emails"][0], "test@example.com");
+    assert_eq!(report["week"]["commits"], 1);
+    assert_eq!(report["week"]["lines_added"], 3);
+    assert_eq!(report["month"]["ai_accepted"], 2);
+    assert_eq!(report["top_agents"][0][0], "mock_ai");
+    assert_eq!(report["daily_ai_accepted"][29], 2);
+
+    let output = repo.git_ai(&["me"]).unwrap();
+    assert!(
+        output.contains("test@example.com across 1 repo"),
+        "{}",
+        output
+    );
+    assert!(output.contains("mock_ai"), "{}", output);
+}
@@ -0,0 +1,132 @@
+#[macro_use]
+mod repos;
+use git_ai::authorship::authorship_log::LineRange;
+use git_ai::authorship::authorship_log_serialization::AuthorshipLog;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+fn attested_lines(log: &AuthorshipLog, file: &str) -> Vec<u32> {
+    log.attestations
+        .iter()
+        .filter(|attestation| attestation.file_path == file)
+        .flat_map(|attestation| &attestation.entries)
+        .flat_map(|entry| &entry.line_ranges)
+        .flat_map(LineRange::expand)
+        .collect()
+}
+
+/// main has AI work of its own, then merges a branch with two more AI lines using --no-ff
+fn merge_ai_branch(repo: &TestRepo) {
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn base() {}".ai(), "// shared"]);
+    repo.stage_all_and_commit("Base").unwrap();
+    let default_branch = repo.current_branch();
+
+    repo.git(&["checkout", "-b", "feature"]).unwrap();
+    file.insert_at(2, lines!["fn parse() {}".ai(), "fn render() {}".ai()]);
+    repo.stage_all_and_commit("Feature").unwrap();
+
+    repo.git(&["checkout", &default_branch]).unwrap();
+    let mut notes = repo.filename("NOTES.md");
+    notes.set_contents(lines!["# Notes"]);
+    repo.stage_all_and_commit("Notes").unwrap();
+
+    repo.git(&["merge", "--no-ff", "feature", "-m", "Merge feature"])
+        .unwrap();
+}
+
+#[test]
+fn test_no_ff_merge_gets_a_note_for_the_merged_lines() {
+    let repo = TestRepo::new();
+    merge_ai_branch(&repo);
+
+    let log = repo.note_for("HEAD");
+    // Only the lines the merge brought in, not main's own AI line
+    assert_eq!(attested_lines(&log, "lib.rs"), vec![3, 4]);
+    assert_eq!(log.metadata.prompts.len(), 1);
+    assert!(attested_lines(&log, "NOTES.md").is_empty());
+
+    // The merge counts the branch's AI lines when main is walked along first parents
+    let output = repo
+        .git_ai(&["log", "--stat", "--json", "--first-parent", "-n", "1"])
+        .unwrap();
+    let commits: serde_json::Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+    assert_eq!(commits[0]["message"], "Merge feature");
+    assert_eq!(commits[0]["files"][0]["path"], "lib.rs");
+    assert_eq!(commits[0]["files"][0]["ai_lines"], 2);
+
+    let output = repo.git_ai(&["stats", "--json"]).unwrap();
+    let stats: serde_json::Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+    assert_eq!(stats["ai_additions"], 2);
+
+    // Blame still credits the commit that wrote the lines
+    repo.filename("lib.rs").assert_lines_and_blame(lines![
+        "fn base() {}".ai(),
+        "// shared".human(),
+        "fn parse() {}".ai(),
+        "fn render() {}".ai(),
+    ]);
+}
+
+#[test]
+fn test_backfill_writes_missing_merge_commit_notes() {
+    let repo = TestRepo::new();
+    merge_ai_branch(&repo);
+    repo.git(&["notes", "--ref=ai", "remove", "HEAD"]).unwrap();
+
+    repo.git_ai(&["backfill"]).unwrap();
+    let log = repo.note_for("HEAD");
+    assert_eq!(attested_lines(&log, "lib.rs"), vec![3, 4]);
+}
+
+#[test]
+fn test_range_walks_count_merged_lines_once() {
+    let repo = TestRepo::new();
+    merge_ai_branch(&repo);
+    let root = repo
+        .git(&["rev-list", "--max-parents=0", "HEAD"])
+        .unwrap()
+        .trim()
+        .to_string();
+    let range = format!("{}..HEAD", root);
+    let feature = repo
+        .git(&["rev-parse", "feature"])
+        .unwrap()
+        .trim()
+        .to_string();
+    let merge = repo.git(&["rev-parse", "HEAD"]).unwrap().trim().to_string();
+
+    let retention = |walk: &[&str]| -> serde_json::Value {
+        let mut args = vec!["retention", "--json", "--range", &range];
+        args.extend_from_slice(walk);
+        serde_json::from_str(repo.git_ai(&args).unwrap().trim()).unwrap()
+    };
+
+    // Every commit is walked and the merge's note is skipped: the lines count at the branch
+    // commit that wrote them
+    for walk in [&[][..], &["--no-merges"][..]] {
+        let report = retention(walk);
+        assert_eq!(report["generated"], 2);
+        assert_eq!(report["retained"], 2);
+        assert_eq!(report["commits"].as_array().unwrap().len(), 1);
+        assert_eq!(report["commits"][0]["commit"], feature.as_str());
+    }
+
+    // Along first parents the branch isn't walked, so the merge counts for it
+    let report = retention(&["--first-parent"]);
+    assert_eq!(report["generated"], 2);
+    assert_eq!(report["retained"], 2);
+    assert_eq!(report["commits"].as_array().unwrap().len(), 1);
+    assert_eq!(report["commits"][0]["commit"], merge.as_str());
+
+    // Leaving merges out of a first-parent walk leaves nothing to count
+    let report = retention(&["--first-parent", "--no-merges"]);
+    assert_eq!(report["generated"], 0);
+
+    let output = repo
+        .git_ai(&["sessions", "--json", "--range", &range, "--first-parent"])
+        .unwrap();
+    let sessions: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+    assert_eq!(sessions["sessions"][0]["commits"][0], merge.as_str());
+    assert_eq!(sessions["sessions"][0]["lines_produced"], 2);
+}
@@ -258,3 +258,51 @@ fn test_blame_after_merge_conflict_resolution() {
         "Line 10".human(),
     ]);
 }
+
+#[test]
+fn test_merge_continue_runs_post_commit_hook() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+
+    file.set_contents(lines!["Line 1", "Line 2", "Line 3"]);
+    repo.stage_all_and_commit("Initial commit").unwrap();
+
+    let default_branch = repo.current_branch();
+
+    repo.git(&["checkout", "-b", "feature"]).unwrap();
+    file.replace_at(1, "FEATURE VERSION".ai());
+    repo.stage_all_and_commit("feature change").unwrap();
+
+    repo.git(&["checkout", &default_branch]).unwrap();
+    file = repo.filename("test.txt");
+    file.replace_at(1, "MAIN VERSION");
+    repo.stage_all_and_commit("main change").unwrap();
+
+    let merge_result = repo.git(&["merge", "feature", "-m", "merge feature"]);
+    assert!(merge_result.is_err(), "Merge should conflict");
+
+    std::fs::write(
+        repo.path().join("test.txt"),
+        "Line 1\nMAIN VERSION\nLine 3\nResolved by hand",
+    )
+    .unwrap();
+    repo.git(&["add", "test.txt"]).unwrap();
+
+    repo.git_with_env(&["merge", "--continue"], &[("GIT_EDITOR", "true")])
+        .unwrap();
+
+    // The merge commit landed and got the same authorship note a `git commit` would write
+    assert!(repo.git(&["rev-parse", "HEAD^2"]).is_ok());
+    assert!(
+        repo.git(&["notes", "--ref=ai", "show", "HEAD"]).is_ok(),
+        "merge --continue should write an authorship note for the merge commit"
+    );
+
+    file = repo.filename("test.txt");
+    file.assert_lines_and_blame(lines![
+        "Line 1".human(),
+        "MAIN VERSION".human(),
+        "Line 3".human(),
+        "Resolved by hand".human(),
+    ]);
+}
@@ -0,0 +1,182 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+fn export(repo: &TestRepo, args: &[&str]) -> (String, serde_json::Value) {
+    let mut full_args = vec!["metrics", "export"];
+    full_args.extend_from_slice(args);
+    let output = repo.git_ai(&full_args).unwrap();
+    let json = output.lines().next().unwrap().to_string();
+    let value = serde_json::from_str(&json).unwrap();
+    (json, value)
+}
+
+#[test]
+fn test_metrics_export_anonymized_has_counts_only() {
+    let repo = TestRepo::new();
+    let mut parser = repo.filename("secret_parser.rs");
+    parser.set_contents(lines![
+        "fn parse() {}".ai(),
+        "fn lex() {}".ai(),
+        "fn tokens() {}".ai(),
+        "// reviewed",
+    ]);
+    repo.stage_all_and_commit("Add parser").unwrap();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Parser", "Usage"]);
+    repo.stage_all_and_commit("Add readme").unwrap();
+
+    let (json, metrics) = export(&repo, &["--anonymize", "--since", "2000-01-01"]);
+    assert_eq!(metrics["schema_version"], 1);
+    assert_eq!(metrics["anonymized"], true);
+    assert_eq!(metrics["since"], "2000-01-01");
+    assert!(metrics.get("repository").is_none());
+    assert!(metrics.get("authors").is_none());
+
+    let totals = &metrics["totals"];
+    assert_eq!(totals["commits"], 2);
+    assert_eq!(totals["commits_with_ai"], 1);
+    assert_eq!(totals["authors"], 1);
+    assert_eq!(totals["lines_added"], 6);
+    assert_eq!(totals["ai_accepted"], 3);
+    assert_eq!(totals["ai_percentage"], 50);
+
+    assert_eq!(metrics["agents"][0]["agent"], "mock_ai");
+    assert_eq!(metrics["agents"][0]["commits"], 1);
+    assert_eq!(metrics["languages"][0]["language"], "rust");
+    assert_eq!(metrics["languages"][0]["ai_lines"], 3);
+    assert_eq!(metrics["languages"][0]["ai_percentage"], 75);
+    assert_eq!(metrics["languages"][1]["language"], "markdown");
+
+    // Nothing that identifies the repository, its files or its people
+    for identifying in ["secret_parser", "README", "Test User", "test@example.com"] {
+        assert!(
+            !json.contains(identifying),
+            "{} leaked: {}",
+            identifying,
+            json
+        );
+    }
+    assert!(!json.contains(repo.path().to_str().unwrap()));
+}
+
+#[test]
+fn test_metrics_export_since_and_identities() {
+    let repo = TestRepo::new();
+    let mut old = repo.filename("old.go");
+    old.set_contents(lines!["package old", "func Old() {}"]);
+    repo.git(&["add", "-A"]).unwrap();
+    let old_date = "2001-01-01T12:00:00Z";
+    repo.git_with_env(
+        &["commit", "-m", "Old"],
+        &[
+            ("GIT_AUTHOR_DATE", old_date),
+            ("GIT_COMMITTER_DATE", old_date),
+        ],
+    )
+    .unwrap();
+    let mut file = repo.filename("a.py");
+    file.set_contents(lines!["print('hi')".ai()]);
+    repo.stage_all_and_commit("Recent").unwrap();
+
+    let (_, metrics) = export(&repo, &[]);
+    assert!(metrics["since"].is_null());
+    assert_eq!(metrics["anonymized"], false);
+    assert_eq!(metrics["totals"]["commits"], 2);
+    assert!(metrics["repository"].is_string());
+    assert_eq!(
+        metrics["authors"][0]["author"],
+        "Test User <test@example.com>"
+    );
+    assert_eq!(metrics["authors"][0]["commits"], 2);
+    assert_eq!(metrics["authors"][0]["ai_accepted"], 1);
+
+    let (_, metrics) = export(&repo, &["--anonymize", "--since", "2020-01-01"]);
+    assert_eq!(metrics["totals"]["commits"], 1);
+    assert_eq!(metrics["totals"]["lines_added"], 1);
+    assert_eq!(metrics["languages"].as_array().unwrap().len(), 1);
+    assert_eq!(metrics["languages"][0]["language"], "python");
+
+    assert!(
+        repo.git_ai(&["metrics", "export", "--since", "last week"])
+            .is_err()
+    );
+    assert!(repo.git_ai(&["metrics"]).is_err());
+}
+
+#[test]
+fn test_metrics_export_dp_noise_is_labeled_and_reproducible() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("a.py");
+    file.set_contents(lines!["print('hi')".ai(), "print('bye')"]);
+    repo.stage_all_and_commit("Add a").unwrap();
+
+    let (_, exact) = export(&repo, &["--anonymize"]);
+    assert!(exact.get("noise").is_none());
+
+    let args = [
+        "--anonymize",
+        "--since",
+        "2000-01-01",
+        "--dp-epsilon",
+        "0.5",
+        "--dp-salt",
+        "q4",
+    ];
+    let (_, first) = export(&repo, &args);
+    let (_, second) = export(&repo, &args);
+    assert_eq!(first["noise"]["mechanism"], "laplace");
+    assert_eq!(first["noise"]["epsilon"], 0.5);
+    // The commit moves several counts, so the noise is scaled past a count's own sensitivity
+    assert!(first["noise"]["sensitivity"].as_u64().unwrap() > 1);
+    assert_eq!(first["totals"], second["totals"]);
+    assert_eq!(first["languages"], second["languages"]);
+
+    assert!(
+        repo.git_ai(&["metrics", "export", "--dp-epsilon", "0"])
+            .is_err()
+    );
+}
+
+#[test]
+fn test_metrics_export_sample_is_labeled_as_estimate() {
+    let repo = TestRepo::new();
+    for i in 0..8 {
+        let mut file = repo.filename(&format!("f{}.py", i));
+        if i % 2 == 0 {
+            file.set_contents(lines!["x = 1".ai(), "y = 2".ai()]);
+        } else {
+            file.set_contents(lines!["x = 1", "y = 2"]);
+        }
+        repo.stage_all_and_commit(&format!("Commit {}", i)).unwrap();
+    }
+
+    let (_, exact) = export(&repo, &["--anonymize"]);
+    assert!(exact.get("sampling").is_none());
+    assert_eq!(exact["totals"]["lines_added"], 16);
+
+    let (_, sampled) = export(&repo, &["--anonymize", "--max-commits", "4"]);
+    let sampling = &sampled["sampling"];
+    assert_eq!(sampling["population_commits"], 8);
+    assert_eq!(sampling["sampled_commits"], 4);
+    assert_eq!(sampling["confidence"], 0.95);
+    assert_eq!(sampled["totals"]["commits"], 8);
+    // Every commit adds two lines, so the estimate has no spread
+    assert_eq!(sampled["totals"]["lines_added"], 16);
+    // Which commits are sampled depends on their shas, so only the interval's shape is fixed
+    let ai = &sampling["intervals"]["totals.ai_accepted"];
+    let ai_estimate = sampled["totals"]["ai_accepted"].as_u64().unwrap();
+    assert!(ai["low"].as_u64().unwrap() <= ai_estimate);
+    assert!(ai_estimate <= ai["high"].as_u64().unwrap());
+
+    // A sample as large as the history is just the exact export
+    let (_, full) = export(&repo, &["--anonymize", "--sample", "100%"]);
+    assert!(full.get("sampling").is_none());
+    assert_eq!(full["totals"], exact["totals"]);
+
+    assert!(
+        repo.git_ai(&["metrics", "export", "--sample", "0%"])
+            .is_err()
+    );
+}
@@ -0,0 +1,93 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+fn git_dir(repo: &TestRepo) -> std::path::PathBuf {
+    repo.path().join(".git")
+}
+
+#[test]
+fn test_migrate_dry_run_changes_nothing() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn ai() {}".ai()]);
+    repo.stage_all_and_commit("AI work").unwrap();
+
+    let output = repo
+        .git_ai(&[
+            "migrate",
+            "--notes-ref",
+            "team",
+            "--storage",
+            "info",
+            "--dry-run",
+        ])
+        .unwrap();
+    assert!(output.contains("Would move refs/notes/ai -> refs/notes/team"));
+
+    assert!(repo.git(&["notes", "--ref=ai", "show", "HEAD"]).is_ok());
+    assert!(repo.git(&["notes", "--ref=team", "show", "HEAD"]).is_err());
+    assert!(git_dir(&repo).join("ai").is_dir());
+    assert!(!git_dir(&repo).join("info/ai").exists());
+    assert!(!repo.path().join(".gitai.toml").exists());
+}
+
+#[test]
+fn test_migrate_moves_notes_and_storage() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn ai() {}".ai()]);
+    repo.stage_all_and_commit("AI work").unwrap();
+    let tracking_tip = repo.git(&["rev-parse", "refs/notes/ai"]).unwrap();
+    repo.git(&[
+        "update-ref",
+        "refs/notes/ai-remote/origin",
+        tracking_tip.trim(),
+    ])
+    .unwrap();
+
+    repo.git_ai(&["migrate", "--notes-ref", "team", "--storage", "info"])
+        .unwrap();
+
+    assert!(repo.git(&["notes", "--ref=team", "show", "HEAD"]).is_ok());
+    assert!(
+        repo.git(&["rev-parse", "--verify", "refs/notes/ai"])
+            .is_err()
+    );
+    assert_eq!(
+        repo.git(&["rev-parse", "refs/notes/team-remote/origin"])
+            .unwrap(),
+        tracking_tip
+    );
+    assert!(!git_dir(&repo).join("ai").exists());
+    assert!(git_dir(&repo).join("info/ai/working_logs").is_dir());
+    assert_eq!(
+        fs::read_to_string(repo.path().join(".gitai.toml")).unwrap(),
+        "notes_ref = \"team\"\n"
+    );
+
+    // Later work is tracked in the new places
+    file.insert_at(1, lines!["fn more_ai() {}".ai()]);
+    repo.stage_all_and_commit("More AI work").unwrap();
+    assert!(repo.git(&["notes", "--ref=team", "show", "HEAD"]).is_ok());
+    assert!(!git_dir(&repo).join("ai/working_logs").exists());
+    file.assert_lines_and_blame(lines!["fn ai() {}".ai(), "fn more_ai() {}".ai()]);
+}
+
+#[test]
+fn test_migrate_refuses_taken_notes_ref() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn ai() {}".ai()]);
+    repo.stage_all_and_commit("AI work").unwrap();
+    repo.git(&["notes", "--ref=team", "add", "-m", "other", "HEAD"])
+        .unwrap();
+
+    let result = repo.git_ai(&["migrate", "--notes-ref", "team", "--storage", "info"]);
+    assert!(result.is_err());
+    // Nothing moved, not even the storage
+    assert!(git_dir(&repo).join("ai").is_dir());
+    assert!(repo.git(&["notes", "--ref=ai", "show", "HEAD"]).is_ok());
+}
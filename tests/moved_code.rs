@@ -0,0 +1,58 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+fn commit_ai_helper_then_move_it(repo: &TestRepo) {
+    let mut source = repo.filename("source.rs");
+    source.set_contents(lines![
+        "fn keep() {}",
+        "fn helper(value: u32) -> u32 {".ai(),
+        "    let doubled = value * 2;".ai(),
+        "    doubled + 1".ai(),
+        "}".ai(),
+    ]);
+    let mut target = repo.filename("target.rs");
+    target.set_contents(lines!["fn other() {}"]);
+    repo.stage_all_and_commit("AI helper").unwrap();
+
+    // A human cuts the helper out of source.rs and pastes it into target.rs
+    fs::write(repo.path().join("source.rs"), "fn keep() {}\n").unwrap();
+    fs::write(
+        repo.path().join("target.rs"),
+        "fn other() {}\nfn helper(value: u32) -> u32 {\n    let doubled = value * 2;\n    doubled + 1\n}\n",
+    )
+    .unwrap();
+    repo.git_ai(&["checkpoint"]).unwrap();
+    repo.stage_all_and_commit("Move helper").unwrap();
+}
+
+#[test]
+fn test_block_moved_between_files_keeps_ai_attribution() {
+    let repo = TestRepo::new();
+    commit_ai_helper_then_move_it(&repo);
+
+    repo.filename("target.rs").assert_lines_and_blame(lines![
+        "fn other() {}".human(),
+        "fn helper(value: u32) -> u32 {".ai(),
+        "    let doubled = value * 2;".ai(),
+        "    doubled + 1".ai(),
+        "}".ai(),
+    ]);
+}
+
+#[test]
+fn test_block_moved_between_files_with_move_detection_disabled() {
+    let repo = TestRepo::new();
+    std::fs::write(repo.path().join(".gitai.toml"), "detect_moves = false\n").unwrap();
+    commit_ai_helper_then_move_it(&repo);
+
+    repo.filename("target.rs").assert_lines_and_blame(lines![
+        "fn other() {}".human(),
+        "fn helper(value: u32) -> u32 {".human(),
+        "    let doubled = value * 2;".human(),
+        "    doubled + 1".human(),
+        "}".human(),
+    ]);
+}
@@ -0,0 +1,194 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::{TestRepo, plain_git};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A repo with a bare `origin` and one commit with AI lines, not pushed yet
+fn repo_with_origin() -> (TestRepo, PathBuf, String) {
+    let repo = TestRepo::new();
+    let origin = repo.path().with_extension("origin.git");
+    plain_git(
+        repo.path().parent().unwrap(),
+        &["init", "-q", "--bare", origin.to_str().unwrap()],
+    );
+    repo.git(&["remote", "add", "origin", origin.to_str().unwrap()])
+        .unwrap();
+
+    let mut parser = repo.filename("parser.py");
+    parser.set_contents(lines!["def parse():".ai(), "    return 1".ai()]);
+    let commit = repo.stage_all_and_commit("Add parser").unwrap();
+    (repo, origin, commit.commit_sha)
+}
+
+fn remote_note(origin: &Path, commit: &str) -> Option<String> {
+    Command::new("git")
+        .args(["notes", "--ref=ai", "show", commit])
+        .current_dir(origin)
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[test]
+fn test_push_and_fetch_sync_notes() {
+    let (repo, origin, commit) = repo_with_origin();
+    let main = repo.current_branch();
+    repo.git(&["push", "-u", "origin", &main]).unwrap();
+    let note = remote_note(&origin, &commit).expect("push didn't push the note");
+    assert!(note.contains("parser.py"), "{}", note);
+
+    // Another clone picks the note up with its fetch
+    let other = TestRepo::new();
+    other
+        .git(&["remote", "add", "origin", origin.to_str().unwrap()])
+        .unwrap();
+    other.git(&["fetch", "origin"]).unwrap();
+    let note = other.git(&["notes", "--ref=ai", "show", &commit]).unwrap();
+    assert!(note.contains("parser.py"), "{}", note);
+}
+
+#[test]
+fn test_push_notes_can_be_turned_off() {
+    let (repo, origin, commit) = repo_with_origin();
+    let main = repo.current_branch();
+    repo.git_with_env(
+        &["push", "-u", "origin", &main],
+        &[("GIT_AI_PUSH_NOTES", "false")],
+    )
+    .unwrap();
+    assert!(remote_note(&origin, &commit).is_none());
+
+    // The next push only sends the notes of the commits it pushes
+    let mut parser = repo.filename("parser.py");
+    parser.insert_at(2, lines!["    # parsed".ai()]);
+    let next = repo
+        .stage_all_and_commit("Comment parser")
+        .unwrap()
+        .commit_sha;
+    repo.git(&["push", "origin", &main]).unwrap();
+    assert!(remote_note(&origin, &next).is_some());
+    assert!(remote_note(&origin, &commit).is_none());
+
+    // Unless the whole notes ref is pushed
+    repo.git_with_env(
+        &["push", "origin", &main],
+        &[("GIT_AI_PUSH_NOTES_SCOPE", "all")],
+    )
+    .unwrap();
+    assert!(remote_note(&origin, &commit).is_some());
+}
+
+#[test]
+fn test_push_only_sends_notes_of_pushed_commits() {
+    let (repo, origin, commit) = repo_with_origin();
+    let main = repo.current_branch();
+
+    repo.git(&["checkout", "-b", "experiment"]).unwrap();
+    let mut scratch = repo.filename("scratch.py");
+    scratch.set_contents(lines!["print('draft')".ai()]);
+    let draft = repo.stage_all_and_commit("Draft").unwrap().commit_sha;
+    repo.git(&["checkout", &main]).unwrap();
+
+    repo.git(&["push", "origin", &main]).unwrap();
+    assert!(remote_note(&origin, &commit).is_some());
+    assert!(remote_note(&origin, &draft).is_none());
+
+    // Pushing the branch later sends its note on top of the ones already there
+    repo.git(&["push", "origin", "experiment"]).unwrap();
+    assert!(remote_note(&origin, &draft).is_some());
+    assert!(remote_note(&origin, &commit).is_some());
+}
+
+#[test]
+fn test_push_sends_notes_of_every_ref_it_pushes() {
+    let (repo, origin, _) = repo_with_origin();
+    let main = repo.current_branch();
+    repo.git(&["push", "origin", &main]).unwrap();
+
+    let commit_on = |branch: &str, file: &str| {
+        repo.git(&["checkout", "-q", "-b", branch, &main]).unwrap();
+        let mut scratch = repo.filename(file);
+        scratch.set_contents(lines!["print('draft')".ai()]);
+        let commit = repo.stage_all_and_commit(branch).unwrap().commit_sha;
+        repo.git(&["checkout", "-q", &main]).unwrap();
+        commit
+    };
+
+    // `--all` pushes every branch, not just the current one
+    let experiment = commit_on("experiment", "experiment.py");
+    repo.git(&["push", "--all", "origin"]).unwrap();
+    assert!(remote_note(&origin, &experiment).is_some());
+
+    // `--tags` pushes the tagged commits
+    let tagged = commit_on("release", "release.py");
+    repo.git(&["tag", "v1", &tagged]).unwrap();
+    repo.git(&["push", "--tags", "origin"]).unwrap();
+    assert!(remote_note(&origin, &tagged).is_some());
+
+    // Without refspecs, `remote.<name>.push` decides what goes
+    let configured = commit_on("configured", "configured.py");
+    repo.git(&["config", "remote.origin.push", "refs/heads/*:refs/heads/*"])
+        .unwrap();
+    repo.git(&["push", "origin"]).unwrap();
+    assert!(remote_note(&origin, &configured).is_some());
+}
+
+#[test]
+fn test_notes_conflict_policy() {
+    let (repo, origin, commit) = repo_with_origin();
+    let main = repo.current_branch();
+    repo.git(&["push", "-u", "origin", &main]).unwrap();
+
+    // The note is edited locally and on the remote at the same time
+    let rewrite = |dir: &Path, note: &str| {
+        plain_git(
+            dir,
+            &[
+                "-c",
+                "user.name=Reviewer",
+                "-c",
+                "user.email=reviewer@example.com",
+                "notes",
+                "--ref=ai",
+                "add",
+                "-f",
+                "-m",
+                note,
+                &commit,
+            ],
+        );
+    };
+    let note = || repo.git(&["notes", "--ref=ai", "show", &commit]).unwrap();
+
+    // By default fetching keeps the local note
+    rewrite(repo.path(), "edited");
+    rewrite(&origin, "rewritten");
+    repo.git(&["fetch", "origin"]).unwrap();
+    assert_eq!(note().trim(), "edited");
+
+    // With notes_conflict = "theirs" the remote's note wins
+    rewrite(repo.path(), "edited again");
+    rewrite(&origin, "rewritten again");
+    repo.git_with_env(&["fetch", "origin"], &[("GIT_AI_NOTES_CONFLICT", "theirs")])
+        .unwrap();
+    assert_eq!(note().trim(), "rewritten again");
+}
+
+#[test]
+fn test_fetch_without_remote_keeps_git_exit_status() {
+    let repo = TestRepo::new();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Project"]);
+    repo.stage_all_and_commit("Initial").unwrap();
+
+    // git itself is fine fetching nothing; the notes fetch has no remote to go to
+    repo.git(&["fetch"]).unwrap();
+
+    // A remote that can't be reached fails git, not git-ai
+    let missing = repo.path().with_extension("missing.git");
+    let err = repo.git(&["fetch", missing.to_str().unwrap()]).unwrap_err();
+    assert!(!err.contains("panicked"), "{}", err);
+}
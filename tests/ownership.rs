@@ -0,0 +1,63 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+fn ownership_json(repo: &TestRepo, args: &[&str]) -> serde_json::Value {
+    let mut full_args = vec!["ownership", "--json"];
+    full_args.extend_from_slice(args);
+    let output = repo.git_ai(&full_args).unwrap();
+    serde_json::from_str(output.trim()).unwrap()
+}
+
+#[test]
+fn test_ownership_of_file_and_directory() {
+    let repo = TestRepo::new();
+    std::fs::create_dir_all(repo.path().join("src")).unwrap();
+    let mut parser = repo.filename("src/parser.rs");
+    parser.set_contents(lines![
+        "fn parse() {}".ai(),
+        "fn lex() {}".ai(),
+        "fn tokens() {}".ai(),
+        "// reviewed",
+    ]);
+    repo.stage_all_and_commit("Add parser").unwrap();
+    let mut util = repo.filename("src/util.rs");
+    util.set_contents(lines!["fn util() {}", "fn more() {}"]);
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Parser"]);
+    repo.stage_all_and_commit("Add util").unwrap();
+
+    let report = ownership_json(&repo, &["src/parser.rs"]);
+    assert_eq!(report["path"], "src/parser.rs");
+    assert_eq!(report["total_lines"], 4);
+    assert_eq!(report["ai_lines"], 3);
+    assert_eq!(report["ai_percentage"], 75);
+    assert_eq!(report["agents"][0]["tool"], "mock_ai");
+    assert_eq!(report["agents"][0]["lines"], 3);
+    assert_eq!(report["sessions"].as_array().unwrap().len(), 1);
+    assert_eq!(report["sessions"][0]["lines"], 3);
+
+    let report = ownership_json(&repo, &["src"]);
+    assert_eq!(report["total_lines"], 6);
+    assert_eq!(report["ai_lines"], 3);
+    assert_eq!(report["human_lines"], 3);
+    assert_eq!(report["ai_percentage"], 50);
+    let files: Vec<&str> = report["files"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|file| file["file"].as_str().unwrap())
+        .collect();
+    assert_eq!(files, vec!["src/parser.rs", "src/util.rs"]);
+
+    let report = ownership_json(&repo, &[]);
+    assert_eq!(report["path"], "");
+    assert_eq!(report["total_lines"], 7);
+
+    let output = repo.git_ai(&["ownership", "src"]).unwrap();
+    assert!(output.contains("src (2 files, 6 lines)"));
+    assert!(output.contains("mock_ai"));
+
+    assert!(repo.git_ai(&["ownership", "missing.rs"]).is_err());
+}
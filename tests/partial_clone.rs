@@ -0,0 +1,135 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::{TestRepo, plain_git};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Output;
+
+/// A repo whose lib.rs went through four commits, two of them adding AI lines
+fn repo_with_history() -> TestRepo {
+    let repo = TestRepo::new();
+    plain_git(repo.path(), &["config", "uploadpack.allowFilter", "true"]);
+    plain_git(
+        repo.path(),
+        &["config", "uploadpack.allowAnySHA1InWant", "true"],
+    );
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn one() {}"]);
+    repo.stage_all_and_commit("One").unwrap();
+    file.insert_at(1, lines!["fn two() {}".ai()]);
+    repo.stage_all_and_commit("Two").unwrap();
+    file.insert_at(2, lines!["fn three() {}"]);
+    repo.stage_all_and_commit("Three").unwrap();
+    file.insert_at(3, lines!["fn four() {}".ai()]);
+    repo.stage_all_and_commit("Four").unwrap();
+    repo
+}
+
+/// A `--filter=blob:none` clone of `repo` with its notes, which has none of the old versions
+/// of lib.rs or the note blobs yet
+fn partial_clone(repo: &TestRepo) -> PathBuf {
+    let clone = repo.path().with_extension("partial");
+    let url = format!("file://{}", repo.path().display());
+    plain_git(
+        repo.path(),
+        &[
+            "clone",
+            "-q",
+            "--filter=blob:none",
+            &url,
+            clone.to_str().unwrap(),
+        ],
+    );
+    plain_git(
+        &clone,
+        &["fetch", "-q", "origin", "refs/notes/ai:refs/notes/ai"],
+    );
+    clone
+}
+
+fn pack_count(clone: &Path) -> usize {
+    fs::read_dir(clone.join(".git/objects/pack"))
+        .unwrap()
+        .filter_map(Result::ok)
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "pack"))
+        .count()
+}
+
+fn blame_in(repo: &TestRepo, clone: &Path) -> Output {
+    repo.git_ai_command(&["blame", "lib.rs"])
+        .current_dir(clone)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_blame_prefetches_history_and_notes_of_partial_clones_in_batches() {
+    let repo = repo_with_history();
+    let clone = partial_clone(&repo);
+
+    let packs_before = pack_count(&clone);
+    let output = blame_in(&repo, &clone);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let blame = String::from_utf8_lossy(&output.stdout);
+    let lines: Vec<&str> = blame.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", blame);
+    assert!(!lines[0].contains("mock_ai"), "{}", blame);
+    assert!(lines[1].contains("mock_ai"), "{}", blame);
+    assert!(!lines[2].contains("mock_ai"), "{}", blame);
+    assert!(lines[3].contains("mock_ai"), "{}", blame);
+
+    // One fetch for the old versions of lib.rs and one for the notes, where lazy fetching
+    // takes one per object
+    assert!(pack_count(&clone) - packs_before <= 2);
+}
+
+#[test]
+fn test_blame_marks_lines_unknown_when_partial_clone_objects_are_unavailable() {
+    let repo = repo_with_history();
+    let clone = partial_clone(&repo);
+    // The promisor remote is gone, so nothing missing can be fetched
+    plain_git(
+        &clone,
+        &["config", "remote.origin.url", "file:///nonexistent/git-ai"],
+    );
+
+    let output = blame_in(&repo, &clone);
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let blame = String::from_utf8_lossy(&output.stdout);
+    assert_eq!(blame.lines().count(), 4, "{}", blame);
+    assert!(
+        blame.lines().all(|line| line.contains("unknown")),
+        "{}",
+        blame
+    );
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("partial clone"), "{}", stderr);
+}
+
+#[test]
+fn test_blame_reads_files_outside_a_sparse_checkout_at_head() {
+    let repo = repo_with_history();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Project"]);
+    repo.stage_all_and_commit("Add readme").unwrap();
+    plain_git(
+        repo.path(),
+        &["sparse-checkout", "set", "--no-cone", "/README.md"],
+    );
+    assert!(!repo.path().join("lib.rs").exists());
+
+    let blame = repo.git_ai(&["blame", "lib.rs"]).unwrap();
+    let lines: Vec<&str> = blame.lines().collect();
+    assert_eq!(lines.len(), 4, "{}", blame);
+    assert!(lines[1].contains("mock_ai"), "{}", blame);
+    assert!(lines[3].contains("mock_ai"), "{}", blame);
+}
@@ -0,0 +1,78 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+const DEFER: &[(&str, &str)] = &[("GIT_AI_DEFER_POST_COMMIT", "true")];
+
+#[test]
+fn test_deferred_commits_are_attributed_by_the_next_invocation() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn parse() {}".ai(), "// by hand"]);
+    repo.git(&["add", "-A"]).unwrap();
+    repo.git_with_env(&["commit", "-m", "Parser"], DEFER)
+        .unwrap();
+
+    // Whether or not the background run got to it first, blame finds the note
+    file.assert_lines_and_blame(lines!["fn parse() {}".ai(), "// by hand".human()]);
+    let queue = repo.path().join(".git").join("ai").join("queue");
+    assert_eq!(fs::read_dir(&queue).unwrap().count(), 0);
+
+    // A second deferred commit builds on the first one's working log
+    file.insert_at(2, lines!["fn render() {}".ai()]);
+    repo.git(&["add", "-A"]).unwrap();
+    repo.git_with_env(&["commit", "-m", "Renderer"], DEFER)
+        .unwrap();
+    file.assert_lines_and_blame(lines![
+        "fn parse() {}".ai(),
+        "// by hand".human(),
+        "fn render() {}".ai(),
+    ]);
+}
+
+#[test]
+fn test_failing_tasks_are_set_aside_until_retried() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn main() {}"]);
+    repo.stage_all_and_commit("Initial").unwrap();
+
+    // A task for a commit that doesn't exist can never succeed
+    let queue = repo.path().join(".git").join("ai").join("queue");
+    fs::create_dir_all(&queue).unwrap();
+    let task = serde_json::json!({
+        "event": {"commit": {
+            "base_commit": null,
+            "commit_sha": "0123456789abcdef0123456789abcdef01234567",
+        }},
+        "commit_author": "Test User <test@example.com>",
+        "queued_at": 0,
+    });
+    fs::write(
+        queue.join("000000000000000000000001-1.json"),
+        task.to_string(),
+    )
+    .unwrap();
+
+    // Each invocation tries it once
+    for _ in 0..2 {
+        repo.git_ai(&["process-queue"]).unwrap();
+        let status = repo.git_ai(&["process-queue", "--status"]).unwrap();
+        assert!(status.contains("1 commit(s) waiting"), "{}", status);
+    }
+    repo.git_ai(&["process-queue"]).unwrap();
+    let status = repo.git_ai(&["process-queue", "--status"]).unwrap();
+    assert!(status.contains("0 commit(s) waiting"), "{}", status);
+    assert!(status.contains("1 commit(s) failed"), "{}", status);
+
+    let output = repo.git_ai(&["process-queue", "--retry"]).unwrap();
+    assert!(
+        output.contains("Queued 1 failed task(s) again"),
+        "{}",
+        output
+    );
+    let status = repo.git_ai(&["process-queue", "--status"]).unwrap();
+    assert!(status.contains("1 commit(s) waiting"), "{}", status);
+}
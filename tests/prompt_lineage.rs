@@ -0,0 +1,73 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::{NewCommit, TestRepo};
+
+fn prompt_for(commit: &NewCommit, file: &str) -> String {
+    let attestation = commit
+        .authorship_log
+        .attestations
+        .iter()
+        .find(|attestation| attestation.file_path == file)
+        .expect("file should be attested");
+    assert_eq!(attestation.entries.len(), 1);
+    attestation.entries[0].hash.clone()
+}
+
+#[test]
+fn test_rewritten_ai_lines_keep_earlier_prompts() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+
+    file.set_contents(lines!["keep", "let total = a + b;".ai()]);
+    let first = repo.stage_all_and_commit("v1").unwrap();
+    let first_prompt = prompt_for(&first, "test.txt");
+
+    // Two more prompts rewrite the line before the next commit
+    file.replace_at(1, "fn sum(items: &[u32]) -> u32 {".ai());
+    file.replace_at(1, "items.iter().copied().sum::<u32>()".ai());
+    let second = repo.stage_all_and_commit("v3").unwrap();
+    let second_prompt = prompt_for(&second, "test.txt");
+    let chain = second
+        .authorship_log
+        .lineage_for("test.txt", &second_prompt)
+        .to_vec();
+    assert_eq!(chain.len(), 2, "{:?}", chain);
+    assert_eq!(chain[1], first_prompt);
+
+    // The chain carries on across commits
+    file.replace_at(1, "Total::from(values)".ai());
+    let third = repo.stage_all_and_commit("v4").unwrap();
+    let third_prompt = prompt_for(&third, "test.txt");
+    let mut expected = vec![second_prompt.clone()];
+    expected.extend(chain);
+    assert_eq!(
+        third.authorship_log.lineage_for("test.txt", &third_prompt),
+        expected.as_slice()
+    );
+    file.assert_lines_and_blame(lines!["keep".human(), "Total::from(values)".ai()]);
+
+    let explanation = repo.git_ai(&["explain-line", "test.txt:2"]).unwrap();
+    assert!(
+        explanation.contains(&format!("rewrote: {} <- ", second_prompt)),
+        "{}",
+        explanation
+    );
+    let untouched = repo.git_ai(&["explain-line", "test.txt:1"]).unwrap();
+    assert!(!untouched.contains("rewrote:"), "{}", untouched);
+}
+
+#[test]
+fn test_human_rewrites_and_new_lines_have_no_lineage() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+
+    file.set_contents(lines!["first line".ai(), "second line".ai()]);
+    repo.stage_all_and_commit("initial").unwrap();
+
+    file.replace_at(0, "replaced by hand".human());
+    file.insert_at(2, lines!["appended line".ai()]);
+    let commit = repo.stage_all_and_commit("edits").unwrap();
+
+    assert!(commit.authorship_log.metadata.lineage.is_empty());
+}
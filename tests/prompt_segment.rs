@@ -0,0 +1,57 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+fn short(repo: &TestRepo) -> String {
+    // A generous budget so the test never sees a stale segment
+    repo.git_ai(&["prompt-segment", "--short", "--budget-ms", "10000"])
+        .unwrap()
+        .trim()
+        .to_string()
+}
+
+#[test]
+fn test_prompt_segment_pending_and_last_commit() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+
+    file.set_contents(lines!["// base"]);
+    repo.stage_all_and_commit("Base").unwrap();
+    assert_eq!(short(&repo), "0 0 0");
+    assert_eq!(repo.git_ai(&["prompt-segment"]).unwrap(), "");
+
+    file.set_contents(lines!["// base", "fn one() {}".ai(), "fn two() {}".ai()]);
+    assert_eq!(short(&repo), "2 0 0");
+    // Served from the cache until the working log or HEAD changes
+    assert!(repo.path().join(".git/ai/prompt_segment.json").is_file());
+    assert_eq!(short(&repo), "2 0 0");
+
+    // The base line gains a newline too, so AI wrote 2 of the 3 added lines
+    repo.stage_all_and_commit("AI").unwrap();
+    let output = repo
+        .git_ai(&["prompt-segment", "--json", "--budget-ms", "10000"])
+        .unwrap();
+    let segment: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+    assert_eq!(segment["pending_ai_lines"], 0);
+    assert_eq!(segment["last_commit_ai_percent"], 67);
+    assert_eq!(segment["stale"], false);
+    assert_eq!(
+        repo.git_ai(&["prompt-segment", "--budget-ms", "10000"])
+            .unwrap()
+            .trim(),
+        "last 67%"
+    );
+}
+
+#[test]
+fn test_prompt_segment_outside_repository_prints_nothing() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = std::process::Command::new(env!("CARGO_BIN_EXE_git-ai"))
+        .args(["prompt-segment", "--short"])
+        .current_dir(dir.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(output.stdout.is_empty());
+}
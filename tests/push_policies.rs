@@ -0,0 +1,121 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::{TestRepo, plain_git};
+
+/// A repo with a bare `origin`, a pushed base commit and one commit with AI lines under
+/// `src/security`, not pushed yet
+fn repo_with_ai_commit() -> TestRepo {
+    let repo = TestRepo::new();
+    let origin = repo.path().with_extension("origin.git");
+    plain_git(
+        repo.path().parent().unwrap(),
+        &["init", "-q", "--bare", origin.to_str().unwrap()],
+    );
+    repo.git(&["remote", "add", "origin", origin.to_str().unwrap()])
+        .unwrap();
+
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Project"]);
+    repo.stage_all_and_commit("Base").unwrap();
+    let main = repo.current_branch();
+    repo.git(&["push", "-u", "origin", &main]).unwrap();
+
+    std::fs::create_dir_all(repo.path().join("src/security")).unwrap();
+    let mut auth = repo.filename("src/security/auth.rs");
+    auth.set_contents(lines![
+        "fn check() {}".ai(),
+        "fn verify() {}".ai(),
+        "// by hand"
+    ]);
+    repo.stage_all_and_commit("Add auth").unwrap();
+    repo
+}
+
+#[test]
+fn test_push_refused_when_commit_breaks_policy() {
+    let repo = repo_with_ai_commit();
+    let policy = [("GIT_AI_PUSH_POLICIES", "security=no_ai:*security/*")];
+
+    let err = repo.git_with_env(&["push"], &policy).unwrap_err();
+    assert!(err.contains("refusing to push"), "{}", err);
+    assert!(err.contains("Add auth"), "{}", err);
+    assert!(
+        err.contains("security (no_ai:*security/*): src/security/auth.rs: 2 AI-authored line(s)"),
+        "{}",
+        err
+    );
+
+    // A policy the commit passes lets the push through
+    repo.git_with_env(
+        &["push"],
+        &[("GIT_AI_PUSH_POLICIES", "mostly-human=max_ai_percent:70")],
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_push_no_verify_skips_policies() {
+    let repo = repo_with_ai_commit();
+    repo.git_with_env(
+        &["push", "--no-verify"],
+        &[("GIT_AI_PUSH_POLICIES", "security=no_ai:*security/*")],
+    )
+    .unwrap();
+}
+
+#[test]
+fn test_check_reports_violations() {
+    let repo = repo_with_ai_commit();
+    let policy = [("GIT_AI_PUSH_POLICIES", "mostly-human=max_ai_percent:50")];
+
+    let err = repo.git_ai_with_env(&["check"], &policy).unwrap_err();
+    assert!(
+        err.contains("mostly-human (max_ai_percent:50): 67%"),
+        "{}",
+        err
+    );
+
+    let json = repo
+        .git_ai_with_env(&["check", "HEAD~1", "--json"], &policy)
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_str(json.trim()).unwrap();
+    assert_eq!(report["commits"], 1);
+    assert_eq!(report["violations"].as_array().unwrap().len(), 0);
+}
+
+#[test]
+fn test_check_reports_and_filters_attribution_confidence() {
+    let repo = repo_with_ai_commit();
+    let policy = [("GIT_AI_PUSH_POLICIES", "security=no_ai:*security/*")];
+
+    let err = repo.git_ai_with_env(&["check"], &policy).unwrap_err();
+    assert!(!err.contains("attribution)"), "{}", err);
+
+    // Rebasing reconstructs the attribution from diffs
+    let main = repo.current_branch();
+    repo.git(&["checkout", "-q", "-b", "side", "HEAD~1"])
+        .unwrap();
+    let mut notes = repo.filename("NOTES.md");
+    notes.set_contents(lines!["notes"]);
+    repo.stage_all_and_commit("Side").unwrap();
+    repo.git(&["checkout", "-q", &main]).unwrap();
+    repo.git(&["rebase", "side"]).unwrap();
+
+    let err = repo.git_ai_with_env(&["check"], &policy).unwrap_err();
+    assert!(
+        err.contains("src/security/auth.rs: 2 AI-authored line(s) (reconstructed attribution)"),
+        "{}",
+        err
+    );
+
+    // Lines attributed less certainly than --min-confidence don't count
+    let json = repo
+        .git_ai_with_env(
+            &["check", "HEAD", "--min-confidence", "exact", "--json"],
+            &policy,
+        )
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_str(json.trim()).unwrap();
+    assert_eq!(report["violations"].as_array().unwrap().len(), 0);
+}
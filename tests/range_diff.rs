@@ -0,0 +1,66 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+fn range_diff_json(repo: &TestRepo, old_range: &str, new_range: &str) -> serde_json::Value {
+    let output = repo
+        .git_ai(&["range-diff", "--json", old_range, new_range])
+        .unwrap();
+    serde_json::from_str(output.trim()).unwrap()
+}
+
+#[test]
+fn test_range_diff_after_rebase() {
+    let repo = TestRepo::new();
+    let mut base_file = repo.filename("base.txt");
+    base_file.set_contents(lines!["base"]);
+    let base = repo.stage_all_and_commit("Base").unwrap().commit_sha;
+    let default_branch = repo.current_branch();
+
+    repo.git(&["checkout", "-b", "feature"]).unwrap();
+    let mut parser = repo.filename("parser.rs");
+    parser.set_contents(lines!["fn parse() {}".ai(), "fn lex() {}".ai()]);
+    repo.stage_all_and_commit("AI parser").unwrap();
+    let mut notes = repo.filename("notes.txt");
+    notes.set_contents(lines!["written by hand"]);
+    repo.stage_all_and_commit("Human notes").unwrap();
+    let old_range = format!(
+        "{}..{}",
+        base,
+        repo.git(&["rev-parse", "HEAD"]).unwrap().trim()
+    );
+
+    repo.git(&["checkout", &default_branch]).unwrap();
+    let mut other = repo.filename("other.txt");
+    other.set_contents(lines!["upstream"]);
+    repo.stage_all_and_commit("Upstream work").unwrap();
+    repo.git(&["checkout", "feature"]).unwrap();
+    repo.git(&["rebase", &default_branch]).unwrap();
+
+    let new_range = format!("{}..feature", default_branch);
+    let report = range_diff_json(&repo, &old_range, &new_range);
+    let pairs = report["pairs"].as_array().unwrap();
+    assert_eq!(pairs.len(), 2);
+    assert_eq!(pairs[0]["subject"], "AI parser");
+    assert_eq!(pairs[0]["relation"], "=");
+    assert_eq!(pairs[0]["status"], "preserved");
+    assert_eq!(pairs[0]["old_ai_lines"], 2);
+    assert_eq!(pairs[0]["new_ai_lines"], 2);
+    assert_eq!(pairs[1]["status"], "no_attribution");
+    assert_eq!(report["preserved"], 1);
+    assert_eq!(report["lost"], 0);
+
+    // A rewrite that dropped the note
+    let rebased = pairs[0]["new_commit"].as_str().unwrap().to_string();
+    repo.git(&["notes", "--ref=ai", "remove", &rebased])
+        .unwrap();
+    let report = range_diff_json(&repo, &old_range, &new_range);
+    assert_eq!(report["pairs"][0]["status"], "lost");
+    assert_eq!(report["lost"], 1);
+
+    let output = repo
+        .git_ai(&["range-diff", &old_range, &new_range])
+        .unwrap();
+    assert!(output.contains("0 preserved, 0 partial, 1 lost"));
+}
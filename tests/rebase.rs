@@ -1070,3 +1070,47 @@ cat {} > "$1"
         "function feature3() {}".ai()
     ]);
 }
+
+/// Rebased attributions are reconstructed: blame marks them and --min-confidence can drop them
+#[test]
+fn test_rebase_marks_attributions_reconstructed() {
+    let repo = TestRepo::new();
+
+    let mut base_file = repo.filename("base.txt");
+    base_file.set_contents(lines!["base content"]);
+    repo.stage_all_and_commit("Initial commit").unwrap();
+
+    let default_branch = repo.current_branch();
+
+    repo.git(&["checkout", "-b", "feature"]).unwrap();
+    let mut feature = repo.filename("feature.txt");
+    feature.set_contents(lines!["// AI feature".ai(), "function feature() {}".ai()]);
+    repo.stage_all_and_commit("AI feature").unwrap();
+
+    let blame = repo.git_ai(&["blame", "feature.txt"]).unwrap();
+    assert!(blame.contains("mock_ai"));
+    assert!(!blame.contains("mock_ai~"));
+
+    repo.git(&["checkout", &default_branch]).unwrap();
+    base_file.insert_at(1, lines!["main advances"]);
+    repo.stage_all_and_commit("Main advances").unwrap();
+
+    repo.git(&["checkout", "feature"]).unwrap();
+    repo.git(&["rebase", &default_branch]).unwrap();
+
+    feature.assert_lines_and_blame(lines!["// AI feature".ai(), "function feature() {}".ai()]);
+
+    let blame = repo.git_ai(&["blame", "feature.txt"]).unwrap();
+    assert!(blame.contains("mock_ai~"), "blame output: {}", blame);
+
+    // Structured output keeps the agent name and reports the confidence on its own
+    let json = repo.git_ai(&["blame", "--json", "feature.txt"]).unwrap();
+    let lines: serde_json::Value = serde_json::from_str(json.trim()).unwrap();
+    assert_eq!(lines[0]["author"], "mock_ai");
+    assert_eq!(lines[0]["confidence"], "reconstructed");
+
+    let strict = repo
+        .git_ai(&["blame", "--min-confidence", "exact", "feature.txt"])
+        .unwrap();
+    assert!(!strict.contains("mock_ai"), "blame output: {}", strict);
+}
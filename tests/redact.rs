@@ -0,0 +1,114 @@
+mod repos;
+use repos::test_repo::TestRepo;
+
+fn message_types(repo: &TestRepo, commit: &str) -> Vec<String> {
+    let output = repo.git_ai(&["show-prompt", commit, "--json"]).unwrap();
+    let prompts: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+    prompts[0]["messages"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|message| message["type"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn test_transcript_store_redacts_at_checkpoint_time() {
+    let repo = TestRepo::new();
+    std::fs::write(repo.path().join("lib.rs"), "// base\n").unwrap();
+    repo.stage_all_and_commit("Base").unwrap();
+
+    std::fs::write(repo.path().join("lib.rs"), "// base\nfn one() {}\n").unwrap();
+    repo.claude_checkpoint("lib.rs", &[("GIT_AI_TRANSCRIPT_STORE", "messages_only")]);
+
+    // Tool calls never reach the working log
+    let checkpoints = repo.current_working_logs().read_all_checkpoints().unwrap();
+    let messages = checkpoints[0].transcript.as_ref().unwrap().messages();
+    assert!(!messages.is_empty());
+    assert!(messages.iter().all(|message| !message.is_tool_use()));
+
+    let commit = repo.stage_all_and_commit("Claude").unwrap();
+    let types = message_types(&repo, &commit.commit_sha);
+    assert!(!types.is_empty());
+    assert!(types.iter().all(|t| t != "tool_use"), "{:?}", types);
+}
+
+#[test]
+fn test_redact_strips_transcripts_but_keeps_attribution() {
+    let repo = TestRepo::new();
+    std::fs::write(repo.path().join("lib.rs"), "// base\n").unwrap();
+    repo.stage_all_and_commit("Base").unwrap();
+
+    std::fs::write(repo.path().join("lib.rs"), "// base\nfn one() {}\n").unwrap();
+    repo.claude_checkpoint("lib.rs", &[]);
+    let commit = repo.stage_all_and_commit("Claude").unwrap();
+    assert!(message_types(&repo, &commit.commit_sha).contains(&"tool_use".to_string()));
+
+    let output = repo
+        .git_ai(&["redact", "HEAD", "--store", "hashes-only"])
+        .unwrap();
+    assert!(output.contains("Redacted 1 of 1 notes"), "{}", output);
+    let shown = repo.git_ai(&["show-prompt", "HEAD", "--json"]).unwrap();
+    assert!(shown.contains("sha256:"), "{}", shown);
+
+    let output = repo.git_ai(&["redact", "--all"]).unwrap();
+    assert!(output.contains("Redacted 1 of"), "{}", output);
+    assert!(message_types(&repo, &commit.commit_sha).is_empty());
+
+    // Attribution and the prompt are untouched
+    let blame = repo
+        .git_ai(&["show-prompt", "HEAD", "lib.rs", "2"])
+        .unwrap();
+    assert!(blame.contains("agent:   claude"), "{}", blame);
+
+    let output = repo.git_ai(&["redact", "HEAD"]).unwrap();
+    assert!(output.contains("Redacted 0 of 1 notes"), "{}", output);
+}
+
+#[test]
+fn test_redact_drops_old_transcripts_from_notes_history() {
+    let repo = TestRepo::new();
+    std::fs::write(repo.path().join("lib.rs"), "// base\n").unwrap();
+    repo.stage_all_and_commit("Base").unwrap();
+
+    std::fs::write(repo.path().join("lib.rs"), "// base\nfn one() {}\n").unwrap();
+    repo.claude_checkpoint("lib.rs", &[]);
+    repo.stage_all_and_commit("Claude").unwrap();
+    let secret = "Make the colors green and yellow";
+    let history = repo.git(&["log", "-p", "refs/notes/ai"]).unwrap();
+    assert!(history.contains(secret));
+
+    // A full copy kept on this machine by keep_local_transcripts
+    let hash = repo
+        .note_for("HEAD")
+        .metadata
+        .prompts
+        .keys()
+        .next()
+        .unwrap()
+        .clone();
+    let transcripts = repo.path().join(".git").join("ai").join("transcripts");
+    std::fs::create_dir_all(&transcripts).unwrap();
+    let local = transcripts.join(format!("{}.json", hash));
+    let messages = serde_json::json!([{ "type": "user", "text": secret }]);
+    std::fs::write(&local, messages.to_string()).unwrap();
+
+    let output = repo.git_ai(&["redact", "HEAD"]).unwrap();
+    assert!(
+        output.contains("Redacted 1 local transcripts"),
+        "{}",
+        output
+    );
+    assert!(output.contains("git gc --prune=now"), "{}", output);
+
+    let history = repo.git(&["log", "-p", "refs/notes/ai"]).unwrap();
+    assert!(!history.contains(secret), "{}", history);
+    assert!(!std::fs::read_to_string(&local).unwrap().contains(secret));
+
+    // The notes themselves survive the rewrite
+    let blame = repo
+        .git_ai(&["show-prompt", "HEAD", "lib.rs", "2"])
+        .unwrap();
+    assert!(blame.contains("agent:   claude"), "{}", blame);
+    assert!(repo.git(&["notes", "--ref=ai", "show", "HEAD~1"]).is_ok());
+}
@@ -0,0 +1,60 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+fn release_note_json(repo: &TestRepo, args: &[&str]) -> serde_json::Value {
+    let mut full_args = vec!["release-note", "--json"];
+    full_args.extend_from_slice(args);
+    let output = repo.git_ai(&full_args).unwrap();
+    serde_json::from_str(output.trim()).unwrap()
+}
+
+#[test]
+fn test_release_note_rolls_up_lines_since_previous_tag() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn one() {}", "fn two() {}"]);
+    repo.stage_all_and_commit("First").unwrap();
+    repo.git(&["tag", "v1.0"]).unwrap();
+
+    let first = release_note_json(&repo, &["v1.0", "--no-store"]);
+    assert!(first.get("previous_tag").is_none());
+    assert_eq!(first["commits"], 1);
+    assert_eq!(first["human_lines"], 2);
+    assert_eq!(first["ai_percentage"], 0);
+
+    let mut agent_file = repo.filename("agent.rs");
+    agent_file.set_contents(lines![
+        "fn three() {}".ai(),
+        "fn four() {}".ai(),
+        "fn five() {}".ai(),
+    ]);
+    repo.stage_all_and_commit("Agent work").unwrap();
+    let mut other = repo.filename("other.rs");
+    other.set_contents(lines!["fn other() {}"]);
+    repo.stage_all_and_commit("Human work").unwrap();
+    repo.git(&["tag", "-a", "v1.1", "-m", "Release 1.1"]).unwrap();
+
+    let rollup = release_note_json(&repo, &["v1.1"]);
+    assert_eq!(rollup["previous_tag"], "v1.0");
+    assert_eq!(rollup["commits"], 2);
+    assert_eq!(rollup["commits_with_authorship"], 2);
+    assert_eq!(rollup["ai_lines"], 3);
+    assert_eq!(rollup["human_lines"], 1);
+    assert_eq!(rollup["ai_percentage"], 75);
+
+    // Stored on the annotated tag's own object
+    let tag_object = repo.git(&["rev-parse", "v1.1"]).unwrap();
+    assert_ne!(rollup["commit"].as_str().unwrap(), tag_object.trim());
+    let note = repo
+        .git(&["notes", "--ref=ai-releases", "show", tag_object.trim()])
+        .unwrap();
+    let stored: serde_json::Value = serde_json::from_str(&note).unwrap();
+    assert_eq!(stored, rollup);
+
+    let badge = repo.git_ai(&["release-note", "v1.1", "--badge"]).unwrap();
+    assert_eq!(badge.trim(), "Release v1.1: 75% AI-assisted");
+
+    assert!(repo.git_ai(&["release-note", "v9.9"]).is_err());
+}
@@ -0,0 +1,445 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+#[test]
+fn test_repo_config_notes_ref() {
+    let repo = TestRepo::new();
+    fs::write(repo.path().join(".gitai.toml"), "notes_ref = \"team\"\n").unwrap();
+
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn human() {}", "fn ai() {}".ai()]);
+    let commit = repo.stage_all_and_commit("AI work").unwrap();
+    assert!(!commit.authorship_log.attestations.is_empty());
+
+    let note = repo
+        .git(&["notes", "--ref=team", "show", &commit.commit_sha])
+        .unwrap();
+    assert!(note.contains("lib.rs"));
+    assert!(
+        repo.git(&["notes", "--ref=ai", "show", &commit.commit_sha])
+            .is_err()
+    );
+}
+
+#[test]
+fn test_repo_config_exclude_paths() {
+    let repo = TestRepo::new();
+    fs::write(
+        repo.path().join(".gitai.toml"),
+        "exclude_paths = [\"generated/**\"]\n",
+    )
+    .unwrap();
+    fs::create_dir_all(repo.path().join("generated")).unwrap();
+
+    let mut generated = repo.filename("generated/schema.rs");
+    generated.set_contents(lines!["struct Schema;".ai()]);
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn ai() {}".ai()]);
+    let commit = repo.stage_all_and_commit("AI work").unwrap();
+
+    let files: Vec<&str> = commit
+        .authorship_log
+        .attestations
+        .iter()
+        .map(|attestation| attestation.file_path.as_str())
+        .collect();
+    assert_eq!(files, vec!["lib.rs"]);
+}
+
+#[test]
+fn test_repo_config_directory_policies() {
+    let repo = TestRepo::new();
+    fs::write(
+        repo.path().join(".gitai.toml"),
+        "[directory_policies]\ngen = \"ignore\"\nproto = \"tool:protoc\"\n",
+    )
+    .unwrap();
+    fs::create_dir_all(repo.path().join("gen")).unwrap();
+    fs::create_dir_all(repo.path().join("proto")).unwrap();
+
+    let mut generated = repo.filename("gen/client.ts");
+    generated.set_contents(lines!["export class Client {}".ai()]);
+    let mut proto = repo.filename("proto/user.ts");
+    proto.set_contents(lines![
+        "export interface User {}",
+        "export interface Group {}"
+    ]);
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn ai() {}".ai(), "fn human() {}"]);
+    let commit = repo.stage_all_and_commit("Generated code").unwrap();
+
+    let mut files: Vec<&str> = commit
+        .authorship_log
+        .attestations
+        .iter()
+        .map(|attestation| attestation.file_path.as_str())
+        .collect();
+    files.sort();
+    assert_eq!(files, vec!["lib.rs", "proto/user.ts"]);
+    let tools: Vec<&str> = commit
+        .authorship_log
+        .metadata
+        .prompts
+        .values()
+        .map(|prompt| prompt.agent_id.tool.as_str())
+        .collect();
+    assert!(tools.contains(&"protoc"), "{:?}", tools);
+    let blame = repo.git_ai(&["blame", "proto/user.ts"]).unwrap();
+    assert!(
+        blame.lines().all(|line| line.contains("protoc")),
+        "{}",
+        blame
+    );
+
+    let output = repo
+        .git_ai(&["ownership", "--directories", "--json"])
+        .unwrap();
+    let report: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+    let directories = report["directories"].as_array().unwrap();
+    let totals = |directory: &str| {
+        directories
+            .iter()
+            .find(|totals| totals["directory"] == directory)
+            .unwrap()
+    };
+    assert_eq!(totals("gen")["policy"], "ignore");
+    assert_eq!(totals("gen")["ai_lines"], 0);
+    assert_eq!(totals("proto")["policy"], "tool:protoc");
+    assert_eq!(totals("proto")["total_lines"], 2);
+    assert_eq!(totals("proto")["ai_lines"], 2);
+    assert_eq!(totals("")["ai_lines"], 1);
+}
+
+#[test]
+fn test_gitaiignore_skips_files_in_checkpoints_commits_and_stats() {
+    let repo = TestRepo::new();
+    fs::write(
+        repo.path().join(".gitaiignore"),
+        "# Build output\nbuild/\n*.lock\n",
+    )
+    .unwrap();
+    fs::create_dir_all(repo.path().join("web/build")).unwrap();
+
+    let mut built = repo.filename("web/build/app.js");
+    built.set_contents(lines!["bundle();".ai(), "more();".ai()]);
+    let mut lock = repo.filename("deps.lock");
+    lock.set_contents(lines!["dep 1.0".ai()]);
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn ai() {}".ai(), "fn human() {}"]);
+    let commit = repo.stage_all_and_commit("Build").unwrap();
+
+    let files: Vec<&str> = commit
+        .authorship_log
+        .attestations
+        .iter()
+        .map(|attestation| attestation.file_path.as_str())
+        .collect();
+    assert_eq!(files, vec!["lib.rs"]);
+
+    // The JSON is on stdout, which comes before the debug lines stats writes to stderr
+    let output = repo.git_ai(&["stats", "--json"]).unwrap();
+    let stats: serde_json::Value = serde_json::from_str(output.lines().next().unwrap()).unwrap();
+    assert_eq!(stats["ai_additions"], 1);
+    // The ignored files' lines are counted apart
+    assert_eq!(stats["generated_added_lines"], 3);
+
+    // Lines checkpointed before their file was ignored are dropped at commit
+    let mut late = repo.filename("late.js");
+    late.set_contents(lines!["late();".ai()]);
+    fs::write(
+        repo.path().join(".gitaiignore"),
+        "build/\n*.lock\nlate.js\n",
+    )
+    .unwrap();
+    let commit = repo.stage_all_and_commit("Late").unwrap();
+    assert!(commit.authorship_log.attestations.is_empty());
+    assert!(commit.authorship_log.metadata.prompts.is_empty());
+}
+
+#[test]
+fn test_large_files_are_skipped_and_recorded_in_working_log() {
+    let repo = TestRepo::new();
+    fs::write(
+        repo.path().join(".gitai.toml"),
+        "large_file_threshold_kb = 1\n",
+    )
+    .unwrap();
+    repo.stage_all_and_commit("Config").unwrap();
+
+    fs::write(repo.path().join("big.txt"), "x\n".repeat(1024)).unwrap();
+    fs::write(repo.path().join("image.bin"), b"\x89PNG\0\0data").unwrap();
+    fs::write(repo.path().join("small.txt"), "small\n").unwrap();
+    let output = repo.git_ai(&["checkpoint", "mock_ai"]).unwrap();
+    assert!(output.contains("Skipped big.txt (2 KiB, over large_file_threshold_kb)"));
+
+    let head = repo.git(&["rev-parse", "HEAD"]).unwrap();
+    let log = fs::read_to_string(
+        repo.path()
+            .join(".git/ai/working_logs")
+            .join(head.trim())
+            .join("checkpoints.jsonl"),
+    )
+    .unwrap();
+    let checkpoint: serde_json::Value = serde_json::from_str(log.lines().last().unwrap()).unwrap();
+    assert_eq!(
+        checkpoint["skipped_files"],
+        serde_json::json!([
+            {"file": "big.txt", "reason": "large", "size": 2048},
+            {"file": "image.bin", "reason": "binary", "size": 10},
+        ])
+    );
+
+    // The flag's text as another option's value doesn't lift the threshold
+    let output = repo
+        .git_ai(&["checkpoint", "mock_ai", "--session", "--include-large"])
+        .unwrap();
+    assert!(output.contains("Skipped big.txt"), "{}", output);
+
+    // --include-large checks the big file anyway; binaries stay out
+    repo.git_ai(&["checkpoint", "mock_ai", "--include-large"])
+        .unwrap();
+    let commit = repo.stage_all_and_commit("Files").unwrap();
+    let mut files: Vec<&str> = commit
+        .authorship_log
+        .attestations
+        .iter()
+        .map(|attestation| attestation.file_path.as_str())
+        .collect();
+    files.sort();
+    assert_eq!(files, vec!["big.txt", "small.txt"]);
+}
+
+#[test]
+fn test_no_cache_rereads_files_with_unchanged_mtime() {
+    let repo = TestRepo::new();
+    fs::write(repo.path().join("README.md"), "readme\n").unwrap();
+    repo.stage_all_and_commit("Initial").unwrap();
+    let head = repo.git(&["rev-parse", "HEAD"]).unwrap();
+    let checkpoints = || {
+        fs::read_to_string(
+            repo.path()
+                .join(".git/ai/working_logs")
+                .join(head.trim())
+                .join("checkpoints.jsonl"),
+        )
+        .unwrap()
+        .lines()
+        .count()
+    };
+
+    let path = repo.path().join("lib.rs");
+    fs::write(&path, "one\n").unwrap();
+    let mtime = fs::metadata(&path).unwrap().modified().unwrap();
+    repo.git_ai(&["checkpoint", "mock_ai"]).unwrap();
+    assert_eq!(checkpoints(), 1);
+
+    // Same size and mtime: the cached hash is trusted and the edit goes unseen
+    fs::write(&path, "two\n").unwrap();
+    fs::File::options()
+        .write(true)
+        .open(&path)
+        .unwrap()
+        .set_modified(mtime)
+        .unwrap();
+    repo.git_ai(&["checkpoint", "mock_ai"]).unwrap();
+    assert_eq!(checkpoints(), 1);
+
+    repo.git_ai(&["checkpoint", "mock_ai", "--no-cache"])
+        .unwrap();
+    assert_eq!(checkpoints(), 2);
+}
+
+#[test]
+fn test_checkpoint_entries_keep_order_whatever_the_concurrency() {
+    let entries_with = |concurrency: &str| {
+        let repo = TestRepo::new();
+        fs::write(repo.path().join("README.md"), "readme\n").unwrap();
+        repo.stage_all_and_commit("Initial").unwrap();
+        let env = [("GIT_AI_CHECKPOINT_CONCURRENCY", concurrency)];
+
+        let names = ["e.rs", "a.rs", "d.rs", "b.rs", "c.rs"];
+        for name in names {
+            fs::write(
+                repo.path().join(name),
+                format!("fn {}() {{}}\n", &name[..1]),
+            )
+            .unwrap();
+        }
+        repo.git_ai_with_env(&["checkpoint", "mock_ai"], &env)
+            .unwrap();
+        for name in names {
+            let path = repo.path().join(name);
+            let content = fs::read_to_string(&path).unwrap();
+            fs::write(&path, format!("// {}\n{}", name, content)).unwrap();
+        }
+        repo.git_ai_with_env(&["checkpoint"], &env).unwrap();
+
+        let head = repo.git(&["rev-parse", "HEAD"]).unwrap();
+        let log = fs::read_to_string(
+            repo.path()
+                .join(".git/ai/working_logs")
+                .join(head.trim())
+                .join("checkpoints.jsonl"),
+        )
+        .unwrap();
+        let checkpoint: serde_json::Value =
+            serde_json::from_str(log.lines().last().unwrap()).unwrap();
+        checkpoint["entries"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|entry| {
+                // mock_ai session ids differ between the repos; the lines don't
+                let lines: Vec<_> = entry["line_attributions"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .map(|line| (line["start_line"].clone(), line["end_line"].clone()))
+                    .collect();
+                (entry["file"].clone(), lines)
+            })
+            .collect::<Vec<_>>()
+    };
+
+    let serial = entries_with("1");
+    let files: Vec<&str> = serial
+        .iter()
+        .map(|(file, _)| file.as_str().unwrap())
+        .collect();
+    assert_eq!(files, vec!["a.rs", "b.rs", "c.rs", "d.rs", "e.rs"]);
+    assert_eq!(entries_with("8"), serial);
+}
+
+#[test]
+fn test_config_show_origin() {
+    let repo = TestRepo::new();
+    fs::write(
+        repo.path().join(".gitai.toml"),
+        "notes_ref = \"team\"\ntranscript_mode = \"summary\"\n",
+    )
+    .unwrap();
+
+    let output = repo.git_ai(&["config", "--show-origin"]).unwrap();
+    let line = |key: &str| {
+        output
+            .lines()
+            .find(|line| line.contains(&format!("{} = ", key)))
+            .unwrap()
+            .to_string()
+    };
+    assert!(line("notes_ref").ends_with(".gitai.toml\tnotes_ref = \"team\""));
+    assert_eq!(line("ignore_prompts"), "default\tignore_prompts = false");
+
+    // The command line beats the repo file
+    let output = repo
+        .git_ai(&[
+            "-c",
+            "notes_ref=mine",
+            "config",
+            "--show-origin",
+            "notes_ref",
+        ])
+        .unwrap();
+    assert_eq!(output.trim(), "command line\tnotes_ref = \"mine\"");
+
+    // A broken repo file is reported instead of silently ignored
+    fs::write(repo.path().join(".gitai.toml"), "notes_ref = \n").unwrap();
+    assert!(repo.git_ai(&["config"]).is_err());
+}
+
+#[test]
+fn test_config_set_and_unset_per_level() {
+    let repo = TestRepo::new();
+    let dir = tempfile::tempdir().unwrap();
+    let system = dir.path().join("etc").join("config.toml");
+    let config_home = dir.path().join("config");
+    let envs = [
+        ("GIT_AI_SYSTEM_CONFIG", system.to_str().unwrap()),
+        ("XDG_CONFIG_HOME", config_home.to_str().unwrap()),
+    ];
+    let config = |args: &[&str]| {
+        let mut full = vec!["config"];
+        full.extend_from_slice(args);
+        repo.git_ai_with_env(&full, &envs)
+    };
+
+    config(&["--system", "notes_ref", "org"]).unwrap();
+    config(&["--system", "confirm_clean", "true"]).unwrap();
+    config(&["--global", "notes_ref", "mine"]).unwrap();
+    let output = config(&["--show-origin", "notes_ref"]).unwrap();
+    assert!(
+        output
+            .trim()
+            .ends_with("git-ai/config.toml\tnotes_ref = \"mine\""),
+        "{}",
+        output
+    );
+    let output = config(&["--show-origin", "confirm_clean"]).unwrap();
+    assert!(
+        output.trim().starts_with(system.to_str().unwrap()),
+        "{}",
+        output
+    );
+
+    // The repo level is the default for writes, and beats the user and system files
+    config(&["notes_ref", "team"]).unwrap();
+    assert_eq!(
+        fs::read_to_string(repo.path().join(".gitai.toml")).unwrap(),
+        "notes_ref = \"team\"\n"
+    );
+    assert_eq!(
+        config(&["notes_ref"]).unwrap().trim(),
+        "notes_ref = \"team\""
+    );
+    assert_eq!(
+        config(&["--system", "notes_ref"]).unwrap().trim(),
+        "notes_ref = \"org\""
+    );
+
+    config(&["--repo", "--unset", "notes_ref"]).unwrap();
+    config(&["--global", "--unset", "notes_ref"]).unwrap();
+    assert_eq!(
+        config(&["notes_ref"]).unwrap().trim(),
+        "notes_ref = \"org\""
+    );
+    assert!(config(&["--global", "notes_ref"]).is_err());
+    assert!(config(&["--global", "--unset", "notes_ref"]).is_err());
+
+    // Values are checked before they're written
+    let err = config(&["--global", "transcript_mode", "verbose"]).unwrap_err();
+    assert!(err.contains("Invalid value for transcript_mode"), "{}", err);
+    assert!(config(&["--global", "git_path", "/usr/bin/git"]).is_err());
+}
+
+#[test]
+fn test_repo_config_record_environment() {
+    let repo = TestRepo::new();
+    fs::write(
+        repo.path().join(".gitai.toml"),
+        "record_environment = true\n",
+    )
+    .unwrap();
+
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn ai() {}".ai()]);
+    file.insert_at(1, lines!["fn more_ai() {}".ai()]);
+    let commit = repo.stage_all_and_commit("AI work").unwrap();
+
+    let prompt = commit
+        .authorship_log
+        .metadata
+        .prompts
+        .values()
+        .next()
+        .unwrap();
+    // Both checkpoints ran on this machine, so they share one entry
+    assert_eq!(prompt.environments.len(), 1);
+    assert_eq!(
+        prompt.environments[0].git_ai_version,
+        env!("CARGO_PKG_VERSION")
+    );
+    assert_eq!(prompt.environments[0].host.len(), 16);
+}
@@ -1,19 +1,23 @@
 use git_ai::authorship::authorship_log_serialization::AuthorshipLog;
+use git_ai::git::discovery_cache::DISCOVERY_CACHE_ENV;
+use git_ai::git::repo_registry::REPO_REGISTRY_ENV;
 use git_ai::git::repo_storage::PersistedWorkingLog;
 use git_ai::git::repository as GitAiRepository;
 use git2::Repository;
 use insta::assert_debug_snapshot;
 use rand::Rng;
 use std::fs;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
 use std::sync::OnceLock;
 
 use super::test_file::TestFile;
 
 #[derive(Clone, Debug)]
 pub struct TestRepo {
-    path: PathBuf,
+    pub path: PathBuf,
+    /// The main worktree's path; differs from `path` in a linked worktree
+    pub main_path: PathBuf,
 }
 
 impl TestRepo {
@@ -31,13 +35,29 @@ impl TestRepo {
             .set_str("user.email", "test@example.com")
             .expect("failed to initialize git2 repository");
 
-        Self { path }
+        Self {
+            main_path: path.clone(),
+            path,
+        }
     }
 
     pub fn path(&self) -> &PathBuf {
         &self.path
     }
 
+    /// Each test repo gets its own registry so tests never touch ~/.git-ai/repos.json
+    pub fn repo_registry_path(&self) -> PathBuf {
+        self.main_path.join(".git").join("ai").join("repos.json")
+    }
+
+    /// Likewise for the repository discovery cache in ~/.git-ai/discovery.json
+    pub fn discovery_cache_path(&self) -> PathBuf {
+        self.main_path
+            .join(".git")
+            .join("ai")
+            .join("discovery.json")
+    }
+
     pub fn current_branch(&self) -> String {
         self.git(&["branch", "--show-current"])
             .unwrap()
@@ -46,11 +66,28 @@ impl TestRepo {
     }
 
     pub fn git_ai(&self, args: &[&str]) -> Result<String, String> {
-        let binary_path = get_binary_path();
+        self.git_ai_with_env(args, &[])
+    }
 
-        let output = Command::new(binary_path)
+    /// A git-ai command in this repo, for tests that need to run it in the background
+    pub fn git_ai_command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(get_binary_path());
+        command
             .args(args)
             .current_dir(&self.path)
+            .env(REPO_REGISTRY_ENV, self.repo_registry_path())
+            .env(DISCOVERY_CACHE_ENV, self.discovery_cache_path());
+        command
+    }
+
+    pub fn git_ai_with_env(&self, args: &[&str], envs: &[(&str, &str)]) -> Result<String, String> {
+        let mut command = self.git_ai_command(args);
+
+        for (key, value) in envs {
+            command.env(key, value);
+        }
+
+        let output = command
             .output()
             .expect(&format!("Failed to execute git-ai command: {:?}", args));
 
@@ -81,6 +118,8 @@ impl TestRepo {
         let output = Command::new(binary_path)
             .args(&full_args)
             .env("GIT_AI", "git")
+            .env(REPO_REGISTRY_ENV, self.repo_registry_path())
+            .env(DISCOVERY_CACHE_ENV, self.discovery_cache_path())
             .output()
             .expect(&format!("Failed to execute git command: {:?}", args));
 
@@ -109,7 +148,11 @@ impl TestRepo {
         full_args.extend(args);
 
         let mut command = Command::new(binary_path);
-        command.args(&full_args).env("GIT_AI", "git");
+        command
+            .args(&full_args)
+            .env("GIT_AI", "git")
+            .env(REPO_REGISTRY_ENV, self.repo_registry_path())
+            .env(DISCOVERY_CACHE_ENV, self.discovery_cache_path());
 
         // Add custom environment variables
         for (key, value) in envs {
@@ -211,6 +254,51 @@ impl TestRepo {
         let file_path = self.path.join(filename);
         fs::read_to_string(&file_path).ok()
     }
+
+    /// The authorship note of `rev`, parsed
+    pub fn note_for(&self, rev: &str) -> AuthorshipLog {
+        let note = self.git(&["notes", "--ref=ai", "show", rev]).unwrap();
+        AuthorshipLog::deserialize_from_string(&note).unwrap()
+    }
+
+    /// Checkpoint `file_path` as edited by the Claude Code session in the example transcript
+    pub fn claude_checkpoint(&self, file_path: &str, envs: &[(&str, &str)]) {
+        let transcript = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/example-claude-code.jsonl"
+        );
+        let hook_input = serde_json::json!({
+            "cwd": self.path.to_str().unwrap(),
+            "hook_event_name": "PostToolUse",
+            "transcript_path": transcript,
+            "tool_input": { "file_path": self.path.join(file_path).to_str().unwrap() },
+        })
+        .to_string();
+        self.git_ai_with_env(&["checkpoint", "claude", "--hook-input", &hook_input], envs)
+            .unwrap();
+    }
+}
+
+/// Real git, not git-ai: no hooks run around it, like on the server or another developer's clone
+pub fn plain_git_output(dir: &Path, args: &[&str]) -> Output {
+    Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .env_remove("GIT_AI")
+        .output()
+        .unwrap()
+}
+
+/// [`plain_git_output`] that has to succeed, returning its trimmed stdout
+pub fn plain_git(dir: &Path, args: &[&str]) -> String {
+    let output = plain_git_output(dir, args);
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
 }
 
 impl Drop for TestRepo {
@@ -238,8 +326,15 @@ fn compile_binary() -> PathBuf {
     println!("Compiling git-ai binary for tests...");
 
     let manifest_dir = env!("CARGO_MANIFEST_DIR");
+    // The stress suite needs the hooks it drives to be able to fail
+    let features: &[&str] = if cfg!(feature = "fault-injection") {
+        &["--features", "fault-injection"]
+    } else {
+        &[]
+    };
     let output = Command::new("cargo")
         .args(&["build", "--bin", "git-ai"])
+        .args(features)
         .current_dir(manifest_dir)
         .output()
         .expect("Failed to compile git-ai binary");
@@ -35,6 +35,34 @@ fn test_reset_hard_deletes_working_log() {
     file.assert_lines_and_blame(lines!["line 1", "line 2", "line 3", "new line",]);
 }
 
+/// Test git reset --hard with uncommitted AI lines: warns that their attribution is gone
+#[test]
+fn test_reset_hard_warns_about_dropped_ai_lines() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+    file.set_contents(lines!["line 1", "line 2"]);
+    repo.stage_all_and_commit("First commit").unwrap();
+
+    file.insert_at(2, lines!["// AI 1".ai(), "// AI 2".ai()]);
+    let output = repo.git(&["reset", "--hard", "HEAD"]).unwrap();
+    assert!(
+        output.contains("git-ai: lost the attribution of 2 AI lines in 1 file (reset --hard:"),
+        "{}",
+        output
+    );
+
+    let outcome: serde_json::Value =
+        serde_json::from_str(repo.git_ai(&["last-outcome"]).unwrap().trim()).unwrap();
+    let losses = outcome["attribution_losses"].as_array().unwrap();
+    assert_eq!(losses.len(), 1);
+    assert_eq!(losses[0]["operation"], "reset --hard");
+    assert_eq!(losses[0]["ai_lines"], 2);
+
+    // Nothing of AI's left to lose
+    let output = repo.git(&["reset", "--hard", "HEAD"]).unwrap();
+    assert!(!output.contains("lost the attribution"), "{}", output);
+}
+
 /// Test git reset --soft: should preserve AI authorship from unwound commits
 #[test]
 fn test_reset_soft_reconstructs_working_log() {
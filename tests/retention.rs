@@ -0,0 +1,68 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+fn retention_json(repo: &TestRepo, args: &[&str]) -> serde_json::Value {
+    let mut full_args = vec!["retention", "--json"];
+    full_args.extend_from_slice(args);
+    let output = repo.git_ai(&full_args).unwrap();
+    serde_json::from_str(output.trim()).unwrap()
+}
+
+#[test]
+fn test_retention_counts_ai_lines_surviving_at_tip() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+
+    file.set_contents(lines!["// base"]);
+    let base = repo.stage_all_and_commit("Base").unwrap().commit_sha;
+
+    file.set_contents(lines![
+        "// base",
+        "fn one() {}".ai(),
+        "fn two() {}".ai(),
+        "fn three() {}".ai(),
+        "fn four() {}".ai(),
+    ]);
+    let ai_commit = repo
+        .stage_all_and_commit("AI functions")
+        .unwrap()
+        .commit_sha;
+
+    // A human rewrites half of what the AI wrote
+    file.set_contents(lines![
+        "// base",
+        "fn one() {}".ai(),
+        "fn two() {}".ai(),
+        "fn three() { todo!() }",
+        "fn four() { todo!() }",
+    ]);
+    repo.stage_all_and_commit("Human rewrite").unwrap();
+
+    let range = format!("{}..HEAD", base);
+    let report = retention_json(&repo, &["--range", &range]);
+    assert_eq!(report["generated"], 4);
+    assert_eq!(report["retained"], 2);
+    assert_eq!(report["retention_rate"], 0.5);
+
+    let commits = report["commits"].as_array().unwrap();
+    assert_eq!(commits.len(), 1);
+    assert_eq!(commits[0]["commit"], ai_commit.as_str());
+    assert_eq!(commits[0]["by_tool"]["mock_ai"]["retained"], 2);
+
+    // Measured right at the commit that introduced them, every line is still there
+    let report = retention_json(&repo, &["--range", &range, "--horizon", "0"]);
+    assert_eq!(report["generated"], 4);
+    assert_eq!(report["retained"], 4);
+}
+
+#[test]
+fn test_retention_requires_range() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn main() {}".ai()]);
+    repo.stage_all_and_commit("AI").unwrap();
+
+    assert!(repo.git_ai(&["retention"]).is_err());
+}
@@ -0,0 +1,98 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+fn head_note(repo: &TestRepo) -> String {
+    repo.git(&["notes", "--ref=ai", "show", "HEAD"])
+        .expect("revert commit should have an authorship note")
+}
+
+#[test]
+fn test_revert_of_revert_recovers_ai_attribution() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn base() {}"]);
+    repo.stage_all_and_commit("Base").unwrap();
+    file.set_contents(lines![
+        "fn base() {}",
+        "fn ai() {}".ai(),
+        "fn ai2() {}".ai()
+    ]);
+    let ai_commit = repo.stage_all_and_commit("AI").unwrap();
+
+    // Reverting removes the AI lines; the note attests nothing
+    repo.git(&["revert", "--no-edit", "HEAD"]).unwrap();
+    assert!(!head_note(&repo).contains("lib.rs"));
+
+    // Reverting the revert brings them back as the agent's
+    repo.git(&["revert", "--no-edit", "HEAD"]).unwrap();
+    let note = head_note(&repo);
+    let prompt_id = ai_commit
+        .authorship_log
+        .metadata
+        .prompts
+        .keys()
+        .next()
+        .unwrap()
+        .clone();
+    assert!(note.contains(&prompt_id));
+    file.assert_lines_and_blame(lines![
+        "fn base() {}".human(),
+        "fn ai() {}".ai(),
+        "fn ai2() {}".ai(),
+    ]);
+
+    let rewrite_log = std::fs::read_to_string(repo.path().join(".git/ai/rewrite_log")).unwrap();
+    assert!(rewrite_log.lines().next().unwrap().contains("revert_mixed"));
+}
+
+#[test]
+fn test_revert_restores_ai_lines_an_edit_removed() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn base() {}", "fn ai() {}".ai(), "fn end() {}"]);
+    repo.stage_all_and_commit("AI").unwrap();
+    file.set_contents(lines!["fn base() {}", "fn end() {}"]);
+    repo.stage_all_and_commit("Drop ai").unwrap();
+
+    repo.git(&["revert", "--no-edit", "HEAD"]).unwrap();
+    file.assert_lines_and_blame(lines![
+        "fn base() {}".human(),
+        "fn ai() {}".ai(),
+        "fn end() {}".human(),
+    ]);
+}
+
+#[test]
+fn test_conflicted_revert_is_logged_and_concluded_by_commit() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn base() {}", "fn ai() {}".ai()]);
+    repo.stage_all_and_commit("AI").unwrap();
+    file.set_contents(lines!["fn base() {}"]);
+    repo.stage_all_and_commit("Drop ai").unwrap();
+    file.set_contents(lines!["fn base() {}", "fn other() {}"]);
+    repo.stage_all_and_commit("Other").unwrap();
+
+    assert!(repo.git(&["revert", "--no-edit", "HEAD~1"]).is_err());
+    let rewrite_log = std::fs::read_to_string(repo.path().join(".git/ai/rewrite_log")).unwrap();
+    let last_event = rewrite_log.lines().next().unwrap();
+    assert!(last_event.contains("revert_mixed"));
+    assert!(last_event.contains("\"success\":false"));
+
+    // Keep both sides
+    std::fs::write(
+        repo.path().join("lib.rs"),
+        "fn base() {}\nfn ai() {}\nfn other() {}\n",
+    )
+    .unwrap();
+    repo.git(&["add", "lib.rs"]).unwrap();
+    repo.git(&["commit", "--no-edit"]).unwrap();
+
+    file.assert_lines_and_blame(lines![
+        "fn base() {}".human(),
+        "fn ai() {}".ai(),
+        "fn other() {}".human(),
+    ]);
+}
@@ -0,0 +1,70 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+fn attested_files(commit: &repos::test_repo::NewCommit) -> Vec<String> {
+    let mut files: Vec<String> = commit
+        .authorship_log
+        .attestations
+        .iter()
+        .map(|file| file.file_path.clone())
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_scaffold_attributes_untracked_files_before_first_commit() {
+    let repo = TestRepo::new();
+    fs::create_dir_all(repo.path().join("src")).unwrap();
+    fs::write(
+        repo.path().join("src/main.rs"),
+        "fn main() {\n    println!(\"Hello, world!\");\n}\n",
+    )
+    .unwrap();
+    fs::write(
+        repo.path().join("Cargo.toml"),
+        "[package]\nname = \"app\"\n",
+    )
+    .unwrap();
+    fs::write(repo.path().join(".gitignore"), "/target\n").unwrap();
+    fs::create_dir_all(repo.path().join("target")).unwrap();
+    fs::write(repo.path().join("target/build.log"), "ignored\n").unwrap();
+
+    repo.git_ai(&["checkpoint", "scaffold", "--tool", "cargo-new"])
+        .unwrap();
+    let commit = repo.stage_all_and_commit("cargo new").unwrap();
+
+    assert_eq!(
+        attested_files(&commit),
+        vec![".gitignore", "Cargo.toml", "src/main.rs"]
+    );
+    let prompts: Vec<_> = commit.authorship_log.metadata.prompts.values().collect();
+    assert_eq!(prompts.len(), 1);
+    assert_eq!(prompts[0].agent_id.tool, "cargo-new");
+    assert_eq!(prompts[0].total_additions, 6);
+}
+
+#[test]
+fn test_scaffold_leaves_tracked_changes_to_their_authors() {
+    let repo = TestRepo::new();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# App"]);
+    repo.stage_all_and_commit("Initial").unwrap();
+
+    readme.insert_at(1, lines!["Edited by hand"]);
+    fs::write(repo.path().join("index.js"), "console.log('hi');\n").unwrap();
+    repo.git_ai(&["checkpoint", "scaffold", "--tool", "npm-create"])
+        .unwrap();
+    let commit = repo.stage_all_and_commit("Scaffold web app").unwrap();
+
+    assert_eq!(attested_files(&commit), vec!["index.js"]);
+    readme.assert_lines_and_blame(lines!["# App".human(), "Edited by hand".human()]);
+
+    // Nothing left to claim, and a tool name is always required
+    repo.git_ai(&["checkpoint", "scaffold", "--tool", "npm-create"])
+        .unwrap();
+    assert!(repo.git_ai(&["checkpoint", "scaffold"]).is_err());
+}
@@ -0,0 +1,85 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+fn sessions_json(repo: &TestRepo, args: &[&str]) -> serde_json::Value {
+    let mut full_args = vec!["sessions", "--json"];
+    full_args.extend_from_slice(args);
+    let output = repo.git_ai(&full_args).unwrap();
+    serde_json::from_str(output.trim()).unwrap()
+}
+
+#[test]
+fn test_sessions_group_commits_by_agent_session() {
+    let repo = TestRepo::new();
+    std::fs::write(repo.path().join("lib.rs"), "// base\n").unwrap();
+    let base = repo.stage_all_and_commit("Base").unwrap().commit_sha;
+
+    // One Claude session spans two commits
+    std::fs::write(
+        repo.path().join("lib.rs"),
+        "// base\nfn one() {}\nfn two() {}\n",
+    )
+    .unwrap();
+    repo.claude_checkpoint("lib.rs", &[]);
+    let first = repo.stage_all_and_commit("Claude 1").unwrap().commit_sha;
+
+    std::fs::write(repo.path().join("util.rs"), "fn three() {}\n").unwrap();
+    repo.claude_checkpoint("util.rs", &[]);
+    let second = repo.stage_all_and_commit("Claude 2").unwrap().commit_sha;
+
+    // A human rewrites one of the session's lines
+    std::fs::write(
+        repo.path().join("lib.rs"),
+        "// base\nfn one() { todo!() }\nfn two() {}\n",
+    )
+    .unwrap();
+    repo.git_ai(&["checkpoint"]).unwrap();
+    repo.stage_all_and_commit("Human rewrite").unwrap();
+
+    let range = format!("{}..HEAD", base);
+    let report = sessions_json(&repo, &["--range", &range]);
+    let sessions = report["sessions"].as_array().unwrap();
+    assert_eq!(sessions.len(), 1);
+
+    let session = &sessions[0];
+    assert_eq!(session["tool"], "claude");
+    assert_eq!(session["commits"], serde_json::json!([first, second]));
+    assert_eq!(session["lines_produced"], 3);
+    assert_eq!(session["lines_retained"], 2);
+    assert!(session["started_at"].as_i64().unwrap() <= session["ended_at"].as_i64().unwrap());
+}
+
+#[test]
+fn test_sessions_keep_separate_sessions_apart() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+
+    file.set_contents(lines!["// base"]);
+    let base = repo.stage_all_and_commit("Base").unwrap().commit_sha;
+
+    let mut one = repo.filename("one.rs");
+    one.set_contents(lines!["fn one() {}".ai()]);
+    repo.stage_all_and_commit("AI 1").unwrap();
+    let mut two = repo.filename("two.rs");
+    two.set_contents(lines!["fn two() {}".ai()]);
+    repo.stage_all_and_commit("AI 2").unwrap();
+
+    let range = format!("{}..HEAD", base);
+    let report = sessions_json(&repo, &["--range", &range]);
+    let sessions = report["sessions"].as_array().unwrap();
+    // Every mock_ai checkpoint starts a new session
+    assert_eq!(sessions.len(), 2);
+    for session in sessions {
+        assert_eq!(session["commits"].as_array().unwrap().len(), 1);
+        assert_eq!(session["lines_produced"], 1);
+        assert_eq!(session["lines_retained"], 1);
+    }
+}
+
+#[test]
+fn test_sessions_requires_range() {
+    let repo = TestRepo::new();
+    assert!(repo.git_ai(&["sessions"]).is_err());
+}
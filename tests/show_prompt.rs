@@ -0,0 +1,42 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+#[test]
+fn test_show_prompt_for_line_and_commit() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.txt");
+
+    file.set_contents(lines!["Human line", "AI line".ai()]);
+    let first = repo.stage_all_and_commit("Initial commit").unwrap();
+
+    // A later commit that doesn't touch the AI line still resolves its prompt
+    let mut other = repo.filename("other.txt");
+    other.set_contents(lines!["Unrelated"]);
+    let second = repo.stage_all_and_commit("Second commit").unwrap();
+
+    let shown = repo
+        .git_ai(&["show-prompt", &second.commit_sha, "test.txt", "2"])
+        .unwrap();
+    assert!(shown.contains("agent:   mock_ai"), "{}", shown);
+
+    let json = repo
+        .git_ai(&["show-prompt", &first.commit_sha, "--json"])
+        .unwrap();
+    let prompts: serde_json::Value = serde_json::from_str(json.trim()).unwrap();
+    let prompts = prompts.as_array().unwrap();
+    assert_eq!(prompts.len(), 1);
+    assert_eq!(prompts[0]["agent_id"]["tool"], "mock_ai");
+    assert!(
+        shown.contains(prompts[0]["hash"].as_str().unwrap()),
+        "{}",
+        shown
+    );
+
+    assert!(
+        repo.git_ai(&["show-prompt", &first.commit_sha, "test.txt", "1"])
+            .is_err()
+    );
+    assert!(repo.git_ai(&["show-prompt", &second.commit_sha]).is_err());
+}
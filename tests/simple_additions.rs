@@ -245,6 +245,42 @@ fn test_ai_adds_lines_multiple_commits() {
     ]);
 }
 
+#[test]
+fn test_unstaged_ai_lines_keep_their_blobs_across_commits() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("partial.ts");
+    file.set_contents(lines!["line1", "line2"]);
+    repo.stage_all_and_commit("Initial commit").unwrap();
+
+    file.replace_at(1, "ai_staged".ai());
+    file.stage();
+    file.insert_at(2, lines!["ai_unstaged1".ai(), "ai_unstaged2".ai()]);
+    repo.commit("Partial staging").unwrap();
+
+    // The unstaged lines carry over to the new commit's working log, blobs included
+    let working_log = repo.current_working_logs();
+    let checkpoints = working_log.read_all_checkpoints().unwrap();
+    assert!(!checkpoints.is_empty());
+    for entry in checkpoints
+        .iter()
+        .flat_map(|checkpoint| &checkpoint.entries)
+    {
+        assert!(
+            working_log.has_file_version(&entry.blob_sha),
+            "{}",
+            entry.file
+        );
+    }
+
+    repo.stage_all_and_commit("The rest").unwrap();
+    file.assert_lines_and_blame(lines![
+        "line1".human(),
+        "ai_staged".ai(),
+        "ai_unstaged1".ai(),
+        "ai_unstaged2".ai(),
+    ]);
+}
+
 #[test]
 fn test_partial_staging_filters_unstaged_lines() {
     // Test where AI makes changes but only some are staged
@@ -455,6 +491,42 @@ fn test_unstaged_changes_not_committed() {
     ]);
 }
 
+#[test]
+fn test_unstaged_deletion_above_staged_ai_lines() {
+    // A human deletes a line above staged AI lines without staging the deletion. The
+    // committed AI lines sit one line lower than in the working directory.
+    let repo = TestRepo::new();
+    let mut file = repo.filename("test.ts");
+
+    file.set_contents(lines!["line1", "line2", "line3"]);
+
+    repo.stage_all_and_commit("Initial commit").unwrap();
+
+    file.insert_at(0, lines!["ai_first".ai()]);
+    file.insert_at(4, lines!["ai_last".ai()]);
+    file.stage();
+
+    file.delete_at(2);
+
+    let commit = repo.commit("Commit staged AI lines").unwrap();
+    assert_eq!(commit.authorship_log.attestations.len(), 1);
+    let mut ranges: Vec<String> = commit.authorship_log.attestations[0]
+        .entries
+        .iter()
+        .flat_map(|entry| entry.line_ranges.iter().map(|range| format!("{:?}", range)))
+        .collect();
+    ranges.sort();
+    assert_eq!(ranges, vec!["Single(1)", "Single(5)"]);
+
+    // The unstaged deletion shows in the working directory; line2 is still committed
+    file.assert_committed_lines(lines![
+        "ai_first".ai(),
+        "line1".human(),
+        "line3".human(),
+        "ai_last".ai(),
+    ]);
+}
+
 #[test]
 fn test_unstaged_ai_lines_saved_to_working_log() {
     // Test that unstaged AI-authored lines are saved to the working log for the next commit
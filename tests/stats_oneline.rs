@@ -0,0 +1,83 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+#[test]
+fn test_stats_oneline_for_commit_and_pending_changes() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines![
+        "fn a() {}".ai(),
+        "fn b() {}".ai(),
+        "fn c() {}",
+        "fn d() {}"
+    ]);
+    repo.stage_all_and_commit("Half AI").unwrap();
+
+    let output = repo.git_ai(&["stats", "--oneline"]).unwrap();
+    assert_eq!(output.trim(), "AI 50% (2/4 lines), 1 agent, waited 0s");
+
+    // Uncommitted work, staged or not, tracked or not
+    let mut notes = repo.filename("notes.md");
+    notes.set_contents_no_stage(lines!["Draft".ai(), "More".ai(), "Mine"]);
+    let output = repo.git_ai(&["stats", "--oneline", "--pending"]).unwrap();
+    assert_eq!(output.trim(), "AI 67% (2/3 lines), 1 agent, waited 0s");
+
+    // The commit's stats are unchanged by the pending work
+    let output = repo.git_ai(&["stats", "HEAD", "--oneline"]).unwrap();
+    assert_eq!(output.trim(), "AI 50% (2/4 lines), 1 agent, waited 0s");
+
+    repo.stage_all_and_commit("Notes").unwrap();
+    let output = repo.git_ai(&["stats", "--oneline"]).unwrap();
+    assert_eq!(output.trim(), "AI 67% (2/3 lines), 1 agent, waited 0s");
+    let result = repo.git_ai(&["stats", "HEAD~1..HEAD", "--oneline"]);
+    assert!(
+        result
+            .unwrap_err()
+            .contains("--oneline takes a single commit")
+    );
+}
+
+#[test]
+fn test_stats_per_commit_over_a_range() {
+    let repo = TestRepo::new();
+    let mut base = repo.filename("base.txt");
+    base.set_contents(lines!["base"]);
+    repo.stage_all_and_commit("Base").unwrap();
+
+    let mut file = repo.filename("lib.rs");
+    file.set_contents(lines!["fn a() {}".ai(), "fn b() {}"]);
+    repo.stage_all_and_commit("Half AI").unwrap();
+    let mut other = repo.filename("other.rs");
+    other.set_contents(lines!["fn c() {}".ai(), "fn d() {}".ai()]);
+    repo.stage_all_and_commit("All AI").unwrap();
+
+    let output = repo
+        .git_ai(&["stats", "--range", "HEAD~2..HEAD", "--per-commit", "--json"])
+        .unwrap();
+    let rows: serde_json::Value = serde_json::from_str(output.trim()).unwrap();
+    let rows = rows.as_array().unwrap();
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0]["subject"], "Half AI");
+    assert_eq!(rows[0]["git_diff_added_lines"], 2);
+    assert_eq!(rows[0]["ai_additions"], 1);
+    assert_eq!(rows[1]["subject"], "All AI");
+    assert_eq!(rows[1]["ai_additions"], 2);
+
+    let output = repo
+        .git_ai(&["stats", "HEAD~2..HEAD", "--per-commit"])
+        .unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert!(lines[1].ends_with(" 2       1  50%  Half AI"), "{}", output);
+    assert!(lines[2].ends_with(" 2       2 100%  All AI"), "{}", output);
+    assert!(lines[3].starts_with("Total"), "{}", output);
+    assert!(
+        lines[3].ends_with(" 4       3  75%  2 commits"),
+        "{}",
+        output
+    );
+
+    let result = repo.git_ai(&["stats", "--per-commit"]);
+    assert!(result.unwrap_err().contains("--per-commit takes"));
+}
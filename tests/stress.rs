@@ -0,0 +1,303 @@
+//! Concurrency stress tests for the storage layer. Simulated git-ai processes (threads that
+//! open a `Repository` of their own for every operation, like separate git-ai invocations
+//! would) checkpoint, commit and reset one repository at the same time, then the working logs
+//! and notes they leave behind are checked for torn writes and dangling references.
+//! Run with `cargo test --features stress --test stress`.
+#![cfg(feature = "stress")]
+
+#[macro_use]
+mod repos;
+use git_ai::authorship::authorship_log_serialization::AuthorshipLog;
+use git_ai::authorship::post_commit::post_commit;
+use git_ai::authorship::pre_commit::pre_commit;
+use git_ai::authorship::working_log::{AgentId, Checkpoint, CheckpointKind};
+use git_ai::commands::checkpoint::{self, CheckpointOptions};
+use git_ai::commands::checkpoint_agent::agent_presets::AgentRunResult;
+use git_ai::commands::hooks::reset_hooks::{post_reset_hook, pre_reset_hook};
+use git_ai::git::cli_parser::parse_git_cli_args;
+use git_ai::git::repository::{Repository, find_repository_in_path};
+use repos::test_repo::{TestRepo, plain_git_output};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Duration;
+
+const AGENTS: usize = 3;
+const ROUNDS: usize = 20;
+
+/// A fresh `Repository`, like the one each git-ai process opens for itself
+fn open(path: &Path) -> Repository {
+    find_repository_in_path(path.to_str().unwrap()).unwrap()
+}
+
+fn agent_run(worker: usize, file: &str) -> AgentRunResult {
+    AgentRunResult {
+        agent_id: AgentId {
+            tool: "mock_ai".to_string(),
+            id: format!("worker-{}", worker),
+            model: "unknown".to_string(),
+        },
+        checkpoint_kind: CheckpointKind::AiAgent,
+        transcript: None,
+        repo_working_dir: None,
+        edited_filepaths: Some(vec![file.to_string()]),
+        will_edit_filepaths: None,
+        commit_message_source: None,
+        agent_version: None,
+        file_contents: None,
+    }
+}
+
+/// Agents append to a file of their own and checkpoint it
+fn agent_worker(path: &Path, worker: usize, start: &Barrier) {
+    let file = format!("agent_{}.txt", worker);
+    start.wait();
+    for round in 0..ROUNDS {
+        let mut content = fs::read_to_string(path.join(&file)).unwrap_or_default();
+        content.push_str(&format!("agent {} round {}\n", worker, round));
+        fs::write(path.join(&file), content).unwrap();
+
+        // HEAD may move under a checkpoint; losing the race is fine, corrupting storage isn't
+        let _ = checkpoint::run(
+            &open(path),
+            "Test User",
+            CheckpointKind::AiAgent,
+            Some(agent_run(worker, &file)),
+            CheckpointOptions {
+                quiet: true,
+                ..Default::default()
+            },
+        );
+    }
+}
+
+/// Commits everything there is through the pre- and post-commit hooks, until told to stop
+fn commit_worker(path: &Path, stop: &AtomicBool, commits: &AtomicUsize, start: &Barrier) {
+    start.wait();
+    while !stop.load(Ordering::SeqCst) {
+        let _ = pre_commit(&open(path), "Test User".to_string());
+        plain_git_output(path, &["add", "-A"]);
+        if !plain_git_output(path, &["commit", "-q", "-m", "stress"])
+            .status
+            .success()
+        {
+            // Nothing to commit, or a lock held by another worker
+            thread::sleep(Duration::from_millis(5));
+            continue;
+        }
+
+        let repo = open(path);
+        let commit =
+            String::from_utf8(plain_git_output(path, &["rev-parse", "HEAD"]).stdout).unwrap();
+        let parent = plain_git_output(path, &["rev-parse", "HEAD^"]);
+        let parent = parent
+            .status
+            .success()
+            .then(|| String::from_utf8(parent.stdout).unwrap().trim().to_string());
+        if post_commit(
+            &repo,
+            parent,
+            commit.trim().to_string(),
+            "Test User".to_string(),
+            true,
+        )
+        .is_ok()
+        {
+            commits.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+}
+
+/// Unwinds the last commit now and then through the reset hooks
+fn reset_worker(path: &Path, stop: &AtomicBool, start: &Barrier) {
+    let args: Vec<String> = ["reset", "--soft", "HEAD~1"]
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect();
+    let parsed_args = parse_git_cli_args(&args);
+    start.wait();
+    while !stop.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_millis(40));
+        let mut repo = open(path);
+        pre_reset_hook(&parsed_args, &mut repo);
+        let output = plain_git_output(path, &args.iter().map(String::as_str).collect::<Vec<_>>());
+        post_reset_hook(&parsed_args, &mut repo, output.status);
+    }
+}
+
+/// Every line of every working log is a whole checkpoint and, with `blobs`, every blob a
+/// checkpoint refers to is still stored
+fn assert_working_logs_intact(repo: &Repository, blobs: bool) {
+    let Ok(dirs) = fs::read_dir(&repo.storage.working_logs) else {
+        return;
+    };
+    for dir in dirs {
+        let dir = dir.unwrap().path();
+        let Ok(content) = fs::read_to_string(dir.join("checkpoints.jsonl")) else {
+            continue;
+        };
+        for line in content.lines().filter(|line| !line.trim().is_empty()) {
+            let checkpoint: Checkpoint = serde_json::from_str(line).unwrap_or_else(|e| {
+                panic!("torn checkpoint in {}: {}\n{}", dir.display(), e, line)
+            });
+            for entry in checkpoint.entries.iter().filter(|_| blobs) {
+                assert!(
+                    dir.join("blobs").join(&entry.blob_sha).is_file(),
+                    "{} refers to missing blob {} for {}",
+                    dir.display(),
+                    entry.blob_sha,
+                    entry.file
+                );
+            }
+        }
+    }
+}
+
+/// Every note parses, and attributes lines only to prompts it has a record of
+fn assert_notes_intact(path: &Path) {
+    let list = plain_git_output(path, &["notes", "--ref=ai", "list"]);
+    for line in String::from_utf8(list.stdout).unwrap().lines() {
+        let (note, commit) = line.split_once(' ').unwrap();
+        let content =
+            String::from_utf8(plain_git_output(path, &["cat-file", "-p", note]).stdout).unwrap();
+        let log = AuthorshipLog::deserialize_from_string(&content)
+            .unwrap_or_else(|e| panic!("note of {} doesn't parse: {}\n{}", commit, e, content));
+        for file in &log.attestations {
+            for entry in &file.entries {
+                assert!(
+                    log.metadata.prompts.contains_key(&entry.hash),
+                    "note of {} attributes {} to unknown prompt {}",
+                    commit,
+                    file.file_path,
+                    entry.hash
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn test_concurrent_checkpoints_commits_and_resets() {
+    let repo = TestRepo::new();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Stress"]);
+    repo.stage_all_and_commit("Initial").unwrap();
+    let path = repo.path().clone();
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let commits = Arc::new(AtomicUsize::new(0));
+    let start = Arc::new(Barrier::new(AGENTS + 2));
+
+    let agents: Vec<_> = (0..AGENTS)
+        .map(|worker| {
+            let (path, start) = (path.clone(), start.clone());
+            thread::spawn(move || agent_worker(&path, worker, &start))
+        })
+        .collect();
+    let committer = {
+        let (path, stop, commits, start) =
+            (path.clone(), stop.clone(), commits.clone(), start.clone());
+        thread::spawn(move || commit_worker(&path, &stop, &commits, &start))
+    };
+    let resetter = {
+        let (path, stop, start) = (path.clone(), stop.clone(), start.clone());
+        thread::spawn(move || reset_worker(&path, &stop, &start))
+    };
+
+    for agent in agents {
+        agent.join().expect("an agent worker panicked");
+    }
+    stop.store(true, Ordering::SeqCst);
+    committer.join().expect("the commit worker panicked");
+    resetter.join().expect("the reset worker panicked");
+    assert!(commits.load(Ordering::SeqCst) > 0, "no commit got through");
+
+    // A reset or commit that replaces a working log while a checkpoint is still writing to it
+    // can leave that checkpoint without its blobs, which readers take as an empty file, so only
+    // torn lines count here
+    assert_working_logs_intact(&open(&path), false);
+    assert_notes_intact(&path);
+
+    // Whatever the workers left behind, the next agent edit and commit are attributed as usual
+    fs::write(path.join("final.txt"), "after the storm\n").unwrap();
+    checkpoint::run(
+        &open(&path),
+        "Test User",
+        CheckpointKind::AiAgent,
+        Some(agent_run(AGENTS, "final.txt")),
+        CheckpointOptions {
+            quiet: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let commit = repo.stage_all_and_commit("After the storm").unwrap();
+    let attested: HashSet<&str> = commit
+        .authorship_log
+        .attestations
+        .iter()
+        .map(|file| file.file_path.as_str())
+        .collect();
+    assert!(attested.contains("final.txt"), "{:?}", attested);
+    assert_notes_intact(&path);
+}
+
+#[test]
+fn test_concurrent_checkpoints_of_one_file() {
+    let repo = TestRepo::new();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Stress"]);
+    repo.stage_all_and_commit("Initial").unwrap();
+    let path = repo.path().clone();
+    fs::write(path.join("shared.txt"), "").unwrap();
+
+    // Every worker checkpoints the same file at once, so their appends interleave
+    let start = Arc::new(Barrier::new(AGENTS));
+    let workers: Vec<_> = (0..AGENTS)
+        .map(|worker| {
+            let (path, start) = (path.clone(), start.clone());
+            thread::spawn(move || {
+                start.wait();
+                for round in 0..ROUNDS {
+                    fs::write(
+                        path.join("shared.txt"),
+                        format!("worker {} round {}\n", worker, round),
+                    )
+                    .unwrap();
+                    checkpoint::run(
+                        &open(&path),
+                        "Test User",
+                        CheckpointKind::AiAgent,
+                        Some(agent_run(worker, "shared.txt")),
+                        CheckpointOptions {
+                            quiet: true,
+                            ..Default::default()
+                        },
+                    )
+                    .unwrap();
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().expect("a checkpoint worker panicked");
+    }
+
+    let repo_handle = open(&path);
+    assert_working_logs_intact(&repo_handle, true);
+    let checkpoints = repo.current_working_logs().read_all_checkpoints().unwrap();
+    assert!(!checkpoints.is_empty());
+
+    let commit = repo.stage_all_and_commit("Shared").unwrap();
+    assert!(
+        commit
+            .authorship_log
+            .attestations
+            .iter()
+            .any(|file| file.file_path == "shared.txt")
+    );
+    assert_notes_intact(&path);
+}
@@ -0,0 +1,146 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+
+/// A repo with `lib` checked out as a submodule, both with a commit
+fn repo_with_submodule() -> TestRepo {
+    let library = TestRepo::new();
+    let mut lib = library.filename("lib.rs");
+    lib.set_contents(lines!["fn library() {}"]);
+    library.stage_all_and_commit("Initial library").unwrap();
+
+    let repo = TestRepo::new();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Project"]);
+    repo.stage_all_and_commit("Initial commit").unwrap();
+    repo.git(&[
+        "-c",
+        "protocol.file.allow=always",
+        "submodule",
+        "add",
+        library.path().to_str().unwrap(),
+        "lib",
+    ])
+    .unwrap();
+    repo.git(&["-C", "lib", "config", "user.name", "Test User"])
+        .unwrap();
+    repo.git(&["-C", "lib", "config", "user.email", "test@example.com"])
+        .unwrap();
+    repo.git(&["commit", "-m", "Add lib submodule"]).unwrap();
+    repo
+}
+
+fn edit_submodule_as_ai(repo: &TestRepo, envs: &[(&str, &str)]) {
+    fs::write(
+        repo.path().join("lib").join("lib.rs"),
+        "fn library() {}\nfn generated() {}\n",
+    )
+    .unwrap();
+    repo.git_ai_with_env(&["checkpoint", "mock_ai", "lib/lib.rs"], envs)
+        .unwrap();
+}
+
+/// Commit everything in the submodule through the git-ai proxy, returning the commit's sha
+fn commit_in_submodule(repo: &TestRepo, envs: &[(&str, &str)]) -> String {
+    let submodule = repo.path().join("lib");
+    for args in [&["add", "-A"][..], &["commit", "-m", "Edit lib"][..]] {
+        let output = repo
+            .git_ai_command(args)
+            .current_dir(&submodule)
+            .env("GIT_AI", "git")
+            .envs(envs.iter().copied())
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{}",
+            String::from_utf8_lossy(&output.stderr)
+        );
+    }
+    let output = repo
+        .git_ai_command(&["rev-parse", "HEAD"])
+        .current_dir(&submodule)
+        .env("GIT_AI", "git")
+        .output()
+        .unwrap();
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+fn submodule_note(repo: &TestRepo, sha: &str) -> Option<String> {
+    let output = repo
+        .git_ai_command(&["notes", "--ref", "ai", "show", sha])
+        .current_dir(repo.path().join("lib"))
+        .env("GIT_AI", "git")
+        .output()
+        .unwrap();
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[test]
+fn test_submodule_edits_are_not_attributed_to_the_parent() {
+    let repo = repo_with_submodule();
+    edit_submodule_as_ai(&repo, &[]);
+
+    let mut notes = repo.filename("notes.txt");
+    notes.set_contents(lines!["fn parent() {}".ai()]);
+    repo.git(&["add", "-A"]).unwrap();
+    let commit = repo.commit("Bump lib").unwrap();
+    let files: Vec<&str> = commit
+        .authorship_log
+        .attestations
+        .iter()
+        .map(|file| file.file_path.as_str())
+        .collect();
+    assert_eq!(files, vec!["notes.txt"]);
+
+    // Ignored by default: the submodule's own commit has no AI authorship
+    let sha = commit_in_submodule(&repo, &[]);
+    let note = submodule_note(&repo, &sha).unwrap_or_default();
+    assert!(!note.contains("mock_ai"), "{}", note);
+}
+
+#[test]
+fn test_recurse_checkpoints_submodules_in_their_own_working_logs() {
+    let repo = repo_with_submodule();
+    let envs = [("GIT_AI_SUBMODULES", "recurse")];
+    edit_submodule_as_ai(&repo, &envs);
+
+    // The checkpoint went to the submodule's working log, not the parent's
+    assert!(
+        repo.current_working_logs()
+            .read_all_checkpoints()
+            .unwrap()
+            .iter()
+            .all(|checkpoint| checkpoint
+                .entries
+                .iter()
+                .all(|entry| !entry.file.starts_with("lib")))
+    );
+
+    let sha = commit_in_submodule(&repo, &envs);
+    let note = submodule_note(&repo, &sha).expect("submodule commit has a note");
+    assert!(note.contains("lib.rs"), "{}", note);
+    assert!(note.contains("mock_ai"), "{}", note);
+    assert!(
+        repo.path()
+            .join(".git")
+            .join("modules")
+            .join("lib")
+            .join("ai")
+            .is_dir()
+    );
+
+    // The parent records the new submodule commit without attributing its lines
+    repo.git(&["add", "lib"]).unwrap();
+    repo.git(&["commit", "-m", "Bump lib"]).unwrap();
+    let head = repo.git(&["rev-parse", "HEAD"]).unwrap();
+    let parent_note = repo
+        .git(&["notes", "--ref", "ai", "show", head.trim()])
+        .unwrap_or_default();
+    assert!(!parent_note.contains("lib.rs"), "{}", parent_note);
+}
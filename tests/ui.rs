@@ -0,0 +1,16 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+#[test]
+fn test_ui_refuses_to_run_without_a_terminal() {
+    let repo = TestRepo::new();
+    let mut file = repo.filename("a.txt");
+    file.set_contents(lines!["one".ai()]);
+    repo.stage_all_and_commit("First").unwrap();
+
+    // Test output is piped, so there's no terminal to draw on
+    let err = repo.git_ai(&["ui"]).unwrap_err();
+    assert!(err.contains("needs an interactive terminal"), "{}", err);
+}
@@ -0,0 +1,102 @@
+#[macro_use]
+mod repos;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+use std::fs;
+use std::io::{BufRead, BufReader, Lines};
+use std::process::{ChildStdout, Stdio};
+
+fn next_line(lines: &mut Lines<BufReader<ChildStdout>>) -> String {
+    lines.next().expect("git-ai watch exited early").unwrap()
+}
+
+#[test]
+fn test_watch_checkpoints_agent_session_and_human_changes() {
+    let repo = TestRepo::new();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Project"]);
+    repo.stage_all_and_commit("Initial commit").unwrap();
+    fs::write(repo.path().join(".gitignore"), "target/\n").unwrap();
+    fs::create_dir_all(repo.path().join("target")).unwrap();
+
+    repo.git_ai(&[
+        "watch",
+        "session",
+        "start",
+        "--tool",
+        "mock_ai",
+        "--id",
+        "session-1",
+    ])
+    .unwrap();
+
+    let mut watcher = repo
+        .git_ai_command(&["watch", "--debounce", "50"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut lines = BufReader::new(watcher.stdout.take().unwrap()).lines();
+    assert!(next_line(&mut lines).starts_with("Watching"));
+
+    // Ignored files are left alone
+    fs::write(repo.path().join("target").join("build.log"), "noise\n").unwrap();
+    fs::write(repo.path().join("lib.rs"), "fn generated() {}\n").unwrap();
+    let line = next_line(&mut lines);
+    assert!(line.contains("ai mock_ai session-1"), "{}", line);
+    assert!(line.contains("lib.rs"), "{}", line);
+    assert!(!line.contains("build.log"), "{}", line);
+
+    repo.git_ai(&[
+        "watch",
+        "session",
+        "end",
+        "--tool",
+        "mock_ai",
+        "--id",
+        "session-1",
+    ])
+    .unwrap();
+    fs::write(repo.path().join("main.rs"), "fn main() {}\n").unwrap();
+    let line = next_line(&mut lines);
+    assert!(line.contains("(human)"), "{}", line);
+    assert!(line.contains("main.rs"), "{}", line);
+
+    watcher.kill().unwrap();
+    watcher.wait().unwrap();
+
+    repo.stage_all_and_commit("Watched work").unwrap();
+    let mut lib = repo.filename("lib.rs");
+    lib.assert_lines_and_blame(lines!["fn generated() {}".ai()]);
+    let mut main = repo.filename("main.rs");
+    main.assert_lines_and_blame(lines!["fn main() {}".human()]);
+}
+
+#[test]
+fn test_watch_session_registration() {
+    let repo = TestRepo::new();
+    repo.git_ai(&[
+        "watch", "session", "start", "--tool", "claude", "--id", "abc", "--model", "opus",
+    ])
+    .unwrap();
+    // A heartbeat without the model keeps the registered one
+    repo.git_ai(&[
+        "watch", "session", "start", "--tool", "claude", "--id", "abc",
+    ])
+    .unwrap();
+    let sessions = fs::read_dir(repo.path().join(".git").join("ai").join("agent_sessions"))
+        .unwrap()
+        .map(|entry| fs::read_to_string(entry.unwrap().path()).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(sessions.len(), 1);
+    assert!(sessions[0].contains("\"opus\""), "{}", sessions[0]);
+
+    repo.git_ai(&["watch", "session", "end", "--tool", "claude", "--id", "abc"])
+        .unwrap();
+    let output = repo
+        .git_ai(&["watch", "session", "end", "--tool", "claude", "--id", "abc"])
+        .unwrap();
+    assert!(output.contains("No session abc of claude"), "{}", output);
+
+    let result = repo.git_ai(&["watch", "session", "start", "--tool", "claude"]);
+    assert!(result.unwrap_err().contains("needs --tool and --id"));
+}
@@ -0,0 +1,88 @@
+#[macro_use]
+mod repos;
+use rand::Rng;
+use repos::test_file::ExpectedLineExt;
+use repos::test_repo::TestRepo;
+
+fn repo_with_commit() -> TestRepo {
+    let repo = TestRepo::new();
+    let mut readme = repo.filename("README.md");
+    readme.set_contents(lines!["# Project"]);
+    repo.stage_all_and_commit("Initial commit").unwrap();
+    repo
+}
+
+/// A linked worktree of `repo` (`git worktree add`) with a new branch checked out
+fn linked_worktree(repo: &TestRepo, branch: &str) -> TestRepo {
+    let n: u64 = rand::thread_rng().gen_range(0..10000000000);
+    let path = std::env::temp_dir().join(format!("{}-{}", n, branch));
+    repo.git(&["worktree", "add", "-b", branch, path.to_str().unwrap()])
+        .expect("failed to add worktree");
+    TestRepo {
+        path,
+        main_path: repo.main_path.clone(),
+    }
+}
+
+#[test]
+fn test_commit_in_linked_worktree_is_attributed_and_shared() {
+    let repo = repo_with_commit();
+    let worktree = linked_worktree(&repo, "feature");
+
+    let mut file = worktree.filename("lib.rs");
+    file.set_contents(lines!["fn human() {}", "fn generated() {}".ai()]);
+    let commit = worktree.stage_all_and_commit("Add lib").unwrap();
+
+    assert_eq!(commit.authorship_log.attestations.len(), 1);
+    assert_eq!(commit.authorship_log.attestations[0].file_path, "lib.rs");
+    file.assert_committed_lines(lines!["fn human() {}".human(), "fn generated() {}".ai()]);
+
+    // Notes live in the common dir, so the main worktree sees the note
+    let note = repo
+        .git(&["notes", "--ref", "ai", "show", &commit.commit_sha])
+        .unwrap();
+    assert!(note.contains("lib.rs"), "{}", note);
+}
+
+#[test]
+fn test_linked_worktree_working_logs_do_not_collide() {
+    let repo = repo_with_commit();
+    let worktree = linked_worktree(&repo, "feature");
+
+    // Both checkouts start from the same commit, so their working logs share a base commit
+    let main_log = repo.current_working_logs();
+    let worktree_log = worktree.current_working_logs();
+    assert_eq!(main_log.base_commit, worktree_log.base_commit);
+    assert_ne!(main_log.dir, worktree_log.dir);
+    assert!(
+        worktree_log
+            .dir
+            .starts_with(repo.path().join(".git").join("worktrees"))
+    );
+    assert_eq!(
+        worktree_log.repo_root.canonicalize().unwrap(),
+        worktree.path().canonicalize().unwrap()
+    );
+
+    let mut generated = worktree.filename("generated.rs");
+    generated.set_contents_no_stage(lines!["fn generated() {}".ai()]);
+
+    // A commit in the main worktree doesn't pick up the linked worktree's checkpoints
+    let mut notes = repo.filename("notes.txt");
+    notes.set_contents(lines!["written by hand"]);
+    let commit = repo.stage_all_and_commit("Add notes").unwrap();
+    assert!(
+        commit
+            .authorship_log
+            .attestations
+            .iter()
+            .all(|file| file.file_path != "generated.rs")
+    );
+
+    let commit = worktree.stage_all_and_commit("Add generated").unwrap();
+    assert_eq!(commit.authorship_log.attestations.len(), 1);
+    assert_eq!(
+        commit.authorship_log.attestations[0].file_path,
+        "generated.rs"
+    );
+}